@@ -0,0 +1,71 @@
+//! Extraction of one large archive read through the file handle and through
+//! `--mmap`. The archive is generated once per run: 2 GiB in 50,000 stored
+//! entries by default, or `BENCH_ARCHIVE_MIB` MiB in `BENCH_ENTRIES` entries.
+//!
+//! cargo bench --bench mmap
+//! BENCH_ARCHIVE_MIB=256 BENCH_ENTRIES=5000 cargo bench --bench mmap
+
+use bulk_unzip::extract::zip_files_at;
+use bulk_unzip::Extractor;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+fn env_or(name: &str, default: u64) -> u64 {
+    std::env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+/// Write `entries` stored entries of `total` bytes between them, in a few
+/// directories, of bytes that don't repeat within an entry
+fn write_archive(path: &Path, total: u64, entries: u64) {
+    let mut zip = ZipWriter::new(BufWriter::new(fs::File::create(path).unwrap()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Stored).large_file(total > u32::MAX as u64);
+    let size = (total / entries) as usize;
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut data = vec![0; size];
+    for i in 0..entries {
+        for chunk in data.chunks_mut(8) {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            chunk.copy_from_slice(&state.to_le_bytes()[..chunk.len()]);
+        }
+        zip.start_file(format!("dir{:03}/file{:06}.bin", i % 100, i), options).unwrap();
+        zip.write_all(&data).unwrap();
+    }
+    zip.finish().unwrap().flush().unwrap();
+}
+
+fn extraction(c: &mut Criterion) {
+    let total = env_or("BENCH_ARCHIVE_MIB", 2048) * 1024 * 1024;
+    let entries = env_or("BENCH_ENTRIES", 50_000);
+    let dir = tempfile::tempdir().unwrap();
+    let archive = dir.path().join("large.zip");
+    write_archive(&archive, total, entries);
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("extract");
+    group.sample_size(10).throughput(Throughput::Bytes(total));
+    for (name, mmap) in [("read", false), ("mmap", true)] {
+        group.bench_function(name, |b| {
+            b.iter_batched(
+                || tempfile::tempdir_in(dir.path()).unwrap(),
+                |output| {
+                    let zip_files = zip_files_at(std::slice::from_ref(&archive), None).unwrap();
+                    let extractor = Extractor::new().output(output.path()).workers(1).mmap(mmap).assume_complete(true);
+                    let report = runtime.block_on(extractor.extract(zip_files)).unwrap();
+                    assert_eq!(report.failed().count(), 0);
+                    output
+                },
+                BatchSize::PerIteration,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, extraction);
+criterion_main!(benches);
@@ -0,0 +1,66 @@
+//! Extract every archive dropped into a directory, then strip the MP3s that
+//! came out of them, using the library API instead of the binary. Polls every
+//! 30 seconds; the state journal in the output directory lets a restarted
+//! watcher pick up where it stopped without extracting anything twice.
+//!
+//! cargo run --example watch_folder -- <incoming> <extracted> [--reset-state]
+
+use anyhow::{Context, Result};
+use bulk_unzip::state_journal::{StateJournal, STATE_FILE_NAME};
+use bulk_unzip::{ExtractStatus, Extractor, MetadataStripper, ProgressEvent};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args_os().skip(1).map(PathBuf::from);
+    let incoming = args.next().context("usage: watch_folder <incoming> <extracted> [--reset-state]")?;
+    let extracted = args.next().unwrap_or_else(|| incoming.join("extracted"));
+    let reset = args.any(|arg| arg == Path::new("--reset-state"));
+
+    let journal = Arc::new(StateJournal::open(extracted.join(STATE_FILE_NAME), reset)?);
+    println!("{} archives already extracted", journal.archives());
+    loop {
+        process(&incoming, &extracted, journal.clone()).await?;
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+async fn process(incoming: &Path, extracted: &Path, journal: Arc<StateJournal>) -> Result<()> {
+    let report = Extractor::new()
+        .output(extracted)
+        .workers(8)
+        .state_journal(journal)
+        .progress(|event| match event {
+            ProgressEvent::Started { archive, index, total } => {
+                println!("[{}/{}] {}", index + 1, total, archive.display())
+            }
+            ProgressEvent::Finished { archive, result } => {
+                if let Err(e) = &result.result {
+                    eprintln!("failed: {}: {}", archive.display(), e);
+                }
+            }
+            _ => {}
+        })
+        .extract_dir(incoming)
+        .await?;
+
+    let new = report.count(ExtractStatus::Extracted);
+    println!("{} extracted, {} failed", new, report.failed().count());
+    if new == 0 {
+        return Ok(());
+    }
+
+    let results = MetadataStripper::new()
+        .keep_fields("title,artist,album")
+        .strip_private(true)
+        .skip_clean(true)
+        .strip_dir(extracted)
+        .await?;
+    println!("{} MP3s processed", results.len());
+
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use bulk_unzip::progress_sink::ProgressKind;
+
+/// Default gap between coalesced progress events: at most 20 per second
+pub const DEFAULT_PROGRESS_INTERVAL_MS: u64 = 50;
+
+/// Coalesces per-entry updates into at most one event per interval. Events
+/// carry cumulative counters, so the updates dropped in between lose nothing.
+pub struct EventThrottle {
+    started: Instant,
+    interval_ms: u64,
+    /// Milliseconds since `started` before which no further event is due
+    next_due: AtomicU64,
+}
+
+impl EventThrottle {
+    pub fn new(interval_ms: u64) -> Self {
+        EventThrottle { started: Instant::now(), interval_ms, next_due: AtomicU64::new(0) }
+    }
+
+    /// Whether an update of this kind should be emitted now. A due progress
+    /// update claims the slot, so concurrent workers emit it only once.
+    pub fn should_emit(&self, kind: ProgressKind) -> bool {
+        self.should_emit_at(kind, self.started.elapsed().as_millis() as u64)
+    }
+
+    /// [`should_emit`](Self::should_emit) `now_ms` milliseconds after the throttle was created
+    fn should_emit_at(&self, kind: ProgressKind, now_ms: u64) -> bool {
+        if kind != ProgressKind::Progress {
+            return true;
+        }
+        let next_due = self.next_due.load(Ordering::Relaxed);
+        now_ms >= next_due
+            && self.next_due
+                .compare_exchange(next_due, now_ms + self.interval_ms, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Stands in for the webview: keeps the kind and cumulative bytes of
+    /// what got through the throttle
+    struct MockEmitter {
+        throttle: EventThrottle,
+        emitted: Vec<(ProgressKind, u64)>,
+    }
+
+    impl MockEmitter {
+        fn new(interval_ms: u64) -> Self {
+            MockEmitter { throttle: EventThrottle::new(interval_ms), emitted: Vec::new() }
+        }
+
+        fn send(&mut self, now_ms: u64, kind: ProgressKind, progress: u64) {
+            if self.throttle.should_emit_at(kind, now_ms) {
+                self.emitted.push((kind, progress));
+            }
+        }
+    }
+
+    #[test]
+    fn progress_is_coalesced_to_one_event_per_interval() {
+        let mut emitter = MockEmitter::new(DEFAULT_PROGRESS_INTERVAL_MS);
+        // One entry a millisecond for a second, each adding to the running total
+        for ms in 0..1000 {
+            emitter.send(ms, ProgressKind::Progress, ms + 1);
+        }
+        assert_eq!(emitter.emitted.len(), 20);
+        let progress: Vec<u64> = emitter.emitted.iter().map(|(_, progress)| *progress).collect();
+        assert_eq!(progress, (0..20).map(|i| i * 50 + 1).collect::<Vec<_>>());
+
+        // The first update after a quiet spell goes out at once
+        emitter.send(1500, ProgressKind::Progress, 1000);
+        assert_eq!(emitter.emitted.last(), Some(&(ProgressKind::Progress, 1000)));
+    }
+
+    #[test]
+    fn archive_state_changes_are_always_emitted() {
+        let mut emitter = MockEmitter::new(DEFAULT_PROGRESS_INTERVAL_MS);
+        emitter.send(0, ProgressKind::Progress, 1);
+        for kind in [ProgressKind::ArchiveStarted, ProgressKind::ArchiveFinished, ProgressKind::ArchiveFailed] {
+            emitter.send(1, kind, 2);
+        }
+        emitter.send(2, ProgressKind::Progress, 3);
+        let kinds: Vec<ProgressKind> = emitter.emitted.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(kinds, [ProgressKind::Progress, ProgressKind::ArchiveStarted, ProgressKind::ArchiveFinished, ProgressKind::ArchiveFailed]);
+
+        // Nor do they use up the slot the next progress update is due in
+        emitter.send(50, ProgressKind::Progress, 4);
+        assert_eq!(emitter.emitted.last(), Some(&(ProgressKind::Progress, 4)));
+    }
+
+    #[test]
+    fn an_interval_of_zero_emits_everything() {
+        let mut emitter = MockEmitter::new(0);
+        for progress in 0..10 {
+            emitter.send(7, ProgressKind::Progress, progress);
+        }
+        assert_eq!(emitter.emitted.len(), 10);
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Oldest entries are dropped once the history grows past this
+const MAX_ENTRIES: usize = 500;
+
+const HISTORY_FILE: &str = "history.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OperationKind {
+    Unzip,
+    Strip,
+}
+
+/// One completed operation, newest last in the stored file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub kind: OperationKind,
+    pub directory: String,
+    pub output: Option<String>,
+    pub processed: usize,
+    pub skipped: usize,
+    pub failed: usize,
+    pub duration_ms: u64,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    /// The options the operation ran with, as sent by the frontend
+    pub options: serde_json::Value,
+}
+
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// JSON-file backed run history stored in the app data directory
+pub struct HistoryStore {
+    dir: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl HistoryStore {
+    pub fn new(dir: PathBuf) -> Self {
+        HistoryStore {
+            dir,
+            lock: Mutex::new(()),
+        }
+    }
+
+    fn path(&self) -> PathBuf {
+        self.dir.join(HISTORY_FILE)
+    }
+
+    /// Read the stored entries. A file that fails to parse is renamed aside so
+    /// a corrupt history never blocks the app; history starts over empty.
+    fn load(&self) -> Vec<HistoryEntry> {
+        let path = self.path();
+        let Ok(contents) = fs::read_to_string(&path) else {
+            return Vec::new();
+        };
+
+        match serde_json::from_str(&contents) {
+            Ok(entries) => entries,
+            Err(_) => {
+                let aside = self.dir.join(format!("{}.corrupt-{}", HISTORY_FILE, now_timestamp()));
+                let _ = fs::rename(&path, aside);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Write via a temporary file and rename so readers never see a partial file
+    fn save(&self, entries: &[HistoryEntry]) -> Result<()> {
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create directory {:?}", self.dir))?;
+
+        let path = self.path();
+        let tmp = self.dir.join(format!("{}.tmp", HISTORY_FILE));
+        let json = serde_json::to_string_pretty(entries)?;
+        fs::write(&tmp, json)
+            .with_context(|| format!("Failed to write {:?}", tmp))?;
+        fs::rename(&tmp, &path)
+            .with_context(|| format!("Failed to replace {:?}", path))?;
+        Ok(())
+    }
+
+    pub fn record(&self, entry: HistoryEntry) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.load();
+        entries.push(entry);
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+        }
+        self.save(&entries)
+    }
+
+    /// Most recent entries first
+    pub fn recent(&self, limit: Option<usize>) -> Vec<HistoryEntry> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries = self.load();
+        entries.reverse();
+        if let Some(limit) = limit {
+            entries.truncate(limit);
+        }
+        entries
+    }
+
+    pub fn clear(&self) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.save(&[])
+    }
+
+    pub fn last_options(&self, kind: OperationKind) -> Option<serde_json::Value> {
+        self.recent(None)
+            .into_iter()
+            .find(|entry| entry.kind == kind)
+            .map(|entry| entry.options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: OperationKind, directory: &str) -> HistoryEntry {
+        HistoryEntry {
+            kind,
+            directory: directory.to_string(),
+            output: Some("/out".to_string()),
+            processed: 3,
+            skipped: 1,
+            failed: 0,
+            duration_ms: 1500,
+            timestamp: 1_700_000_000,
+            options: serde_json::json!({ "directory": directory, "workers": 4 }),
+        }
+    }
+
+    #[test]
+    fn entries_round_trip_newest_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().join("app"));
+        store.record(entry(OperationKind::Unzip, "/first")).unwrap();
+        store.record(entry(OperationKind::Strip, "/second")).unwrap();
+
+        // A new store reads what the last one wrote
+        let recent = HistoryStore::new(dir.path().join("app")).recent(None);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].kind, OperationKind::Strip);
+        assert_eq!(recent[0].directory, "/second");
+        assert_eq!(recent[1].output.as_deref(), Some("/out"));
+        assert_eq!((recent[1].processed, recent[1].skipped, recent[1].duration_ms), (3, 1, 1500));
+        assert_eq!(store.recent(Some(1)).len(), 1);
+        assert!(!dir.path().join("app/history.json.tmp").exists());
+    }
+
+    #[test]
+    fn last_options_are_those_of_the_latest_run_of_the_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().to_path_buf());
+        assert!(store.last_options(OperationKind::Unzip).is_none());
+        store.record(entry(OperationKind::Unzip, "/a")).unwrap();
+        store.record(entry(OperationKind::Unzip, "/b")).unwrap();
+        store.record(entry(OperationKind::Strip, "/c")).unwrap();
+
+        assert_eq!(store.last_options(OperationKind::Unzip).unwrap()["directory"], "/b");
+        assert_eq!(store.last_options(OperationKind::Strip).unwrap()["directory"], "/c");
+        store.clear().unwrap();
+        assert!(store.recent(None).is_empty());
+    }
+
+    #[test]
+    fn only_the_newest_entries_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::new(dir.path().to_path_buf());
+        for i in 0..MAX_ENTRIES + 2 {
+            store.record(entry(OperationKind::Unzip, &format!("/{}", i))).unwrap();
+        }
+
+        let recent = store.recent(None);
+        assert_eq!(recent.len(), MAX_ENTRIES);
+        assert_eq!(recent[0].directory, format!("/{}", MAX_ENTRIES + 1));
+        assert_eq!(recent[MAX_ENTRIES - 1].directory, "/2");
+    }
+
+    #[test]
+    fn a_corrupt_file_is_renamed_aside() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(HISTORY_FILE), "[{ not json").unwrap();
+        let store = HistoryStore::new(dir.path().to_path_buf());
+
+        assert!(store.recent(None).is_empty());
+        let aside: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .filter(|name| name.starts_with("history.json.corrupt-"))
+            .collect();
+        assert_eq!(aside.len(), 1);
+        assert_eq!(fs::read_to_string(dir.path().join(&aside[0])).unwrap(), "[{ not json");
+
+        // History starts over
+        store.record(entry(OperationKind::Strip, "/new")).unwrap();
+        assert_eq!(store.recent(None).len(), 1);
+    }
+}
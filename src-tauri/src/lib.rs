@@ -1,19 +1,80 @@
+mod event_throttle;
+mod history;
+mod password_requests;
+
 use anyhow::{Context, Result};
-use futures::future::join_all;
+use futures::FutureExt;
+use glob::Pattern;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use serde::{Deserialize, Serialize};
-use std::fs;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
-use tauri::State;
-use walkdir::WalkDir;
-use zip::ZipArchive;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, State};
+use tokio::task::JoinHandle;
+
+pub use bulk_unzip::metrics::OperationMetrics;
+pub use history::HistoryStore;
+pub use password_requests::PasswordRequests;
 
-mod metadata_stripper;
-use metadata_stripper::{bulk_strip_metadata, MetadataArgs};
+use bulk_unzip::archive_groups::{compile_group_pattern, ArchiveGroup, GroupOrder};
+use bulk_unzip::art_safety::ArtSafety;
+use bulk_unzip::capabilities::Capabilities;
+use bulk_unzip::copy_hooks::CopyControl;
+use bulk_unzip::date_buckets::DateSource;
+use bulk_unzip::defaults::DEFAULTS;
+use bulk_unzip::disposal::Disposal;
+use bulk_unzip::duplicates::{find_duplicates, write_report, ArchiveDuplicates, DuplicateGroup};
+use bulk_unzip::extract::{find_zip_files, find_zip_files_in, zip_files_at, ArchiveHealth, ZipFile};
+use bulk_unzip::field_names::{check_field_lists, InvalidFieldName};
+use bulk_unzip::file_types::{ArchiveKind, FileTypeCounts};
+use bulk_unzip::fingerprint::sha256_files;
+use bulk_unzip::io_profile::{settings_for_run, IoProfile, Workload};
+use bulk_unzip::listing::{list_archive, ArchiveListing};
+use bulk_unzip::long_names::TruncatedName;
+use bulk_unzip::metadata_stripper::{
+    self, bulk_strip_metadata_with_report, remove_frames, FrameEdit, FrameEditError, MetadataArgs, StripResult, StripStatus, WalkOptions,
+    DEFAULT_MIN_SIZE,
+};
+use bulk_unzip::metrics::MetricsSnapshot;
+use bulk_unzip::name_template::NameTemplate;
+use bulk_unzip::ordering::SortOrder;
+use bulk_unzip::output_lock::BusyPolicy;
+use bulk_unzip::output_paths::CollisionPolicy;
+use bulk_unzip::passwords::Passwords;
+use bulk_unzip::preserve::PreserveAttr;
+use bulk_unzip::presets::{apply_to_form, from_form, AppliedPreset, Preset, PresetStore};
+use bulk_unzip::priority::run_in_background;
+use bulk_unzip::progress_sink::{ProgressKind, UnzipProgress};
+use bulk_unzip::result_log::{read_page, results_file_for, RecentError};
+use bulk_unzip::result_stream::ResultStream;
+use bulk_unzip::run_report::{panic_message, ReportArgs, RunReport};
+use bulk_unzip::sampling::{SampleMode, SampleSummary};
+use bulk_unzip::scan_export::{write_scan, ExportFormat, ScanRow};
+use bulk_unzip::scan_progress::{ScanMonitor, ScanProgress};
+use bulk_unzip::scan_roots::{RootCounts, RootOutcome, ScanRoots};
+use bulk_unzip::schemas::SCHEMA_VERSION;
+use bulk_unzip::sniff::{AudioContainer, AudioFormat};
+use bulk_unzip::stability::DEFAULT_STABILIZE_TIMEOUT;
+use bulk_unzip::stats::{is_encrypted, DirectoryStats};
+use bulk_unzip::validation_failure::OnValidationFailure;
+use bulk_unzip::warnings::Warning;
+use bulk_unzip::{ArchiveReport, ExtractStatus as ArchiveStatus, Extractor, ProgressEvent};
+use event_throttle::{EventThrottle, DEFAULT_PROGRESS_INTERVAL_MS};
+use history::{now_timestamp, HistoryEntry, OperationKind};
+use password_requests::{PasswordAsker, DEFAULT_PASSWORD_TIMEOUT};
 
+/// An archive `scan_zip_files` found, with the label of the directory it was
+/// found under when several were scanned
 #[derive(Serialize, Deserialize)]
-pub struct ZipFile {
-    path: String,
-    size: u64,
+pub struct ScannedZipFile {
+    #[serde(flatten)]
+    zip_file: ZipFile,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    root: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -21,14 +82,15 @@ pub struct Mp3File {
     path: String,
     size: u64,
     has_metadata: bool,
-}
-
-#[derive(Serialize, Deserialize)]
-pub struct UnzipProgress {
-    current_file: String,
-    progress: u64,
-    total: u64,
-    message: String,
+    /// Flags `.mp3` files that are really M4A, WAV, ...
+    format: AudioContainer,
+    /// e.g. `ID3v2.3`, absent without an ID3v2 tag
+    tag_version: Option<String>,
+    /// Size the ID3v2 header declares, padding included
+    tag_size_bytes: u64,
+    /// Label of the directory the file was found under, when several were scanned
+    #[serde(skip_serializing_if = "Option::is_none")]
+    root: Option<String>,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -42,189 +104,1424 @@ pub struct StripProgress {
 #[derive(Serialize, Deserialize)]
 pub struct UnzipOptions {
     directory: String,
+    /// More directories to extract from along with `directory`, each into
+    /// `<output>/<directory name>/`
+    #[serde(default)]
+    directories: Vec<String>,
     output: String,
+    #[serde(default = "default_workers")]
     workers: usize,
+    /// Storage the archives are on; picks the worker count and buffer size instead of `workers`
+    #[serde(default = "default_io_profile")]
+    io_profile: Option<IoProfile>,
+    /// Lower the app's CPU and I/O priority for the run, and halve the
+    /// workers when `io_profile` picks them
+    #[serde(default)]
+    background: bool,
+    #[serde(default = "default_skip_existing")]
+    skip_existing: bool,
+    containing: Option<String>,
+    /// Extract only archives of this kind, `java` or `android`, leaving out
+    /// their `META-INF/` signature files
+    #[serde(default)]
+    profile: Option<ArchiveKind>,
+    /// With `profile`, extract signature files too
+    #[serde(default)]
+    keep_signatures: bool,
+    #[serde(default)]
+    overwrite: bool,
+    /// How a file `overwrite` replaces with a directory is removed
+    #[serde(default)]
+    disposal: Disposal,
+    #[serde(default)]
+    attempt_suspect: bool,
+    /// Extract only this many of the archives found, after sorting and filtering
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Which archives `limit` keeps: the first or a random subset
+    #[serde(default)]
+    sample: SampleMode,
+    /// Seed for a random sample, to pick the same archives again
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Extract only the first of byte-identical archives
+    #[serde(default)]
+    skip_duplicates: bool,
+    /// Extract only the latest archive of each group of file names this
+    /// regex matches, its first capture group being the group key
+    #[serde(default)]
+    latest_per_group: Option<String>,
+    /// Which archive of a `latest_per_group` group is the latest
+    #[serde(default)]
+    group_order: GroupOrder,
+    /// Seconds to wait for archives still being written to stop changing
+    #[serde(default = "default_stabilize_timeout")]
+    stabilize_timeout: u64,
+    /// Ask for the password of encrypted archives with `password-required`
+    /// events instead of skipping them
+    #[serde(default = "default_true")]
+    ask_passwords: bool,
+    /// Seconds to wait for `provide_password` or `decline_password` before
+    /// skipping the archive
+    #[serde(default = "default_password_timeout")]
+    password_timeout: u64,
+    /// Remove directories left empty under each archive's directory once it is extracted
+    #[serde(default)]
+    prune_empty: bool,
+    name_template: Option<String>,
+    #[serde(default = "default_max_entries")]
+    max_entries: usize,
+    /// Extract archives exceeding `max_entries` anyway
+    #[serde(default)]
+    force: bool,
+    /// Bytes in-flight buffers may use; the GUI's low memory mode
+    #[serde(default)]
+    memory_limit: Option<u64>,
+    /// Cap on the combined write rate, in bytes per second
+    #[serde(default)]
+    limit_rate: Option<u64>,
+    /// Fail archives with invalid entry timestamps instead of clamping them
+    #[serde(default)]
+    strict_times: bool,
+    /// Refuse to start when the output filesystem can't store some entries
+    #[serde(default)]
+    strict_fs: bool,
+    /// Least time between two `unzip-progress` events for entry progress
+    #[serde(default = "default_progress_interval_ms")]
+    progress_interval_ms: u64,
+    /// Extract into `comics/`, `books/`, `java/`, `android/` or `archives/` under the output by archive kind
+    #[serde(default)]
+    label_by_type: bool,
+    /// Extract under `YYYY/MM/` in the output by this date of each archive, outside any type label
+    #[serde(default)]
+    split_output_by_date: Option<DateSource>,
+    /// When two archives would extract into the same directory at once
+    #[serde(default)]
+    on_busy: BusyPolicy,
+    /// What becomes of the directory of an archive failing a CRC-32 or entry count check
+    #[serde(default)]
+    on_validation_failure: OnValidationFailure,
+    /// Order to extract and list archives in
+    #[serde(default)]
+    sort: SortOrder,
+    /// Run report path; defaults to `bulk-unzip-report-<timestamp>.json` in the output directory
+    #[serde(default)]
+    report: Option<String>,
+    #[serde(default)]
+    no_report: bool,
+}
+
+/// Outcome of extracting a single archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExtractStatus {
+    Extracted,
+    SkippedExisting,
+    /// No entry matches the `containing` pattern
+    SkippedFiltered,
+    /// Password-protected, and the password wasn't given
+    SkippedEncrypted,
+    SkippedSuspect,
+    /// Byte-identical to the archive in `duplicate_of`, which was extracted instead
+    SkippedDuplicate,
+    /// A later archive of its `latest_per_group` group, `superseded_by`, was extracted instead
+    SkippedSuperseded,
+    /// Still growing or locked after `stabilize_timeout`, e.g. a download in progress
+    SkippedUnstable,
+    /// Extracted by an earlier run and unchanged since, per a state journal
+    SkippedProcessed,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ExtractResult {
+    path: String,
+    status: ExtractStatus,
+    file_types: FileTypeCounts,
+    /// What was surprising about it, e.g. clamped timestamps or entries
+    /// whose path would leave the extraction directory
+    warnings: Vec<Warning>,
+    /// Extracted files that are archives themselves, for an "extract nested" follow-up
+    nested_archives: Vec<String>,
+    /// Entry paths shortened for having a component too long for the filesystem
+    truncated_names: Vec<TruncatedName>,
+    /// The archive extracted in place of this identical one
+    duplicate_of: Option<String>,
+    /// The later archive of its group extracted in place of this one
+    superseded_by: Option<String>,
+    error: Option<String>,
+}
+
+impl ExtractResult {
+    /// The result of an archive that ended with `status`, before anything
+    /// it extracted is added
+    fn with_status(path: &Path, status: &ArchiveStatus) -> Self {
+        let mut result = ExtractResult {
+            path: path.to_string_lossy().into_owned(),
+            status: ExtractStatus::Extracted,
+            file_types: FileTypeCounts::default(),
+            warnings: Vec::new(),
+            nested_archives: Vec::new(),
+            truncated_names: Vec::new(),
+            duplicate_of: None,
+            superseded_by: None,
+            error: None,
+        };
+        result.status = match status {
+            ArchiveStatus::Extracted => ExtractStatus::Extracted,
+            ArchiveStatus::SkippedExisting => ExtractStatus::SkippedExisting,
+            ArchiveStatus::SkippedFiltered => ExtractStatus::SkippedFiltered,
+            ArchiveStatus::SkippedEncrypted => ExtractStatus::SkippedEncrypted,
+            ArchiveStatus::SkippedSuspect => ExtractStatus::SkippedSuspect,
+            ArchiveStatus::SkippedDuplicateOf(kept) => {
+                result.duplicate_of = Some(kept.to_string_lossy().into_owned());
+                ExtractStatus::SkippedDuplicate
+            }
+            ArchiveStatus::SkippedSuperseded(kept) => {
+                result.superseded_by = Some(kept.to_string_lossy().into_owned());
+                ExtractStatus::SkippedSuperseded
+            }
+            ArchiveStatus::SkippedUnstable => ExtractStatus::SkippedUnstable,
+            ArchiveStatus::SkippedProcessed => ExtractStatus::SkippedProcessed,
+        };
+        result
+    }
+}
+
+impl From<&ArchiveReport> for ExtractResult {
+    fn from(report: &ArchiveReport) -> Self {
+        let mut result = match &report.result {
+            Ok(status) => ExtractResult::with_status(&report.path, status),
+            Err(e) => ExtractResult {
+                status: ExtractStatus::Failed,
+                error: Some(e.clone()),
+                ..ExtractResult::with_status(&report.path, &ArchiveStatus::Extracted)
+            },
+        };
+        result.file_types = report.file_types.clone();
+        result.warnings = report.warnings.clone();
+        result.nested_archives = report.nested_archives.clone();
+        result.truncated_names = report.truncated_names.clone();
+        result
+    }
+}
+
+/// Per-archive results plus the batch-wide file type histogram
+#[derive(Serialize, Deserialize)]
+pub struct UnzipReport {
+    schema_version: u32,
+    results: Vec<ExtractResult>,
+    file_types: FileTypeCounts,
+    /// What the output's FAT or exFAT filesystem can't store, when it can't store everything
+    fs_warning: Option<String>,
+    /// Groups of byte-identical archives, with `skip_duplicates`
+    duplicates: Vec<ArchiveDuplicates>,
+    /// `latest_per_group` groups and the archive extracted of each
+    groups: Vec<ArchiveGroup>,
+    /// How many of the archives found `limit` kept
+    sample: Option<SampleSummary>,
+    /// Archives found and what became of them by directory, when several were scanned
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    roots: Vec<RootCounts>,
+}
+
+/// Options for extracting one dropped archive
+#[derive(Serialize, Deserialize)]
+pub struct SingleUnzipOptions {
+    output: String,
+    #[serde(default = "default_skip_existing")]
     skip_existing: bool,
+    containing: Option<String>,
+    #[serde(default)]
+    overwrite: bool,
+    #[serde(default)]
+    disposal: Disposal,
+    #[serde(default = "default_max_entries")]
+    max_entries: usize,
+    #[serde(default)]
+    force: bool,
+    #[serde(default)]
+    strict_times: bool,
+    #[serde(default = "default_progress_interval_ms")]
+    progress_interval_ms: u64,
+}
+
+/// Options for removing hand-picked frames from one file
+#[derive(Serialize, Deserialize)]
+pub struct SingleStripOptions {
+    #[serde(default = "default_backup")]
+    backup: bool,
+    #[serde(default)]
+    dry_run: bool,
+}
+
+/// Held for the duration of every batch or single-archive operation so that
+/// operations started while another is running queue instead of interleaving
+#[derive(Default)]
+pub struct OperationLock(tokio::sync::Mutex<()>);
+
+/// Set by `cancel_operation`; long-running operations check it and stop early
+#[derive(Default)]
+pub struct CancelFlag(AtomicBool);
+
+/// Controls of the batch extraction in progress, for `cancel_operation`,
+/// `pause_operation` and `resume_operation`
+#[derive(Default)]
+pub struct ActiveCopy(Mutex<Option<Arc<CopyControl>>>);
+
+/// Rows of the most recent zip or MP3 scan, for `export_scan`
+#[derive(Default)]
+pub struct LastScan(Mutex<Vec<ScanRow>>);
+
+/// The results files of this session's streamed runs, by operation id, so
+/// the window can page through them with `get_results_page`
+#[derive(Default)]
+pub struct ResultFiles(Mutex<ResultFilesState>);
+
+#[derive(Default)]
+struct ResultFilesState {
+    last_id: u64,
+    files: HashMap<u64, PathBuf>,
+}
+
+impl ResultFiles {
+    /// Remember `path` and return the operation id to page it by
+    pub fn register(&self, path: PathBuf) -> u64 {
+        let mut state = self.0.lock().unwrap_or_else(|e| e.into_inner());
+        state.last_id += 1;
+        let id = state.last_id;
+        state.files.insert(id, path);
+        id
+    }
+
+    pub fn get(&self, operation_id: u64) -> Option<PathBuf> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).files.get(&operation_id).cloned()
+    }
 }
 
 #[derive(Serialize, Deserialize)]
 pub struct StripOptions {
     directory: String,
     output: Option<String>,
+    /// What files with the same name do when copied to `output`; later ones are renamed
+    #[serde(default)]
+    on_collision: CollisionPolicy,
+    #[serde(default = "default_workers")]
     workers: usize,
+    /// Storage the MP3s are on; picks the worker count instead of `workers`
+    #[serde(default = "default_io_profile")]
+    io_profile: Option<IoProfile>,
+    /// Lower the app's CPU and I/O priority for the run, and halve the
+    /// workers when `io_profile` picks them
+    #[serde(default)]
+    background: bool,
+    #[serde(default = "default_skip_clean")]
     skip_clean: bool,
     keep_fields: Option<String>,
+    remove_fields: Option<String>,
+    /// Rewrite kept genres to canonical names
+    #[serde(default)]
+    normalize_genre: bool,
+    /// TOML file of genre corrections for `normalize_genre`
+    genre_map: Option<String>,
+    /// Rewrite ID3v2.2 and v2.3 tags as v2.4, keeping every frame
+    #[serde(default)]
+    upgrade_tags: bool,
     remove_all: bool,
+    /// Fields `remove_all` keeps, e.g. `copyright,encoder`
+    #[serde(default)]
+    protect: Option<String>,
+    /// File types to process: mp3, wav and aiff
+    #[serde(default = "default_formats")]
+    formats: Vec<AudioFormat>,
     dry_run: bool,
+    #[serde(default = "default_true")]
+    recursive: bool,
+    max_depth: Option<usize>,
+    #[serde(default)]
+    skip_hidden: bool,
+    #[serde(default)]
+    include: Vec<String>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    where_expr: Option<String>,
+    #[serde(default)]
+    strip_private: bool,
+    #[serde(default)]
+    strip_txxx: Vec<String>,
+    /// Modify files even when their content isn't MPEG audio
+    #[serde(default)]
+    force: bool,
+    /// Order to process and list files in
+    #[serde(default)]
+    sort: SortOrder,
+    /// Process only this many of the MP3s found, after sorting and filtering
+    #[serde(default)]
+    limit: Option<usize>,
+    /// Which MP3s `limit` keeps: the first or a random subset
+    #[serde(default)]
+    sample: SampleMode,
+    /// Seed for a random sample, to pick the same MP3s again
+    #[serde(default)]
+    seed: Option<u64>,
+    /// Also process AppleDouble `._*` files and empty files
+    #[serde(default)]
+    include_hidden: bool,
+    /// Never write tags to files smaller than this many bytes
+    #[serde(default = "default_min_size")]
+    min_size: u64,
+    /// Bytes of padding after rewritten ID3 tags; none by default
+    #[serde(default)]
+    padding: usize,
+    /// Read each rewritten MP3 back, restoring it when its tag or audio is off
+    #[serde(default = "default_true")]
+    verify_write: bool,
+    /// Refuse to start unless this many bytes stay free on the output volume
+    #[serde(default)]
+    min_free_space: Option<u64>,
+    /// Attributes of the original files the copies in `output` keep
+    #[serde(default)]
+    preserve: Vec<PreserveAttr>,
+    /// Remove each original once its copy in `output` is written and checked
+    #[serde(default)]
+    delete_source: bool,
+    /// Whether `delete_source` deletes originals or moves them to the trash
+    #[serde(default)]
+    disposal: Disposal,
+    /// What happens when embedded art about to go has no cover image beside it
+    #[serde(default)]
+    art_safety: ArtSafety,
+    /// Write results to `<report>.ndjson` as they finish and return only
+    /// counts, for batches too large to hold or send at once
+    #[serde(default)]
+    stream_results: bool,
+    /// Run report path; defaults to `bulk-unzip-report-<timestamp>.json` in the
+    /// output directory, or the scanned one when stripping in place
+    #[serde(default)]
+    report: Option<String>,
+    #[serde(default)]
+    no_report: bool,
 }
 
-async fn find_zip_files(directory: &Path) -> Result<Vec<ZipFile>> {
-    let mut zip_files = Vec::new();
-    
-    for entry in WalkDir::new(directory)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "zip") {
-            let metadata = fs::metadata(path)
-                .with_context(|| format!("Failed to read metadata for {:?}", path))?;
-            zip_files.push(ZipFile {
-                path: path.to_string_lossy().to_string(),
-                size: metadata.len(),
-            });
-        }
+/// Sent as `archive-finished` the moment an archive's result is known;
+/// `index` is its position in the final results
+#[derive(Clone, Serialize)]
+pub struct ArchiveFinished<'a> {
+    index: usize,
+    total: usize,
+    result: &'a ExtractResult,
+}
+
+/// Sent as `file-finished` the moment a file's strip result is known;
+/// `index` is its position in the final results
+#[derive(Clone, Serialize)]
+pub struct FileFinished<'a> {
+    index: usize,
+    total: usize,
+    result: &'a StripResult,
+}
+
+/// What `strip_metadata` returns: every result, or for a streamed run the
+/// counts and the operation to read its results by with `get_results_page`
+#[derive(Serialize)]
+pub struct StripRun {
+    /// Empty when the results were streamed
+    results: Vec<StripResult>,
+    /// Results by status
+    counts: BTreeMap<String, usize>,
+    report_path: Option<String>,
+    /// Set when the results were streamed
+    operation_id: Option<u64>,
+    results_file: Option<String>,
+    /// The last failures of a streamed run
+    recent_errors: Vec<RecentError>,
+    /// Directories whose only artwork was embedded, as `art_safety` found
+    art_safety_directories: usize,
+}
+
+/// Why a strip run failed. A misspelled field name comes back on its own,
+/// for the GUI to show beside the field it was typed in.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StripError {
+    InvalidFieldName(InvalidFieldName),
+    Failed { message: String },
+}
+
+impl From<InvalidFieldName> for StripError {
+    fn from(error: InvalidFieldName) -> Self {
+        StripError::InvalidFieldName(error)
     }
-    
-    Ok(zip_files)
 }
 
-async fn extract_zip_file(
-    zip_file: &ZipFile,
-    output_dir: &Path,
-    skip_existing: bool,
-) -> Result<()> {
-    let path = PathBuf::from(&zip_file.path);
-    let file_name = path.file_stem().unwrap().to_string_lossy();
-    let extract_dir = output_dir.join(&*file_name);
-    
-    // Skip if directory exists and skip_existing is true
-    if skip_existing && extract_dir.exists() {
-        return Ok(());
-    }
-    
-    // Create extraction directory
-    fs::create_dir_all(&extract_dir)
-        .with_context(|| format!("Failed to create directory {:?}", extract_dir))?;
-    
-    // Open zip file
-    let file = fs::File::open(&path)
-        .with_context(|| format!("Failed to open zip file {:?}", path))?;
-    
-    let mut archive = ZipArchive::new(file)
-        .with_context(|| format!("Failed to read zip archive {:?}", path))?;
-    
-    // Extract all files
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)
-            .with_context(|| format!("Failed to read file at index {} in {:?}", i, path))?;
-        
-        let outpath = extract_dir.join(file.name());
-        
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)
-                .with_context(|| format!("Failed to create directory {:?}", outpath))?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    fs::create_dir_all(p)
-                        .with_context(|| format!("Failed to create parent directory {:?}", p))?;
-                }
+impl From<String> for StripError {
+    fn from(message: String) -> Self {
+        StripError::Failed { message }
+    }
+}
+
+/// Sent as `run-finished` when a batch unzip or strip ends, however it ended
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RunFinished {
+    command: String,
+    /// Where the run report was written, if it was
+    report_path: Option<String>,
+    /// The error or panic that stopped the run
+    failure: Option<String>,
+}
+
+/// A form's `report` and `no_report` options as the CLI's report flags
+fn report_args(report: Option<&str>, no_report: bool) -> ReportArgs {
+    ReportArgs { report: report.map(PathBuf::from), no_report, stream_results: false }
+}
+
+/// Record how the run ended, write its report when enabled and emit
+/// `run-finished`; the result is turned into the command's error
+fn finish_report<T>(app: &AppHandle, mut report: RunReport, path: Option<PathBuf>, outcome: std::thread::Result<Result<T>>) -> Result<T, String> {
+    let outcome = match outcome {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(e)) => Err(format!("{:#}", e)),
+        Err(panic) => Err(format!("panicked: {}", panic_message(panic.as_ref()))),
+    };
+    if let Err(failure) = &outcome {
+        report.fail(failure.clone());
+    }
+    let written = path.filter(|path| match report.write(path) {
+        Ok(()) => true,
+        Err(e) => {
+            eprintln!("Failed to write run report: {:#}", e);
+            false
+        }
+    });
+    let _ = app.emit("run-finished", RunFinished {
+        command: report.command.clone(),
+        report_path: written.map(|path| path.to_string_lossy().into_owned()),
+        failure: report.failure.clone(),
+    });
+    outcome
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_workers() -> usize {
+    DEFAULTS.workers
+}
+
+fn default_io_profile() -> Option<IoProfile> {
+    DEFAULTS.io_profile.and_then(|name| serde_json::from_value(name.into()).ok())
+}
+
+fn default_skip_existing() -> bool {
+    DEFAULTS.skip_existing
+}
+
+fn default_skip_clean() -> bool {
+    DEFAULTS.skip_clean
+}
+
+fn default_backup() -> bool {
+    DEFAULTS.backup
+}
+
+fn default_stabilize_timeout() -> u64 {
+    DEFAULT_STABILIZE_TIMEOUT.as_secs()
+}
+
+fn default_password_timeout() -> u64 {
+    DEFAULT_PASSWORD_TIMEOUT.as_secs()
+}
+
+fn default_formats() -> Vec<AudioFormat> {
+    vec![AudioFormat::Mp3]
+}
+
+fn default_min_size() -> u64 {
+    DEFAULT_MIN_SIZE
+}
+
+/// Archives with more entries than this are refused unless forced
+fn default_max_entries() -> usize {
+    DEFAULTS.max_entries
+}
+
+fn default_progress_interval_ms() -> u64 {
+    DEFAULT_PROGRESS_INTERVAL_MS
+}
+
+fn compile_containing(containing: Option<&str>) -> Result<Option<Pattern>> {
+    containing
+        .map(|glob| Pattern::new(glob).with_context(|| format!("Invalid containing pattern {:?}", glob)))
+        .transpose()
+}
+
+/// Turns the progress of an extraction into `unzip-progress` events and
+/// the operation's metrics
+struct ProgressReporter {
+    app: AppHandle,
+    total: u64,
+    metrics: OperationMetrics,
+    throttle: EventThrottle,
+    /// How often `watch_bytes` reads the byte count
+    poll: Duration,
+    /// The archive started last, which progress events name
+    current: Mutex<String>,
+}
+
+impl ProgressReporter {
+    fn new(app: AppHandle, total: u64, metrics: OperationMetrics, interval_ms: u64) -> Self {
+        ProgressReporter {
+            app,
+            total,
+            metrics,
+            throttle: EventThrottle::new(interval_ms),
+            poll: Duration::from_millis(interval_ms.max(1)),
+            current: Mutex::new(String::new()),
+        }
+    }
+
+    /// Bring the byte count up to `done` across the batch
+    fn advance_to(&self, done: u64) {
+        let written = self.metrics.snapshot().bytes_written;
+        if done <= written {
+            return;
+        }
+        self.metrics.add_bytes(done - written);
+        // The last bytes always get through, so the bar ends at 100%
+        if done >= self.total || self.throttle.should_emit(ProgressKind::Progress) {
+            let current = self.current.lock().unwrap_or_else(|e| e.into_inner()).clone();
+            self.emit(ProgressKind::Progress, &current, format!("Extracting: {}", current));
+        }
+    }
+
+    /// Keep the byte count up to date from `position`, the batch bar's,
+    /// until the returned task is aborted
+    fn watch_bytes(self: &Arc<Self>, position: impl Fn() -> u64 + Send + 'static) -> JoinHandle<()> {
+        let reporter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(reporter.poll);
+            loop {
+                interval.tick().await;
+                reporter.advance_to(position());
             }
-            
-            let mut outfile = fs::File::create(&outpath)
-                .with_context(|| format!("Failed to create file {:?}", outpath))?;
-            
-            std::io::copy(&mut file, &mut outfile)
-                .with_context(|| format!("Failed to write file {:?}", outpath))?;
+        })
+    }
+
+    fn archive_started(&self, archive: &Path) {
+        let name = archive.file_name().unwrap_or_default().to_string_lossy();
+        *self.current.lock().unwrap_or_else(|e| e.into_inner()) = name.to_string();
+        self.archive_event(ProgressKind::ArchiveStarted, archive);
+    }
+
+    /// Count a finished archive's files and report how it ended
+    fn archive_finished(&self, report: &ArchiveReport) {
+        self.metrics.add_files(report.file_types.files() as u64);
+        self.metrics.archive_done();
+        let kind = if report.result.is_ok() { ProgressKind::ArchiveFinished } else { ProgressKind::ArchiveFailed };
+        self.archive_event(kind, &report.path);
+    }
+
+    /// Report an archive starting, finishing or failing, never throttled
+    fn archive_event(&self, kind: ProgressKind, archive: &Path) {
+        let name = archive.file_name().unwrap_or_default().to_string_lossy();
+        let message = match kind {
+            ProgressKind::ArchiveStarted => format!("Started: {}", name),
+            ProgressKind::ArchiveFailed => format!("Failed: {}", name),
+            _ => format!("Finished: {}", name),
+        };
+        if self.throttle.should_emit(kind) {
+            self.emit(kind, &name, message);
         }
     }
-    
-    Ok(())
+
+    /// Sent as `unzip-progress`; every event carries the running totals, so
+    /// dropped ones cause no drift
+    fn emit(&self, kind: ProgressKind, current_file: &str, message: String) {
+        let metrics = self.metrics.snapshot();
+        let _ = self.app.emit("unzip-progress", UnzipProgress {
+            kind,
+            current_file: current_file.to_string(),
+            progress: metrics.bytes_written,
+            total: self.total,
+            message,
+            metrics,
+        });
+    }
 }
 
-async fn bulk_unzip(options: UnzipOptions) -> Result<Vec<String>> {
-    let directory = PathBuf::from(&options.directory);
+/// Bars the extractor counts the bytes it writes on, drawn nowhere
+fn hidden_bars() -> (MultiProgress, ProgressBar) {
+    (MultiProgress::with_draw_target(ProgressDrawTarget::hidden()), ProgressBar::hidden())
+}
+
+/// The roots of a scan: `directory`, then any further `directories`
+fn input_roots(directory: &str, directories: &[String]) -> ScanRoots {
+    let paths: Vec<PathBuf> = std::iter::once(directory).chain(directories.iter().map(String::as_str)).map(PathBuf::from).collect();
+    ScanRoots::new(&paths)
+}
+
+/// The extractor the unzip form describes, but for what depends on the
+/// run: its I/O settings, passwords and progress
+fn extractor(options: &UnzipOptions, roots: &ScanRoots, control: &Arc<CopyControl>) -> Result<Extractor> {
+    let mut extractor = Extractor::new()
+        .output(&options.output)
+        .roots(roots.clone())
+        .skip_existing(options.skip_existing)
+        .overwrite(options.overwrite)
+        .disposal(options.disposal)
+        .max_entries((!options.force).then_some(options.max_entries))
+        .strict_times(options.strict_times)
+        .prune_empty(options.prune_empty)
+        .on_busy(options.on_busy)
+        .on_validation_failure(options.on_validation_failure)
+        .skip_signatures(options.profile.is_some() && !options.keep_signatures)
+        .control(control.clone())
+        .memory_limit(options.memory_limit)
+        .sort(options.sort)
+        .strict_fs(options.strict_fs)
+        .attempt_suspect(options.attempt_suspect)
+        .stabilize_timeout(Duration::from_secs(options.stabilize_timeout))
+        .skip_duplicates(options.skip_duplicates)
+        .label_by_type(options.label_by_type);
+    if let Some(pattern) = compile_containing(options.containing.as_deref())? {
+        extractor = extractor.containing(pattern);
+    }
+    if let Some(pattern) = &options.latest_per_group {
+        extractor = extractor.latest_per_group(compile_group_pattern(pattern)?, options.group_order);
+    }
+    if let Some(limit) = options.limit {
+        extractor = extractor.limit(limit, options.sample, options.seed);
+    }
+    if let Some(template) = &options.name_template {
+        extractor = extractor.name_template(NameTemplate::parse(template)?);
+    }
+    if let Some(source) = options.split_output_by_date {
+        extractor = extractor.split_output_by_date(source);
+    }
+    Ok(extractor)
+}
+
+async fn bulk_unzip(options: UnzipOptions, app: AppHandle, metrics: OperationMetrics, control: Arc<CopyControl>, requests: PasswordRequests, report: &mut RunReport) -> Result<UnzipReport> {
+    let roots = input_roots(&options.directory, &options.directories);
     let output = PathBuf::from(&options.output);
     
-    let zip_files = find_zip_files(&directory).await?;
-    
+    let containing = compile_containing(options.containing.as_deref())?;
+    let mut zip_files = find_zip_files_in(&roots, containing.as_ref(), &mut ScanMonitor::default().excluding_output(Some(&output))).await?;
+    if let Some(kind) = options.profile {
+        zip_files.retain(|f| f.kind == kind);
+    }
     if zip_files.is_empty() {
-        return Ok(vec!["No zip files found".to_string()]);
+        return Ok(UnzipReport { schema_version: SCHEMA_VERSION, results: Vec::new(), file_types: FileTypeCounts::default(), fs_warning: None, duplicates: Vec::new(), groups: Vec::new(), sample: None, roots: Vec::new() });
+    }
+    
+    let workers = options.io_profile.is_none().then_some(options.workers);
+    let mut io = settings_for_run(options.io_profile, workers, Workload::Extract, roots.first());
+    if options.background {
+        report.set_priority(&run_in_background(&mut io, workers.is_some()));
+    }
+    report.set_io(&io);
+    let mut extractor = extractor(&options, &roots, &control)?.io_settings(io);
+    let plan = extractor.plan(zip_files).await?;
+    if plan.memory.workers != plan.io.workers || plan.memory.buffer_size != plan.io.buffer_size {
+        eprintln!("Memory limit: using {} workers with {} byte buffers", plan.memory.workers, plan.memory.buffer_size);
+    }
+    
+    report.set_scan(&serde_json::json!({
+        "archives": plan.scanned.len(),
+        "total_size": plan.scanned.iter().map(|f| f.size).sum::<u64>(),
+        "uncompressed_size": plan.scanned.iter().map(|f| f.uncompressed_size).sum::<u64>(),
+        "suspect": plan.scanned.iter().filter(|f| f.health != ArchiveHealth::Ok).count(),
+    }));
+    if options.latest_per_group.is_some() {
+        report.set_groups(&plan.groups);
     }
+    if let Some(sample) = &plan.sample {
+        report.set_sample(sample);
+    }
+    if options.skip_duplicates {
+        report.set_duplicates(&plan.duplicates);
+    }
+    // Failing to identify the filesystem doesn't stop the run
+    let fs_warning = match &plan.output_fs {
+        Ok(check) if !check.is_empty() => Some(format!("Output is on {}: {}", check.filesystem.name(), check.problems().join("; "))),
+        Ok(_) => None,
+        Err(e) => Some(format!("{:#}", e)),
+    };
     
-    // Create output directory
-    fs::create_dir_all(&output)
-        .with_context(|| format!("Failed to create output directory {:?}", output))?;
+    let total: u64 = plan.archives.iter().map(|f| f.uncompressed_size).sum();
+    let operation = metrics.begin(plan.archives.len() as u64, total);
+    let reporter = Arc::new(ProgressReporter::new(app, total, metrics, options.progress_interval_ms));
+    if options.ask_passwords {
+        let timeout = Duration::from_secs(options.password_timeout);
+        let asker = PasswordAsker::new(reporter.app.clone(), requests, operation, timeout, control.clone());
+        extractor = extractor.passwords(Passwords::default().with_prompt(Arc::new(asker)));
+    }
     
-    // Process zip files with limited concurrency
-    let chunks: Vec<_> = zip_files
-        .chunks((zip_files.len() + options.workers - 1) / options.workers)
+    // Archives the plan leaves out go back in scan order, which is known
+    // before anything runs
+    let mut order: Vec<PathBuf> = plan
+        .archives
+        .iter()
+        .chain(&plan.suspect)
+        .chain(&plan.unstable)
+        .chain(&plan.processed)
+        .map(|f| f.path.clone())
+        .chain(plan.duplicates.iter().flat_map(|group| group.skipped.iter().cloned()))
+        .chain(plan.groups.iter().flat_map(|group| group.superseded.iter().cloned()))
         .collect();
+    options.sort.sort_by_path(&mut order, |path| path.as_path());
+    let app = reporter.app.clone();
+    let stream = Arc::new(ResultStream::new(order, move |index, total, result: &ExtractResult| {
+        let _ = app.emit("archive-finished", ArchiveFinished { index, total, result });
+    }));
+    let left_out = [(&plan.suspect, ArchiveStatus::SkippedSuspect), (&plan.unstable, ArchiveStatus::SkippedUnstable), (&plan.processed, ArchiveStatus::SkippedProcessed)];
+    for (zip_files, status) in left_out {
+        for zip_file in zip_files {
+            stream.finish(&zip_file.path, ExtractResult::with_status(&zip_file.path, &status));
+        }
+    }
+    for group in &plan.duplicates {
+        for path in &group.skipped {
+            stream.finish(path, ExtractResult::with_status(path, &ArchiveStatus::SkippedDuplicateOf(group.kept.clone())));
+        }
+    }
+    for group in &plan.groups {
+        for path in &group.superseded {
+            stream.finish(path, ExtractResult::with_status(path, &ArchiveStatus::SkippedSuperseded(group.kept.clone())));
+        }
+    }
     
-    let mut results = Vec::new();
+    let (multi, batch_bar) = hidden_bars();
+    let position = batch_bar.clone();
+    let byte_watch = reporter.watch_bytes(move || position.position());
+    let (events, finished) = (reporter.clone(), stream.clone());
+    let batch = extractor
+        .progress_bars(multi, batch_bar.clone(), ProgressStyle::default_bar())
+        .progress(move |event| match event {
+            ProgressEvent::Started { archive, .. } => events.archive_started(archive),
+            ProgressEvent::Finished { archive, result } => {
+                events.archive_finished(result);
+                finished.finish(archive, ExtractResult::from(result));
+            }
+            _ => {}
+        })
+        .run(plan)
+        .await;
+    byte_watch.abort();
+    reporter.advance_to(batch_bar.position());
+    let batch = batch?;
+    
+    let results: Vec<ExtractResult> = batch.archives.iter().map(ExtractResult::from).collect();
+    for result in &results {
+        report.add_result(result);
+    }
+    let failures: Vec<(&str, &str)> = results
+        .iter()
+        .filter_map(|r| r.error.as_deref().map(|error| (r.path.as_str(), error)))
+        .collect();
+    report.set_errors(&failures);
     
-    for chunk in chunks {
-        let futures: Vec<_> = chunk
-            .iter()
-            .map(|zip_file| {
-                let output_dir = output.clone();
-                let skip_existing = options.skip_existing;
-                
-                async move {
-                    match extract_zip_file(zip_file, &output_dir, skip_existing).await {
-                        Ok(_) => format!("✅ Extracted: {}", zip_file.path),
-                        Err(e) => format!("❌ Error extracting {}: {}", zip_file.path, e),
-                    }
-                }
-            })
-            .collect();
-        
-        let chunk_results = join_all(futures).await;
-        results.extend(chunk_results);
+    let mut root_counts = if roots.is_multiple() { roots.counts() } else { Vec::new() };
+    for result in &results {
+        if let Some(counts) = root_counts.get_mut(roots.index_of(Path::new(&result.path))) {
+            counts.add(match result.status {
+                ExtractStatus::Extracted => RootOutcome::Processed,
+                ExtractStatus::Failed => RootOutcome::Failed,
+                _ => RootOutcome::Skipped,
+            });
+        }
+    }
+    if !root_counts.is_empty() {
+        report.set_roots(&root_counts);
     }
     
-    Ok(results)
+    Ok(UnzipReport {
+        schema_version: SCHEMA_VERSION,
+        results,
+        file_types: batch.file_types,
+        fs_warning,
+        duplicates: batch.duplicates,
+        groups: batch.groups,
+        sample: batch.sample,
+        roots: root_counts,
+    })
 }
 
 #[tauri::command]
-pub async fn unzip_files(options: UnzipOptions) -> Result<Vec<String>, String> {
-    bulk_unzip(options)
-        .await
-        .map_err(|e| e.to_string())
+pub async fn unzip_files(
+    app: AppHandle,
+    options: UnzipOptions,
+    history: State<'_, HistoryStore>,
+    lock: State<'_, OperationLock>,
+    metrics: State<'_, OperationMetrics>,
+    active: State<'_, ActiveCopy>,
+    requests: State<'_, PasswordRequests>,
+) -> Result<UnzipReport, String> {
+    let _guard = lock.0.lock().await;
+    let started = Instant::now();
+    let options_json = serde_json::to_value(&options).map_err(|e| e.to_string())?;
+    let directory = options.directory.clone();
+    let output = Some(options.output.clone());
+    let report_path = report_args(options.report.as_deref(), options.no_report).path(Path::new(&options.output), false);
+    let mut run_report = RunReport::new("unzip", &options);
+    let control = Arc::new(match options.limit_rate {
+        Some(rate) => CopyControl::new().rate_limit(rate),
+        None => CopyControl::new(),
+    });
+    *active.0.lock().unwrap_or_else(|e| e.into_inner()) = Some(control.clone());
+
+    let outcome = AssertUnwindSafe(bulk_unzip(options, app.clone(), metrics.inner().clone(), control, requests.inner().clone(), &mut run_report))
+        .catch_unwind()
+        .await;
+    *active.0.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    let report = finish_report(&app, run_report, report_path, outcome)?;
+
+    let count = |status: ExtractStatus| report.results.iter().filter(|r| r.status == status).count();
+    let entry = HistoryEntry {
+        kind: OperationKind::Unzip,
+        directory,
+        output,
+        processed: count(ExtractStatus::Extracted),
+        skipped: count(ExtractStatus::SkippedExisting) + count(ExtractStatus::SkippedFiltered) + count(ExtractStatus::SkippedEncrypted) + count(ExtractStatus::SkippedSuspect) + count(ExtractStatus::SkippedDuplicate) + count(ExtractStatus::SkippedSuperseded) + count(ExtractStatus::SkippedUnstable) + count(ExtractStatus::SkippedProcessed),
+        failed: count(ExtractStatus::Failed),
+        duration_ms: started.elapsed().as_millis() as u64,
+        timestamp: now_timestamp(),
+        options: options_json,
+    };
+    if let Err(e) = history.record(entry) {
+        eprintln!("Failed to record history: {}", e);
+    }
+
+    Ok(report)
+}
+
+/// The strip form's options as the library's arguments
+fn metadata_args(options: StripOptions) -> MetadataArgs {
+    let mut args = MetadataArgs::default();
+    args.directories = vec![PathBuf::from(options.directory)];
+    args.delete_source = options.delete_source && options.output.is_some();
+    args.output = options.output.map(PathBuf::from);
+    args.on_collision = options.on_collision;
+    args.workers = options.io_profile.is_none().then_some(options.workers);
+    args.io_profile = options.io_profile;
+    args.background = options.background;
+    args.skip_clean = options.skip_clean;
+    args.keep_fields = options.keep_fields;
+    args.remove_fields = options.remove_fields;
+    args.normalize_genre = options.normalize_genre;
+    args.genre_map = options.genre_map.map(PathBuf::from);
+    args.upgrade_tags = options.upgrade_tags;
+    args.remove_all = options.remove_all;
+    args.protect = options.protect;
+    args.formats = options.formats;
+    args.dry_run = options.dry_run;
+    args.recursive = options.recursive;
+    args.max_depth = options.max_depth;
+    args.skip_hidden = options.skip_hidden;
+    args.include = options.include;
+    args.exclude = options.exclude;
+    args.where_expr = options.where_expr;
+    args.strip_private = options.strip_private;
+    args.strip_txxx = options.strip_txxx;
+    args.force = options.force;
+    args.sort = options.sort;
+    args.limit = options.limit;
+    args.sample = options.sample;
+    args.seed = options.seed;
+    args.include_hidden = options.include_hidden;
+    args.min_size = options.min_size;
+    args.padding = options.padding;
+    args.no_verify_write = !options.verify_write;
+    args.min_free_space = options.min_free_space;
+    args.preserve = options.preserve;
+    args.disposal = options.disposal;
+    args.art_safety = options.art_safety;
+    args
 }
 
 #[tauri::command]
-pub async fn strip_metadata(options: StripOptions) -> Result<Vec<String>, String> {
-    let metadata_args = MetadataArgs {
-        directory: PathBuf::from(&options.directory),
-        output: options.output.map(PathBuf::from),
-        workers: options.workers,
-        skip_clean: options.skip_clean,
-        keep_fields: options.keep_fields,
-        remove_all: options.remove_all,
-        dry_run: options.dry_run,
+pub async fn strip_metadata(
+    app: AppHandle,
+    options: StripOptions,
+    history: State<'_, HistoryStore>,
+    lock: State<'_, OperationLock>,
+    result_files: State<'_, ResultFiles>,
+) -> Result<StripRun, StripError> {
+    // Checked before anything is written, so a typo fails the run up front
+    check_field_lists(options.keep_fields.as_deref(), options.remove_fields.as_deref(), options.protect.as_deref())?;
+    let _guard = lock.0.lock().await;
+    let started = Instant::now();
+    let options_json = serde_json::to_value(&options).map_err(|e| e.to_string())?;
+    let directory = options.directory.clone();
+    let output = options.output.clone();
+    let report_path = report_args(options.report.as_deref(), options.no_report)
+        .path(Path::new(output.as_deref().unwrap_or(&directory)), options.dry_run);
+    let mut run_report = RunReport::new("strip", &options);
+    if options.stream_results {
+        let Some(path) = &report_path else {
+            return Err("Streamed results are written beside the run report; give a dry run a report path".to_string().into());
+        };
+        run_report.stream_results(&results_file_for(path)).map_err(|e| format!("{:#}", e))?;
+    }
+    let log = run_report.result_log();
+
+    let metadata_args = metadata_args(options);
+    let art_directories = Mutex::new(HashSet::new());
+    let on_finished = |index, total, result: &StripResult| {
+        if let Some(hit) = &result.art_safety {
+            art_directories.lock().unwrap_or_else(|e| e.into_inner()).insert(hit.directory.clone());
+        }
+        let _ = app.emit("file-finished", FileFinished { index, total, result });
+    };
+    let outcome = AssertUnwindSafe(bulk_strip_metadata_with_report(metadata_args, &mut run_report, on_finished))
+        .catch_unwind()
+        .await;
+    let results = finish_report(&app, run_report, report_path.clone(), outcome)?;
+
+    let counts = match &log {
+        Some(log) => log.counts(),
+        None => results.iter().fold(BTreeMap::new(), |mut counts, r| {
+            *counts.entry(format!("{:?}", r.status)).or_default() += 1;
+            counts
+        }),
     };
+    let count = |status: StripStatus| counts.get(&format!("{:?}", status)).copied().unwrap_or(0);
+    let entry = HistoryEntry {
+        kind: OperationKind::Strip,
+        directory,
+        output,
+        processed: count(StripStatus::Stripped) + count(StripStatus::DryRun) + count(StripStatus::CopiedUnchanged),
+        skipped: count(StripStatus::Skipped) + count(StripStatus::SkippedNoop) + count(StripStatus::SkippedFiltered) + count(StripStatus::SkippedCollision) + count(StripStatus::WrongFormat),
+        failed: count(StripStatus::Failed) + count(StripStatus::SkippedOutOfSpace) + count(StripStatus::WriteVerificationFailed),
+        duration_ms: started.elapsed().as_millis() as u64,
+        timestamp: now_timestamp(),
+        options: options_json,
+    };
+    if let Err(e) = history.record(entry) {
+        eprintln!("Failed to record history: {}", e);
+    }
+
+    Ok(StripRun {
+        results,
+        counts,
+        report_path: report_path.map(|path| path.to_string_lossy().into_owned()),
+        operation_id: log.as_ref().map(|log| result_files.register(log.path().to_path_buf())),
+        results_file: log.as_ref().map(|log| log.path().to_string_lossy().into_owned()),
+        recent_errors: log.map(|log| log.recent_errors()).unwrap_or_default(),
+        art_safety_directories: art_directories.into_inner().unwrap_or_else(|e| e.into_inner()).len(),
+    })
+}
+
+/// Up to `limit` results of the streamed strip `operation_id`, from the
+/// `offset`th on, in the order they finished
+#[tauri::command]
+pub async fn get_results_page(
+    operation_id: u64,
+    offset: usize,
+    limit: usize,
+    result_files: State<'_, ResultFiles>,
+) -> Result<Vec<serde_json::Value>, String> {
+    let path = result_files
+        .get(operation_id)
+        .ok_or_else(|| format!("Operation {} has no streamed results", operation_id))?;
+    read_page(&path, offset, limit).map_err(|e| format!("{:#}", e))
+}
+
+/// Extract one archive, e.g. a zip dropped onto the window
+#[tauri::command]
+pub async fn extract_single_zip(
+    app: AppHandle,
+    path: String,
+    options: SingleUnzipOptions,
+    lock: State<'_, OperationLock>,
+    metrics: State<'_, OperationMetrics>,
+) -> Result<ExtractResult, String> {
+    let zip_path = PathBuf::from(&path);
+    if !zip_path.is_file() {
+        return Err(format!("{:?} is not a file", zip_path));
+    }
+    if ArchiveKind::from_path(&zip_path).is_none() {
+        return Err(format!("{:?} is not a zip, comic or ebook archive", zip_path));
+    }
+    
+    let _guard = lock.0.lock().await;
+    
+    let containing = compile_containing(options.containing.as_deref()).map_err(|e| e.to_string())?;
+    let zip_file = zip_files_at(std::slice::from_ref(&zip_path), containing.as_ref())
+        .map_err(|e| e.to_string())?
+        .remove(0);
+    if zip_file.health == ArchiveHealth::Rar {
+        return Err(format!("{:?} is a rar archive: rar unsupported", zip_path));
+    }
+    
+    metrics.begin(1, zip_file.uncompressed_size);
+    let reporter = Arc::new(ProgressReporter::new(app, zip_file.uncompressed_size, metrics.inner().clone(), options.progress_interval_ms));
+    let (multi, batch_bar) = hidden_bars();
+    let position = batch_bar.clone();
+    let byte_watch = reporter.watch_bytes(move || position.position());
+    let events = reporter.clone();
+    let batch = Extractor::new()
+        .output(&options.output)
+        .workers(1)
+        .skip_existing(options.skip_existing)
+        .overwrite(options.overwrite)
+        .disposal(options.disposal)
+        .max_entries((!options.force).then_some(options.max_entries))
+        .strict_times(options.strict_times)
+        // Dropped onto the window, so tried even when the scan flags it
+        .attempt_suspect(true)
+        .assume_complete(true)
+        .progress_bars(multi, batch_bar.clone(), ProgressStyle::default_bar())
+        .progress(move |event| match event {
+            ProgressEvent::Started { archive, .. } => events.archive_started(archive),
+            ProgressEvent::Finished { result, .. } => events.archive_finished(result),
+            _ => {}
+        })
+        .extract(vec![zip_file])
+        .await;
+    byte_watch.abort();
+    reporter.advance_to(batch_bar.position());
+    let batch = batch.map_err(|e| format!("{:#}", e))?;
+    Ok(ExtractResult::from(&batch.archives[0]))
+}
+
+/// Remove exactly the named frames from one file, for the tag editor.
+/// Frames are named by label (`TXXX (SERATO_MARKERS)`), frame ID or field name.
+#[tauri::command]
+pub async fn strip_single_file(
+    path: String,
+    frames_to_remove: Vec<String>,
+    options: SingleStripOptions,
+    lock: State<'_, OperationLock>,
+) -> Result<FrameEdit, FrameEditError> {
+    let _guard = lock.0.lock().await;
+    remove_frames(Path::new(&path), &frames_to_remove, options.backup, options.dry_run)
+}
+
+/// Monitor that emits `scan-progress` events and stops when the operation is cancelled
+fn scan_monitor<'a>(app: AppHandle, cancel: &'a CancelFlag) -> ScanMonitor<'a> {
+    ScanMonitor::reporting(
+        move |progress: &ScanProgress| {
+            let _ = app.emit("scan-progress", progress.clone());
+        },
+        &cancel.0,
+    )
+}
+
+/// Ask the running operation to stop
+/// Live numbers of extraction `operation_id`, for polling between `unzip-progress` events
+#[tauri::command]
+pub async fn get_metrics(operation_id: u64, metrics: State<'_, OperationMetrics>) -> Result<MetricsSnapshot, String> {
+    if metrics.operation_id() != operation_id {
+        return Err(format!("Operation {} is no longer current", operation_id));
+    }
+    Ok(metrics.snapshot())
+}
+
+#[tauri::command]
+pub async fn cancel_operation(cancel: State<'_, CancelFlag>, active: State<'_, ActiveCopy>) -> Result<(), String> {
+    cancel.0.store(true, Ordering::Relaxed);
+    if let Some(control) = active.0.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        control.cancel();
+    }
+    Ok(())
+}
+
+/// Answer the `password-required` event with this token; a wrong password
+/// is asked for again, up to the event's `attempts`
+#[tauri::command]
+pub async fn provide_password(token: String, password: String, requests: State<'_, PasswordRequests>) -> Result<(), String> {
+    if requests.answer(&token, Some(password)) {
+        Ok(())
+    } else {
+        Err("No archive is waiting for this password any more; it timed out or was cancelled".to_string())
+    }
+}
+
+/// Skip the archive the `password-required` event with this token asks about
+#[tauri::command]
+pub async fn decline_password(token: String, requests: State<'_, PasswordRequests>) -> Result<(), String> {
+    requests.answer(&token, None);
+    Ok(())
+}
+
+/// Pause the running batch extraction inside its current entry
+#[tauri::command]
+pub async fn pause_operation(active: State<'_, ActiveCopy>) -> Result<(), String> {
+    if let Some(control) = active.0.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        control.pause();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_operation(active: State<'_, ActiveCopy>) -> Result<(), String> {
+    if let Some(control) = active.0.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        control.resume();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn scan_zip_files(
+    app: AppHandle,
+    directory: String,
+    directories: Option<Vec<String>>,
+    containing: Option<String>,
+    output: Option<String>,
+    hash: Option<bool>,
+    sort: Option<SortOrder>,
+    cancel: State<'_, CancelFlag>,
+    last_scan: State<'_, LastScan>,
+) -> Result<Vec<ScannedZipFile>, String> {
+    let roots = input_roots(&directory, &directories.unwrap_or_default());
+    let containing = compile_containing(containing.as_deref()).map_err(|e| e.to_string())?;
+    cancel.0.store(false, Ordering::Relaxed);
+    let mut monitor = scan_monitor(app.clone(), &cancel).excluding_output(output.as_deref().map(Path::new));
+    let mut zip_files = find_zip_files_in(&roots, containing.as_ref(), &mut monitor)
+        .await
+        .map_err(|e| e.to_string())?;
+    sort.unwrap_or_default().sort_by_path(&mut zip_files, |f| &f.path);
+    
+    // Hashing reads every byte, so it reports through the same scan-progress events
+    if hash.unwrap_or(false) {
+        let walked = monitor.progress();
+        let hashed = AtomicU64::new(0);
+        let throttle = EventThrottle::new(DEFAULT_PROGRESS_INTERVAL_MS);
+        let paths: Vec<PathBuf> = zip_files.iter().map(|f| f.path.clone()).collect();
+        let hashes = sha256_files(&paths, &cancel.0, |read| {
+            let bytes_hashed = hashed.fetch_add(read, Ordering::Relaxed) + read;
+            if throttle.should_emit(ProgressKind::Progress) {
+                let _ = app.emit("scan-progress", ScanProgress { bytes_hashed, ..walked.clone() });
+            }
+        })
+        .map_err(|e| e.to_string())?;
+        let _ = app.emit("scan-progress", ScanProgress { bytes_hashed: hashed.into_inner(), ..walked });
+        for (zip_file, hash) in zip_files.iter_mut().zip(hashes) {
+            zip_file.sha256 = Some(hash);
+        }
+    }
     
-    bulk_strip_metadata(metadata_args)
+    *last_scan.0.lock().unwrap_or_else(|e| e.into_inner()) = zip_files
+        .iter()
+        .map(|f| ScanRow {
+            kind: f.kind.name(),
+            path: f.path.to_string_lossy().into_owned(),
+            size: f.size,
+            entries: f.entries,
+            uncompressed_size: f.entries.map(|_| f.uncompressed_size),
+            suspect: (f.health != ArchiveHealth::Ok).then(|| f.health.label().to_string()),
+            mtime: Some(f.mtime),
+            sha256: f.sha256.clone(),
+            zip64: Some(f.zip64),
+            ..ScanRow::default()
+        })
+        .collect();
+    Ok(zip_files
+        .into_iter()
+        .map(|zip_file| ScannedZipFile {
+            root: roots.is_multiple().then(|| roots.root_of(&zip_file.path).label.clone()),
+            zip_file,
+        })
+        .collect())
+}
+
+#[tauri::command]
+pub async fn scan_mp3_files(
+    app: AppHandle,
+    directory: String,
+    directories: Option<Vec<String>>,
+    sort: Option<SortOrder>,
+    include_hidden: Option<bool>,
+    cancel: State<'_, CancelFlag>,
+    last_scan: State<'_, LastScan>,
+) -> Result<Vec<Mp3File>, String> {
+    let roots = input_roots(&directory, &directories.unwrap_or_default());
+    cancel.0.store(false, Ordering::Relaxed);
+    let mut monitor = scan_monitor(app, &cancel);
+    let walk = WalkOptions { include_hidden: include_hidden.unwrap_or(false), ..WalkOptions::default() };
+    let mut files = metadata_stripper::find_mp3_files_in(&roots, &walk, &mut monitor)
         .await
-        .map(|_| vec!["Metadata stripping completed".to_string()])
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    sort.unwrap_or_default().sort_by_path(&mut files, |f| &f.path);
+    
+    *last_scan.0.lock().unwrap_or_else(|e| e.into_inner()) = files
+        .iter()
+        .map(|f| ScanRow {
+            kind: "mp3",
+            path: f.path.to_string_lossy().to_string(),
+            size: f.size,
+            suspect: f.format.is_other_format().then(|| format!("{} content", f.format)),
+            has_metadata: Some(f.has_metadata),
+            title: f.tags.title.clone(),
+            artist: f.tags.artist.clone(),
+            album: f.tags.album.clone(),
+            has_art: Some(f.tags.has_art),
+            tag_version: f.tag_version.clone(),
+            tag_size_bytes: Some(f.tag_size_bytes),
+            ..ScanRow::default()
+        })
+        .collect();
+    Ok(files
+        .into_iter()
+        .map(|f| Mp3File {
+            path: f.path.to_string_lossy().to_string(),
+            size: f.size,
+            has_metadata: f.has_metadata,
+            format: f.format,
+            tag_version: f.tag_version,
+            tag_size_bytes: f.tag_size_bytes,
+            root: roots.is_multiple().then(|| roots.root_of(&f.path).label.clone()),
+        })
+        .collect())
 }
 
+/// Write the most recent scan to `path` as CSV or JSON
 #[tauri::command]
-pub async fn scan_zip_files(directory: String) -> Result<Vec<ZipFile>, String> {
+pub async fn export_scan(path: String, format: ExportFormat, last_scan: State<'_, LastScan>) -> Result<usize, String> {
+    let rows = last_scan.0.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    if rows.is_empty() {
+        return Err("Nothing to export; run a scan first".to_string());
+    }
+    write_scan(&rows, Path::new(&path), format).map_err(|e| e.to_string())?;
+    Ok(rows.len())
+}
+
+#[tauri::command]
+pub async fn find_duplicate_mp3s(directory: String, report: Option<String>) -> Result<Vec<DuplicateGroup>, String> {
     let path = PathBuf::from(directory);
-    find_zip_files(&path)
+    let mp3_files = metadata_stripper::find_mp3_files(&path, &WalkOptions::default(), &mut ScanMonitor::default())
         .await
-        .map_err(|e| e.to_string())
+        .map_err(|e| e.to_string())?;
+    let duplicates = find_duplicates(&mp3_files);
+    
+    if let Some(report) = report {
+        write_report(&duplicates, Path::new(&report)).map_err(|e| e.to_string())?;
+    }
+    
+    Ok(duplicates.groups)
 }
 
+/// An archive's entries with compression method, sizes and ratio, plus totals
 #[tauri::command]
-pub async fn scan_mp3_files(directory: String) -> Result<Vec<Mp3File>, String> {
+pub async fn list_zip_contents(path: String) -> Result<ArchiveListing, String> {
+    list_archive(Path::new(&path)).map_err(|e| e.to_string())
+}
+
+/// Summarize a directory's archives and MP3s for the dashboard, emitting
+/// `scan-progress` while walking; stopped by `cancel_operation`
+#[tauri::command]
+pub async fn get_directory_stats(
+    app: AppHandle,
+    directory: String,
+    cancel: State<'_, CancelFlag>,
+) -> Result<DirectoryStats, String> {
     let path = PathBuf::from(directory);
-    metadata_stripper::find_mp3_files(&path)
+    cancel.0.store(false, Ordering::Relaxed);
+    let zip_files = find_zip_files(&path, None, &mut scan_monitor(app.clone(), &cancel))
         .await
-        .map(|files| {
-            files
-                .into_iter()
-                .map(|f| Mp3File {
-                    path: f.path.to_string_lossy().to_string(),
-                    size: f.size,
-                    has_metadata: f.has_metadata,
-                })
-                .collect()
-        })
-        .map_err(|e| e.to_string())
-} 
\ No newline at end of file
+        .map_err(|e| e.to_string())?;
+    let mp3_files = metadata_stripper::find_mp3_files(&path, &WalkOptions::default(), &mut scan_monitor(app, &cancel))
+        .await
+        .map_err(|e| e.to_string())?;
+    
+    let mut stats = DirectoryStats::default();
+    for zip_file in &zip_files {
+        if cancel.0.load(Ordering::Relaxed) {
+            return Err("Scan cancelled".to_string());
+        }
+        let zip_path = zip_file.path.as_path();
+        let readable = zip_file.health == ArchiveHealth::Ok && zip_file.entries.is_some();
+        stats.add_archive(
+            zip_path,
+            zip_file.size,
+            zip_file.entries.map(|_| zip_file.uncompressed_size),
+            zip_file.health != ArchiveHealth::Ok,
+            readable && is_encrypted(zip_path),
+        );
+    }
+    for mp3_file in &mp3_files {
+        stats.add_mp3(&mp3_file.path, mp3_file.size, mp3_file.has_metadata);
+    }
+    Ok(stats)
+}
+
+/// Past operations, most recent first
+#[tauri::command]
+pub async fn get_history(limit: Option<usize>, history: State<'_, HistoryStore>) -> Result<Vec<HistoryEntry>, String> {
+    Ok(history.recent(limit))
+}
+
+#[tauri::command]
+pub async fn clear_history(history: State<'_, HistoryStore>) -> Result<(), String> {
+    history.clear().map_err(|e| e.to_string())
+}
+
+/// Options of the most recent run of `kind`, for pre-filling the form
+#[tauri::command]
+pub async fn get_last_options(kind: OperationKind, history: State<'_, HistoryStore>) -> Result<Option<serde_json::Value>, String> {
+    Ok(history.last_options(kind))
+}
+
+/// The options of a form left untouched, to tell which of a preset's were set
+fn form_defaults(kind: OperationKind) -> serde_json::Value {
+    let defaults = match kind {
+        OperationKind::Unzip => serde_json::from_value::<UnzipOptions>(serde_json::json!({ "directory": "", "output": "" }))
+            .and_then(serde_json::to_value),
+        OperationKind::Strip => serde_json::from_value::<StripOptions>(serde_json::json!({ "directory": "", "remove_all": false, "dry_run": false }))
+            .and_then(serde_json::to_value),
+    };
+    defaults.expect("the form's defaults deserialize")
+}
+
+fn fits_form(kind: OperationKind, options: &serde_json::Value) -> bool {
+    match kind {
+        OperationKind::Unzip => serde_json::from_value::<UnzipOptions>(options.clone()).is_ok(),
+        OperationKind::Strip => serde_json::from_value::<StripOptions>(options.clone()).is_ok(),
+    }
+}
+
+fn command_name(kind: OperationKind) -> &'static str {
+    match kind {
+        OperationKind::Unzip => "unzip",
+        OperationKind::Strip => "strip",
+    }
+}
+
+/// Presets saved by the app or by the CLI's `--save-preset`, by name
+#[tauri::command]
+pub async fn list_presets() -> Result<BTreeMap<String, Preset>, String> {
+    PresetStore::open().and_then(|store| store.list()).map_err(|e| format!("{:#}", e))
+}
+
+/// Save the options of the `kind` form that differ from its defaults as
+/// preset `name`, for the app and the CLI's `--preset` alike
+#[tauri::command]
+pub async fn save_preset(name: String, kind: OperationKind, options: serde_json::Value) -> Result<(), String> {
+    let preset = Preset { command: command_name(kind).to_string(), options: from_form(&options, &form_defaults(kind)) };
+    PresetStore::open().and_then(|store| store.save(&name, &preset)).map_err(|e| format!("{:#}", e))
+}
+
+/// The `kind` form's `options` with preset `name` applied over them
+#[tauri::command]
+pub async fn apply_preset(name: String, kind: OperationKind, options: serde_json::Value) -> Result<AppliedPreset, String> {
+    let preset = PresetStore::open().and_then(|store| store.get(&name)).map_err(|e| format!("{:#}", e))?;
+    if preset.command != command_name(kind) {
+        return Err(format!("Preset {:?} is for {}, not {}", name, preset.command, command_name(kind)));
+    }
+    Ok(apply_to_form(options, &preset, |options| fits_form(kind, options)))
+}
+
+/// What this build supports, so the GUI can hide controls for the rest
+#[tauri::command]
+pub async fn get_capabilities() -> Result<Capabilities, String> {
+    Ok(Capabilities::detect())
+}
+
+/// Version of the results the commands return, so the GUI can tell when
+/// it was built against another layout
+#[tauri::command]
+pub async fn get_schema_version() -> Result<u32, String> {
+    Ok(SCHEMA_VERSION)
+}
@@ -4,14 +4,48 @@
 mod lib;
 
 use lib::*;
+use tauri::Manager;
 
 fn main() {
     tauri::Builder::default()
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            app.manage(HistoryStore::new(data_dir));
+            Ok(())
+        })
+        .manage(OperationLock::default())
+        .manage(CancelFlag::default())
+        .manage(ActiveCopy::default())
+        .manage(LastScan::default())
+        .manage(OperationMetrics::default())
+        .manage(PasswordRequests::default())
+        .manage(ResultFiles::default())
         .invoke_handler(tauri::generate_handler![
             unzip_files,
+            extract_single_zip,
             strip_metadata,
+            strip_single_file,
             scan_zip_files,
-            scan_mp3_files
+            scan_mp3_files,
+            export_scan,
+            get_directory_stats,
+            list_zip_contents,
+            cancel_operation,
+            pause_operation,
+            resume_operation,
+            provide_password,
+            decline_password,
+            get_metrics,
+            find_duplicate_mp3s,
+            get_history,
+            clear_history,
+            get_last_options,
+            list_presets,
+            save_preset,
+            apply_preset,
+            get_capabilities,
+            get_results_page,
+            get_schema_version
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,119 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+
+use bulk_unzip::copy_hooks::CopyControl;
+use bulk_unzip::passwords::{PasswordPrompt, PROMPT_ATTEMPTS};
+
+/// How often a worker waiting for a password checks for cancellation
+const CANCEL_POLL: Duration = Duration::from_millis(200);
+/// How long a worker waits for an answer before skipping the archive
+pub const DEFAULT_PASSWORD_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Workers waiting on a `password-required` event, by its token, for
+/// `provide_password` and `decline_password` to answer
+#[derive(Default, Clone)]
+pub struct PasswordRequests(Arc<Mutex<HashMap<String, Sender<Option<String>>>>>);
+
+impl PasswordRequests {
+    /// Hand `answer` to the worker waiting on `token`, `None` to skip the
+    /// archive; false when none is, e.g. it timed out
+    pub fn answer(&self, token: &str, answer: Option<String>) -> bool {
+        let waiting = self.0.lock().unwrap_or_else(|e| e.into_inner()).remove(token);
+        waiting.is_some_and(|waiting| waiting.send(answer).is_ok())
+    }
+
+    fn open(&self, token: &str) -> Receiver<Option<String>> {
+        let (sender, receiver) = mpsc::channel();
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).insert(token.to_string(), sender);
+        receiver
+    }
+
+    fn close(&self, token: &str) {
+        self.0.lock().unwrap_or_else(|e| e.into_inner()).remove(token);
+    }
+}
+
+/// Payload of `password-required`
+#[derive(Debug, Clone, Serialize)]
+struct PasswordRequired<'a> {
+    token: &'a str,
+    archive: &'a Path,
+    /// Counts from 1; above 1, the password given before was wrong
+    attempt: usize,
+    attempts: usize,
+    timeout_secs: u64,
+}
+
+/// Payload of `password-request-closed`, sent when a request stops
+/// waiting unanswered, so the app can take its dialog down
+#[derive(Debug, Clone, Serialize)]
+struct PasswordRequestClosed<'a> {
+    token: &'a str,
+    /// `timed out` or `cancelled`
+    reason: &'static str,
+}
+
+/// Asks the app for the passwords of one operation's encrypted archives;
+/// [`Passwords`](bulk_unzip::passwords::Passwords) remembers those that
+/// opened one and asks about one archive at a time
+pub struct PasswordAsker {
+    app: AppHandle,
+    requests: PasswordRequests,
+    /// Starts every token, so an answer meant for an earlier operation
+    /// can't reach this one
+    operation: u64,
+    next: AtomicU64,
+    timeout: Duration,
+    control: Arc<CopyControl>,
+}
+
+/// Never shows a password
+impl fmt::Debug for PasswordAsker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PasswordAsker").field("operation", &self.operation).field("timeout", &self.timeout).finish()
+    }
+}
+
+impl PasswordAsker {
+    pub fn new(app: AppHandle, requests: PasswordRequests, operation: u64, timeout: Duration, control: Arc<CopyControl>) -> Self {
+        PasswordAsker { app, requests, operation, next: AtomicU64::new(0), timeout, control }
+    }
+}
+
+/// Blocks the extracting worker until the app answers, the request times
+/// out or the operation is cancelled
+impl PasswordPrompt for PasswordAsker {
+    fn ask(&self, archive: &Path, attempt: usize) -> Option<String> {
+        if self.control.is_cancelled() {
+            return None;
+        }
+        let token = format!("{}-{}", self.operation, self.next.fetch_add(1, Ordering::Relaxed));
+        let answer = self.requests.open(&token);
+        let request = PasswordRequired { token: &token, archive, attempt, attempts: PROMPT_ATTEMPTS, timeout_secs: self.timeout.as_secs() };
+        let _ = self.app.emit("password-required", request);
+        let deadline = Instant::now() + self.timeout;
+        let reason = loop {
+            match answer.recv_timeout(CANCEL_POLL) {
+                Ok(answer) => return answer,
+                Err(RecvTimeoutError::Disconnected) => return None,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+            if self.control.is_cancelled() {
+                break "cancelled";
+            }
+            if Instant::now() >= deadline {
+                break "timed out";
+            }
+        };
+        self.requests.close(&token);
+        let _ = self.app.emit("password-request-closed", PasswordRequestClosed { token: &token, reason });
+        None
+    }
+}
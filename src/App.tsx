@@ -1,34 +1,376 @@
-import { useState } from 'react'
+import { useEffect, useState } from 'react'
 import { invoke } from '@tauri-apps/api/core'
+import { listen } from '@tauri-apps/api/event'
 import { FolderOpen, FileArchive, Music, Play, Square } from 'lucide-react'
 import './App.css'
 
+// Budget for in-flight buffers in low memory mode
+const LOW_MEMORY_LIMIT = 256 * 1024 * 1024
+// Layout of the results these interfaces describe; see `get_schema_version`
+const SCHEMA_VERSION = 1
+// Results read at a time from a streamed run's results file
+const RESULTS_PAGE_SIZE = 500
+
 interface ZipFile {
   path: string
   size: number
+  matching_entries?: number
+  entries?: number
+  uncompressed_size: number
+  health: 'Ok' | 'Empty' | 'TooSmall' | 'NoCentralDirectory' | 'Rar'
+  output_name: string
+  mtime: number
+  sha256?: string
+  kind: ArchiveKind
+  zip64: boolean
+  // Label of the directory it was found under, when several are scanned
+  root?: string
+}
+
+type ArchiveKind = 'zip' | 'comic' | 'book' | 'java' | 'android'
+
+// Extensions the scanner treats as archives
+const ARCHIVE_EXTENSIONS = ['.zip', '.cbz', '.cbr', '.epub', '.jar', '.war', '.ear', '.apk']
+const isScannedArchive = (path: string) => ARCHIVE_EXTENSIONS.some(ext => path.toLowerCase().endsWith(ext))
+
+interface OperationMetrics {
+  operation_id: number
+  archives_done: number
+  archives_total: number
+  bytes_written: number
+  bytes_total: number
+  files_written: number
+  elapsed_ms: number
+  bytes_per_sec: number
+  files_per_sec: number
+  eta_ms?: number
+}
+
+interface UnzipProgress {
+  kind: 'Progress' | 'ArchiveStarted' | 'ArchiveFinished' | 'ArchiveFailed'
+  current_file: string
+  progress: number
+  total: number
+  message: string
+  metrics: OperationMetrics
+}
+
+interface ScanProgress {
+  directories: number
+  files_matched: number
+  bytes: number
+  bytes_hashed: number
+  sidecars_skipped: number
 }
 
 interface Mp3File {
   path: string
   size: number
   has_metadata: boolean
+  format: AudioContainer
+  tag_version?: string
+  tag_size_bytes: number
+  root?: string
+}
+
+type AudioContainer = 'Mpeg' | 'Mp4' | 'Wav' | 'Aiff' | 'Flac' | 'Ogg' | 'Unknown'
+type AudioFormat = 'mp3' | 'wav' | 'aiff'
+
+const isOtherFormat = (format: AudioContainer) => format !== 'Mpeg' && format !== 'Unknown'
+
+interface FileTypeCounts {
+  extensions: Record<string, number>
+  no_extension: number
+  directories: number
+}
+
+interface ExtractResult {
+  path: string
+  status: 'Extracted' | 'SkippedExisting' | 'SkippedFiltered' | 'SkippedEncrypted' | 'SkippedSuspect' | 'SkippedDuplicate' | 'SkippedSuperseded' | 'SkippedUnstable' | 'SkippedProcessed' | 'Failed'
+  file_types: FileTypeCounts
+  // e.g. clamped timestamps, rewritten, refused or shortened entry paths
+  warnings: { category: string, count: number, message: string }[]
+  truncated_names: { original: string, truncated: string }[]
+  nested_archives: string[]
+  duplicate_of?: string
+  superseded_by?: string
+  error?: string
+}
+
+interface ArchiveDuplicates {
+  sha256: string
+  size: number
+  kept: string
+  skipped: string[]
+}
+
+interface ArchiveGroup {
+  key: string
+  kept: string
+  superseded: string[]
+}
+
+// What became of the archives found under one input directory
+interface RootCounts {
+  root: string
+  label: string
+  found: number
+  processed: number
+  skipped: number
+  failed: number
+}
+
+interface UnzipReport {
+  schema_version: number
+  results: ExtractResult[]
+  file_types: FileTypeCounts
+  fs_warning: string | null
+  duplicates: ArchiveDuplicates[]
+  groups: ArchiveGroup[]
+  sample: SampleSummary | null
+  roots?: RootCounts[]
+}
+
+interface StripResult {
+  path: string
+  status: 'Stripped' | 'Skipped' | 'Failed' | 'DryRun' | 'CopiedUnchanged' | 'WrongFormat' | 'SkippedNoop' | 'SkippedFiltered' | 'SkippedCollision' | 'SkippedOutOfSpace' | 'WriteVerificationFailed'
+  frames_removed: string[]
+  frames_kept: string[]
+  bytes_saved: number
+  diff: TagDiff
+  detected_format?: AudioContainer
+  tag_upgrade?: TagUpgrade
+  collision?: OutputCollision
+  incomplete_copy?: IncompleteCopy
+  source_removed?: 'Delete' | 'Trash'
+  source_kept?: string
+  reflinked?: number
+  art_safety?: ArtSafetyHit
+  error?: string
+}
+
+type ArtSafety = 'ignore' | 'warn' | 'export' | 'skip'
+
+// How files a run removes go: deleted, or moved to the system trash
+type Disposal = 'delete' | 'trash'
+
+// A file whose embedded art was the only artwork in its directory
+interface ArtSafetyHit {
+  directory: string
+  action: ArtSafety
+  exported: string | null
+}
+
+// What strip_metadata returns; a streamed run leaves `results` empty and is
+// read back page by page with get_results_page
+interface StripRun {
+  results: StripResult[]
+  counts: Record<string, number>
+  report_path: string | null
+  operation_id: number | null
+  results_file: string | null
+  recent_errors: { path: string, error: string }[]
+  art_safety_directories: number
+}
+
+interface IncompleteCopy {
+  path: string
+  removed: boolean
+}
+
+interface OutputCollision {
+  wanted: string
+  holder: string
+  written_to?: string
+}
+
+interface TagUpgrade {
+  from: string
+  to: string
+  frames_before: number
+  frames_after: number
+  merged: string[]
+}
+
+interface TagDiff {
+  lines: { op: 'Removed' | 'Kept' | 'Changed', frame: string, value: string, new_value?: string }[]
+}
+
+type SortOrder = 'path' | 'natural'
+
+type DateSource = 'archive' | 'entries'
+
+type OnValidationFailure = 'remove' | 'keep' | 'rename'
+
+type SampleMode = 'first' | 'random'
+
+type GroupOrder = 'mtime' | 'name'
+
+interface SampleSummary {
+  kept: number
+  discovered: number
+  seed: number | null
 }
+type PreserveAttr = 'times' | 'perms' | 'owner' | 'all'
+type IoProfile = 'ssd' | 'hdd' | 'network' | 'auto'
 
 interface UnzipOptions {
   directory: string
+  // More directories to extract from, each into its own folder of the output
+  directories?: string[]
   output: string
   workers: number
+  io_profile?: IoProfile
+  background?: boolean
   skip_existing: boolean
+  containing?: string
+  overwrite?: boolean
+  attempt_suspect?: boolean
+  skip_duplicates?: boolean
+  latest_per_group?: string
+  group_order?: GroupOrder
+  prune_empty?: boolean
+  limit?: number
+  sample?: SampleMode
+  seed?: number
+  label_by_type?: boolean
+  split_output_by_date?: DateSource | null
+  profile?: 'java' | 'android'
+  keep_signatures?: boolean
+  on_busy?: 'wait' | 'rename' | 'fail'
+  on_validation_failure?: OnValidationFailure
+  sort?: SortOrder
+  name_template?: string
+  max_entries?: number
+  force?: boolean
+  memory_limit?: number
+  limit_rate?: number
+  strict_times?: boolean
+  strict_fs?: boolean
+  progress_interval_ms?: number
+  report?: string
+  no_report?: boolean
+  // Ask for the passwords of encrypted archives instead of skipping them
+  ask_passwords?: boolean
+  password_timeout?: number
 }
 
 interface StripOptions {
   directory: string
   output?: string
+  on_collision?: 'rename' | 'skip' | 'overwrite'
   workers: number
+  io_profile?: IoProfile
+  background?: boolean
   skip_clean: boolean
   keep_fields?: string
+  remove_fields?: string
+  normalize_genre?: boolean
+  genre_map?: string
+  upgrade_tags?: boolean
   remove_all: boolean
+  protect?: string
+  formats?: AudioFormat[]
   dry_run: boolean
+  recursive?: boolean
+  max_depth?: number
+  skip_hidden?: boolean
+  include?: string[]
+  exclude?: string[]
+  where_expr?: string
+  force?: boolean
+  sort?: SortOrder
+  limit?: number
+  sample?: SampleMode
+  seed?: number
+  include_hidden?: boolean
+  min_size?: number
+  padding?: number
+  verify_write?: boolean
+  min_free_space?: number
+  preserve?: PreserveAttr[]
+  delete_source?: boolean
+  disposal?: Disposal
+  art_safety?: ArtSafety
+  stream_results?: boolean
+  report?: string
+  no_report?: boolean
+}
+
+// Option defaults, which packagers can change when building
+interface BuildDefaults {
+  workers: number
+  io_profile: IoProfile | null
+  stabilize_timeout_secs: number
+  max_entries: number
+  min_size: number
+  skip_existing: boolean
+  skip_clean: boolean
+  backup: boolean
+}
+
+interface Capabilities {
+  version: string
+  default_workers: number
+  defaults: BuildDefaults
+  sevenz: boolean
+  rar: boolean
+  zstd_entries: boolean
+  watch: boolean
+  notifications: boolean
+  symlinks: boolean
+  trash: boolean
+  reflink: boolean
+}
+
+// Sent as archive-finished and file-finished; index is the row in the final results
+interface ResultFinished<T> {
+  index: number
+  total: number
+  result: T
+}
+
+// A misspelled name in a field list, which strip_metadata rejects before touching any file
+interface InvalidFieldName {
+  kind: 'invalid_field_name'
+  list: 'keep_fields' | 'remove_fields' | 'protect'
+  name: string
+  suggestion: string | null
+  valid: string[]
+}
+
+type StripError = InvalidFieldName | { kind: 'failed', message: string }
+
+function describeFieldError(error: InvalidFieldName): string {
+  const hint = error.suggestion ? ` Did you mean "${error.suggestion}"?` : ''
+  return `Unknown field "${error.name}".${hint} Valid fields: ${error.valid.join(', ')}, or a frame ID in capitals such as TXXX.`
+}
+
+// Options saved under a name, shared with the CLI's --save-preset and --preset
+interface Preset {
+  command: 'unzip' | 'strip'
+  options: Record<string, unknown>
+}
+
+// A form's options with a preset applied; ignored lists preset options the form has no place for
+interface AppliedPreset {
+  options: Record<string, unknown>
+  ignored: string[]
+}
+
+// An encrypted archive waiting for provide_password or decline_password
+interface PasswordRequired {
+  token: string
+  archive: string
+  attempt: number
+  attempts: number
+  timeout_secs: number
+}
+
+interface RunFinished {
+  command: string
+  report_path: string | null
+  failure: string | null
 }
 
 function App() {
@@ -37,13 +379,25 @@ function App() {
   const [mp3Files, setMp3Files] = useState<Mp3File[]>([])
   const [isProcessing, setIsProcessing] = useState(false)
   const [results, setResults] = useState<string[]>([])
+  const [unzipProgress, setUnzipProgress] = useState<UnzipProgress | null>(null)
+  const [nestedArchives, setNestedArchives] = useState<string[]>([])
+  const [reportPath, setReportPath] = useState<string | null>(null)
+  const [kindFilter, setKindFilter] = useState<ArchiveKind | 'all'>('all')
+  const [paused, setPaused] = useState(false)
+  const [capabilities, setCapabilities] = useState<Capabilities | null>(null)
+  const [resultsPage, setResultsPage] = useState<{ operationId: number, offset: number } | null>(null)
+  const [fieldError, setFieldError] = useState<InvalidFieldName | null>(null)
+  const [presets, setPresets] = useState<Record<string, Preset>>({})
+  const [passwordRequest, setPasswordRequest] = useState<PasswordRequired | null>(null)
+  const [password, setPassword] = useState('')
   
   // Unzip options
   const [unzipOptions, setUnzipOptions] = useState<UnzipOptions>({
     directory: '',
     output: 'extracted',
     workers: 4,
-    skip_existing: false
+    skip_existing: false,
+    max_entries: 1000000
   })
   
   // Strip options
@@ -55,13 +409,81 @@ function App() {
     dry_run: false
   })
 
+  // A backend with another result layout would be misread rather than rejected
+  useEffect(() => {
+    invoke<number>('get_schema_version').then(version => {
+      if (version !== SCHEMA_VERSION) {
+        setResults(prev => [`⚠️ The backend returns results in layout v${version}, this window reads v${SCHEMA_VERSION}; some details may be missing`, ...prev])
+      }
+    })
+  }, [])
+
+  // What this build supports; its defaults replace the form's guesses
+  useEffect(() => {
+    invoke<Capabilities>('get_capabilities').then(caps => {
+      setCapabilities(caps)
+      const { defaults } = caps
+      setUnzipOptions(prev => ({
+        ...prev,
+        workers: caps.default_workers,
+        io_profile: defaults.io_profile ?? undefined,
+        skip_existing: defaults.skip_existing,
+        max_entries: defaults.max_entries,
+      }))
+      setStripOptions(prev => ({
+        ...prev,
+        workers: caps.default_workers,
+        io_profile: defaults.io_profile ?? undefined,
+        skip_clean: defaults.skip_clean,
+        min_size: defaults.min_size,
+      }))
+    })
+  }, [])
+
+  // Presets from the config file the CLI uses too
+  useEffect(() => {
+    invoke<Record<string, Preset>>('list_presets').then(setPresets).catch(() => setPresets({}))
+  }, [])
+
+  // Every batch run ends with `run-finished`, carrying where its report went
+  useEffect(() => {
+    const unlisten = listen<RunFinished>('run-finished', event => setReportPath(event.payload.report_path))
+    return () => { unlisten.then(f => f()) }
+  }, [])
+
+  // Extract a single zip dropped onto the window
+  useEffect(() => {
+    if (activeTab !== 'unzip') return
+    const unlisten = listen<{ paths: string[] }>('tauri://drag-drop', async event => {
+      const zips = event.payload.paths.filter(isScannedArchive)
+      if (zips.length !== 1) return
+      try {
+        const result = await invoke<ExtractResult>('extract_single_zip', {
+          path: zips[0],
+          options: {
+            output: unzipOptions.output,
+            skip_existing: unzipOptions.skip_existing,
+            containing: unzipOptions.containing,
+            overwrite: unzipOptions.overwrite,
+          },
+        })
+        setResults([result.status === 'Failed'
+          ? `❌ Error extracting ${result.path}: ${result.error}`
+          : `${result.status}: ${result.path}`])
+      } catch (error) {
+        setResults([`Error: ${error}`])
+      }
+    })
+    return () => { unlisten.then(f => f()) }
+  }, [activeTab, unzipOptions])
+
   const selectDirectory = () => {
     const selected = prompt('Enter directory path:')
     
     if (selected) {
       if (activeTab === 'unzip') {
-        setUnzipOptions(prev => ({ ...prev, directory: selected }))
-        scanZipFiles(selected)
+        setUnzipOptions(prev => ({ ...prev, directory: selected, directories: [] }))
+        scanZipFiles(selected, [])
       } else {
         setStripOptions(prev => ({ ...prev, directory: selected }))
         scanMp3Files(selected)
@@ -69,6 +491,55 @@ function App() {
     }
   }
 
+  const applyPreset = async (kind: 'unzip' | 'strip', name: string) => {
+    try {
+      const options = kind === 'unzip' ? unzipOptions : stripOptions
+      const applied = await invoke<AppliedPreset>('apply_preset', { name, kind, options })
+      if (kind === 'unzip') {
+        setUnzipOptions(applied.options as unknown as UnzipOptions)
+      } else {
+        setStripOptions(applied.options as unknown as StripOptions)
+      }
+      setResults(applied.ignored.length > 0
+        ? [`🎛️ Applied preset "${name}"; not available in the app: ${applied.ignored.join(', ')}`]
+        : [`🎛️ Applied preset "${name}"`])
+    } catch (error) {
+      setResults([`Error: ${error}`])
+    }
+  }
+
+  const savePreset = async (kind: 'unzip' | 'strip') => {
+    const name = prompt('Save these options as preset:')
+    if (!name) return
+    try {
+      const options = kind === 'unzip' ? unzipOptions : stripOptions
+      await invoke('save_preset', { name, kind, options })
+      setPresets(await invoke<Record<string, Preset>>('list_presets'))
+      setResults([`💾 Saved preset "${name}"`])
+    } catch (error) {
+      setResults([`Error: ${error}`])
+    }
+  }
+
+  const presetControls = (kind: 'unzip' | 'strip') => (
+    <div className="option-group">
+      <label>Preset:</label>
+      <select
+        value=""
+        onChange={(e) => e.target.value && applyPreset(kind, e.target.value)}
+        disabled={isProcessing}
+      >
+        <option value="">Apply a saved preset...</option>
+        {Object.entries(presets)
+          .filter(([, preset]) => preset.command === kind)
+          .map(([name]) => <option key={name} value={name}>{name}</option>)}
+      </select>
+      <button className="select-button" onClick={() => savePreset(kind)} disabled={isProcessing}>
+        Save as preset...
+      </button>
+    </div>
+  )
+
   const selectOutputDirectory = () => {
     const selected = prompt('Enter output directory path:')
     
@@ -81,22 +552,96 @@ function App() {
     }
   }
 
-  const scanZipFiles = async (directory: string) => {
+  const listenScanProgress = () =>
+    listen<ScanProgress>('scan-progress', event => {
+      const { directories, files_matched, bytes, bytes_hashed, sidecars_skipped } = event.payload
+      const skipped = sidecars_skipped > 0 ? `, ${sidecars_skipped} AppleDouble/empty skipped` : ''
+      setResults([bytes_hashed > 0
+        ? `Hashing: ${formatFileSize(bytes_hashed)} of ${formatFileSize(bytes)}`
+        : `Scanning: ${directories} directories, ${files_matched} files (${formatFileSize(bytes)})${skipped}`])
+    })
+
+  const addUnzipDirectory = () => {
+    const added = prompt('Enter another directory to extract from:')
+    if (added) {
+      const directories = [...(unzipOptions.directories ?? []), added]
+      setUnzipOptions(prev => ({ ...prev, directories }))
+      scanZipFiles(unzipOptions.directory, directories)
+    }
+  }
+
+  const scanZipFiles = async (directory: string, directories = unzipOptions.directories) => {
+    const unlisten = await listenScanProgress()
     try {
-      const files = await invoke<ZipFile[]>('scan_zip_files', { directory })
+      const files = await invoke<ZipFile[]>('scan_zip_files', { directory, directories, output: unzipOptions.output, sort: unzipOptions.sort })
       setZipFiles(files)
     } catch (error) {
       console.error('Error scanning zip files:', error)
+    } finally {
+      unlisten()
+      setResults([])
     }
   }
 
   const scanMp3Files = async (directory: string) => {
+    const unlisten = await listenScanProgress()
     try {
-      const files = await invoke<Mp3File[]>('scan_mp3_files', { directory })
+      const files = await invoke<Mp3File[]>('scan_mp3_files', { directory, sort: stripOptions.sort, includeHidden: stripOptions.include_hidden })
       setMp3Files(files)
     } catch (error) {
       console.error('Error scanning MP3 files:', error)
+    } finally {
+      unlisten()
+      setResults([])
+    }
+  }
+
+  const describeArchive = (r: ExtractResult) => {
+    if (r.status === 'Failed') {
+      return `❌ Error extracting ${r.path}: ${r.error}`
     }
+    if (r.status === 'SkippedDuplicate') {
+      return `SkippedDuplicate: ${r.path} (identical to ${r.duplicate_of})`
+    }
+    if (r.status === 'SkippedSuperseded') {
+      return `SkippedSuperseded: ${r.path} (superseded by ${r.superseded_by})`
+    }
+    const notes = r.warnings.map(w => w.message)
+    return notes.length > 0 ? `${r.status}: ${r.path} (${notes.join('; ')})` : `${r.status}: ${r.path}`
+  }
+
+  const describeStrip = (r: StripResult) => {
+    const name = r.path.split(/[/\\]/).pop()
+    if (r.status === 'Failed') {
+      const copy = r.incomplete_copy
+      return [`❌ ${name}: ${r.error}`, ...(copy && !copy.removed ? [`  ⚠️ ${copy.path} may be damaged; delete it`] : [])]
+    }
+    if (r.status === 'SkippedOutOfSpace') return [`💥 ${name}: not started, the output volume is full`]
+    if (r.status === 'WriteVerificationFailed') return [`⚠️ ${name}: write verification failed, ${r.error}`]
+    if (r.status === 'WrongFormat') return [`⚠️ ${name}: ${r.detected_format} content, not MP3; left untouched`]
+    if (r.status === 'SkippedCollision') return [`⚠️ ${name}: left out, ${r.collision?.holder} has its output name`]
+    const upgrade = r.tag_upgrade
+      ? [`  ⬆️ ${r.tag_upgrade.from} → ${r.tag_upgrade.to}, ${r.tag_upgrade.frames_before} → ${r.tag_upgrade.frames_after} frames`
+        + (r.tag_upgrade.merged.length > 0 ? ` (${r.tag_upgrade.merged.join(', ')} merged into TDRC)` : '')]
+      : []
+    const renamed = r.collision?.written_to ? ` → ${r.collision.written_to.split(/[/\\]/).pop()}` : ''
+    if (r.status === 'DryRun') {
+      const diff = r.diff.lines.map(line => line.op === 'Changed'
+        ? `  ~ ${line.frame} ${line.value} → ${line.new_value}`
+        : `  ${line.op === 'Removed' ? '-' : '='} ${line.frame} ${line.value}`)
+      return [`${r.status}: ${name}${renamed}`, ...diff, ...upgrade]
+    }
+    const removed = r.frames_removed.length > 0 ? ` (removed ${r.frames_removed.join(', ')})` : ''
+    const size = r.bytes_saved < 0 ? `, grew ${formatFileSize(-r.bytes_saved)}` : ''
+    const source = r.source_removed === 'Trash' ? ['  🗑️ original moved to the trash']
+      : r.source_removed === 'Delete' ? ['  🗑️ original deleted']
+      : r.source_kept ? [`  ⚠️ original kept: ${r.source_kept}`]
+      : []
+    const art = r.art_safety?.exported ? [`  🖼️ cover art saved to ${r.art_safety.exported}`]
+      : r.art_safety?.action === 'skip' ? ['  🖼️ art kept, the only artwork in its directory']
+      : r.art_safety?.action === 'warn' ? ['  ⚠️ removed the only artwork in its directory']
+      : []
+    return [`${r.status}: ${name}${renamed}${removed}${size}`, ...upgrade, ...source, ...art]
   }
 
   const handleUnzip = async () => {
@@ -104,12 +649,111 @@ function App() {
     
     setIsProcessing(true)
     setResults([])
+    setResultsPage(null)
+    setReportPath(null)
+    setNestedArchives([])
+    const unlisten = await listen<UnzipProgress>('unzip-progress', event => setUnzipProgress(event.payload))
+    // Rows fill in at their final position as each archive finishes
+    const rows: string[] = []
+    const unlistenFinished = await listen<ResultFinished<ExtractResult>>('archive-finished', event => {
+      rows[event.payload.index] = describeArchive(event.payload.result)
+      setResults(rows.filter(row => row !== undefined))
+    })
+    const unlistenPassword = await listen<PasswordRequired>('password-required', event => {
+      setPassword('')
+      setPasswordRequest(event.payload)
+    })
+    const unlistenClosed = await listen<{ token: string }>('password-request-closed', event =>
+      setPasswordRequest(request => request?.token === event.payload.token ? null : request))
     
     try {
-      const results = await invoke<string[]>('unzip_files', { options: unzipOptions })
-      setResults(results)
+      const report = await invoke<UnzipReport>('unzip_files', { options: unzipOptions })
+      if (report.results.length === 0) {
+        setResults(['No zip files found'])
+        return
+      }
+      const types = Object.entries(report.file_types.extensions)
+        .sort((a, b) => b[1] - a[1])
+        .map(([ext, count]) => `${ext}: ${count}`)
+      const nested = report.results.filter(r => r.nested_archives.length > 0)
+      setNestedArchives(nested.flatMap(r => r.nested_archives))
+      setResults([
+        ...(report.fs_warning ? [`⚠️ ${report.fs_warning}`] : []),
+        ...(report.sample
+          ? [`🎯 Processing ${report.sample.kept.toLocaleString()} of ${report.sample.discovered.toLocaleString()} discovered`
+            + (report.sample.seed !== null ? ` (random, seed ${report.sample.seed})` : '')]
+          : []),
+        ...(report.duplicates.length > 0
+          ? [`${report.duplicates.reduce((n, g) => n + g.skipped.length, 0)} duplicate archives in ${report.duplicates.length} groups skipped`]
+          : []),
+        ...(report.groups.length > 0
+          ? [`${report.groups.length} groups, ${report.groups.reduce((n, g) => n + g.superseded.length, 0)} superseded archives skipped`,
+            ...report.groups.map(g => `  ${g.key}: ${g.kept}`)]
+          : []),
+        ...report.results.map(describeArchive),
+        ...(report.roots ?? []).map(r =>
+          `📂 ${r.label} (${r.root}): ${r.found} found, ${r.processed} processed, ${r.skipped} skipped, ${r.failed} failed`),
+        ...(types.length > 0 ? [`File types: ${types.join(', ')}`] : []),
+        ...(nested.length > 0 ? [`${nested.length} extracted directories contain further archives`] : []),
+      ])
     } catch (error) {
       setResults([`Error: ${error}`])
+    } finally {
+      unlisten()
+      unlistenFinished()
+      unlistenPassword()
+      unlistenClosed()
+      setPasswordRequest(null)
+      setUnzipProgress(null)
+      setPaused(false)
+      setIsProcessing(false)
+    }
+  }
+
+  // An empty password skips the archive, like declining it
+  const answerPassword = async (given: string | null) => {
+    if (!passwordRequest) return
+    const token = passwordRequest.token
+    setPasswordRequest(null)
+    setPassword('')
+    try {
+      await (given ? invoke('provide_password', { token, password: given }) : invoke('decline_password', { token }))
+    } catch (error) {
+      setResults(prev => [...prev, `Error: ${error}`])
+    }
+  }
+
+  // Pausing and cancelling take effect inside the entry being extracted
+  const togglePause = async () => {
+    await invoke(paused ? 'resume_operation' : 'pause_operation')
+    setPaused(!paused)
+  }
+
+  // Extract archives found inside the last run's output, each next to itself
+  const handleExtractNested = async () => {
+    const zips = nestedArchives.filter(p => p.toLowerCase().endsWith('.zip'))
+    setIsProcessing(true)
+    const lines: string[] = []
+    try {
+      for (const path of zips) {
+        try {
+          const result = await invoke<ExtractResult>('extract_single_zip', {
+            path,
+            options: {
+              output: path.slice(0, Math.max(path.lastIndexOf('/'), path.lastIndexOf('\\'))),
+              skip_existing: unzipOptions.skip_existing,
+              overwrite: unzipOptions.overwrite,
+            },
+          })
+          lines.push(result.status === 'Failed'
+            ? `❌ Error extracting ${result.path}: ${result.error}`
+            : `${result.status}: ${result.path}`)
+        } catch (error) {
+          lines.push(`❌ Error extracting ${path}: ${error}`)
+        }
+      }
+      setResults(lines)
+      setNestedArchives([])
     } finally {
       setIsProcessing(false)
     }
@@ -120,17 +764,77 @@ function App() {
     
     setIsProcessing(true)
     setResults([])
+    setResultsPage(null)
+    setReportPath(null)
+    setFieldError(null)
+    const streamed = stripOptions.stream_results ?? false
+    const rows: string[][] = []
+    const unlistenFinished = await listen<ResultFinished<StripResult>>('file-finished', event => {
+      if (streamed) {
+        setResults([`⏳ ${event.payload.index + 1} of ${event.payload.total} files done`])
+        return
+      }
+      rows[event.payload.index] = describeStrip(event.payload.result)
+      setResults(rows.flat())
+    })
     
     try {
-      const results = await invoke<string[]>('strip_metadata', { options: stripOptions })
-      setResults(results)
+      const run = await invoke<StripRun>('strip_metadata', { options: stripOptions })
+      if (run.operation_id !== null) {
+        setResults([
+          ...Object.entries(run.counts).map(([status, count]) => `📊 ${status}: ${count}`),
+          ...run.recent_errors.map(e => `❌ ${e.path}: ${e.error}`),
+          ...(run.art_safety_directories > 0 ? [`🖼️ Directories whose only artwork was embedded: ${run.art_safety_directories}`] : []),
+          `📝 Every result is in ${run.results_file}`,
+        ])
+        setResultsPage({ operationId: run.operation_id, offset: 0 })
+        return
+      }
+      const results = run.results
+      const grown = results.filter(r => r.bytes_saved < 0)
+      const growth = grown.length > 0
+        ? [`📏 ${grown.length} files grew by ${formatFileSize(-grown.reduce((sum, r) => sum + r.bytes_saved, 0))}`]
+        : []
+      const art = run.art_safety_directories > 0
+        ? [`🖼️ Directories whose only artwork was embedded: ${run.art_safety_directories}`]
+        : []
+      const reflinked = results.filter(r => r.reflinked != null)
+      const shared = reflinked.length > 0
+        ? [`♻️ Reflinked copies: ${reflinked.length} (about ${formatFileSize(reflinked.reduce((sum, r) => sum + (r.reflinked ?? 0), 0))} of I/O saved)`]
+        : []
+      setResults([...results.flatMap(describeStrip), ...growth, ...shared, ...art])
     } catch (error) {
-      setResults([`Error: ${error}`])
+      // Tauri itself still rejects bad arguments with a plain string
+      const failure = error as StripError | string
+      if (typeof failure === 'string') {
+        setResults([`Error: ${failure}`])
+      } else if (failure.kind === 'invalid_field_name') {
+        setFieldError(failure)
+      } else {
+        setResults([`Error: ${failure.message}`])
+      }
     } finally {
+      unlistenFinished()
       setIsProcessing(false)
     }
   }
 
+  const loadResultsPage = async () => {
+    if (!resultsPage) return
+    try {
+      const page = await invoke<StripResult[]>('get_results_page', {
+        operationId: resultsPage.operationId,
+        offset: resultsPage.offset,
+        limit: RESULTS_PAGE_SIZE,
+      })
+      setResults(prev => [...prev, ...page.flatMap(describeStrip)])
+      setResultsPage(page.length < RESULTS_PAGE_SIZE ? null : { ...resultsPage, offset: resultsPage.offset + page.length })
+    } catch (error) {
+      setResults(prev => [...prev, `Error: ${error}`])
+      setResultsPage(null)
+    }
+  }
+
   const formatFileSize = (bytes: number) => {
     const sizes = ['B', 'KB', 'MB', 'GB']
     if (bytes === 0) return '0 B'
@@ -138,11 +842,25 @@ function App() {
     return `${(bytes / Math.pow(1024, i)).toFixed(2)} ${sizes[i]}`
   }
 
+  const formatDuration = (ms: number) => {
+    const seconds = Math.round(ms / 1000)
+    return `${Math.floor(seconds / 60)}:${String(seconds % 60).padStart(2, '0')}`
+  }
+
+  const formatMetrics = (m: OperationMetrics) => [
+    `${m.archives_done}/${m.archives_total} archives`,
+    `${formatFileSize(m.bytes_written)} written`,
+    `${formatFileSize(Math.round(m.bytes_per_sec))}/s`,
+    `${m.files_per_sec.toFixed(1)} files/s`,
+    `elapsed ${formatDuration(m.elapsed_ms)}`,
+    ...(m.eta_ms !== undefined && m.eta_ms !== null ? [`ETA ${formatDuration(m.eta_ms)}`] : []),
+  ].join(' · ')
+
   return (
     <div className="app">
       <header className="app-header">
         <h1>Bulk Unzip</h1>
-        <p>Extract zip files and strip MP3 metadata with ease</p>
+        <p>Extract zip files and strip MP3 metadata with ease{capabilities && ` · v${capabilities.version}`}</p>
       </header>
 
       <div className="tabs">
@@ -183,6 +901,18 @@ function App() {
                   <label>Input Directory:</label>
                   <span className="path">{unzipOptions.directory}</span>
                 </div>
+
+                <div className="option-group">
+                  <label>Also Extract From:</label>
+                  {(unzipOptions.directories ?? []).map(directory => (
+                    <span key={directory} className="path">{directory}</span>
+                  ))}
+                  <button onClick={addUnzipDirectory} disabled={isProcessing}>
+                    Add Directory
+                  </button>
+                </div>
+
+                {presetControls('unzip')}
                 
                 <div className="option-group">
                   <label>Output Directory:</label>
@@ -207,10 +937,34 @@ function App() {
                     max="16"
                     value={unzipOptions.workers}
                     onChange={(e) => setUnzipOptions(prev => ({ ...prev, workers: parseInt(e.target.value) || 1 }))}
-                    disabled={isProcessing}
+                    disabled={isProcessing || !!unzipOptions.io_profile}
                   />
                 </div>
 
+                <div className="option-group">
+                  <label>I/O profile:</label>
+                  <select
+                    value={unzipOptions.io_profile || ''}
+                    onChange={(e) => setUnzipOptions(prev => ({ ...prev, io_profile: (e.target.value || undefined) as IoProfile | undefined }))}
+                    disabled={isProcessing}
+                  >
+                    <option value="">None (use workers)</option>
+                    <option value="ssd">SSD</option>
+                    <option value="hdd">HDD</option>
+                    <option value="network">Network</option>
+                    <option value="auto">Auto (sample the disk)</option>
+                  </select>
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.background ?? false}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, background: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Run in the background (lower priority, half the workers)
+                  </label>
+                </div>
+
                 <div className="option-group">
                   <label>
                     <input
@@ -222,19 +976,232 @@ function App() {
                     Skip existing directories
                   </label>
                 </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.ask_passwords ?? true}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, ask_passwords: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Ask for the password of encrypted archives
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>Package profile:</label>
+                  <select
+                    value={unzipOptions.profile || ''}
+                    onChange={(e) => setUnzipOptions(prev => ({ ...prev, profile: (e.target.value || undefined) as 'java' | 'android' | undefined }))}
+                    disabled={isProcessing}
+                  >
+                    <option value="">None (every archive)</option>
+                    <option value="java">Java (.jar, .war, .ear)</option>
+                    <option value="android">Android (.apk)</option>
+                  </select>
+                  {unzipOptions.profile && (
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={unzipOptions.keep_signatures ?? false}
+                        onChange={(e) => setUnzipOptions(prev => ({ ...prev, keep_signatures: e.target.checked }))}
+                        disabled={isProcessing}
+                      />
+                      Keep META-INF signature files
+                    </label>
+                  )}
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.label_by_type ?? false}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, label_by_type: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Sort output into comics/, books/, java/, android/ and archives/
+                  </label>
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.skip_duplicates ?? false}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, skip_duplicates: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Extract only the first of identical archives
+                  </label>
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.prune_empty ?? false}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, prune_empty: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Remove empty directories after extracting
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>Split output by date:</label>
+                  <select
+                    value={unzipOptions.split_output_by_date || ''}
+                    onChange={(e) => setUnzipOptions(prev => ({ ...prev, split_output_by_date: (e.target.value || null) as DateSource | null }))}
+                    disabled={isProcessing}
+                  >
+                    <option value="">Off</option>
+                    <option value="archive">YYYY/MM/ by archive date</option>
+                    <option value="entries">YYYY/MM/ by newest entry</option>
+                  </select>
+                </div>
+
+                <div className="option-group">
+                  <label>When an archive fails its CRC or entry count check:</label>
+                  <select
+                    value={unzipOptions.on_validation_failure || 'rename'}
+                    onChange={(e) => setUnzipOptions(prev => ({ ...prev, on_validation_failure: e.target.value as OnValidationFailure }))}
+                    disabled={isProcessing}
+                  >
+                    <option value="rename">Move its output aside (.failed-…)</option>
+                    <option value="remove">Remove its output</option>
+                    <option value="keep">Keep its output</option>
+                  </select>
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.sort === 'natural'}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, sort: e.target.checked ? 'natural' : 'path' }))}
+                      disabled={isProcessing}
+                    />
+                    Natural order (disc2 before disc10)
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>Latest per group:</label>
+                  <input
+                    type="text"
+                    value={unzipOptions.latest_per_group ?? ''}
+                    onChange={(e) => setUnzipOptions(prev => ({ ...prev, latest_per_group: e.target.value || undefined }))}
+                    placeholder="Regex with a group key, e.g. ^(report)_"
+                    disabled={isProcessing}
+                  />
+                  {unzipOptions.latest_per_group !== undefined && (
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={unzipOptions.group_order === 'name'}
+                        onChange={(e) => setUnzipOptions(prev => ({ ...prev, group_order: e.target.checked ? 'name' : 'mtime' }))}
+                        disabled={isProcessing}
+                      />
+                      Latest by name instead of modification time
+                    </label>
+                  )}
+                </div>
+
+                <div className="option-group">
+                  <label>Limit:</label>
+                  <input
+                    type="number"
+                    min="1"
+                    value={unzipOptions.limit ?? ''}
+                    onChange={(e) => setUnzipOptions(prev => ({ ...prev, limit: parseInt(e.target.value) || undefined }))}
+                    placeholder="All archives"
+                    disabled={isProcessing}
+                  />
+                  {unzipOptions.limit !== undefined && (
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={unzipOptions.sample === 'random'}
+                        onChange={(e) => setUnzipOptions(prev => ({ ...prev, sample: e.target.checked ? 'random' : 'first' }))}
+                        disabled={isProcessing}
+                      />
+                      Random sample instead of the first
+                    </label>
+                  )}
+                  {unzipOptions.sample === 'random' && unzipOptions.limit !== undefined && (
+                    <input
+                      type="number"
+                      min="0"
+                      value={unzipOptions.seed ?? ''}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, seed: e.target.value === '' ? undefined : parseInt(e.target.value) }))}
+                      placeholder="Seed (optional)"
+                      disabled={isProcessing}
+                    />
+                  )}
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.strict_fs ?? false}
+                      onChange={(e) => setUnzipOptions(prev => ({ ...prev, strict_fs: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Refuse FAT/exFAT outputs that can't hold every entry
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={unzipOptions.memory_limit !== undefined}
+                      onChange={(e) => setUnzipOptions(prev => ({
+                        ...prev,
+                        memory_limit: e.target.checked ? LOW_MEMORY_LIMIT : undefined
+                      }))}
+                      disabled={isProcessing}
+                    />
+                    Low memory mode
+                  </label>
+                </div>
               </div>
             )}
 
             {zipFiles.length > 0 && (
               <div className="file-list">
-                <h3>Found {zipFiles.length} ZIP files:</h3>
+                <h3>Found {zipFiles.length} archives:</h3>
+                <select value={kindFilter} onChange={(e) => setKindFilter(e.target.value as ArchiveKind | 'all')}>
+                  <option value="all">All types</option>
+                  <option value="zip">Zips</option>
+                  <option value="comic">Comics</option>
+                  <option value="book">Books</option>
+                  <option value="java">Java packages</option>
+                  <option value="android">Android packages</option>
+                </select>
                 <div className="files">
-                  {zipFiles.map((file, index) => (
-                    <div key={index} className="file-item">
+                  {zipFiles.filter(file => kindFilter === 'all' || file.kind === kindFilter).map((file, index) => {
+                    const overLimit = !unzipOptions.force
+                      && file.entries !== undefined
+                      && unzipOptions.max_entries !== undefined
+                      && file.entries > unzipOptions.max_entries
+                    return (
+                    <div
+                      key={index}
+                      className={`file-item ${file.health !== 'Ok' || overLimit ? 'suspect' : ''}`}
+                      title={file.health === 'Rar'
+                        ? 'Rar archive: rar unsupported'
+                        : file.health !== 'Ok'
+                        ? `Suspect archive: ${file.health}`
+                        : overLimit ? `${file.entries} entries exceeds the limit of ${unzipOptions.max_entries}` : undefined}
+                    >
                       <span className="file-name">{file.path.split(/[/\\]/).pop()}</span>
+                      {file.zip64 && (
+                        <span className="zip64-badge" title="Zip64 archive: may hold files of 4 GiB or more, or more entries than a FAT32 directory allows">
+                          zip64
+                        </span>
+                      )}
                       <span className="file-size">{formatFileSize(file.size)}</span>
                     </div>
-                  ))}
+                    )
+                  })}
                 </div>
               </div>
             )}
@@ -246,7 +1213,51 @@ function App() {
                 disabled={isProcessing}
               >
                 {isProcessing ? <Square size={16} /> : <Play size={16} />}
-                {isProcessing ? 'Processing...' : 'Extract Files'}
+                {isProcessing
+                  ? unzipProgress && unzipProgress.total > 0
+                    ? `${formatFileSize(unzipProgress.progress)} / ${formatFileSize(unzipProgress.total)}`
+                    : 'Processing...'
+                  : 'Extract Files'}
+              </button>
+            )}
+
+            {isProcessing && unzipProgress && (
+              <div className="metrics">
+                {formatMetrics(unzipProgress.metrics)}
+                <button className="select-button" onClick={togglePause}>
+                  {paused ? 'Resume' : 'Pause'}
+                </button>
+                <button className="select-button" onClick={() => invoke('cancel_operation')}>
+                  Cancel
+                </button>
+              </div>
+            )}
+
+            {passwordRequest && (
+              <form className="option-group" onSubmit={(e) => { e.preventDefault(); answerPassword(password) }}>
+                <label>
+                  {passwordRequest.attempt > 1 && '❌ Wrong password. '}
+                  🔑 Password for {passwordRequest.archive.split(/[/\\]/).pop()} ({passwordRequest.attempt}/{passwordRequest.attempts}):
+                </label>
+                <input
+                  type="password"
+                  value={password}
+                  onChange={(e) => setPassword(e.target.value)}
+                  autoFocus
+                />
+                <button type="submit" className="select-button">Open</button>
+                <button type="button" className="select-button" onClick={() => answerPassword(null)}>Skip</button>
+              </form>
+            )}
+
+            {nestedArchives.some(p => p.toLowerCase().endsWith('.zip')) && (
+              <button
+                className="process-button"
+                onClick={handleExtractNested}
+                disabled={isProcessing}
+              >
+                <FileArchive size={16} />
+                Extract Nested ({nestedArchives.filter(p => p.toLowerCase().endsWith('.zip')).length})
               </button>
             )}
           </div>
@@ -270,6 +1281,8 @@ function App() {
                   <label>Input Directory:</label>
                   <span className="path">{stripOptions.directory}</span>
                 </div>
+
+                {presetControls('strip')}
                 
                 <div className="option-group">
                   <label>Output Directory (optional):</label>
@@ -295,10 +1308,34 @@ function App() {
                     max="16"
                     value={stripOptions.workers}
                     onChange={(e) => setStripOptions(prev => ({ ...prev, workers: parseInt(e.target.value) || 1 }))}
-                    disabled={isProcessing}
+                    disabled={isProcessing || !!stripOptions.io_profile}
                   />
                 </div>
 
+                <div className="option-group">
+                  <label>I/O profile:</label>
+                  <select
+                    value={stripOptions.io_profile || ''}
+                    onChange={(e) => setStripOptions(prev => ({ ...prev, io_profile: (e.target.value || undefined) as IoProfile | undefined }))}
+                    disabled={isProcessing}
+                  >
+                    <option value="">None (use workers)</option>
+                    <option value="ssd">SSD</option>
+                    <option value="hdd">HDD</option>
+                    <option value="network">Network</option>
+                    <option value="auto">Auto (sample the disk)</option>
+                  </select>
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={stripOptions.background ?? false}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, background: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Run in the background (lower priority, half the workers)
+                  </label>
+                </div>
+
                 <div className="option-group">
                   <label>
                     <input
@@ -307,7 +1344,7 @@ function App() {
                       onChange={(e) => setStripOptions(prev => ({ ...prev, skip_clean: e.target.checked }))}
                       disabled={isProcessing}
                     />
-                    Skip files without metadata
+                    Skip files with nothing to change
                   </label>
                 </div>
 
@@ -316,13 +1353,30 @@ function App() {
                     <input
                       type="checkbox"
                       checked={stripOptions.remove_all}
-                      onChange={(e) => setStripOptions(prev => ({ ...prev, remove_all: e.target.checked }))}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, remove_all: e.target.checked, protect: e.target.checked ? prev.protect : undefined }))}
                       disabled={isProcessing}
                     />
                     Remove all metadata
                   </label>
                 </div>
 
+                <div className="option-group">
+                  {(['wav', 'aiff'] as AudioFormat[]).map(format => (
+                    <label key={format}>
+                      <input
+                        type="checkbox"
+                        checked={(stripOptions.formats || ['mp3']).includes(format)}
+                        onChange={(e) => setStripOptions(prev => {
+                          const formats = (prev.formats || ['mp3']).filter(f => f !== format)
+                          return { ...prev, formats: e.target.checked ? [...formats, format] : formats }
+                        })}
+                        disabled={isProcessing}
+                      />
+                      Also strip {format.toUpperCase()} files
+                    </label>
+                  ))}
+                </div>
+
                 <div className="option-group">
                   <label>
                     <input
@@ -335,16 +1389,270 @@ function App() {
                   </label>
                 </div>
 
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={stripOptions.force || false}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, force: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Modify files that aren't really MP3s
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={stripOptions.sort === 'natural'}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, sort: e.target.checked ? 'natural' : 'path' }))}
+                      disabled={isProcessing}
+                    />
+                    Natural order (track2 before track10)
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>Limit:</label>
+                  <input
+                    type="number"
+                    min="1"
+                    value={stripOptions.limit ?? ''}
+                    onChange={(e) => setStripOptions(prev => ({ ...prev, limit: parseInt(e.target.value) || undefined }))}
+                    placeholder="All MP3s"
+                    disabled={isProcessing}
+                  />
+                  {stripOptions.limit !== undefined && (
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={stripOptions.sample === 'random'}
+                        onChange={(e) => setStripOptions(prev => ({ ...prev, sample: e.target.checked ? 'random' : 'first' }))}
+                        disabled={isProcessing}
+                      />
+                      Random sample instead of the first
+                    </label>
+                  )}
+                  {stripOptions.sample === 'random' && stripOptions.limit !== undefined && (
+                    <input
+                      type="number"
+                      min="0"
+                      value={stripOptions.seed ?? ''}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, seed: e.target.value === '' ? undefined : parseInt(e.target.value) }))}
+                      placeholder="Seed (optional)"
+                      disabled={isProcessing}
+                    />
+                  )}
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={stripOptions.include_hidden || false}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, include_hidden: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Include AppleDouble (._*) and empty files
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={stripOptions.verify_write ?? true}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, verify_write: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Read each rewritten MP3 back and restore it if the write went wrong
+                  </label>
+                </div>
+
+                <div className="option-group">
+                  <label>Tag padding:</label>
+                  <select
+                    value={stripOptions.padding ?? 0}
+                    onChange={(e) => setStripOptions(prev => ({ ...prev, padding: parseInt(e.target.value) }))}
+                    disabled={isProcessing}
+                  >
+                    <option value={0}>None</option>
+                    <option value={256}>Minimal (256 B)</option>
+                    <option value={1024}>Default (1 KB)</option>
+                  </select>
+                </div>
+
+                <div className="option-group">
+                  <label>Embedded art with no cover image beside it:</label>
+                  <select
+                    value={stripOptions.art_safety ?? 'ignore'}
+                    onChange={(e) => setStripOptions(prev => ({ ...prev, art_safety: e.target.value as ArtSafety }))}
+                    disabled={isProcessing}
+                  >
+                    <option value="ignore">Remove it</option>
+                    <option value="warn">Remove it and warn</option>
+                    <option value="export">Save it as cover.jpg first</option>
+                    <option value="skip">Keep it</option>
+                  </select>
+                </div>
+
+                {stripOptions.output && (
+                  <div className="option-group">
+                    <label>Same file names:</label>
+                    <select
+                      value={stripOptions.on_collision ?? 'rename'}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, on_collision: e.target.value as StripOptions['on_collision'] }))}
+                      disabled={isProcessing}
+                    >
+                      <option value="rename">Rename later copies</option>
+                      <option value="skip">Keep the first</option>
+                      <option value="overwrite">Keep the last</option>
+                    </select>
+                  </div>
+                )}
+
+                {stripOptions.output && (
+                  <div className="option-group">
+                    <label>Keep free on the output drive (MB):</label>
+                    <input
+                      type="number"
+                      min="0"
+                      value={stripOptions.min_free_space !== undefined ? stripOptions.min_free_space / (1024 * 1024) : ''}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, min_free_space: e.target.value === '' ? undefined : parseInt(e.target.value) * 1024 * 1024 }))}
+                      placeholder="Don't check"
+                      disabled={isProcessing}
+                    />
+                  </div>
+                )}
+
+                {stripOptions.output && (
+                  <div className="option-group">
+                    <label>Keep from the originals:</label>
+                    {(['times', 'perms', 'owner'] as PreserveAttr[]).map(attr => (
+                      <label key={attr}>
+                        <input
+                          type="checkbox"
+                          checked={stripOptions.preserve?.includes(attr) ?? false}
+                          onChange={(e) => setStripOptions(prev => ({
+                            ...prev,
+                            preserve: e.target.checked
+                              ? [...(prev.preserve ?? []), attr]
+                              : (prev.preserve ?? []).filter(a => a !== attr),
+                          }))}
+                          disabled={isProcessing}
+                        />
+                        {attr === 'times' ? 'Modification times' : attr === 'perms' ? 'Permissions' : 'Owner (needs root)'}
+                      </label>
+                    ))}
+                  </div>
+                )}
+
+                {stripOptions.output && (
+                  <div className="option-group">
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={stripOptions.delete_source ?? false}
+                        onChange={(e) => setStripOptions(prev => ({ ...prev, delete_source: e.target.checked }))}
+                        disabled={isProcessing}
+                      />
+                      Remove each original once its copy is checked
+                    </label>
+                    {stripOptions.delete_source && capabilities?.trash && (
+                      <label>
+                        <input
+                          type="checkbox"
+                          checked={stripOptions.disposal === 'trash'}
+                          onChange={(e) => setStripOptions(prev => ({ ...prev, disposal: e.target.checked ? 'trash' : 'delete' }))}
+                          disabled={isProcessing}
+                        />
+                        Move them to the trash instead of deleting
+                      </label>
+                    )}
+                  </div>
+                )}
+
+                <div className="option-group">
+                  <label>
+                    <input
+                      type="checkbox"
+                      checked={stripOptions.stream_results ?? false}
+                      onChange={(e) => setStripOptions(prev => ({ ...prev, stream_results: e.target.checked }))}
+                      disabled={isProcessing}
+                    />
+                    Write results to disk as they finish (for very large batches)
+                  </label>
+                </div>
+
                 {!stripOptions.remove_all && (
                   <div className="option-group">
                     <label>Keep fields (comma-separated):</label>
                     <input
                       type="text"
                       value={stripOptions.keep_fields || ''}
-                      onChange={(e) => setStripOptions(prev => ({ ...prev, keep_fields: e.target.value || undefined }))}
+                      onChange={(e) => {
+                        setStripOptions(prev => ({ ...prev, keep_fields: e.target.value || undefined }))
+                        setFieldError(null)
+                      }}
                       placeholder="title,artist,album,year"
                       disabled={isProcessing}
                     />
+                    {fieldError?.list === 'keep_fields' && <p className="field-error">{describeFieldError(fieldError)}</p>}
+                  </div>
+                )}
+
+                {stripOptions.remove_all && (
+                  <div className="option-group">
+                    <label>Protect fields (comma-separated):</label>
+                    <input
+                      type="text"
+                      value={stripOptions.protect || ''}
+                      onChange={(e) => {
+                        setStripOptions(prev => ({ ...prev, protect: e.target.value || undefined }))
+                        setFieldError(null)
+                      }}
+                      placeholder="copyright,encoder"
+                      disabled={isProcessing}
+                    />
+                    {fieldError?.list === 'protect' && <p className="field-error">{describeFieldError(fieldError)}</p>}
+                  </div>
+                )}
+
+                {!stripOptions.remove_all && (
+                  <div className="option-group">
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={stripOptions.normalize_genre || false}
+                        onChange={(e) => setStripOptions(prev => ({ ...prev, normalize_genre: e.target.checked }))}
+                        disabled={isProcessing}
+                      />
+                      Normalize genres ((17) → Rock)
+                    </label>
+                    {stripOptions.normalize_genre && (
+                      <input
+                        type="text"
+                        value={stripOptions.genre_map || ''}
+                        onChange={(e) => setStripOptions(prev => ({ ...prev, genre_map: e.target.value || undefined }))}
+                        placeholder="Genre map (genres.toml, optional)"
+                        disabled={isProcessing}
+                      />
+                    )}
+                  </div>
+                )}
+
+                {!stripOptions.remove_all && !stripOptions.keep_fields && !stripOptions.remove_fields && (
+                  <div className="option-group">
+                    <label>
+                      <input
+                        type="checkbox"
+                        checked={stripOptions.upgrade_tags || false}
+                        onChange={(e) => setStripOptions(prev => ({ ...prev, upgrade_tags: e.target.checked }))}
+                        disabled={isProcessing}
+                      />
+                      Upgrade tags to ID3v2.4, keeping every frame
+                    </label>
                   </div>
                 )}
               </div>
@@ -353,14 +1661,25 @@ function App() {
             {mp3Files.length > 0 && (
               <div className="file-list">
                 <h3>Found {mp3Files.length} MP3 files:</h3>
+                <p className="tag-versions">
+                  {Object.entries(mp3Files.reduce<Record<string, number>>((counts, f) => {
+                    const version = f.tag_version ?? 'no ID3v2'
+                    counts[version] = (counts[version] ?? 0) + 1
+                    return counts
+                  }, {})).sort().map(([version, count]) => `${version}: ${count}`).join(', ')}
+                </p>
                 <div className="files">
                   {mp3Files.map((file, index) => (
                     <div key={index} className="file-item">
                       <span className="file-name">{file.path.split(/[/\\]/).pop()}</span>
                       <span className="file-size">{formatFileSize(file.size)}</span>
-                      <span className={`metadata-status ${file.has_metadata ? 'has-metadata' : 'no-metadata'}`}>
-                        {file.has_metadata ? 'Has metadata' : 'No metadata'}
-                      </span>
+                      {isOtherFormat(file.format) ? (
+                        <span className="metadata-status wrong-format">{file.format} content</span>
+                      ) : (
+                        <span className={`metadata-status ${file.has_metadata ? 'has-metadata' : 'no-metadata'}`}>
+                          {file.has_metadata ? `Has metadata${file.tag_version ? ` (${file.tag_version}, ${formatFileSize(file.tag_size_bytes)})` : ''}` : 'No metadata'}
+                        </span>
+                      )}
                     </div>
                   ))}
                 </div>
@@ -390,6 +1709,12 @@ function App() {
                 </div>
               ))}
             </div>
+            {resultsPage && !isProcessing && (
+              <button onClick={loadResultsPage}>
+                {resultsPage.offset === 0 ? 'Show results' : 'Show more results'}
+              </button>
+            )}
+            {reportPath && <p className="report-path">Run report: {reportPath}</p>}
           </div>
         )}
       </div>
@@ -0,0 +1,213 @@
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::extract::ZipFile;
+use crate::ordering::natural_cmp;
+
+/// Which archive of a `--latest-per-group` group is the latest
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupOrder {
+    /// The most recently modified
+    #[default]
+    Mtime,
+    /// The last by file name, numbers compared by value, so `v10` beats `v9`
+    Name,
+}
+
+/// Archives sharing a group key, of which only the latest is extracted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ArchiveGroup {
+    /// The pattern's first capture group, e.g. `report` for `report_v2.zip`
+    pub key: String,
+    pub kept: PathBuf,
+    /// The rest of the group, in scan order
+    pub superseded: Vec<PathBuf>,
+}
+
+/// Compile a `--latest-per-group` pattern, which needs a capture group for the key
+pub fn compile_group_pattern(pattern: &str) -> Result<Regex> {
+    let regex = Regex::new(pattern).with_context(|| format!("Invalid --latest-per-group pattern {:?}", pattern))?;
+    if regex.captures_len() < 2 {
+        bail!("--latest-per-group pattern {:?} has no capture group to take the group key from, e.g. ^(report)_", pattern);
+    }
+    Ok(regex)
+}
+
+/// The group key of the archive at `path`: what the pattern's first capture
+/// group matches in its file name. `None` when the pattern doesn't match or
+/// the group took no part in the match.
+pub fn group_key(pattern: &Regex, path: &Path) -> Option<String> {
+    let name = path.file_name()?.to_string_lossy();
+    let captures = pattern.captures(&name)?;
+    Some(captures.get(1)?.as_str().to_string())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+}
+
+/// How `a` compares to `b` for being the latest; the other criterion breaks
+/// ties, and the path after that, so the choice doesn't depend on scan order
+fn compare_latest(order: GroupOrder, a: &ZipFile, b: &ZipFile) -> Ordering {
+    let by_name = || natural_cmp(&file_name(&a.path), &file_name(&b.path));
+    let by_mtime = || a.mtime.cmp(&b.mtime);
+    match order {
+        GroupOrder::Mtime => by_mtime().then_with(by_name),
+        GroupOrder::Name => by_name().then_with(by_mtime),
+    }
+    .then_with(|| a.path.cmp(&b.path))
+}
+
+/// Separate archives superseded by a later one of their group from those to
+/// extract, which keep their order. Archives the pattern doesn't match, and
+/// those without entries matching `--containing`, belong to no group and are
+/// kept. Every group is returned, including those of a single archive.
+pub fn split_superseded(zip_files: Vec<ZipFile>, pattern: &Regex, order: GroupOrder) -> (Vec<ZipFile>, Vec<ArchiveGroup>) {
+    let mut members: Vec<(String, Vec<usize>)> = Vec::new();
+    let mut group_of: HashMap<String, usize> = HashMap::new();
+    for (i, zip_file) in zip_files.iter().enumerate() {
+        if zip_file.matching_entries == Some(0) {
+            continue;
+        }
+        let Some(key) = group_key(pattern, &zip_file.path) else {
+            continue;
+        };
+        let group = *group_of.entry(key.clone()).or_insert_with(|| {
+            members.push((key, Vec::new()));
+            members.len() - 1
+        });
+        members[group].1.push(i);
+    }
+
+    let mut superseded = vec![false; zip_files.len()];
+    let groups: Vec<ArchiveGroup> = members
+        .into_iter()
+        .map(|(key, indices)| {
+            let latest = *indices
+                .iter()
+                .max_by(|&&a, &&b| compare_latest(order, &zip_files[a], &zip_files[b]))
+                .expect("groups have a member");
+            for &i in indices.iter().filter(|&&i| i != latest) {
+                superseded[i] = true;
+            }
+            ArchiveGroup {
+                key,
+                kept: zip_files[latest].path.clone(),
+                superseded: indices.iter().filter(|&&i| i != latest).map(|&i| zip_files[i].path.clone()).collect(),
+            }
+        })
+        .collect();
+    let kept = zip_files.into_iter().zip(superseded).filter(|(_, superseded)| !superseded).map(|(zip_file, _)| zip_file).collect();
+    (kept, groups)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::ArchiveHealth;
+    use crate::file_types::ArchiveKind;
+
+    fn zip_file(name: &str, mtime: i64) -> ZipFile {
+        let path = PathBuf::from("in").join(name);
+        ZipFile {
+            output_name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+            path,
+            size: 22,
+            matching_entries: None,
+            entries: Some(1),
+            uncompressed_size: 0,
+            health: ArchiveHealth::Ok,
+            mtime,
+            sha256: None,
+            kind: ArchiveKind::Zip,
+            zip64: false,
+        }
+    }
+
+    fn names(zip_files: &[ZipFile]) -> Vec<String> {
+        zip_files.iter().map(|f| file_name(&f.path)).collect()
+    }
+
+    #[test]
+    fn patterns_need_a_capture_group() {
+        assert!(compile_group_pattern("^(report)_").is_ok());
+        let error = compile_group_pattern("^report_").unwrap_err();
+        assert!(error.to_string().contains("has no capture group"), "{}", error);
+        let error = compile_group_pattern("^(report_").unwrap_err();
+        assert!(error.to_string().starts_with("Invalid --latest-per-group pattern"), "{}", error);
+    }
+
+    #[test]
+    fn keys_come_from_the_first_capture_group_of_the_file_name() {
+        let pattern = compile_group_pattern(r"^(.+)_v?\d+\.zip$").unwrap();
+        assert_eq!(group_key(&pattern, Path::new("2024/report_v2.zip")).as_deref(), Some("report"));
+        assert_eq!(group_key(&pattern, Path::new("report_2024/notes.zip")), None);
+        // The group took no part in the match
+        let pattern = compile_group_pattern("^(?:(report)|notes)").unwrap();
+        assert_eq!(group_key(&pattern, Path::new("notes.zip")), None);
+        assert_eq!(group_key(&pattern, Path::new("report.zip")).as_deref(), Some("report"));
+    }
+
+    #[test]
+    fn only_the_newest_of_a_group_is_kept() {
+        let pattern = compile_group_pattern("^(report|invoice)_").unwrap();
+        let zip_files = vec![
+            zip_file("invoice_1.zip", 100),
+            zip_file("notes.zip", 50),
+            zip_file("report_final.zip", 300),
+            zip_file("report_v1.zip", 100),
+            zip_file("report_v2.zip", 200),
+        ];
+        let (kept, groups) = split_superseded(zip_files, &pattern, GroupOrder::Mtime);
+        // Archives in no group are extracted as usual, in scan order
+        assert_eq!(names(&kept), ["invoice_1.zip", "notes.zip", "report_final.zip"]);
+        assert_eq!(
+            groups,
+            [
+                ArchiveGroup { key: "invoice".into(), kept: "in/invoice_1.zip".into(), superseded: Vec::new() },
+                ArchiveGroup {
+                    key: "report".into(),
+                    kept: "in/report_final.zip".into(),
+                    superseded: vec!["in/report_v1.zip".into(), "in/report_v2.zip".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn name_order_compares_numbers_by_value() {
+        let pattern = compile_group_pattern("^(report)_").unwrap();
+        let zip_files = vec![zip_file("report_v9.zip", 300), zip_file("report_v10.zip", 100), zip_file("report_final.zip", 200)];
+        let (kept, groups) = split_superseded(zip_files, &pattern, GroupOrder::Name);
+        assert_eq!(names(&kept), ["report_v10.zip"]);
+        assert_eq!(groups[0].superseded, [PathBuf::from("in/report_v9.zip"), PathBuf::from("in/report_final.zip")]);
+    }
+
+    #[test]
+    fn ties_are_broken_the_same_whatever_the_scan_order() {
+        let pattern = compile_group_pattern("^(report)_").unwrap();
+        let mut zip_files = vec![zip_file("report_a.zip", 100), zip_file("report_b.zip", 100), zip_file("report_c.zip", 50)];
+        let (forward, _) = split_superseded(zip_files.clone(), &pattern, GroupOrder::Mtime);
+        zip_files.reverse();
+        let (backward, _) = split_superseded(zip_files, &pattern, GroupOrder::Mtime);
+        assert_eq!(names(&forward), ["report_b.zip"]);
+        assert_eq!(names(&backward), ["report_b.zip"]);
+    }
+
+    #[test]
+    fn archives_without_matching_entries_belong_to_no_group() {
+        let pattern = compile_group_pattern("^(report)_").unwrap();
+        let mut newest = zip_file("report_v2.zip", 200);
+        newest.matching_entries = Some(0);
+        let (kept, groups) = split_superseded(vec![zip_file("report_v1.zip", 100), newest], &pattern, GroupOrder::Mtime);
+        assert_eq!(names(&kept), ["report_v1.zip", "report_v2.zip"]);
+        assert_eq!(groups[0].kept, Path::new("in/report_v1.zip"));
+        assert!(groups[0].superseded.is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use indicatif::ProgressBar;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{ZipArchive, ZipWriter};
+
+use crate::format::format_size;
+use crate::scan_roots::{ScanRoots, SeenFiles};
+use crate::scratch::{move_file, ScratchDir};
+use crate::sniff::{sniff_file, AudioFormat};
+use crate::metadata_stripper::{
+    is_apple_double, read_id3v2_header, strip_metadata_file, FrameRules, MetadataArgs, Mp3File, StripResult, StripStatus,
+    TagInfo,
+};
+
+fn is_mp3_entry(name: &str) -> bool {
+    !name.ends_with('/') && name.to_lowercase().ends_with(".mp3")
+}
+
+/// Path of the rewritten archive: `<stem>-clean.zip` beside the original, or
+/// the original itself when replacing in place
+fn clean_archive_path(zip_path: &Path, in_place: bool) -> PathBuf {
+    if in_place {
+        return zip_path.to_path_buf();
+    }
+    let stem = zip_path.file_stem().unwrap().to_string_lossy();
+    zip_path.with_file_name(format!("{}-clean.zip", stem))
+}
+
+/// Strip every MP3 entry of one archive and write a new archive containing the
+/// cleaned MP3s plus all other entries raw-copied with their original compression.
+/// An MP3 that fails to strip is raw-copied too, as it was.
+///
+/// Each MP3 is staged in a directory in scratch space, which is removed when
+/// this function returns, successful or not.
+pub async fn strip_archive(
+    zip_path: &Path,
+    rules: &FrameRules,
+    in_place: bool,
+    dry_run: bool,
+    verbose: bool,
+    scratch: &ScratchDir,
+) -> Result<Vec<StripResult>> {
+    let file = fs::File::open(zip_path)
+        .with_context(|| format!("Failed to open zip file {:?}", zip_path))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {:?}", zip_path))?;
+
+    let staging = scratch.staging("strip")?;
+
+    let output_path = clean_archive_path(zip_path, in_place);
+    let partial_path = staging.path().join("archive.zip.partial");
+    let mut writer = if dry_run {
+        None
+    } else {
+        let out = fs::File::create(&partial_path)
+            .with_context(|| format!("Failed to create {:?}", partial_path))?;
+        Some(ZipWriter::new(out))
+    };
+
+    let mut results = Vec::new();
+
+    for i in 0..archive.len() {
+        let name = archive.by_index_raw(i)
+            .with_context(|| format!("Failed to read file at index {} in {:?}", i, zip_path))?
+            .name()
+            .to_string();
+
+        let sidecar = is_apple_double(Path::new(&name)) && !rules.include_hidden;
+        if !is_mp3_entry(&name) || sidecar {
+            if let Some(writer) = writer.as_mut() {
+                let entry = archive.by_index_raw(i)?;
+                writer.raw_copy_file(entry)
+                    .with_context(|| format!("Failed to copy {} from {:?}", name, zip_path))?;
+            }
+            continue;
+        }
+
+        let mut entry = archive.by_index(i)
+            .with_context(|| format!("Failed to read {} in {:?}", name, zip_path))?;
+        let options = FileOptions::default()
+            .compression_method(entry.compression())
+            .last_modified_time(entry.last_modified())
+            .large_file(entry.size() >= u32::MAX as u64);
+        let options = match entry.unix_mode() {
+            Some(mode) => options.unix_permissions(mode),
+            None => options,
+        };
+
+        // Stage under the entry's own file name so per-file messages stay recognizable
+        let staged_dir = staging.path().join(i.to_string());
+        fs::create_dir(&staged_dir)
+            .with_context(|| format!("Failed to create {:?}", staged_dir))?;
+        let staged_path = staged_dir.join(Path::new(&name).file_name().unwrap_or_default());
+        {
+            let mut staged = fs::File::create(&staged_path)
+                .with_context(|| format!("Failed to create {:?}", staged_path))?;
+            io::copy(&mut entry, &mut staged)
+                .with_context(|| format!("Failed to extract {} from {:?}", name, zip_path))?;
+        }
+        drop(entry);
+
+        scratch.note_usage();
+        let tag = id3::Tag::read_from_path(&staged_path).ok();
+        let id3v2 = read_id3v2_header(&staged_path).ok().flatten();
+        let mp3_file = Mp3File {
+            path: staged_path.clone(),
+            size: fs::metadata(&staged_path)?.len(),
+            has_metadata: tag.is_some(),
+            tags: tag.as_ref().map(TagInfo::from_tag).unwrap_or_default(),
+            kind: AudioFormat::Mp3,
+            format: sniff_file(&staged_path).unwrap_or_default(),
+            tag_version: id3v2.map(|header| header.version()),
+            tag_size_bytes: id3v2.map_or(0, |header| header.size),
+        };
+
+        let mut result = match strip_metadata_file(&mp3_file, None, rules, dry_run, verbose, ProgressBar::hidden()).await {
+            Ok(result) => result,
+            Err(e) => StripResult::failed(&staged_path, &e),
+        };
+        result.path = zip_path.join(&name);
+
+        if let Some(writer) = writer.as_mut() {
+            if matches!(result.status, StripStatus::Failed | StripStatus::WriteVerificationFailed) {
+                // The staged copy may be half rewritten; the archive keeps the entry as it was
+                writer.raw_copy_file(archive.by_index_raw(i)?)
+                    .with_context(|| format!("Failed to copy {} from {:?}", name, zip_path))?;
+            } else {
+                writer.start_file(name.as_str(), options)
+                    .with_context(|| format!("Failed to add {} to {:?}", name, output_path))?;
+                let mut cleaned = fs::File::open(&staged_path)?;
+                io::copy(&mut cleaned, writer)
+                    .with_context(|| format!("Failed to write {} to {:?}", name, output_path))?;
+            }
+        }
+        fs::remove_dir_all(&staged_dir)
+            .with_context(|| format!("Failed to remove temporary directory {:?}", staged_dir))?;
+
+        results.push(result);
+    }
+
+    if let Some(mut writer) = writer {
+        writer.finish()
+            .with_context(|| format!("Failed to finish {:?}", output_path))?;
+        scratch.note_usage();
+        move_file(&partial_path, &output_path)
+            .with_context(|| format!("Failed to move cleaned archive to {:?}", output_path))?;
+    }
+
+    Ok(results)
+}
+
+fn find_archives(roots: &ScanRoots) -> Vec<PathBuf> {
+    let mut seen = SeenFiles::default();
+    roots
+        .iter()
+        .flat_map(|root| WalkDir::new(&root.path).follow_links(true).into_iter())
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "zip"))
+        .filter(|p| !p.file_stem().is_some_and(|s| s.to_string_lossy().ends_with("-clean")))
+        .filter(|p| !roots.is_multiple() || seen.first_sighting(p))
+        .collect()
+}
+
+pub async fn bulk_strip_archives(args: &MetadataArgs, roots: &ScanRoots, rules: &FrameRules, workers: usize) -> Result<Vec<StripResult>> {
+    println!("🔍 Scanning for zip files in {}...", roots);
+    let mut archives = find_archives(roots);
+    args.sort.sort_by_path(&mut archives, |p| p);
+
+    if archives.is_empty() {
+        println!("❌ No zip files found in {}", roots);
+        return Ok(Vec::new());
+    }
+
+    println!("📦 Found {} zip files", archives.len());
+    let scratch = ScratchDir::prepare(args.temp_dir.as_deref(), roots.first())?;
+
+    let chunks: Vec<_> = archives
+        .chunks(archives.len().div_ceil(workers))
+        .collect();
+
+    let futures: Vec<_> = chunks
+        .into_iter()
+        .map(|chunk| {
+            let chunk = chunk.to_vec();
+            let rules = rules.clone();
+            let in_place = args.in_place_archive;
+            let dry_run = args.dry_run;
+            let verbose = args.verbose;
+            let scratch = &scratch;
+
+            async move {
+                let mut results = Vec::new();
+                for zip_path in chunk {
+                    match strip_archive(&zip_path, &rules, in_place, dry_run, verbose, scratch).await {
+                        Ok(archive_results) => results.extend(archive_results),
+                        Err(e) => results.push(StripResult::failed(&zip_path, &e)),
+                    }
+                }
+                results
+            }
+        })
+        .collect();
+
+    let results = join_all(futures).await.into_iter().flatten().collect();
+    println!("🗂️  Peak temporary space: {} in {:?}", format_size(scratch.peak_usage()), scratch.path());
+    scratch.cleanup()?;
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::TagLike;
+    use std::io::{Cursor, Write};
+    use zip::CompressionMethod;
+
+    /// MPEG audio frames, enough of them to clear the default minimum size
+    fn audio() -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.resize(417, 0);
+        frame.repeat(12)
+    }
+
+    fn mp3_with_v23_tag() -> Vec<u8> {
+        let mut tag = id3::Tag::new();
+        tag.set_title("Title");
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes, id3::Version::Id3v23).unwrap();
+        bytes.extend(audio());
+        bytes
+    }
+
+    /// An ID3v2.2 tag with a frame ID3v2.4 has no equivalent for, which
+    /// `--upgrade-tags` fails on
+    fn mp3_failing_upgrade() -> Vec<u8> {
+        let mut frames = b"TT2\x00\x00\x06\x00Title".to_vec();
+        frames.extend(b"XYZ\x00\x00\x04\x00abc");
+        let size = frames.len() as u32;
+        let mut bytes = b"ID3\x02\x00\x00".to_vec();
+        bytes.extend([(size >> 21) as u8 & 0x7f, (size >> 14) as u8 & 0x7f, (size >> 7) as u8 & 0x7f, size as u8 & 0x7f]);
+        bytes.extend(frames);
+        bytes.extend(audio());
+        bytes
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, CompressionMethod, Option<i32>, &[u8])]) {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, method, level, data) in entries {
+            let options = FileOptions::default().compression_method(*method).compression_level(*level);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(data).unwrap();
+        }
+        fs::write(path, writer.finish().unwrap().into_inner()).unwrap();
+    }
+
+    /// Each entry's name, compression, CRC and compressed bytes
+    fn raw_entries(path: &Path) -> Vec<(String, CompressionMethod, u32, Vec<u8>)> {
+        let mut archive = ZipArchive::new(fs::File::open(path).unwrap()).unwrap();
+        (0..archive.len())
+            .map(|i| {
+                let mut entry = archive.by_index_raw(i).unwrap();
+                let mut raw = Vec::new();
+                io::copy(&mut entry, &mut raw).unwrap();
+                (entry.name().to_string(), entry.compression(), entry.crc32(), raw)
+            })
+            .collect()
+    }
+
+    fn stripped(in_place: bool) {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("album.zip");
+        let notes = b"liner notes ".repeat(100);
+        let good = mp3_with_v23_tag();
+        let bad = mp3_failing_upgrade();
+        // The failing MP3 is compressed at a level recompressing it wouldn't reproduce
+        write_zip(&zip_path, &[
+            ("album/notes.txt", CompressionMethod::Deflated, Some(1), &notes),
+            ("album/cover.jpg", CompressionMethod::Stored, None, b"\xFF\xD8\xFF\xE0 not really a jpeg"),
+            ("album/01.mp3", CompressionMethod::Deflated, None, &good),
+            ("album/02.mp3", CompressionMethod::Deflated, Some(1), &bad),
+        ]);
+        let before = raw_entries(&zip_path);
+
+        let rules = FrameRules::from_args(&MetadataArgs { upgrade_tags: true, ..MetadataArgs::default() }).unwrap();
+        let scratch = ScratchDir::prepare(None, dir.path()).unwrap();
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let results = runtime.block_on(strip_archive(&zip_path, &rules, in_place, false, false, &scratch)).unwrap();
+        scratch.cleanup().unwrap();
+
+        let statuses: Vec<_> = results.iter().map(|result| (result.path.clone(), result.status)).collect();
+        assert_eq!(statuses, vec![
+            (zip_path.join("album/01.mp3"), StripStatus::Stripped),
+            (zip_path.join("album/02.mp3"), StripStatus::Failed),
+        ]);
+
+        let after = raw_entries(&clean_archive_path(&zip_path, in_place));
+        assert_eq!(after.len(), before.len());
+        for index in [0, 1, 3] {
+            assert_eq!(after[index], before[index], "{} changed", before[index].0);
+        }
+        assert_eq!(after[2].0, "album/01.mp3");
+        assert_ne!(after[2].3, before[2].3);
+    }
+
+    #[test]
+    fn failed_entries_are_copied_unchanged() {
+        stripped(false);
+    }
+
+    #[test]
+    fn failed_entries_survive_in_place() {
+        stripped(true);
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::{Context, Result};
+use id3::frame::PictureType;
+use id3::{Tag, TagLike};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Names, compared case-insensitively, of the image files players take as an album's art
+const SIDECAR_STEMS: &[&str] = &["cover", "folder", "front", "album", "albumart", "artwork"];
+const SIDECAR_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png"];
+
+/// What a strip does about files whose embedded art it would remove while
+/// no cover image sits beside them, leaving their album without artwork
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtSafety {
+    /// Remove the art regardless
+    #[default]
+    Ignore,
+    /// Remove the art and list the directories left without any
+    Warn,
+    /// Write the front cover out as `cover.jpg` first
+    Export,
+    /// Leave the pictures in those files
+    Skip,
+}
+
+/// A file whose embedded art was its directory's only artwork
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ArtSafetyHit {
+    /// Directory the stripped file is written to
+    pub directory: PathBuf,
+    pub action: ArtSafety,
+    /// Image `export` wrote; `None` on a dry run, or when another file of the
+    /// directory wrote it first
+    pub exported: Option<PathBuf>,
+}
+
+/// Whether `tag` has pictures and `planned` keeps none of them
+pub fn removes_art(tag: &Tag, planned: &Tag) -> bool {
+    tag.pictures().next().is_some() && planned.pictures().next().is_none()
+}
+
+/// Whether `directory` holds a cover image, e.g. `Folder.JPG`
+pub fn has_sidecar_art(directory: &Path) -> bool {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return false;
+    };
+    entries.flatten().any(|entry| {
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        name.rsplit_once('.').is_some_and(|(stem, extension)| {
+            SIDECAR_STEMS.contains(&stem) && SIDECAR_EXTENSIONS.contains(&extension)
+        })
+    })
+}
+
+/// Put the APIC frames of `original` back into `planned`
+pub fn keep_art(original: &Tag, planned: &mut Tag) {
+    for frame in original.frames().filter(|frame| frame.id() == "APIC") {
+        planned.add_frame(frame.clone());
+    }
+}
+
+/// Write the front cover of `tag`, or its first picture when none is marked
+/// as such, into `directory` as `cover.jpg` (`cover.png` for PNG data).
+/// Returns `None` when that file already exists, e.g. written by another
+/// track of the album.
+pub fn export_cover(tag: &Tag, directory: &Path) -> Result<Option<PathBuf>> {
+    let Some(picture) = tag.pictures()
+        .find(|picture| picture.picture_type == PictureType::CoverFront)
+        .or_else(|| tag.pictures().next())
+    else {
+        return Ok(None);
+    };
+    let name = if picture.mime_type.eq_ignore_ascii_case("image/png") { "cover.png" } else { "cover.jpg" };
+    let path = directory.join(name);
+    fs::create_dir_all(directory)
+        .with_context(|| format!("Failed to create directory {:?}", directory))?;
+    let mut file = match OpenOptions::new().write(true).create_new(true).open(&path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Ok(None),
+        Err(e) => return Err(e).with_context(|| format!("Failed to create {:?}", path)),
+    };
+    if let Err(e) = file.write_all(&picture.data) {
+        let _ = fs::remove_file(&path);
+        return Err(e).with_context(|| format!("Failed to write the cover art to {:?}", path));
+    }
+    Ok(Some(path))
+}
@@ -0,0 +1,98 @@
+use serde::Serialize;
+
+use crate::defaults::{Defaults, DEFAULTS};
+use crate::io_profile::DEFAULT_WORKERS;
+
+/// Each Cargo feature and the [`Capabilities::features`] entry it switches
+pub const CARGO_FEATURES: [(&str, &str); 1] = [("reflink", "reflink copies")];
+
+/// What this build can do, so a front end can hide controls for the rest
+/// instead of letting commands fail
+#[derive(Debug, Clone, Serialize)]
+pub struct Capabilities {
+    pub version: &'static str,
+    /// Workers a run uses without `--workers` or `--io-profile`
+    pub default_workers: usize,
+    /// Option defaults, as the build's `BULK_UNZIP_DEFAULT_*` variables set them
+    pub defaults: Defaults,
+    /// Extracting 7z archives; they are only recognized as nested archives
+    pub sevenz: bool,
+    /// Extracting rar archives such as `.cbr` comics, which the scan flags as unsupported
+    pub rar: bool,
+    /// zstd-compressed zip entries, decoded by the zip crate's default features
+    pub zstd_entries: bool,
+    /// Watching a directory and extracting archives as they arrive
+    pub watch: bool,
+    /// Desktop notifications when a run finishes
+    pub notifications: bool,
+    /// Whether an unprivileged process can create symlinks; Windows needs
+    /// Developer Mode, which isn't checked
+    pub symlinks: bool,
+    /// Moving replaced files to the system trash instead of deleting them
+    pub trash: bool,
+    /// Copy-on-write copies for `strip --output`, with the `reflink` feature
+    /// on Linux and macOS
+    pub reflink: bool,
+}
+
+impl Capabilities {
+    pub fn detect() -> Self {
+        Capabilities {
+            version: env!("CARGO_PKG_VERSION"),
+            default_workers: DEFAULT_WORKERS,
+            defaults: DEFAULTS,
+            sevenz: false,
+            rar: false,
+            zstd_entries: true,
+            watch: false,
+            notifications: false,
+            symlinks: cfg!(unix),
+            trash: cfg!(any(unix, windows)),
+            reflink: cfg!(all(feature = "reflink", any(target_os = "linux", target_os = "macos"))),
+        }
+    }
+
+    /// Each capability with a label, in the order `--version --verbose` prints them
+    pub fn features(&self) -> [(&'static str, bool); 8] {
+        [
+            ("7z archives", self.sevenz),
+            ("rar archives", self.rar),
+            ("zstd entries", self.zstd_entries),
+            ("watch mode", self.watch),
+            ("notifications", self.notifications),
+            ("symlinks", self.symlinks),
+            ("trash", self.trash),
+            ("reflink copies", self.reflink),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_cargo_feature_toggles_exactly_one_capability() {
+        let manifest: toml::Table = toml::from_str(include_str!("../Cargo.toml")).unwrap();
+        let mut features: Vec<&str> = manifest["features"].as_table().unwrap().keys().map(String::as_str).filter(|name| *name != "default").collect();
+        features.sort();
+        let mut covered: Vec<&str> = CARGO_FEATURES.iter().map(|(feature, _)| *feature).collect();
+        covered.sort();
+        assert_eq!(features, covered, "every feature in Cargo.toml needs an entry in CARGO_FEATURES");
+
+        let labels = Capabilities::detect().features().map(|(label, _)| label);
+        let mut switched: Vec<&str> = CARGO_FEATURES.iter().map(|(_, capability)| *capability).collect();
+        for (feature, capability) in CARGO_FEATURES {
+            assert_eq!(labels.iter().filter(|label| **label == capability).count(), 1, "{} switches {:?}", feature, capability);
+        }
+        switched.sort();
+        switched.dedup();
+        assert_eq!(switched.len(), CARGO_FEATURES.len());
+    }
+
+    #[test]
+    fn reflink_copies_follow_the_feature() {
+        let supported = cfg!(any(target_os = "linux", target_os = "macos"));
+        assert_eq!(Capabilities::detect().reflink, cfg!(feature = "reflink") && supported);
+    }
+}
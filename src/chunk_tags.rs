@@ -0,0 +1,363 @@
+use anyhow::{bail, Context, Result};
+use id3::Tag;
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// Bytes of a chunk header: four-character ID, then the body size
+const CHUNK_HEADER: usize = 8;
+/// `RIFF`/`FORM`, the file size and the form type
+const ROOT_HEADER: usize = 12;
+
+/// The chunked containers whose ID3 tag lives in a chunk of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    /// RIFF WAVE, sizes little-endian
+    Wav,
+    /// AIFF or AIFF-C, sizes big-endian
+    Aiff,
+}
+
+impl Container {
+    fn size(self, bytes: &[u8]) -> usize {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        match self {
+            Container::Wav => u32::from_le_bytes(bytes) as usize,
+            Container::Aiff => u32::from_be_bytes(bytes) as usize,
+        }
+    }
+
+    fn size_bytes(self, size: usize) -> Result<[u8; 4]> {
+        let size = u32::try_from(size).context("Chunk too large for a 32-bit size")?;
+        Ok(match self {
+            Container::Wav => size.to_le_bytes(),
+            Container::Aiff => size.to_be_bytes(),
+        })
+    }
+
+    /// The chunk holding the samples, which must come through byte for byte
+    fn audio_chunk(self) -> &'static [u8; 4] {
+        match self {
+            Container::Wav => b"data",
+            Container::Aiff => b"SSND",
+        }
+    }
+
+    /// ID of a new ID3 chunk, for files that had none
+    fn id3_chunk(self) -> &'static [u8; 4] {
+        match self {
+            Container::Wav => b"id3 ",
+            Container::Aiff => b"ID3 ",
+        }
+    }
+}
+
+/// One chunk of a WAV or AIFF file
+struct Chunk {
+    id: [u8; 4],
+    /// The body, without the header or pad byte
+    body: Range<usize>,
+    /// Header, body and pad byte
+    whole: Range<usize>,
+}
+
+impl Chunk {
+    fn is_id3(&self) -> bool {
+        self.id.eq_ignore_ascii_case(b"id3 ")
+    }
+}
+
+/// The root form and top-level chunks of a WAV or AIFF file. A chunk
+/// declaring more than the file holds, as streaming recorders leave behind,
+/// is cut off at the end of the file.
+fn parse(data: &[u8]) -> Result<(Container, Vec<Chunk>)> {
+    let container = match data.get(..ROOT_HEADER) {
+        Some([b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E']) => Container::Wav,
+        Some([b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C']) => Container::Aiff,
+        _ => bail!("not a WAV or AIFF file"),
+    };
+    let end = (container.size(&data[4..8]) + CHUNK_HEADER).min(data.len());
+    let mut chunks = Vec::new();
+    let mut offset = ROOT_HEADER;
+    while offset + CHUNK_HEADER <= end {
+        let size = container.size(&data[offset + 4..offset + 8]);
+        let body_end = (offset + CHUNK_HEADER + size).min(end);
+        let whole_end = (body_end + size % 2).min(end);
+        chunks.push(Chunk {
+            id: [data[offset], data[offset + 1], data[offset + 2], data[offset + 3]],
+            body: offset + CHUNK_HEADER..body_end,
+            whole: offset..whole_end,
+        });
+        offset = whole_end;
+    }
+    Ok((container, chunks))
+}
+
+fn push_chunk(out: &mut Vec<u8>, container: Container, id: &[u8; 4], body: &[u8]) -> Result<()> {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&container.size_bytes(body.len())?);
+    out.extend_from_slice(body);
+    if body.len() % 2 == 1 {
+        out.push(0);
+    }
+    Ok(())
+}
+
+/// One item of a WAV file's `LIST INFO` chunk, e.g. `IART` or `ICMT`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfoItem {
+    pub id: String,
+    pub value: String,
+    /// Where the item sits, header and pad byte included
+    whole: Range<usize>,
+}
+
+/// The `LIST INFO` chunk's body and items, if the file has one
+fn info_items(data: &[u8], chunk: &Chunk) -> Option<Vec<InfoItem>> {
+    let body = &data[chunk.body.clone()];
+    if &chunk.id != b"LIST" || body.get(..4) != Some(b"INFO") {
+        return None;
+    }
+    let mut items = Vec::new();
+    let mut offset = 4;
+    while offset + CHUNK_HEADER <= body.len() {
+        let size = Container::Wav.size(&body[offset + 4..offset + 8]);
+        let value_end = (offset + CHUNK_HEADER + size).min(body.len());
+        let whole_end = (value_end + size % 2).min(body.len());
+        let value = &body[offset + CHUNK_HEADER..value_end];
+        items.push(InfoItem {
+            id: String::from_utf8_lossy(&body[offset..offset + 4]).into_owned(),
+            value: String::from_utf8_lossy(value).trim_end_matches('\0').to_string(),
+            whole: offset..whole_end,
+        });
+        offset = whole_end;
+    }
+    Some(items)
+}
+
+/// The `LIST INFO` items of a WAV file; empty for AIFF or without the chunk
+pub fn read_info(path: &Path) -> Result<Vec<InfoItem>> {
+    let data = fs::read(path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    let (_, chunks) = parse(&data).with_context(|| format!("Failed to read the chunks of {:?}", path))?;
+    Ok(chunks.iter().filter_map(|chunk| info_items(&data, chunk)).flatten().collect())
+}
+
+/// How [`rewrite_chunks`] changes a file's metadata
+#[derive(Default)]
+pub struct ChunkEdit<'a> {
+    /// `None` leaves the ID3 chunk as it is, `Some(None)` removes it and
+    /// `Some(Some(tag))` replaces it with `tag` as ID3v2.4
+    pub id3: Option<Option<&'a Tag>>,
+    /// IDs of the `LIST INFO` items to remove; the chunk goes once it is empty
+    pub remove_info: Vec<String>,
+    /// Bytes of padding written after a replaced ID3 tag
+    pub padding: usize,
+}
+
+/// Rewrite a WAV or AIFF file with its ID3 chunk and `LIST INFO` items
+/// changed as `edit` says. Every other chunk is copied byte for byte, the
+/// `RIFF`/`FORM` size is recomputed, and the audio chunk is checked to be
+/// unchanged before the file is replaced.
+pub fn rewrite_chunks(path: &Path, edit: &ChunkEdit) -> Result<()> {
+    let data = fs::read(path)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    let (container, chunks) = parse(&data).with_context(|| format!("Failed to read the chunks of {:?}", path))?;
+
+    let new_id3 = match edit.id3 {
+        Some(Some(tag)) => {
+            let mut body = Vec::new();
+            id3::Encoder::new()
+                .version(id3::Version::Id3v24)
+                .padding(edit.padding)
+                .encode(tag, &mut body)
+                .with_context(|| format!("Failed to encode the ID3 tag for {:?}", path))?;
+            Some(body)
+        }
+        _ => None,
+    };
+
+    let mut out = data[..ROOT_HEADER].to_vec();
+    let mut wrote_id3 = false;
+    for chunk in &chunks {
+        if chunk.is_id3() && edit.id3.is_some() {
+            if let Some(body) = new_id3.as_ref().filter(|_| !wrote_id3) {
+                push_chunk(&mut out, container, &chunk.id, body)?;
+                wrote_id3 = true;
+            }
+            continue;
+        }
+        if let Some(items) = info_items(&data, chunk).filter(|_| !edit.remove_info.is_empty()) {
+            let kept: Vec<&InfoItem> = items.iter().filter(|item| !edit.remove_info.contains(&item.id)).collect();
+            if !kept.is_empty() {
+                let body_start = chunk.body.start;
+                let mut body = b"INFO".to_vec();
+                for item in kept {
+                    body.extend_from_slice(&data[body_start + item.whole.start..body_start + item.whole.end]);
+                }
+                push_chunk(&mut out, container, b"LIST", &body)?;
+            }
+            continue;
+        }
+        out.extend_from_slice(&data[chunk.whole.clone()]);
+    }
+    if let (Some(body), false) = (&new_id3, wrote_id3) {
+        push_chunk(&mut out, container, container.id3_chunk(), body)?;
+    }
+    let root_size = container.size_bytes(out.len() - CHUNK_HEADER)?;
+    out[4..8].copy_from_slice(&root_size);
+    // Anything after the form, such as a tag some tools append, stays as it was
+    let form_end = chunks.last().map_or(ROOT_HEADER, |chunk| chunk.whole.end);
+    out.extend_from_slice(&data[form_end..]);
+
+    let audio = |bytes: &[u8], chunks: &[Chunk]| {
+        chunks.iter().find(|c| &c.id == container.audio_chunk()).map(|c| bytes[c.body.clone()].to_vec())
+    };
+    let (_, new_chunks) = parse(&out).with_context(|| format!("Rewritten {:?} no longer parses", path))?;
+    if audio(&data, &chunks) != audio(&out, &new_chunks) {
+        bail!("The audio chunk of {:?} would change; left untouched", path);
+    }
+    fs::write(path, &out)
+        .with_context(|| format!("Failed to write stripped file {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::TagLike;
+
+    fn tag_bytes(title: &str) -> Vec<u8> {
+        let mut tag = Tag::new();
+        tag.set_title(title);
+        tag.set_artist("Recorder");
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes, id3::Version::Id3v23).unwrap();
+        bytes
+    }
+
+    fn info_body(items: &[(&str, &str)]) -> Vec<u8> {
+        let mut body = b"INFO".to_vec();
+        for (id, value) in items {
+            push_chunk(&mut body, Container::Wav, id.as_bytes().try_into().unwrap(), format!("{}\0", value).as_bytes()).unwrap();
+        }
+        body
+    }
+
+    /// A file of `container` holding `chunks`, with a correct form size
+    fn form(container: Container, chunks: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut out = match container {
+            Container::Wav => b"RIFF\0\0\0\0WAVE".to_vec(),
+            Container::Aiff => b"FORM\0\0\0\0AIFF".to_vec(),
+        };
+        for (id, body) in chunks {
+            push_chunk(&mut out, container, id, body).unwrap();
+        }
+        let size = container.size_bytes(out.len() - CHUNK_HEADER).unwrap();
+        out[4..8].copy_from_slice(&size);
+        out
+    }
+
+    fn samples() -> Vec<u8> {
+        (0..1001).map(|i| (i * 7 % 256) as u8).collect()
+    }
+
+    fn chunk_ids(data: &[u8]) -> Vec<String> {
+        parse(data).unwrap().1.iter().map(|chunk| String::from_utf8_lossy(&chunk.id).into_owned()).collect()
+    }
+
+    fn body<'a>(data: &'a [u8], id: &[u8; 4]) -> &'a [u8] {
+        let (_, chunks) = parse(data).unwrap();
+        let chunk = chunks.iter().find(|chunk| &chunk.id == id).unwrap();
+        &data[chunk.body.clone()]
+    }
+
+    fn assert_form_size(container: Container, data: &[u8]) {
+        assert_eq!(container.size(&data[4..8]), data.len() - CHUNK_HEADER);
+    }
+
+    #[test]
+    fn wav_loses_its_id3_chunk_and_info_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("field.wav");
+        let info = info_body(&[("INAM", "Dawn chorus"), ("ICMT", "GPS 51.5,-0.1"), ("ISFT", "Recorder 2")]);
+        fs::write(&path, form(Container::Wav, &[(b"fmt ", vec![1; 16]), (b"LIST", info), (b"data", samples()), (b"id3 ", tag_bytes("Dawn"))])).unwrap();
+        let items: Vec<(String, String)> = read_info(&path).unwrap().into_iter().map(|item| (item.id, item.value)).collect();
+        assert_eq!(items[1], ("ICMT".to_string(), "GPS 51.5,-0.1".to_string()));
+
+        let edit = ChunkEdit { id3: Some(None), remove_info: vec!["ICMT".to_string(), "INAM".to_string()], padding: 0 };
+        rewrite_chunks(&path, &edit).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_form_size(Container::Wav, &data);
+        assert_eq!(chunk_ids(&data), ["fmt ", "LIST", "data"]);
+        assert_eq!(body(&data, b"fmt "), [1; 16]);
+        assert_eq!(body(&data, b"data"), samples());
+        let items: Vec<String> = read_info(&path).unwrap().into_iter().map(|item| item.id).collect();
+        assert_eq!(items, ["ISFT"]);
+        assert!(Tag::read_from_path(&path).is_err());
+    }
+
+    #[test]
+    fn wav_tag_is_replaced_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("field.wav");
+        let info = info_body(&[("ICMT", "GPS")]);
+        fs::write(&path, form(Container::Wav, &[(b"fmt ", vec![1; 16]), (b"id3 ", tag_bytes("Old")), (b"data", samples()), (b"LIST", info)])).unwrap();
+        let mut tag = Tag::new();
+        tag.set_title("Kept");
+
+        rewrite_chunks(&path, &ChunkEdit { id3: Some(Some(&tag)), remove_info: vec!["ICMT".to_string()], padding: 0 }).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_form_size(Container::Wav, &data);
+        // An emptied LIST INFO chunk goes
+        assert_eq!(chunk_ids(&data), ["fmt ", "id3 ", "data"]);
+        assert_eq!(body(&data, b"data"), samples());
+        let read = Tag::read_from_path(&path).unwrap();
+        assert_eq!((read.title(), read.artist()), (Some("Kept"), None));
+    }
+
+    #[test]
+    fn aiff_gains_and_loses_an_id3_chunk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("take.aiff");
+        let original = form(Container::Aiff, &[(b"COMM", vec![2; 18]), (b"SSND", samples())]);
+        fs::write(&path, &original).unwrap();
+        let mut tag = Tag::new();
+        tag.set_title("Take 1");
+
+        rewrite_chunks(&path, &ChunkEdit { id3: Some(Some(&tag)), ..ChunkEdit::default() }).unwrap();
+        let data = fs::read(&path).unwrap();
+        assert_form_size(Container::Aiff, &data);
+        assert_eq!(chunk_ids(&data), ["COMM", "SSND", "ID3 "]);
+        assert_eq!(Tag::read_from_path(&path).unwrap().title(), Some("Take 1"));
+        assert!(read_info(&path).unwrap().is_empty());
+
+        rewrite_chunks(&path, &ChunkEdit { id3: Some(None), ..ChunkEdit::default() }).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn odd_chunks_and_trailing_bytes_survive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("odd.wav");
+        let mut original = form(Container::Wav, &[(b"fmt ", vec![1; 16]), (b"bext", vec![3; 5]), (b"data", samples())]);
+        original.extend_from_slice(b"TAG trailing");
+        fs::write(&path, &original).unwrap();
+
+        // Nothing to change: the same bytes come back
+        rewrite_chunks(&path, &ChunkEdit { id3: Some(None), ..ChunkEdit::default() }).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), original);
+    }
+
+    #[test]
+    fn other_files_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.wav");
+        fs::write(&path, b"ID3 not really a wav").unwrap();
+
+        let error = rewrite_chunks(&path, &ChunkEdit { id3: Some(None), ..ChunkEdit::default() }).unwrap_err();
+        assert_eq!(format!("{:#}", error), format!("Failed to read the chunks of {:?}: not a WAV or AIFF file", path));
+        assert_eq!(fs::read(&path).unwrap(), b"ID3 not really a wav");
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use zip::ZipArchive;
+
+use crate::entry_depth::OverdeepEntries;
+use crate::entry_paths::{plan_entry_paths, read_entry_names};
+use crate::extract::{extract_dir_paths, ExtractOptions, ZipFile};
+
+/// An archive entry that would be written to a contested path
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Claim {
+    pub archive: PathBuf,
+    /// Entry name as stored, or the entry whose parent directory this is
+    pub entry: String,
+    /// The path is a directory for this claim: a directory entry or the parent of a file
+    pub is_dir: bool,
+}
+
+/// One output path that more than one entry would write, at least one of them a file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collision {
+    pub path: PathBuf,
+    pub claims: Vec<Claim>,
+}
+
+impl Collision {
+    /// Whether entries of different archives want the path
+    pub fn across_archives(&self) -> bool {
+        self.claims.iter().any(|claim| claim.archive != self.claims[0].archive)
+    }
+}
+
+/// Every output path a run would write, checked for collisions without extracting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollisionReport {
+    pub archives: usize,
+    /// Distinct output paths, directories included
+    pub paths: usize,
+    pub collisions: Vec<Collision>,
+    /// Archives whose central directory couldn't be read, with the error
+    pub unreadable: Vec<String>,
+    /// Entries over `--max-entry-depth`, across the archives
+    pub overdeep: OverdeepEntries,
+    /// Archives with such entries
+    pub overdeep_archives: usize,
+}
+
+/// The directory `extract_zip_file` would choose for `zip_file`, without
+/// removing anything when `overwrite` is set
+fn planned_extract_dir(output_dir: &Path, zip_file: &ZipFile, overwrite: bool) -> PathBuf {
+    let (extract_dir, renamed) = extract_dir_paths(output_dir, &zip_file.output_name);
+    if overwrite || !extract_dir.is_file() { extract_dir } else { renamed }
+}
+
+/// Compute the output path of every entry of every archive that would be
+/// extracted, after name templates, normalization and flattening, and group
+/// the paths several entries would write. Paths are compared
+/// case-insensitively, as on Windows and macOS filesystems.
+pub fn find_collisions(zip_files: &[ZipFile], output_dir: &Path, options: &ExtractOptions) -> Result<CollisionReport> {
+    let mut report = CollisionReport::default();
+    let mut claims: BTreeMap<String, (PathBuf, Vec<Claim>)> = BTreeMap::new();
+
+    for zip_file in zip_files {
+        // Archives extraction would skip write nothing
+        let over_limit = matches!((options.max_entries, zip_file.entries), (Some(limit), Some(n)) if n > limit);
+        let extract_dir = planned_extract_dir(output_dir, zip_file, options.overwrite);
+        if zip_file.matching_entries == Some(0) || over_limit || (options.skip_existing && extract_dir.is_dir()) {
+            continue;
+        }
+
+        let names = fs::File::open(&zip_file.path)
+            .with_context(|| format!("Failed to open zip file {:?}", zip_file.path))
+            .and_then(|file| {
+                ZipArchive::new(file).with_context(|| format!("Failed to read zip archive {:?}", zip_file.path))
+            })
+            .and_then(|mut archive| read_entry_names(&mut archive, &zip_file.path));
+        let names = match names {
+            Ok(names) => names,
+            Err(e) => {
+                report.unreadable.push(format!("{:#}", e));
+                continue;
+            }
+        };
+        report.archives += 1;
+
+        let plan = plan_entry_paths(&names, options.flatten_depth, options.normalize_extensions.as_ref(), options.depth_limit);
+        if plan.overdeep.count() > 0 {
+            report.overdeep.merge(&plan.overdeep);
+            report.overdeep_archives += 1;
+        }
+        for (path, (entry, _)) in plan.paths.iter().zip(&names) {
+            let Some(path) = path else { continue };
+            let is_dir = path.ends_with('/');
+            let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+            // The extraction directory itself, then each parent, then the entry
+            for end in 0..=components.len() {
+                let path_is_dir = end < components.len() || is_dir;
+                let full = components[..end].iter().fold(extract_dir.clone(), |dir, c| dir.join(c));
+                let key = full.to_string_lossy().to_lowercase();
+                let (_, path_claims) = claims.entry(key).or_insert_with(|| (full, Vec::new()));
+                let duplicate = path_is_dir
+                    && path_claims.iter().any(|claim| claim.is_dir && claim.archive == zip_file.path);
+                if !duplicate {
+                    path_claims.push(Claim { archive: zip_file.path.clone(), entry: entry.clone(), is_dir: path_is_dir });
+                }
+            }
+        }
+    }
+
+    report.paths = claims.len();
+    report.collisions = claims
+        .into_values()
+        .filter(|(_, claims)| claims.len() > 1 && claims.iter().any(|claim| !claim.is_dir))
+        .map(|(path, claims)| Collision { path, claims })
+        .collect();
+    Ok(report)
+}
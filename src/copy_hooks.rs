@@ -0,0 +1,223 @@
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often a paused copy checks whether it was resumed or cancelled
+const PAUSE_POLL: Duration = Duration::from_millis(50);
+
+/// The copy was stopped by [`CopyControl::cancel`]
+#[derive(Debug)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Extraction cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Whether an I/O error is a copy stopped by [`CopyControl::cancel`]
+pub fn is_cancelled(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|e| e.is::<Cancelled>())
+}
+
+/// Caps the throughput of every copy sharing it, averaged since it was created
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: u64,
+    started: Instant,
+    consumed: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        RateLimiter { bytes_per_sec: bytes_per_sec.max(1), started: Instant::now(), consumed: AtomicU64::new(0) }
+    }
+
+    /// Account for `bytes` just copied, sleeping until they fit under the cap
+    pub fn consume(&self, bytes: u64) {
+        let consumed = self.consumed.fetch_add(bytes, Ordering::Relaxed) + bytes;
+        let due = Duration::from_secs_f64(consumed as f64 / self.bytes_per_sec as f64);
+        if let Some(wait) = due.checked_sub(self.started.elapsed()) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+/// Cancels, pauses and throttles running copies from another thread, e.g.
+/// a cancel button or Ctrl-C. Checked between chunks, so it takes effect
+/// inside large entries rather than between them.
+#[derive(Debug, Default)]
+pub struct CopyControl {
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+    limiter: Option<RateLimiter>,
+}
+
+impl CopyControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit the combined throughput of every copy using this control
+    pub fn rate_limit(mut self, bytes_per_sec: u64) -> Self {
+        self.limiter = Some(RateLimiter::new(bytes_per_sec));
+        self
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// The flag [`cancel`](Self::cancel) sets, for work that polls one
+    /// directly like [`sha256_files`](crate::fingerprint::sha256_files)
+    pub fn cancel_flag(&self) -> &AtomicBool {
+        &self.cancelled
+    }
+
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::Relaxed);
+    }
+
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// Block while paused; fail once cancelled
+    fn checkpoint(&self) -> io::Result<()> {
+        while self.is_paused() && !self.is_cancelled() {
+            thread::sleep(PAUSE_POLL);
+        }
+        if self.is_cancelled() {
+            return Err(io::Error::other(Cancelled));
+        }
+        Ok(())
+    }
+}
+
+/// Copy `reader` to `writer` one `buffer`-sized chunk at a time. Before each
+/// chunk the copy waits out a pause and stops if cancelled; after each it
+/// passes the chunk to `on_chunk` (progress, hashing) and lets the rate
+/// limiter catch up.
+pub fn copy_with_hooks(
+    reader: &mut impl Read,
+    writer: &mut impl Write,
+    buffer: &mut [u8],
+    control: Option<&CopyControl>,
+    mut on_chunk: impl FnMut(&[u8]),
+) -> io::Result<u64> {
+    let mut copied = 0;
+    loop {
+        if let Some(control) = control {
+            control.checkpoint()?;
+        }
+        let read = match reader.read(buffer) {
+            Ok(0) => return Ok(copied),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        writer.write_all(&buffer[..read])?;
+        on_chunk(&buffer[..read]);
+        copied += read as u64;
+        if let Some(limiter) = control.and_then(|c| c.limiter.as_ref()) {
+            limiter.consume(read as u64);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn copies_in_chunks() {
+        let data: Vec<u8> = (0..=255).cycle().take(10_000).collect();
+        let mut output = Vec::new();
+        let mut chunks = Vec::new();
+
+        let copied = copy_with_hooks(&mut Cursor::new(&data), &mut output, &mut [0; 4096], None, |chunk| chunks.push(chunk.len())).unwrap();
+
+        assert_eq!(copied, 10_000);
+        assert_eq!(output, data);
+        assert_eq!(chunks, [4096, 4096, 1808]);
+    }
+
+    #[test]
+    fn cancel_stops_mid_copy() {
+        let control = CopyControl::new();
+        let mut output = Vec::new();
+        let mut chunks = 0;
+
+        let error = copy_with_hooks(&mut Cursor::new(vec![0; 10_000]), &mut output, &mut [0; 1000], Some(&control), |_| {
+            chunks += 1;
+            if chunks == 3 {
+                control.cancel();
+            }
+        })
+        .unwrap_err();
+
+        assert!(is_cancelled(&error));
+        assert_eq!(error.to_string(), "Extraction cancelled");
+        assert_eq!(output.len(), 3000);
+        assert!(!is_cancelled(&io::Error::other("disk full")));
+    }
+
+    #[test]
+    fn pause_holds_the_copy_until_resumed() {
+        let control = CopyControl::new();
+        control.pause();
+        let started = Instant::now();
+
+        let copied = thread::scope(|scope| {
+            let copy = scope.spawn(|| copy_with_hooks(&mut Cursor::new(vec![1; 100]), &mut Vec::new(), &mut [0; 10], Some(&control), |_| {}));
+            thread::sleep(Duration::from_millis(200));
+            assert!(!copy.is_finished());
+            control.resume();
+            copy.join().unwrap().unwrap()
+        });
+
+        assert_eq!(copied, 100);
+        assert!(started.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn cancel_ends_a_pause() {
+        let control = CopyControl::new();
+        control.pause();
+
+        let error = thread::scope(|scope| {
+            let copy = scope.spawn(|| copy_with_hooks(&mut Cursor::new(vec![1; 100]), &mut Vec::new(), &mut [0; 10], Some(&control), |_| {}));
+            thread::sleep(Duration::from_millis(100));
+            control.cancel();
+            copy.join().unwrap().unwrap_err()
+        });
+
+        assert!(is_cancelled(&error));
+    }
+
+    #[test]
+    fn rate_limit_holds_throughput_near_the_cap() {
+        let control = CopyControl::new().rate_limit(200_000);
+        let started = Instant::now();
+
+        copy_with_hooks(&mut Cursor::new(vec![0; 100_000]), &mut Vec::new(), &mut [0; 10_000], Some(&control), |_| {}).unwrap();
+
+        // 100 kB at 200 kB/s
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(450), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1500), "{:?}", elapsed);
+    }
+}
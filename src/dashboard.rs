@@ -0,0 +1,469 @@
+use anyhow::{Context, Result};
+use indicatif::{MultiProgress, ProgressDrawTarget};
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use ratatui::crossterm::{cursor, execute};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Gauge, Paragraph, Sparkline, Wrap};
+use ratatui::{Frame, Terminal};
+use std::collections::VecDeque;
+use std::io::{self, IsTerminal, Stdout};
+use std::panic;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::copy_hooks::CopyControl;
+use crate::format::{format_size, truncate_middle};
+use crate::metrics::MetricsSnapshot;
+use crate::status_server::StatusBoard;
+
+/// Smallest terminal the dashboard is drawn in; below it `--tui` falls back
+/// to the progress bars
+pub const MIN_WIDTH: u16 = 60;
+pub const MIN_HEIGHT: u16 = 16;
+/// How often the dashboard redraws and reads keys
+const TICK: Duration = Duration::from_millis(250);
+/// Time covered by one bar of the throughput sparkline
+const SAMPLE: Duration = Duration::from_secs(1);
+/// Throughput samples kept, enough for the widest terminal
+const SAMPLES: usize = 1024;
+/// Failures the error pane shows
+const SHOWN_FAILURES: usize = 5;
+
+/// Whether the terminal is in the dashboard's raw mode and alternate screen
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+static PANIC_HOOK: Once = Once::new();
+
+/// Leave raw mode and the alternate screen and show the cursor, if the
+/// dashboard set them up; the panic hook and closing the dashboard both
+/// call it, so it only acts once
+fn restore_terminal() {
+    if ACTIVE.swap(false, Ordering::SeqCst) {
+        let _ = terminal::disable_raw_mode();
+        let _ = execute!(io::stdout(), LeaveAlternateScreen, cursor::Show);
+    }
+}
+
+fn too_small(width: u16, height: u16) -> bool {
+    width < MIN_WIDTH || height < MIN_HEIGHT
+}
+
+/// Why the dashboard can't be shown here, e.g. output redirected to a file
+pub fn unavailable() -> Option<String> {
+    if !io::stdout().is_terminal() {
+        return Some("output isn't a terminal".to_string());
+    }
+    match terminal::size() {
+        Ok((width, height)) if too_small(width, height) => {
+            Some(format!("the terminal is {}x{}, the dashboard needs {}x{}", width, height, MIN_WIDTH, MIN_HEIGHT))
+        }
+        Ok(_) => None,
+        Err(e) => Some(format!("can't read the terminal size: {}", e)),
+    }
+}
+
+/// The `--tui` full-screen view of an extraction: overall progress, the
+/// archive each worker is on, throughput and the latest failures, all read
+/// from the [`StatusBoard`] `--serve-status` serves. Keys pause, cancel and
+/// toggle the error pane. Closing it, or a panic, puts the terminal back.
+pub struct Dashboard {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Dashboard {
+    /// Take over the terminal, hiding `bars`; they come back should the
+    /// terminal shrink below the minimum size
+    pub fn start(board: Arc<StatusBoard>, control: Arc<CopyControl>, bars: MultiProgress, workers: usize) -> Result<Self> {
+        PANIC_HOOK.call_once(|| {
+            let previous = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                restore_terminal();
+                previous(info);
+            }));
+        });
+        ACTIVE.store(true, Ordering::SeqCst);
+        let terminal = terminal::enable_raw_mode()
+            .and_then(|()| execute!(io::stdout(), EnterAlternateScreen, cursor::Hide))
+            .and_then(|()| Terminal::new(CrosstermBackend::new(io::stdout())));
+        let terminal = match terminal {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                restore_terminal();
+                return Err(e).context("Failed to set up the terminal for the dashboard");
+            }
+        };
+        bars.set_draw_target(ProgressDrawTarget::hidden());
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let view = View { board, control, bars, workers, state: DashboardState::new(Instant::now()) };
+        let thread = thread::spawn({
+            let stop = stop.clone();
+            move || view.run(terminal, &stop)
+        });
+        Ok(Dashboard { stop, thread: Some(thread) })
+    }
+
+    /// Close the dashboard and put the terminal back for the summary
+    pub fn finish(self) {
+        drop(self);
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+        restore_terminal();
+    }
+}
+
+/// What the dashboard shows and how keys change it, apart from the
+/// terminal it is drawn on
+struct DashboardState {
+    /// Bytes per second, one sample per [`SAMPLE`], oldest first
+    throughput: VecDeque<u64>,
+    /// When the last sample was taken, and the bytes written by then
+    sampled: (Instant, u64),
+    archives_done: u64,
+    show_errors: bool,
+    /// `q` was pressed and waits for `y` or `n`
+    confirm_cancel: bool,
+}
+
+impl DashboardState {
+    fn new(now: Instant) -> Self {
+        DashboardState {
+            throughput: VecDeque::new(),
+            sampled: (now, 0),
+            archives_done: 0,
+            show_errors: true,
+            confirm_cancel: false,
+        }
+    }
+
+    /// Add a throughput sample once [`SAMPLE`] has passed since the last one
+    fn sample(&mut self, now: Instant, bytes_written: u64) {
+        let (at, written) = self.sampled;
+        let elapsed = now.saturating_duration_since(at);
+        if elapsed < SAMPLE {
+            return;
+        }
+        let rate = bytes_written.saturating_sub(written) as f64 / elapsed.as_secs_f64();
+        if self.throughput.len() == SAMPLES {
+            self.throughput.pop_front();
+        }
+        self.throughput.push_back(rate as u64);
+        self.sampled = (now, bytes_written);
+    }
+
+    /// The latest `shown` throughput samples, oldest first
+    fn recent_throughput(&self, shown: usize) -> Vec<u64> {
+        self.throughput.iter().skip(self.throughput.len().saturating_sub(shown)).copied().collect()
+    }
+
+    /// Whether archives finished since the last call
+    fn archives_finished(&mut self, archives_done: u64) -> bool {
+        let finished = archives_done != self.archives_done;
+        self.archives_done = archives_done;
+        finished
+    }
+
+    /// Apply `key` to the view and to `control`; true when the process
+    /// should quit at once, on Ctrl-C after the run was already cancelled
+    fn key(&mut self, key: KeyEvent, control: &CopyControl) -> bool {
+        // Raw mode turns Ctrl-C into a key: like the signal, it cancels, then quits
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            if control.is_cancelled() {
+                return true;
+            }
+            control.cancel();
+            return false;
+        }
+        if self.confirm_cancel {
+            self.confirm_cancel = false;
+            if matches!(key.code, KeyCode::Char('y' | 'Y')) {
+                control.cancel();
+            }
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('p') if control.is_paused() => control.resume(),
+            KeyCode::Char('p') => control.pause(),
+            KeyCode::Char('q') if !control.is_cancelled() => self.confirm_cancel = true,
+            KeyCode::Char('e') => self.show_errors = !self.show_errors,
+            _ => {}
+        }
+        false
+    }
+}
+
+struct View {
+    board: Arc<StatusBoard>,
+    control: Arc<CopyControl>,
+    bars: MultiProgress,
+    workers: usize,
+    state: DashboardState,
+}
+
+impl View {
+    fn run(mut self, mut terminal: Terminal<CrosstermBackend<Stdout>>, stop: &AtomicBool) {
+        while !stop.load(Ordering::Relaxed) {
+            let metrics = self.board.metrics.snapshot();
+            self.state.sample(Instant::now(), metrics.bytes_written);
+            // Notes an archive prints when it finishes, e.g. on renamed
+            // extensions, land on the dashboard; repaint over them in full
+            if self.state.archives_finished(metrics.archives_done) {
+                let _ = terminal.clear();
+            }
+            if let Err(e) = terminal.draw(|frame| self.draw(frame, &metrics)) {
+                return self.fall_back(&format!("failed to draw: {}", e));
+            }
+            match event::poll(TICK).and_then(|ready| if ready { event::read().map(Some) } else { Ok(None) }) {
+                Ok(Some(Event::Key(key))) if key.kind == KeyEventKind::Press => {
+                    if self.state.key(key, &self.control) {
+                        restore_terminal();
+                        std::process::exit(130);
+                    }
+                }
+                // Drawing adapts to any other size on the next tick
+                Ok(Some(Event::Resize(width, height))) if too_small(width, height) => {
+                    return self.fall_back(&format!("the terminal shrank to {}x{}", width, height));
+                }
+                Ok(_) => {}
+                Err(e) => return self.fall_back(&format!("failed to read keys: {}", e)),
+            }
+        }
+    }
+
+    /// Give the terminal back to the progress bars for the rest of the run.
+    /// Keys no longer reach the dashboard, so a pause is lifted; Ctrl-C
+    /// works as without `--tui`.
+    fn fall_back(&self, reason: &str) {
+        restore_terminal();
+        if self.control.is_paused() {
+            self.control.resume();
+            eprintln!("▶️  Resumed");
+        }
+        eprintln!("⚠️  Dashboard closed, {}; showing progress bars", reason);
+        self.bars.set_draw_target(ProgressDrawTarget::stderr());
+    }
+
+    fn draw(&self, frame: &mut Frame, metrics: &MetricsSnapshot) {
+        let (failed, failures) = self.board.recent_failures();
+        let errors_height = if self.state.show_errors { SHOWN_FAILURES as u16 + 2 } else { 0 };
+        let [overall, workers, throughput, errors, keys] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(6),
+            Constraint::Length(errors_height),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        let state = if self.control.is_cancelled() {
+            "Cancelling".red()
+        } else if self.control.is_paused() {
+            "Paused".yellow()
+        } else {
+            "Extracting".green()
+        };
+        let eta = metrics.eta_ms.map_or("-".to_string(), clock);
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::bordered().title(Line::from(vec![" ".into(), state, format!(" {} ", clock(metrics.elapsed_ms)).into()])))
+                .gauge_style(Style::new().fg(Color::Green))
+                .ratio(progress(metrics))
+                .label(format!(
+                    "{} / {}  ·  {}/{} archives  ·  {} files  ·  ETA {}",
+                    format_size(metrics.bytes_written),
+                    format_size(metrics.bytes_total),
+                    metrics.archives_done,
+                    metrics.archives_total,
+                    metrics.files_written,
+                    eta
+                )),
+            overall,
+        );
+
+        let running = self.board.running();
+        let rows = workers.height.saturating_sub(2) as usize;
+        let width = workers.width.saturating_sub(4) as usize;
+        let mut lines: Vec<Line> = running
+            .iter()
+            .take(if running.len() > rows { rows.saturating_sub(1) } else { rows })
+            .map(|archive| Line::from(format!("▸ {}", truncate_middle(&file_name(archive), width))))
+            .collect();
+        if running.len() > lines.len() {
+            lines.push(Line::from(format!("  and {} more", running.len() - lines.len())).dark_gray());
+        }
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(format!(" Workers {}/{} ", running.len(), self.workers))),
+            workers,
+        );
+
+        let shown = throughput.width.saturating_sub(2) as usize;
+        let samples = self.state.recent_throughput(shown);
+        let current = samples.last().copied().unwrap_or(0);
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(format!(
+                    " Throughput {}/s, average {}/s ",
+                    format_size(current),
+                    format_size(metrics.bytes_per_sec as u64)
+                )))
+                .style(Style::new().fg(Color::Cyan))
+                .data(&samples),
+            throughput,
+        );
+
+        if self.state.show_errors {
+            let title = format!(" Errors: {} ", failed);
+            let lines: Vec<Line> = failures
+                .iter()
+                .rev()
+                .take(SHOWN_FAILURES)
+                .rev()
+                .map(|(archive, error)| Line::from(format!("{}: {}", file_name(archive), error)))
+                .collect();
+            let block = Block::bordered().title(if failed > 0 { title.red() } else { title.into() });
+            frame.render_widget(Paragraph::new(lines).block(block), errors);
+        }
+
+        let pause = if self.control.is_paused() { "resume" } else { "pause" };
+        let errors_key = if self.state.show_errors { "hide errors" } else { "show errors" };
+        let mut footer = vec![format!(" [p] {}  [q] cancel  [e] {}", pause, errors_key).into()];
+        if !self.state.show_errors && failed > 0 {
+            footer.push(format!("  {} errors", failed).red());
+        }
+        frame.render_widget(Paragraph::new(Line::from(footer)).dark_gray(), keys);
+
+        if self.state.confirm_cancel {
+            let area = centered(frame.area(), 50, 5);
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new("Cancel the run? Archives being extracted are reported as failed.  [y/n]")
+                    .wrap(Wrap { trim: true })
+                    .block(Block::bordered().title(" Cancel ").yellow()),
+                area,
+            );
+        }
+    }
+}
+
+/// Share of the bytes written, full when there is nothing to write
+fn progress(metrics: &MetricsSnapshot) -> f64 {
+    if metrics.bytes_total == 0 {
+        1.0
+    } else {
+        (metrics.bytes_written as f64 / metrics.bytes_total as f64).min(1.0)
+    }
+}
+
+fn file_name(archive: &str) -> String {
+    Path::new(archive).file_name().map_or(archive.to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+/// Milliseconds as `HH:MM:SS`
+fn clock(ms: u64) -> String {
+    let seconds = ms / 1000;
+    format!("{:02}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// A `width` by `height` area in the middle of `area`, clipped to it
+fn centered(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect::new(area.x + (area.width - width) / 2, area.y + (area.height - height) / 2, width, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn throughput_is_sampled_once_a_second_and_capped() {
+        let start = Instant::now();
+        let mut state = DashboardState::new(start);
+        state.sample(start + Duration::from_millis(500), 1000);
+        assert!(state.throughput.is_empty());
+        state.sample(start + Duration::from_secs(2), 4000);
+        state.sample(start + Duration::from_secs(3), 5000);
+        assert_eq!(state.recent_throughput(10), [2000, 1000]);
+        assert_eq!(state.recent_throughput(1), [1000]);
+
+        for second in 4..SAMPLES as u64 + 10 {
+            state.sample(start + Duration::from_secs(second), 5000 + second);
+        }
+        assert_eq!(state.throughput.len(), SAMPLES);
+        assert_eq!(state.throughput.back(), Some(&1));
+    }
+
+    #[test]
+    fn finished_archives_are_noticed_once() {
+        let mut state = DashboardState::new(Instant::now());
+        assert!(!state.archives_finished(0));
+        assert!(state.archives_finished(2));
+        assert!(!state.archives_finished(2));
+    }
+
+    #[test]
+    fn keys_pause_toggle_errors_and_confirm_cancelling() {
+        let control = CopyControl::new();
+        let mut state = DashboardState::new(Instant::now());
+
+        assert!(!state.key(press(KeyCode::Char('p')), &control));
+        assert!(control.is_paused());
+        state.key(press(KeyCode::Char('p')), &control);
+        assert!(!control.is_paused());
+
+        state.key(press(KeyCode::Char('e')), &control);
+        assert!(!state.show_errors);
+        state.key(press(KeyCode::Char('e')), &control);
+        assert!(state.show_errors);
+
+        // Anything but `y` dismisses the question, and the next key acts as usual
+        state.key(press(KeyCode::Char('q')), &control);
+        assert!(state.confirm_cancel);
+        state.key(press(KeyCode::Char('e')), &control);
+        assert!(!state.confirm_cancel && state.show_errors && !control.is_cancelled());
+        state.key(press(KeyCode::Char('q')), &control);
+        state.key(press(KeyCode::Char('y')), &control);
+        assert!(control.is_cancelled() && !state.confirm_cancel);
+        // Nothing left to confirm
+        state.key(press(KeyCode::Char('q')), &control);
+        assert!(!state.confirm_cancel);
+    }
+
+    #[test]
+    fn ctrl_c_cancels_then_quits() {
+        let control = CopyControl::new();
+        let mut state = DashboardState::new(Instant::now());
+        let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+        assert!(!state.key(ctrl_c, &control));
+        assert!(control.is_cancelled());
+        assert!(state.key(ctrl_c, &control));
+    }
+
+    #[test]
+    fn progress_is_full_with_nothing_to_write() {
+        let metrics = |bytes_written, bytes_total| MetricsSnapshot { bytes_written, bytes_total, ..MetricsSnapshot::default() };
+        assert_eq!(progress(&metrics(0, 0)), 1.0);
+        assert_eq!(progress(&metrics(25, 100)), 0.25);
+        // Totals can lag what was written
+        assert_eq!(progress(&metrics(150, 100)), 1.0);
+        assert_eq!(clock(3_725_000), "01:02:05");
+    }
+}
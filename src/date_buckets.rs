@@ -0,0 +1,137 @@
+use chrono::{Datelike, Local, NaiveDate, TimeZone};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use zip::ZipArchive;
+
+/// Which date `--split-output-by-date` files an archive's directory under
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DateSource {
+    /// The archive's modification time, in local time
+    #[default]
+    Archive,
+    /// The newest entry timestamp inside it, falling back to the archive's
+    /// modification time when it has no valid one
+    Entries,
+}
+
+/// The `YYYY/MM` directory an archive modified at `mtime` (seconds since the
+/// Unix epoch) is extracted under
+pub fn date_bucket(path: &Path, mtime: i64, source: DateSource) -> String {
+    let date = match source {
+        DateSource::Archive => None,
+        DateSource::Entries => newest_entry_date(path),
+    };
+    let date = date.unwrap_or_else(|| local_date(mtime));
+    format!("{:04}/{:02}", date.year(), date.month())
+}
+
+/// The local calendar date of `mtime`. A time a DST change repeats takes
+/// its earlier reading, which always falls on the same date.
+fn local_date(mtime: i64) -> NaiveDate {
+    Local
+        .timestamp_opt(mtime, 0)
+        .earliest()
+        .map(|time| time.date_naive())
+        .unwrap_or_default()
+}
+
+/// Entry timestamps are local wall-clock times already, so their date is
+/// taken as written rather than converted. Fields out of range, as written by
+/// tools that store a zero date, are ignored.
+fn newest_entry_date(path: &Path) -> Option<NaiveDate> {
+    let file = fs::File::open(path).ok()?;
+    let mut archive = ZipArchive::new(BufReader::new(file)).ok()?;
+    (0..archive.len())
+        .filter_map(|i| {
+            let time = archive.by_index_raw(i).ok()?.last_modified();
+            NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+                .and_hms_opt(time.hour() as u32, time.minute() as u32, time.second() as u32)
+        })
+        .max()
+        .map(|time| time.date())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::io::{Cursor, Write};
+    use std::process::Command;
+    use zip::write::FileOptions;
+    use zip::{DateTime, ZipWriter};
+
+    /// US Eastern time, with the 2007 DST rules, as a POSIX TZ string so
+    /// no zoneinfo database is needed
+    const EASTERN: &str = "EST5EDT,M3.2.0,M11.1.0";
+
+    fn utc(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap().timestamp()
+    }
+
+    /// Local time is read from `TZ` once per process, so the checks run in
+    /// a child process of this test binary with it fixed to [`EASTERN`]
+    #[test]
+    fn archive_dates_are_local_across_dst_changes() {
+        if std::env::var("TZ").as_deref() != Ok(EASTERN) {
+            let child = Command::new(std::env::current_exe().unwrap())
+                .args(["date_buckets::tests::archive_dates_are_local_across_dst_changes", "--exact", "--nocapture"])
+                .env("TZ", EASTERN)
+                .output()
+                .unwrap();
+            assert!(child.status.success(), "{}", String::from_utf8_lossy(&child.stderr));
+            assert!(String::from_utf8_lossy(&child.stdout).contains("1 passed"));
+            return;
+        }
+
+        let bucket = |mtime| date_bucket(Path::new("missing.zip"), mtime, DateSource::Archive);
+        // 23:30 EDT on 31 March is already April in UTC
+        assert_eq!(bucket(utc(2024, 4, 1, 3, 30)), "2024/03");
+        assert_eq!(bucket(utc(2024, 4, 1, 4, 0)), "2024/04");
+        // 23:30 EST on New Year's Eve
+        assert_eq!(bucket(utc(2024, 1, 1, 4, 30)), "2023/12");
+        // Just after clocks go forward at 02:00 on 10 March and both
+        // readings of the repeated hour on 3 November
+        assert_eq!(bucket(utc(2024, 3, 10, 7, 1)), "2024/03");
+        assert_eq!(bucket(utc(2024, 11, 3, 5, 30)), "2024/11");
+        assert_eq!(bucket(utc(2024, 11, 3, 6, 30)), "2024/11");
+        // 23:30 EDT on 31 October, the night DST is about to end
+        assert_eq!(bucket(utc(2024, 11, 1, 3, 30)), "2024/10");
+        assert_eq!(local_date(utc(2024, 11, 3, 6, 30)), NaiveDate::from_ymd_opt(2024, 11, 3).unwrap());
+    }
+
+    fn write_zip(path: &Path, times: &[DateTime]) {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (i, time) in times.iter().enumerate() {
+            zip.start_file(format!("{}.jpg", i), FileOptions::default().last_modified_time(*time)).unwrap();
+            zip.write_all(b"jpeg").unwrap();
+        }
+        fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    }
+
+    #[test]
+    fn entry_dates_are_taken_as_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("camera.zip");
+        let time = |year, month, day, hour| DateTime::from_date_and_time(year, month, day, hour, 59, 58).unwrap();
+        write_zip(&path, &[time(2023, 12, 31, 23), time(2024, 3, 31, 23), time(2022, 6, 1, 12)]);
+
+        // The newest entry, not the archive's time or the time zone
+        assert_eq!(date_bucket(&path, utc(2025, 7, 1, 12, 0), DateSource::Entries), "2024/03");
+        assert_eq!(date_bucket(&path, utc(2025, 7, 1, 12, 0), DateSource::Archive), "2025/07");
+    }
+
+    #[test]
+    fn entries_without_a_valid_date_fall_back_to_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("zeroed.zip");
+        let zeroed = DateTime::from_msdos(0, 0);
+        write_zip(&path, &[zeroed]);
+
+        assert_eq!(date_bucket(&path, utc(2025, 7, 15, 12, 0), DateSource::Entries), "2025/07");
+        assert_eq!(date_bucket(&dir.path().join("missing.zip"), utc(2025, 7, 15, 12, 0), DateSource::Entries), "2025/07");
+    }
+}
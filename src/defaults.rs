@@ -0,0 +1,183 @@
+//! Defaults a packager can change without patching the source: set any of
+//! the `BULK_UNZIP_DEFAULT_*` variables below when building, e.g.
+//! `BULK_UNZIP_DEFAULT_WORKERS=8 cargo build --release`. A value that
+//! doesn't parse fails the build rather than being ignored.
+
+use serde::Serialize;
+
+/// The defaults of the CLI and the desktop app, after any build-time overrides
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Defaults {
+    /// `BULK_UNZIP_DEFAULT_WORKERS`: workers without `--workers` or an I/O profile; 4
+    pub workers: usize,
+    /// `BULK_UNZIP_DEFAULT_IO_PROFILE`: ssd, hdd, network or auto; none, which uses `workers`
+    pub io_profile: Option<&'static str>,
+    /// `BULK_UNZIP_DEFAULT_STABILIZE_TIMEOUT`: seconds to wait for archives still being written; 10
+    pub stabilize_timeout_secs: u64,
+    /// `BULK_UNZIP_DEFAULT_MAX_ENTRIES`: archives with more entries are refused; 1000000
+    pub max_entries: usize,
+    /// `BULK_UNZIP_DEFAULT_MIN_SIZE`: bytes below which audio files are left alone; 4096
+    pub min_size: u64,
+    /// `BULK_UNZIP_DEFAULT_SKIP_EXISTING`: skip archives whose directory exists; false
+    pub skip_existing: bool,
+    /// `BULK_UNZIP_DEFAULT_SKIP_CLEAN`: skip files stripping wouldn't change; false
+    pub skip_clean: bool,
+    /// `BULK_UNZIP_DEFAULT_BACKUP`: keep a `.bak` copy when the app removes hand-picked frames; false
+    pub backup: bool,
+}
+
+pub const DEFAULTS: Defaults = Defaults {
+    workers: number(option_env!("BULK_UNZIP_DEFAULT_WORKERS"), 4) as usize,
+    io_profile: io_profile(option_env!("BULK_UNZIP_DEFAULT_IO_PROFILE")),
+    stabilize_timeout_secs: number(option_env!("BULK_UNZIP_DEFAULT_STABILIZE_TIMEOUT"), 10),
+    max_entries: number(option_env!("BULK_UNZIP_DEFAULT_MAX_ENTRIES"), 1_000_000) as usize,
+    min_size: number(option_env!("BULK_UNZIP_DEFAULT_MIN_SIZE"), 4 * 1024),
+    skip_existing: flag(option_env!("BULK_UNZIP_DEFAULT_SKIP_EXISTING"), false),
+    skip_clean: flag(option_env!("BULK_UNZIP_DEFAULT_SKIP_CLEAN"), false),
+    backup: flag(option_env!("BULK_UNZIP_DEFAULT_BACKUP"), false),
+};
+
+/// The variables set for this build, for `--version --verbose`
+pub fn overrides() -> Vec<(&'static str, &'static str)> {
+    [
+        ("BULK_UNZIP_DEFAULT_WORKERS", option_env!("BULK_UNZIP_DEFAULT_WORKERS")),
+        ("BULK_UNZIP_DEFAULT_IO_PROFILE", option_env!("BULK_UNZIP_DEFAULT_IO_PROFILE")),
+        ("BULK_UNZIP_DEFAULT_STABILIZE_TIMEOUT", option_env!("BULK_UNZIP_DEFAULT_STABILIZE_TIMEOUT")),
+        ("BULK_UNZIP_DEFAULT_MAX_ENTRIES", option_env!("BULK_UNZIP_DEFAULT_MAX_ENTRIES")),
+        ("BULK_UNZIP_DEFAULT_MIN_SIZE", option_env!("BULK_UNZIP_DEFAULT_MIN_SIZE")),
+        ("BULK_UNZIP_DEFAULT_SKIP_EXISTING", option_env!("BULK_UNZIP_DEFAULT_SKIP_EXISTING")),
+        ("BULK_UNZIP_DEFAULT_SKIP_CLEAN", option_env!("BULK_UNZIP_DEFAULT_SKIP_CLEAN")),
+        ("BULK_UNZIP_DEFAULT_BACKUP", option_env!("BULK_UNZIP_DEFAULT_BACKUP")),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| Some((name, value?)))
+    .collect()
+}
+
+const fn number(value: Option<&str>, fallback: u64) -> u64 {
+    let Some(value) = value else {
+        return fallback;
+    };
+    let bytes = value.as_bytes();
+    assert!(!bytes.is_empty(), "a BULK_UNZIP_DEFAULT_* number is empty");
+    let mut n: u64 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        assert!(bytes[i].is_ascii_digit(), "a BULK_UNZIP_DEFAULT_* number isn't a whole number");
+        n = n * 10 + (bytes[i] - b'0') as u64;
+        i += 1;
+    }
+    n
+}
+
+const fn flag(value: Option<&str>, fallback: bool) -> bool {
+    match value {
+        None => fallback,
+        Some(value) if same(value, "true") || same(value, "1") => true,
+        Some(value) if same(value, "false") || same(value, "0") => false,
+        Some(_) => panic!("a BULK_UNZIP_DEFAULT_* flag isn't true, false, 1 or 0"),
+    }
+}
+
+const fn io_profile(value: Option<&'static str>) -> Option<&'static str> {
+    match value {
+        Some(value) if same(value, "ssd") || same(value, "hdd") || same(value, "network") || same(value, "auto") => Some(value),
+        None => None,
+        Some(_) => panic!("BULK_UNZIP_DEFAULT_IO_PROFILE isn't ssd, hdd, network or auto"),
+    }
+}
+
+const fn same(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn values_parse_at_compile_time() {
+        assert_eq!(number(None, 4), 4);
+        assert_eq!(number(Some("16"), 4), 16);
+        assert!(flag(Some("1"), false));
+        assert!(!flag(Some("false"), true));
+        assert!(flag(None, true));
+        assert_eq!(io_profile(Some("hdd")), Some("hdd"));
+        assert_eq!(io_profile(None), None);
+        assert!(same("auto", "auto"));
+        assert!(!same("auto", "autos"));
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't a whole number")]
+    fn numbers_must_be_whole() {
+        number(Some("4.5"), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "isn't true, false, 1 or 0")]
+    fn flags_must_be_boolean() {
+        flag(Some("yes"), false);
+    }
+
+    #[test]
+    fn unset_variables_keep_the_documented_defaults() {
+        if !overrides().is_empty() {
+            return;
+        }
+        assert_eq!(DEFAULTS.workers, 4);
+        assert_eq!(DEFAULTS.io_profile, None);
+        assert_eq!(DEFAULTS.stabilize_timeout_secs, 10);
+        assert_eq!(DEFAULTS.max_entries, 1_000_000);
+        assert_eq!(DEFAULTS.min_size, 4096);
+        assert_eq!((DEFAULTS.skip_existing, DEFAULTS.skip_clean, DEFAULTS.backup), (false, false, false));
+    }
+
+    /// Rebuilds the CLI with overrides set, twice over the normal build;
+    /// run with `cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn overrides_show_in_the_cli_help() {
+        let help = |command: &str| {
+            let output = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+                .current_dir(env!("CARGO_MANIFEST_DIR"))
+                .args(["run", "--quiet", "--bin", "bulk_unzip", "--", command, "--help"])
+                .env("BULK_UNZIP_DEFAULT_STABILIZE_TIMEOUT", "30")
+                .env("BULK_UNZIP_DEFAULT_MAX_ENTRIES", "5000")
+                .env("BULK_UNZIP_DEFAULT_SKIP_EXISTING", "true")
+                .env("BULK_UNZIP_DEFAULT_MIN_SIZE", "1000")
+                .env("BULK_UNZIP_DEFAULT_SKIP_CLEAN", "1")
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+            String::from_utf8(output.stdout).unwrap()
+        };
+        // The default following each option's help
+        let default_of = |help: &str, option: &str| {
+            let start = help.find(option).unwrap_or_else(|| panic!("no {} in the help", option));
+            let rest = &help[start..];
+            let at = rest.find("[default: ").unwrap() + "[default: ".len();
+            rest[at..rest[at..].find(']').unwrap() + at].to_string()
+        };
+
+        let unzip = help("unzip");
+        assert_eq!(default_of(&unzip, "--skip-existing"), "true");
+        assert_eq!(default_of(&unzip, "--stabilize-timeout"), "30");
+        assert_eq!(default_of(&unzip, "--max-entries"), "5000");
+        let strip = help("strip");
+        assert_eq!(default_of(&strip, "--skip-clean"), "true");
+        assert_eq!(default_of(&strip, "--min-size"), "1000");
+    }
+}
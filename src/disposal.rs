@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+/// How a run removes files the user may want back: originals of stripped
+/// copies, files an update no longer extracts, files an entry hook failed
+/// and files in the way of an archive's directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Disposal {
+    /// Delete them for good
+    #[default]
+    Delete,
+    /// Move them to the system trash. A file that can't go there, e.g. on a
+    /// network share, is kept and its removal fails; it is never deleted instead.
+    Trash,
+}
+
+impl Disposal {
+    pub fn disposer(self) -> Arc<dyn Disposer> {
+        match self {
+            Disposal::Delete => Arc::new(PermanentDelete),
+            Disposal::Trash => Arc::new(SystemTrash),
+        }
+    }
+}
+
+/// Removes one of the user's files; every code path that does goes through
+/// the run's disposer
+pub trait Disposer: fmt::Debug + Send + Sync {
+    fn dispose(&self, path: &Path) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PermanentDelete;
+
+impl Disposer for PermanentDelete {
+    fn dispose(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path).with_context(|| format!("Failed to delete {:?}", path))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemTrash;
+
+impl Disposer for SystemTrash {
+    fn dispose(&self, path: &Path) -> Result<()> {
+        trash::delete(path).with_context(|| format!("Failed to move {:?} to the trash", path))
+    }
+}
+
+/// Deletes what it's given and records it, or with `fail` refuses as the
+/// trash can, so tests can check removals go through the run's disposer
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub(crate) struct RecordingDisposer {
+    pub disposed: std::sync::Mutex<Vec<std::path::PathBuf>>,
+    pub fail: bool,
+}
+
+#[cfg(test)]
+impl RecordingDisposer {
+    pub fn failing() -> Self {
+        RecordingDisposer { fail: true, ..Self::default() }
+    }
+
+    pub fn disposed(&self) -> Vec<std::path::PathBuf> {
+        self.disposed.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+impl Disposer for RecordingDisposer {
+    fn dispose(&self, path: &Path) -> Result<()> {
+        self.disposed.lock().unwrap().push(path.to_path_buf());
+        if self.fail {
+            anyhow::bail!("Failed to move {:?} to the trash", path);
+        }
+        PermanentDelete.dispose(path)
+    }
+}
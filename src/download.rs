@@ -0,0 +1,232 @@
+use indicatif::{ProgressBar, ProgressStyle};
+use reqwest::header::RANGE;
+use reqwest::redirect::Policy;
+use reqwest::{Client, StatusCode};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::format::format_size;
+
+/// Give up on a download after this many interrupted attempts
+const MAX_ATTEMPTS: u32 = 5;
+
+/// Base delay between attempts, multiplied by the attempt number
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+const MAX_REDIRECTS: usize = 10;
+
+/// Where a single archive to extract comes from instead of a directory scan
+#[derive(Debug, Clone)]
+pub enum DownloadSource {
+    Url(String),
+    Stdin,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadArgs {
+    pub source: DownloadSource,
+    /// Sent as `Authorization: Bearer <token>`
+    pub bearer_token: Option<String>,
+    /// Extra request headers as (name, value)
+    pub headers: Vec<(String, String)>,
+}
+
+/// Failure fetching the archive, kept apart from extraction errors so callers
+/// can tell a bad network from a bad zip
+#[derive(Debug)]
+pub enum DownloadError {
+    /// Connection, redirect or body read failure, after retrying
+    Network(reqwest::Error),
+    /// The server answered with a non-success status
+    Status(StatusCode),
+    /// Spooling the archive to disk failed
+    Io(io::Error),
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DownloadError::Network(e) => write!(f, "Download failed: {}", e),
+            DownloadError::Status(status) => write!(f, "Download failed: server returned {}", status),
+            DownloadError::Io(e) => write!(f, "Failed to save download: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DownloadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DownloadError::Network(e) => Some(e),
+            DownloadError::Status(_) => None,
+            DownloadError::Io(e) => Some(e),
+        }
+    }
+}
+
+impl From<reqwest::Error> for DownloadError {
+    fn from(error: reqwest::Error) -> Self {
+        DownloadError::Network(error)
+    }
+}
+
+impl From<io::Error> for DownloadError {
+    fn from(error: io::Error) -> Self {
+        DownloadError::Io(error)
+    }
+}
+
+/// Parse a `--header "Name: value"` argument
+pub fn parse_header(header: &str) -> Result<(String, String), String> {
+    match header.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => Err(format!("Expected \"Name: value\", got {:?}", header)),
+    }
+}
+
+/// File name for the spooled archive: the last URL path segment, with a
+/// `.zip` extension so the scan picks it up
+fn archive_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let segment = path
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .filter(|s| !s.is_empty() && !s.contains(':'))
+        .unwrap_or("download");
+    let stem = match segment.len().checked_sub(4) {
+        Some(i) if segment.is_char_boundary(i) && segment[i..].eq_ignore_ascii_case(".zip") => &segment[..i],
+        _ => segment,
+    };
+    format!("{}.zip", stem)
+}
+
+/// Spool the archive into `dir` and return its path. Zip needs a seekable
+/// file to read the central directory, so nothing is extracted until the
+/// whole body has arrived.
+pub async fn fetch(args: &DownloadArgs, dir: &Path) -> Result<PathBuf, DownloadError> {
+    match &args.source {
+        DownloadSource::Url(url) => {
+            let path = dir.join(archive_name(url));
+            fetch_url(url, args, &path).await?;
+            Ok(path)
+        }
+        DownloadSource::Stdin => {
+            let path = dir.join("stdin.zip");
+            spool_stdin(&path)?;
+            Ok(path)
+        }
+    }
+}
+
+fn download_bar() -> ProgressBar {
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes:>10}/{total_bytes:10} {binary_bytes_per_sec} {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    bar.set_message("Downloading");
+    bar
+}
+
+/// Download `url` to `path`. Interrupted transfers are retried and resumed
+/// with a Range request; a server that ignores the range is downloaded again
+/// from the start.
+async fn fetch_url(url: &str, args: &DownloadArgs, path: &Path) -> Result<(), DownloadError> {
+    let client = Client::builder().redirect(Policy::limited(MAX_REDIRECTS)).build()?;
+    let mut file = fs::File::create(path)?;
+    let bar = download_bar();
+    let mut written = 0;
+
+    let mut attempt = 1;
+    loop {
+        match fetch_attempt(&client, url, args, &mut file, &mut written, &bar).await {
+            Ok(()) => break,
+            Err(DownloadError::Network(e)) if attempt < MAX_ATTEMPTS && !e.is_builder() => {
+                bar.suspend(|| eprintln!("⚠️  Download interrupted ({}), retrying from {}", e, format_size(written)));
+                tokio::time::sleep(RETRY_DELAY * attempt).await;
+                attempt += 1;
+            }
+            Err(e) => {
+                bar.abandon();
+                return Err(e);
+            }
+        }
+    }
+
+    file.flush()?;
+    bar.finish_with_message(format!("Downloaded {}", format_size(written)));
+    Ok(())
+}
+
+async fn fetch_attempt(
+    client: &Client,
+    url: &str,
+    args: &DownloadArgs,
+    file: &mut fs::File,
+    written: &mut u64,
+    bar: &ProgressBar,
+) -> Result<(), DownloadError> {
+    let mut request = client.get(url);
+    if let Some(token) = &args.bearer_token {
+        request = request.bearer_auth(token);
+    }
+    for (name, value) in &args.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    if *written > 0 {
+        request = request.header(RANGE, format!("bytes={}-", written));
+    }
+
+    let mut response = request.send().await?;
+    let status = response.status();
+    if !status.is_success() {
+        return Err(DownloadError::Status(status));
+    }
+    if *written > 0 && status != StatusCode::PARTIAL_CONTENT {
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        *written = 0;
+    }
+    if let Some(remaining) = response.content_length() {
+        bar.set_length(*written + remaining);
+    }
+    bar.set_position(*written);
+
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        *written += chunk.len() as u64;
+        bar.set_position(*written);
+    }
+    Ok(())
+}
+
+/// Copy standard input to `path`, showing how much has arrived
+fn spool_stdin(path: &Path) -> Result<(), DownloadError> {
+    let mut file = fs::File::create(path)?;
+    let bar = download_bar();
+    bar.set_style(ProgressStyle::default_spinner().template("{spinner} {bytes} {binary_bytes_per_sec} {msg}").unwrap());
+    bar.set_message("Reading from stdin");
+    bar.enable_steady_tick(Duration::from_millis(100));
+
+    let mut stdin = io::stdin().lock();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = match stdin.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        file.write_all(&buffer[..read])?;
+        bar.inc(read as u64);
+    }
+
+    file.flush()?;
+    bar.finish_with_message("Read from stdin");
+    Ok(())
+}
@@ -0,0 +1,233 @@
+use anyhow::{Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+
+use crate::extract::ZipFile;
+use crate::fingerprint::sha256_files;
+use crate::metadata_stripper::{audio_region, Mp3File};
+use crate::schemas::versioned;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DuplicateFile {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// Files whose audio frames hash identically, regardless of their tags
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub audio_bytes: u64,
+    pub files: Vec<DuplicateFile>,
+}
+
+/// A file left out of the comparison because it couldn't be read
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UnreadableFile {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DuplicateReport {
+    pub scanned: usize,
+    pub groups: Vec<DuplicateGroup>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unreadable: Vec<UnreadableFile>,
+}
+
+pub fn hash_audio(path: &Path) -> Result<(String, u64)> {
+    let region = audio_region(path)?;
+    let audio_bytes = region.end - region.start;
+
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    file.seek(SeekFrom::Start(region.start))?;
+
+    let mut hasher = Sha256::new();
+    let mut reader = file.take(audio_bytes);
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = reader.read(&mut buffer)
+            .with_context(|| format!("Failed to read audio from {:?}", path))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    let hash = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+    Ok((hash, audio_bytes))
+}
+
+/// Group files with identical audio content. Files are first bucketed by
+/// audio length so only candidates of the same size are hashed. A file that
+/// can't be read is listed as unreadable and left out.
+pub fn find_duplicates(mp3_files: &[Mp3File]) -> DuplicateReport {
+    let mut unreadable = Vec::new();
+    let mut by_length: HashMap<u64, Vec<&Mp3File>> = HashMap::new();
+    for mp3_file in mp3_files {
+        match audio_region(&mp3_file.path) {
+            Ok(region) => by_length.entry(region.end - region.start).or_default().push(mp3_file),
+            Err(e) => unreadable.push(UnreadableFile { path: mp3_file.path.clone(), error: format!("{:#}", e) }),
+        }
+    }
+
+    let candidates: Vec<&Mp3File> = by_length
+        .into_values()
+        .filter(|files| files.len() > 1)
+        .flatten()
+        .collect();
+
+    let hashed: Vec<(&Mp3File, Result<(String, u64)>)> = candidates
+        .par_iter()
+        .map(|mp3_file| (*mp3_file, hash_audio(&mp3_file.path)))
+        .collect();
+
+    let mut by_hash: HashMap<String, DuplicateGroup> = HashMap::new();
+    for (mp3_file, hashed) in hashed {
+        let (hash, audio_bytes) = match hashed {
+            Ok(hashed) => hashed,
+            Err(e) => {
+                unreadable.push(UnreadableFile { path: mp3_file.path.clone(), error: format!("{:#}", e) });
+                continue;
+            }
+        };
+        let file = DuplicateFile { path: mp3_file.path.clone(), size: mp3_file.size };
+        by_hash
+            .entry(hash.clone())
+            .or_insert_with(|| DuplicateGroup { hash, audio_bytes, files: Vec::new() })
+            .files
+            .push(file);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_hash
+        .into_values()
+        .filter(|group| group.files.len() > 1)
+        .collect();
+    for group in &mut groups {
+        group.files.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+    groups.sort_by(|a, b| a.files[0].path.cmp(&b.files[0].path));
+    unreadable.sort_by(|a, b| a.path.cmp(&b.path));
+
+    DuplicateReport { scanned: mp3_files.len(), groups, unreadable }
+}
+
+/// Byte-identical archives: `kept` is the first in extraction order and is
+/// the only one extracted
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ArchiveDuplicates {
+    pub sha256: String,
+    pub size: u64,
+    pub kept: PathBuf,
+    pub skipped: Vec<PathBuf>,
+}
+
+/// Indices of archives sharing their size with another, the only ones that
+/// can be duplicates
+fn same_size(zip_files: &[ZipFile]) -> Vec<usize> {
+    let mut by_size: HashMap<u64, usize> = HashMap::new();
+    for zip_file in zip_files {
+        *by_size.entry(zip_file.size).or_default() += 1;
+    }
+    (0..zip_files.len()).filter(|&i| by_size[&zip_files[i].size] > 1).collect()
+}
+
+/// Bytes [`split_duplicate_archives`] will hash
+pub fn duplicate_candidate_bytes(zip_files: &[ZipFile]) -> u64 {
+    same_size(zip_files).into_iter().map(|i| zip_files[i].size).sum()
+}
+
+/// Separate archives identical to an earlier one in `zip_files` from those to
+/// extract, which keep their order. Only archives sharing a size are hashed,
+/// in parallel, filling in their `sha256`; `cancel` stops the hashing with an
+/// error.
+pub fn split_duplicate_archives(
+    mut zip_files: Vec<ZipFile>,
+    cancel: &AtomicBool,
+    on_read: impl Fn(u64) + Sync,
+) -> Result<(Vec<ZipFile>, Vec<ArchiveDuplicates>)> {
+    let candidates = same_size(&zip_files);
+    let paths: Vec<PathBuf> = candidates.iter().map(|&i| zip_files[i].path.clone()).collect();
+    let hashes = sha256_files(&paths, cancel, on_read)?;
+    for (&i, hash) in candidates.iter().zip(hashes) {
+        zip_files[i].sha256 = Some(hash);
+    }
+
+    let mut groups: Vec<ArchiveDuplicates> = Vec::new();
+    let mut group_of: HashMap<(u64, String), usize> = HashMap::new();
+    let mut kept = Vec::with_capacity(zip_files.len());
+    for zip_file in zip_files {
+        let Some(hash) = zip_file.sha256.clone() else {
+            kept.push(zip_file);
+            continue;
+        };
+        match group_of.get(&(zip_file.size, hash.clone())) {
+            Some(&group) => groups[group].skipped.push(zip_file.path),
+            None => {
+                group_of.insert((zip_file.size, hash.clone()), groups.len());
+                groups.push(ArchiveDuplicates { sha256: hash, size: zip_file.size, kept: zip_file.path.clone(), skipped: Vec::new() });
+                kept.push(zip_file);
+            }
+        }
+    }
+    groups.retain(|group| !group.skipped.is_empty());
+    Ok((kept, groups))
+}
+
+pub fn write_report(report: &DuplicateReport, path: &Path) -> Result<()> {
+    let json = serde_json::to_string_pretty(&versioned(report))?;
+    fs::write(path, json)
+        .with_context(|| format!("Failed to write duplicate report to {:?}", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_stripper::TagInfo;
+    use crate::sniff::AudioFormat;
+
+    fn mp3_file(path: PathBuf) -> Mp3File {
+        Mp3File {
+            size: fs::metadata(&path).map_or(0, |metadata| metadata.len()),
+            path,
+            has_metadata: false,
+            tags: TagInfo::default(),
+            kind: AudioFormat::Mp3,
+            format: Default::default(),
+            tag_version: None,
+            tag_size_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn unreadable_files_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = [0xFF, 0xFB, 0x90, 0x00].repeat(300);
+        fs::write(dir.path().join("a.mp3"), &audio).unwrap();
+        fs::write(dir.path().join("b.mp3"), &audio).unwrap();
+        // A directory can be opened but not read
+        fs::create_dir(dir.path().join("c.mp3")).unwrap();
+        let files: Vec<Mp3File> = ["a.mp3", "b.mp3", "c.mp3", "gone.mp3"]
+            .iter()
+            .map(|name| mp3_file(dir.path().join(name)))
+            .collect();
+
+        let report = find_duplicates(&files);
+
+        assert_eq!(report.scanned, 4);
+        assert_eq!(report.groups.len(), 1);
+        let paths: Vec<&Path> = report.groups[0].files.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(paths, [dir.path().join("a.mp3"), dir.path().join("b.mp3")]);
+        let unreadable: Vec<&Path> = report.unreadable.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(unreadable, [dir.path().join("c.mp3"), dir.path().join("gone.mp3")]);
+        assert!(report.unreadable.iter().all(|file| !file.error.is_empty()));
+    }
+}
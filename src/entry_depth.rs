@@ -0,0 +1,157 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::long_names::short_hash;
+
+/// `--max-entry-depth`: the most path components an entry may have, its
+/// file name included, and what becomes of deeper entries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct DepthLimit {
+    pub max_depth: usize,
+    /// `--flatten-overdeep`: collapse the directories past the budget into
+    /// one hashed directory instead of skipping the entry
+    pub flatten: bool,
+}
+
+/// Entries of one archive deeper than `--max-entry-depth`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct OverdeepEntries {
+    /// Left out of the extraction
+    pub skipped: usize,
+    /// Extracted with their deep directories collapsed
+    pub flattened: usize,
+    /// Components of the deepest entry
+    pub deepest: usize,
+    /// The first such entry, with where it went when flattened
+    pub example: Option<String>,
+}
+
+impl OverdeepEntries {
+    pub fn count(&self) -> usize {
+        self.skipped + self.flattened
+    }
+
+    pub fn merge(&mut self, other: &OverdeepEntries) {
+        self.skipped += other.skipped;
+        self.flattened += other.flattened;
+        self.deepest = self.deepest.max(other.deepest);
+        if self.example.is_none() {
+            self.example.clone_from(&other.example);
+        }
+    }
+}
+
+impl DepthLimit {
+    /// Check `--max-entry-depth` against `--flatten-overdeep`, which needs
+    /// room for the hashed directory and the file name
+    pub fn new(max_depth: usize, flatten: bool) -> Result<Self, String> {
+        match (max_depth, flatten) {
+            (0, _) => Err("--max-entry-depth must be at least 1".to_string()),
+            (1, true) => Err("--flatten-overdeep needs a --max-entry-depth of at least 2, for the hashed directory and the file name".to_string()),
+            _ => Ok(DepthLimit { max_depth, flatten }),
+        }
+    }
+
+    /// Where the entry at `path` goes: unchanged within the budget, else
+    /// `None` to skip it or, with `flatten`, its path collapsed by
+    /// [`collapse_path`]
+    pub fn apply(&self, path: String, overdeep: &mut OverdeepEntries) -> Option<String> {
+        let depth = path.split('/').filter(|c| !c.is_empty()).count();
+        if depth <= self.max_depth {
+            return Some(path);
+        }
+        overdeep.deepest = overdeep.deepest.max(depth);
+        if !self.flatten {
+            overdeep.skipped += 1;
+            overdeep.example.get_or_insert(path);
+            return None;
+        }
+        overdeep.flattened += 1;
+        let collapsed = collapse_path(&path, self.max_depth);
+        overdeep.example.get_or_insert_with(|| format!("{} -> {}", path, collapsed));
+        Some(collapsed)
+    }
+}
+
+/// `path` cut to `max_depth` components: its first `max_depth - 2`
+/// directories, then one directory named by a hash of the rest, then the
+/// file name. Files of one directory share the hashed directory, and a
+/// directory entry becomes that hashed directory, so the tree below the
+/// budget keeps its shape. `max_depth` is at least 2.
+pub fn collapse_path(path: &str, max_depth: usize) -> String {
+    let is_dir = path.ends_with('/');
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let (directories, name) = if is_dir { (&components[..], None) } else { (&components[..components.len() - 1], components.last()) };
+    let kept = max_depth.saturating_sub(2).min(directories.len());
+    let mut collapsed: Vec<String> = directories[..kept].iter().map(|c| c.to_string()).collect();
+    collapsed.push(short_hash(&directories[kept..].join("/")));
+    collapsed.extend(name.map(|name| name.to_string()));
+    let collapsed = collapsed.join("/");
+    if is_dir { collapsed + "/" } else { collapsed }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An entry `depth` components deep, the last the file name
+    fn deep_name(depth: usize) -> String {
+        let mut components: Vec<String> = (1..depth).map(|i| format!("d{}", i)).collect();
+        components.push("leaf.txt".to_string());
+        components.join("/")
+    }
+
+    #[test]
+    fn limits_leave_room_to_flatten() {
+        assert!(DepthLimit::new(0, false).is_err());
+        assert!(DepthLimit::new(1, true).unwrap_err().contains("at least 2"));
+        assert_eq!(DepthLimit::new(1, false), Ok(DepthLimit { max_depth: 1, flatten: false }));
+        assert_eq!(DepthLimit::new(2, true), Ok(DepthLimit { max_depth: 2, flatten: true }));
+    }
+
+    #[test]
+    fn overdeep_entries_are_skipped() {
+        let limit = DepthLimit::new(8, false).unwrap();
+        let mut overdeep = OverdeepEntries::default();
+        assert_eq!(limit.apply(deep_name(8), &mut overdeep), Some(deep_name(8)));
+        assert_eq!(limit.apply("a//b/".to_string(), &mut overdeep).as_deref(), Some("a//b/"));
+        assert_eq!(limit.apply(deep_name(100), &mut overdeep), None);
+        assert_eq!(limit.apply(deep_name(9), &mut overdeep), None);
+        assert_eq!(overdeep, OverdeepEntries { skipped: 2, flattened: 0, deepest: 100, example: Some(deep_name(100)) });
+    }
+
+    #[test]
+    fn overdeep_entries_are_collapsed_within_the_budget() {
+        let limit = DepthLimit::new(8, true).unwrap();
+        let mut overdeep = OverdeepEntries::default();
+        let collapsed = limit.apply(deep_name(100), &mut overdeep).unwrap();
+        let components: Vec<&str> = collapsed.split('/').collect();
+        assert_eq!(components.len(), 8);
+        assert_eq!(components[..6], ["d1", "d2", "d3", "d4", "d5", "d6"]);
+        assert_eq!(components[7], "leaf.txt");
+        assert_eq!((overdeep.flattened, overdeep.skipped, overdeep.deepest), (1, 0, 100));
+        assert_eq!(overdeep.example, Some(format!("{} -> {}", deep_name(100), collapsed)));
+    }
+
+    #[test]
+    fn a_directory_keeps_its_files_together_when_collapsed() {
+        let file = collapse_path("a/b/c/d/song.mp3", 3);
+        let other = collapse_path("a/b/c/d/cover.jpg", 3);
+        let directory = collapse_path("a/b/c/d/", 3);
+        assert_eq!(file, format!("a/{}/song.mp3", short_hash("b/c/d")));
+        assert_eq!(other, format!("a/{}/cover.jpg", short_hash("b/c/d")));
+        assert_eq!(directory, format!("a/{}/", short_hash("b/c/d")));
+        assert_ne!(collapse_path("a/b/x/d/song.mp3", 3), file);
+        // At the smallest budget, just the hashed directory and the name
+        assert_eq!(collapse_path("a/b/song.mp3", 2), format!("{}/song.mp3", short_hash("a/b")));
+    }
+
+    #[test]
+    fn counts_of_several_archives_add_up() {
+        let mut total = OverdeepEntries { skipped: 1, flattened: 0, deepest: 12, example: None };
+        total.merge(&OverdeepEntries { skipped: 2, flattened: 3, deepest: 100, example: Some("deep".to_string()) });
+        total.merge(&OverdeepEntries { skipped: 0, flattened: 1, deepest: 9, example: Some("later".to_string()) });
+        assert_eq!(total, OverdeepEntries { skipped: 3, flattened: 4, deepest: 100, example: Some("deep".to_string()) });
+        assert_eq!(total.count(), 7);
+    }
+}
@@ -0,0 +1,332 @@
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+
+use crate::disposal::Disposer;
+
+/// Default for `--hook-workers`
+pub const DEFAULT_HOOK_WORKERS: usize = 2;
+
+/// Checks one extracted file, e.g. with a virus scanner; an error fails the entry
+pub type EntryCallback = dyn Fn(&Path) -> Result<()> + Send + Sync;
+
+/// What runs on every file once it is extracted
+#[derive(Clone)]
+pub enum EntryHook {
+    /// A shell command given the file as `$1`, and in place of `{}`; a
+    /// non-zero exit fails the entry. On Windows, a program and its
+    /// arguments, run without cmd.
+    Command(String),
+    Callback(Arc<EntryCallback>),
+}
+
+impl fmt::Debug for EntryHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntryHook::Command(command) => f.debug_tuple("Command").field(command).finish(),
+            EntryHook::Callback(_) => f.write_str("Callback"),
+        }
+    }
+}
+
+impl EntryHook {
+    fn check(&self, path: &Path) -> Result<()> {
+        match self {
+            EntryHook::Command(command) => run_command(command, path),
+            EntryHook::Callback(callback) => callback(path),
+        }
+    }
+}
+
+/// An entry its hook failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct HookFailure {
+    pub path: PathBuf,
+    pub error: String,
+    /// Removed by `--hook-delete-on-fail`, as the run's `--disposal` says
+    pub deleted: bool,
+}
+
+/// Hook results for the entries of one archive
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct HookCounts {
+    pub passed: usize,
+    pub failed: usize,
+    pub failures: Vec<HookFailure>,
+}
+
+impl HookCounts {
+    pub fn merge(&mut self, other: &HookCounts) {
+        self.passed += other.passed;
+        self.failed += other.failed;
+        self.failures.extend(other.failures.iter().cloned());
+    }
+}
+
+#[derive(Default)]
+struct BatchState {
+    pending: usize,
+    counts: HookCounts,
+}
+
+/// The hooks queued for one archive's entries
+#[derive(Default)]
+pub struct HookBatch {
+    state: Mutex<BatchState>,
+    done: Condvar,
+}
+
+impl HookBatch {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// Block until every hook queued so far has finished, returning their results
+    pub fn wait(&self) -> HookCounts {
+        let mut state = self.lock();
+        while state.pending > 0 {
+            state = self.done.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        state.counts.clone()
+    }
+
+    fn finished(&self, path: PathBuf, result: Result<()>, deleted: bool) {
+        let mut state = self.lock();
+        match result {
+            Ok(()) => state.counts.passed += 1,
+            Err(e) => {
+                state.counts.failed += 1;
+                state.counts.failures.push(HookFailure { path, error: format!("{:#}", e), deleted });
+            }
+        }
+        state.pending -= 1;
+        self.done.notify_all();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BatchState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+struct Job {
+    path: PathBuf,
+    batch: Arc<HookBatch>,
+}
+
+/// Runs the entry hook on its own workers, so extraction only queues each
+/// file and moves on however slow the hook is. Dropping the runner waits
+/// for the hooks still queued.
+pub struct HookRunner {
+    sender: Option<Sender<Job>>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+impl fmt::Debug for HookRunner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookRunner").field("workers", &self.workers.len()).finish()
+    }
+}
+
+impl HookRunner {
+    /// Start `workers` threads running `hook`; a file its hook fails is
+    /// removed by `on_fail`, when given
+    pub fn new(hook: EntryHook, workers: usize, on_fail: Option<Arc<dyn Disposer>>) -> Self {
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let hook = Arc::new(hook);
+        let workers = (0..workers.max(1))
+            .map(|_| {
+                let receiver = receiver.clone();
+                let hook = hook.clone();
+                let on_fail = on_fail.clone();
+                thread::spawn(move || run_jobs(&receiver, &hook, on_fail.as_deref()))
+            })
+            .collect();
+        HookRunner { sender: Some(sender), workers }
+    }
+
+    /// Queue the hook for a file just extracted
+    pub fn queue(&self, batch: &Arc<HookBatch>, path: PathBuf) {
+        batch.lock().pending += 1;
+        let job = Job { path, batch: batch.clone() };
+        if let Some(sender) = &self.sender {
+            if let Err(mpsc::SendError(job)) = sender.send(job) {
+                job.batch.finished(job.path, Err(anyhow::anyhow!("Entry hook workers stopped")), false);
+            }
+        }
+    }
+}
+
+impl Drop for HookRunner {
+    fn drop(&mut self) {
+        self.sender = None;
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_jobs(receiver: &Mutex<Receiver<Job>>, hook: &EntryHook, on_fail: Option<&dyn Disposer>) {
+    loop {
+        let job = receiver.lock().unwrap_or_else(|e| e.into_inner()).recv();
+        let Ok(Job { path, batch }) = job else {
+            return;
+        };
+        let (result, deleted) = match (hook.check(&path), on_fail) {
+            (Err(e), Some(disposer)) => match disposer.dispose(&path) {
+                Ok(()) => (Err(e), true),
+                Err(removal) => (Err(anyhow::anyhow!("{:#}; the file was kept: {:#}", e, removal)), false),
+            },
+            (result, _) => (result, false),
+        };
+        batch.finished(path, result, deleted);
+    }
+}
+
+fn run_command(command: &str, path: &Path) -> Result<()> {
+    let output = shell(command, path)
+        .stdin(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run entry hook {:?}", command))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        match stderr.lines().rfind(|line| !line.trim().is_empty()) {
+            Some(line) => bail!("Entry hook failed ({}): {}", output.status, line.trim()),
+            None => bail!("Entry hook failed ({})", output.status),
+        }
+    }
+    Ok(())
+}
+
+/// The path, from an archive and so untrusted, is only ever `$1` and never
+/// part of the script, so no name can run as code
+#[cfg(not(windows))]
+fn shell(command: &str, path: &Path) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(script(command)).arg("sh").arg(path);
+    shell
+}
+
+/// `command` with each `{}`, bare or already quoted, turned into `"$1"`
+#[cfg(not(windows))]
+fn script(command: &str) -> String {
+    command.replace("\"{}\"", "{}").replace("'{}'", "{}").replace("{}", "\"$1\"")
+}
+
+/// cmd expands `%VAR%` and runs what follows `&` even inside quotes, so the
+/// program is started directly, with the path as an argument of its own in
+/// place of `{}`, or last when there is none
+#[cfg(windows)]
+fn shell(command: &str, path: &Path) -> Command {
+    let path = path.to_string_lossy();
+    let mut words = split_words(command).into_iter();
+    let mut program = Command::new(words.next().unwrap_or_default());
+    let mut placed = false;
+    for word in words {
+        placed |= word.contains("{}");
+        program.arg(word.replace("{}", &path));
+    }
+    if !placed {
+        program.arg(&*path);
+    }
+    program
+}
+
+/// Words of a command line split at whitespace outside double quotes, the
+/// quotes removed
+#[cfg(any(windows, test))]
+fn split_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quoted = false;
+    for c in command.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.get_or_insert_with(String::new);
+            }
+            c if c.is_whitespace() && !quoted => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disposal::RecordingDisposer;
+    use std::fs;
+
+    fn run(disposer: Arc<RecordingDisposer>, paths: &[PathBuf]) -> HookCounts {
+        let hook = EntryHook::Callback(Arc::new(|path: &Path| {
+            if path.extension().is_some_and(|ext| ext == "exe") {
+                bail!("infected");
+            }
+            Ok(())
+        }));
+        let runner = HookRunner::new(hook, 2, Some(disposer));
+        let batch = HookBatch::new();
+        for path in paths {
+            runner.queue(&batch, path.clone());
+        }
+        batch.wait()
+    }
+
+    #[test]
+    fn failed_entries_go_through_the_disposer() {
+        let dir = tempfile::tempdir().unwrap();
+        let (good, bad) = (dir.path().join("notes.txt"), dir.path().join("setup.exe"));
+        fs::write(&good, b"notes").unwrap();
+        fs::write(&bad, b"MZ").unwrap();
+
+        let disposer = Arc::new(RecordingDisposer::default());
+        let counts = run(disposer.clone(), &[good.clone(), bad.clone()]);
+        assert_eq!((counts.passed, counts.failed), (1, 1));
+        assert_eq!(counts.failures, [HookFailure { path: bad.clone(), error: "infected".to_string(), deleted: true }]);
+        assert_eq!(disposer.disposed(), [bad.as_path()]);
+        assert!(good.exists() && !bad.exists());
+    }
+
+    #[test]
+    fn files_the_disposer_cannot_remove_are_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let bad = dir.path().join("setup.exe");
+        fs::write(&bad, b"MZ").unwrap();
+
+        let counts = run(Arc::new(RecordingDisposer::failing()), std::slice::from_ref(&bad));
+        let failure = &counts.failures[0];
+        assert!(!failure.deleted);
+        assert!(failure.error.starts_with("infected; the file was kept: Failed to move"), "{}", failure.error);
+        assert!(bad.exists());
+    }
+
+    #[test]
+    fn command_lines_split_at_unquoted_spaces() {
+        assert_eq!(split_words(r#"C:\Tools\scan.exe  --quiet "{}""#), [r"C:\Tools\scan.exe", "--quiet", "{}"]);
+        assert_eq!(split_words(r#""C:\Program Files\scan.exe" --file={} """#), [r"C:\Program Files\scan.exe", "--file={}", ""]);
+        assert!(split_words("  ").is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn entry_names_never_run_as_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("it's $(echo injected) `echo too` a.txt");
+        fs::write(&path, b"notes").unwrap();
+        let record = dir.path().join("record");
+
+        for hook in ["test -f {} && printf %s {}", r#"test -f "{}" && printf %s "{}""#, "printf %s '{}'", r#"printf %s "$1""#] {
+            let command = format!("{} > '{}'", hook, record.display());
+            run_command(&command, &path).unwrap_or_else(|e| panic!("{}: {:#}", hook, e));
+            assert_eq!(fs::read(&record).unwrap(), path.as_os_str().as_encoded_bytes(), "{}", hook);
+        }
+    }
+}
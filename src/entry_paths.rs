@@ -0,0 +1,329 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Component, Path};
+use zip::ZipArchive;
+
+use crate::entry_depth::{DepthLimit, OverdeepEntries};
+use crate::extensions::{ExtensionMap, RenamedExtensions};
+use crate::flatten::FlattenPlan;
+use crate::long_names::{truncate_path, TruncatedName};
+
+/// "Version made by" host systems whose tools write `\` as the path separator:
+/// MS-DOS/FAT, OS/2 HPFS, Windows NTFS and VFAT
+const BACKSLASH_HOSTS: [u8; 4] = [0, 6, 10, 14];
+
+/// Entries of one archive whose names were rewritten for extraction
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NormalizedPaths {
+    pub count: usize,
+    /// Raw name of the first rewritten entry
+    pub example: Option<String>,
+}
+
+/// Entries of one archive left out because their path would leave the
+/// extraction directory
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UnsafePaths {
+    pub count: usize,
+    /// Raw name of the first one left out
+    pub example: Option<String>,
+}
+
+/// Whether an entry path stays inside the directory it's extracted into: no
+/// `..`, root, drive prefix or empty component, a trailing `/` aside
+pub fn is_contained(path: &str) -> bool {
+    let path = path.strip_suffix('/').unwrap_or(path);
+    !path.is_empty()
+        && path.split('/').all(|component| !component.is_empty() && component != "..")
+        && Path::new(path).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+/// Reads the host system of each entry from the archive's central directory,
+/// which the zip crate doesn't expose
+pub struct EntryHosts {
+    file: fs::File,
+}
+
+impl EntryHosts {
+    pub fn open(zip_path: &Path) -> io::Result<Self> {
+        Ok(EntryHosts { file: fs::File::open(zip_path)? })
+    }
+
+    /// Whether the entry whose central directory header starts at
+    /// `central_header_start` was written on DOS or Windows
+    pub fn uses_backslashes(&mut self, central_header_start: u64) -> io::Result<bool> {
+        // Signature (4 bytes), then "version made by": spec version, host system
+        self.file.seek(SeekFrom::Start(central_header_start + 5))?;
+        let mut host = [0; 1];
+        self.file.read_exact(&mut host)?;
+        Ok(BACKSLASH_HOSTS.contains(&host[0]))
+    }
+}
+
+/// Name to extract an entry as: `\` becomes `/` for archives written on
+/// DOS or Windows, and on Windows trailing spaces and dots are trimmed from
+/// every component, as the filesystem would silently drop them
+pub fn normalize_entry_name(name: &str, backslash_separators: bool) -> String {
+    let name = if backslash_separators { name.replace('\\', "/") } else { name.to_string() };
+    if !cfg!(windows) {
+        return name;
+    }
+    name.split('/')
+        .map(|component| match component.trim_end_matches([' ', '.']) {
+            "" if component.chars().any(|c| c != ' ') => component,
+            trimmed => trimmed,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+impl NormalizedPaths {
+    /// Extraction name for entry `name`, counting it when it differs
+    pub fn entry_name(&mut self, name: &str, backslash_separators: bool) -> String {
+        let normalized = normalize_entry_name(name, backslash_separators);
+        if normalized != name {
+            self.count += 1;
+            self.example.get_or_insert_with(|| name.to_string());
+        }
+        normalized
+    }
+}
+
+/// Where the entries of one archive extract to, relative to its extraction directory
+#[derive(Debug, Clone, Default)]
+pub struct EntryPlan {
+    /// Output path of each entry by index, `None` for a directory flattened
+    /// away, an entry over `--max-entry-depth` or one whose path escapes
+    pub paths: Vec<Option<String>>,
+    pub normalized: NormalizedPaths,
+    pub unsafe_paths: UnsafePaths,
+    pub renamed_extensions: RenamedExtensions,
+    pub flatten: FlattenPlan,
+    pub overdeep: OverdeepEntries,
+    /// Paths shortened because a component was too long for the filesystem
+    pub truncated: Vec<TruncatedName>,
+}
+
+/// Every entry name as stored in the central directory, with whether it was
+/// written on DOS or Windows
+pub fn read_entry_names<R: Read + Seek>(archive: &mut ZipArchive<R>, zip_path: &Path) -> Result<Vec<(String, bool)>> {
+    let mut hosts = EntryHosts::open(zip_path)
+        .with_context(|| format!("Failed to open zip file {:?}", zip_path))?;
+    let mut names = Vec::with_capacity(archive.len());
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)
+            .with_context(|| format!("Failed to read file at index {} in {:?}", i, zip_path))?;
+        let backslashes = hosts.uses_backslashes(entry.central_header_start())
+            .with_context(|| format!("Failed to read the central directory of {:?}", zip_path))?;
+        names.push((entry.name().to_string(), backslashes));
+    }
+    Ok(names)
+}
+
+/// Apply normalization, `--normalize-extensions`, `--flatten-depth`,
+/// `--max-entry-depth` and truncation of over-long components to the names
+/// from [`read_entry_names`]. Touches nothing on disk, so extraction, dry
+/// runs and verification all agree on where each entry goes. Entries whose
+/// path would leave the extraction directory get no path at all.
+pub fn plan_entry_paths(
+    names: &[(String, bool)],
+    flatten_depth: usize,
+    extensions: Option<&ExtensionMap>,
+    depth: Option<DepthLimit>,
+) -> EntryPlan {
+    let mut normalized = NormalizedPaths::default();
+    let mut unsafe_paths = UnsafePaths::default();
+    let mut escapes = vec![false; names.len()];
+    let names: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(i, (name, backslashes))| {
+            let normalized = normalized.entry_name(name, *backslashes);
+            if is_contained(&normalized) {
+                return normalized;
+            }
+            escapes[i] = true;
+            unsafe_paths.count += 1;
+            unsafe_paths.example.get_or_insert_with(|| name.clone());
+            // Left out of flattening, so it can't shape the other entries' paths
+            String::new()
+        })
+        .collect();
+    let (names, renamed_extensions) = match extensions {
+        Some(extensions) => extensions.apply(names),
+        None => (names, RenamedExtensions::default()),
+    };
+    let flatten = if flatten_depth > 0 {
+        FlattenPlan::new(&names, flatten_depth)
+    } else {
+        FlattenPlan::default()
+    };
+    let mut overdeep = OverdeepEntries::default();
+    let mut truncated = Vec::new();
+    let paths = names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| {
+            if escapes[i] {
+                return None;
+            }
+            let mut path = flatten.apply(i, name)?;
+            if let Some(depth) = depth {
+                path = depth.apply(path, &mut overdeep)?;
+            }
+            let short = truncate_path(&path);
+            if short != path {
+                truncated.push(TruncatedName { original: path, truncated: short.clone() });
+            }
+            // Flattening, collapsing and truncation only drop or shorten components
+            debug_assert!(is_contained(&short), "{:?} escapes", short);
+            Some(short)
+        })
+        .collect();
+    EntryPlan { paths, normalized, unsafe_paths, renamed_extensions, flatten, overdeep, truncated }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extensions::parse_extension_map;
+
+    fn unix_names(names: &[&str]) -> Vec<(String, bool)> {
+        names.iter().map(|name| (name.to_string(), false)).collect()
+    }
+
+    fn planned(plan: &EntryPlan) -> Vec<Option<&str>> {
+        plan.paths.iter().map(|path| path.as_deref()).collect()
+    }
+
+    /// Resolves `path` lexically under a root, independently of [`is_contained`]
+    fn stays_inside(path: &str) -> bool {
+        let mut depth = 0usize;
+        for component in Path::new(path).components() {
+            match component {
+                Component::Normal(_) => depth += 1,
+                Component::CurDir => {}
+                Component::ParentDir => match depth.checked_sub(1) {
+                    Some(up) => depth = up,
+                    None => return false,
+                },
+                Component::RootDir | Component::Prefix(_) => return false,
+            }
+        }
+        true
+    }
+
+    /// Deterministic xorshift, so a failing case can be replayed
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+
+        /// An archive of up to eight names built from `parts`, some directories
+        fn archive(&mut self, parts: &[&str]) -> Vec<(String, bool)> {
+            (0..=self.below(8))
+                .map(|_| {
+                    let mut name = (0..=self.below(4)).map(|_| parts[self.below(parts.len())]).collect::<Vec<_>>().join("/");
+                    if self.below(4) == 0 {
+                        name.push('/');
+                    }
+                    (name, self.below(2) == 0)
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn containment() {
+        for path in ["a", "a/b.txt", "a/b/", "./a", "a/./b", "..a", "a..", "...", "a\\..\\b"] {
+            assert!(is_contained(path), "{:?}", path);
+        }
+        for path in ["", "/", "..", "../", "../a", "a/../../b", "a/..", "/etc/passwd", "a//b", "a/b//"] {
+            assert!(!is_contained(path), "{:?}", path);
+        }
+    }
+
+    #[test]
+    fn escaping_entries_get_no_path() {
+        let plan = plan_entry_paths(&unix_names(&["ok.txt", "../up.txt", "/abs.txt", "a/../../b.txt", "a//c.txt"]), 0, None, None);
+        assert_eq!(planned(&plan), [Some("ok.txt"), None, None, None, None]);
+        assert_eq!(plan.unsafe_paths.count, 4);
+        assert_eq!(plan.unsafe_paths.example.as_deref(), Some("../up.txt"));
+    }
+
+    #[test]
+    fn dos_separators_are_checked_after_normalization() {
+        let names = [("..\\..\\PWNED.txt".to_string(), true), ("dir\\a.txt".to_string(), true), ("..\\kept.txt".to_string(), false)];
+        let plan = plan_entry_paths(&names, 0, None, None);
+        // Written on Unix, `\` is part of the name rather than a separator
+        let kept = if cfg!(windows) { None } else { Some("..\\kept.txt") };
+        assert_eq!(planned(&plan), [None, Some("dir/a.txt"), kept]);
+        assert_eq!(plan.unsafe_paths.example.as_deref(), Some("..\\..\\PWNED.txt"));
+        assert_eq!(plan.normalized.count, 2);
+    }
+
+    #[test]
+    fn escaping_entries_dont_shape_flattening() {
+        // `..` would otherwise be a second top-level directory and stop `top/` flattening
+        let plan = plan_entry_paths(&unix_names(&["top/a.txt", "top/b.txt", "../x.txt"]), 1, None, None);
+        assert_eq!(planned(&plan), [Some("a.txt"), Some("b.txt"), None]);
+    }
+
+    #[test]
+    fn no_planned_path_leaves_the_root() {
+        let parts = ["a", "b", "..", "", ".", "c.JPEG", "..\\..\\d", "C:", "e\\"];
+        let extensions = parse_extension_map("jpeg=jpg").unwrap();
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        for _ in 0..2000 {
+            let names = rng.archive(&parts);
+            let depth = [None, Some(DepthLimit::new(2, false).unwrap()), Some(DepthLimit::new(2, true).unwrap())][rng.below(3)];
+            let plan = plan_entry_paths(&names, rng.below(4), [None, Some(&extensions)][rng.below(2)], depth);
+            assert_eq!(plan.paths.len(), names.len());
+            for path in plan.paths.iter().flatten() {
+                assert!(stays_inside(path) && is_contained(path), "{:?} from {:?}", path, names);
+            }
+        }
+    }
+
+    #[test]
+    fn flattening_is_idempotent() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        for _ in 0..2000 {
+            let names: Vec<(String, bool)> = rng.archive(&["a", "b", "c.txt", "d"]).into_iter().map(|(name, _)| (name, false)).collect();
+            let once: Vec<(String, bool)> = plan_entry_paths(&names, 8, None, None)
+                .paths
+                .into_iter()
+                .flatten()
+                .map(|path| (path, false))
+                .collect();
+            let twice: Vec<(String, bool)> = plan_entry_paths(&once, 8, None, None)
+                .paths
+                .into_iter()
+                .flatten()
+                .map(|path| (path, false))
+                .collect();
+            assert_eq!(once, twice, "from {:?}", names);
+        }
+    }
+
+    #[test]
+    fn flattening_stops_at_files_and_before_collisions() {
+        let names = unix_names(&["wrap/pkg/", "wrap/pkg/a.txt", "wrap/pkg/lib/a.txt", "wrap/pkg/lib/b.txt", "wrap/readme.txt"]);
+        let plan = plan_entry_paths(&names, 8, None, None);
+        // Directories flattened away entirely get no path
+        assert_eq!(planned(&plan), [None, Some("a.txt"), Some("lib/a.txt"), Some("lib/b.txt"), Some("readme.txt")]);
+        assert_eq!((plan.flatten.levels, plan.flatten.stopped.len()), (2, 1));
+
+        // Never more than asked
+        let plan = plan_entry_paths(&names, 1, None, None);
+        assert_eq!(planned(&plan)[1..], [Some("pkg/a.txt"), Some("pkg/lib/a.txt"), Some("pkg/lib/b.txt"), Some("readme.txt")]);
+        assert!(plan.flatten.stopped.is_empty());
+    }
+}
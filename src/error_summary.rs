@@ -0,0 +1,140 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::copy_hooks::is_cancelled;
+use crate::fs_limits::FS_LIMITATION;
+
+/// Examples kept per group; the count keeps growing past this
+const MAX_EXAMPLES: usize = 3;
+
+/// Broad category of an error for grouping: the I/O error kind when an I/O
+/// error caused it, otherwise the innermost message
+pub fn error_kind(error: &anyhow::Error) -> String {
+    if let Some(io_error) = error.chain().find_map(|e| e.downcast_ref::<io::Error>()) {
+        if io_error.kind() == io::ErrorKind::FileTooLarge {
+            return FS_LIMITATION.to_string();
+        }
+        if is_cancelled(io_error) {
+            return "cancelled".to_string();
+        }
+        return io_error.kind().to_string();
+    }
+    error.root_cause().to_string()
+}
+
+/// One failure: the file or archive it concerns and the full message
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorExample {
+    pub subject: PathBuf,
+    pub message: String,
+}
+
+/// Errors of one kind within one archive or directory
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorGroup {
+    pub kind: String,
+    pub scope: PathBuf,
+    pub count: usize,
+    pub examples: Vec<ErrorExample>,
+}
+
+/// Collects the errors of a run so thousands of near-identical failures end
+/// up as one summary line each. Every error is also written in full to the
+/// error log, when one is configured.
+pub struct ErrorCollector {
+    groups: Mutex<Vec<ErrorGroup>>,
+    log: Option<(PathBuf, Mutex<BufWriter<fs::File>>)>,
+}
+
+impl ErrorCollector {
+    pub fn new(log_path: Option<&Path>) -> Result<Self> {
+        let log = log_path
+            .map(|path| {
+                fs::File::create(path)
+                    .with_context(|| format!("Failed to create error log {:?}", path))
+                    .map(|file| (path.to_path_buf(), Mutex::new(BufWriter::new(file))))
+            })
+            .transpose()?;
+        Ok(ErrorCollector { groups: Mutex::new(Vec::new()), log })
+    }
+
+    /// Record that `subject`, inside the archive or directory `scope`, failed with `error`
+    pub fn record_error(&self, scope: &Path, subject: &Path, error: &anyhow::Error) {
+        self.record(scope, subject, &error_kind(error), &format!("{:#}", error));
+    }
+
+    /// Record a failure whose kind was classified earlier
+    pub fn record(&self, scope: &Path, subject: &Path, kind: &str, message: &str) {
+        if let Some((_, log)) = &self.log {
+            let mut log = log.lock().unwrap_or_else(|e| e.into_inner());
+            let _ = writeln!(log, "{}: {}", subject.display(), message);
+        }
+
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner());
+        let index = match groups.iter().position(|g| g.kind == kind && g.scope == scope) {
+            Some(index) => index,
+            None => {
+                groups.push(ErrorGroup { kind: kind.to_string(), scope: scope.to_path_buf(), count: 0, examples: Vec::new() });
+                groups.len() - 1
+            }
+        };
+        let group = &mut groups[index];
+        group.count += 1;
+        if group.examples.len() < MAX_EXAMPLES {
+            group.examples.push(ErrorExample { subject: subject.to_path_buf(), message: message.to_string() });
+        }
+    }
+
+    /// The groups recorded so far, in the order first seen
+    pub fn groups(&self) -> Vec<ErrorGroup> {
+        self.groups.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// One line per group, largest first, then flush the error log
+    pub fn print_summary(&self) {
+        let mut groups = self.groups.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        if groups.is_empty() {
+            return;
+        }
+        groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+
+        for group in &groups {
+            let first = &group.examples[0];
+            if group.count == 1 {
+                eprintln!("❌ {:?}: {}", first.subject, first.message);
+                continue;
+            }
+            let names: Vec<String> = group
+                .examples
+                .iter()
+                .map(|example| example.subject.file_name().unwrap_or_default().to_string_lossy().into_owned())
+                .collect();
+            eprintln!(
+                "❌ {} under {:?} ({} affected, e.g. {}{})",
+                capitalize(&group.kind),
+                group.scope,
+                group.count,
+                names.join(", "),
+                if group.count > names.len() { ", ..." } else { "" }
+            );
+            eprintln!("   first: {}", first.message);
+        }
+
+        if let Some((path, log)) = &self.log {
+            let _ = log.lock().unwrap_or_else(|e| e.into_inner()).flush();
+            eprintln!("📝 Every error is listed in {:?}", path);
+        }
+    }
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
@@ -0,0 +1,145 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// `--normalize-extensions`: entry extensions are lowercased, then replaced
+/// by their canonical form when `--extension-map` gives one, e.g. `jpeg` to
+/// `jpg`. Keys and values are lowercase and without the dot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct ExtensionMap(BTreeMap<String, String>);
+
+/// Parse an `--extension-map` such as `jpeg=jpg, .TIF=tiff`. Dots and case
+/// don't matter; mappings apply once, so `jpeg=jpg,jpg=png` doesn't turn
+/// `jpeg` into `png`.
+pub fn parse_extension_map(map: &str) -> Result<ExtensionMap, String> {
+    let mut mappings = BTreeMap::new();
+    for mapping in map.split(',').map(str::trim).filter(|mapping| !mapping.is_empty()) {
+        let (from, to) = mapping
+            .split_once('=')
+            .ok_or_else(|| format!("{:?} isn't a mapping; expected FROM=TO, e.g. jpeg=jpg", mapping))?;
+        let (from, to) = (extension(from)?, extension(to)?);
+        match mappings.insert(from.clone(), to.clone()) {
+            Some(earlier) if earlier != to => return Err(format!("{:?} is mapped to both {:?} and {:?}", from, earlier, to)),
+            _ => {}
+        }
+    }
+    Ok(ExtensionMap(mappings))
+}
+
+/// One side of a mapping, lowercased and without its leading dot
+fn extension(extension: &str) -> Result<String, String> {
+    let trimmed = extension.trim();
+    let bare = trimmed.strip_prefix('.').unwrap_or(trimmed);
+    if bare.is_empty() {
+        return Err(format!("Empty extension in --extension-map near {:?}", extension));
+    }
+    if bare.contains(['.', '/', '\\', '=']) {
+        return Err(format!("{:?} isn't a single extension", trimmed));
+    }
+    Ok(bare.to_lowercase())
+}
+
+impl ExtensionMap {
+    /// `name` with the extension of its last component normalized; directory
+    /// entries, names without an extension and dotfiles such as `.DS_Store`
+    /// come back unchanged
+    pub fn normalize(&self, name: &str) -> String {
+        if name.ends_with('/') {
+            return name.to_string();
+        }
+        let start = name.rfind('/').map_or(0, |i| i + 1);
+        let Some(dot) = name[start..].rfind('.').filter(|&dot| dot > 0).map(|dot| start + dot) else {
+            return name.to_string();
+        };
+        let lower = name[dot + 1..].to_lowercase();
+        let extension = self.0.get(&lower).unwrap_or(&lower);
+        format!("{}.{}", &name[..dot], extension)
+    }
+
+    /// Normalize every name, recording which ones changed
+    pub fn apply(&self, names: Vec<String>) -> (Vec<String>, RenamedExtensions) {
+        let mut renamed = RenamedExtensions::default();
+        let mut changed = Vec::new();
+        let names: Vec<String> = names
+            .into_iter()
+            .map(|name| {
+                let normalized = self.normalize(&name);
+                if normalized != name {
+                    renamed.count += 1;
+                    renamed.example.get_or_insert_with(|| format!("{} -> {}", name, normalized));
+                    changed.push(normalized.clone());
+                }
+                normalized
+            })
+            .collect();
+        let mut uses: HashMap<&str, usize> = HashMap::new();
+        for name in &names {
+            *uses.entry(name).or_default() += 1;
+        }
+        for name in changed {
+            if uses.get(name.as_str()).is_some_and(|&n| n > 1) && !renamed.collisions.contains(&name) {
+                renamed.collisions.push(name);
+            }
+        }
+        (names, renamed)
+    }
+}
+
+/// Entries of one archive whose extension `--normalize-extensions` changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct RenamedExtensions {
+    pub count: usize,
+    /// The first rename, e.g. `Track 01.MP3 -> Track 01.mp3`
+    pub example: Option<String>,
+    /// Names more than one entry now extracts to, e.g. both `a.MP3` and
+    /// `a.mp3`; like any entries sharing a name, the last one in the archive wins
+    pub collisions: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(pairs: &[(&str, &str)]) -> ExtensionMap {
+        ExtensionMap(pairs.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect())
+    }
+
+    #[test]
+    fn maps_ignore_dots_case_and_blanks() {
+        assert_eq!(parse_extension_map("jpeg=jpg, .TIF=tiff,,").unwrap(), map(&[("jpeg", "jpg"), ("tif", "tiff")]));
+        assert_eq!(parse_extension_map("").unwrap(), ExtensionMap::default());
+        // Saying the same twice is fine
+        assert_eq!(parse_extension_map("jpeg=jpg,JPEG=.jpg").unwrap(), map(&[("jpeg", "jpg")]));
+    }
+
+    #[test]
+    fn malformed_maps_are_rejected() {
+        assert_eq!(parse_extension_map("jpeg").unwrap_err(), "\"jpeg\" isn't a mapping; expected FROM=TO, e.g. jpeg=jpg");
+        assert_eq!(parse_extension_map("jpeg=.").unwrap_err(), "Empty extension in --extension-map near \".\"");
+        assert_eq!(parse_extension_map("tar.gz=tgz").unwrap_err(), "\"tar.gz\" isn't a single extension");
+        assert_eq!(parse_extension_map("a=b=c").unwrap_err(), "\"b=c\" isn't a single extension");
+        assert_eq!(parse_extension_map("jpeg=jpg,jpeg=png").unwrap_err(), "\"jpeg\" is mapped to both \"jpg\" and \"png\"");
+    }
+
+    #[test]
+    fn only_the_extension_of_the_last_component_changes() {
+        let map = parse_extension_map("jpeg=jpg,jpg=png").unwrap();
+        assert_eq!(map.normalize("Album.V2/Track 01.MP3"), "Album.V2/Track 01.mp3");
+        assert_eq!(map.normalize("Covers/FRONT.JPEG"), "Covers/FRONT.jpg");
+        // Applied once
+        assert_eq!(map.normalize("back.jpg"), "back.png");
+        for unchanged in ["Album.V2/", "README", "Album/.DS_Store", "notes.txt"] {
+            assert_eq!(map.normalize(unchanged), unchanged);
+        }
+    }
+
+    #[test]
+    fn renames_are_counted_and_collisions_listed() {
+        let names = ["a.MP3", "a.mp3", "b.Mp3", "c.mp3", "d.JPG", "d.JPG"].map(String::from).to_vec();
+        let (names, renamed) = ExtensionMap::default().apply(names);
+        assert_eq!(names, ["a.mp3", "a.mp3", "b.mp3", "c.mp3", "d.jpg", "d.jpg"]);
+        assert_eq!(renamed.count, 4);
+        assert_eq!(renamed.example.as_deref(), Some("a.MP3 -> a.mp3"));
+        assert_eq!(renamed.collisions, ["a.mp3", "d.jpg"]);
+    }
+}
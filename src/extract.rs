@@ -0,0 +1,1764 @@
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use indicatif::ProgressBar;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Read, Seek};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+use crate::copy_hooks::{copy_with_hooks, is_cancelled, CopyControl};
+use crate::date_buckets::{date_bucket, DateSource};
+use crate::defaults::DEFAULTS;
+use crate::disposal::{Disposer, PermanentDelete};
+use crate::entry_depth::{DepthLimit, OverdeepEntries};
+use crate::entry_hooks::{HookBatch, HookCounts, HookRunner};
+use crate::entry_paths::{is_contained, plan_entry_paths, read_entry_names};
+use crate::extensions::{ExtensionMap, RenamedExtensions};
+use crate::file_types::{is_archive, is_signature_file, ArchiveKind, FileTypeCounts};
+use crate::fingerprint::{mtime_secs, sha256_files, Fingerprint};
+use crate::format::truncate_middle;
+use crate::input_guard::InputSet;
+use crate::long_names::{TruncatedName, MAX_COMPONENT_BYTES};
+use crate::mapped_archive::{map_unchanged, GuardedMap};
+use crate::memory_budget::DEFAULT_BUFFER_SIZE;
+use crate::name_template::NameTemplate;
+use crate::ordering::SortOrder;
+use crate::output_lock::{BusyPolicy, OutputLock};
+use crate::passwords::{PasswordSource, Passwords};
+use crate::ownership::{ChownFailures, Ownership};
+use crate::preserve::{set_mode, Preserve};
+use crate::scan_progress::ScanMonitor;
+use crate::scan_roots::{ScanRoots, SeenFiles};
+use crate::stability::wait_until_settled;
+use crate::state_journal::StateJournal;
+use crate::stats::is_encrypted;
+use crate::timestamps::TimeClamps;
+use crate::validation_failure::{handle_failed_output, is_validation_failure, OnValidationFailure, ValidationFailed};
+use crate::verify::file_crc32;
+use crate::warnings::{ArchiveWarnings, StrictMode, Warning, WarningCategory};
+use crate::zip64::{check_entry_count, is_zip64};
+
+/// Default for `max_entries`, also the CLI's `--max-entries` default
+pub const DEFAULT_MAX_ENTRIES: usize = DEFAULTS.max_entries;
+
+/// Per-archive extraction settings shared by every worker
+#[derive(Debug, Clone, Serialize)]
+#[non_exhaustive]
+pub struct ExtractOptions {
+    pub skip_existing: bool,
+    pub overwrite: bool,
+    pub use_mmap: bool,
+    /// Refuse archives with more entries than this
+    pub max_entries: Option<usize>,
+    pub ownership: Ownership,
+    /// Entry attributes to keep: times and Unix modes here, while `owner`
+    /// takes effect through `ownership.preserve_owner`
+    pub preserve: Preserve,
+    /// Size of the buffer entries are copied through
+    pub buffer_size: usize,
+    pub strict_times: bool,
+    /// Strip up to this many leading directories from every entry
+    pub flatten_depth: usize,
+    /// Lowercase, and optionally map, the extension of every entry
+    pub normalize_extensions: Option<ExtensionMap>,
+    /// Skip, or collapse, entries with more path components than this
+    pub depth_limit: Option<DepthLimit>,
+    /// Extract into existing directories, rewriting only files that are
+    /// missing, older than their entry or of a different size
+    pub update: bool,
+    /// With `update`, remove the files the last run extracted from an
+    /// archive, per `receipts`, that no entry of it produces any more
+    pub delete_removed: bool,
+    /// Extract into directories an interrupted run left behind, keeping the
+    /// files whose size and CRC-32 match their entry and rewriting the rest
+    pub resume: bool,
+    /// Remove directories under each archive's directory left empty once it is extracted
+    pub prune_empty: bool,
+    /// Shorten path components too long for the filesystem instead of failing the archive
+    pub truncate_long_names: bool,
+    /// What to do when another archive is extracting into the same directory
+    pub on_busy: BusyPolicy,
+    /// What becomes of the directory of an archive failing a CRC-32 or entry count check
+    pub on_validation_failure: OnValidationFailure,
+    /// Leave out `META-INF/` signature files of JARs and APKs
+    pub skip_signatures: bool,
+    /// Fail archives raising a warning of a category it doesn't except
+    pub strict: Option<StrictMode>,
+    /// Cancels, pauses or throttles the copy of every entry between chunks
+    #[serde(skip)]
+    pub control: Option<Arc<CopyControl>>,
+    /// Checks every extracted file, e.g. with a virus scanner, off the copy loop
+    #[serde(skip)]
+    pub entry_hooks: Option<Arc<HookRunner>>,
+    /// The archives of the run, which no entry may be written over
+    #[serde(skip)]
+    pub inputs: Option<Arc<InputSet>>,
+    /// Tried on encrypted archives, which are skipped when none opens them
+    #[serde(skip)]
+    pub passwords: Option<Arc<Passwords>>,
+    /// Removes the files `delete_removed` and `overwrite` get rid of, and
+    /// those an entry hook failed
+    #[serde(skip)]
+    pub disposer: Arc<dyn Disposer>,
+    /// Where `delete_removed` finds the files the last run extracted from
+    /// each archive; without it nothing is removed
+    #[serde(skip)]
+    pub receipts: Option<Arc<StateJournal>>,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            skip_existing: false,
+            overwrite: false,
+            use_mmap: false,
+            max_entries: Some(DEFAULT_MAX_ENTRIES),
+            ownership: Ownership::default(),
+            preserve: Preserve::TIMES,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            strict_times: false,
+            flatten_depth: 0,
+            normalize_extensions: None,
+            depth_limit: None,
+            update: false,
+            delete_removed: false,
+            resume: false,
+            prune_empty: false,
+            truncate_long_names: true,
+            on_busy: BusyPolicy::default(),
+            on_validation_failure: OnValidationFailure::default(),
+            skip_signatures: false,
+            strict: None,
+            control: None,
+            entry_hooks: None,
+            inputs: None,
+            passwords: None,
+            disposer: Arc::new(PermanentDelete),
+            receipts: None,
+        }
+    }
+}
+
+/// An archive found by the scan
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ZipFile {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Entries matching `--containing`, when a pattern was given and the archive was readable
+    pub matching_entries: Option<usize>,
+    /// Entries in the central directory, when the archive was readable
+    pub entries: Option<usize>,
+    /// Sum of the entries' uncompressed sizes, 0 when the archive is unreadable
+    pub uncompressed_size: u64,
+    pub health: ArchiveHealth,
+    /// Name of the directory the archive is extracted into, the stem unless templated
+    pub output_name: String,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: i64,
+    /// Hex SHA-256 of the archive, filled in by [`hash_zip_files`]
+    pub sha256: Option<String>,
+    /// Comic, ebook, Java or Android package or plain zip, from the extension
+    pub kind: ArchiveKind,
+    /// Has zip64 records: more than 65,535 entries or something past 4 GiB
+    pub zip64: bool,
+}
+
+impl ZipFile {
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint { size: self.size, mtime: self.mtime, sha256: self.sha256.clone() }
+    }
+}
+
+/// Result of the cheap sanity checks run on each archive during the scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArchiveHealth {
+    Ok,
+    /// Zero bytes, typically a failed download
+    Empty,
+    /// Smaller than an end-of-central-directory record
+    TooSmall,
+    /// No end-of-central-directory signature where one must be
+    NoCentralDirectory,
+    /// A rar archive, typically a `.cbr` comic, which can't be extracted
+    Rar,
+}
+
+impl ArchiveHealth {
+    pub fn label(self) -> &'static str {
+        match self {
+            ArchiveHealth::Ok => "ok",
+            ArchiveHealth::Empty => "empty",
+            ArchiveHealth::TooSmall => "too small",
+            ArchiveHealth::NoCentralDirectory => "no central directory",
+            ArchiveHealth::Rar => "rar unsupported",
+        }
+    }
+
+    /// Whether extraction should go ahead: healthy archives always, suspect
+    /// ones with `attempt_suspect`, rar archives never
+    pub fn should_extract(self, attempt_suspect: bool) -> bool {
+        match self {
+            ArchiveHealth::Ok => true,
+            ArchiveHealth::Rar => false,
+            _ => attempt_suspect,
+        }
+    }
+}
+
+/// Size of an end-of-central-directory record without a comment
+const EOCD_SIZE: u64 = 22;
+const EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+/// Start of both RAR 4 and RAR 5 archives
+const RAR_SIGNATURE: &[u8] = b"Rar!\x1a\x07";
+
+/// Cheap sanity check of an archive, without parsing its central directory
+pub fn check_archive_health(path: &Path, size: u64) -> ArchiveHealth {
+    if size == 0 {
+        return ArchiveHealth::Empty;
+    }
+    let mut head = [0u8; RAR_SIGNATURE.len()];
+    if fs::File::open(path).and_then(|mut file| file.read_exact(&mut head)).is_ok() && head == RAR_SIGNATURE {
+        return ArchiveHealth::Rar;
+    }
+    if size < EOCD_SIZE {
+        return ArchiveHealth::TooSmall;
+    }
+    
+    // The record is the last thing in the file, followed only by a comment of
+    // at most 64 KiB. Unreadable files are left for extraction to report.
+    let tail_len = size.min(EOCD_SIZE + u16::MAX as u64);
+    let mut tail = Vec::new();
+    let read = fs::File::open(path).and_then(|mut file| {
+        file.seek(io::SeekFrom::End(-(tail_len as i64)))?;
+        file.read_to_end(&mut tail)
+    });
+    if read.is_err() || tail.windows(EOCD_SIGNATURE.len()).any(|w| w == EOCD_SIGNATURE) {
+        ArchiveHealth::Ok
+    } else {
+        ArchiveHealth::NoCentralDirectory
+    }
+}
+
+/// Outcome of extracting a single archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum ExtractStatus {
+    Extracted,
+    /// The output directory exists and `skip_existing` is set
+    SkippedExisting,
+    /// No entry matches `--containing`
+    SkippedFiltered,
+    /// Password-protected entries that no password given opened
+    SkippedEncrypted,
+    /// Flagged by the scan as empty, truncated, without central directory or rar
+    SkippedSuspect,
+    /// Byte-identical to this archive, which was extracted instead
+    SkippedDuplicateOf(PathBuf),
+    /// A later archive of its `--latest-per-group` group, this one, was extracted instead
+    SkippedSuperseded(PathBuf),
+    /// Still growing or locked after `--stabilize-timeout`, e.g. a download in
+    /// progress; picked up by the next run rather than treated as corrupt
+    SkippedUnstable,
+    /// Recorded in the state journal as extracted by an earlier run, and unchanged since
+    SkippedProcessed,
+}
+
+/// What extracting one archive produced, besides the files themselves
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[non_exhaustive]
+pub struct ExtractOutcome {
+    pub status: ExtractStatus,
+    pub file_types: FileTypeCounts,
+    /// Extracted paths of entries that are archives themselves
+    pub nested_archives: Vec<String>,
+    /// Most leading directories `flatten_depth` removed from an entry
+    pub levels_flattened: usize,
+    /// Branches flattened less than asked to avoid collisions
+    pub flatten_stopped: Vec<String>,
+    /// What `update` did with each file, all zero otherwise
+    pub update: UpdateCounts,
+    /// Extracted by `resume` into a directory an interrupted run left;
+    /// `update` counts the files it kept and rewrote
+    pub resumed: bool,
+    /// Entry paths shortened by `truncate_long_names`
+    pub truncated_names: Vec<TruncatedName>,
+    /// Entries whose extension `normalize_extensions` changed
+    pub renamed_extensions: RenamedExtensions,
+    /// Entries over `depth_limit`
+    pub overdeep: OverdeepEntries,
+    /// Files the entry hook passed and failed
+    pub hooks: HookCounts,
+    /// For encrypted archives, where the password that opened them came from
+    pub password_source: Option<PasswordSource>,
+    /// What was surprising about it, e.g. clamped timestamps or a skip
+    pub warnings: Vec<Warning>,
+    /// Files it extracted, relative to its directory, for the state file's record
+    #[serde(skip)]
+    pub extracted: Vec<String>,
+}
+
+/// Files of one archive by what `update` did with them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct UpdateCounts {
+    /// Written where nothing existed
+    pub new: usize,
+    /// Rewritten because the entry is newer or differs in size
+    pub updated: usize,
+    pub unchanged: usize,
+    /// Removed by `delete_removed`
+    pub deleted: usize,
+}
+
+impl UpdateCounts {
+    pub fn merge(&mut self, other: &UpdateCounts) {
+        self.new += other.new;
+        self.updated += other.updated;
+        self.unchanged += other.unchanged;
+        self.deleted += other.deleted;
+    }
+}
+
+impl ExtractOutcome {
+    /// Skipped for `status`, with the warning a surprising skip raises
+    pub fn skipped(status: ExtractStatus) -> Self {
+        let warning = |category, message: String| Warning { category, count: 1, message };
+        let warnings = match &status {
+            ExtractStatus::SkippedEncrypted => Some(warning(WarningCategory::EncryptedArchives, "skipped, no password given opens it".to_string())),
+            ExtractStatus::SkippedSuspect => Some(warning(WarningCategory::SuspectArchives, "skipped as suspect".to_string())),
+            ExtractStatus::SkippedUnstable => Some(warning(WarningCategory::UnstableArchives, "skipped, still being written".to_string())),
+            ExtractStatus::SkippedDuplicateOf(kept) => Some(warning(WarningCategory::DuplicateArchives, format!("skipped, byte-identical to {:?}", kept))),
+            _ => None,
+        };
+        ExtractOutcome {
+            status,
+            file_types: FileTypeCounts::default(),
+            nested_archives: Vec::new(),
+            levels_flattened: 0,
+            flatten_stopped: Vec::new(),
+            update: UpdateCounts::default(),
+            resumed: false,
+            truncated_names: Vec::new(),
+            renamed_extensions: RenamedExtensions::default(),
+            overdeep: OverdeepEntries::default(),
+            hooks: HookCounts::default(),
+            password_source: None,
+            warnings: warnings.into_iter().collect(),
+            extracted: Vec::new(),
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// With `strict`, an error naming the warnings of `outcome` it fails
+    pub fn check_strict(&self, outcome: &ExtractOutcome) -> Result<()> {
+        match &self.strict {
+            Some(strict) => strict.check(&outcome.warnings),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Count entries in the archive's central directory whose name matches `pattern`
+pub fn count_matching_entries(path: &Path, pattern: &Pattern) -> Result<usize> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("Failed to open zip file {:?}", path))?;
+    let archive = ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {:?}", path))?;
+    let options = MatchOptions { case_sensitive: false, ..MatchOptions::new() };
+    Ok(archive.file_names().filter(|name| pattern.matches_with(name, options)).count())
+}
+
+/// Entry count and sum of the uncompressed sizes recorded in the archive's central directory
+pub fn archive_totals(path: &Path) -> Result<(usize, u64)> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("Failed to open zip file {:?}", path))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {:?}", path))?;
+    Ok((archive.len(), total_uncompressed_size(&mut archive)?))
+}
+
+fn total_uncompressed_size<R: Read + Seek>(archive: &mut ZipArchive<R>) -> Result<u64> {
+    let mut total = 0;
+    for i in 0..archive.len() {
+        total += archive.by_index_raw(i)?.size();
+    }
+    Ok(total)
+}
+
+/// Every archive under `directory` (`.zip`, `.cbz`, `.cbr`, `.epub`, `.jar`, `.war`, `.ear`, `.apk`), with the
+/// scan's per-archive details
+pub async fn find_zip_files(directory: &Path, containing: Option<&Pattern>, monitor: &mut ScanMonitor<'_>) -> Result<Vec<ZipFile>> {
+    find_zip_files_in(&ScanRoots::new(&[directory.to_path_buf()]), containing, monitor).await
+}
+
+/// [`find_zip_files`] under every root; an archive reachable from several,
+/// through nested roots or links, is listed once, under the first
+pub async fn find_zip_files_in(roots: &ScanRoots, containing: Option<&Pattern>, monitor: &mut ScanMonitor<'_>) -> Result<Vec<ZipFile>> {
+    let mut zip_files = Vec::new();
+    let mut seen = SeenFiles::default();
+    
+    for root in roots.iter() {
+        let mut entries = WalkDir::new(&root.path).follow_links(true).into_iter();
+        while let Some(entry) = entries.next() {
+            let Ok(entry) = entry else { continue };
+            if monitor.excludes(&entry) {
+                entries.skip_current_dir();
+                continue;
+            }
+            monitor.visit(&entry)?;
+            let path = entry.path();
+            if let Some(kind) = ArchiveKind::from_path(path).filter(|_| path.is_file()) {
+                if roots.is_multiple() && !seen.first_sighting(path) {
+                    continue;
+                }
+                let zip_file = zip_file_info(path, kind, containing)?;
+                monitor.matched(zip_file.size);
+                zip_files.push(zip_file);
+            }
+        }
+    }
+    
+    monitor.finish();
+    SortOrder::Path.sort_by_path(&mut zip_files, |f| &f.path);
+    Ok(zip_files)
+}
+
+fn zip_file_info(path: &Path, kind: ArchiveKind, containing: Option<&Pattern>) -> Result<ZipFile> {
+    let metadata = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata for {:?}", path))?;
+    let totals = archive_totals(path).ok();
+    Ok(ZipFile {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        matching_entries: containing.and_then(|pattern| count_matching_entries(path, pattern).ok()),
+        entries: totals.map(|(entries, _)| entries),
+        uncompressed_size: totals.map_or(0, |(_, size)| size),
+        health: check_archive_health(path, metadata.len()),
+        output_name: path.file_stem().unwrap().to_string_lossy().into_owned(),
+        mtime: mtime_secs(&metadata),
+        sha256: None,
+        kind,
+        zip64: is_zip64(path),
+    })
+}
+
+/// Scan just these archives, as a `retry` of earlier failures does, instead
+/// of walking a directory
+pub fn zip_files_at(paths: &[PathBuf], containing: Option<&Pattern>) -> Result<Vec<ZipFile>> {
+    paths
+        .iter()
+        .map(|path| {
+            let kind = ArchiveKind::from_path(path)
+                .with_context(|| format!("{:?} is not a supported archive", path))?;
+            zip_file_info(path, kind, containing)
+        })
+        .collect()
+}
+
+/// Wait up to `timeout` for archives still being written to settle, then
+/// scan those again so a download caught half-way isn't judged by its
+/// truncated state. Returns the archives to go on with and those still
+/// changing, which keep their order.
+pub async fn settle_zip_files(zip_files: Vec<ZipFile>, containing: Option<&Pattern>, timeout: Duration) -> Result<(Vec<ZipFile>, Vec<ZipFile>)> {
+    let watched = wait_until_settled(zip_files.iter().map(|f| f.path.as_path()), timeout).await;
+    let mut ready = Vec::new();
+    let mut unstable = Vec::new();
+    for zip_file in zip_files {
+        if watched.unstable.contains(&zip_file.path) {
+            unstable.push(zip_file);
+        } else if watched.settled.contains(&zip_file.path) {
+            ready.push(zip_file_info(&zip_file.path, zip_file.kind, containing)?);
+        } else {
+            ready.push(zip_file);
+        }
+    }
+    Ok((ready, unstable))
+}
+
+/// Fill in the SHA-256 of every archive, reading them in parallel. Reads
+/// every byte, so it only runs when asked for (`scan --hash`).
+pub fn hash_zip_files(zip_files: &mut [ZipFile], cancel: &AtomicBool, on_read: impl Fn(u64) + Sync) -> Result<()> {
+    let paths: Vec<PathBuf> = zip_files.iter().map(|f| f.path.clone()).collect();
+    let hashes = sha256_files(&paths, cancel, on_read)?;
+    for (zip_file, hash) in zip_files.iter_mut().zip(hashes) {
+        zip_file.sha256 = Some(hash);
+    }
+    Ok(())
+}
+
+/// The two directories an archive named `name` may extract to: `<output>/<name>`,
+/// or `<output>/<name> (extracted)` when a regular file occupies the first
+pub fn extract_dir_paths(output_dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+    (output_dir.join(name), output_dir.join(format!("{} (extracted)", name)))
+}
+
+/// The directory an earlier run extracted `zip_file` into, if there is one
+pub fn find_extract_dir(output_dir: &Path, zip_file: &ZipFile) -> Option<PathBuf> {
+    let (extract_dir, renamed) = extract_dir_paths(output_dir, &zip_file.output_name);
+    [extract_dir, renamed].into_iter().find(|dir| dir.is_dir())
+}
+
+/// Directory to extract an archive into. A regular file already occupying
+/// `<output>/<stem>` is removed by `disposer` with `overwrite`; otherwise the
+/// archive is extracted to `<stem> (extracted)` instead.
+pub fn resolve_extract_dir(output_dir: &Path, stem: &str, overwrite: bool, disposer: &dyn Disposer) -> Result<PathBuf> {
+    let (extract_dir, renamed) = extract_dir_paths(output_dir, stem);
+    if !extract_dir.is_file() {
+        return Ok(extract_dir);
+    }
+    
+    if overwrite {
+        disposer.dispose(&extract_dir)?;
+        return Ok(extract_dir);
+    }
+    
+    if renamed.is_file() {
+        anyhow::bail!("Output path {:?} exists and is a file", extract_dir);
+    }
+    Ok(renamed)
+}
+
+/// Extract one archive into its directory under `output_dir`, advancing
+/// `progress_bar` and `batch_bar` by the bytes written
+pub async fn extract_zip_file(
+    zip_file: &ZipFile,
+    output_dir: &Path,
+    options: &ExtractOptions,
+    progress_bar: ProgressBar,
+    batch_bar: &ProgressBar,
+) -> Result<ExtractOutcome> {
+    let file_name = zip_file.path.file_stem().unwrap().to_string_lossy();
+    
+    // Skip archives the --containing scan found nothing in
+    if zip_file.matching_entries == Some(0) {
+        progress_bar.finish_with_message(format!("Skipped (no matching entries): {}", file_name));
+        batch_bar.inc(zip_file.uncompressed_size);
+        return Ok(ExtractOutcome::skipped(ExtractStatus::SkippedFiltered));
+    }
+    
+    if let (Some(limit), Some(entries)) = (options.max_entries, zip_file.entries) {
+        if entries > limit {
+            progress_bar.finish_with_message(format!("Refused (too many entries): {}", file_name));
+            batch_bar.inc(zip_file.uncompressed_size);
+            anyhow::bail!(
+                "entry count limit: {} entries exceeds --max-entries {} (use --force to extract anyway)",
+                entries,
+                limit
+            );
+        }
+    }
+    
+    // `overwrite` removes a file in the way of the directory, which may be an input
+    if let (true, Some(inputs)) = (options.overwrite, &options.inputs) {
+        inputs.check(&output_dir.join(&zip_file.output_name))?;
+    }
+    let extract_dir = resolve_extract_dir(output_dir, &zip_file.output_name, options.overwrite, options.disposer.as_ref())?;
+    // Held until the archive is done, so no other archive writes into the
+    // directory meanwhile
+    let lock = OutputLock::acquire(&extract_dir, options.on_busy).await?;
+    let extract_dir = lock.path();
+    
+    // Skip if directory exists and skip_existing is true
+    if options.skip_existing && !options.update && extract_dir.is_dir() {
+        progress_bar.finish_with_message(format!("Skipped existing: {}", file_name));
+        batch_bar.inc(zip_file.uncompressed_size);
+        return Ok(ExtractOutcome::skipped(ExtractStatus::SkippedExisting));
+    }
+    
+    // Checked before the directory is created, so nothing is left behind
+    let mut password = None;
+    if is_encrypted(&zip_file.path) {
+        let found = match &options.passwords {
+            Some(passwords) => passwords.find(&zip_file.path)?,
+            None => None,
+        };
+        let Some(found) = found else {
+            progress_bar.finish_with_message(format!("Skipped (encrypted): {}", file_name));
+            batch_bar.inc(zip_file.uncompressed_size);
+            let mut outcome = ExtractOutcome::skipped(ExtractStatus::SkippedEncrypted);
+            outcome.password_source = Some(PasswordSource::None);
+            return Ok(outcome);
+        };
+        password = Some(found);
+    }
+    let receipt = match (options.update && options.delete_removed, &options.receipts) {
+        (true, Some(receipts)) => receipts.receipt(zip_file),
+        _ => None,
+    };
+    let reading = Reading {
+        // What an interrupted run wrote here is checked file by file
+        resuming: options.resume && extract_dir.is_dir(),
+        password: password.as_ref().map(|(_, password)| password.as_bytes()),
+        receipt: receipt.as_deref(),
+    };
+    // Only a directory this archive created is removed or moved aside when it fails a check
+    let created = !extract_dir.is_dir();
+    
+    // Create extraction directory
+    fs::create_dir_all(extract_dir)
+        .with_context(|| format!("Failed to create directory {:?}", extract_dir))?;
+    
+    // Open zip file
+    let file = fs::File::open(&zip_file.path)
+        .with_context(|| format!("Failed to open zip file {:?}", zip_file.path))?;
+    
+    // When mapping fails or the file changed meanwhile, read the file instead
+    let mmap = if options.use_mmap { map_unchanged(&file) } else { None };
+    
+    let extracted = match &mmap {
+        Some(map) => ZipArchive::new(GuardedMap::new(map, &file))
+            .with_context(|| format!("Failed to read zip archive {:?}", zip_file.path))
+            .and_then(|mut archive| extract_entries(&mut archive, &zip_file.path, extract_dir, options, reading, &progress_bar, batch_bar)),
+        None => ZipArchive::new(file)
+            .with_context(|| format!("Failed to read zip archive {:?}", zip_file.path))
+            .and_then(|mut archive| extract_entries(&mut archive, &zip_file.path, extract_dir, options, reading, &progress_bar, batch_bar)),
+    };
+    let mut outcome = extracted.map_err(|e| {
+        if is_validation_failure(&e) {
+            handle_failed_output(extract_dir, created, options.on_validation_failure, e)
+        } else {
+            e
+        }
+    })?;
+    
+    outcome.password_source = password.map(|(source, _)| source);
+    
+    progress_bar.finish_with_message(format!("Completed: {}", file_name));
+    Ok(outcome)
+}
+
+/// How [`extract_entries`] reads one archive
+#[derive(Clone, Copy)]
+struct Reading<'a> {
+    /// Into a directory an interrupted run left, keeping the files it finished
+    resuming: bool,
+    /// Opens the encrypted entries
+    password: Option<&'a [u8]>,
+    /// The files the last run extracted from the archive, for `delete_removed`
+    receipt: Option<&'a [String]>,
+}
+
+fn extract_entries<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    zip_path: &Path,
+    extract_dir: &Path,
+    options: &ExtractOptions,
+    reading: Reading,
+    progress_bar: &ProgressBar,
+    batch_bar: &ProgressBar,
+) -> Result<ExtractOutcome> {
+    progress_bar.set_length(total_uncompressed_size(archive)?);
+    let archive_name = zip_path.file_name().unwrap().to_string_lossy();
+    let ownership = &options.ownership;
+    let mut file_types = FileTypeCounts::default();
+    let mut nested_archives = Vec::new();
+    let mut buffer = vec![0; options.buffer_size];
+    let mut chown_failures = ChownFailures::default();
+    let mut time_clamps = TimeClamps::default();
+    let mut update = UpdateCounts::default();
+    let mut extracted = Vec::new();
+    // Applied last, so a directory made read-only still receives its files
+    let mut dir_modes = Vec::new();
+    let hook_batch = options.entry_hooks.as_ref().map(|_| HookBatch::new());
+    ownership.apply(extract_dir, true, &[], &mut chown_failures)?;
+    
+    // Flattening needs every entry name up front to rule out collisions
+    let plan = plan_entry_paths(
+        &read_entry_names(archive, zip_path)?,
+        options.flatten_depth,
+        options.normalize_extensions.as_ref(),
+        options.depth_limit,
+    );
+    let (normalized_paths, flatten) = (&plan.normalized, &plan.flatten);
+    if let (false, Some(long)) = (options.truncate_long_names, plan.truncated.first()) {
+        anyhow::bail!(
+            "entry {:?} has a path component over the {}-byte limit of most filesystems (--truncate-long-names shortens it)",
+            long.original,
+            MAX_COMPONENT_BYTES
+        );
+    }
+    // Checked before anything is written, e.g. an entry named like an archive
+    // still in the queue when the output is the input directory
+    if let Some(inputs) = &options.inputs {
+        for name in plan.paths.iter().flatten().filter(|name| !name.ends_with('/')) {
+            inputs.check(&extract_dir.join(name))?;
+        }
+    }
+    
+    // Extract all files
+    let mut visited = 0;
+    for (i, name) in plan.paths.iter().enumerate() {
+        visited += 1;
+        let Some(name) = name else { continue };
+        let file = match reading.password {
+            Some(password) => archive.by_index_decrypt(i, password).map_err(anyhow::Error::from).and_then(|file| Ok(file?)),
+            None => archive.by_index(i).map_err(anyhow::Error::from),
+        };
+        let mut file = file.with_context(|| format!("Failed to read file at index {} in {:?}", i, zip_path))?;
+        if options.skip_signatures && is_signature_file(file.name()) {
+            progress_bar.inc(file.size());
+            batch_bar.inc(file.size());
+            continue;
+        }
+        let outpath = extract_dir.join(name);
+        if !name.ends_with('/') {
+            extracted.push(name.clone());
+        }
+        file_types.record(name);
+        progress_bar.set_message(format!("{}: {}", archive_name, name));
+        
+        if name.ends_with('/') {
+            create_dirs(&outpath, ownership, &mut chown_failures)?;
+            ownership.apply(&outpath, true, file.extra_data(), &mut chown_failures)?;
+            if let (true, None, Some(mode)) = (options.preserve.perms, ownership.mode, file.unix_mode()) {
+                dir_modes.push((outpath, mode));
+            }
+        } else {
+            let modified = time_clamps.entry_time(name, file.last_modified(), options.strict_times)?;
+            if options.update {
+                match fs::metadata(&outpath) {
+                    Ok(existing) if existing.is_file() => {
+                        let stale = existing.len() != file.size()
+                            || existing.modified().map_or(true, |on_disk| modified > on_disk);
+                        if !stale {
+                            update.unchanged += 1;
+                            progress_bar.inc(file.size());
+                            batch_bar.inc(file.size());
+                            continue;
+                        }
+                        update.updated += 1;
+                    }
+                    _ => update.new += 1,
+                }
+            }
+            // The interrupted run may have written this file in full, in part or not at all
+            let intact = reading.resuming && match fs::metadata(&outpath) {
+                Ok(existing) if existing.is_file() => {
+                    let intact = existing.len() == file.size()
+                        && file_crc32(&outpath).is_ok_and(|crc| crc == file.crc32());
+                    if intact {
+                        update.unchanged += 1;
+                    } else {
+                        update.updated += 1;
+                    }
+                    intact
+                }
+                _ => {
+                    update.new += 1;
+                    false
+                }
+            };
+            let outfile = if intact {
+                progress_bar.inc(file.size());
+                batch_bar.inc(file.size());
+                // Its time is set before its mode, so a file still showing the
+                // wrong time hasn't been made read-only yet
+                let on_disk = fs::metadata(&outpath).and_then(|m| m.modified()).ok();
+                if options.preserve.times && on_disk != Some(modified) {
+                    fs::File::options()
+                        .write(true)
+                        .open(&outpath)
+                        .and_then(|outfile| outfile.set_modified(modified))
+                        .with_context(|| format!("Failed to set modification time of {:?}", outpath))?;
+                }
+                None
+            } else {
+                if let Some(p) = outpath.parent() {
+                    create_dirs(p, ownership, &mut chown_failures)?;
+                }
+                
+                let mut outfile = fs::File::create(&outpath)
+                    .with_context(|| format!("Failed to create file {:?}", outpath))?;
+                
+                let copied = copy_with_hooks(&mut file, &mut outfile, &mut buffer, options.control.as_deref(), |chunk| {
+                    progress_bar.inc(chunk.len() as u64);
+                    batch_bar.inc(chunk.len() as u64);
+                });
+                if let Err(e) = copied {
+                    // Don't leave a truncated file looking like a finished one
+                    if is_cancelled(&e) {
+                        let _ = fs::remove_file(&outpath);
+                    }
+                    return Err(e).with_context(|| format!("Failed to write file {:?}", outpath));
+                }
+                if options.preserve.times {
+                    outfile.set_modified(modified)
+                        .with_context(|| format!("Failed to set modification time of {:?}", outpath))?;
+                }
+                Some(outfile)
+            };
+            // --chmod wins over the recorded mode
+            if let (true, Some(mode)) = (options.preserve.perms, file.unix_mode()) {
+                set_mode(&outpath, mode)?;
+            }
+            ownership.apply(&outpath, false, file.extra_data(), &mut chown_failures)?;
+            if is_archive(name) {
+                nested_archives.push(outpath.to_string_lossy().into_owned());
+            }
+            // Closed first, as some scanners can't open a file still being written
+            drop(outfile);
+            if let (Some(runner), Some(batch)) = (&options.entry_hooks, &hook_batch) {
+                runner.queue(batch, outpath);
+            }
+        }
+    }
+    // Before pruning, which a file the hook deleted may leave work for
+    let hooks = hook_batch.map(|batch| batch.wait()).unwrap_or_default();
+    
+    // A zip64 archive misread as a classic one lists only its first 65,535 entries
+    check_entry_count(zip_path, archive.len(), visited).context(ValidationFailed)?;
+    
+    if options.update && options.delete_removed {
+        match reading.receipt {
+            Some(receipt) => update.deleted = delete_removed(extract_dir, receipt, &plan.paths, options.disposer.as_ref())?,
+            None => progress_bar.suspend(|| {
+                eprintln!("⚠️  {}: no earlier extraction recorded in the state file, so --delete-removed removed nothing", archive_name)
+            }),
+        }
+    }
+    // Before the recorded modes, which can make a parent read-only
+    let pruned = if options.prune_empty { prune_empty_dirs(extract_dir)? } else { 0 };
+    for (dir, mode) in dir_modes.iter().rev().filter(|(dir, _)| dir.is_dir()) {
+        set_mode(dir, *mode)?;
+    }
+    
+    let mut warnings = ArchiveWarnings::new(progress_bar);
+    if let Some(example) = &normalized_paths.example {
+        warnings.raise(
+            WarningCategory::SanitizedNames,
+            normalized_paths.count,
+            format!(
+                "Normalized {} entry paths in {} (Windows separators or trailing spaces), e.g. {:?}",
+                normalized_paths.count, archive_name, example
+            ),
+        );
+    }
+    if let Some(example) = &plan.renamed_extensions.example {
+        warnings.raise(
+            WarningCategory::NormalizedExtensions,
+            plan.renamed_extensions.count,
+            format!("Renamed {} extensions in {}, e.g. {}", plan.renamed_extensions.count, archive_name, example),
+        );
+    }
+    for name in &plan.renamed_extensions.collisions {
+        warnings.raise(
+            WarningCategory::ExtensionCollisions,
+            1,
+            format!("{}: several entries extract to {:?} once their extensions are normalized; the last one is kept", archive_name, name),
+        );
+    }
+    if options.update {
+        progress_bar.suspend(|| {
+            eprintln!(
+                "🔄 {}: {} new, {} updated, {} unchanged, {} deleted",
+                archive_name, update.new, update.updated, update.unchanged, update.deleted
+            )
+        });
+    }
+    if reading.resuming {
+        progress_bar.suspend(|| {
+            eprintln!(
+                "⏯️  {}: resumed, {} files intact, {} rewritten, {} new",
+                archive_name, update.unchanged, update.updated, update.new
+            )
+        });
+    }
+    if pruned > 0 {
+        progress_bar.suspend(|| eprintln!("🧹 Removed {} empty directories in {}", pruned, archive_name));
+    }
+    if let Some(example) = &plan.unsafe_paths.example {
+        warnings.raise(
+            WarningCategory::UnsafePaths,
+            plan.unsafe_paths.count,
+            format!(
+                "Skipped {} entries in {} whose path leaves the extraction directory, e.g. {:?}",
+                plan.unsafe_paths.count, archive_name, example
+            ),
+        );
+    }
+    if let Some(first) = plan.truncated.first() {
+        warnings.raise(
+            WarningCategory::ShortenedNames,
+            plan.truncated.len(),
+            format!(
+                "Shortened {} entry paths too long for the filesystem in {}, e.g. to {:?}",
+                plan.truncated.len(), archive_name, first.truncated
+            ),
+        );
+    }
+    if let Some(first) = hooks.failures.first() {
+        warnings.raise(
+            WarningCategory::HookFailures,
+            hooks.failed,
+            format!(
+                "{} of {} files in {} failed the entry hook{}, e.g. {:?}: {}",
+                hooks.failed,
+                hooks.passed + hooks.failed,
+                archive_name,
+                if first.deleted { " and were removed" } else { "" },
+                first.path,
+                first.error
+            ),
+        );
+    }
+    if flatten.levels > 0 {
+        progress_bar.suspend(|| eprintln!("📁 Flattened up to {} directory levels in {}", flatten.levels, archive_name));
+    }
+    for stopped in &flatten.stopped {
+        warnings.raise(WarningCategory::FlattenStopped, 1, format!("{}: {}", archive_name, stopped));
+    }
+    if let (Some(limit), Some(example)) = (options.depth_limit, &plan.overdeep.example) {
+        warnings.raise(
+            WarningCategory::OverdeepEntries,
+            plan.overdeep.count(),
+            format!(
+                "{} {} entries deeper than {} levels in {} (deepest {}), e.g. {}",
+                if limit.flatten { "Collapsed" } else { "Skipped" },
+                plan.overdeep.count(),
+                limit.max_depth,
+                archive_name,
+                plan.overdeep.deepest,
+                truncate_middle(example, 160)
+            ),
+        );
+    }
+    if let Some(example) = &time_clamps.example {
+        warnings.raise(
+            WarningCategory::ClampedTimestamps,
+            time_clamps.count,
+            format!("Clamped {} invalid entry timestamps in {}, e.g. {:?}", time_clamps.count, archive_name, example),
+        );
+    }
+    if let Some(first) = &chown_failures.first {
+        warnings.raise(
+            WarningCategory::ChownFailed,
+            chown_failures.count,
+            format!(
+                "Could not change the owner of {} entries in {} (not running as root?), e.g. {}",
+                chown_failures.count, archive_name, first
+            ),
+        );
+    }
+    
+    Ok(ExtractOutcome {
+        status: ExtractStatus::Extracted,
+        file_types,
+        nested_archives,
+        levels_flattened: plan.flatten.levels,
+        flatten_stopped: plan.flatten.stopped,
+        update,
+        resumed: reading.resuming,
+        truncated_names: plan.truncated,
+        renamed_extensions: plan.renamed_extensions,
+        overdeep: plan.overdeep,
+        hooks,
+        password_source: None,
+        warnings: warnings.into_raised(),
+        extracted,
+    })
+}
+
+/// Remove the files of `receipt`, what the last run extracted from the
+/// archive, that none of `paths` extracts to now, returning how many were
+/// removed. Files anyone else put under `extract_dir` are never touched, nor
+/// is anything reached through a symlink out of it.
+fn delete_removed(extract_dir: &Path, receipt: &[String], paths: &[Option<String>], disposer: &dyn Disposer) -> Result<usize> {
+    let expected: HashSet<&str> = paths.iter().flatten().map(|path| path.trim_end_matches('/')).collect();
+    let root = fs::canonicalize(extract_dir)
+        .with_context(|| format!("Failed to resolve {:?}", extract_dir))?;
+    let mut deleted = 0;
+    for path in receipt.iter().filter(|path| is_contained(path) && !expected.contains(path.as_str())) {
+        let outpath = extract_dir.join(path);
+        let inside = outpath.parent().and_then(|parent| fs::canonicalize(parent).ok()).is_some_and(|parent| parent.starts_with(&root));
+        if inside && fs::symlink_metadata(&outpath).is_ok_and(|metadata| metadata.is_file()) {
+            disposer.dispose(&outpath)?;
+            deleted += 1;
+        }
+    }
+    Ok(deleted)
+}
+
+/// Remove every directory under `extract_dir` that is empty, deepest first so
+/// a parent emptied by removing its children goes too. `extract_dir` itself
+/// stays, and symlinks are not followed out of it. Returns how many were removed.
+fn prune_empty_dirs(extract_dir: &Path) -> Result<usize> {
+    let mut pruned = 0;
+    for entry in WalkDir::new(extract_dir).min_depth(1).contents_first(true).into_iter().filter_map(|e| e.ok()) {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let empty = fs::read_dir(entry.path())
+            .with_context(|| format!("Failed to read directory {:?}", entry.path()))?
+            .next()
+            .is_none();
+        if empty {
+            fs::remove_dir(entry.path())
+                .with_context(|| format!("Failed to remove empty directory {:?}", entry.path()))?;
+            pruned += 1;
+        }
+    }
+    Ok(pruned)
+}
+
+/// Create `dir` and any missing parents, applying `ownership` to each one created
+fn create_dirs(dir: &Path, ownership: &Ownership, chown_failures: &mut ChownFailures) -> Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    let missing: Vec<&Path> = dir.ancestors().take_while(|p| !p.exists()).collect();
+    fs::create_dir_all(dir)
+        .with_context(|| format!("Failed to create directory {:?}", dir))?;
+    if !ownership.is_empty() {
+        for created in missing.into_iter().rev() {
+            ownership.apply(created, true, &[], chown_failures)?;
+        }
+    }
+    Ok(())
+}
+
+/// Put each archive's extraction directory in a folder for its kind, e.g.
+/// `comics/<name>`; applied after any name template
+pub fn apply_type_labels(zip_files: &mut [ZipFile]) {
+    for zip_file in zip_files {
+        zip_file.output_name = format!("{}/{}", zip_file.kind.directory(), zip_file.output_name);
+    }
+}
+
+/// With several roots, put each archive's extraction directory under its
+/// root's label, e.g. `incoming/<name>`, so same-named archives of different
+/// roots don't meet; applied last, so the label is outermost
+pub fn apply_root_labels(zip_files: &mut [ZipFile], roots: &ScanRoots) {
+    if !roots.is_multiple() {
+        return;
+    }
+    for zip_file in zip_files {
+        zip_file.output_name = format!("{}/{}", roots.root_of(&zip_file.path).label, zip_file.output_name);
+    }
+}
+
+/// File each archive's extraction directory under `YYYY/MM/` by date; applied
+/// after the name template and type labels, so the date is outermost
+pub fn apply_date_buckets(zip_files: &mut [ZipFile], source: DateSource) {
+    for zip_file in zip_files {
+        let bucket = date_bucket(&zip_file.path, zip_file.mtime, source);
+        zip_file.output_name = format!("{}/{}", bucket, zip_file.output_name);
+    }
+}
+
+/// Name each archive's extraction directory from the template, numbering names
+/// that several archives would otherwise share
+pub fn apply_name_template(zip_files: &mut [ZipFile], template: &NameTemplate) -> Result<()> {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for zip_file in zip_files {
+        let name = template.render(&zip_file.path)?;
+        let count = seen.entry(name.to_lowercase()).or_insert(0);
+        *count += 1;
+        zip_file.output_name = if *count == 1 { name } else { format!("{} ({})", name, count) };
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disposal::RecordingDisposer;
+    use crate::input_guard::WouldClobberInput;
+    use crate::scan_progress::STAGING_PREFIX;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// Write a zip of `entries` to `path`, its central directory claiming
+    /// MS-DOS as the host system when `dos`
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])], dos: bool) {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        let mut bytes = zip.finish().unwrap().into_inner();
+        if dos {
+            // Central directory headers: signature, then "version made by": spec version, host
+            for i in 0..bytes.len() - 4 {
+                if bytes[i..i + 4] == *b"PK\x01\x02" {
+                    bytes[i + 5] = 0;
+                }
+            }
+        }
+        fs::write(path, bytes).unwrap();
+    }
+
+    async fn extract(zip_path: &Path, output_dir: &Path, options: &ExtractOptions) -> Result<ExtractOutcome> {
+        let zip_file = zip_files_at(&[zip_path.to_path_buf()], None).unwrap().remove(0);
+        extract_zip_file(&zip_file, output_dir, options, ProgressBar::hidden(), &ProgressBar::hidden()).await
+    }
+
+    fn files_under(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = WalkDir::new(dir)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| entry.into_path())
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Extract `zip_path` with `options`, recording it in `journal` as a run would
+    async fn extract_recorded(zip_path: &Path, output_dir: &Path, options: &ExtractOptions, journal: &StateJournal) -> ExtractOutcome {
+        let zip_file = zip_files_at(&[zip_path.to_path_buf()], None).unwrap().remove(0);
+        let outcome = extract_zip_file(&zip_file, output_dir, options, ProgressBar::hidden(), &ProgressBar::hidden()).await.unwrap();
+        journal.record(&zip_file, &outcome.extracted).unwrap();
+        outcome
+    }
+
+    #[tokio::test]
+    async fn delete_removed_keeps_files_it_never_extracted() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        let journal = Arc::new(StateJournal::open(dir.path().join("state"), false).unwrap());
+        write_zip(&zip_path, &[("keep.txt", b"v1"), ("sub/old.txt", b"v1")], false);
+        extract_recorded(&zip_path, &output, &ExtractOptions::default(), &journal).await;
+        fs::write(output.join("a/MY_NOTES.txt"), b"mine").unwrap();
+        fs::write(output.join("a/sub/notes.txt"), b"mine").unwrap();
+
+        write_zip(&zip_path, &[("keep.txt", b"v2!")], false);
+        let options = ExtractOptions { update: true, delete_removed: true, receipts: Some(journal.clone()), ..ExtractOptions::default() };
+        let outcome = extract_recorded(&zip_path, &output, &options, &journal).await;
+
+        assert_eq!(outcome.update.deleted, 1);
+        assert!(!output.join("a/sub/old.txt").exists());
+        assert_eq!(fs::read(output.join("a/keep.txt")).unwrap(), b"v2!");
+        assert_eq!(fs::read(output.join("a/MY_NOTES.txt")).unwrap(), b"mine");
+        assert_eq!(fs::read(output.join("a/sub/notes.txt")).unwrap(), b"mine");
+        assert_eq!(journal.receipt(&zip_files_at(&[zip_path], None).unwrap()[0]).unwrap(), ["keep.txt"]);
+    }
+
+    #[tokio::test]
+    async fn delete_removed_without_a_receipt_removes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        write_zip(&zip_path, &[("keep.txt", b"v1"), ("old.txt", b"v1")], false);
+        extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+
+        write_zip(&zip_path, &[("keep.txt", b"v1")], false);
+        let journal = Arc::new(StateJournal::open(dir.path().join("state"), false).unwrap());
+        let options = ExtractOptions { update: true, delete_removed: true, receipts: Some(journal), ..ExtractOptions::default() };
+        let outcome = extract(&zip_path, &output, &options).await.unwrap();
+
+        assert_eq!(outcome.update.deleted, 0);
+        assert!(output.join("a/old.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn mapped_archives_extract_like_read_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("a.zip");
+        write_zip(&zip_path, &[("one.txt", b"1"), ("sub/two.txt", b"22")], false);
+
+        let options = ExtractOptions { use_mmap: true, ..ExtractOptions::default() };
+        extract(&zip_path, &dir.path().join("mapped"), &options).await.unwrap();
+        extract(&zip_path, &dir.path().join("read"), &ExtractOptions::default()).await.unwrap();
+
+        assert_eq!(fs::read(dir.path().join("mapped/a/sub/two.txt")).unwrap(), b"22");
+        assert_eq!(fs::read(dir.path().join("mapped/a/one.txt")).unwrap(), fs::read(dir.path().join("read/a/one.txt")).unwrap());
+    }
+
+    #[tokio::test]
+    async fn dos_parent_entries_stay_inside_the_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("evil.zip");
+        let output = dir.path().join("nested/out");
+        write_zip(
+            &zip_path,
+            &[("..\\..\\PWNED.txt", b"pwned"), ("dir\\..\\..\\..\\PWNED.txt", b"pwned"), ("\\PWNED.txt", b"pwned"), ("dir\\ok.txt", b"ok")],
+            true,
+        );
+
+        let outcome = extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+
+        assert_eq!(files_under(dir.path()), [zip_path.clone(), output.join("evil/dir/ok.txt")]);
+        let warning = outcome.warnings.iter().find(|warning| warning.category == WarningCategory::UnsafePaths).unwrap();
+        assert_eq!(warning.count, 3);
+    }
+
+    #[tokio::test]
+    async fn a_file_named_like_the_directory_is_kept_unless_overwriting() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("photos.zip"), dir.path().join("out"));
+        write_zip(&zip_path, &[("one.jpg", b"1")], false);
+        fs::create_dir_all(&output).unwrap();
+        fs::write(output.join("photos"), b"mine").unwrap();
+
+        extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+        assert_eq!(fs::read(output.join("photos")).unwrap(), b"mine");
+        assert_eq!(fs::read(output.join("photos (extracted)/one.jpg")).unwrap(), b"1");
+
+        let options = ExtractOptions { overwrite: true, ..ExtractOptions::default() };
+        extract(&zip_path, &output, &options).await.unwrap();
+        assert_eq!(fs::read(output.join("photos/one.jpg")).unwrap(), b"1");
+    }
+
+    #[tokio::test]
+    async fn a_file_in_the_way_of_both_names_fails_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("photos.zip"), dir.path().join("out"));
+        write_zip(&zip_path, &[("one.jpg", b"1")], false);
+        fs::create_dir_all(&output).unwrap();
+        fs::write(output.join("photos"), b"mine").unwrap();
+        fs::write(output.join("photos (extracted)"), b"also mine").unwrap();
+
+        let error = extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap_err();
+
+        assert!(format!("{:#}", error).contains("exists and is a file"), "{:#}", error);
+        assert_eq!(fs::read(output.join("photos")).unwrap(), b"mine");
+        assert_eq!(fs::read(output.join("photos (extracted)")).unwrap(), b"also mine");
+    }
+
+    #[tokio::test]
+    async fn a_second_scan_leaves_out_what_the_first_run_wrote() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("outer.zip"), dir.path().join("extracted"));
+        write_zip(&dir.path().join("inner.zip"), &[("a.txt", b"a")], false);
+        let inner = fs::read(dir.path().join("inner.zip")).unwrap();
+        fs::remove_file(dir.path().join("inner.zip")).unwrap();
+        write_zip(&zip_path, &[("inner.zip", &inner)], false);
+        fs::create_dir(dir.path().join(format!("{}partial", STAGING_PREFIX))).unwrap();
+        fs::write(dir.path().join(format!("{}partial/half.zip", STAGING_PREFIX)), &inner).unwrap();
+
+        let first = find_zip_files(dir.path(), None, &mut ScanMonitor::new().excluding_output(Some(&output))).await.unwrap();
+        extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+        assert!(output.join("outer/inner.zip").is_file());
+        // Given another way, the output is still recognised
+        let output = dir.path().join(".").join("extracted");
+        let second = find_zip_files(dir.path(), None, &mut ScanMonitor::new().excluding_output(Some(&output))).await.unwrap();
+
+        let paths = |zip_files: &[ZipFile]| zip_files.iter().map(|f| f.path.clone()).collect::<Vec<_>>();
+        assert_eq!(paths(&first), [zip_path]);
+        assert_eq!(paths(&second), paths(&first));
+        assert_eq!(find_zip_files(dir.path(), None, &mut ScanMonitor::new()).await.unwrap().len(), 2);
+    }
+
+    #[test]
+    fn empty_truncated_and_rar_archives_are_suspect() {
+        let dir = tempfile::tempdir().unwrap();
+        let health = |name: &str, bytes: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, bytes).unwrap();
+            check_archive_health(&path, bytes.len() as u64)
+        };
+        write_zip(&dir.path().join("ok.zip"), &[("a.txt", b"a")], false);
+        let zip = fs::read(dir.path().join("ok.zip")).unwrap();
+
+        assert_eq!(health("ok.zip", &zip), ArchiveHealth::Ok);
+        assert_eq!(health("empty.zip", b""), ArchiveHealth::Empty);
+        assert_eq!(health("small.zip", b"PK\x03\x04"), ArchiveHealth::TooSmall);
+        // A download cut off before the central directory
+        assert_eq!(health("cut.zip", &zip[..zip.len() - 30]), ArchiveHealth::NoCentralDirectory);
+        assert_eq!(health("comic.cbr", b"Rar!\x1a\x07\x01\x00 and the rest"), ArchiveHealth::Rar);
+    }
+
+    #[test]
+    fn suspect_archives_are_extracted_only_when_asked_and_rar_never() {
+        assert!(ArchiveHealth::Ok.should_extract(false));
+        for health in [ArchiveHealth::Empty, ArchiveHealth::TooSmall, ArchiveHealth::NoCentralDirectory] {
+            assert!(!health.should_extract(false));
+            assert!(health.should_extract(true));
+        }
+        assert!(!ArchiveHealth::Rar.should_extract(true));
+    }
+
+    #[test]
+    fn templated_names_several_archives_share_are_numbered() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths: Vec<PathBuf> = ["a/report.zip", "b/report.zip", "c/Report.zip", "c/other.zip"].iter().map(|name| dir.path().join(name)).collect();
+        for path in &paths {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            write_zip(path, &[("a.txt", b"a")], false);
+        }
+        let mut zip_files = zip_files_at(&paths, None).unwrap();
+
+        apply_name_template(&mut zip_files, &NameTemplate::parse("{stem}").unwrap()).unwrap();
+
+        let names: Vec<&str> = zip_files.iter().map(|f| f.output_name.as_str()).collect();
+        // Compared ignoring case, for case-insensitive filesystems
+        assert_eq!(names, ["report", "report (2)", "Report (3)", "other"]);
+    }
+
+    #[tokio::test]
+    async fn zip64_archives_extract_every_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("many.zip"), dir.path().join("out"));
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for i in 0..70_000 {
+            zip.start_file(format!("{}/{}.txt", i / 1000, i), stored).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        fs::write(&zip_path, zip.finish().unwrap().into_inner()).unwrap();
+        assert!(zip_files_at(std::slice::from_ref(&zip_path), None).unwrap()[0].zip64);
+
+        extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+
+        assert_eq!(files_under(&output).len(), 70_000);
+        assert_eq!(fs::read(output.join("many/69/69999.txt")).unwrap(), b"x");
+    }
+
+    /// Writes and extracts a 4 GiB entry; run with `cargo test --release -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn zip64_entries_past_4_gib_extract_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("large.zip"), dir.path().join("out"));
+        let size = u32::MAX as u64 + 4096;
+        let mut zip = ZipWriter::new(fs::File::create(&zip_path).unwrap());
+        zip.start_file("small.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"before").unwrap();
+        zip.start_file("large.bin", FileOptions::default().large_file(true)).unwrap();
+        let chunk = vec![0u8; 1 << 20];
+        let mut written = 0;
+        while written < size {
+            let len = chunk.len().min((size - written) as usize);
+            zip.write_all(&chunk[..len]).unwrap();
+            written += len as u64;
+        }
+        zip.start_file("after.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"after").unwrap();
+        zip.finish().unwrap();
+
+        extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+
+        assert_eq!(fs::metadata(output.join("large/large.bin")).unwrap().len(), size);
+        assert_eq!(fs::read(output.join("large/small.txt")).unwrap(), b"before");
+        assert_eq!(fs::read(output.join("large/after.txt")).unwrap(), b"after");
+    }
+
+    #[tokio::test]
+    async fn over_long_names_are_truncated_or_fail_the_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        let long = format!("{}.txt", "名".repeat(100));
+        write_zip(&zip_path, &[(&format!("docs/{}", long), b"long"), ("docs/short.txt", b"short")], false);
+
+        let outcome = extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap();
+        let truncated = crate::long_names::truncate_component(&long, crate::long_names::MAX_COMPONENT_BYTES);
+        assert_eq!(fs::read(output.join("a/docs").join(&truncated)).unwrap(), b"long");
+        assert_eq!(fs::read(output.join("a/docs/short.txt")).unwrap(), b"short");
+        assert_eq!(outcome.truncated_names, [TruncatedName { original: format!("docs/{}", long), truncated: format!("docs/{}", truncated) }]);
+
+        let options = ExtractOptions { truncate_long_names: false, ..ExtractOptions::default() };
+        let error = extract(&zip_path, &dir.path().join("strict"), &options).await.unwrap_err();
+        assert!(format!("{:#}", error).contains("over the 255-byte limit"), "{:#}", error);
+    }
+
+    #[tokio::test]
+    async fn entries_named_like_a_queued_archive_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        let (first, second) = (dir.path().join("a.zip"), dir.path().join("a/second.zip"));
+        write_zip(&first, &[("second.zip", b"not an archive"), ("notes.txt", b"notes")], false);
+        write_zip(&second, &[("song.mp3", b"audio")], false);
+        let second_bytes = fs::read(&second).unwrap();
+        // The output is the input directory, so `a.zip` extracts into `a/`
+        let inputs = InputSet::new([first.as_path(), second.as_path()]);
+        let options = ExtractOptions { inputs: Some(Arc::new(inputs)), ..ExtractOptions::default() };
+
+        let error = extract(&first, dir.path(), &options).await.unwrap_err();
+        let clobber = error.downcast_ref::<WouldClobberInput>().unwrap();
+        assert_eq!(clobber.input, fs::canonicalize(&second).unwrap());
+        assert!(!dir.path().join("a/notes.txt").exists());
+
+        assert_eq!(fs::read(&second).unwrap(), second_bytes);
+        extract(&second, dir.path(), &options).await.unwrap();
+        assert_eq!(fs::read(dir.path().join("second/song.mp3")).unwrap(), b"audio");
+    }
+
+    #[tokio::test]
+    async fn removals_go_through_the_disposer() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        let journal = Arc::new(StateJournal::open(dir.path().join("state"), false).unwrap());
+        write_zip(&zip_path, &[("keep.txt", b"v1"), ("old.txt", b"v1")], false);
+        extract_recorded(&zip_path, &output, &ExtractOptions::default(), &journal).await;
+
+        let disposer = Arc::new(RecordingDisposer::default());
+        write_zip(&zip_path, &[("keep.txt", b"v2")], false);
+        let options = ExtractOptions {
+            update: true,
+            delete_removed: true,
+            receipts: Some(journal.clone()),
+            disposer: disposer.clone(),
+            ..ExtractOptions::default()
+        };
+        extract_recorded(&zip_path, &output, &options, &journal).await;
+        assert_eq!(disposer.disposed(), [output.join("a/old.txt")]);
+
+        // A file in the way of the archive's directory
+        let other = dir.path().join("other");
+        fs::create_dir(&other).unwrap();
+        fs::write(other.join("a"), b"in the way").unwrap();
+        let options = ExtractOptions { overwrite: true, disposer: disposer.clone(), ..ExtractOptions::default() };
+        extract(&zip_path, &other, &options).await.unwrap();
+        assert_eq!(disposer.disposed(), [output.join("a/old.txt"), other.join("a")]);
+        assert_eq!(fs::read(other.join("a/keep.txt")).unwrap(), b"v2");
+    }
+
+    #[tokio::test]
+    async fn failed_removals_keep_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        write_zip(&zip_path, &[("keep.txt", b"v1")], false);
+        fs::create_dir(&output).unwrap();
+        fs::write(output.join("a"), b"in the way").unwrap();
+
+        let options = ExtractOptions { overwrite: true, disposer: Arc::new(RecordingDisposer::failing()), ..ExtractOptions::default() };
+        let error = extract(&zip_path, &output, &options).await.unwrap_err();
+
+        assert!(format!("{:#}", error).contains("to the trash"), "{:#}", error);
+        assert_eq!(fs::read(output.join("a")).unwrap(), b"in the way");
+    }
+
+    #[test]
+    fn the_journal_keys_on_the_bucketed_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("camera.zip");
+        write_zip(&zip_path, &[("a.jpg", b"jpeg")], false);
+        let journal = StateJournal::open(dir.path().join("state"), false).unwrap();
+        let mut zip_files = zip_files_at(std::slice::from_ref(&zip_path), None).unwrap();
+        apply_date_buckets(&mut zip_files, DateSource::Archive);
+        assert_eq!(zip_files[0].output_name.matches('/').count(), 2);
+        journal.record(&zip_files[0], &["a.jpg".to_string()]).unwrap();
+        assert!(journal.contains(&zip_files[0]));
+
+        // The same archive filed under another month is extracted again
+        let mut moved = zip_files[0].clone();
+        moved.output_name = "1999/01/camera".to_string();
+        assert!(!journal.contains(&moved));
+        // Nor does an unbucketed run trust it
+        let plain = zip_files_at(&[zip_path], None).unwrap();
+        assert!(!journal.contains(&plain[0]));
+    }
+
+    /// A stored zip of two entries whose last one's data no longer matches its CRC-32
+    fn write_corrupt_zip(path: &Path) {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        zip.start_file("first.txt", stored).unwrap();
+        zip.write_all(b"fine").unwrap();
+        zip.start_file("last.txt", stored).unwrap();
+        zip.write_all(b"damaged at the end").unwrap();
+        let mut bytes = zip.finish().unwrap().into_inner();
+        let at = bytes.windows(7).position(|w| w == b"damaged").unwrap();
+        bytes[at] ^= 0xff;
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_failed_crc_check_leaves_the_output_as_the_policy_says() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("a.zip");
+        write_corrupt_zip(&zip_path);
+
+        for policy in [OnValidationFailure::Remove, OnValidationFailure::Keep, OnValidationFailure::Rename] {
+            let output = dir.path().join(format!("{:?}", policy));
+            let options = ExtractOptions { on_validation_failure: policy, ..ExtractOptions::default() };
+            let error = extract(&zip_path, &output, &options).await.unwrap_err();
+            assert!(is_validation_failure(&error), "{:#}", error);
+
+            let left: Vec<String> = fs::read_dir(&output)
+                .unwrap()
+                .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            match policy {
+                OnValidationFailure::Remove => {
+                    assert!(left.is_empty(), "{:?}", left);
+                    assert!(error.to_string().starts_with("partial output"), "{}", error);
+                    assert!(error.to_string().ends_with("removed"), "{}", error);
+                }
+                OnValidationFailure::Keep => {
+                    assert_eq!(left, ["a"]);
+                    assert!(output.join("a/first.txt").is_file());
+                    assert!(error.to_string().starts_with("partial output kept in"), "{}", error);
+                }
+                OnValidationFailure::Rename => {
+                    assert_eq!(left.len(), 1);
+                    assert!(left[0].starts_with("a.failed-"), "{:?}", left);
+                    assert!(output.join(&left[0]).join("first.txt").is_file());
+                    assert!(error.to_string().starts_with("partial output moved to"), "{}", error);
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failed_check_keeps_a_directory_that_was_there_before() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        write_corrupt_zip(&zip_path);
+        fs::create_dir_all(output.join("a")).unwrap();
+        fs::write(output.join("a/mine.txt"), b"mine").unwrap();
+
+        let options = ExtractOptions { on_validation_failure: OnValidationFailure::Remove, ..ExtractOptions::default() };
+        let error = extract(&zip_path, &output, &options).await.unwrap_err();
+        assert!(error.to_string().contains("existed before this run"), "{}", error);
+        assert_eq!(fs::read(output.join("a/mine.txt")).unwrap(), b"mine");
+    }
+
+    #[tokio::test]
+    async fn normalized_extensions_that_collide_keep_the_last_entry() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        write_zip(&zip_path, &[("Track.MP3", b"first"), ("Track.mp3", b"second"), ("Cover.JPEG", b"art"), ("notes.txt", b"")], false);
+
+        let map = crate::extensions::parse_extension_map("jpeg=jpg").unwrap();
+        let options = ExtractOptions { normalize_extensions: Some(map), ..ExtractOptions::default() };
+        let outcome = extract(&zip_path, &output, &options).await.unwrap();
+        assert_eq!(files_under(&output), [output.join("a/Cover.jpg"), output.join("a/Track.mp3"), output.join("a/notes.txt")]);
+        assert_eq!(fs::read(output.join("a/Track.mp3")).unwrap(), b"second");
+        assert_eq!(outcome.renamed_extensions.count, 2);
+        assert_eq!(outcome.renamed_extensions.collisions, ["Track.mp3"]);
+    }
+
+    #[tokio::test]
+    async fn a_100_deep_entry_is_skipped_or_collapsed() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("a.zip");
+        let deep = format!("{}/leaf.txt", (1..100).map(|i| format!("d{}", i)).collect::<Vec<_>>().join("/"));
+        write_zip(&zip_path, &[("top.txt", b"top"), (deep.as_str(), b"deep")], false);
+
+        // A dry run sees what the extraction will do
+        let zip_files = zip_files_at(std::slice::from_ref(&zip_path), None).unwrap();
+        let skip = ExtractOptions { depth_limit: Some(DepthLimit::new(8, false).unwrap()), ..ExtractOptions::default() };
+        let planned = crate::collisions::find_collisions(&zip_files, &dir.path().join("skipped"), &skip).unwrap();
+        assert_eq!((planned.overdeep.skipped, planned.overdeep.deepest, planned.overdeep_archives), (1, 100, 1));
+        assert!(!dir.path().join("skipped").exists());
+
+        let output = dir.path().join("skipped");
+        let outcome = extract(&zip_path, &output, &skip).await.unwrap();
+        assert_eq!(files_under(&output), [output.join("a/top.txt")]);
+        assert_eq!(outcome.overdeep, planned.overdeep);
+
+        let output = dir.path().join("flattened");
+        let flatten = ExtractOptions { depth_limit: Some(DepthLimit::new(8, true).unwrap()), ..ExtractOptions::default() };
+        let outcome = extract(&zip_path, &output, &flatten).await.unwrap();
+        assert_eq!(outcome.overdeep.flattened, 1);
+        let files = files_under(&output);
+        assert_eq!(files.len(), 2);
+        let leaf = files.iter().find(|file| file.ends_with("leaf.txt")).unwrap();
+        assert_eq!(leaf.strip_prefix(output.join("a")).unwrap().components().count(), 8);
+        assert_eq!(fs::read(leaf).unwrap(), b"deep");
+    }
+
+    #[tokio::test]
+    async fn archives_sharing_a_stem_never_write_one_directory_at_once() {
+        let dir = tempfile::tempdir().unwrap();
+        for parent in ["a", "b"] {
+            fs::create_dir(dir.path().join(parent)).unwrap();
+            write_zip(&dir.path().join(parent).join("x.zip"), &[(&format!("{}.txt", parent), b"data")], false);
+        }
+        let output = dir.path().join("out");
+        // As if a.zip were still extracting into out/x
+        let held = OutputLock::acquire(&output.join("x"), BusyPolicy::Wait).await.unwrap();
+
+        let fail = ExtractOptions { on_busy: BusyPolicy::Fail, ..ExtractOptions::default() };
+        let refused = extract(&dir.path().join("b/x.zip"), &output, &fail).await.unwrap_err();
+        assert!(refused.downcast_ref::<crate::output_lock::OutputBusy>().is_some());
+        assert!(!output.join("x").exists());
+
+        let rename = ExtractOptions { on_busy: BusyPolicy::Rename, ..ExtractOptions::default() };
+        extract(&dir.path().join("b/x.zip"), &output, &rename).await.unwrap();
+        assert_eq!(files_under(&output), [output.join("x (2)/b.txt")]);
+
+        let waiting = tokio::spawn({
+            let (zip_path, output) = (dir.path().join("a/x.zip"), output.clone());
+            async move { extract(&zip_path, &output, &ExtractOptions::default()).await.unwrap() }
+        });
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert!(!waiting.is_finished());
+        assert!(!output.join("x").exists());
+        drop(held);
+        waiting.await.unwrap();
+        assert_eq!(files_under(&output), [output.join("x/a.txt"), output.join("x (2)/b.txt")]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn preserve_keeps_recorded_times_modes_and_owners() {
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("a.zip");
+        let entry = FileOptions::default()
+            .unix_permissions(0o750)
+            .last_modified_time(zip::DateTime::from_date_and_time(2001, 6, 15, 12, 0, 0).unwrap());
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file_with_extra_data("a.txt", entry).unwrap();
+        // Info-ZIP Unix extra field: version 1, 4-byte UID 4321, 4-byte GID 4321
+        zip.write_all(&[0x75, 0x78, 11, 0, 1, 4, 0xe1, 0x10, 0, 0, 4, 0xe1, 0x10, 0, 0]).unwrap();
+        zip.end_extra_data().unwrap();
+        zip.write_all(b"a").unwrap();
+        fs::write(&zip_path, zip.finish().unwrap().into_inner()).unwrap();
+
+        let all = ExtractOptions {
+            preserve: Preserve { times: true, perms: true, owner: true },
+            ownership: Ownership { preserve_owner: true, ..Ownership::default() },
+            ..ExtractOptions::default()
+        };
+        extract(&zip_path, &dir.path().join("all"), &all).await.unwrap();
+        let metadata = fs::metadata(dir.path().join("all/a/a.txt")).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o750);
+        // Within the year whatever the local time zone
+        let modified = metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap();
+        assert!(modified > Duration::from_secs(978_307_200) && modified < Duration::from_secs(1_009_843_200), "{:?}", modified);
+        // Changing the owner takes root
+        if unsafe { libc::geteuid() } == 0 {
+            assert_eq!((metadata.uid(), metadata.gid()), (4321, 4321));
+        }
+
+        let neither = ExtractOptions { preserve: Preserve::default(), ..ExtractOptions::default() };
+        extract(&zip_path, &dir.path().join("neither"), &neither).await.unwrap();
+        let metadata = fs::metadata(dir.path().join("neither/a/a.txt")).unwrap();
+        assert_ne!(metadata.permissions().mode() & 0o7777, 0o750);
+        assert!(metadata.modified().unwrap().duration_since(UNIX_EPOCH).unwrap() > Duration::from_secs(1_009_843_200));
+        assert_ne!(metadata.uid(), 4321);
+    }
+
+    #[tokio::test]
+    async fn every_skip_reports_its_own_status() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("a.zip"), dir.path().join("out"));
+        write_zip(&zip_path, &[("a.txt", b"a")], false);
+
+        let status = |outcome: Result<ExtractOutcome>| outcome.unwrap().status;
+        assert_eq!(status(extract(&zip_path, &output, &ExtractOptions::default()).await), ExtractStatus::Extracted);
+        let skip_existing = ExtractOptions { skip_existing: true, ..ExtractOptions::default() };
+        assert_eq!(status(extract(&zip_path, &output, &skip_existing).await), ExtractStatus::SkippedExisting);
+
+        let containing = Pattern::new("*.mp3").unwrap();
+        let zip_file = zip_files_at(std::slice::from_ref(&zip_path), Some(&containing)).unwrap().remove(0);
+        let outcome = extract_zip_file(&zip_file, &dir.path().join("filtered"), &ExtractOptions::default(), ProgressBar::hidden(), &ProgressBar::hidden()).await;
+        assert_eq!(status(outcome), ExtractStatus::SkippedFiltered);
+
+        // Set the encrypted flag of the local and central headers; with no
+        // password to try, the data is never read
+        let mut bytes = fs::read(&zip_path).unwrap();
+        for i in 0..bytes.len() - 4 {
+            match &bytes[i..i + 4] {
+                b"PK\x03\x04" => bytes[i + 6] |= 1,
+                b"PK\x01\x02" => bytes[i + 8] |= 1,
+                _ => {}
+            }
+        }
+        let encrypted = dir.path().join("e.zip");
+        fs::write(&encrypted, bytes).unwrap();
+        let outcome = extract(&encrypted, &output, &ExtractOptions::default()).await.unwrap();
+        assert_eq!(outcome.status, ExtractStatus::SkippedEncrypted);
+        assert_eq!(outcome.password_source, Some(PasswordSource::None));
+        assert!(!output.join("e").exists());
+    }
+
+    #[tokio::test]
+    async fn prune_empty_leaves_no_empty_directories_behind_filters() {
+        let dir = tempfile::tempdir().unwrap();
+        let (zip_path, output) = (dir.path().join("app.jar"), dir.path().join("out"));
+        write_zip(&zip_path, &[
+            ("keep.txt", b"kept"),
+            ("META-INF/CERT.SF", b"signature"),
+            ("META-INF/CERT.RSA", b"signature"),
+            ("deep/a/b/c/d/e.txt", b"too deep"),
+            ("empty/nested/", b""),
+        ], false);
+        fs::create_dir_all(output.join("unrelated")).unwrap();
+        let dirs_under = |dir: &Path| -> Vec<PathBuf> {
+            let mut dirs: Vec<PathBuf> = WalkDir::new(dir)
+                .min_depth(1)
+                .into_iter()
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.file_type().is_dir())
+                .map(|entry| entry.path().strip_prefix(dir).unwrap().to_path_buf())
+                .collect();
+            dirs.sort();
+            dirs
+        };
+        let filtered = ExtractOptions {
+            skip_signatures: true,
+            depth_limit: Some(DepthLimit { max_depth: 3, flatten: false }),
+            ..ExtractOptions::default()
+        };
+
+        // Skipped entries get no parent directories; the archive's own empty ones stay
+        extract(&zip_path, &output.join("kept"), &filtered).await.unwrap();
+        assert_eq!(dirs_under(&output.join("kept/app")), [PathBuf::from("empty"), PathBuf::from("empty/nested")]);
+
+        let outcome = extract(&zip_path, &output, &ExtractOptions { prune_empty: true, ..filtered }).await.unwrap();
+        assert_eq!(outcome.status, ExtractStatus::Extracted);
+        assert_eq!(files_under(&output.join("app")), [output.join("app/keep.txt")]);
+        assert!(dirs_under(&output.join("app")).is_empty());
+        // Never above the archive's directory
+        assert!(output.join("unrelated").is_dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn pruning_does_not_follow_symlinks_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let (extract_dir, outside) = (dir.path().join("app"), dir.path().join("outside"));
+        fs::create_dir_all(extract_dir.join("a/b/c")).unwrap();
+        fs::create_dir_all(outside.join("empty")).unwrap();
+        std::os::unix::fs::symlink(&outside, extract_dir.join("link")).unwrap();
+
+        assert_eq!(prune_empty_dirs(&extract_dir).unwrap(), 3);
+        assert!(extract_dir.is_dir() && !extract_dir.join("a").exists());
+        assert!(outside.join("empty").is_dir());
+    }
+
+    #[tokio::test]
+    async fn flatten_depth_strips_wrappers_until_paths_would_collide() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("a.zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("delivery/final/v2/a.txt", b"a"),
+                ("delivery/final/v2/sub/a.txt", b"sub a"),
+                ("delivery/final/v2/sub/b.txt", b"sub b"),
+                ("META-INF/CERT.SF", b"signature"),
+                ("delivery/top.txt", b"top"),
+            ],
+            false,
+        );
+        let flatten = ExtractOptions { flatten_depth: 5, ..ExtractOptions::default() };
+
+        // `top.txt` is shallower than the depth and lands at its file name;
+        // `sub/` stays, its `a.txt` would overwrite the other one
+        let output = dir.path().join("flattened");
+        let outcome = extract(&zip_path, &output, &flatten).await.unwrap();
+        let app = output.join("a");
+        assert_eq!(
+            files_under(&output),
+            [app.join("CERT.SF"), app.join("a.txt"), app.join("sub/a.txt"), app.join("sub/b.txt"), app.join("top.txt")]
+        );
+        assert_eq!(fs::read(app.join("a.txt")).unwrap(), b"a");
+        assert_eq!(outcome.levels_flattened, 3);
+        assert_eq!(outcome.flatten_stopped, ["delivery/final/v2/sub/: stopped after 3 levels, \"a.txt\" would collide"]);
+
+        // Signatures are matched by their name in the archive, the depth
+        // limit counts what is left after flattening
+        let filtered = ExtractOptions {
+            skip_signatures: true,
+            depth_limit: Some(DepthLimit { max_depth: 1, flatten: false }),
+            ..flatten
+        };
+        let output = dir.path().join("filtered");
+        extract(&zip_path, &output, &filtered).await.unwrap();
+        assert_eq!(files_under(&output), [output.join("a/a.txt"), output.join("a/top.txt")]);
+    }
+}
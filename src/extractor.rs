@@ -0,0 +1,1102 @@
+use anyhow::{Context, Result};
+use futures::future::join_all;
+use glob::Pattern;
+use regex::Regex;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::archive_groups::{split_superseded, ArchiveGroup, GroupOrder};
+use crate::collisions::{find_collisions, CollisionReport};
+use crate::copy_hooks::CopyControl;
+use crate::date_buckets::DateSource;
+use crate::disposal::{Disposal, Disposer};
+use crate::duplicates::{duplicate_candidate_bytes, split_duplicate_archives, ArchiveDuplicates};
+use crate::entry_depth::{DepthLimit, OverdeepEntries};
+use crate::entry_hooks::{EntryHook, HookCounts, HookRunner, DEFAULT_HOOK_WORKERS};
+use crate::error_summary::error_kind;
+use crate::extensions::{ExtensionMap, RenamedExtensions};
+use crate::extract::{
+    apply_date_buckets, apply_name_template, apply_root_labels, apply_type_labels, extract_zip_file, find_zip_files, settle_zip_files, ExtractOptions, ExtractOutcome,
+    ExtractStatus, UpdateCounts, ZipFile,
+};
+use crate::file_types::FileTypeCounts;
+use crate::fs_limits::{check_target, FsCheck, FsProbe, SystemFs, FS_LIMITATION};
+use crate::input_guard::InputSet;
+use crate::io_profile::{IoProfile, IoSettings, SampledReads, Workload};
+use crate::long_names::TruncatedName;
+use crate::memory_budget::{plan_memory, MemoryPlan};
+use crate::name_template::NameTemplate;
+use crate::ordering::SortOrder;
+use crate::output_lock::BusyPolicy;
+use crate::ownership::Ownership;
+use crate::passwords::{PasswordSource, Passwords};
+use crate::preserve::Preserve;
+use crate::sampling::{Sample, SampleMode, SampleSummary};
+use crate::scan_progress::ScanMonitor;
+use crate::scan_roots::ScanRoots;
+use crate::stability::{may_be_writing, DEFAULT_STABILIZE_TIMEOUT};
+use crate::state_journal::StateJournal;
+use crate::validation_failure::OnValidationFailure;
+use crate::warnings::{StrictMode, Warning, WarningCategory};
+
+/// Progress of an [`Extractor`] run, one event per archive start and finish
+/// and, while planning, the waits before them
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum ProgressEvent<'a> {
+    Started { archive: &'a Path, index: usize, total: usize },
+    Finished { archive: &'a Path, result: &'a ArchiveReport },
+    /// Waiting for this many archives modified moments ago to stop changing
+    Settling { archives: usize },
+    /// `read` more bytes hashed of the `total` that `skip_duplicates` reads
+    Hashing { read: u64, total: u64 },
+}
+
+/// What happened to one archive
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct ArchiveReport {
+    pub path: PathBuf,
+    /// The extraction status, or the error that stopped the archive
+    pub result: Result<ExtractStatus, String>,
+    pub file_types: FileTypeCounts,
+    /// Extracted files that are archives themselves; not extracted further
+    pub nested_archives: Vec<String>,
+    /// Most leading directories removed from an entry by `flatten_depth`
+    pub levels_flattened: usize,
+    /// Branches flattened less than asked to avoid collisions
+    pub flatten_stopped: Vec<String>,
+    /// New, updated, unchanged and deleted files with `update`
+    pub update: UpdateCounts,
+    /// Extracted by `resume` into a directory an interrupted run left
+    pub resumed: bool,
+    /// Entry paths shortened for being too long for the filesystem
+    pub truncated_names: Vec<TruncatedName>,
+    /// Entries whose extension `normalize_extensions` changed
+    pub renamed_extensions: RenamedExtensions,
+    /// Entries skipped or collapsed by `max_entry_depth`
+    pub overdeep: OverdeepEntries,
+    /// Bytes written for this archive
+    pub bytes_written: u64,
+    /// Files the entry hook passed and failed
+    pub hooks: HookCounts,
+    /// For encrypted archives, where the password that opened them came from
+    pub password_source: Option<PasswordSource>,
+    /// What was surprising about it; with `strict`, why it failed
+    pub warnings: Vec<Warning>,
+    /// For a failed archive, the kind of error for grouping; see [`error_kind`]
+    pub error_kind: Option<String>,
+}
+
+impl ArchiveReport {
+    /// With `options.strict`, an archive raising a warning it doesn't except
+    /// fails, keeping what it reported
+    fn new(path: &Path, outcome: Result<ExtractOutcome>, bytes_written: u64, options: &ExtractOptions) -> Self {
+        match outcome {
+            Ok(outcome) => {
+                let strict_failure = options.check_strict(&outcome).err();
+                ArchiveReport {
+                    path: path.to_path_buf(),
+                    result: match &strict_failure {
+                        Some(e) => Err(format!("{:#}", e)),
+                        None => Ok(outcome.status),
+                    },
+                    file_types: outcome.file_types,
+                    nested_archives: outcome.nested_archives,
+                    levels_flattened: outcome.levels_flattened,
+                    flatten_stopped: outcome.flatten_stopped,
+                    update: outcome.update,
+                    resumed: outcome.resumed,
+                    truncated_names: outcome.truncated_names,
+                    renamed_extensions: outcome.renamed_extensions,
+                    overdeep: outcome.overdeep,
+                    bytes_written,
+                    hooks: outcome.hooks,
+                    password_source: outcome.password_source,
+                    warnings: outcome.warnings,
+                    error_kind: strict_failure.as_ref().map(error_kind),
+                }
+            }
+            Err(e) => ArchiveReport {
+                path: path.to_path_buf(),
+                result: Err(format!("{:#}", e)),
+                file_types: FileTypeCounts::default(),
+                nested_archives: Vec::new(),
+                levels_flattened: 0,
+                flatten_stopped: Vec::new(),
+                update: UpdateCounts::default(),
+                resumed: false,
+                truncated_names: Vec::new(),
+                renamed_extensions: RenamedExtensions::default(),
+                overdeep: OverdeepEntries::default(),
+                bytes_written,
+                hooks: HookCounts::default(),
+                password_source: None,
+                warnings: Vec::new(),
+                error_kind: Some(error_kind(&e)),
+            },
+        }
+    }
+
+    /// The outcome it was reported from, as run reports record it; `None`
+    /// when it failed
+    pub fn outcome(&self) -> Option<ExtractOutcome> {
+        let status = self.result.as_ref().ok()?;
+        Some(ExtractOutcome {
+            status: status.clone(),
+            file_types: self.file_types.clone(),
+            nested_archives: self.nested_archives.clone(),
+            levels_flattened: self.levels_flattened,
+            flatten_stopped: self.flatten_stopped.clone(),
+            update: self.update,
+            resumed: self.resumed,
+            truncated_names: self.truncated_names.clone(),
+            renamed_extensions: self.renamed_extensions.clone(),
+            overdeep: self.overdeep.clone(),
+            hooks: self.hooks.clone(),
+            password_source: self.password_source.clone(),
+            warnings: self.warnings.clone(),
+            extracted: Vec::new(),
+        })
+    }
+}
+
+/// Per-archive results of an [`Extractor`] run, in scan order
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct BatchReport {
+    pub archives: Vec<ArchiveReport>,
+    /// Histogram over every extracted archive
+    pub file_types: FileTypeCounts,
+    /// The I/O profile the run used, before any memory limit applied
+    pub io: IoSettings,
+    /// Byte-identical archives of which only the first was extracted
+    pub duplicates: Vec<ArchiveDuplicates>,
+    /// Groups of [`Extractor::latest_per_group`], of which only the latest was extracted
+    pub groups: Vec<ArchiveGroup>,
+    /// How many of the archives found [`Extractor::limit`] kept
+    pub sample: Option<SampleSummary>,
+}
+
+impl BatchReport {
+    pub fn count(&self, status: ExtractStatus) -> usize {
+        self.archives.iter().filter(|a| a.result.as_ref().is_ok_and(|s| *s == status)).count()
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &ArchiveReport> {
+        self.archives.iter().filter(|a| a.result.is_err())
+    }
+
+    /// Archives whose output contains further archives
+    pub fn with_nested_archives(&self) -> impl Iterator<Item = &ArchiveReport> {
+        self.archives.iter().filter(|a| !a.nested_archives.is_empty())
+    }
+}
+
+/// What an [`Extractor`] run will extract, and what it leaves out and why;
+/// from [`Extractor::plan`], for [`Extractor::run`]
+#[non_exhaustive]
+pub struct ExtractionPlan {
+    /// The archives to extract, in order
+    pub archives: Vec<ZipFile>,
+    /// Every archive past the state journal, before the suspect, group,
+    /// limit and duplicate filters left out any
+    pub scanned: Vec<ZipFile>,
+    /// Still changing once the stabilize timeout ran out
+    pub unstable: Vec<ZipFile>,
+    /// Extracted and unchanged since, per the state journal
+    pub processed: Vec<ZipFile>,
+    /// Flagged empty, truncated, without central directory or rar
+    pub suspect: Vec<ZipFile>,
+    pub groups: Vec<ArchiveGroup>,
+    pub sample: Option<SampleSummary>,
+    pub duplicates: Vec<ArchiveDuplicates>,
+    /// The I/O settings, before any memory limit applied
+    pub io: IoSettings,
+    /// Workers and buffers that fit the memory limit
+    pub memory: MemoryPlan,
+    /// What the output filesystem can't store of `archives`; an error when
+    /// the filesystem couldn't be identified, which doesn't stop the run
+    pub output_fs: Result<FsCheck>,
+}
+
+type ProgressCallback = Arc<dyn Fn(ProgressEvent<'_>) + Send + Sync>;
+
+/// Bars an [`Extractor`] draws instead of its hidden ones
+#[derive(Clone)]
+struct ProgressBars {
+    multi: MultiProgress,
+    batch: ProgressBar,
+    style: ProgressStyle,
+}
+
+/// Extracts every zip archive in a directory, each into its own directory
+/// under the output, with the same behaviour as `bulk_unzip unzip`.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use bulk_unzip::{Extractor, ProgressEvent};
+///
+/// let report = Extractor::new()
+///     .output("/srv/extracted")
+///     .workers(8)
+///     .skip_existing(true)
+///     .progress(|event| {
+///         if let ProgressEvent::Finished { archive, .. } = event {
+///             println!("done: {:?}", archive);
+///         }
+///     })
+///     .extract_dir("/srv/incoming")
+///     .await?;
+/// println!("{} archives failed", report.failed().count());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Extractor {
+    output: PathBuf,
+    workers: Option<usize>,
+    io_profile: Option<IoProfile>,
+    io: Option<IoSettings>,
+    memory_limit: Option<u64>,
+    options: ExtractOptions,
+    containing: Option<Pattern>,
+    name_template: Option<NameTemplate>,
+    label_by_type: bool,
+    split_output_by_date: Option<DateSource>,
+    roots: Option<ScanRoots>,
+    sort: SortOrder,
+    strict_fs: bool,
+    fs_probe: Arc<dyn FsProbe>,
+    attempt_suspect: bool,
+    assume_complete: bool,
+    stabilize_timeout: Duration,
+    sample: Sample,
+    skip_duplicates: bool,
+    latest_per_group: Option<(Regex, GroupOrder)>,
+    journal: Option<Arc<StateJournal>>,
+    entry_hook: Option<EntryHook>,
+    hook_workers: usize,
+    hook_delete_on_fail: bool,
+    progress: Option<ProgressCallback>,
+    bars: Option<ProgressBars>,
+}
+
+impl Default for Extractor {
+    fn default() -> Self {
+        Extractor {
+            output: PathBuf::from("extracted"),
+            workers: None,
+            io_profile: None,
+            io: None,
+            memory_limit: None,
+            options: ExtractOptions::default(),
+            containing: None,
+            name_template: None,
+            label_by_type: false,
+            split_output_by_date: None,
+            roots: None,
+            sort: SortOrder::Path,
+            strict_fs: false,
+            fs_probe: Arc::new(SystemFs),
+            attempt_suspect: false,
+            assume_complete: false,
+            stabilize_timeout: DEFAULT_STABILIZE_TIMEOUT,
+            sample: Sample::default(),
+            skip_duplicates: false,
+            latest_per_group: None,
+            journal: None,
+            entry_hook: None,
+            hook_workers: DEFAULT_HOOK_WORKERS,
+            hook_delete_on_fail: false,
+            progress: None,
+            bars: None,
+        }
+    }
+}
+
+impl Extractor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.output = output.into();
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.workers = Some(workers.max(1));
+        self
+    }
+
+    /// Pick the worker count and buffer size for the storage the archives
+    /// are on; `Auto` samples the first archive's directory, falling back to
+    /// the defaults when that fails. An explicit [`workers`](Self::workers)
+    /// still wins.
+    pub fn io_profile(mut self, profile: IoProfile) -> Self {
+        self.io_profile = Some(profile);
+        self
+    }
+
+    /// Use these worker and buffer settings, e.g. from
+    /// [`settings_for_run`](crate::io_profile::settings_for_run), instead of
+    /// resolving [`io_profile`](Self::io_profile) and [`workers`](Self::workers)
+    pub fn io_settings(mut self, io: IoSettings) -> Self {
+        self.io = Some(io);
+        self
+    }
+
+    pub fn skip_existing(mut self, skip_existing: bool) -> Self {
+        self.options.skip_existing = skip_existing;
+        self
+    }
+
+    /// Extract into existing directories, rewriting only stale or missing files
+    pub fn update(mut self, update: bool) -> Self {
+        self.options.update = update;
+        self
+    }
+
+    /// Extract into directories an interrupted run left behind, keeping files
+    /// whose size and CRC-32 match their entry. Pair with `state_journal` so
+    /// finished archives are skipped.
+    pub fn resume(mut self, resume: bool) -> Self {
+        self.options.resume = resume;
+        self
+    }
+
+    /// With `update`, remove the files the last run extracted from an
+    /// archive that it no longer contains, as the
+    /// [`state_journal`](Self::state_journal) recorded them; without a
+    /// journal, or a record of the archive, nothing is removed
+    pub fn delete_removed(mut self, delete_removed: bool) -> Self {
+        self.options.delete_removed = delete_removed;
+        self
+    }
+
+    /// Remove directories left empty under each archive's directory once it is extracted
+    pub fn prune_empty(mut self, prune_empty: bool) -> Self {
+        self.options.prune_empty = prune_empty;
+        self
+    }
+
+    /// Shorten entry path components too long for the filesystem; on by
+    /// default, and archives holding such entries fail without it
+    pub fn truncate_long_names(mut self, truncate: bool) -> Self {
+        self.options.truncate_long_names = truncate;
+        self
+    }
+
+    /// What an archive does when another one is extracting into the same
+    /// directory; waits by default
+    pub fn on_busy(mut self, policy: BusyPolicy) -> Self {
+        self.options.on_busy = policy;
+        self
+    }
+
+    /// What becomes of the directory of an archive failing a CRC-32 or entry
+    /// count check; moved aside to `<name>.failed-<timestamp>` by default
+    pub fn on_validation_failure(mut self, policy: OnValidationFailure) -> Self {
+        self.options.on_validation_failure = policy;
+        self
+    }
+
+    /// Cancel, pause or throttle extraction from another thread; takes effect
+    /// between chunks of an entry, so even huge entries stop promptly
+    pub fn control(mut self, control: Arc<CopyControl>) -> Self {
+        self.options.control = Some(control);
+        self
+    }
+
+    /// Extract encrypted archives with the first of `passwords` that opens
+    /// them, instead of skipping them
+    pub fn passwords(mut self, passwords: Passwords) -> Self {
+        self.options.passwords = Some(Arc::new(passwords));
+        self
+    }
+
+    /// Replace a regular file occupying an archive's output directory
+    pub fn overwrite(mut self, overwrite: bool) -> Self {
+        self.options.overwrite = overwrite;
+        self
+    }
+
+    /// Read archives through memory maps; see `--mmap` for the caveats
+    pub fn mmap(mut self, mmap: bool) -> Self {
+        self.options.use_mmap = mmap;
+        self
+    }
+
+    /// Refuse archives with more entries than this; `None` for no limit
+    pub fn max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.options.max_entries = max_entries;
+        self
+    }
+
+    /// Strip up to this many leading directories from every entry, where no files collide
+    pub fn flatten_depth(mut self, depth: usize) -> Self {
+        self.options.flatten_depth = depth;
+        self
+    }
+
+    /// Lowercase the extension of every entry, then replace it by its
+    /// canonical form in `map`, e.g. `.JPEG` to `.jpg`
+    pub fn normalize_extensions(mut self, map: ExtensionMap) -> Self {
+        self.options.normalize_extensions = Some(map);
+        self
+    }
+
+    /// Skip entries with more path components than `limit` allows, or
+    /// collapse their deep directories into one hashed directory
+    pub fn max_entry_depth(mut self, limit: DepthLimit) -> Self {
+        self.options.depth_limit = Some(limit);
+        self
+    }
+
+    pub fn strict_times(mut self, strict_times: bool) -> Self {
+        self.options.strict_times = strict_times;
+        self
+    }
+
+    pub fn ownership(mut self, ownership: Ownership) -> Self {
+        self.options.ownership = ownership;
+        self
+    }
+
+    /// Leave out the `META-INF/` signature files of Java and Android packages
+    pub fn skip_signatures(mut self, skip: bool) -> Self {
+        self.options.skip_signatures = skip;
+        self
+    }
+
+    /// Entry attributes to keep; modification times by default. `owner`
+    /// works like [`Ownership::preserve_owner`] and is ignored off Unix.
+    pub fn preserve(mut self, preserve: Preserve) -> Self {
+        self.options.preserve = preserve;
+        self
+    }
+
+    /// Fit workers and copy buffers into this many bytes
+    pub fn memory_limit(mut self, limit: Option<u64>) -> Self {
+        self.memory_limit = limit;
+        self
+    }
+
+    /// Only extract archives with an entry matching this glob
+    pub fn containing(mut self, pattern: Pattern) -> Self {
+        self.containing = Some(pattern);
+        self
+    }
+
+    pub fn name_template(mut self, template: NameTemplate) -> Self {
+        self.name_template = Some(template);
+        self
+    }
+
+    /// Extract into `comics/`, `books/` or `archives/` under the output by archive kind
+    pub fn label_by_type(mut self, label_by_type: bool) -> Self {
+        self.label_by_type = label_by_type;
+        self
+    }
+
+    /// Extract under `YYYY/MM/` in the output by each archive's date, outside
+    /// any type label
+    pub fn split_output_by_date(mut self, source: DateSource) -> Self {
+        self.split_output_by_date = Some(source);
+        self
+    }
+
+    /// The directories the archives were found in: with several, each
+    /// archive extracts under its root's label, outside any date
+    pub fn roots(mut self, roots: ScanRoots) -> Self {
+        self.roots = Some(roots);
+        self
+    }
+
+    /// Order to extract and report archives in; byte-wise by path by default
+    pub fn sort(mut self, sort: SortOrder) -> Self {
+        self.sort = sort;
+        self
+    }
+
+    /// Fail before extracting anything when the output filesystem can't store
+    /// some entries or options; see [`check_target`]
+    pub fn strict_fs(mut self, strict_fs: bool) -> Self {
+        self.strict_fs = strict_fs;
+        self
+    }
+
+    /// Ask `probe` which filesystem the output is on instead of the OS
+    pub fn fs_probe(mut self, probe: Arc<dyn FsProbe>) -> Self {
+        self.fs_probe = probe;
+        self
+    }
+
+    /// Fail archives raising a warning `strict` doesn't except, e.g. clamped
+    /// timestamps, and before extracting anything as with
+    /// [`strict_fs`](Self::strict_fs) unless it excepts `output-filesystem`
+    pub fn strict(mut self, strict: StrictMode) -> Self {
+        self.options.strict = Some(strict);
+        self
+    }
+
+    /// Also try archives the scan flagged as empty, truncated or without central directory
+    pub fn attempt_suspect(mut self, attempt_suspect: bool) -> Self {
+        self.attempt_suspect = attempt_suspect;
+        self
+    }
+
+    /// How long to wait for archives modified in the last couple of seconds,
+    /// such as downloads in progress, to stop changing; those that don't are
+    /// reported as [`ExtractStatus::SkippedUnstable`]
+    pub fn stabilize_timeout(mut self, timeout: Duration) -> Self {
+        self.stabilize_timeout = timeout;
+        self
+    }
+
+    /// The archives are known to be complete, e.g. just downloaded: extract
+    /// recently modified ones without waiting for them to stop changing
+    pub fn assume_complete(mut self, complete: bool) -> Self {
+        self.assume_complete = complete;
+        self
+    }
+
+    /// Extract only `limit` of the archives found, after sorting and
+    /// filtering: the first ones, or a random subset that `seed` makes
+    /// repeatable. The rest are left out of the report.
+    pub fn limit(mut self, limit: usize, mode: SampleMode, seed: Option<u64>) -> Self {
+        self.sample = Sample::new(Some(limit), mode, seed);
+        self
+    }
+
+    /// Extract only the first of byte-identical archives; the rest are
+    /// reported as [`ExtractStatus::SkippedDuplicateOf`]. Hashing stops with
+    /// an error when the [`control`](Self::control) is cancelled.
+    pub fn skip_duplicates(mut self, skip_duplicates: bool) -> Self {
+        self.skip_duplicates = skip_duplicates;
+        self
+    }
+
+    /// Extract only the latest archive of each group of names `pattern`
+    /// matches, its first capture group being the group key (see
+    /// [`compile_group_pattern`](crate::archive_groups::compile_group_pattern));
+    /// the rest are reported as [`ExtractStatus::SkippedSuperseded`].
+    /// Archives it doesn't match are extracted as usual.
+    pub fn latest_per_group(mut self, pattern: Regex, order: GroupOrder) -> Self {
+        self.latest_per_group = Some((pattern, order));
+        self
+    }
+
+    /// Skip archives the journal records as extracted and unchanged, reporting
+    /// them as [`ExtractStatus::SkippedProcessed`], and record every archive
+    /// extracted. Share one journal across the runs of a long-lived process.
+    pub fn state_journal(mut self, journal: Arc<StateJournal>) -> Self {
+        self.options.receipts = Some(journal.clone());
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Run a shell command on every extracted file, with its path as `$1` and
+    /// in place of `{}`; a non-zero exit fails the entry. Hooks run on
+    /// [`hook_workers`](Self::hook_workers) threads of their own, so a slow
+    /// scanner queues up instead of holding up extraction.
+    pub fn post_entry_hook(mut self, command: impl Into<String>) -> Self {
+        self.entry_hook = Some(EntryHook::Command(command.into()));
+        self
+    }
+
+    /// Like [`post_entry_hook`](Self::post_entry_hook), with a callback
+    /// instead of a command; an error fails the entry
+    pub fn on_entry(mut self, callback: impl Fn(&Path) -> Result<()> + Send + Sync + 'static) -> Self {
+        self.entry_hook = Some(EntryHook::Callback(Arc::new(callback)));
+        self
+    }
+
+    /// How many entry hooks run at once; 2 by default
+    pub fn hook_workers(mut self, workers: usize) -> Self {
+        self.hook_workers = workers.max(1);
+        self
+    }
+
+    /// Remove files their entry hook failed
+    pub fn hook_delete_on_fail(mut self, delete: bool) -> Self {
+        self.hook_delete_on_fail = delete;
+        self
+    }
+
+    /// Whether the files the run removes are deleted, the default, or moved
+    /// to the trash: old files of [`delete_removed`](Self::delete_removed),
+    /// files [`overwrite`](Self::overwrite) replaces with a directory, and
+    /// those their entry hook failed
+    pub fn disposal(mut self, disposal: Disposal) -> Self {
+        self.options.disposer = disposal.disposer();
+        self
+    }
+
+    /// Remove those files with `disposer` instead, e.g. one that archives them
+    pub fn disposer(mut self, disposer: Arc<dyn Disposer>) -> Self {
+        self.options.disposer = disposer;
+        self
+    }
+
+    /// The options each archive is extracted with, for extracting archives
+    /// by other means, e.g. into a tar stream
+    pub fn extract_options(&self) -> &ExtractOptions {
+        &self.options
+    }
+
+    /// Called from the workers as archives start and finish
+    pub fn progress(mut self, callback: impl Fn(ProgressEvent<'_>) + Send + Sync + 'static) -> Self {
+        self.progress = Some(Arc::new(callback));
+        self
+    }
+
+    /// Draw a bar per archive in `multi`, styled `style`, and advance
+    /// `batch` over the uncompressed bytes of the run; bars are hidden by default
+    pub fn progress_bars(mut self, multi: MultiProgress, batch: ProgressBar, style: ProgressStyle) -> Self {
+        self.bars = Some(ProgressBars { multi, batch, style });
+        self
+    }
+
+    /// Scan `directory` and extract every archive found. Per-archive failures
+    /// are reported in the result; only setup errors fail the whole run.
+    pub async fn extract_dir(&self, directory: impl AsRef<Path>) -> Result<BatchReport> {
+        let directory = directory.as_ref();
+        let mut monitor = ScanMonitor::new().excluding_output(Some(&self.output));
+        let zip_files = find_zip_files(directory, self.containing.as_ref(), &mut monitor).await?;
+        self.extract(zip_files).await
+    }
+
+    /// Extract archives found by [`find_zip_files`]: [`plan`](Self::plan),
+    /// then [`run`](Self::run)
+    pub async fn extract(&self, zip_files: Vec<ZipFile>) -> Result<BatchReport> {
+        let plan = self.plan(zip_files).await?;
+        self.run(plan).await
+    }
+
+    /// Decide which of `zip_files` to extract, waiting for those still being
+    /// written and hashing for duplicates, without writing anything. Fails
+    /// when the output filesystem can't hold them under `strict_fs`.
+    pub async fn plan(&self, mut zip_files: Vec<ZipFile>) -> Result<ExtractionPlan> {
+        let ownership = self.applied_ownership();
+        ownership.check_supported()?;
+        self.sort.sort_by_path(&mut zip_files, |f| &f.path);
+        let io = match self.io {
+            Some(io) => io,
+            None => {
+                let source = zip_files.first().and_then(|f| f.path.parent()).unwrap_or(Path::new("."));
+                IoSettings::resolve(self.io_profile, Workload::Extract, source, &SampledReads)
+                    .unwrap_or_default()
+                    .with_workers(self.workers)
+            }
+        };
+        let memory = plan_memory(self.memory_limit, io.workers, io.buffer_size);
+
+        let mut unstable = Vec::new();
+        let writing = zip_files.iter().filter(|f| may_be_writing(&f.path)).count();
+        if !self.assume_complete && writing > 0 {
+            self.emit(ProgressEvent::Settling { archives: writing });
+            (zip_files, unstable) = settle_zip_files(zip_files, self.containing.as_ref(), self.stabilize_timeout).await?;
+        }
+        // Named before the journal is consulted, which checks where each archive went
+        if let Some(template) = &self.name_template {
+            apply_name_template(&mut zip_files, template)?;
+        }
+        if self.label_by_type {
+            apply_type_labels(&mut zip_files);
+        }
+        if let Some(source) = self.split_output_by_date {
+            apply_date_buckets(&mut zip_files, source);
+        }
+        if let Some(roots) = &self.roots {
+            apply_root_labels(&mut zip_files, roots);
+        }
+        let (zip_files, processed) = match &self.journal {
+            Some(journal) => journal.split_processed(zip_files),
+            None => (zip_files, Vec::new()),
+        };
+        let scanned = zip_files.clone();
+        let (mut zip_files, suspect): (Vec<ZipFile>, Vec<ZipFile>) = zip_files
+            .into_iter()
+            .partition(|f| f.health.should_extract(self.attempt_suspect));
+        let mut groups = Vec::new();
+        if let Some((pattern, order)) = &self.latest_per_group {
+            (zip_files, groups) = split_superseded(zip_files, pattern, *order);
+        }
+        // Before hashing and the filesystem check, so they cover only what runs
+        if self.sample.limit.is_some() {
+            zip_files.retain(|f| f.matching_entries != Some(0));
+        }
+        let (mut zip_files, sample) = self.sample.apply(zip_files);
+        let mut duplicates = Vec::new();
+        if self.skip_duplicates {
+            let not_cancelled = AtomicBool::new(false);
+            let cancel = self.options.control.as_ref().map_or(&not_cancelled, |control| control.cancel_flag());
+            let total = duplicate_candidate_bytes(&zip_files);
+            let progress = &self.progress;
+            (zip_files, duplicates) = split_duplicate_archives(zip_files, cancel, |read| {
+                if let Some(progress) = progress {
+                    progress(ProgressEvent::Hashing { read, total });
+                }
+            })?;
+        }
+
+        let sets_permissions = !ownership.is_empty() || self.options.preserve.perms;
+        let output_fs = check_target(self.fs_probe.as_ref(), &self.output, zip_files.iter().map(|f| f.path.as_path()), sets_permissions);
+        let strict_fs = self.strict_fs || self.options.strict.as_ref().is_some_and(|strict| strict.fails_on(WarningCategory::OutputFilesystem));
+        if let (true, Ok(check)) = (strict_fs, &output_fs) {
+            if !check.is_empty() {
+                anyhow::bail!("{}: output {:?} is on {}: {}", FS_LIMITATION, self.output, check.filesystem.name(), check.problems().join("; "));
+            }
+        }
+        Ok(ExtractionPlan { archives: zip_files, scanned, unstable, processed, suspect, groups, sample, duplicates, io, memory, output_fs })
+    }
+
+    /// The output paths more than one entry of the planned archives would
+    /// write, without writing anything
+    pub fn collisions(&self, plan: &ExtractionPlan) -> Result<CollisionReport> {
+        find_collisions(&plan.archives, &self.output, &self.run_options(plan))
+    }
+
+    /// Extract the archives of `plan`. Per-archive failures are reported in
+    /// the result; only setup errors fail the whole run.
+    pub async fn run(&self, plan: ExtractionPlan) -> Result<BatchReport> {
+        let mut options = self.run_options(&plan);
+        let on_fail = self.hook_delete_on_fail.then(|| options.disposer.clone());
+        options.entry_hooks = self.entry_hook.clone().map(|hook| Arc::new(HookRunner::new(hook, self.hook_workers, on_fail)));
+        fs::create_dir_all(&self.output)
+            .with_context(|| format!("Failed to create output directory {:?}", self.output))?;
+
+        let ExtractionPlan { archives: zip_files, unstable, processed, suspect: skipped_suspect, groups, sample, duplicates, io, memory, .. } = plan;
+        let total = zip_files.len();
+        let batch_bar = match &self.bars {
+            Some(bars) => {
+                bars.batch.set_length(zip_files.iter().map(|f| f.uncompressed_size).sum());
+                bars.batch.clone()
+            }
+            None => ProgressBar::hidden(),
+        };
+        let chunk_size = total.div_ceil(memory.workers).max(1);
+        let futures: Vec<_> = zip_files
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(chunk_index, chunk)| {
+                let batch_bar = &batch_bar;
+                let options = &options;
+                async move {
+                    let mut reports = Vec::new();
+                    for (offset, zip_file) in chunk.iter().enumerate() {
+                        let index = chunk_index * chunk_size + offset;
+                        self.emit(ProgressEvent::Started { archive: &zip_file.path, index, total });
+                        let progress_bar = self.archive_bar(zip_file);
+                        let mut outcome = extract_zip_file(zip_file, &self.output, options, progress_bar.clone(), batch_bar).await;
+                        // An archive with files the hook failed, or --strict failed, is retried by the next run
+                        let delivered = outcome.as_ref().is_ok_and(|o| {
+                            o.status == ExtractStatus::Extracted && o.hooks.failed == 0 && options.check_strict(o).is_ok()
+                        });
+                        if let (Some(journal), true) = (&self.journal, delivered) {
+                            if let Err(e) = journal.record(zip_file, outcome.as_ref().map_or(&[][..], |o| &o.extracted)) {
+                                outcome = Err(e.context("Extracted, but not recorded in the state file"));
+                            }
+                        }
+                        let report = ArchiveReport::new(&zip_file.path, outcome, progress_bar.position(), options);
+                        self.emit(ProgressEvent::Finished { archive: &zip_file.path, result: &report });
+                        reports.push(report);
+                    }
+                    reports
+                }
+            })
+            .collect();
+
+        let mut report = BatchReport {
+            archives: join_all(futures).await.into_iter().flatten().collect(),
+            file_types: FileTypeCounts::default(),
+            io,
+            duplicates: Vec::new(),
+            groups: Vec::new(),
+            sample,
+        };
+        let skipped = |path: &Path, status: ExtractStatus| ArchiveReport::new(path, Ok(ExtractOutcome::skipped(status)), 0, &options);
+        report.archives.extend(skipped_suspect.iter().map(|zip_file| skipped(&zip_file.path, ExtractStatus::SkippedSuspect)));
+        report.archives.extend(unstable.iter().map(|zip_file| skipped(&zip_file.path, ExtractStatus::SkippedUnstable)));
+        report.archives.extend(processed.iter().map(|zip_file| skipped(&zip_file.path, ExtractStatus::SkippedProcessed)));
+        for group in &duplicates {
+            report.archives.extend(group.skipped.iter().map(|path| skipped(path, ExtractStatus::SkippedDuplicateOf(group.kept.clone()))));
+        }
+        for group in &groups {
+            report.archives.extend(group.superseded.iter().map(|path| skipped(path, ExtractStatus::SkippedSuperseded(group.kept.clone()))));
+        }
+        report.duplicates = duplicates;
+        report.groups = groups;
+        self.sort.sort_by_path(&mut report.archives, |archive| &archive.path);
+        for archive in &report.archives {
+            report.file_types.merge(&archive.file_types);
+        }
+        Ok(report)
+    }
+
+    /// The ownership to apply, `preserve` owner included
+    fn applied_ownership(&self) -> Ownership {
+        let mut ownership = self.options.ownership;
+        ownership.preserve_owner |= cfg!(unix) && self.options.preserve.owner;
+        ownership
+    }
+
+    /// The options every archive of `plan` is extracted with, but for the entry hooks
+    fn run_options(&self, plan: &ExtractionPlan) -> ExtractOptions {
+        let mut options = self.options.clone();
+        options.buffer_size = plan.memory.buffer_size;
+        options.ownership = self.applied_ownership();
+        options.inputs = Some(Arc::new(InputSet::new(plan.archives.iter().map(|f| f.path.as_path()))));
+        options
+    }
+
+    fn emit(&self, event: ProgressEvent<'_>) {
+        if let Some(progress) = &self.progress {
+            progress(event);
+        }
+    }
+
+    /// The bar extracting `zip_file` advances
+    fn archive_bar(&self, zip_file: &ZipFile) -> ProgressBar {
+        let Some(bars) = &self.bars else {
+            return ProgressBar::hidden();
+        };
+        let bar = bars.multi.add(ProgressBar::new(0));
+        bar.set_style(bars.style.clone());
+        bar.set_message(format!("Extracting: {}", zip_file.path.file_name().unwrap_or_default().to_string_lossy()));
+        bar
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extract::zip_files_at;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in entries {
+            zip.start_file(*name, FileOptions::default()).unwrap();
+            zip.write_all(data).unwrap();
+        }
+        fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn run_reports_what_the_plan_left_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in");
+        fs::create_dir(&input).unwrap();
+        write_zip(&input.join("a.zip"), &[("a.txt", b"same")]);
+        fs::copy(input.join("a.zip"), input.join("b.zip")).unwrap();
+        write_zip(&input.join("c.zip"), &[("c.txt", b"other")]);
+        fs::write(input.join("d.zip"), b"").unwrap();
+        let paths: Vec<PathBuf> = ["a", "b", "c", "d"].iter().map(|name| input.join(format!("{}.zip", name))).collect();
+
+        // Just written, so without it they'd be waited on
+        let extractor = Extractor::new().output(dir.path().join("out")).skip_duplicates(true).assume_complete(true);
+        let plan = extractor.plan(zip_files_at(&paths, None).unwrap()).await.unwrap();
+        let names = |files: &[ZipFile]| files.iter().map(|f| f.output_name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&plan.archives), ["a", "c"]);
+        assert_eq!(names(&plan.scanned), ["a", "b", "c", "d"]);
+        assert_eq!(names(&plan.suspect), ["d"]);
+        assert_eq!(plan.duplicates[0].skipped, [input.join("b.zip")]);
+        assert!(plan.unstable.is_empty());
+
+        let report = extractor.run(plan).await.unwrap();
+        let statuses: Vec<_> = report.archives.iter().map(|archive| archive.result.clone().unwrap()).collect();
+        assert_eq!(
+            statuses,
+            [
+                ExtractStatus::Extracted,
+                ExtractStatus::SkippedDuplicateOf(input.join("a.zip")),
+                ExtractStatus::Extracted,
+                ExtractStatus::SkippedSuspect,
+            ]
+        );
+        assert!(dir.path().join("out/c/c.txt").is_file());
+        assert!(!dir.path().join("out/b").exists());
+    }
+
+    #[tokio::test]
+    async fn strict_failures_keep_their_warnings_and_error_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let empty = dir.path().join("empty.zip");
+        fs::write(&empty, b"").unwrap();
+
+        let report = Extractor::new()
+            .output(dir.path().join("out"))
+            .strict(StrictMode::default())
+            .assume_complete(true)
+            .extract(zip_files_at(&[empty], None).unwrap())
+            .await
+            .unwrap();
+        let archive = &report.archives[0];
+        assert!(archive.result.as_ref().unwrap_err().starts_with("--strict: suspect-archives"));
+        assert_eq!(archive.warnings[0].category, WarningCategory::SuspectArchives);
+        assert!(archive.error_kind.is_some());
+        assert!(archive.outcome().is_none());
+    }
+
+    #[tokio::test]
+    async fn an_archive_that_fails_a_late_check_gets_no_receipt() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in");
+        fs::create_dir(&input).unwrap();
+        write_zip(&input.join("good.zip"), &[("a.txt", b"fine")]);
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("b.txt", FileOptions::default().compression_method(zip::CompressionMethod::Stored)).unwrap();
+        zip.write_all(b"damaged").unwrap();
+        let mut bytes = zip.finish().unwrap().into_inner();
+        let at = bytes.windows(7).position(|w| w == b"damaged").unwrap();
+        bytes[at] ^= 0xff;
+        fs::write(input.join("bad.zip"), bytes).unwrap();
+
+        let journal = Arc::new(StateJournal::open(dir.path().join("state"), false).unwrap());
+        let extractor = Extractor::new().output(dir.path().join("out")).state_journal(journal.clone()).assume_complete(true);
+        let zip_files = zip_files_at(&[input.join("bad.zip"), input.join("good.zip")], None).unwrap();
+        let plan = extractor.plan(zip_files.clone()).await.unwrap();
+        let report = extractor.run(plan).await.unwrap();
+
+        assert!(report.archives[0].result.is_err());
+        assert!(!journal.contains(&zip_files[0]));
+        assert!(journal.contains(&zip_files[1]));
+        // Moved aside, so the next run extracts it afresh
+        assert!(!dir.path().join("out/bad").exists());
+    }
+
+    #[tokio::test]
+    async fn superseded_archives_are_reported_with_the_one_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("in");
+        fs::create_dir(&input).unwrap();
+        for name in ["report_v1", "report_v2", "notes"] {
+            write_zip(&input.join(format!("{}.zip", name)), &[("a.txt", name.as_bytes())]);
+        }
+        let paths: Vec<PathBuf> = ["notes", "report_v1", "report_v2"].iter().map(|name| input.join(format!("{}.zip", name))).collect();
+
+        let pattern = crate::archive_groups::compile_group_pattern("^(report)_").unwrap();
+        let extractor = Extractor::new()
+            .output(dir.path().join("out"))
+            .latest_per_group(pattern, GroupOrder::Name)
+            .assume_complete(true);
+        let plan = extractor.plan(zip_files_at(&paths, None).unwrap()).await.unwrap();
+        let report = extractor.run(plan).await.unwrap();
+
+        let status = |name: &str| {
+            let archive = report.archives.iter().find(|archive| archive.path.ends_with(name)).unwrap();
+            archive.result.clone().unwrap()
+        };
+        assert_eq!(status("notes.zip"), ExtractStatus::Extracted);
+        assert_eq!(status("report_v2.zip"), ExtractStatus::Extracted);
+        assert_eq!(status("report_v1.zip"), ExtractStatus::SkippedSuperseded(input.join("report_v2.zip")));
+        assert_eq!(report.groups.len(), 1);
+        assert!(!dir.path().join("out/report_v1").exists());
+    }
+
+    #[tokio::test]
+    async fn archives_failed_by_strict_are_not_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("album.zip");
+        write_zip(&zip_path, &[("Track 01.MP3", b"audio")]);
+        let zip_files = zip_files_at(std::slice::from_ref(&zip_path), None).unwrap();
+
+        for (except, delivered) in [(Vec::new(), false), (vec![WarningCategory::NormalizedExtensions], true)] {
+            let journal = Arc::new(StateJournal::open(dir.path().join(format!("state-{}", delivered)), false).unwrap());
+            let report = Extractor::new()
+                .output(dir.path().join(format!("out-{}", delivered)))
+                .normalize_extensions(ExtensionMap::default())
+                .strict(StrictMode::new(except))
+                .state_journal(journal.clone())
+                .assume_complete(true)
+                .extract(zip_files.clone())
+                .await
+                .unwrap();
+            let archive = &report.archives[0];
+            assert_eq!(archive.result.is_ok(), delivered, "{:?}", archive.result);
+            assert_eq!(archive.warnings[0].category, WarningCategory::NormalizedExtensions);
+            assert_eq!(journal.contains(&zip_files[0]), delivered);
+            // Failed after extracting, so the files are there either way
+            assert!(dir.path().join(format!("out-{}/album/Track 01.mp3", delivered)).is_file());
+        }
+    }
+
+    struct FakeFs(crate::fs_limits::FsKind);
+
+    impl FsProbe for FakeFs {
+        fn kind(&self, _path: &Path) -> std::io::Result<crate::fs_limits::FsKind> {
+            Ok(self.0)
+        }
+    }
+
+    #[tokio::test]
+    async fn strict_fs_refuses_options_the_output_filesystem_cannot_honour() {
+        let dir = tempfile::tempdir().unwrap();
+        let zip_path = dir.path().join("a.zip");
+        write_zip(&zip_path, &[("a.txt", b"a")]);
+        let zip_files = zip_files_at(std::slice::from_ref(&zip_path), None).unwrap();
+        let extractor = Extractor::new()
+            .output(dir.path().join("out"))
+            .preserve(Preserve { times: true, perms: true, owner: false })
+            .fs_probe(Arc::new(FakeFs(crate::fs_limits::FsKind::ExFat)))
+            .assume_complete(true);
+
+        // Only a warning for the caller to print
+        let plan = extractor.clone().plan(zip_files.clone()).await.unwrap();
+        let check = plan.output_fs.unwrap();
+        assert_eq!(check.filesystem.name(), "exFAT");
+        assert!(check.permissions);
+
+        let Err(error) = extractor.strict_fs(true).plan(zip_files).await else { panic!("planned despite --strict-fs") };
+        let error = error.to_string();
+        assert!(error.starts_with(FS_LIMITATION), "{}", error);
+        assert!(error.contains("is on exFAT: --chmod"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn archives_still_being_written_are_skipped_as_unstable() {
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::SystemTime;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (growing, done) = (dir.path().join("growing.zip"), dir.path().join("done.zip"));
+        write_zip(&growing, &[("a.txt", b"a")]);
+        write_zip(&done, &[("b.txt", b"b")]);
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&done).unwrap().set_modified(an_hour_ago).unwrap();
+        let zip_files = zip_files_at(&[growing.clone(), done.clone()], None).unwrap();
+
+        // A download in progress, appending until the run is over
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut file = fs::OpenOptions::new().append(true).open(&growing).unwrap();
+        let stopping = stop.clone();
+        let appender = std::thread::spawn(move || {
+            while !stopping.load(Ordering::Relaxed) {
+                file.write_all(&[0; 64]).unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+            }
+        });
+        let report = Extractor::new()
+            .output(dir.path().join("out"))
+            .stabilize_timeout(Duration::ZERO)
+            .extract(zip_files)
+            .await
+            .unwrap();
+        stop.store(true, Ordering::Relaxed);
+        appender.join().unwrap();
+
+        let status = |path: &Path| report.archives.iter().find(|archive| archive.path == path).unwrap().result.clone();
+        assert_eq!(status(&growing), Ok(ExtractStatus::SkippedUnstable));
+        assert_eq!(status(&done), Ok(ExtractStatus::Extracted));
+        assert!(!dir.path().join("out/growing").exists());
+    }
+}
@@ -0,0 +1,205 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Names `--keep-fields` understands besides frame IDs
+pub const KEEP_FIELDS: &[&str] = &["title", "artist", "album", "year", "track", "genre"];
+
+/// Names `--remove-fields` and `--protect` understand besides frame IDs;
+/// `links` stands for every `W***` frame
+pub const REMOVE_FIELDS: &[&str] = &[
+    "title", "artist", "album", "year", "track", "genre", "comment", "chapters", "lyrics", "copyright", "encoder",
+    "links",
+];
+
+/// Which of the field lists a name was given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldList {
+    KeepFields,
+    RemoveFields,
+    Protect,
+}
+
+impl FieldList {
+    pub fn option(self) -> &'static str {
+        match self {
+            FieldList::KeepFields => "--keep-fields",
+            FieldList::RemoveFields => "--remove-fields",
+            FieldList::Protect => "--protect",
+        }
+    }
+
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            FieldList::KeepFields => KEEP_FIELDS,
+            FieldList::RemoveFields | FieldList::Protect => REMOVE_FIELDS,
+        }
+    }
+}
+
+/// A name in a field list that is neither a known field nor a frame ID
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct InvalidFieldName {
+    pub list: FieldList,
+    pub name: String,
+    /// The closest valid name, when one is near enough to be a typo
+    pub suggestion: Option<String>,
+    pub valid: Vec<String>,
+}
+
+impl fmt::Display for InvalidFieldName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unknown field {:?} in {}", self.name, self.list.option())?;
+        if let Some(suggestion) = &self.suggestion {
+            write!(f, " (did you mean {:?}?)", suggestion)?;
+        }
+        write!(f, "; valid fields are {}, or a frame ID in capitals such as TXXX", self.valid.join(", "))
+    }
+}
+
+impl std::error::Error for InvalidFieldName {}
+
+/// Parse a comma-separated field list, e.g. `title, Artist,TCOP`, into
+/// lowercase field names and uppercase frame IDs, rejecting anything else
+/// before a typo can silently strip the field it meant to keep. Frame IDs
+/// must be written in capitals, four letters or digits, so that `titl` is
+/// taken for a misspelled name rather than a frame no file has.
+pub fn parse_field_list(list: FieldList, fields: &str) -> Result<Vec<String>, InvalidFieldName> {
+    fields
+        .split(',')
+        .map(str::trim)
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let lower = field.to_lowercase();
+            if list.names().contains(&lower.as_str()) {
+                Ok(lower)
+            } else if is_frame_id(field) {
+                Ok(field.to_string())
+            } else {
+                Err(InvalidFieldName {
+                    list,
+                    name: field.to_string(),
+                    suggestion: suggest(field, list.names()),
+                    valid: list.names().iter().map(|name| name.to_string()).collect(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// Check the keep, remove and protect lists without using them
+pub fn check_field_lists(keep_fields: Option<&str>, remove_fields: Option<&str>, protect: Option<&str>) -> Result<(), InvalidFieldName> {
+    let lists = [(FieldList::KeepFields, keep_fields), (FieldList::RemoveFields, remove_fields), (FieldList::Protect, protect)];
+    for (list, fields) in lists {
+        if let Some(fields) = fields {
+            parse_field_list(list, fields)?;
+        }
+    }
+    Ok(())
+}
+
+/// `TIT2`, `TXXX`: four capitals or digits, starting with a capital
+fn is_frame_id(field: &str) -> bool {
+    field.len() == 4
+        && field.starts_with(|c: char| c.is_ascii_uppercase())
+        && field.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// The name in `names` closest to `field`, at most two edits away; failing
+/// that, `field` in capitals when that makes it a frame ID
+fn suggest(field: &str, names: &[&str]) -> Option<String> {
+    let lower = field.to_lowercase();
+    names
+        .iter()
+        .map(|name| (edit_distance(&lower, name), name))
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, name)| name.to_string())
+        .or_else(|| Some(field.to_uppercase()).filter(|upper| is_frame_id(upper)))
+}
+
+/// Edits, counting swapped neighbours as one, to turn `a` into `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_take_names_in_any_case_and_frame_ids_in_capitals() {
+        assert_eq!(parse_field_list(FieldList::KeepFields, "title, Artist,,TCOP ,TXXX").unwrap(), ["title", "artist", "TCOP", "TXXX"]);
+        assert_eq!(parse_field_list(FieldList::RemoveFields, "links,LYRICS,T2O3").unwrap(), ["links", "lyrics", "T2O3"]);
+        assert_eq!(parse_field_list(FieldList::Protect, "").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn unknown_names_are_rejected_with_the_closest_match() {
+        let error = parse_field_list(FieldList::KeepFields, "titel,artist").unwrap_err();
+        assert_eq!((error.list, error.name.as_str(), error.suggestion.as_deref()), (FieldList::KeepFields, "titel", Some("title")));
+        assert_eq!(error.valid, KEEP_FIELDS);
+        assert_eq!(
+            error.to_string(),
+            "Unknown field \"titel\" in --keep-fields (did you mean \"title\"?); valid fields are \
+             title, artist, album, year, track, genre, or a frame ID in capitals such as TXXX"
+        );
+
+        // Only --remove-fields and --protect know the longer list
+        assert!(parse_field_list(FieldList::RemoveFields, "comment").is_ok());
+        let error = parse_field_list(FieldList::KeepFields, "comment").unwrap_err();
+        assert_eq!(error.suggestion, None);
+        assert_eq!(parse_field_list(FieldList::Protect, "copyrigth").unwrap_err().suggestion.as_deref(), Some("copyright"));
+        assert_eq!(parse_field_list(FieldList::RemoveFields, "lnks").unwrap_err().suggestion.as_deref(), Some("links"));
+    }
+
+    #[test]
+    fn malformed_frame_ids_are_rejected() {
+        for field in ["TIT", "TIT22", "1TIT", "T-T2", "TÏT2"] {
+            assert!(parse_field_list(FieldList::RemoveFields, field).is_err(), "{}", field);
+        }
+        // A frame ID in lowercase is most likely one
+        let error = parse_field_list(FieldList::RemoveFields, "tcop").unwrap_err();
+        assert_eq!(error.suggestion.as_deref(), Some("TCOP"));
+        // Nothing near enough to suggest
+        assert_eq!(parse_field_list(FieldList::KeepFields, "everything").unwrap_err().suggestion, None);
+    }
+
+    #[test]
+    fn edit_distance_counts_swaps_as_one() {
+        assert_eq!(edit_distance("title", "title"), 0);
+        assert_eq!(edit_distance("titel", "title"), 1);
+        assert_eq!(edit_distance("tilte", "title"), 1);
+        assert_eq!(edit_distance("tite", "title"), 1);
+        assert_eq!(edit_distance("", "year"), 4);
+        assert_eq!(edit_distance("album", "artist"), 5);
+    }
+
+    #[test]
+    fn every_list_is_checked() {
+        assert!(check_field_lists(Some("title"), Some("comment"), Some("copyright")).is_ok());
+        assert!(check_field_lists(None, None, None).is_ok());
+        assert_eq!(check_field_lists(Some("title"), Some("coment"), None).unwrap_err().list, FieldList::RemoveFields);
+        assert_eq!(check_field_lists(None, None, Some("encodr")).unwrap_err().list, FieldList::Protect);
+    }
+}
@@ -0,0 +1,218 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Extensions of archives that can turn up inside an extracted archive
+pub const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "7z", "rar", "tar", "gz", "tgz", "bz2", "xz", "jar", "war", "ear", "apk"];
+
+/// Whether an entry looks like an archive of its own, judged by its extension
+pub fn is_archive(entry_name: &str) -> bool {
+    !entry_name.ends_with('/')
+        && Path::new(entry_name)
+            .extension()
+            .is_some_and(|ext| ARCHIVE_EXTENSIONS.iter().any(|a| ext.eq_ignore_ascii_case(a)))
+}
+
+/// What a scanned archive holds, judged by its extension. They are all zips
+/// inside, except `.cbr` comics, which are usually rar.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveKind {
+    #[default]
+    Zip,
+    /// `.cbz` and `.cbr`
+    Comic,
+    /// `.epub`
+    Book,
+    /// `.jar`, `.war` and `.ear`
+    Java,
+    /// `.apk`
+    Android,
+}
+
+/// Extensions the archive scan picks up, with the kind each stands for
+pub const SCANNED_EXTENSIONS: &[(&str, ArchiveKind)] = &[
+    ("zip", ArchiveKind::Zip),
+    ("cbz", ArchiveKind::Comic),
+    ("cbr", ArchiveKind::Comic),
+    ("epub", ArchiveKind::Book),
+    ("jar", ArchiveKind::Java),
+    ("war", ArchiveKind::Java),
+    ("ear", ArchiveKind::Java),
+    ("apk", ArchiveKind::Android),
+];
+
+impl ArchiveKind {
+    /// Kind of the file at `path`, `None` when the scan doesn't handle its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?;
+        SCANNED_EXTENSIONS
+            .iter()
+            .find(|(known, _)| ext.eq_ignore_ascii_case(known))
+            .map(|(_, kind)| *kind)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ArchiveKind::Zip => "zip",
+            ArchiveKind::Comic => "comic",
+            ArchiveKind::Book => "book",
+            ArchiveKind::Java => "java",
+            ArchiveKind::Android => "android",
+        }
+    }
+
+    /// Output subdirectory for this kind when outputs are labelled by type
+    pub fn directory(self) -> &'static str {
+        match self {
+            ArchiveKind::Zip => "archives",
+            ArchiveKind::Comic => "comics",
+            ArchiveKind::Book => "books",
+            ArchiveKind::Java => "java",
+            ArchiveKind::Android => "android",
+        }
+    }
+
+    /// Whether the archive is a Java or Android package, whose entries
+    /// [`package_entry_type`] tells apart
+    pub fn is_package(self) -> bool {
+        matches!(self, ArchiveKind::Java | ArchiveKind::Android)
+    }
+}
+
+/// Extensions of the files signing a JAR or APK (v1 scheme) in `META-INF/`
+const SIGNATURE_EXTENSIONS: &[&str] = &["sf", "rsa", "dsa", "ec"];
+
+/// Whether an entry is a package signature file, `META-INF/*.SF` or a
+/// signature block beside it, which verify nothing once unpacked
+pub fn is_signature_file(entry_name: &str) -> bool {
+    let Some((dir, file)) = entry_name.split_once('/') else {
+        return false;
+    };
+    dir.eq_ignore_ascii_case("META-INF")
+        && !file.contains('/')
+        && Path::new(file)
+            .extension()
+            .is_some_and(|ext| SIGNATURE_EXTENSIONS.iter().any(|s| ext.eq_ignore_ascii_case(s)))
+}
+
+/// What an entry of a JAR, WAR or APK holds, for listing and counting them
+/// apart. An APK's `AndroidManifest.xml` is compiled binary XML, extracted
+/// verbatim but not readable as text.
+pub fn package_entry_type(entry_name: &str) -> &'static str {
+    if is_signature_file(entry_name) {
+        return "signature";
+    }
+    if entry_name == "AndroidManifest.xml" {
+        return "manifest (binary XML)";
+    }
+    if entry_name.eq_ignore_ascii_case("META-INF/MANIFEST.MF") {
+        return "manifest";
+    }
+    let ext = Path::new(entry_name).extension().map(|ext| ext.to_string_lossy().to_lowercase());
+    match ext.as_deref() {
+        Some("class") => "class",
+        Some("dex") => "dex",
+        Some("so") => "native library",
+        Some("jar") => "library",
+        _ => "resource",
+    }
+}
+
+/// Histogram of extracted entries keyed by lowercase extension
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct FileTypeCounts {
+    pub extensions: BTreeMap<String, usize>,
+    pub no_extension: usize,
+    pub directories: usize,
+}
+
+impl FileTypeCounts {
+    /// Count one archive entry by its name as stored in the archive
+    pub fn record(&mut self, entry_name: &str) {
+        if entry_name.ends_with('/') {
+            self.directories += 1;
+            return;
+        }
+
+        match Path::new(entry_name).extension() {
+            Some(ext) => {
+                *self.extensions.entry(ext.to_string_lossy().to_lowercase()).or_default() += 1;
+            }
+            None => self.no_extension += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: &FileTypeCounts) {
+        for (ext, count) in &other.extensions {
+            *self.extensions.entry(ext.clone()).or_default() += count;
+        }
+        self.no_extension += other.no_extension;
+        self.directories += other.directories;
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.extensions.is_empty() && self.no_extension == 0 && self.directories == 0
+    }
+
+    /// Files counted, leaving out directories
+    pub fn files(&self) -> usize {
+        self.extensions.values().sum::<usize>() + self.no_extension
+    }
+
+    /// Extensions ordered by count, most common first
+    pub fn by_count(&self) -> Vec<(&str, usize)> {
+        let mut counts: Vec<_> = self.extensions.iter().map(|(ext, count)| (ext.as_str(), *count)).collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn java_and_android_packages_are_scanned() {
+        for (name, kind) in [
+            ("lib.jar", ArchiveKind::Java),
+            ("app.WAR", ArchiveKind::Java),
+            ("suite.ear", ArchiveKind::Java),
+            ("app.apk", ArchiveKind::Android),
+            ("plain.zip", ArchiveKind::Zip),
+        ] {
+            assert_eq!(ArchiveKind::from_path(Path::new(name)), Some(kind), "{}", name);
+        }
+        assert_eq!(ArchiveKind::from_path(Path::new("app.aab")), None);
+        assert!(ArchiveKind::Java.is_package() && ArchiveKind::Android.is_package());
+        assert!(!ArchiveKind::Zip.is_package());
+    }
+
+    #[test]
+    fn only_top_level_meta_inf_signatures_are_signature_files() {
+        assert!(is_signature_file("META-INF/CERT.SF"));
+        assert!(is_signature_file("meta-inf/cert.rsa"));
+        assert!(is_signature_file("META-INF/KEY.EC"));
+        assert!(!is_signature_file("META-INF/MANIFEST.MF"));
+        assert!(!is_signature_file("META-INF/services/CERT.SF"));
+        assert!(!is_signature_file("lib/META-INF/CERT.SF"));
+        assert!(!is_signature_file("CERT.SF"));
+    }
+
+    #[test]
+    fn package_entries_are_labelled_by_type() {
+        for (entry, label) in [
+            ("com/example/Main.class", "class"),
+            ("classes.dex", "dex"),
+            ("lib/arm64-v8a/libnative.so", "native library"),
+            ("WEB-INF/lib/dep.jar", "library"),
+            ("AndroidManifest.xml", "manifest (binary XML)"),
+            ("META-INF/MANIFEST.MF", "manifest"),
+            ("META-INF/CERT.SF", "signature"),
+            ("res/layout/main.xml", "resource"),
+        ] {
+            assert_eq!(package_entry_type(entry), label, "{}", entry);
+        }
+    }
+}
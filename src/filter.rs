@@ -0,0 +1,345 @@
+use anyhow::{bail, Context, Result};
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+
+use crate::metadata_stripper::TagInfo;
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Include/exclude globs matched against paths relative to the scan directory
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Result<Self> {
+        let compile = |globs: &[String]| -> Result<Vec<Pattern>> {
+            globs
+                .iter()
+                .map(|g| Pattern::new(g).with_context(|| format!("Invalid glob pattern {:?}", g)))
+                .collect()
+        };
+
+        Ok(PathFilter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        let included = self.include.is_empty()
+            || self.include.iter().any(|p| p.matches_path_with(relative_path, GLOB_OPTIONS));
+        let excluded = self.exclude.iter().any(|p| p.matches_path_with(relative_path, GLOB_OPTIONS));
+        included && !excluded
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Eq,
+    NotEq,
+    LParen,
+    RParen,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RParen);
+            }
+            '=' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    bail!("Expected '==' in filter expression {:?}", input);
+                }
+                tokens.push(Token::Eq);
+            }
+            '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    bail!("Expected '!=' in filter expression {:?}", input);
+                }
+                tokens.push(Token::NotEq);
+            }
+            '"' | '\'' => {
+                let quote = c;
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => value.push(escaped),
+                            None => bail!("Unterminated string in filter expression {:?}", input),
+                        },
+                        Some(ch) if ch == quote => break,
+                        Some(ch) => value.push(ch),
+                        None => bail!("Unterminated string in filter expression {:?}", input),
+                    }
+                }
+                tokens.push(Token::Str(value));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if ch.is_whitespace() || "()=!\"'".contains(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Eq,
+    NotEq,
+    Contains,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Compare { field: String, op: Op, value: String },
+    HasArt,
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+/// Tag fields that can appear on the left-hand side of a comparison
+const FILTER_FIELDS: &[&str] = &["title", "artist", "album", "year", "track", "genre", "comment"];
+
+/// A `--where` expression evaluated against scanned tag info.
+///
+/// Supported syntax: `<field> == "value"`, `<field> != "value"`,
+/// `<field> contains "value"` (case-insensitive), the `has_art` flag, and
+/// `and`/`or`/`not` with parentheses. Missing fields compare as `""`.
+#[derive(Debug, Clone)]
+pub struct TagFilter {
+    expr: Expr,
+}
+
+impl TagFilter {
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_or()?;
+        if parser.pos != parser.tokens.len() {
+            bail!("Unexpected {:?} in filter expression {:?}", parser.tokens[parser.pos], input);
+        }
+        Ok(TagFilter { expr })
+    }
+
+    pub fn matches(&self, tags: &TagInfo) -> bool {
+        eval(&self.expr, tags)
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.tokens.get(self.pos), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword))
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut left = self.parse_and()?;
+        while self.peek_keyword("or") {
+            self.pos += 1;
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut left = self.parse_unary()?;
+        while self.peek_keyword("and") {
+            self.pos += 1;
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        if self.peek_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::LParen) => {
+                let expr = self.parse_or()?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(expr),
+                    _ => bail!("Missing closing ')' in filter expression"),
+                }
+            }
+            Some(Token::Word(word)) if word.eq_ignore_ascii_case("has_art") => Ok(Expr::HasArt),
+            Some(Token::Word(field)) => {
+                let field = field.to_lowercase();
+                if !FILTER_FIELDS.contains(&field.as_str()) {
+                    bail!("Unknown filter field {:?} (expected one of: {})", field, FILTER_FIELDS.join(", "));
+                }
+
+                let op = match self.next() {
+                    Some(Token::Eq) => Op::Eq,
+                    Some(Token::NotEq) => Op::NotEq,
+                    Some(Token::Word(w)) if w.eq_ignore_ascii_case("contains") => Op::Contains,
+                    other => bail!("Expected '==', '!=' or 'contains' after {:?}, found {:?}", field, other),
+                };
+
+                let value = match self.next() {
+                    Some(Token::Str(s)) | Some(Token::Word(s)) => s,
+                    other => bail!("Expected a value after operator for {:?}, found {:?}", field, other),
+                };
+
+                Ok(Expr::Compare { field, op, value })
+            }
+            other => bail!("Unexpected {:?} in filter expression", other),
+        }
+    }
+}
+
+fn eval(expr: &Expr, tags: &TagInfo) -> bool {
+    match expr {
+        Expr::Compare { field, op, value } => {
+            let actual = tags.field(field).unwrap_or_default();
+            match op {
+                Op::Eq => actual == *value,
+                Op::NotEq => actual != *value,
+                Op::Contains => actual.to_lowercase().contains(&value.to_lowercase()),
+            }
+        }
+        Expr::HasArt => tags.has_art,
+        Expr::Not(inner) => !eval(inner, tags),
+        Expr::And(left, right) => eval(left, tags) && eval(right, tags),
+        Expr::Or(left, right) => eval(left, tags) || eval(right, tags),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags() -> TagInfo {
+        TagInfo {
+            title: Some("Blue in Green".to_string()),
+            artist: Some("Miles Davis".to_string()),
+            album: Some("Kind of Blue".to_string()),
+            year: Some(1959),
+            track: Some(3),
+            genre: None,
+            comment: None,
+            has_art: true,
+        }
+    }
+
+    fn matches(expr: &str) -> bool {
+        TagFilter::parse(expr).unwrap().matches(&tags())
+    }
+
+    fn parse_error(expr: &str) -> String {
+        TagFilter::parse(expr).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn comparisons() {
+        assert!(matches("year == 1959"));
+        assert!(!matches("year == 1960"));
+        assert!(matches("track != 4"));
+        assert!(!matches("track != 3"));
+        assert!(matches("artist contains davis"));
+        assert!(matches("ALBUM CONTAINS 'BLUE'"));
+        assert!(!matches("artist contains coltrane"));
+        // Missing fields compare as ""
+        assert!(matches("genre == ''"));
+        assert!(matches("comment != jazz"));
+    }
+
+    #[test]
+    fn has_art() {
+        assert!(matches("has_art"));
+        assert!(!matches("not has_art"));
+        assert!(!TagFilter::parse("has_art").unwrap().matches(&TagInfo::default()));
+    }
+
+    #[test]
+    fn quoted_values() {
+        assert!(matches("title == \"Blue in Green\""));
+        assert!(matches("artist == 'Miles Davis'"));
+        // Unquoted, a value ends at the first space
+        assert!(parse_error("title == Blue in Green").contains("Unexpected Word(\"in\")"));
+        assert!(matches(r#"title != "Blue \"in\" Green""#));
+        assert!(TagFilter::parse("title == 'it\\'s'").unwrap().matches(&TagInfo { title: Some("it's".to_string()), ..TagInfo::default() }));
+    }
+
+    #[test]
+    fn precedence() {
+        // and binds tighter than or
+        assert!(matches("year == 1 and track == 1 or has_art"));
+        assert!(matches("has_art or year == 1 and track == 1"));
+        assert!(!matches("(has_art or year == 1) and track == 1"));
+        // not binds tighter than and
+        assert!(!matches("not has_art and year == 1959"));
+        assert!(matches("not (has_art and year == 1960)"));
+        assert!(matches("not not has_art"));
+        assert!(matches("has_art AND year == 1959"));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(parse_error("year = 1959").contains("Expected '=='"));
+        assert!(parse_error("year ! 1959").contains("Expected '!='"));
+        assert!(parse_error("title == 'open").contains("Unterminated string"));
+        assert!(parse_error("(has_art").contains("Missing closing ')'"));
+        assert!(parse_error("label == x").contains("Unknown filter field \"label\""));
+        assert!(parse_error("year > 1959").contains("Expected '==', '!=' or 'contains'"));
+        assert!(parse_error("year ==").contains("Expected a value"));
+        assert!(parse_error("has_art has_art").contains("Unexpected"));
+        assert!(parse_error("has_art and").contains("Unexpected None"));
+        assert!(parse_error("").contains("Unexpected None"));
+    }
+}
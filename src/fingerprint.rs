@@ -0,0 +1,72 @@
+use anyhow::{bail, Context, Result};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// Identifies one version of a file on disk. The scan's `--hash` output and the
+/// incremental-extraction ledger both record archives in this form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: i64,
+    /// Hex SHA-256 of the contents, when the file was hashed
+    pub sha256: Option<String>,
+}
+
+impl Fingerprint {
+    /// Whether both describe the same contents: by hash when both have one,
+    /// otherwise by size and modification time
+    pub fn same_contents(&self, other: &Fingerprint) -> bool {
+        match (&self.sha256, &other.sha256) {
+            (Some(a), Some(b)) => a == b && self.size == other.size,
+            _ => self.size == other.size && self.mtime == other.mtime,
+        }
+    }
+}
+
+/// Modification time in seconds since the Unix epoch, negative before it and
+/// 0 when the platform doesn't record one
+pub fn mtime_secs(metadata: &fs::Metadata) -> i64 {
+    match metadata.modified().map(|modified| modified.duration_since(UNIX_EPOCH)) {
+        Ok(Ok(since)) => since.as_secs() as i64,
+        Ok(Err(before)) => -(before.duration().as_secs() as i64),
+        Err(_) => 0,
+    }
+}
+
+/// Hex SHA-256 of a file's contents, checking `cancel` between reads and
+/// passing the size of every chunk read to `on_read`
+pub fn sha256_file(path: &Path, cancel: &AtomicBool, on_read: impl Fn(u64)) -> Result<String> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            bail!("Hashing cancelled");
+        }
+        let read = match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        };
+        hasher.update(&buffer[..read]);
+        on_read(read as u64);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Hash every file in parallel, in the order given
+pub fn sha256_files(paths: &[PathBuf], cancel: &AtomicBool, on_read: impl Fn(u64) + Sync) -> Result<Vec<String>> {
+    paths
+        .par_iter()
+        .map(|path| sha256_file(path, cancel, &on_read))
+        .collect()
+}
@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Leading directory levels removed from the entries of one archive by
+/// `--flatten-depth`, planned before extraction so that flattening never
+/// makes two entries land on the same path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlattenPlan {
+    /// Levels to strip from each entry, by index in the archive
+    #[serde(skip)]
+    strip: Vec<usize>,
+    /// Most levels removed from any entry
+    pub levels: usize,
+    /// Branches flattened less than asked because going further would collide
+    pub stopped: Vec<String>,
+}
+
+/// Files and directories the entries currently extract to, counted so that a
+/// branch's own paths can be taken out while checking it
+#[derive(Default)]
+struct Occupied {
+    files: HashMap<String, usize>,
+    dirs: HashMap<String, usize>,
+}
+
+impl Occupied {
+    fn update(&mut self, components: &[&str], is_dir: bool, add: bool) {
+        let dir_len = if is_dir { components.len() } else { components.len().saturating_sub(1) };
+        let mut paths: Vec<(bool, String)> = (1..=dir_len).map(|end| (true, components[..end].join("/"))).collect();
+        if !is_dir && !components.is_empty() {
+            paths.push((false, components.join("/")));
+        }
+        for (dir, path) in paths {
+            let counts = if dir { &mut self.dirs } else { &mut self.files };
+            if add {
+                *counts.entry(path).or_default() += 1;
+            } else if let Some(count) = counts.get_mut(&path) {
+                *count -= 1;
+                if *count == 0 {
+                    counts.remove(&path);
+                }
+            }
+        }
+    }
+
+    /// Whether an entry extracted at `components` would overwrite a file, or
+    /// a file would have to exist where it needs a directory
+    fn conflicts(&self, components: &[&str], is_dir: bool) -> bool {
+        let dir_len = if is_dir { components.len() } else { components.len().saturating_sub(1) };
+        if (1..=dir_len).any(|end| self.files.contains_key(&components[..end].join("/"))) {
+            return true;
+        }
+        let path = components.join("/");
+        !is_dir && (self.files.contains_key(&path) || self.dirs.contains_key(&path))
+    }
+}
+
+fn split(name: &str) -> Vec<&str> {
+    name.trim_end_matches('/').split('/').filter(|c| !c.is_empty()).collect()
+}
+
+impl FlattenPlan {
+    /// Plan to strip up to `max_depth` leading components from every entry.
+    /// Each top-level directory of what the entries currently extract to is
+    /// a branch, lifted a level at a time; a branch stops when it holds a
+    /// file at the top, or when lifting its contents would collide with what
+    /// another branch extracts. Lifting repeats until no branch can go
+    /// further, so a branch blocked by another's paths is retried once those
+    /// have moved out of the way, and flattening the result again changes
+    /// nothing.
+    pub fn new(names: &[String], max_depth: usize) -> Self {
+        let components: Vec<Vec<&str>> = names.iter().map(|name| split(name)).collect();
+        let is_dir = |i: usize| names[i].ends_with('/');
+        let mut strip = vec![0; names.len()];
+        let mut active: Vec<bool> = components.iter().map(|c| !c.is_empty()).collect();
+
+        let mut occupied = Occupied::default();
+        for (i, c) in components.iter().enumerate() {
+            occupied.update(c, is_dir(i), true);
+        }
+
+        let blocked = loop {
+            // Entries still being flattened, grouped by the directory they
+            // currently extract into, which lifting removes
+            let mut branches: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+            for i in (0..names.len()).filter(|&i| active[i] && strip[i] < max_depth) {
+                branches.entry(components[i][strip[i]]).or_default().push(i);
+            }
+
+            let mut blocked = Vec::new();
+            let mut lifted_any = false;
+            for members in branches.into_values() {
+                if members.iter().any(|&i| !is_dir(i) && components[i].len() <= strip[i] + 1) {
+                    for &i in &members {
+                        active[i] = false;
+                    }
+                    continue;
+                }
+
+                for &i in &members {
+                    occupied.update(&components[i][strip[i]..], is_dir(i), false);
+                }
+                let collision = members
+                    .iter()
+                    .map(|&i| (i, &components[i][strip[i] + 1..]))
+                    .find(|&(i, lifted)| occupied.conflicts(lifted, is_dir(i)));
+
+                if let Some((_, lifted)) = collision {
+                    for &i in &members {
+                        occupied.update(&components[i][strip[i]..], is_dir(i), true);
+                    }
+                    let first = members[0];
+                    blocked.push(format!(
+                        "{}/: stopped after {} levels, {:?} would collide",
+                        components[first][..=strip[first]].join("/"),
+                        strip[first],
+                        lifted.join("/")
+                    ));
+                } else {
+                    for &i in &members {
+                        strip[i] += 1;
+                        occupied.update(&components[i][strip[i]..], is_dir(i), true);
+                        if components[i].len() == strip[i] {
+                            active[i] = false;
+                        }
+                    }
+                    lifted_any = true;
+                }
+            }
+            if !lifted_any {
+                break blocked;
+            }
+        };
+
+        FlattenPlan { levels: strip.iter().copied().max().unwrap_or(0), strip, stopped: blocked }
+    }
+
+    /// Name to extract entry `index` as, `None` for a directory that was
+    /// flattened away entirely
+    pub fn apply(&self, index: usize, name: &str) -> Option<String> {
+        let levels = self.strip.get(index).copied().unwrap_or(0);
+        if levels == 0 {
+            return Some(name.to_string());
+        }
+        let components = split(name);
+        if components.len() <= levels {
+            return None;
+        }
+        let flattened = components[levels..].join("/");
+        Some(if name.ends_with('/') { flattened + "/" } else { flattened })
+    }
+}
@@ -0,0 +1,112 @@
+use console::{measure_text_width, pad_str, Alignment};
+
+const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+
+/// Render a byte count with the largest unit that keeps the value >= 1
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2} {}", value, UNITS[unit])
+}
+
+/// Like `format_size`, keeping the sign for values such as bytes saved
+pub fn format_size_signed(bytes: i64) -> String {
+    if bytes < 0 {
+        format!("-{}", format_size(bytes.unsigned_abs()))
+    } else {
+        format_size(bytes as u64)
+    }
+}
+
+/// Shorten `text` to at most `max` characters by replacing its middle with `…`
+pub fn truncate_middle(text: &str, max: usize) -> String {
+    let len = text.chars().count();
+    if len <= max || max < 3 {
+        return text.to_string();
+    }
+
+    let keep = max - 1;
+    let head = keep / 2;
+    let tail = keep - head;
+    let start: String = text.chars().take(head).collect();
+    let end: String = text.chars().skip(len - tail).collect();
+    format!("{}…{}", start, end)
+}
+
+/// Print rows as aligned columns. Cells may contain color codes; widths are
+/// measured on the visible text.
+pub fn print_table(headers: &[(&str, Alignment)], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, (header, _))| {
+            rows.iter()
+                .map(|row| measure_text_width(&row[i]))
+                .chain(std::iter::once(header.len()))
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let render = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(headers)
+            .zip(&widths)
+            .map(|((cell, (_, align)), width)| pad_str(cell, *width, *align, None).into_owned())
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("   {}", render(headers.iter().map(|(h, _)| *h).collect()));
+    for row in rows {
+        println!("   {}", render(row.iter().map(String::as_str).collect()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes_use_the_largest_unit_keeping_the_value_at_least_one() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1024), "1.00 KB");
+        assert_eq!(format_size(40 * 1024 + 512), "40.50 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.00 MB");
+        assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.00 GB");
+        assert_eq!(format_size(2 * 1024u64.pow(4)), "2.00 TB");
+        // TB is the largest unit
+        assert_eq!(format_size(2048 * 1024u64.pow(4)), "2048.00 TB");
+    }
+
+    #[test]
+    fn signed_sizes_keep_the_sign() {
+        assert_eq!(format_size_signed(-2048), "-2.00 KB");
+        assert_eq!(format_size_signed(512), "512 B");
+        assert_eq!(format_size_signed(i64::MIN), format!("-{}", format_size(1 << 63)));
+    }
+
+    #[test]
+    fn long_text_loses_its_middle() {
+        assert_eq!(truncate_middle("short.zip", 20), "short.zip");
+        assert_eq!(truncate_middle("abcdefghij", 10), "abcdefghij");
+        assert_eq!(truncate_middle("abcdefghijk", 10), "abcd…ghijk");
+        assert_eq!(truncate_middle("abcdefghijk", 10).chars().count(), 10);
+        // Counted in characters, not bytes
+        assert_eq!(truncate_middle("ééééééé", 5), "éé…éé");
+        // Too short to hold an ellipsis and something either side
+        assert_eq!(truncate_middle("abcdef", 2), "abcdef");
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{bail, Context, Result};
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+
+use crate::format::format_size;
+
+/// Room a run needs on its output volume against what the volume has
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct SpaceCheck {
+    /// Bytes the run is about to write
+    pub needed: u64,
+    /// Bytes available to this user on the output volume
+    pub available: u64,
+    /// Bytes to leave free, from `--min-free-space`
+    pub reserve: u64,
+}
+
+impl SpaceCheck {
+    pub fn is_short(&self) -> bool {
+        self.needed.saturating_add(self.reserve) > self.available
+    }
+
+    pub fn describe(&self) -> String {
+        let reserve = if self.reserve > 0 { format!(" and {} kept free", format_size(self.reserve)) } else { String::new() };
+        format!("{} to write{}, {} available", format_size(self.needed), reserve, format_size(self.available))
+    }
+}
+
+/// Compare `needed` bytes, plus `reserve` to leave free, with the space
+/// available on the volume `output` is or will be created on
+pub fn check_space(output: &Path, needed: u64, reserve: u64) -> Result<SpaceCheck> {
+    let existing = output.ancestors().find(|p| p.exists()).unwrap_or(Path::new("."));
+    let available = platform::available(existing)
+        .with_context(|| format!("Failed to determine the free space of {:?}", output))?;
+    Ok(SpaceCheck { needed, available, reserve })
+}
+
+/// The free space preflight of `unzip` and `strip`: warn when the output
+/// volume looks too small, or refuse to start when `min_free` was given.
+/// A volume that can't be queried only gets a warning.
+pub fn preflight(output: &Path, needed: u64, min_free: Option<u64>) -> Result<Option<SpaceCheck>> {
+    let check = match check_space(output, needed, min_free.unwrap_or(0)) {
+        Ok(check) => check,
+        Err(e) => {
+            println!("⚠️  {:#}", e);
+            return Ok(None);
+        }
+    };
+    if check.is_short() {
+        if min_free.is_some() {
+            bail!("Not enough space on the output volume of {:?}: {}", output, check.describe());
+        }
+        println!("⚠️  Output volume may fill up: {} (--min-free-space to refuse)", check.describe());
+    }
+    Ok(Some(check))
+}
+
+/// Whether the output volume filled up or the user ran out of quota
+pub fn is_out_of_space(error: &anyhow::Error) -> bool {
+    error.chain().filter_map(|cause| cause.downcast_ref::<io::Error>()).any(|e| {
+        matches!(e.kind(), io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded)
+    })
+}
+
+#[cfg(unix)]
+mod platform {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn available(path: &Path) -> io::Result<u64> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    pub fn available(path: &Path) -> io::Result<u64> {
+        let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut available = 0u64;
+        if unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut available, std::ptr::null_mut(), std::ptr::null_mut()) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(available)
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+    use std::io;
+    use std::path::Path;
+
+    pub fn available(_path: &Path) -> io::Result<u64> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "free space is unknown on this platform"))
+    }
+}
@@ -0,0 +1,320 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use zip::ZipArchive;
+
+use crate::format::format_size;
+use crate::zip64::is_zip64;
+
+/// Largest file FAT12/16/32 can hold: 4 GiB less one byte
+pub const FAT_MAX_FILE_SIZE: u64 = u32::MAX as u64;
+
+/// Error kind for failures caused by what the output filesystem can't store
+pub const FS_LIMITATION: &str = "filesystem limitation";
+
+/// The filesystem families whose limits change what can be extracted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsKind {
+    /// FAT12, FAT16 or FAT32
+    Fat,
+    ExFat,
+    /// Anything else, assumed to store whatever an archive holds
+    Other,
+}
+
+impl FsKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            FsKind::Fat => "FAT32",
+            FsKind::ExFat => "exFAT",
+            FsKind::Other => "other",
+        }
+    }
+
+    pub fn max_file_size(self) -> Option<u64> {
+        (self == FsKind::Fat).then_some(FAT_MAX_FILE_SIZE)
+    }
+
+    pub fn supports_symlinks(self) -> bool {
+        self == FsKind::Other
+    }
+
+    /// Whether Unix modes and owners can be set; FAT and exFAT have neither
+    pub fn supports_permissions(self) -> bool {
+        self == FsKind::Other
+    }
+}
+
+/// Tells which filesystem a path is on. [`SystemFs`] asks the OS; anything
+/// else can stand in for it.
+pub trait FsProbe: Send + Sync {
+    fn kind(&self, path: &Path) -> io::Result<FsKind>;
+}
+
+/// Asks the operating system: `statfs` on Unix, `GetVolumeInformationW` on Windows
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemFs;
+
+impl FsProbe for SystemFs {
+    /// Probes the nearest existing ancestor, so an output directory that
+    /// hasn't been created yet reports the filesystem it will be created on
+    fn kind(&self, path: &Path) -> io::Result<FsKind> {
+        let existing = path.ancestors().find(|p| p.exists()).unwrap_or(Path::new("."));
+        platform::kind(existing)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::FsKind;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    const MSDOS_SUPER_MAGIC: i64 = 0x4d44;
+    const EXFAT_SUPER_MAGIC: i64 = 0x2011_bab0;
+
+    pub fn kind(path: &Path) -> io::Result<FsKind> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(match stat.f_type as i64 {
+            MSDOS_SUPER_MAGIC => FsKind::Fat,
+            EXFAT_SUPER_MAGIC => FsKind::ExFat,
+            _ => FsKind::Other,
+        })
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::FsKind;
+    use std::ffi::{CStr, CString};
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn kind(path: &Path) -> io::Result<FsKind> {
+        let path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+        if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+        Ok(match name.to_bytes() {
+            b"msdos" => FsKind::Fat,
+            b"exfat" => FsKind::ExFat,
+            _ => FsKind::Other,
+        })
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::FsKind;
+    use std::io;
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::Path;
+    use windows_sys::Win32::Storage::FileSystem::{GetVolumeInformationW, GetVolumePathNameW};
+
+    pub fn kind(path: &Path) -> io::Result<FsKind> {
+        let path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut root = [0u16; 261];
+        let mut fs_name = [0u16; 261];
+        unsafe {
+            if GetVolumePathNameW(path.as_ptr(), root.as_mut_ptr(), root.len() as u32) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let ok = GetVolumeInformationW(
+                root.as_ptr(),
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                fs_name.as_mut_ptr(),
+                fs_name.len() as u32,
+            );
+            if ok == 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let len = fs_name.iter().position(|&c| c == 0).unwrap_or(fs_name.len());
+        Ok(match String::from_utf16_lossy(&fs_name[..len]).as_str() {
+            "FAT" | "FAT32" => FsKind::Fat,
+            "exFAT" => FsKind::ExFat,
+            _ => FsKind::Other,
+        })
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use super::FsKind;
+    use std::io;
+    use std::path::Path;
+
+    pub fn kind(_path: &Path) -> io::Result<FsKind> {
+        Ok(FsKind::Other)
+    }
+}
+
+/// What the output filesystem can't store out of a planned extraction
+#[derive(Debug, Clone, Serialize)]
+pub struct FsCheck {
+    pub filesystem: FsKind,
+    /// Symlink entries, which are written as plain files holding the link target
+    pub symlinks: usize,
+    /// Entries larger than the filesystem's largest file; these fail
+    pub oversized: usize,
+    /// Archives with zip64 records, whose entry counts can exceed what a FAT32 directory holds
+    pub zip64: usize,
+    /// `--chmod`, `--chown`, `--preserve-owner` or `--preserve perms` were given but have no effect
+    pub permissions: bool,
+}
+
+impl FsCheck {
+    pub fn is_empty(&self) -> bool {
+        self.symlinks == 0 && self.oversized == 0 && self.zip64 == 0 && !self.permissions
+    }
+
+    /// One line per incompatibility, for warnings and errors
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if let (true, Some(max)) = (self.oversized > 0, self.filesystem.max_file_size()) {
+            problems.push(format!("{} entries larger than {} will fail", self.oversized, format_size(max)));
+        }
+        if self.zip64 > 0 {
+            problems.push(format!(
+                "{} zip64 archives may hold more entries than a {} directory allows (65,534, fewer with long names)",
+                self.zip64,
+                self.filesystem.name()
+            ));
+        }
+        if self.symlinks > 0 {
+            problems.push(format!("{} symlink entries will be written as plain files", self.symlinks));
+        }
+        if self.permissions {
+            problems.push("--chmod, --chown, --preserve-owner and --preserve perms have no effect".to_string());
+        }
+        problems
+    }
+}
+
+/// Check the archives about to be extracted into `output` against what its
+/// filesystem supports. Only reads the archives' central directories, and
+/// only when the filesystem has limits.
+pub fn check_target<'a>(
+    probe: &dyn FsProbe,
+    output: &Path,
+    archives: impl IntoIterator<Item = &'a Path>,
+    sets_permissions: bool,
+) -> Result<FsCheck> {
+    let filesystem = probe.kind(output)
+        .with_context(|| format!("Failed to determine the filesystem of {:?}", output))?;
+    let mut check = FsCheck {
+        filesystem,
+        symlinks: 0,
+        oversized: 0,
+        zip64: 0,
+        permissions: sets_permissions && !filesystem.supports_permissions(),
+    };
+    if filesystem == FsKind::Other {
+        return Ok(check);
+    }
+
+    let max_size = filesystem.max_file_size().unwrap_or(u64::MAX);
+    for path in archives {
+        if filesystem == FsKind::Fat && is_zip64(path) {
+            check.zip64 += 1;
+        }
+        // Unreadable archives fail on their own when extracted
+        let Some(mut archive) = fs::File::open(path).ok().and_then(|f| ZipArchive::new(f).ok()) else {
+            continue;
+        };
+        for i in 0..archive.len() {
+            let Ok(entry) = archive.by_index_raw(i) else { continue };
+            if entry.unix_mode().is_some_and(|mode| mode & 0o170000 == 0o120000) && !filesystem.supports_symlinks() {
+                check.symlinks += 1;
+            }
+            if entry.size() > max_size {
+                check.oversized += 1;
+            }
+        }
+    }
+    Ok(check)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use std::path::PathBuf;
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// Reports the same filesystem for every path
+    struct FakeFs(FsKind);
+
+    impl FsProbe for FakeFs {
+        fn kind(&self, _path: &Path) -> io::Result<FsKind> {
+            Ok(self.0)
+        }
+    }
+
+    fn write_zip_with_symlink(dir: &Path) -> PathBuf {
+        let path = dir.join("a.zip");
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("a.txt", FileOptions::default()).unwrap();
+        zip.write_all(b"a").unwrap();
+        zip.add_symlink("link", "a.txt", FileOptions::default()).unwrap();
+        fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+        path
+    }
+
+    #[test]
+    fn fat_and_exfat_targets_count_what_they_cannot_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = write_zip_with_symlink(dir.path());
+
+        for filesystem in [FsKind::Fat, FsKind::ExFat] {
+            let check = check_target(&FakeFs(filesystem), dir.path(), [archive.as_path()], true).unwrap();
+            assert_eq!(check.filesystem, filesystem);
+            assert_eq!((check.symlinks, check.oversized, check.zip64), (1, 0, 0));
+            assert!(check.permissions);
+            assert_eq!(check.problems(), [
+                "1 symlink entries will be written as plain files",
+                "--chmod, --chown, --preserve-owner and --preserve perms have no effect",
+            ]);
+        }
+    }
+
+    #[test]
+    fn other_filesystems_and_clean_archives_raise_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = write_zip_with_symlink(dir.path());
+        let check = check_target(&FakeFs(FsKind::Other), dir.path(), [archive.as_path()], true).unwrap();
+        assert!(check.is_empty());
+
+        let clean = dir.path().join("clean.zip");
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        zip.start_file("a.txt", FileOptions::default()).unwrap();
+        fs::write(&clean, zip.finish().unwrap().into_inner()).unwrap();
+        let check = check_target(&FakeFs(FsKind::Fat), dir.path(), [clean.as_path()], false).unwrap();
+        assert!(check.is_empty());
+        assert!(check.problems().is_empty());
+    }
+
+    #[test]
+    fn only_fat_limits_file_size() {
+        assert_eq!(FsKind::Fat.max_file_size(), Some(FAT_MAX_FILE_SIZE));
+        assert_eq!(FsKind::ExFat.max_file_size(), None);
+        assert_eq!(FsKind::Other.max_file_size(), None);
+    }
+}
@@ -0,0 +1,240 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// The ID3v1 genre table, including the Winamp extensions, indexed by genre number
+pub const ID3V1_GENRES: [&str; 192] = [
+    "Blues", "Classic Rock", "Country", "Dance", "Disco", "Funk", "Grunge", "Hip-Hop",
+    "Jazz", "Metal", "New Age", "Oldies", "Other", "Pop", "R&B", "Rap",
+    "Reggae", "Rock", "Techno", "Industrial", "Alternative", "Ska", "Death Metal", "Pranks",
+    "Soundtrack", "Euro-Techno", "Ambient", "Trip-Hop", "Vocal", "Jazz+Funk", "Fusion", "Trance",
+    "Classical", "Instrumental", "Acid", "House", "Game", "Sound Clip", "Gospel", "Noise",
+    "Alternative Rock", "Bass", "Soul", "Punk", "Space", "Meditative", "Instrumental Pop", "Instrumental Rock",
+    "Ethnic", "Gothic", "Darkwave", "Techno-Industrial", "Electronic", "Pop-Folk", "Eurodance", "Dream",
+    "Southern Rock", "Comedy", "Cult", "Gangsta", "Top 40", "Christian Rap", "Pop/Funk", "Jungle",
+    "Native American", "Cabaret", "New Wave", "Psychedelic", "Rave", "Showtunes", "Trailer", "Lo-Fi",
+    "Tribal", "Acid Punk", "Acid Jazz", "Polka", "Retro", "Musical", "Rock & Roll", "Hard Rock",
+    "Folk", "Folk-Rock", "National Folk", "Swing", "Fast Fusion", "Bebop", "Latin", "Revival",
+    "Celtic", "Bluegrass", "Avantgarde", "Gothic Rock", "Progressive Rock", "Psychedelic Rock", "Symphonic Rock", "Slow Rock",
+    "Big Band", "Chorus", "Easy Listening", "Acoustic", "Humour", "Speech", "Chanson", "Opera",
+    "Chamber Music", "Sonata", "Symphony", "Booty Bass", "Primus", "Porn Groove", "Satire", "Slow Jam",
+    "Club", "Tango", "Samba", "Folklore", "Ballad", "Power Ballad", "Rhythmic Soul", "Freestyle",
+    "Duet", "Punk Rock", "Drum Solo", "A Cappella", "Euro-House", "Dance Hall", "Goa", "Drum & Bass",
+    "Club-House", "Hardcore Techno", "Terror", "Indie", "BritPop", "Afro-Punk", "Polsk Punk", "Beat",
+    "Christian Gangsta Rap", "Heavy Metal", "Black Metal", "Crossover", "Contemporary Christian", "Christian Rock", "Merengue", "Salsa",
+    "Thrash Metal", "Anime", "JPop", "Synthpop", "Abstract", "Art Rock", "Baroque", "Bhangra",
+    "Big Beat", "Breakbeat", "Chillout", "Downtempo", "Dub", "EBM", "Eclectic", "Electro",
+    "Electroclash", "Emo", "Experimental", "Garage", "Global", "IDM", "Illbient", "Industro-Goth",
+    "Jam Band", "Krautrock", "Leftfield", "Lounge", "Math Rock", "New Romantic", "Nu-Breakz", "Post-Punk",
+    "Post-Rock", "Psytrance", "Shoegaze", "Space Rock", "Trop Rock", "World Music", "Neoclassical", "Audiobook",
+    "Audio Theatre", "Neue Deutsche Welle", "Podcast", "Indie Rock", "G-Funk", "Dubstep", "Garage Rock", "Psybient",
+];
+
+/// Corrections applied after title-casing, before any `--genre-map` entries
+const BUILTIN_ALIASES: &[(&str, &str)] = &[
+    ("hip hop", "Hip-Hop"),
+    ("hiphop", "Hip-Hop"),
+    ("rnb", "R&B"),
+    ("r and b", "R&B"),
+    ("drum and bass", "Drum & Bass"),
+    ("dnb", "Drum & Bass"),
+    ("lofi", "Lo-Fi"),
+    ("edm", "EDM"),
+];
+
+/// The name of an ID3v1 genre number
+pub fn genre_name(index: u8) -> Option<&'static str> {
+    ID3V1_GENRES.get(index as usize).copied()
+}
+
+/// The genres a single TCON value names. Resolves ID3v2.3 references like
+/// `(17)`, `(17)(6)`, `(RX)` and `(CR)`, bare ID3v1 numbers like `17`, and
+/// `((` escapes. Text after references refines the last one, so
+/// `(4)Eurodisco` is `Eurodisco`.
+pub fn parse_genre(raw: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = raw.trim();
+    while let Some(after) = rest.strip_prefix('(') {
+        if after.starts_with('(') {
+            break;
+        }
+        let Some(end) = after.find(')') else { break };
+        let name = match &after[..end] {
+            "RX" => Some("Remix"),
+            "CR" => Some("Cover"),
+            number => number.parse().ok().and_then(genre_name),
+        };
+        let Some(name) = name else { break };
+        names.push(name.to_string());
+        rest = after[end + 1..].trim_start();
+    }
+
+    let rest = rest.strip_prefix('(').filter(|r| r.starts_with('(')).unwrap_or(rest).trim();
+    if rest.is_empty() {
+        return names;
+    }
+    match rest.parse().ok().and_then(genre_name) {
+        Some(name) => names.push(name.to_string()),
+        None => {
+            names.pop();
+            names.push(rest.to_string());
+        }
+    }
+    names
+}
+
+/// Upper-case the first letter of every word and lower-case the rest; words
+/// are split on whitespace, `-` and `/`
+pub fn title_case(name: &str) -> String {
+    let mut word_start = true;
+    name.chars()
+        .flat_map(|c| {
+            let cased: Vec<char> = if word_start { c.to_uppercase().collect() } else { c.to_lowercase().collect() };
+            word_start = c.is_whitespace() || c == '-' || c == '/';
+            cased
+        })
+        .collect()
+}
+
+/// Rewrites TCON values to canonical genre names: references become names
+/// from [`ID3V1_GENRES`], free text is title-cased, and aliases fix the rest
+#[derive(Debug, Clone)]
+pub struct GenreNormalizer {
+    /// Lower-cased name to replacement
+    aliases: HashMap<String, String>,
+}
+
+impl Default for GenreNormalizer {
+    fn default() -> Self {
+        GenreNormalizer {
+            aliases: BUILTIN_ALIASES.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect(),
+        }
+    }
+}
+
+impl GenreNormalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add aliases, replacing built-in ones with the same name; names match
+    /// case-insensitively
+    pub fn with_aliases(mut self, aliases: impl IntoIterator<Item = (String, String)>) -> Self {
+        self.aliases.extend(aliases.into_iter().map(|(from, to)| (from.trim().to_lowercase(), to)));
+        self
+    }
+
+    /// Built-in aliases merged with a `--genre-map` file of `"From" = "To"` lines
+    pub fn from_map_file(path: Option<&Path>) -> Result<Self> {
+        let normalizer = Self::new();
+        let Some(path) = path else { return Ok(normalizer) };
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read genre map {:?}", path))?;
+        let aliases: HashMap<String, String> = toml::from_str(&text)
+            .with_context(|| format!("Invalid genre map {:?}; expected \"From\" = \"To\" lines", path))?;
+        Ok(normalizer.with_aliases(aliases))
+    }
+
+    /// Canonical form of one genre name
+    pub fn canonical(&self, name: &str) -> String {
+        let name = ID3V1_GENRES
+            .iter()
+            .find(|genre| genre.eq_ignore_ascii_case(name))
+            .map(|genre| genre.to_string())
+            .unwrap_or_else(|| title_case(name));
+        self.aliases.get(&name.to_lowercase()).cloned().unwrap_or(name)
+    }
+
+    /// Normalize every value of a TCON frame, dropping duplicates
+    pub fn normalize<'a>(&self, values: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+        let mut genres: Vec<String> = Vec::new();
+        for name in values.into_iter().flat_map(parse_genre) {
+            let name = self.canonical(&name);
+            if !genres.iter().any(|g| g.eq_ignore_ascii_case(&name)) {
+                genres.push(name);
+            }
+        }
+        genres
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn genre_numbers_follow_the_id3v1_table() {
+        assert_eq!(genre_name(0), Some("Blues"));
+        assert_eq!(genre_name(17), Some("Rock"));
+        assert_eq!(genre_name(79), Some("Hard Rock"));
+        assert_eq!(genre_name(147), Some("Synthpop"));
+        assert_eq!(genre_name(191), Some("Psybient"));
+        assert_eq!(genre_name(192), None);
+        assert_eq!(genre_name(255), None);
+    }
+
+    #[test]
+    fn references_resolve_to_names() {
+        assert_eq!(parse_genre("(17)"), ["Rock"]);
+        assert_eq!(parse_genre("17"), ["Rock"]);
+        assert_eq!(parse_genre("(17)(6)"), ["Rock", "Grunge"]);
+        assert_eq!(parse_genre("(RX)(CR)"), ["Remix", "Cover"]);
+        assert_eq!(parse_genre("(4)Eurodisco"), ["Eurodisco"]);
+        assert_eq!(parse_genre("((Weird) stuff"), ["(Weird) stuff"]);
+        assert_eq!(parse_genre("(999)"), ["(999)"]);
+        assert_eq!(parse_genre("  Jazz "), ["Jazz"]);
+        assert!(parse_genre("").is_empty());
+    }
+
+    #[test]
+    fn title_case_splits_on_spaces_dashes_and_slashes() {
+        assert_eq!(title_case("classic ROCK"), "Classic Rock");
+        assert_eq!(title_case("trip-hop"), "Trip-Hop");
+        assert_eq!(title_case("pop/funk"), "Pop/Funk");
+    }
+
+    #[test]
+    fn canonical_names_prefer_the_table_then_aliases() {
+        let normalizer = GenreNormalizer::new();
+        assert_eq!(normalizer.canonical("r&b"), "R&B");
+        assert_eq!(normalizer.canonical("JPOP"), "JPop");
+        assert_eq!(normalizer.canonical("hip hop"), "Hip-Hop");
+        assert_eq!(normalizer.canonical("EDM"), "EDM");
+        assert_eq!(normalizer.canonical("vaporwave"), "Vaporwave");
+    }
+
+    #[test]
+    fn user_aliases_override_builtin_ones() {
+        let normalizer = GenreNormalizer::new().with_aliases([
+            (" Hip Hop ".to_string(), "Rap".to_string()),
+            ("Vaporwave".to_string(), "Electronic".to_string()),
+        ]);
+        assert_eq!(normalizer.canonical("hip hop"), "Rap");
+        assert_eq!(normalizer.canonical("VAPORWAVE"), "Electronic");
+        assert_eq!(normalizer.canonical("dnb"), "Drum & Bass");
+    }
+
+    #[test]
+    fn normalize_drops_duplicates() {
+        let normalizer = GenreNormalizer::new();
+        assert_eq!(normalizer.normalize(["(7)", "hip hop", "(17)rock"]), ["Hip-Hop", "Rock"]);
+    }
+
+    #[test]
+    fn map_file_merges_with_builtin_aliases() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("genres.toml");
+        fs::write(&path, "\"Synth Pop\" = \"Synthpop\"\n").unwrap();
+
+        let normalizer = GenreNormalizer::from_map_file(Some(&path)).unwrap();
+        assert_eq!(normalizer.canonical("synth pop"), "Synthpop");
+        assert_eq!(normalizer.canonical("lofi"), "Lo-Fi");
+
+        fs::write(&path, "not a map").unwrap();
+        let error = GenreNormalizer::from_map_file(Some(&path)).unwrap_err();
+        assert!(error.to_string().starts_with("Invalid genre map"), "{}", error);
+        assert!(GenreNormalizer::from_map_file(Some(&dir.path().join("missing.toml"))).is_err());
+        assert_eq!(GenreNormalizer::from_map_file(None).unwrap().canonical("rnb"), "R&B");
+    }
+}
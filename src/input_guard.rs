@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A write refused because its target is one of the run's own inputs, e.g.
+/// an entry named like an archive still waiting in the queue when the
+/// output is the input directory
+#[derive(Debug, Clone)]
+pub struct WouldClobberInput {
+    pub target: PathBuf,
+    pub input: PathBuf,
+}
+
+impl fmt::Display for WouldClobberInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} would overwrite the input {:?}; choose an output outside the input directory", self.target, self.input)
+    }
+}
+
+impl std::error::Error for WouldClobberInput {}
+
+/// The files a run reads, held as canonical paths so a target reached
+/// through `..`, a symlink or a relative output still matches
+#[derive(Debug, Default)]
+pub struct InputSet {
+    paths: HashSet<PathBuf>,
+}
+
+impl InputSet {
+    pub fn new<'a>(inputs: impl IntoIterator<Item = &'a Path>) -> Self {
+        InputSet { paths: inputs.into_iter().filter_map(|path| fs::canonicalize(path).ok()).collect() }
+    }
+
+    /// Fail when writing `target` would replace one of the inputs. A target
+    /// that doesn't exist yet can't be one.
+    pub fn check(&self, target: &Path) -> Result<(), WouldClobberInput> {
+        match fs::canonicalize(target) {
+            Ok(input) if self.paths.contains(&input) => Err(WouldClobberInput { target: target.to_path_buf(), input }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn targets_match_inputs_however_they_are_reached() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        let input = dir.path().join("a.zip");
+        fs::write(&input, b"zip").unwrap();
+        let inputs = InputSet::new([input.as_path(), dir.path().join("missing.zip").as_path()]);
+
+        let error = inputs.check(&dir.path().join("sub/../a.zip")).unwrap_err();
+        assert_eq!(error.input, fs::canonicalize(&input).unwrap());
+        assert!(error.to_string().contains("would overwrite the input"));
+        inputs.check(&dir.path().join("b.zip")).unwrap();
+        inputs.check(&dir.path().join("missing.zip")).unwrap();
+    }
+}
@@ -0,0 +1,285 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use walkdir::WalkDir;
+
+use crate::defaults::DEFAULTS;
+use crate::format::format_size;
+use crate::memory_budget::DEFAULT_BUFFER_SIZE;
+
+/// Workers a command runs when neither `--workers` nor `--io-profile` is given
+pub const DEFAULT_WORKERS: usize = DEFAULTS.workers;
+
+/// Copy buffer for disks where fewer, longer reads beat seeking
+const LARGE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Median random-read latency below which the source is taken for an SSD
+const SSD_MAX_LATENCY: Duration = Duration::from_millis(1);
+
+/// Median random-read latency below which the source is taken for a spinning
+/// disk; anything slower is assumed to be a network share
+const HDD_MAX_LATENCY: Duration = Duration::from_millis(20);
+
+const SAMPLE_BLOCK: usize = 4096;
+const SAMPLE_READS: usize = 32;
+const SAMPLE_FILES: usize = 16;
+
+/// The storage the source files live on, which decides how many workers
+/// and how large a buffer pay off
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum IoProfile {
+    /// Many parallel workers; seeks are free
+    Ssd,
+    /// Few workers with large buffers, so heads don't thrash between files
+    Hdd,
+    /// High latency but parallel-friendly: moderate workers, large buffers
+    Network,
+    /// Sample random-read latency on the source volume and pick one of the above
+    Auto,
+}
+
+/// The kind of work a run does, since a profile suits extraction and tag
+/// stripping differently
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Workload {
+    /// Long sequential reads and writes
+    Extract,
+    /// Small reads and rewrites of many files
+    Strip,
+}
+
+impl IoProfile {
+    pub fn name(self) -> &'static str {
+        match self {
+            IoProfile::Ssd => "ssd",
+            IoProfile::Hdd => "hdd",
+            IoProfile::Network => "network",
+            IoProfile::Auto => "auto",
+        }
+    }
+
+    fn workers(self, workload: Workload) -> usize {
+        match (self, workload) {
+            (IoProfile::Hdd, _) => 2,
+            (IoProfile::Network, Workload::Extract) => 4,
+            _ => 8,
+        }
+    }
+
+    fn buffer_size(self) -> usize {
+        match self {
+            IoProfile::Hdd | IoProfile::Network => LARGE_BUFFER_SIZE,
+            _ => DEFAULT_BUFFER_SIZE,
+        }
+    }
+
+    /// The profile a measured median random-read latency suggests
+    pub fn from_latency(latency: Duration) -> IoProfile {
+        if latency < SSD_MAX_LATENCY {
+            IoProfile::Ssd
+        } else if latency < HDD_MAX_LATENCY {
+            IoProfile::Hdd
+        } else {
+            IoProfile::Network
+        }
+    }
+}
+
+/// Measures how long small reads at random offsets take on a volume.
+/// [`SampledReads`] reads real files; anything else can stand in for it.
+pub trait LatencyProbe {
+    fn random_read_latency(&self, dir: &Path) -> io::Result<Duration>;
+}
+
+/// Reads 4 KiB blocks at random offsets of files under the directory and
+/// takes the median. Blocks already in the page cache read fast, so a disk
+/// that was just scanned can look quicker than it is.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SampledReads;
+
+impl LatencyProbe for SampledReads {
+    fn random_read_latency(&self, dir: &Path) -> io::Result<Duration> {
+        let files: Vec<(PathBuf, u64)> = WalkDir::new(dir)
+            .max_depth(3)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| Some((entry.path().to_path_buf(), entry.metadata().ok()?.len())))
+            .filter(|(_, len)| *len >= (SAMPLE_BLOCK * 16) as u64)
+            .take(SAMPLE_FILES)
+            .collect();
+        if files.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no files large enough to sample"));
+        }
+
+        // xorshift64, seeded from the clock; offsets only need to be spread out
+        let mut state = SystemTime::now().duration_since(UNIX_EPOCH).map_or(1, |d| d.as_nanos() as u64) | 1;
+        let mut block = [0u8; SAMPLE_BLOCK];
+        let mut samples = Vec::with_capacity(SAMPLE_READS);
+        for i in 0..SAMPLE_READS {
+            let (path, len) = &files[i % files.len()];
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let offset = state % (len - SAMPLE_BLOCK as u64);
+            let mut file = File::open(path)?;
+            let started = Instant::now();
+            file.seek(SeekFrom::Start(offset))?;
+            file.read_exact(&mut block)?;
+            samples.push(started.elapsed());
+        }
+        samples.sort();
+        Ok(samples[samples.len() / 2])
+    }
+}
+
+/// Worker count and buffer size a run uses, and the profile they came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IoSettings {
+    /// `None` when no `--io-profile` was given; never `Auto` once resolved
+    pub profile: Option<IoProfile>,
+    /// Median random-read latency measured for `--io-profile auto`
+    pub sampled_latency_us: Option<u64>,
+    pub workers: usize,
+    pub buffer_size: usize,
+}
+
+impl Default for IoSettings {
+    fn default() -> Self {
+        IoSettings { profile: None, sampled_latency_us: None, workers: DEFAULT_WORKERS, buffer_size: DEFAULT_BUFFER_SIZE }
+    }
+}
+
+impl IoSettings {
+    /// Settings for `profile` doing `workload` on files under `source`;
+    /// `Auto` samples `source` through `probe`
+    pub fn resolve(profile: Option<IoProfile>, workload: Workload, source: &Path, probe: &dyn LatencyProbe) -> Result<IoSettings> {
+        let (profile, latency) = match profile {
+            None => return Ok(IoSettings::default()),
+            Some(IoProfile::Auto) => {
+                let latency = probe.random_read_latency(source)
+                    .with_context(|| format!("Failed to sample read latency under {:?}", source))?;
+                (IoProfile::from_latency(latency), Some(latency))
+            }
+            Some(profile) => (profile, None),
+        };
+        Ok(IoSettings {
+            profile: Some(profile),
+            sampled_latency_us: latency.map(|l| l.as_micros() as u64),
+            workers: profile.workers(workload),
+            buffer_size: profile.buffer_size(),
+        })
+    }
+
+    /// Use an explicit `--workers` instead of the profile's count
+    pub fn with_workers(mut self, workers: Option<usize>) -> Self {
+        if let Some(workers) = workers {
+            self.workers = workers.max(1);
+        }
+        self
+    }
+
+    /// e.g. `hdd (sampled 8.4 ms random reads): 2 workers, 1.00 MB buffers`
+    pub fn describe(&self) -> String {
+        let profile = self.profile.map_or("default", IoProfile::name);
+        let sampled = match self.sampled_latency_us {
+            Some(us) if us < 1000 => format!(" (sampled {} µs random reads)", us),
+            Some(us) => format!(" (sampled {:.1} ms random reads)", us as f64 / 1000.0),
+            None => String::new(),
+        };
+        format!("{}{}: {} workers, {} buffers", profile, sampled, self.workers, format_size(self.buffer_size as u64))
+    }
+}
+
+/// [`IoSettings::resolve`] for a command about to run, sampling the real
+/// disk. A failed sample warns and falls back to the defaults; the chosen
+/// profile and what it implies are printed as part of the run header.
+pub fn settings_for_run(profile: Option<IoProfile>, workers: Option<usize>, workload: Workload, source: &Path) -> IoSettings {
+    let settings = IoSettings::resolve(profile, workload, source, &SampledReads)
+        .unwrap_or_else(|e| {
+            println!("⚠️  {:#}; using the default I/O settings", e);
+            IoSettings::default()
+        })
+        .with_workers(workers);
+    if profile.is_some() {
+        println!("⚙️  I/O profile {}", settings.describe());
+    }
+    settings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Reports a fixed latency, or no files to sample, and counts the calls
+    struct FakeLatency {
+        latency: Option<Duration>,
+        sampled: Cell<usize>,
+    }
+
+    impl FakeLatency {
+        fn new(latency: Option<Duration>) -> Self {
+            FakeLatency { latency, sampled: Cell::new(0) }
+        }
+    }
+
+    impl LatencyProbe for FakeLatency {
+        fn random_read_latency(&self, _dir: &Path) -> io::Result<Duration> {
+            self.sampled.set(self.sampled.get() + 1);
+            self.latency.ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no files large enough to sample"))
+        }
+    }
+
+    fn auto(latency: Duration, workload: Workload) -> IoSettings {
+        IoSettings::resolve(Some(IoProfile::Auto), workload, Path::new("source"), &FakeLatency::new(Some(latency))).unwrap()
+    }
+
+    #[test]
+    fn auto_picks_workers_and_buffers_from_sampled_latency() {
+        let ssd = auto(Duration::from_micros(200), Workload::Extract);
+        assert_eq!(ssd, IoSettings { profile: Some(IoProfile::Ssd), sampled_latency_us: Some(200), workers: 8, buffer_size: DEFAULT_BUFFER_SIZE });
+        assert_eq!(ssd.describe(), format!("ssd (sampled 200 µs random reads): 8 workers, {} buffers", format_size(DEFAULT_BUFFER_SIZE as u64)));
+
+        let hdd = auto(Duration::from_micros(8400), Workload::Extract);
+        assert_eq!(hdd, IoSettings { profile: Some(IoProfile::Hdd), sampled_latency_us: Some(8400), workers: 2, buffer_size: LARGE_BUFFER_SIZE });
+        assert!(hdd.describe().starts_with("hdd (sampled 8.4 ms random reads): 2 workers"), "{}", hdd.describe());
+
+        let network = auto(Duration::from_millis(40), Workload::Extract);
+        assert_eq!((network.profile, network.workers, network.buffer_size), (Some(IoProfile::Network), 4, LARGE_BUFFER_SIZE));
+        // Stripping small files over a share is latency-bound, so it keeps more workers
+        assert_eq!(auto(Duration::from_millis(40), Workload::Strip).workers, 8);
+    }
+
+    #[test]
+    fn latency_thresholds_separate_the_profiles() {
+        assert_eq!(IoProfile::from_latency(SSD_MAX_LATENCY - Duration::from_micros(1)), IoProfile::Ssd);
+        assert_eq!(IoProfile::from_latency(SSD_MAX_LATENCY), IoProfile::Hdd);
+        assert_eq!(IoProfile::from_latency(HDD_MAX_LATENCY), IoProfile::Network);
+    }
+
+    #[test]
+    fn only_auto_samples_the_source() {
+        let probe = FakeLatency::new(Some(Duration::from_micros(200)));
+        assert_eq!(IoSettings::resolve(None, Workload::Extract, Path::new("source"), &probe).unwrap(), IoSettings::default());
+        let hdd = IoSettings::resolve(Some(IoProfile::Hdd), Workload::Strip, Path::new("source"), &probe).unwrap();
+        assert_eq!((hdd.workers, hdd.sampled_latency_us), (2, None));
+        assert_eq!(probe.sampled.get(), 0);
+
+        // An explicit --workers wins over the profile
+        assert_eq!(hdd.with_workers(Some(6)).workers, 6);
+        assert_eq!(hdd.with_workers(Some(0)).workers, 1);
+    }
+
+    #[test]
+    fn a_failed_sample_is_an_error() {
+        let probe = FakeLatency::new(None);
+        let error = IoSettings::resolve(Some(IoProfile::Auto), Workload::Extract, Path::new("source"), &probe).unwrap_err();
+        assert!(format!("{:#}", error).contains("no files large enough to sample"), "{:#}", error);
+        assert_eq!(probe.sampled.get(), 1);
+    }
+}
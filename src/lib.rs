@@ -0,0 +1,84 @@
+//! Bulk extraction of zip archives and ID3 metadata stripping, as used by the
+//! `bulk_unzip` command-line tool.
+//!
+//! [`Extractor`] and [`MetadataStripper`] are the entry points for embedding;
+//! the modules below are what they and the CLI are built from.
+
+pub mod archive_groups;
+pub mod archive_strip;
+pub mod art_safety;
+pub mod capabilities;
+pub mod chunk_tags;
+pub mod collisions;
+pub mod copy_hooks;
+pub mod dashboard;
+pub mod date_buckets;
+pub mod defaults;
+pub mod disposal;
+pub mod download;
+pub mod duplicates;
+pub mod entry_depth;
+pub mod entry_hooks;
+pub mod entry_paths;
+pub mod error_summary;
+pub mod extensions;
+pub mod extract;
+pub mod extractor;
+pub mod field_names;
+pub mod file_types;
+pub mod filter;
+pub mod fingerprint;
+pub mod flatten;
+pub mod format;
+pub mod free_space;
+pub mod fs_limits;
+pub mod genre;
+pub mod input_guard;
+pub mod io_profile;
+pub mod listing;
+pub mod long_names;
+pub mod mapped_archive;
+pub mod memory_budget;
+pub mod metadata_stripper;
+pub mod metrics;
+pub mod name_template;
+pub mod ordering;
+pub mod output_lock;
+pub mod output_paths;
+pub mod ownership;
+pub mod password_prompt;
+pub mod passwords;
+pub mod preserve;
+pub mod presets;
+pub mod priority;
+pub mod progress_sink;
+pub mod reflink;
+pub mod result_log;
+pub mod result_stream;
+pub mod run_report;
+pub mod sampling;
+pub mod scan_export;
+pub mod scan_progress;
+pub mod scan_roots;
+pub mod schemas;
+pub mod scratch;
+pub mod sniff;
+pub mod stability;
+pub mod state_journal;
+pub mod stats;
+pub mod status_server;
+pub mod tar_stream;
+pub mod stripper;
+pub mod tag_diff;
+pub mod tag_upgrade;
+pub mod timestamps;
+pub mod validation_failure;
+pub mod verify;
+pub mod warnings;
+pub mod write_check;
+pub mod zip64;
+
+pub use extract::{ExtractOptions, ExtractOutcome, ExtractStatus, UpdateCounts};
+pub use extractor::{ArchiveReport, BatchReport, ExtractionPlan, Extractor, ProgressEvent};
+pub use metadata_stripper::{MetadataArgs, StripResult, StripStatus};
+pub use stripper::MetadataStripper;
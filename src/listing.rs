@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use zip::{CompressionMethod, ZipArchive};
+
+/// Every compression method the zip format defines, with the name it is listed
+/// under. The zip crate's enum only has variants for the methods it was built
+/// to decode, so these are compared as constants.
+const METHOD_NAMES: &[(CompressionMethod, &str)] = &[
+    (CompressionMethod::STORE, "stored"),
+    (CompressionMethod::SHRINK, "shrink"),
+    (CompressionMethod::REDUCE_1, "reduce-1"),
+    (CompressionMethod::REDUCE_2, "reduce-2"),
+    (CompressionMethod::REDUCE_3, "reduce-3"),
+    (CompressionMethod::REDUCE_4, "reduce-4"),
+    (CompressionMethod::IMPLODE, "implode"),
+    (CompressionMethod::DEFLATE, "deflate"),
+    (CompressionMethod::DEFLATE64, "deflate64"),
+    (CompressionMethod::PKWARE_IMPLODE, "pkware-implode"),
+    (CompressionMethod::BZIP2, "bzip2"),
+    (CompressionMethod::LZMA, "lzma"),
+    (CompressionMethod::IBM_ZOS_CMPSC, "ibm-cmpsc"),
+    (CompressionMethod::IBM_TERSE, "ibm-terse"),
+    (CompressionMethod::ZSTD_DEPRECATED, "zstd"),
+    (CompressionMethod::ZSTD, "zstd"),
+    (CompressionMethod::MP3, "mp3"),
+    (CompressionMethod::XZ, "xz"),
+    (CompressionMethod::JPEG, "jpeg"),
+    (CompressionMethod::WAVPACK, "wavpack"),
+    (CompressionMethod::PPMD, "ppmd"),
+    (CompressionMethod::AES, "aes"),
+];
+
+/// Lowercase name of a compression method, e.g. `stored` or `deflate`
+pub fn method_name(method: CompressionMethod) -> String {
+    METHOD_NAMES
+        .iter()
+        .find(|(known, _)| *known == method)
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| format!("{:?}", method).to_lowercase())
+}
+
+/// Compressed size as a fraction of the uncompressed size; 1.0 for empty entries
+fn ratio(compressed: u64, uncompressed: u64) -> f64 {
+    if uncompressed == 0 { 1.0 } else { compressed as f64 / uncompressed as f64 }
+}
+
+/// One entry of an archive's central directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveEntryInfo {
+    pub name: String,
+    pub is_dir: bool,
+    /// Compression method name, see [`method_name`]
+    pub method: String,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    /// Compressed size as a fraction of the uncompressed size
+    pub ratio: f64,
+}
+
+/// An archive's entries with totals across them
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArchiveListing {
+    pub entries: Vec<ArchiveEntryInfo>,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    pub ratio: f64,
+    /// Number of file entries stored with each method
+    pub methods: BTreeMap<String, usize>,
+}
+
+/// Read an archive's central directory without decompressing anything
+pub fn list_archive(path: &Path) -> Result<ArchiveListing> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("Failed to open zip file {:?}", path))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {:?}", path))?;
+
+    let mut listing = ArchiveListing::default();
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)
+            .with_context(|| format!("Failed to read entry {} of {:?}", i, path))?;
+        let info = ArchiveEntryInfo {
+            name: entry.name().to_string(),
+            is_dir: entry.is_dir(),
+            method: method_name(entry.compression()),
+            compressed_size: entry.compressed_size(),
+            uncompressed_size: entry.size(),
+            ratio: ratio(entry.compressed_size(), entry.size()),
+        };
+        listing.compressed_size += info.compressed_size;
+        listing.uncompressed_size += info.uncompressed_size;
+        if !info.is_dir {
+            *listing.methods.entry(info.method.clone()).or_default() += 1;
+        }
+        listing.entries.push(info);
+    }
+    listing.ratio = ratio(listing.compressed_size, listing.uncompressed_size);
+    Ok(listing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    #[test]
+    #[allow(deprecated)]
+    fn every_defined_method_has_a_name() {
+        // The method numbers APPNOTE.TXT assigns
+        let defined = [0, 1, 2, 3, 4, 5, 6, 8, 9, 10, 12, 14, 16, 18, 20, 93, 94, 95, 96, 97, 98, 99];
+        for id in defined {
+            let method = CompressionMethod::from_u16(id);
+            assert!(METHOD_NAMES.iter().any(|(known, _)| *known == method), "method {} has no name", id);
+        }
+
+        assert_eq!(method_name(CompressionMethod::Stored), "stored");
+        assert_eq!(method_name(CompressionMethod::Deflated), "deflate");
+        assert_eq!(method_name(CompressionMethod::from_u16(12)), "bzip2");
+        assert_eq!(method_name(CompressionMethod::from_u16(20)), "zstd");
+        assert_eq!(method_name(CompressionMethod::from_u16(93)), "zstd");
+        assert_eq!(method_name(CompressionMethod::from_u16(99)), "aes");
+        assert_eq!(method_name(CompressionMethod::from_u16(200)), "unsupported(200)");
+    }
+
+    #[test]
+    fn listing_totals_and_method_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.zip");
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let stored = FileOptions::default().compression_method(CompressionMethod::Stored);
+        let deflated = FileOptions::default().compression_method(CompressionMethod::Deflated);
+        zip.add_directory("docs/", stored).unwrap();
+        zip.start_file("docs/photo.jpg", stored).unwrap();
+        zip.write_all(&[7; 1000]).unwrap();
+        zip.start_file("docs/text.txt", deflated).unwrap();
+        zip.write_all(&[b'a'; 10_000]).unwrap();
+        fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+
+        let listing = list_archive(&path).unwrap();
+
+        let names: Vec<&str> = listing.entries.iter().map(|entry| entry.name.as_str()).collect();
+        assert_eq!(names, ["docs/", "docs/photo.jpg", "docs/text.txt"]);
+        assert!(listing.entries[0].is_dir);
+        assert_eq!(listing.entries[0].ratio, 1.0);
+        assert_eq!(listing.entries[1].method, "stored");
+        assert_eq!((listing.entries[1].compressed_size, listing.entries[1].ratio), (1000, 1.0));
+        let text = &listing.entries[2];
+        assert_eq!(text.method, "deflate");
+        assert!(text.ratio < 0.1, "{}", text.ratio);
+        assert_eq!(listing.uncompressed_size, 11_000);
+        assert_eq!(listing.compressed_size, 1000 + text.compressed_size);
+        // Directories aren't counted
+        assert_eq!(listing.methods, BTreeMap::from([("deflate".to_string(), 1), ("stored".to_string(), 1)]));
+    }
+}
@@ -0,0 +1,114 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Longest single path component, in bytes, that ext4, APFS and most other
+/// filesystems accept; NTFS counts UTF-16 units, which this never exceeds
+pub const MAX_COMPONENT_BYTES: usize = 255;
+
+/// Extensions longer than this are kept as part of the name rather than
+/// preserved whole
+const MAX_EXTENSION_BYTES: usize = 16;
+
+/// Hex digits of the name's hash appended after `~`, enough that two long
+/// names sharing a prefix stay distinct
+const HASH_DIGITS: usize = 8;
+
+/// `~` and the first hex digits of the SHA-256 of `text`, standing in for it in a path
+pub fn short_hash(text: &str) -> String {
+    let hash: String = Sha256::digest(text.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect();
+    format!("~{}", &hash[..HASH_DIGITS])
+}
+
+/// An entry whose path had a component over [`MAX_COMPONENT_BYTES`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct TruncatedName {
+    pub original: String,
+    pub truncated: String,
+}
+
+/// `component` cut to at most `max_bytes`, on a character boundary, keeping
+/// its extension and ending the name with `~` and a short hash of the whole
+/// component so different long names don't collide. Returned unchanged when
+/// it already fits.
+pub fn truncate_component(component: &str, max_bytes: usize) -> String {
+    if component.len() <= max_bytes {
+        return component.to_string();
+    }
+    let suffix = short_hash(component);
+    let extension = match component.rfind('.') {
+        Some(dot) if dot > 0 && component.len() - dot <= MAX_EXTENSION_BYTES => &component[dot..],
+        _ => "",
+    };
+    let stem = &component[..component.len() - extension.len()];
+    let mut end = max_bytes.saturating_sub(suffix.len() + extension.len()).min(stem.len());
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}{}{}", &stem[..end], suffix, extension)
+}
+
+/// `path` with every over-long component truncated; a trailing `/` is kept
+pub fn truncate_path(path: &str) -> String {
+    path.split('/')
+        .map(|component| truncate_component(component, MAX_COMPONENT_BYTES))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_that_fit_are_unchanged() {
+        let name = "日".repeat(85);
+        assert_eq!(name.len(), MAX_COMPONENT_BYTES);
+        assert_eq!(truncate_component(&name, MAX_COMPONENT_BYTES), name);
+        assert_eq!(truncate_path("a/b.txt"), "a/b.txt");
+    }
+
+    #[test]
+    fn multi_byte_names_are_cut_on_a_character_boundary() {
+        for (character, count) in [("日", 86), ("😀", 64), ("é", 128), ("a", 256)] {
+            let name = format!("{}.txt", character.repeat(count));
+            let short = truncate_component(&name, MAX_COMPONENT_BYTES);
+
+            assert!(short.len() <= MAX_COMPONENT_BYTES, "{} is {} bytes", short, short.len());
+            assert!(short.len() > MAX_COMPONENT_BYTES - character.len(), "{} is {} bytes", short, short.len());
+            assert!(short.ends_with(&format!("{}.txt", short_hash(&name))), "{}", short);
+            assert!(name.starts_with(short.trim_end_matches(&format!("{}.txt", short_hash(&name)))));
+        }
+    }
+
+    #[test]
+    fn long_extensions_are_not_kept_whole() {
+        let name = format!("report.{}", "x".repeat(300));
+        let short = truncate_component(&name, MAX_COMPONENT_BYTES);
+        assert_eq!(short.len(), MAX_COMPONENT_BYTES);
+        assert!(short.starts_with("report.xxx"));
+        assert!(short.ends_with(&short_hash(&name)));
+
+        let hidden = format!(".{}", "x".repeat(300));
+        assert!(truncate_component(&hidden, MAX_COMPONENT_BYTES).ends_with(&short_hash(&hidden)));
+    }
+
+    #[test]
+    fn names_sharing_a_prefix_stay_distinct() {
+        let prefix = "a".repeat(300);
+        let first = truncate_component(&format!("{}1.txt", prefix), MAX_COMPONENT_BYTES);
+        let second = truncate_component(&format!("{}2.txt", prefix), MAX_COMPONENT_BYTES);
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn only_long_components_of_a_path_change() {
+        let long = "b".repeat(300);
+        let short = truncate_path(&format!("docs/{}/file.txt", long));
+        let parts: Vec<&str> = short.split('/').collect();
+        assert_eq!(parts.len(), 3);
+        assert_eq!((parts[0], parts[2]), ("docs", "file.txt"));
+        assert_eq!(parts[1], truncate_component(&long, MAX_COMPONENT_BYTES));
+        assert!(truncate_path(&format!("{}/", long)).ends_with('/'));
+    }
+}
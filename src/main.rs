@@ -1,242 +1,2316 @@
 use anyhow::{Context, Result};
-use clap::{Parser, Subcommand};
-use futures::future::join_all;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use console::Alignment;
+use futures::FutureExt;
+use glob::Pattern;
+use regex::Regex;
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
 use std::fs;
+use std::net::SocketAddr;
+use std::panic::AssertUnwindSafe;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
-use zip::ZipArchive;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+use std::time::Duration;
 
-mod metadata_stripper;
-use metadata_stripper::{bulk_strip_metadata, MetadataArgs};
+use bulk_unzip::download::{self, parse_header, DownloadArgs, DownloadSource};
+use bulk_unzip::archive_groups::{compile_group_pattern, split_superseded, ArchiveGroup, GroupOrder};
+use bulk_unzip::capabilities::Capabilities;
+use bulk_unzip::collisions::CollisionReport;
+use bulk_unzip::copy_hooks::CopyControl;
+use bulk_unzip::dashboard::{self, Dashboard};
+use bulk_unzip::date_buckets::DateSource;
+use bulk_unzip::defaults::{overrides, DEFAULTS};
+use bulk_unzip::disposal::Disposal;
+use bulk_unzip::duplicates::{find_duplicates, write_report, ArchiveDuplicates};
+use bulk_unzip::entry_depth::{DepthLimit, OverdeepEntries};
+use bulk_unzip::entry_hooks::{HookCounts, DEFAULT_HOOK_WORKERS};
+use bulk_unzip::error_summary::ErrorCollector;
+use bulk_unzip::extensions::{parse_extension_map, ExtensionMap};
+use bulk_unzip::extract::{
+    apply_date_buckets, apply_name_template, apply_type_labels, find_extract_dir, find_zip_files, find_zip_files_in, hash_zip_files, settle_zip_files, zip_files_at, ArchiveHealth, ExtractOptions, ExtractStatus, UpdateCounts, ZipFile,
+    DEFAULT_MAX_ENTRIES,
+};
+use bulk_unzip::file_types::{package_entry_type, ArchiveKind, FileTypeCounts};
+use bulk_unzip::format::{format_size, print_table, truncate_middle};
+use bulk_unzip::free_space::preflight;
+use bulk_unzip::listing::list_archive;
+use bulk_unzip::io_profile::{settings_for_run, IoProfile, Workload};
+use bulk_unzip::memory_budget::parse_size;
+use bulk_unzip::metadata_stripper::{bulk_strip_metadata_with_report, find_mp3_files, MetadataArgs, Mp3File, WalkOptions};
+use bulk_unzip::name_template::NameTemplate;
+use bulk_unzip::ordering::SortOrder;
+use bulk_unzip::output_lock::BusyPolicy;
+use bulk_unzip::ownership::{parse_mode, parse_owner, Ownership};
+use bulk_unzip::password_prompt::TerminalPrompt;
+use bulk_unzip::passwords::{PasswordMap, PasswordSource, Passwords};
+use bulk_unzip::preserve::{Preserve, PreserveAttr};
+use bulk_unzip::presets::{self, PresetAction, PresetArgs, PresetStore};
+use bulk_unzip::priority::run_in_background;
+use bulk_unzip::result_log::results_file_for;
+use bulk_unzip::run_report::{command_line, panic_message, ReportArgs, RunReport};
+use bulk_unzip::sampling::{Sample, SampleMode};
+use bulk_unzip::scan_export::{write_scan, ExportFormat, ScanRow};
+use bulk_unzip::scan_progress::ScanMonitor;
+use bulk_unzip::scan_roots::{RootOutcome, ScanRoots};
+use bulk_unzip::schemas::{schema, versioned, SchemaKind};
+use bulk_unzip::scratch::{move_file, ScratchDir};
+use bulk_unzip::stability::{DEFAULT_STABILIZE_TIMEOUT, SETTLE_WINDOW};
+use bulk_unzip::state_journal::{StateJournal, STATE_FILE_NAME};
+use bulk_unzip::stats::{is_encrypted, DirectoryStats};
+use bulk_unzip::status_server::{parse_serve_addr, StatusBoard, StatusServer};
+use bulk_unzip::tar_stream::write_tar;
+use bulk_unzip::validation_failure::OnValidationFailure;
+use bulk_unzip::verify::{verify_archive, VerifyOptions};
+use bulk_unzip::warnings::{taxonomy, StrictMode, WarningCategory};
+use bulk_unzip::{BatchReport, ExtractionPlan, Extractor, ProgressEvent};
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, disable_version_flag = true, arg_required_else_help = true)]
 struct Args {
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
+
+    /// Print version
+    #[arg(short = 'V', long)]
+    version: bool,
+
+    /// With --version, also list which optional features this build supports
+    #[arg(long, requires = "version")]
+    verbose: bool,
+
+    /// Disable colored output (also honors the NO_COLOR environment variable)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// How unzip and strip remove files the user may want back (stripped
+    /// originals, files --delete-removed or --overwrite drop, files an entry
+    /// hook failed): delete them, or move them to the system trash
+    #[arg(long, global = true, value_enum, default_value_t = Disposal::Delete)]
+    disposal: Disposal,
 }
 
+// Parsed once per run, so its largest variant's size costs nothing
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Bulk extract zip files
     Unzip {
-        /// Directory containing zip files to extract
-        #[arg(short, long, default_value = ".")]
-        directory: PathBuf,
+        #[command(flatten)]
+        unzip: UnzipCommand,
 
-        /// Output directory for extracted files
-        #[arg(short, long, default_value = "extracted")]
-        output: PathBuf,
+        #[command(flatten)]
+        report: ReportArgs,
 
-        /// Number of concurrent extractions
-        #[arg(short, long, default_value = "4")]
-        workers: usize,
-
-        /// Skip existing extracted directories
-        #[arg(short, long)]
-        skip_existing: bool,
+        #[command(flatten)]
+        preset: PresetArgs,
     },
     
     /// Strip metadata from MP3 files
     Strip {
-        /// Directory containing MP3 files to process
+        #[command(flatten)]
+        metadata: MetadataArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+
+        #[command(flatten)]
+        preset: PresetArgs,
+    },
+
+    /// Scan MP3 files without modifying them
+    Scan {
+        /// Directory containing files to scan
         #[arg(short, long, default_value = ".")]
         directory: PathBuf,
 
-        /// Output directory for processed files (if not specified, files are modified in place)
-        #[arg(short, long)]
-        output: Option<PathBuf>,
+        /// Which files to scan
+        #[arg(long, value_enum, default_value = "mp3")]
+        kind: ScanKind,
+
+        /// Write every scanned file to a report; the format follows the extension (.csv or .json)
+        #[arg(long)]
+        out: Option<PathBuf>,
 
-        /// Number of concurrent workers
-        #[arg(short, long, default_value = "4")]
-        workers: usize,
+        /// Group files whose audio content is identical, ignoring tags
+        #[arg(long)]
+        duplicates: bool,
 
-        /// Skip files that already have no metadata
-        #[arg(short, long)]
-        skip_clean: bool,
+        /// Write duplicate groups to a JSON report
+        #[arg(long)]
+        report: Option<PathBuf>,
 
-        /// Keep only specific metadata fields (comma-separated: title,artist,album,year)
-        #[arg(short, long)]
-        keep_fields: Option<String>,
+        /// Compute the SHA-256 of every archive (reads them in full), for change detection
+        #[arg(long)]
+        hash: bool,
+    },
 
-        /// Remove all metadata completely
-        #[arg(short, long)]
-        remove_all: bool,
+    /// Summarize the archives and MP3s in a directory without processing anything
+    Stats {
+        /// Directory to summarize
+        #[arg(short, long, default_value = ".")]
+        directory: PathBuf,
 
-        /// Show what would be done without actually doing it
-        #[arg(short, long)]
-        dry_run: bool,
+        /// Print a table or JSON
+        #[arg(long, value_enum, default_value = "table")]
+        format: StatsFormat,
     },
+
+    /// Check extracted output against the archives it came from without re-extracting
+    VerifyOutput {
+        /// Directory containing the zip files
+        #[arg(short, long, default_value = ".")]
+        directory: PathBuf,
+
+        /// Output directory the archives were extracted to
+        #[arg(short, long, default_value = "extracted")]
+        output: PathBuf,
+
+        /// Also compare CRC-32 checksums, reading every extracted file
+        #[arg(long)]
+        crc: bool,
+
+        /// Report files in the output that no archive entry accounts for
+        #[arg(long)]
+        check_extra: bool,
+
+        /// The --name-template the archives were extracted with
+        #[arg(long, value_parser = NameTemplate::parse)]
+        name_template: Option<NameTemplate>,
+
+        /// Whether the archives were extracted with --label-by-type
+        #[arg(long)]
+        label_by_type: bool,
+
+        /// Whether the archives were extracted with --split-output-by-date
+        #[arg(long)]
+        split_output_by_date: bool,
+
+        /// The --date-source the archives were extracted with
+        #[arg(long, value_enum, default_value = "archive", requires = "split_output_by_date")]
+        date_source: DateSource,
+
+        /// The --flatten-depth the archives were extracted with
+        #[arg(long, default_value_t = 0)]
+        flatten_depth: usize,
+
+        /// The archives were extracted with --normalize-extensions
+        #[arg(long)]
+        normalize_extensions: bool,
+
+        /// The --extension-map the archives were extracted with
+        #[arg(long, value_name = "FROM=TO,...", value_parser = parse_extension_map, requires = "normalize_extensions")]
+        extension_map: Option<ExtensionMap>,
+
+        /// The --max-entry-depth the archives were extracted with
+        #[arg(long, value_name = "N")]
+        max_entry_depth: Option<usize>,
+
+        /// The archives were extracted with --flatten-overdeep
+        #[arg(long, requires = "max_entry_depth")]
+        flatten_overdeep: bool,
+    },
+
+    /// List an archive's entries with their compression method and ratio
+    List {
+        /// Zip file to list
+        archive: PathBuf,
+
+        /// Order entries by uncompressed size (largest first), ratio (worst first) or name
+        #[arg(long, value_enum, default_value = "name")]
+        sort: ListSort,
+    },
+
+    /// Run an earlier unzip or strip again for just the archives or files that
+    /// failed, merging the new outcomes into its run report
+    Retry {
+        /// Run report of the earlier run; updated in place
+        #[arg(long)]
+        report: PathBuf,
+
+        /// Result statuses to retry, comma-separated
+        #[arg(long, value_delimiter = ',', default_value = "Failed,SkippedEncrypted")]
+        statuses: Vec<String>,
+
+        /// Options of the earlier command to change, after `--`, e.g.
+        /// `-- --attempt-suspect --workers 1`
+        #[arg(last = true)]
+        overrides: Vec<String>,
+    },
+
+    /// List, show or delete the option presets unzip and strip save with
+    /// --save-preset, kept in the config file ($BULK_UNZIP_CONFIG, or
+    /// bulk-unzip/config.toml in the user's configuration directory)
+    Presets {
+        #[command(subcommand)]
+        action: PresetAction,
+    },
+
+    /// Print the JSON Schema of a serialized result type, for tools that
+    /// consume reports; `x-schema-version` is the version documents carry
+    Schema {
+        #[arg(value_enum)]
+        kind: SchemaKind,
+    },
+}
+
+/// The options of `unzip` as given on the command line
+#[derive(clap::Args, Debug)]
+struct UnzipCommand {
+    /// Directory containing zip files to extract; give it more than once to
+    /// extract from several, each into `<output>/<directory name>/`
+    #[arg(short = 'd', long = "directory", value_name = "DIRECTORY", default_value = ".")]
+    directories: Vec<PathBuf>,
+
+    /// Output directory for extracted files
+    #[arg(short, long, default_value = "extracted")]
+    output: PathBuf,
+
+    /// Number of concurrent extractions [default: 4 unless the build sets BULK_UNZIP_DEFAULT_WORKERS, or the --io-profile's count]
+    #[arg(short, long, visible_alias = "threads")]
+    workers: Option<usize>,
+
+    /// Storage the archives are on, which picks the worker count and buffer
+    /// size: ssd, hdd, network, or auto to sample random-read latency
+    #[arg(long, value_enum, default_value = DEFAULTS.io_profile)]
+    io_profile: Option<IoProfile>,
+
+    /// Be a good citizen on a busy machine: lower the process's CPU and I/O
+    /// priority, and halve the worker count unless --workers is given
+    #[arg(long)]
+    background: bool,
+
+    /// Skip existing extracted directories
+    #[arg(short, long, num_args = 0..=1, default_value_t = DEFAULTS.skip_existing, default_missing_value = "true", action = clap::ArgAction::Set)]
+    skip_existing: bool,
+
+    /// Extract into existing directories, rewriting only files that are missing,
+    /// older than their entry or of a different size
+    #[arg(long, conflicts_with = "skip_existing")]
+    update: bool,
+
+    /// With --update, remove the files an earlier run extracted from an archive that
+    /// it no longer contains, per the --state-file; nothing else is touched
+    #[arg(long, requires = "update", requires = "state_file")]
+    delete_removed: bool,
+
+    /// Journal every archive extracted and skip those it lists, unchanged, on later
+    /// runs; at PATH or `.bulk-unzip-state` in the output directory
+    #[arg(long, value_name = "PATH", num_args = 0..=1, conflicts_with_all = ["dry_run", "to_stdout"])]
+    state_file: Option<Option<PathBuf>>,
+
+    /// Forget every archive the --state-file lists before starting
+    #[arg(long, requires = "state_file")]
+    reset_state: bool,
+
+    /// Pick up after an interrupted run: archives the state file lists are skipped,
+    /// and in directories a run left behind only files whose size or CRC-32 differ
+    /// from their entry are rewritten; implies --state-file
+    #[arg(long, conflicts_with_all = ["update", "dry_run", "to_stdout", "reset_state"])]
+    resume: bool,
+
+    /// After each archive, remove directories under its output directory left empty
+    #[arg(long, conflicts_with = "to_stdout")]
+    prune_empty: bool,
+
+    /// Shorten entry path components over 255 bytes, keeping the extension and
+    /// adding a short hash; with false, such archives fail instead
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    truncate_long_names: bool,
+
+    /// Only extract archives containing at least one entry matching this glob (e.g. "*.mp3")
+    #[arg(long)]
+    containing: Option<String>,
+
+    /// Extract only Java packages (.jar, .war, .ear) or Android ones (.apk),
+    /// leaving out their `META-INF/` signature files
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+
+    /// With --profile, extract signature files too
+    #[arg(long, requires = "profile")]
+    keep_signatures: bool,
+
+    /// List every discovered archive with its size and entry count
+    #[arg(short, long)]
+    verbose: bool,
+
+    /// Remove a regular file that has the archive's name in the output directory
+    /// (by default the archive is extracted to `<name> (extracted)` instead)
+    #[arg(long)]
+    overwrite: bool,
+
+    /// Name extraction directories from a template: {stem}, {name}, {parent},
+    /// {date:%Y-%m-%d} (archive modification time) and {size} (bytes)
+    #[arg(long, value_parser = NameTemplate::parse)]
+    name_template: Option<NameTemplate>,
+
+    /// When two archives would extract into the same directory at once: wait
+    /// for the first to finish, rename the second to `<name> (2)`, or fail it
+    #[arg(long, value_enum, default_value = "wait")]
+    on_busy: BusyPolicy,
+
+    /// When an archive fails a CRC-32 or entry count check: move its directory aside
+    /// to `<name>.failed-<timestamp>`, remove it, or keep it. A directory that existed
+    /// before the run is always kept
+    #[arg(long, value_enum, default_value = "rename")]
+    on_validation_failure: OnValidationFailure,
+
+    /// Order to extract and report archives in: byte-wise by path, or
+    /// natural (numbers by value, so disc2 precedes disc10)
+    #[arg(long, value_enum, default_value = "path")]
+    sort: SortOrder,
+
+    /// Extract comics, ebooks, Java and Android packages and other zips into
+    /// `comics/`, `books/`, `java/`, `android/` and `archives/` under the output directory
+    #[arg(long)]
+    label_by_type: bool,
+
+    /// Extract each archive under `YYYY/MM/` in the output directory by its date.
+    /// The date is outermost: `2024/03/comics/<name>` with --label-by-type, the
+    /// name coming from --name-template if given
+    #[arg(long)]
+    split_output_by_date: bool,
+
+    /// Date --split-output-by-date goes by: the archive's modification time,
+    /// or the newest entry timestamp inside it
+    #[arg(long, value_enum, default_value = "archive", requires = "split_output_by_date")]
+    date_source: DateSource,
+
+    /// Also try to extract archives the scan flagged as empty or corrupt
+    #[arg(long)]
+    attempt_suspect: bool,
+
+    /// Seconds to wait for archives changed in the last 2 seconds, such as
+    /// downloads in progress, to stop growing; those that don't are skipped
+    /// as unstable and left for the next run
+    #[arg(long, default_value_t = DEFAULT_STABILIZE_TIMEOUT.as_secs())]
+    stabilize_timeout: u64,
+
+    /// Process only N of the archives found, after sorting and filtering
+    #[arg(long)]
+    limit: Option<usize>,
+
+    /// Which archives --limit keeps: the first N, or a random N (still
+    /// extracted in sort order)
+    #[arg(long, value_enum, default_value = "first", requires = "limit")]
+    sample: SampleMode,
+
+    /// Seed for --sample random, to pick the same archives again
+    #[arg(long, requires = "limit")]
+    seed: Option<u64>,
+
+    /// Extract only the first of byte-identical archives and skip the rest.
+    /// Archives sharing a size are hashed with SHA-256 to tell.
+    #[arg(long)]
+    skip_duplicates: bool,
+
+    /// Extract only the latest archive of each group of file names this
+    /// regex matches, its first capture group being the group key: with
+    /// `^(report)_`, report_v1.zip, report_v2.zip and report_final.zip form
+    /// one group. Archives it doesn't match are extracted as usual.
+    #[arg(long, value_name = "REGEX")]
+    latest_per_group: Option<String>,
+
+    /// Which archive of a --latest-per-group group is the latest: the most
+    /// recently modified, or the last by natural sort of the file names
+    #[arg(long, value_enum, default_value = "mtime", requires = "latest_per_group")]
+    group_order: GroupOrder,
+
+    /// Refuse archives with more entries than this
+    #[arg(long, default_value_t = DEFAULT_MAX_ENTRIES)]
+    max_entries: usize,
+
+    /// Extract archives exceeding --max-entries anyway
+    #[arg(long)]
+    force: bool,
+
+    /// Fail archives with invalid or far-future entry timestamps instead of
+    /// clamping them to the epoch or tomorrow
+    #[arg(long)]
+    strict_times: bool,
+
+    /// Refuse to start when the output is on a FAT or exFAT filesystem that
+    /// can't store some entries (symlinks, files over 4 GiB) or permissions
+    #[arg(long)]
+    strict_fs: bool,
+
+    /// Fail every archive raising a warning, e.g. clamped timestamps,
+    /// sanitized names or a skipped encrypted archive, and exit with
+    /// status 1 when any archive failed; implies --strict-fs
+    #[arg(long, conflicts_with = "to_stdout")]
+    strict: bool,
+
+    /// Warning categories --strict lets through, comma-separated
+    #[arg(long, value_name = "CATEGORIES", value_delimiter = ',', requires = "strict")]
+    strict_except: Vec<WarningCategory>,
+
+    /// Strip up to N leading directories from every entry (e.g. `delivery/final/v2/`),
+    /// stopping early in any branch where files would otherwise collide
+    #[arg(long, default_value_t = 0)]
+    flatten_depth: usize,
+
+    /// Lowercase the extension of every extracted file, e.g. `Track 01.MP3`
+    /// to `Track 01.mp3`, leaving the rest of the name as it is
+    #[arg(long)]
+    normalize_extensions: bool,
+
+    /// Also replace extensions by a canonical one, e.g. "jpeg=jpg,tif=tiff"
+    #[arg(long, value_name = "FROM=TO,...", value_parser = parse_extension_map, requires = "normalize_extensions")]
+    extension_map: Option<ExtensionMap>,
+
+    /// Skip entries with more than N path components, file name included,
+    /// e.g. machine-generated 60-level directory chains; counted per archive
+    #[arg(long, value_name = "N")]
+    max_entry_depth: Option<usize>,
+
+    /// Extract entries over --max-entry-depth instead of skipping them,
+    /// their directories past the first N-2 collapsed into one hashed directory
+    #[arg(long, requires = "max_entry_depth")]
+    flatten_overdeep: bool,
+
+    /// TOML file mapping glob patterns to the password of the archives
+    /// they match, e.g. "vendor-a/*.zip" = "secret1"; tried on encrypted
+    /// archives before any --password
+    #[arg(long, value_name = "FILE", conflicts_with = "to_stdout")]
+    password_map: Option<PathBuf>,
+
+    /// Password tried on encrypted archives no --password-map entry
+    /// opens (repeatable, tried in order); visible to other users in the
+    /// process list, so prefer --password-map
+    #[arg(long = "password", value_name = "PASSWORD", conflicts_with = "to_stdout")]
+    passwords: Vec<String>,
+
+    /// Skip encrypted archives nothing given opens instead of asking for
+    /// their password, as happens when stderr is a terminal
+    #[arg(long)]
+    no_prompt: bool,
+
+    /// Cap the memory used by in-flight buffers, e.g. 256M; buffers shrink and
+    /// fewer workers run when the requested combination wouldn't fit
+    #[arg(long, value_parser = parse_size)]
+    memory_limit: Option<u64>,
+
+    /// Refuse to start unless this much stays free on the output volume once
+    /// every archive is extracted, e.g. 1G; without it, a shortfall only warns
+    #[arg(long, value_parser = parse_size, conflicts_with = "to_stdout")]
+    min_free_space: Option<u64>,
+
+    /// Cap the combined write rate of all workers, e.g. 20M for 20 MiB/s
+    #[arg(long, value_parser = parse_size)]
+    limit_rate: Option<u64>,
+
+    /// Memory-map archives instead of reading them through the file handle.
+    /// Archives changing as they are mapped are read instead, and one found
+    /// truncated while extracted fails; truncating it mid-read can still
+    /// crash the run, so archives must not be modified while being extracted.
+    #[arg(long)]
+    mmap: bool,
+
+    /// Run this shell command on every extracted file, e.g. a virus scanner; the
+    /// path is `$1` and replaces `{}`. A non-zero exit fails the entry. On
+    /// Windows the program is run without cmd, the path in place of `{}`.
+    #[arg(long, value_name = "COMMAND", conflicts_with = "to_stdout")]
+    post_entry_hook: Option<String>,
+
+    /// How many --post-entry-hook commands run at once, apart from the extraction workers
+    #[arg(long, default_value_t = DEFAULT_HOOK_WORKERS, requires = "post_entry_hook")]
+    hook_workers: usize,
+
+    /// Remove files --post-entry-hook fails
+    #[arg(long, requires = "post_entry_hook")]
+    hook_delete_on_fail: bool,
+
+    /// Give every extracted file and directory this numeric owner, as "uid:gid" (Unix only)
+    #[arg(long, value_parser = parse_owner)]
+    chown: Option<(u32, u32)>,
+
+    /// Give every extracted file this octal mode, e.g. 640; directories also get
+    /// search permission wherever read is granted (Unix only)
+    #[arg(long, value_parser = parse_mode)]
+    chmod: Option<u32>,
+
+    /// Restore the owner recorded in each entry's Unix extra field, falling back
+    /// to --chown for entries without one (Unix only)
+    #[arg(long)]
+    preserve_owner: bool,
+
+    /// Entry attributes to keep, comma-separated: times, perms (recorded Unix
+    /// modes, overridden by --chmod), owner (as --preserve-owner) or all
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "times")]
+    preserve: Vec<PreserveAttr>,
+
+    /// Directory for intermediate files such as downloads; defaults to
+    /// `.bulk-unzip-tmp` inside the output directory
+    #[arg(long)]
+    temp_dir: Option<PathBuf>,
+
+    /// Download one archive from this URL and extract it instead of scanning --directory
+    #[arg(long, conflicts_with = "from_stdin")]
+    from_url: Option<String>,
+
+    /// Read one archive from standard input and extract it instead of scanning --directory
+    #[arg(long)]
+    from_stdin: bool,
+
+    /// Write every error in full to this file; the summary groups repeated errors
+    #[arg(long)]
+    error_log: Option<PathBuf>,
+
+    /// Write nothing; list the output paths that more than one entry would write
+    #[arg(long, conflicts_with_all = ["from_url", "from_stdin"])]
+    dry_run: bool,
+
+    /// Write every archive's entries to stdout as one tar stream, each archive
+    /// under its own top-level directory, instead of creating files.
+    /// Messages and progress go to stderr.
+    #[arg(long, conflicts_with_all = ["output", "dry_run", "update", "skip_existing", "skip_duplicates", "from_url", "from_stdin"])]
+    to_stdout: bool,
+
+    /// Serve the run's progress over HTTP while it runs: `GET /status` for
+    /// the metrics and every archive's status as JSON, `GET /events` for a
+    /// stream of server-sent progress events. Takes a port, `:port` or
+    /// `host:port`; a port alone listens on localhost only.
+    #[arg(long, value_name = "ADDR", value_parser = parse_serve_addr, conflicts_with_all = ["dry_run", "to_stdout"])]
+    serve_status: Option<SocketAddr>,
+
+    /// Let --serve-status listen on an address other machines can reach
+    #[arg(long, requires = "serve_status")]
+    allow_remote: bool,
+
+    /// Show a full-screen dashboard instead of the progress bars: overall
+    /// progress, each worker's archive, throughput and the latest errors.
+    /// p pauses, q cancels after asking, e toggles the errors. Falls back
+    /// to the bars when output isn't a terminal or it's under 60x16.
+    #[arg(long, conflicts_with_all = ["dry_run", "to_stdout"])]
+    tui: bool,
+
+    /// Keep the downloaded archive in the output directory after extracting
+    #[arg(long)]
+    keep_download: bool,
+
+    /// Send `Authorization: Bearer <TOKEN>` with --from-url
+    #[arg(long, requires = "from_url")]
+    bearer_token: Option<String>,
+
+    /// Extra request header for --from-url, as "Name: value" (repeatable)
+    #[arg(long = "header", value_parser = parse_header, requires = "from_url")]
+    headers: Vec<(String, String)>,
+
+    /// Extract just these archives instead of scanning --directory; set by `retry`
+    #[arg(long, hide = true, conflicts_with_all = ["from_url", "from_stdin"])]
+    only: Vec<PathBuf>,
 }
 
-#[derive(Clone)]
-struct ZipFile {
-    path: PathBuf,
-    size: u64,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ListSort {
+    Size,
+    Ratio,
+    Name,
 }
 
-async fn find_zip_files(directory: &Path) -> Result<Vec<ZipFile>> {
-    let mut zip_files = Vec::new();
-    
-    for entry in WalkDir::new(directory)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "zip") {
-            let metadata = fs::metadata(path)
-                .with_context(|| format!("Failed to read metadata for {:?}", path))?;
-            zip_files.push(ZipFile {
-                path: path.to_path_buf(),
-                size: metadata.len(),
-            });
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum StatsFormat {
+    Table,
+    Json,
+}
+
+/// Defaults for unpacking one platform's packages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+enum Profile {
+    Java,
+    Android,
+}
+
+impl Profile {
+    /// The only archives a run with this profile extracts
+    fn kind(self) -> ArchiveKind {
+        match self {
+            Profile::Java => ArchiveKind::Java,
+            Profile::Android => ArchiveKind::Android,
         }
     }
-    
-    Ok(zip_files)
 }
 
-async fn extract_zip_file(
-    zip_file: &ZipFile,
-    output_dir: &Path,
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ScanKind {
+    Zip,
+    Mp3,
+    All,
+}
+
+#[derive(Debug, Serialize)]
+struct UnzipArgs {
+    directories: Vec<PathBuf>,
+    output: PathBuf,
+    workers: Option<usize>,
+    io_profile: Option<IoProfile>,
+    background: bool,
     skip_existing: bool,
-    progress_bar: ProgressBar,
-) -> Result<()> {
-    let file_name = zip_file.path.file_stem().unwrap().to_string_lossy();
-    let extract_dir = output_dir.join(&*file_name);
-    
-    // Skip if directory exists and skip_existing is true
-    if skip_existing && extract_dir.exists() {
-        progress_bar.finish_with_message(format!("Skipped existing: {}", file_name));
+    update: bool,
+    delete_removed: bool,
+    prune_empty: bool,
+    truncate_long_names: bool,
+    containing: Option<String>,
+    profile: Option<Profile>,
+    keep_signatures: bool,
+    verbose: bool,
+    overwrite: bool,
+    name_template: Option<NameTemplate>,
+    label_by_type: bool,
+    /// Date source of `--split-output-by-date`, when given
+    split_output_by_date: Option<DateSource>,
+    on_busy: BusyPolicy,
+    on_validation_failure: OnValidationFailure,
+    sort: SortOrder,
+    attempt_suspect: bool,
+    stabilize_timeout: Duration,
+    sample: Sample,
+    skip_duplicates: bool,
+    latest_per_group: Option<String>,
+    group_order: GroupOrder,
+    max_entries: usize,
+    force: bool,
+    strict_times: bool,
+    strict_fs: bool,
+    strict: Option<StrictMode>,
+    flatten_depth: usize,
+    normalize_extensions: Option<ExtensionMap>,
+    depth_limit: Option<DepthLimit>,
+    password_map: Option<PathBuf>,
+    /// Never written to the run report
+    #[serde(skip)]
+    passwords: Vec<String>,
+    no_prompt: bool,
+    dry_run: bool,
+    to_stdout: bool,
+    serve_status: Option<SocketAddr>,
+    allow_remote: bool,
+    tui: bool,
+    memory_limit: Option<u64>,
+    min_free_space: Option<u64>,
+    limit_rate: Option<u64>,
+    mmap: bool,
+    post_entry_hook: Option<String>,
+    hook_workers: usize,
+    hook_delete_on_fail: bool,
+    disposal: Disposal,
+    ownership: Ownership,
+    preserve: Preserve,
+    temp_dir: Option<PathBuf>,
+    /// Fetch a single archive instead of scanning `directory`
+    #[serde(serialize_with = "serialize_download")]
+    download: Option<DownloadArgs>,
+    keep_download: bool,
+    error_log: Option<PathBuf>,
+    /// Archives to extract instead of scanning `directory`
+    only: Vec<PathBuf>,
+    state_file: Option<PathBuf>,
+    reset_state: bool,
+    resume: bool,
+}
+
+/// Only where the archive came from; the token and headers may be secret
+fn serialize_download<S: serde::Serializer>(download: &Option<DownloadArgs>, serializer: S) -> Result<S::Ok, S::Error> {
+    match download.as_ref().map(|download| &download.source) {
+        Some(DownloadSource::Url(url)) => serializer.serialize_some(url),
+        Some(DownloadSource::Stdin) => serializer.serialize_some("stdin"),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl UnzipCommand {
+    /// The options as the run uses and reports them
+    fn resolve(self, disposal: Disposal) -> Result<UnzipArgs> {
+        let source = match (self.from_url, self.from_stdin) {
+            (Some(url), _) => Some(DownloadSource::Url(url)),
+            (None, true) => Some(DownloadSource::Stdin),
+            (None, false) => None,
+        };
+        let download = source.map(|source| DownloadArgs { source, bearer_token: self.bearer_token, headers: self.headers });
+        // Owners can't be set off Unix, where `--preserve all` still keeps times and perms
+        let preserve = Preserve::from_attrs(&self.preserve);
+        // --resume knows what finished from the journal
+        let state_file = self
+            .state_file
+            .or(self.resume.then_some(None))
+            .map(|path| path.unwrap_or_else(|| self.output.join(STATE_FILE_NAME)));
+        Ok(UnzipArgs {
+            directories: self.directories,
+            output: self.output,
+            workers: self.workers,
+            io_profile: self.io_profile,
+            background: self.background,
+            skip_existing: self.skip_existing,
+            update: self.update,
+            delete_removed: self.delete_removed,
+            prune_empty: self.prune_empty,
+            truncate_long_names: self.truncate_long_names,
+            containing: self.containing,
+            profile: self.profile,
+            keep_signatures: self.keep_signatures,
+            verbose: self.verbose,
+            overwrite: self.overwrite,
+            name_template: self.name_template,
+            label_by_type: self.label_by_type,
+            split_output_by_date: self.split_output_by_date.then_some(self.date_source),
+            on_busy: self.on_busy,
+            on_validation_failure: self.on_validation_failure,
+            sort: self.sort,
+            attempt_suspect: self.attempt_suspect,
+            stabilize_timeout: Duration::from_secs(self.stabilize_timeout),
+            sample: Sample::new(self.limit, self.sample, self.seed),
+            skip_duplicates: self.skip_duplicates,
+            latest_per_group: self.latest_per_group,
+            group_order: self.group_order,
+            max_entries: self.max_entries,
+            force: self.force,
+            strict_times: self.strict_times,
+            strict_fs: self.strict_fs,
+            strict: self.strict.then(|| StrictMode::new(self.strict_except)),
+            flatten_depth: self.flatten_depth,
+            normalize_extensions: self.normalize_extensions.then(|| self.extension_map.unwrap_or_default()),
+            depth_limit: self.max_entry_depth.map(|max| DepthLimit::new(max, self.flatten_overdeep)).transpose().map_err(anyhow::Error::msg)?,
+            password_map: self.password_map,
+            passwords: self.passwords,
+            no_prompt: self.no_prompt,
+            dry_run: self.dry_run,
+            to_stdout: self.to_stdout,
+            serve_status: self.serve_status,
+            allow_remote: self.allow_remote,
+            tui: self.tui,
+            memory_limit: self.memory_limit,
+            min_free_space: self.min_free_space,
+            limit_rate: self.limit_rate,
+            mmap: self.mmap,
+            post_entry_hook: self.post_entry_hook,
+            hook_workers: self.hook_workers,
+            hook_delete_on_fail: self.hook_delete_on_fail,
+            disposal,
+            ownership: Ownership { owner: self.chown, mode: self.chmod, preserve_owner: self.preserve_owner || (cfg!(unix) && preserve.owner) },
+            preserve,
+            temp_dir: self.temp_dir,
+            download,
+            keep_download: self.keep_download,
+            error_log: self.error_log,
+            only: self.only,
+            state_file,
+            reset_state: self.reset_state,
+            resume: self.resume,
+        })
+    }
+}
+
+/// With `--stream-results`, send the results of `report` to a file beside
+/// the report at `path`
+fn stream_results(report: &mut RunReport, args: &ReportArgs, path: Option<&Path>) -> Result<()> {
+    if !args.stream_results {
         return Ok(());
     }
+    let Some(path) = path else {
+        anyhow::bail!("--stream-results writes beside the run report; give a dry run one with --report");
+    };
+    report.stream_results(&results_file_for(path))
+}
+
+/// Write the run report, if enabled, whether the run succeeded, failed or
+/// panicked, then pass on its result or resume the panic. With `quiet_stdout`
+/// stdout carries data, so the report's path is announced on stderr.
+fn finish_report(mut report: RunReport, path: Option<PathBuf>, quiet_stdout: bool, outcome: std::thread::Result<Result<()>>) -> Result<()> {
+    match &outcome {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => report.fail(format!("{:#}", e)),
+        Err(panic) => report.fail(format!("panicked: {}", panic_message(panic.as_ref()))),
+    }
+    if let Some(path) = path {
+        let streamed = report.results_file.as_ref().map(|file| format!(", results streamed to {:?}", file)).unwrap_or_default();
+        match report.write(&path) {
+            Ok(()) if quiet_stdout => eprintln!("📝 Run report written to {:?}{}", path, streamed),
+            Ok(()) => println!("📝 Run report written to {:?}{}", path, streamed),
+            Err(e) => eprintln!("⚠️  Failed to write run report: {:#}", e),
+        }
+    }
+    match outcome {
+        Ok(result) => result,
+        Err(panic) => std::panic::resume_unwind(panic),
+    }
+}
+
+/// Which scanned archives `--to-stdout` streams, and under which names
+struct ArchiveSelection<'a> {
+    containing: Option<&'a Pattern>,
+    kind: Option<ArchiveKind>,
+    sort: SortOrder,
+    name_template: Option<&'a NameTemplate>,
+    label_by_type: bool,
+    split_output_by_date: Option<DateSource>,
+    attempt_suspect: bool,
+    stabilize_timeout: Duration,
+    latest_per_group: Option<(&'a Regex, GroupOrder)>,
+    sample: Sample,
+}
+
+/// `--to-stdout`: stream the archives as one tar, in order, on stdout. Stdout
+/// carries nothing but the tar, so every message goes to stderr, and a
+/// failure part way returns an error so the exit status reports the
+/// truncated stream.
+async fn unzip_to_stdout(roots: &ScanRoots, selection: ArchiveSelection<'_>, options: ExtractOptions, report: &mut RunReport) -> Result<()> {
+    eprintln!("🔍 Scanning for zip files in {}...", roots);
+    let mut zip_files = find_zip_files_in(roots, selection.containing, &mut ScanMonitor::new()).await?;
+    if let Some(kind) = selection.kind {
+        zip_files.retain(|f| f.kind == kind);
+    }
+    selection.sort.sort_by_path(&mut zip_files, |f| &f.path);
+    let (zip_files, unstable) = settle_zip_files(zip_files, selection.containing, selection.stabilize_timeout).await?;
+    for zip_file in &unstable {
+        eprintln!("⏳ Skipped (still being written): {:?}", zip_file.path);
+    }
+    let (zip_files, skipped_suspect): (Vec<ZipFile>, Vec<ZipFile>) = zip_files
+        .into_iter()
+        .partition(|f| f.health.should_extract(selection.attempt_suspect) && f.matching_entries != Some(0));
+    let (zip_files, groups) = match selection.latest_per_group {
+        Some((pattern, order)) => split_superseded(zip_files, pattern, order),
+        None => (zip_files, Vec::new()),
+    };
+    if !groups.is_empty() {
+        let superseded: usize = groups.iter().map(|group| group.superseded.len()).sum();
+        eprintln!("🗂️  Not streaming {} superseded archives of {} groups", superseded, groups.len());
+        report.set_groups(&groups);
+    }
+    let (mut zip_files, sampled) = selection.sample.apply(zip_files);
+    if let Some(sampled) = &sampled {
+        eprintln!("🎯 Limit: {}", sampled.describe());
+    }
+    if let Some(template) = selection.name_template {
+        apply_name_template(&mut zip_files, template)?;
+    }
+    if selection.label_by_type {
+        apply_type_labels(&mut zip_files);
+    }
+    if let Some(source) = selection.split_output_by_date {
+        apply_date_buckets(&mut zip_files, source);
+    }
+    let total_uncompressed: u64 = zip_files.iter().map(|f| f.uncompressed_size).sum();
+    report.set_scan(&serde_json::json!({
+        "archives": zip_files.len(),
+        "total_size": zip_files.iter().map(|f| f.size).sum::<u64>(),
+        "uncompressed_size": total_uncompressed,
+        "skipped": skipped_suspect.len(),
+    }));
+    if let Some(sampled) = &sampled {
+        report.set_sample(sampled);
+    }
+    if !skipped_suspect.is_empty() {
+        eprintln!("⏭️  Not streaming {} suspect or non-matching archives", skipped_suspect.len());
+    }
+    if zip_files.is_empty() {
+        eprintln!("❌ No zip files to stream in {}", roots);
+        return Ok(());
+    }
+    eprintln!("📦 Streaming {} archives ({}) as tar to stdout", zip_files.len(), format_size(total_uncompressed));
+
+    // indicatif draws on stderr
+    let progress = ProgressBar::new(total_uncompressed);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.green/white} {bytes:>10}/{total_bytes:10} {binary_bytes_per_sec} ETA {eta}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    let bar = progress.clone();
+    let summary = tokio::task::spawn_blocking(move || {
+        let stdout = std::io::BufWriter::new(std::io::stdout().lock());
+        write_tar(&zip_files, stdout, &options, &bar)
+    })
+    .await??;
+    progress.finish_and_clear();
+    report.add_result(&summary);
+    eprintln!(
+        "✅ Streamed {} files ({}) from {} archives",
+        summary.files,
+        format_size(summary.bytes),
+        summary.archives
+    );
+    if summary.skipped + summary.unsafe_paths > 0 {
+        eprintln!("⚠️  Left out {} archives over --max-entries and {} unsafe entry paths", summary.skipped, summary.unsafe_paths);
+    }
+    Ok(())
+}
+
+/// The extractor `args` describe, but for what depends on the scan: its
+/// roots, I/O settings, journal, passwords and progress
+fn extractor(args: &UnzipArgs, control: &Arc<CopyControl>) -> Result<Extractor> {
+    let mut extractor = Extractor::new()
+        .output(&args.output)
+        // A packager's skip-existing default gives way to an explicit --update or --resume
+        .skip_existing(args.skip_existing && !args.update && !args.resume)
+        .update(args.update)
+        .delete_removed(args.delete_removed)
+        .resume(args.resume)
+        .prune_empty(args.prune_empty)
+        .truncate_long_names(args.truncate_long_names)
+        .overwrite(args.overwrite)
+        .mmap(args.mmap)
+        .max_entries((!args.force).then_some(args.max_entries))
+        .ownership(args.ownership)
+        .preserve(args.preserve)
+        .strict_times(args.strict_times)
+        .flatten_depth(args.flatten_depth)
+        .on_busy(args.on_busy)
+        .on_validation_failure(args.on_validation_failure)
+        .skip_signatures(args.profile.is_some() && !args.keep_signatures)
+        .control(control.clone())
+        .disposal(args.disposal)
+        .memory_limit(args.memory_limit)
+        .sort(args.sort)
+        .strict_fs(args.strict_fs)
+        .attempt_suspect(args.attempt_suspect)
+        .stabilize_timeout(args.stabilize_timeout)
+        .skip_duplicates(args.skip_duplicates)
+        .label_by_type(args.label_by_type)
+        .hook_workers(args.hook_workers)
+        .hook_delete_on_fail(args.hook_delete_on_fail);
+    if let Some(containing) = &args.containing {
+        let pattern = Pattern::new(containing).with_context(|| format!("Invalid --containing pattern {:?}", containing))?;
+        extractor = extractor.containing(pattern);
+    }
+    if let Some(pattern) = &args.latest_per_group {
+        extractor = extractor.latest_per_group(compile_group_pattern(pattern)?, args.group_order);
+    }
+    if let Some(limit) = args.sample.limit {
+        extractor = extractor.limit(limit, args.sample.mode, args.sample.seed);
+    }
+    if let Some(template) = &args.name_template {
+        extractor = extractor.name_template(template.clone());
+    }
+    if let Some(source) = args.split_output_by_date {
+        extractor = extractor.split_output_by_date(source);
+    }
+    if let Some(strict) = &args.strict {
+        extractor = extractor.strict(strict.clone());
+    }
+    if let Some(map) = &args.normalize_extensions {
+        extractor = extractor.normalize_extensions(map.clone());
+    }
+    if let Some(limit) = args.depth_limit {
+        extractor = extractor.max_entry_depth(limit);
+    }
+    if let Some(command) = &args.post_entry_hook {
+        extractor = extractor.post_entry_hook(command.clone());
+    }
+    Ok(extractor)
+}
+
+async fn bulk_unzip(args: UnzipArgs, status: Option<Arc<StatusBoard>>, report: &mut RunReport) -> Result<()> {
+    let errors = ErrorCollector::new(args.error_log.as_deref())?;
+    args.ownership.check_supported()?;
+    let control = Arc::new(match args.limit_rate {
+        Some(rate) => CopyControl::new().rate_limit(rate),
+        None => CopyControl::new(),
+    });
+    let extractor = extractor(&args, &control)?;
+    let mut passwords = Passwords::new(args.password_map.as_deref().map(PasswordMap::load).transpose()?, args.passwords.clone());
+    let containing = args.containing.as_deref().map(Pattern::new).transpose()?;
+    let output = &args.output;
+    if args.to_stdout {
+        let latest_per_group = args.latest_per_group.as_deref().map(compile_group_pattern).transpose()?;
+        let selection = ArchiveSelection {
+            containing: containing.as_ref(),
+            kind: args.profile.map(Profile::kind),
+            sort: args.sort,
+            name_template: args.name_template.as_ref(),
+            label_by_type: args.label_by_type,
+            split_output_by_date: args.split_output_by_date,
+            attempt_suspect: args.attempt_suspect,
+            stabilize_timeout: args.stabilize_timeout,
+            latest_per_group: latest_per_group.as_ref().map(|pattern| (pattern, args.group_order)),
+            sample: args.sample,
+        };
+        return unzip_to_stdout(&ScanRoots::new(&args.directories), selection, extractor.extract_options().clone(), report).await;
+    }
     
-    // Create extraction directory
-    fs::create_dir_all(&extract_dir)
-        .with_context(|| format!("Failed to create directory {:?}", extract_dir))?;
+    // Resuming also clears the staging files an interrupted run left in scratch space
+    let scratch = if (args.temp_dir.is_some() && !args.dry_run) || args.download.is_some() || args.resume {
+        fs::create_dir_all(output)
+            .with_context(|| format!("Failed to create output directory {:?}", output))?;
+        Some(ScratchDir::prepare(args.temp_dir.as_deref(), output)?)
+    } else {
+        None
+    };
     
-    // Open zip file
-    let file = fs::File::open(&zip_file.path)
-        .with_context(|| format!("Failed to open zip file {:?}", zip_file.path))?;
+    // The spooled download lives in a staging directory in scratch space,
+    // removed when this is dropped unless --keep-download moves it out first
+    let mut staging = None;
+    let roots = match (&args.download, &scratch) {
+        (Some(download), Some(scratch)) => {
+            let dir = scratch.staging("download")?;
+            let archive = download::fetch(download, dir.path()).await?;
+            scratch.note_usage();
+            let roots = ScanRoots::new(&[dir.path().to_path_buf()]);
+            staging = Some((dir, archive));
+            roots
+        }
+        _ => ScanRoots::new(&args.directories),
+    };
     
-    let mut archive = ZipArchive::new(file)
-        .with_context(|| format!("Failed to read zip archive {:?}", zip_file.path))?;
+    let mut io = settings_for_run(args.io_profile, args.workers, Workload::Extract, roots.first());
+    if args.background {
+        report.set_priority(&run_in_background(&mut io, args.workers.is_some()));
+    }
+    report.set_io(&io);
     
-    let total_entries = archive.len();
-    progress_bar.set_length(total_entries as u64);
+    println!("🔍 Scanning for zip files in {}...", roots);
+    let mut zip_files = if args.only.is_empty() {
+        find_zip_files_in(&roots, containing.as_ref(), &mut ScanMonitor::new().excluding_output(Some(output))).await?
+    } else {
+        zip_files_at(&args.only, containing.as_ref())?
+    };
+    if let Some(profile) = args.profile {
+        zip_files.retain(|f| f.kind == profile.kind());
+    }
     
-    // Extract all files
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)
-            .with_context(|| format!("Failed to read file at index {} in {:?}", i, zip_file.path))?;
-        
-        let outpath = extract_dir.join(file.name());
-        
-        if file.name().ends_with('/') {
-            fs::create_dir_all(&outpath)
-                .with_context(|| format!("Failed to create directory {:?}", outpath))?;
-        } else {
-            if let Some(p) = outpath.parent() {
-                if !p.exists() {
-                    fs::create_dir_all(p)
-                        .with_context(|| format!("Failed to create parent directory {:?}", p))?;
-                }
-            }
-            
-            let mut outfile = fs::File::create(&outpath)
-                .with_context(|| format!("Failed to create file {:?}", outpath))?;
-            
-            std::io::copy(&mut file, &mut outfile)
-                .with_context(|| format!("Failed to write file {:?}", outpath))?;
+    if zip_files.is_empty() {
+        println!("❌ No zip files found in {}", roots);
+        drop(staging);
+        if let Some(scratch) = scratch {
+            scratch.cleanup()?;
         }
-        
-        progress_bar.inc(1);
+        return Ok(());
     }
     
-    progress_bar.finish_with_message(format!("Completed: {}", file_name));
-    Ok(())
-}
-
-async fn bulk_unzip(directory: PathBuf, output: PathBuf, workers: usize, skip_existing: bool) -> Result<()> {
-    println!("🔍 Scanning for zip files in {:?}...", directory);
-    let zip_files = find_zip_files(&directory).await?;
+    // A download this run fetched is complete; anything else may still be arriving
+    let mut extractor = extractor.io_settings(io).roots(roots.clone()).assume_complete(staging.is_some());
+    let journal = args.state_file.clone().map(|path| StateJournal::open(path, args.reset_state)).transpose()?.map(Arc::new);
+    if let Some(journal) = &journal {
+        if journal.discarded_bytes() > 0 {
+            println!("⚠️  State file {:?}: dropped {} of damaged records", journal.path(), format_size(journal.discarded_bytes()));
+        }
+        extractor = extractor.state_journal(journal.clone());
+    }
     
-    if zip_files.is_empty() {
-        println!("❌ No zip files found in {:?}", directory);
-        return Ok(());
+    // Ctrl-C stops inside the current entry, or the duplicate hashing; the
+    // archives it interrupts are reported as failed. A second Ctrl-C quits
+    // immediately.
+    let interrupt_control = control.clone();
+    let interrupt = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            interrupt_control.cancel();
+            eprintln!("⏹️  Cancelling... (Ctrl-C again to quit now)");
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
+            }
+        }
+    });
+    
+    let hashing = if args.skip_duplicates { ProgressBar::new(0) } else { ProgressBar::hidden() };
+    hashing.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes:>10}/{total_bytes:10} {binary_bytes_per_sec} {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    hashing.set_message("Hashing");
+    let wait = args.stabilize_timeout.max(SETTLE_WINDOW);
+    let hashed = hashing.clone();
+    let plan = extractor
+        .clone()
+        .progress(move |event| match event {
+            ProgressEvent::Settling { archives } => {
+                println!("⏳ Waiting up to {}s for {} archives still being written...", wait.as_secs(), archives)
+            }
+            ProgressEvent::Hashing { read, total } => {
+                hashed.set_length(total);
+                hashed.inc(read);
+            }
+            _ => {}
+        })
+        .plan(zip_files)
+        .await?;
+    hashing.finish_and_clear();
+    if let (Some(limit), true) = (args.memory_limit, plan.memory.workers != plan.io.workers || plan.memory.buffer_size != plan.io.buffer_size) {
+        println!(
+            "🧠 Memory limit {}: using {} workers with {} buffers",
+            format_size(limit),
+            plan.memory.workers,
+            format_size(plan.memory.buffer_size as u64)
+        );
+    }
+    if !plan.unstable.is_empty() {
+        println!("⚠️  {} archives still changing, skipped (run again once they finish)", plan.unstable.len());
+    }
+    if let (Some(journal), false) = (&journal, plan.processed.is_empty()) {
+        println!("📒 {} archives already extracted per {:?}, skipped", plan.processed.len(), journal.path());
+    }
+    print_scan(&plan, &args, &roots, report);
+    if args.latest_per_group.is_some() {
+        print_archive_groups(&plan.groups, &roots);
+        report.set_groups(&plan.groups);
+    }
+    if let Some(sampled) = &plan.sample {
+        println!("🎯 Limit: {}", sampled.describe());
+        report.set_sample(sampled);
+    }
+    if args.skip_duplicates {
+        print_duplicate_archives(&plan.duplicates, &roots);
+        report.set_duplicates(&plan.duplicates);
+    }
+    match &plan.output_fs {
+        Ok(check) if !check.is_empty() => {
+            println!("⚠️  Output is on {}: {} (--strict-fs to refuse)", check.filesystem.name(), check.problems().join("; "))
+        }
+        Ok(_) => {}
+        Err(e) => println!("⚠️  {:#}", e),
+    }
+    let needed = plan.archives.iter().map(|f| f.uncompressed_size).sum();
+    if let Some(space) = preflight(output, needed, args.min_free_space)? {
+        report.set_space(&space);
     }
     
-    println!("📦 Found {} zip files:", zip_files.len());
-    let total_size: u64 = zip_files.iter().map(|f| f.size).sum();
-    println!("📊 Total size: {:.2} GB", total_size as f64 / 1024.0 / 1024.0 / 1024.0);
+    if args.dry_run {
+        interrupt.abort();
+        let collisions = extractor.collisions(&plan)?;
+        print_collision_report(&collisions, &roots, output);
+        report.add_result(&collisions);
+        return Ok(());
+    }
     
     // Create output directory
-    fs::create_dir_all(&output)
+    fs::create_dir_all(output)
         .with_context(|| format!("Failed to create output directory {:?}", output))?;
     
     // Setup progress tracking
     let multi_progress = MultiProgress::new();
     let style = ProgressStyle::default_bar()
-        .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+        .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes:>10}/{total_bytes:10} {eta:>4} {msg}")
         .unwrap()
         .progress_chars("#>-");
+    let batch_bar = multi_progress.add(ProgressBar::new(needed));
+    batch_bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.green/white} {bytes:>10}/{total_bytes:10} {binary_bytes_per_sec} ETA {eta} {msg}")
+            .unwrap()
+            .progress_chars("#>-"),
+    );
+    batch_bar.set_message("Total");
+    if let Some(status) = &status {
+        status.begin(plan.archives.iter().map(|f| f.path.as_path()), needed);
+    }
+    let byte_watch = status.as_ref().map(|status| {
+        let batch_bar = batch_bar.clone();
+        status.watch_bytes(move || batch_bar.position())
+    });
+    let dashboard = match (&status, args.tui) {
+        (Some(status), true) => match dashboard::unavailable() {
+            Some(reason) => {
+                println!("⚠️  No dashboard, {}; showing progress bars", reason);
+                None
+            }
+            None => Dashboard::start(status.clone(), control.clone(), multi_progress.clone(), plan.memory.workers)
+                .inspect_err(|e| println!("⚠️  {:#}; showing progress bars", e))
+                .ok(),
+        },
+        _ => None,
+    };
+    // Asked for on the terminal once nothing given opens an archive; not
+    // under the dashboard, which owns the screen
+    if let (false, None, Some(prompt)) = (args.no_prompt, &dashboard, TerminalPrompt::new(multi_progress.clone(), control.clone())) {
+        passwords = passwords.with_prompt(Arc::new(prompt));
+    }
+    let use_passwords = !passwords.is_empty();
+    if use_passwords {
+        extractor = extractor.passwords(passwords);
+    }
+    let board = status.clone();
+    let position = batch_bar.clone();
+    let batch = extractor
+        .progress_bars(multi_progress.clone(), batch_bar.clone(), style)
+        .progress(move |event| {
+            let Some(status) = &board else {
+                return;
+            };
+            match event {
+                ProgressEvent::Started { archive, .. } => status.archive_started(archive),
+                ProgressEvent::Finished { archive, result } => {
+                    status.advance_to(position.position());
+                    match &result.result {
+                        Ok(outcome) => status.archive_finished(archive, Ok((outcome, result.file_types.files() as u64))),
+                        Err(e) => status.archive_finished(archive, Err(e)),
+                    }
+                }
+                _ => {}
+            }
+        })
+        .run(plan)
+        .await;
+    if let Some(dashboard) = dashboard {
+        dashboard.finish();
+    }
+    interrupt.abort();
+    batch_bar.finish();
+    if let Some(byte_watch) = byte_watch {
+        byte_watch.abort();
+    }
+    let batch = batch?;
+    if let Some(status) = &status {
+        status.advance_to(batch_bar.position());
+        for archive in &batch.archives {
+            match &archive.result {
+                Ok(outcome) => status.record(&archive.path, outcome),
+                Err(e) => status.record_failed(&archive.path, e),
+            }
+        }
+    }
     
-    // Process zip files with limited concurrency
-    let chunks: Vec<_> = zip_files
-        .chunks((zip_files.len() + workers - 1) / workers)
+    // Archives raising each category of warning, those --strict failed included
+    let mut raised: BTreeMap<WarningCategory, usize> = BTreeMap::new();
+    let mut root_counts = if roots.is_multiple() { roots.counts() } else { Vec::new() };
+    for archive in &batch.archives {
+        let categories: BTreeSet<WarningCategory> = archive.warnings.iter().map(|warning| warning.category).collect();
+        for category in categories {
+            *raised.entry(category).or_default() += 1;
+        }
+        if let Some(counts) = root_counts.get_mut(roots.index_of(&archive.path)) {
+            counts.add(match &archive.result {
+                Ok(ExtractStatus::Extracted) => RootOutcome::Processed,
+                Ok(_) => RootOutcome::Skipped,
+                Err(_) => RootOutcome::Failed,
+            });
+        }
+        for failure in &archive.hooks.failures {
+            errors.record(&archive.path, &failure.path, "entry hook", &failure.error);
+        }
+        if let (Err(error), Some(kind)) = (&archive.result, &archive.error_kind) {
+            errors.record(archive.path.parent().unwrap_or(Path::new("")), &archive.path, kind, error);
+        }
+        match (archive.outcome(), &archive.result) {
+            (Some(outcome), _) => report.add_result(&serde_json::json!({ "archive": archive.path, "outcome": outcome })),
+            // Keeps the warnings --strict failed it over, for scripts to tell them apart
+            (None, Err(error)) if !archive.warnings.is_empty() => {
+                report.add_result(&serde_json::json!({ "archive": archive.path, "status": "Failed", "error": error, "warnings": archive.warnings }))
+            }
+            (None, error) => report.add_result(&serde_json::json!({ "archive": archive.path, "status": "Failed", "error": error.as_ref().err() })),
+        }
+    }
+    // The archives that succeeded, as opposed to those --strict failed after extracting
+    let succeeded = || batch.archives.iter().filter(|archive| archive.result.is_ok());
+    let failed = batch.failed().count();
+    
+    println!("📊 Extracted: {}", console::style(batch.count(ExtractStatus::Extracted)).green());
+    println!("📊 Skipped (existing): {}", console::style(batch.count(ExtractStatus::SkippedExisting)).yellow());
+    let skips = [
+        ("no matching entries", batch.count(ExtractStatus::SkippedFiltered)),
+        ("encrypted", batch.count(ExtractStatus::SkippedEncrypted)),
+        ("suspect", batch.count(ExtractStatus::SkippedSuspect)),
+        ("still being written", batch.count(ExtractStatus::SkippedUnstable)),
+        ("in the state file", batch.count(ExtractStatus::SkippedProcessed)),
+        ("duplicate", batch.duplicates.iter().map(|group| group.skipped.len()).sum()),
+        ("superseded", batch.groups.iter().map(|group| group.superseded.len()).sum()),
+    ];
+    for (reason, skipped) in skips.into_iter().filter(|(_, n)| *n > 0) {
+        println!("📊 Skipped ({}): {}", reason, console::style(skipped).yellow());
+    }
+    if use_passwords {
+        let opened_by = |source: fn(&PasswordSource) -> bool| succeeded().filter(|a| a.password_source.as_ref().is_some_and(source)).count();
+        let by_map = opened_by(|source| matches!(source, PasswordSource::Map { .. }));
+        let by_list = opened_by(|source| matches!(source, PasswordSource::List { .. }));
+        let typed = opened_by(|source| matches!(source, PasswordSource::Prompt));
+        if by_map + by_list + typed > 0 {
+            println!("🔑 Encrypted archives opened: {} by --password-map, {} by --password, {} typed in", by_map, by_list, typed);
+        }
+    }
+    if !raised.is_empty() {
+        let listed: Vec<String> = raised.iter().map(|(category, archives)| format!("{} {}", archives, category)).collect();
+        println!("⚠️  Archives with warnings: {}", listed.join(", "));
+    }
+    report.set_warnings(&taxonomy(&raised, args.strict.as_ref()));
+    if failed > 0 {
+        println!("📊 Failed: {}", console::style(failed).red());
+    }
+    for counts in &root_counts {
+        println!("📂 {}", counts);
+    }
+    if !root_counts.is_empty() {
+        report.set_roots(&root_counts);
+    }
+    if args.update {
+        let mut counts = UpdateCounts::default();
+        for archive in succeeded() {
+            counts.merge(&archive.update);
+        }
+        println!(
+            "📊 Files: {} new, {} updated, {} unchanged{}",
+            counts.new,
+            counts.updated,
+            counts.unchanged,
+            if args.delete_removed { format!(", {} deleted", counts.deleted) } else { String::new() }
+        );
+    }
+    let resumed: Vec<_> = succeeded().filter(|archive| archive.resumed).collect();
+    if !resumed.is_empty() {
+        let mut counts = UpdateCounts::default();
+        for archive in &resumed {
+            counts.merge(&archive.update);
+        }
+        println!(
+            "⏯️  Resumed {} archives: {} files intact, {} rewritten, {} new",
+            resumed.len(),
+            counts.unchanged,
+            counts.updated,
+            counts.new
+        );
+    }
+    if args.normalize_extensions.is_some() {
+        let renamed: usize = succeeded().map(|archive| archive.renamed_extensions.count).sum();
+        let collisions: usize = succeeded().map(|archive| archive.renamed_extensions.collisions.len()).sum();
+        println!(
+            "🔤 Renamed {} extensions{}",
+            renamed,
+            if collisions > 0 { format!(", {} names now shared by several entries", collisions) } else { String::new() }
+        );
+    }
+    if let Some(limit) = args.depth_limit {
+        let mut overdeep = OverdeepEntries::default();
+        for archive in succeeded() {
+            overdeep.merge(&archive.overdeep);
+        }
+        if overdeep.count() > 0 {
+            println!(
+                "📏 {} {} entries deeper than {} levels (deepest {})",
+                if limit.flatten { "Collapsed" } else { "Skipped" },
+                overdeep.count(),
+                limit.max_depth,
+                overdeep.deepest
+            );
+        }
+    }
+    if args.post_entry_hook.is_some() {
+        let mut hooks = HookCounts::default();
+        for archive in succeeded() {
+            hooks.merge(&archive.hooks);
+        }
+        let deleted = hooks.failures.iter().filter(|f| f.deleted).count();
+        println!(
+            "🛡️  Entry hook: {} files passed, {} failed{}",
+            console::style(hooks.passed).green(),
+            console::style(hooks.failed).red(),
+            if args.hook_delete_on_fail { format!(", {} removed", deleted) } else { String::new() }
+        );
+    }
+    let mut file_types = FileTypeCounts::default();
+    for archive in succeeded() {
+        file_types.merge(&archive.file_types);
+    }
+    print_file_types(&file_types);
+    print_nested_archives(&batch, args.verbose);
+    errors.print_summary();
+    report.set_errors(&errors.groups());
+    
+    if let (true, Some((_, archive))) = (args.keep_download, &staging) {
+        let kept = output.join(archive.file_name().unwrap());
+        move_file(archive, &kept)?;
+        println!("💾 Kept downloaded archive at {:?}", kept);
+    }
+    drop(staging);
+    if let Some(scratch) = scratch {
+        // Scratch space prepared only to clear leftovers isn't worth a line
+        if !args.resume || scratch.peak_usage() > 0 {
+            println!("🗂️  Peak temporary space: {} in {:?}", format_size(scratch.peak_usage()), scratch.path());
+        }
+        scratch.cleanup()?;
+    }
+    
+    if control.is_cancelled() {
+        anyhow::bail!("Extraction cancelled");
+    }
+    if args.strict.is_some() && failed > 0 {
+        anyhow::bail!("{} archives failed under --strict", failed);
+    }
+    println!("✅ Bulk extraction completed! Files extracted to: {:?}", output);
+    Ok(())
+}
+
+/// What the scan found, once the state journal had its say: counts, the
+/// `--verbose` table and the suspect archives
+fn print_scan(plan: &ExtractionPlan, args: &UnzipArgs, roots: &ScanRoots, report: &mut RunReport) {
+    let scanned = &plan.scanned;
+    println!("📦 Found {} zip files:", scanned.len());
+    let total_size: u64 = scanned.iter().map(|f| f.size).sum();
+    println!("📊 Total size: {}", format_size(total_size));
+    let matching = scanned.iter().filter(|f| f.matching_entries != Some(0)).count();
+    if args.containing.is_some() {
+        println!("🔎 Archives with matching entries: {} of {}", matching, scanned.len());
+    }
+    if args.verbose {
+        print_zip_table(scanned, roots);
+    }
+    if !args.force {
+        let over_limit = scanned.iter().filter(|f| f.entries.is_some_and(|n| n > args.max_entries)).count();
+        if over_limit > 0 {
+            println!("⚠️  {} archives exceed --max-entries {} and will not be extracted", over_limit, args.max_entries);
+        }
+    }
+    
+    let suspect = |health: ArchiveHealth| scanned.iter().filter(|f| f.health == health).count();
+    let (empty, too_small, no_cd, rar) = (
+        suspect(ArchiveHealth::Empty),
+        suspect(ArchiveHealth::TooSmall),
+        suspect(ArchiveHealth::NoCentralDirectory),
+        suspect(ArchiveHealth::Rar),
+    );
+    report.set_scan(&serde_json::json!({
+        "archives": scanned.len(),
+        "total_size": total_size,
+        "uncompressed_size": scanned.iter().map(|f| f.uncompressed_size).sum::<u64>(),
+        "matching": matching,
+        "empty": empty,
+        "too_small": too_small,
+        "no_central_directory": no_cd,
+        "rar": rar,
+    }));
+    if empty + too_small + no_cd > 0 {
+        println!(
+            "⚠️  Suspect archives: {} empty, {} too small, {} without central directory{}",
+            empty,
+            too_small,
+            no_cd,
+            if args.attempt_suspect { "" } else { " (skipped, use --attempt-suspect to try them)" },
+        );
+    }
+    if rar > 0 {
+        println!("⚠️  {} rar archives (e.g. .cbr comics) skipped: rar unsupported", rar);
+    }
+}
+
+fn print_duplicate_archives(groups: &[ArchiveDuplicates], roots: &ScanRoots) {
+    if groups.is_empty() {
+        println!("🔐 No duplicate archives");
+        return;
+    }
+    let skipped: usize = groups.iter().map(|group| group.skipped.len()).sum();
+    println!("🔐 {} duplicate archives in {} groups will be skipped:", skipped, groups.len());
+    let relative = |path: &Path| roots.relative(path).display().to_string();
+    for group in groups {
+        println!("   {} ({}, sha256 {})", relative(&group.kept), format_size(group.size), &group.sha256[..12]);
+        for path in &group.skipped {
+            println!("      = {}", relative(path));
+        }
+    }
+}
+
+fn print_archive_groups(groups: &[ArchiveGroup], roots: &ScanRoots) {
+    if groups.is_empty() {
+        println!("🗂️  No archive matches --latest-per-group");
+        return;
+    }
+    let superseded: usize = groups.iter().map(|group| group.superseded.len()).sum();
+    println!("🗂️  {} groups, {} superseded archives will be skipped:", groups.len(), superseded);
+    let relative = |path: &Path| roots.relative(path).display().to_string();
+    for group in groups {
+        println!("   {}: {}", group.key, relative(&group.kept));
+        for path in &group.superseded {
+            println!("      < {}", relative(path));
+        }
+    }
+}
+
+fn print_collision_report(report: &CollisionReport, roots: &ScanRoots, output: &Path) {
+    println!("🔍 Dry run: {} archives would write {} paths under {:?}", report.archives, report.paths, output);
+    for error in &report.unreadable {
+        println!("❌ {}", error);
+    }
+    if let Some(example) = &report.overdeep.example {
+        println!(
+            "📏 {} entries in {} archives are deeper than --max-entry-depth (deepest {}) and would be {}, e.g. {}",
+            report.overdeep.count(),
+            report.overdeep_archives,
+            report.overdeep.deepest,
+            if report.overdeep.flattened > 0 { "collapsed" } else { "skipped" },
+            truncate_middle(example, 160)
+        );
+    }
+    if report.collisions.is_empty() {
+        println!("✅ No output path would be written by more than one entry");
+        return;
+    }
+    let across = report.collisions.iter().filter(|c| c.across_archives()).count();
+    println!(
+        "⚠️  {} output paths would collide ({} between different archives):",
+        console::style(report.collisions.len()).red(),
+        across
+    );
+    for collision in &report.collisions {
+        println!("   {}", collision.path.display());
+        for claim in &collision.claims {
+            println!(
+                "      ← {}: {}{}",
+                roots.relative(&claim.archive).display(),
+                claim.entry,
+                if claim.is_dir { " (directory)" } else { "" }
+            );
+        }
+    }
+}
+
+fn print_zip_table(zip_files: &[ZipFile], roots: &ScanRoots) {
+    let rows: Vec<Vec<String>> = zip_files
+        .iter()
+        .map(|zip_file| {
+            let relative = roots.relative(&zip_file.path);
+            let entries = zip_file.entries;
+            let status = match (entries, zip_file.matching_entries) {
+                _ if zip_file.health != ArchiveHealth::Ok => console::style(zip_file.health.label()).red(),
+                (None, _) => console::style("unreadable").red(),
+                (_, Some(0)) => console::style("no match").yellow(),
+                _ => console::style("ok").green(),
+            };
+            vec![
+                truncate_middle(&relative.to_string_lossy(), 60),
+                zip_file.kind.name().to_string(),
+                format_size(zip_file.size),
+                entries.map_or_else(|| "-".to_string(), |n| n.to_string()),
+                status.to_string(),
+            ]
+        })
         .collect();
     
-    let futures: Vec<_> = chunks
-        .into_iter()
-        .map(|chunk| {
-            let chunk = chunk.to_vec();
-            let output_dir = output.clone();
-            let skip_existing = skip_existing;
-            let multi_progress = multi_progress.clone();
-            let style = style.clone();
-            
-            async move {
-                for zip_file in chunk {
-                    let progress_bar = multi_progress.add(ProgressBar::new(0));
-                    progress_bar.set_style(style.clone());
-                    progress_bar.set_message(format!("Extracting: {}", zip_file.path.file_name().unwrap().to_string_lossy()));
-                    
-                    if let Err(e) = extract_zip_file(&zip_file, &output_dir, skip_existing, progress_bar).await {
-                        eprintln!("❌ Error extracting {:?}: {}", zip_file.path, e);
-                    }
-                }
+    print_table(
+        &[
+            ("PATH", Alignment::Left),
+            ("TYPE", Alignment::Left),
+            ("SIZE", Alignment::Right),
+            ("ENTRIES", Alignment::Right),
+            ("STATUS", Alignment::Left),
+        ],
+        &rows,
+    );
+}
+
+fn print_file_types(file_types: &FileTypeCounts) {
+    if file_types.is_empty() {
+        return;
+    }
+    
+    println!("📂 File types extracted:");
+    for (ext, count) in file_types.by_count() {
+        println!("   {:<12} {:>8}", ext, count);
+    }
+    if file_types.no_extension > 0 {
+        println!("   {:<12} {:>8}", "(no ext)", file_types.no_extension);
+    }
+    if file_types.directories > 0 {
+        println!("   {:<12} {:>8}", "(dirs)", file_types.directories);
+    }
+}
+
+/// Histogram of ID3v2 tag versions with the space their tags declare
+fn print_tag_versions(mp3_files: &[Mp3File]) {
+    let mut versions: BTreeMap<&str, (usize, u64)> = BTreeMap::new();
+    for f in mp3_files {
+        let (count, bytes) = versions.entry(f.tag_version.as_deref().unwrap_or("(no ID3v2)")).or_default();
+        *count += 1;
+        *bytes += f.tag_size_bytes;
+    }
+    if versions.is_empty() {
+        return;
+    }
+    
+    println!("🏷️  Tag versions:");
+    for (version, (count, bytes)) in versions {
+        if bytes == 0 {
+            println!("   {:<12} {:>8}", version, count);
+        } else {
+            println!("   {:<12} {:>8}   {} in tags, {} average", version, count, format_size(bytes), format_size(bytes / count as u64));
+        }
+    }
+}
+
+/// Summarize extracted directories that still contain archives; they are not extracted further
+fn print_nested_archives(batch: &BatchReport, verbose: bool) {
+    let nested: Vec<&Vec<String>> = batch.with_nested_archives().map(|archive| &archive.nested_archives).collect();
+    if nested.is_empty() {
+        return;
+    }
+
+    println!(
+        "🗃️  {} extracted directories contain further archives ({} archives, run again on the output to extract them)",
+        nested.len(),
+        nested.iter().map(|n| n.len()).sum::<usize>()
+    );
+    if verbose {
+        for path in nested.into_iter().flatten() {
+            println!("   {}", path);
+        }
+    }
+}
+
+async fn scan(directory: PathBuf, kind: ScanKind, out: Option<PathBuf>, duplicates: bool, report: Option<PathBuf>, hash: bool) -> Result<()> {
+    let format = out.as_deref().map(ExportFormat::from_path).transpose()?;
+    let mut rows = Vec::new();
+    
+    if kind != ScanKind::Mp3 {
+        println!("🔍 Scanning for zip files in {:?}...", directory);
+        let mut zip_files = find_zip_files(&directory, None, &mut ScanMonitor::new()).await?;
+        let total_size: u64 = zip_files.iter().map(|f| f.size).sum();
+        let suspect = zip_files.iter().filter(|f| f.health != ArchiveHealth::Ok).count();
+        println!("📦 Found {} zip files:", zip_files.len());
+        println!("📊 Suspect archives: {}", suspect);
+        let zip64 = zip_files.iter().filter(|f| f.zip64).count();
+        if zip64 > 0 {
+            println!("📊 Zip64 archives: {} (may hold files of 4 GiB or more, too large for FAT32)", zip64);
+        }
+        println!("📊 Total size: {}", format_size(total_size));
+        if hash {
+            let bar = ProgressBar::new(total_size);
+            bar.set_style(
+                ProgressStyle::default_bar()
+                    .template("[{elapsed_precise}] {bar:40.cyan/blue} {bytes:>10}/{total_bytes:10} {binary_bytes_per_sec} {msg}")
+                    .unwrap()
+                    .progress_chars("#>-"),
+            );
+            bar.set_message("Hashing");
+            hash_zip_files(&mut zip_files, &AtomicBool::new(false), |read| bar.inc(read))?;
+            bar.finish_and_clear();
+            println!("🔐 Hashed {} archives", zip_files.len());
+        }
+        rows.extend(zip_files.iter().map(|f| ScanRow {
+            kind: f.kind.name(),
+            path: f.path.to_string_lossy().into_owned(),
+            size: f.size,
+            entries: f.entries,
+            uncompressed_size: f.entries.map(|_| f.uncompressed_size),
+            suspect: (f.health != ArchiveHealth::Ok).then(|| f.health.label().to_string()),
+            mtime: Some(f.mtime),
+            sha256: f.sha256.clone(),
+            zip64: Some(f.zip64),
+            ..ScanRow::default()
+        }));
+    }
+    
+    let mut mp3_files = Vec::new();
+    if kind != ScanKind::Zip {
+        println!("🔍 Scanning for MP3 files in {:?}...", directory);
+        let mut monitor = ScanMonitor::new();
+        mp3_files = find_mp3_files(&directory, &WalkOptions::default(), &mut monitor).await?;
+        
+        let with_metadata = mp3_files.iter().filter(|f| f.has_metadata).count();
+        let total_size: u64 = mp3_files.iter().map(|f| f.size).sum();
+        println!("📦 Found {} MP3 files:", mp3_files.len());
+        println!("📊 Files with metadata: {}", with_metadata);
+        println!("📊 Total size: {}", format_size(total_size));
+        if monitor.sidecars() > 0 {
+            println!("🙈 Skipped {} AppleDouble (._*) and empty files", monitor.sidecars());
+        }
+        for f in mp3_files.iter().filter(|f| f.format.is_other_format()) {
+            println!("⚠️  {:?} is {}, not MPEG audio", f.path, f.format);
+        }
+        print_tag_versions(&mp3_files);
+        rows.extend(mp3_files.iter().map(|f| ScanRow {
+            kind: "mp3",
+            path: f.path.to_string_lossy().into_owned(),
+            size: f.size,
+            suspect: f.format.is_other_format().then(|| format!("{} content", f.format)),
+            has_metadata: Some(f.has_metadata),
+            title: f.tags.title.clone(),
+            artist: f.tags.artist.clone(),
+            album: f.tags.album.clone(),
+            has_art: Some(f.tags.has_art),
+            tag_version: f.tag_version.clone(),
+            tag_size_bytes: Some(f.tag_size_bytes),
+            ..ScanRow::default()
+        }));
+    }
+    
+    if let (Some(out), Some(format)) = (&out, format) {
+        write_scan(&rows, out, format)?;
+        println!("📝 Scan report with {} files written to {:?}", rows.len(), out);
+    }
+    
+    if !duplicates || mp3_files.is_empty() {
+        return Ok(());
+    }
+    
+    println!("🔍 Hashing audio content...");
+    let found = find_duplicates(&mp3_files);
+    for file in &found.unreadable {
+        println!("⚠️  Skipped {:?}: {}", file.path, file.error);
+    }
+    
+    if found.groups.is_empty() {
+        println!("✅ No duplicate audio found");
+    } else {
+        println!("⚠️  Found {} groups of duplicate audio:", found.groups.len());
+        for group in &found.groups {
+            println!("  {} ({} files)", &group.hash[..16], group.files.len());
+            for file in &group.files {
+                println!("    {:?} ({})", file.path, format_size(file.size));
             }
+        }
+    }
+    
+    if let Some(report_path) = report {
+        write_report(&found, &report_path)?;
+        println!("📝 Duplicate report written to {:?}", report_path);
+    }
+    
+    Ok(())
+}
+
+async fn stats(directory: PathBuf, format: StatsFormat) -> Result<()> {
+    let zip_files = find_zip_files(&directory, None, &mut ScanMonitor::new()).await?;
+    let mp3_files = find_mp3_files(&directory, &WalkOptions::default(), &mut ScanMonitor::new()).await?;
+    
+    let mut stats = DirectoryStats::default();
+    for zip_file in &zip_files {
+        let readable = zip_file.health == ArchiveHealth::Ok && zip_file.entries.is_some();
+        stats.add_archive(
+            &zip_file.path,
+            zip_file.size,
+            zip_file.entries.map(|_| zip_file.uncompressed_size),
+            zip_file.health != ArchiveHealth::Ok,
+            readable && is_encrypted(&zip_file.path),
+        );
+    }
+    for mp3_file in &mp3_files {
+        stats.add_mp3(&mp3_file.path, mp3_file.size, mp3_file.has_metadata);
+    }
+    
+    if format == StatsFormat::Json {
+        println!("{}", serde_json::to_string_pretty(&versioned(&stats))?);
+        return Ok(());
+    }
+    
+    println!("📊 Summary of {:?}:", directory);
+    let mut rows: Vec<Vec<String>> = stats
+        .archives
+        .iter()
+        .map(|(ext, count)| vec![format!("Archives (.{})", ext), count.to_string()])
+        .collect();
+    rows.extend([
+        vec!["Compressed size".to_string(), format_size(stats.compressed_size)],
+        vec!["Estimated uncompressed size".to_string(), format_size(stats.estimated_uncompressed_size)],
+        vec!["Encrypted archives".to_string(), stats.encrypted_archives.to_string()],
+        vec!["Corrupt archives".to_string(), stats.corrupt_archives.to_string()],
+        vec!["MP3 files".to_string(), stats.mp3_files.to_string()],
+        vec!["MP3s with metadata".to_string(), stats.mp3_with_metadata.to_string()],
+        vec!["MP3 size".to_string(), format_size(stats.mp3_size)],
+    ]);
+    print_table(&[("METRIC", Alignment::Left), ("VALUE", Alignment::Right)], &rows);
+    
+    if !stats.largest.is_empty() {
+        println!("📦 Largest items:");
+        let rows: Vec<Vec<String>> = stats
+            .largest
+            .iter()
+            .map(|item| {
+                let relative = Path::new(&item.path).strip_prefix(&directory).unwrap_or(Path::new(&item.path));
+                vec![item.kind.clone(), truncate_middle(&relative.to_string_lossy(), 60), format_size(item.size)]
+            })
+            .collect();
+        print_table(&[("KIND", Alignment::Left), ("PATH", Alignment::Left), ("SIZE", Alignment::Right)], &rows);
+    }
+    
+    Ok(())
+}
+
+fn manage_presets(action: PresetAction) -> Result<()> {
+    let store = PresetStore::open()?;
+    match action {
+        PresetAction::List => {
+            let presets = store.list()?;
+            if presets.is_empty() {
+                println!("📭 No presets saved in {:?}", store.path());
+            }
+            for (name, preset) in &presets {
+                println!("🎛️  {} ({}, {} options)", name, preset.command, preset.options.len());
+            }
+        }
+        PresetAction::Show { name } => {
+            // As it is written in the config file
+            let preset = BTreeMap::from([("presets", BTreeMap::from([(name.as_str(), store.get(&name)?)]))]);
+            print!("{}", toml::to_string_pretty(&preset).context("Failed to serialize the preset")?);
+        }
+        PresetAction::Delete { name } => {
+            if !store.delete(&name)? {
+                anyhow::bail!("No preset named {:?} in {:?}", name, store.path());
+            }
+            println!("🗑️  Deleted preset {:?}", name);
+        }
+    }
+    Ok(())
+}
+
+fn list(archive: PathBuf, sort: ListSort) -> Result<()> {
+    let mut listing = list_archive(&archive)?;
+    match sort {
+        ListSort::Size => listing.entries.sort_by_key(|entry| std::cmp::Reverse(entry.uncompressed_size)),
+        ListSort::Ratio => listing.entries.sort_by(|a, b| b.ratio.total_cmp(&a.ratio)),
+        ListSort::Name => listing.entries.sort_by(|a, b| a.name.cmp(&b.name)),
+    }
+    
+    // Class files, resources and manifests of a JAR or APK are labelled and counted apart
+    let package = ArchiveKind::from_path(&archive).is_some_and(ArchiveKind::is_package);
+    let mut entry_types: BTreeMap<&str, usize> = BTreeMap::new();
+    println!("📦 {:?}:", archive);
+    let rows: Vec<Vec<String>> = listing
+        .entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .map(|entry| {
+            let mut row = vec![
+                truncate_middle(&entry.name, 60),
+                entry.method.clone(),
+                format_size(entry.compressed_size),
+                format_size(entry.uncompressed_size),
+                format!("{:.1}%", entry.ratio * 100.0),
+            ];
+            if package {
+                let entry_type = package_entry_type(&entry.name);
+                *entry_types.entry(entry_type).or_default() += 1;
+                row.push(entry_type.to_string());
+            }
+            row
         })
         .collect();
+    let mut columns = vec![
+        ("NAME", Alignment::Left),
+        ("METHOD", Alignment::Left),
+        ("COMPRESSED", Alignment::Right),
+        ("SIZE", Alignment::Right),
+        ("RATIO", Alignment::Right),
+    ];
+    if package {
+        columns.push(("TYPE", Alignment::Left));
+    }
+    print_table(&columns, &rows);
+    
+    let methods: Vec<String> = listing.methods.iter().map(|(method, count)| format!("{} {}", count, method)).collect();
+    println!("📊 {} files: {} → {} ({:.1}%), {}",
+        rows.len(),
+        format_size(listing.uncompressed_size),
+        format_size(listing.compressed_size),
+        listing.ratio * 100.0,
+        methods.join(", "));
+    if package {
+        let types: Vec<String> = entry_types.iter().map(|(entry_type, count)| format!("{} {}", count, entry_type)).collect();
+        println!("📊 By type: {}", types.join(", "));
+    }
+    Ok(())
+}
+
+/// How many paths of each kind to list for an archive that fails verification
+const VERIFY_EXAMPLES: usize = 5;
+
+async fn verify_output(directory: PathBuf, output: PathBuf, name_template: Option<NameTemplate>, label_by_type: bool, split_output_by_date: Option<DateSource>, options: VerifyOptions) -> Result<()> {
+    println!("🔍 Scanning for zip files in {:?}...", directory);
+    let mut zip_files = find_zip_files(&directory, None, &mut ScanMonitor::new().excluding_output(Some(&output))).await?;
+    if zip_files.is_empty() {
+        println!("❌ No zip files found in {:?}", directory);
+        return Ok(());
+    }
+    if let Some(template) = &name_template {
+        apply_name_template(&mut zip_files, template)?;
+    }
+    if label_by_type {
+        apply_type_labels(&mut zip_files);
+    }
+    if let Some(source) = split_output_by_date {
+        apply_date_buckets(&mut zip_files, source);
+    }
     
-    // Wait for all extractions to complete
-    join_all(futures).await;
+    let mut failed = 0;
+    for zip_file in &zip_files {
+        let archive_name = zip_file.path.strip_prefix(&directory).unwrap_or(&zip_file.path).to_string_lossy();
+        if zip_file.health == ArchiveHealth::Rar {
+            println!("⚠️  {}: rar unsupported, never extracted", archive_name);
+            continue;
+        }
+        let Some(extract_dir) = find_extract_dir(&output, zip_file) else {
+            failed += 1;
+            println!("❌ {}: no output directory {:?}", archive_name, output.join(&zip_file.output_name));
+            continue;
+        };
+        
+        let verification = match verify_archive(&zip_file.path, &extract_dir, &options) {
+            Ok(verification) => verification,
+            Err(e) => {
+                failed += 1;
+                println!("❌ {}: {:#}", archive_name, e);
+                continue;
+            }
+        };
+        if verification.passed() {
+            println!("✅ {}: {} entries match", archive_name, verification.checked);
+            continue;
+        }
+        
+        failed += 1;
+        println!(
+            "❌ {}: {} missing, {} mismatched, {} extra of {} entries",
+            archive_name,
+            verification.missing.len(),
+            verification.mismatched.len(),
+            verification.extra.len(),
+            verification.checked
+        );
+        for (label, paths) in [("missing", &verification.missing), ("mismatched", &verification.mismatched), ("extra", &verification.extra)] {
+            for path in paths.iter().take(VERIFY_EXAMPLES) {
+                println!("   {:<10} {}", label, path);
+            }
+            if paths.len() > VERIFY_EXAMPLES {
+                println!("   {:<10} … and {} more", label, paths.len() - VERIFY_EXAMPLES);
+            }
+        }
+    }
     
-    println!("✅ Bulk extraction completed! Files extracted to: {:?}", output);
+    if failed > 0 {
+        anyhow::bail!("{} of {} archives failed verification", failed, zip_files.len());
+    }
+    println!("✅ All {} archives match their output in {:?}", zip_files.len(), output);
     Ok(())
 }
 
+/// `retry`: parse the earlier run's command line again with `overrides`
+/// after it, so they win, restricted to `paths` and reporting to `report`
+fn retry_command(argv: &[String], overrides: &[String], paths: &[PathBuf], report: &Path) -> Result<(Commands, Disposal)> {
+    if argv.iter().any(|arg| arg.starts_with("--from-url") || arg == "--from-stdin") {
+        anyhow::bail!("A run that downloaded its archive can't be retried from its report");
+    }
+    let mut args = vec!["bulk_unzip".to_string()];
+    args.extend(argv.iter().filter(|arg| *arg != "--no-report").cloned());
+    args.extend(overrides.iter().cloned());
+    for path in paths {
+        args.push("--only".to_string());
+        args.push(path.to_string_lossy().into_owned());
+    }
+    args.push("--report".to_string());
+    args.push(report.to_string_lossy().into_owned());
+
+    let command = Args::command()
+        .mut_subcommand("unzip", |unzip| unzip.args_override_self(true))
+        .mut_subcommand("strip", |strip| strip.args_override_self(true));
+    let args = presets::expand(&command, args.into_iter().map(Into::into).collect())?;
+    let matches = command.try_get_matches_from(&args).context("Can't run the report's command again with these options")?;
+    let args = Args::from_arg_matches(&matches)?;
+    match args.command {
+        Some(command @ (Commands::Unzip { .. } | Commands::Strip { .. })) => Ok((command, args.disposal)),
+        _ => anyhow::bail!("Only unzip and strip runs can be retried"),
+    }
+}
+
+async fn retry(report_path: PathBuf, statuses: Vec<String>, overrides: Vec<String>) -> Result<()> {
+    let mut previous = RunReport::load(&report_path)?;
+    if previous.argv.is_empty() {
+        anyhow::bail!("{:?} doesn't record the command line it ran; only reports of command-line runs can be retried", report_path);
+    }
+    let paths = previous.paths_with_status(&statuses);
+    if paths.is_empty() {
+        println!("✅ Nothing to retry: no result in {:?} is {}", report_path, statuses.join(" or "));
+        return Ok(());
+    }
+    println!("🔁 Retrying {} of {} results ({}) from {:?}", paths.len(), previous.results.len(), statuses.join(", "), report_path);
+
+    let mut retry_report = report_path.as_os_str().to_owned();
+    retry_report.push(".retry");
+    let retry_report = PathBuf::from(retry_report);
+    let (command, disposal) = retry_command(&previous.argv, &overrides, &paths, &retry_report)?;
+    let outcome = Box::pin(run(command, disposal)).await;
+
+    let retried = RunReport::load(&retry_report)?;
+    fs::remove_file(&retry_report)
+        .with_context(|| format!("Failed to remove {:?}", retry_report))?;
+    // Read back into `retried` by the load, when the run streamed its results
+    let retry_results = results_file_for(&retry_report);
+    if retry_results.exists() {
+        fs::remove_file(&retry_results)
+            .with_context(|| format!("Failed to remove {:?}", retry_results))?;
+    }
+    previous.merge_retry(retried, &statuses);
+    previous.write(&report_path)?;
+    println!("📝 Merged the new outcomes into {:?}", report_path);
+    outcome
+}
+
+/// `--version`, and with `--verbose` the capability matrix the GUI gets from `get_capabilities`
+fn version_lines(capabilities: &Capabilities, verbose: bool) -> Vec<String> {
+    let mut lines = vec![format!("bulk_unzip {}", capabilities.version)];
+    if !verbose {
+        return lines;
+    }
+    lines.push(format!("Default workers: {}", capabilities.default_workers));
+    for (name, value) in overrides() {
+        lines.push(format!("Build default: {}={}", name, value));
+    }
+    for (feature, supported) in capabilities.features() {
+        lines.push(format!("  {} {}", if supported { "✅" } else { "❌" }, feature));
+    }
+    lines
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let argv = presets::expand(&Args::command(), env::args_os().collect())?;
+    let args = Args::parse_from(&argv);
     
-    match args.command {
-        Commands::Unzip { directory, output, workers, skip_existing } => {
-            bulk_unzip(directory, output, workers, skip_existing).await
-        }
-        Commands::Strip { directory, output, workers, skip_clean, keep_fields, remove_all, dry_run } => {
-            let metadata_args = MetadataArgs {
-                directory,
-                output,
-                workers,
-                skip_clean,
-                keep_fields,
-                remove_all,
-                dry_run,
+    if args.no_color {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    }
+    
+    // Like clap's own flag, --version wins over any subcommand
+    if args.version {
+        for line in version_lines(&Capabilities::detect(), args.verbose) {
+            println!("{}", line);
+        }
+        return Ok(());
+    }
+    let Some(command) = args.command else {
+        Args::command().print_help()?;
+        return Ok(());
+    };
+    if let Some(name) = presets::save_requested(&Args::command(), &argv)? {
+        println!("💾 Saved these options as preset {:?}", name);
+    }
+    run(command, args.disposal).await
+}
+
+async fn run(command: Commands, disposal: Disposal) -> Result<()> {
+    match command {
+        Commands::Unzip { unzip, report, preset: _ } => {
+            let unzip_args = unzip.resolve(disposal)?;
+            // Nothing lands in the output directory when streaming, so neither does the report
+            let report_path = report.path(&unzip_args.output, unzip_args.dry_run || unzip_args.to_stdout);
+            let to_stdout = unzip_args.to_stdout;
+            let mut run_report = RunReport::new("unzip", &unzip_args);
+            run_report.set_command_line(command_line());
+            stream_results(&mut run_report, &report, report_path.as_deref())?;
+            let status_server = match unzip_args.serve_status {
+                Some(addr) => {
+                    let server = StatusServer::start(addr, unzip_args.allow_remote).await?;
+                    println!("📡 Serving status on http://{0}/status and http://{0}/events", server.addr());
+                    Some(server)
+                }
+                None => None,
+            };
+            // The dashboard reads the board --serve-status serves, or one of its own
+            let status = match &status_server {
+                Some(server) => Some(server.board().clone()),
+                None => unzip_args.tui.then(|| Arc::new(StatusBoard::default())),
+            };
+            let outcome = AssertUnwindSafe(bulk_unzip(unzip_args, status, &mut run_report)).catch_unwind().await;
+            if let Some(server) = status_server {
+                server.shutdown().await;
+            }
+            finish_report(run_report, report_path, to_stdout, outcome)
+        }
+        Commands::Strip { mut metadata, report, preset: _ } => {
+            metadata.disposal = disposal;
+            metadata.check_fields()?;
+            let report_path = report.path(metadata.output.as_deref().unwrap_or(&metadata.directories[0]), metadata.dry_run);
+            let mut run_report = RunReport::new("strip", &metadata);
+            run_report.set_command_line(command_line());
+            stream_results(&mut run_report, &report, report_path.as_deref())?;
+            let outcome = AssertUnwindSafe(bulk_strip_metadata_with_report(metadata, &mut run_report, |_, _, _| {}))
+                .catch_unwind()
+                .await
+                .map(|result| result.map(|_| ()));
+            finish_report(run_report, report_path, false, outcome)
+        }
+        Commands::Scan { directory, kind, out, duplicates, report, hash } => {
+            scan(directory, kind, out, duplicates, report, hash).await
+        }
+        Commands::Stats { directory, format } => {
+            stats(directory, format).await
+        }
+        Commands::VerifyOutput { directory, output, crc, check_extra, name_template, label_by_type, split_output_by_date, date_source, flatten_depth, normalize_extensions, extension_map, max_entry_depth, flatten_overdeep } => {
+            let mut options = VerifyOptions::default();
+            options.crc = crc;
+            options.check_extra = check_extra;
+            options.flatten_depth = flatten_depth;
+            options.normalize_extensions = normalize_extensions.then(|| extension_map.unwrap_or_default());
+            options.depth_limit = max_entry_depth.map(|max| DepthLimit::new(max, flatten_overdeep)).transpose().map_err(anyhow::Error::msg)?;
+            verify_output(directory, output, name_template, label_by_type, split_output_by_date.then_some(date_source), options).await
+        }
+        Commands::List { archive, sort } => list(archive, sort),
+        Commands::Retry { report, statuses, overrides } => retry(report, statuses, overrides).await,
+        Commands::Presets { action } => manage_presets(action),
+        Commands::Schema { kind } => {
+            println!("{}", serde_json::to_string_pretty(&schema(kind))?);
+            Ok(())
+        }
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::{ContextKind, ContextValue};
+    use clap::Arg;
+    use std::ffi::OsString;
+    use std::sync::OnceLock;
+
+    /// Point the presets at a config file of this test run's own
+    fn test_config() -> &'static Path {
+        static CONFIG: OnceLock<(tempfile::TempDir, PathBuf)> = OnceLock::new();
+        let (_, path) = CONFIG.get_or_init(|| {
+            let dir = tempfile::tempdir().unwrap();
+            let path = dir.path().join("config.toml");
+            env::set_var(presets::CONFIG_ENV, &path);
+            (dir, path)
+        });
+        path
+    }
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        std::iter::once("bulk_unzip").chain(args.iter().copied()).map(OsString::from).collect()
+    }
+
+    /// The values of every option of `subcommand` as `argv` leaves them
+    fn values(argv: &[OsString], subcommand: &str) -> BTreeMap<String, (Option<Vec<OsString>>, bool)> {
+        let matches = Args::command().try_get_matches_from(argv).unwrap();
+        let matches = matches.subcommand_matches(subcommand).unwrap();
+        Args::command()
+            .find_subcommand(subcommand)
+            .unwrap()
+            .get_arguments()
+            .map(|arg| arg.get_id().as_str())
+            .filter(|id| !["preset", "save_preset"].contains(id))
+            .map(|id| {
+                let raw = matches.get_raw(id).map(|values| values.map(OsString::from).collect());
+                (id.to_string(), (raw, matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine)))
+            })
+            .collect()
+    }
+
+    /// Command-line arguments that give `arg` a value clap accepts, with
+    /// any options it requires
+    fn giving(subcommand: &str, arg: &Arg) -> Option<Vec<String>> {
+        let long = format!("--{}", arg.get_long()?);
+        let candidates: Vec<Vec<String>> = if !arg.get_action().takes_values() {
+            vec![vec![long]]
+        } else {
+            let defaults: Vec<_> = arg.get_default_values().iter().map(|value| value.to_string_lossy().into_owned()).collect();
+            let possible: Vec<String> = arg
+                .get_possible_values()
+                .iter()
+                .map(|value| value.get_name().to_string())
+                .filter(|value| !defaults.contains(value))
+                .collect();
+            let values = if possible.is_empty() {
+                ["3", "2K", "30s", "*.tmp", "title", "mp3=mp3", "photos/(.*)", "0:0"].map(String::from).to_vec()
+            } else {
+                possible
             };
-            bulk_strip_metadata(metadata_args).await
+            values.into_iter().map(|value| vec![format!("{}={}", long, value)]).collect()
+        };
+        candidates.into_iter().find_map(|mut tokens| {
+            let command = Args::command();
+            for _ in 0..4 {
+                let args: Vec<&str> = std::iter::once(subcommand).chain(tokens.iter().map(String::as_str)).collect();
+                let error = match command.clone().try_get_matches_from(argv(&args)) {
+                    Ok(_) => return Some(tokens),
+                    Err(error) if error.kind() == clap::error::ErrorKind::MissingRequiredArgument => error,
+                    Err(_) => return None,
+                };
+                // e.g. "--update, --state-file [<PATH>]"
+                let Some(ContextValue::Strings(missing)) = error.get(ContextKind::InvalidArg) else {
+                    return None;
+                };
+                let options = command.find_subcommand(subcommand).unwrap();
+                for missing in missing {
+                    let long = missing.split_whitespace().next()?.trim_start_matches("--");
+                    let required = options.get_arguments().find(|arg| arg.get_long() == Some(long))?;
+                    tokens.splice(0..0, giving(subcommand, required)?);
+                }
+            }
+            None
+        })
+    }
+
+    #[test]
+    fn no_two_options_share_a_flag() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn every_option_survives_a_preset_round_trip() {
+        test_config();
+        let mut untested = Vec::new();
+        for subcommand in ["unzip", "strip"] {
+            let command = Args::command();
+            let options: Vec<Arg> = command.find_subcommand(subcommand).unwrap().get_arguments().cloned().collect();
+            for arg in options.iter().filter(|arg| !arg.is_positional()) {
+                let id = arg.get_id().as_str();
+                if ["preset", "save_preset", "help", "directory", "directories", "bearer_token", "headers", "passwords", "only"].contains(&id) {
+                    continue;
+                }
+                let Some(tokens) = giving(subcommand, arg) else {
+                    untested.push(format!("{} {}", subcommand, id));
+                    continue;
+                };
+                let name = format!("{}-{}", subcommand, id);
+                let mut given = vec![subcommand];
+                given.extend(tokens.iter().map(String::as_str));
+                let given = argv(&given);
+                let mut saving = given.clone();
+                saving.push(format!("--save-preset={}", name).into());
+                assert_eq!(presets::save_requested(&Args::command(), &saving).unwrap(), Some(name.clone()));
+
+                let replayed = presets::expand(&Args::command(), argv(&[subcommand, "--preset", &name])).unwrap();
+                assert_eq!(values(&replayed, subcommand), values(&given, subcommand), "{:?} came back as {:?}", tokens, replayed);
+            }
         }
+        assert!(untested.is_empty(), "no value found to try for {:?}", untested);
+    }
+
+    #[test]
+    fn explicit_options_win_over_a_preset_and_a_preset_over_defaults() {
+        test_config();
+        let saving = argv(&["unzip", "-d", "in", "--workers=8", "--skip-existing", "--background", "--output=delivered", "--save-preset=intake"]);
+        presets::save_requested(&Args::command(), &saving).unwrap();
+
+        let replayed = presets::expand(&Args::command(), argv(&["unzip", "--preset", "intake", "--workers=2", "--update"])).unwrap();
+        let Some(Commands::Unzip { unzip, .. }) = Args::try_parse_from(&replayed).unwrap().command else {
+            panic!("{:?} isn't an unzip run", replayed);
+        };
+        assert_eq!(unzip.workers, Some(2));
+        // --skip-existing can't go with the --update given
+        assert_eq!((unzip.update, unzip.skip_existing, unzip.background), (true, false, true));
+        assert_eq!(unzip.output, Path::new("delivered"));
+        // Nor is the input directory saved
+        assert_eq!(unzip.directories, [Path::new(".")]);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn presets_are_only_replayed_by_their_own_command() {
+        test_config();
+        presets::save_requested(&Args::command(), &argv(&["strip", "--remove-all", "--save-preset=podcast"])).unwrap();
+
+        let error = presets::expand(&Args::command(), argv(&["unzip", "--preset", "podcast"])).unwrap_err();
+        assert_eq!(error.to_string(), "Preset \"podcast\" is for strip, not unzip");
+        let error = presets::expand(&Args::command(), argv(&["strip", "--preset", "podcats"])).unwrap_err();
+        assert!(error.to_string().starts_with("No preset named \"podcats\"; saved presets: "), "{}", error);
+    }
+
+    #[test]
+    fn version_verbose_prints_the_capability_matrix() {
+        let capabilities = Capabilities { sevenz: true, rar: false, watch: true, notifications: false, symlinks: true, trash: false, reflink: true, ..Capabilities::detect() };
+        assert_eq!(version_lines(&capabilities, false), [format!("bulk_unzip {}", env!("CARGO_PKG_VERSION"))]);
+
+        let lines = version_lines(&capabilities, true);
+        assert_eq!(lines[1], format!("Default workers: {}", capabilities.default_workers));
+        assert_eq!(lines.len(), 2 + overrides().len() + 8);
+        assert_eq!(lines[lines.len() - 8..], [
+            "  ✅ 7z archives",
+            "  ❌ rar archives",
+            "  ✅ zstd entries",
+            "  ✅ watch mode",
+            "  ❌ notifications",
+            "  ✅ symlinks",
+            "  ❌ trash",
+            "  ✅ reflink copies",
+        ]);
+    }
+}
@@ -0,0 +1,139 @@
+use memmap2::Mmap;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::SystemTime;
+
+/// Length and modification time, which change when a file is written to
+fn file_version(file: &fs::File) -> Option<(u64, SystemTime)> {
+    let metadata = file.metadata().ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+/// Memory-map `file`, unless it is being written to: its length and
+/// modification time must be the same after mapping as before, and the map
+/// as long as the file
+pub fn map_unchanged(file: &fs::File) -> Option<Mmap> {
+    let before = file_version(file)?;
+    // SAFETY: the map is only read through a `GuardedMap`, which reads the
+    // file instead once it no longer covers the bytes asked for; the checks
+    // here turn away a file that was changing as it was mapped.
+    let map = unsafe { Mmap::map(file) }.ok()?;
+    let after = file_version(file)?;
+    (before == after && map.len() as u64 == after.0).then_some(map)
+}
+
+/// Reads an archive through its memory map, first checking that the file
+/// still holds the bytes about to be read. Once it doesn't, e.g. it was
+/// truncated, every further read goes through the file, and comes up short
+/// or fails as a buffered read would, failing the archive rather than
+/// touching pages past the end of the file.
+///
+/// A file truncated between the check and the copy out of the map can
+/// still crash the process (SIGBUS on Unix); that window is one read wide,
+/// which is why `--mmap` is opt-in and archives must not be modified while
+/// they are extracted.
+pub struct GuardedMap<'a> {
+    map: &'a [u8],
+    file: &'a fs::File,
+    position: u64,
+    /// Set once the file was found shorter than the bytes a read needed
+    file_reads: bool,
+}
+
+impl<'a> GuardedMap<'a> {
+    pub fn new(map: &'a Mmap, file: &'a fs::File) -> Self {
+        GuardedMap { map, file, position: 0, file_reads: false }
+    }
+
+    /// Whether reads went to the file after it was found shorter than the map
+    pub fn fell_back(&self) -> bool {
+        self.file_reads
+    }
+}
+
+impl Read for GuardedMap<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = self.position.min(self.map.len() as u64) as usize;
+        let end = start + buf.len().min(self.map.len() - start);
+        if !self.file_reads && self.file.metadata()?.len() < end as u64 {
+            self.file_reads = true;
+        }
+        let read = if self.file_reads {
+            let mut file = self.file;
+            file.seek(SeekFrom::Start(self.position))?;
+            file.read(buf)?
+        } else {
+            buf[..end - start].copy_from_slice(&self.map[start..end]);
+            end - start
+        };
+        self.position += read as u64;
+        Ok(read)
+    }
+}
+
+impl Seek for GuardedMap<'_> {
+    fn seek(&mut self, to: SeekFrom) -> io::Result<u64> {
+        let position = match to {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => (self.map.len() as u64).checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "seek before the start of the archive"))?;
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unchanged_files_are_mapped_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.zip");
+        fs::write(&path, b"PK archive bytes").unwrap();
+        let map = map_unchanged(&fs::File::open(&path).unwrap()).unwrap();
+        assert_eq!(&map[..], b"PK archive bytes");
+    }
+
+    #[test]
+    fn reads_seek_through_the_map() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.zip");
+        fs::write(&path, b"0123456789").unwrap();
+        let file = fs::File::open(&path).unwrap();
+        let map = map_unchanged(&file).unwrap();
+        let mut reader = GuardedMap::new(&map, &file);
+
+        let mut buf = [0; 4];
+        reader.seek(SeekFrom::End(-3)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"789");
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        reader.seek(SeekFrom::Start(2)).unwrap();
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"2345");
+        assert!(reader.seek(SeekFrom::Current(-7)).is_err());
+        assert!(!reader.fell_back());
+    }
+
+    #[test]
+    fn a_file_truncated_while_mapped_is_read_instead() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.zip");
+        fs::write(&path, vec![7; 64 * 1024]).unwrap();
+        let file = fs::OpenOptions::new().read(true).write(true).open(&path).unwrap();
+        let map = map_unchanged(&file).unwrap();
+        let mut reader = GuardedMap::new(&map, &file);
+        file.set_len(10).unwrap();
+
+        // Pages past the new end would SIGBUS if read from the map
+        let mut buf = [0; 4096];
+        reader.seek(SeekFrom::Start(32 * 1024)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 0);
+        assert!(reader.fell_back());
+        reader.seek(SeekFrom::Start(4)).unwrap();
+        assert_eq!(reader.read(&mut buf).unwrap(), 6);
+        assert_eq!(&buf[..6], &[7; 6]);
+    }
+}
@@ -0,0 +1,121 @@
+/// Copy buffer each worker uses when no memory limit applies
+pub const DEFAULT_BUFFER_SIZE: usize = 256 * 1024;
+
+/// Smallest copy buffer worth using; below this, workers are dropped instead
+const MIN_BUFFER_SIZE: usize = 16 * 1024;
+
+/// Decompressor state and read-ahead each worker holds besides its copy buffer
+const WORKER_OVERHEAD: u64 = 256 * 1024;
+
+/// Worker count and per-worker buffer size that fit a memory limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryPlan {
+    pub workers: usize,
+    pub buffer_size: usize,
+}
+
+/// Fit `workers` workers, each copying through `buffer_size` bytes, into
+/// `limit` bytes. Buffers shrink first, down to a floor, then workers are
+/// dropped. One worker always runs, even when the limit is below its needs.
+pub fn plan_memory(limit: Option<u64>, workers: usize, buffer_size: usize) -> MemoryPlan {
+    let workers = workers.max(1);
+    let per_worker = |buffer: usize| WORKER_OVERHEAD + buffer as u64;
+    let requested = MemoryPlan { workers, buffer_size };
+    let Some(limit) = limit else {
+        return requested;
+    };
+    if workers as u64 * per_worker(buffer_size) <= limit {
+        return requested;
+    }
+
+    let share = limit / workers as u64;
+    if share >= per_worker(MIN_BUFFER_SIZE) {
+        let buffer = ((share - WORKER_OVERHEAD) as usize / 4096 * 4096).max(MIN_BUFFER_SIZE);
+        return MemoryPlan { workers, buffer_size: buffer.min(buffer_size) };
+    }
+
+    let buffer_size = MIN_BUFFER_SIZE.min(buffer_size);
+    let workers = ((limit / per_worker(buffer_size)) as usize).clamp(1, workers);
+    MemoryPlan { workers, buffer_size }
+}
+
+/// Parse a byte count such as `256M`, `1.5G` or `65536`; suffixes are binary
+/// multiples and may be followed by `B` or `iB`
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let invalid = || format!("Expected a size such as 256M or 2G, got {:?}", size);
+    let trimmed = size.trim();
+    let lower = trimmed.to_lowercase();
+    let unit_start = lower.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(lower.len());
+    let (number, unit) = lower.split_at(unit_start);
+    let multiplier: u64 = match unit.trim_end_matches("ib").trim_end_matches('b') {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        _ => return Err(invalid()),
+    };
+    let value: f64 = number.trim().parse().map_err(|_| invalid())?;
+    if !value.is_finite() || value < 0.0 {
+        return Err(invalid());
+    }
+    Ok((value * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KB: u64 = 1024;
+    const MB: u64 = 1024 * KB;
+
+    #[test]
+    fn no_limit_or_room_to_spare_keeps_the_settings() {
+        assert_eq!(plan_memory(None, 16, DEFAULT_BUFFER_SIZE), MemoryPlan { workers: 16, buffer_size: DEFAULT_BUFFER_SIZE });
+        assert_eq!(plan_memory(None, 0, DEFAULT_BUFFER_SIZE).workers, 1);
+        // 16 workers of 256 KiB buffers and 256 KiB overhead take 8 MiB
+        assert_eq!(plan_memory(Some(8 * MB), 16, DEFAULT_BUFFER_SIZE), MemoryPlan { workers: 16, buffer_size: DEFAULT_BUFFER_SIZE });
+    }
+
+    #[test]
+    fn buffers_shrink_before_workers_are_dropped() {
+        // 384 KiB each leaves 128 KiB for the buffer
+        assert_eq!(plan_memory(Some(6 * MB), 16, DEFAULT_BUFFER_SIZE), MemoryPlan { workers: 16, buffer_size: 128 * KB as usize });
+        // Rounded down to whole pages
+        assert_eq!(plan_memory(Some(6 * MB + 1000), 16, DEFAULT_BUFFER_SIZE).buffer_size, 128 * KB as usize);
+    }
+
+    #[test]
+    fn workers_are_dropped_once_buffers_hit_the_floor() {
+        assert_eq!(plan_memory(Some(MB), 16, DEFAULT_BUFFER_SIZE), MemoryPlan { workers: 3, buffer_size: MIN_BUFFER_SIZE });
+        // One worker runs however low the limit
+        assert_eq!(plan_memory(Some(1000), 16, DEFAULT_BUFFER_SIZE), MemoryPlan { workers: 1, buffer_size: MIN_BUFFER_SIZE });
+        // Buffers never grow past what was asked for
+        assert_eq!(plan_memory(Some(500 * KB), 4, 4096), MemoryPlan { workers: 1, buffer_size: 4096 });
+    }
+
+    #[test]
+    fn plans_fit_the_limit_unless_down_to_one_worker() {
+        for limit in [300 * KB, MB, 3 * MB, 7 * MB, 64 * MB] {
+            for workers in [1, 2, 8, 32] {
+                let plan = plan_memory(Some(limit), workers, DEFAULT_BUFFER_SIZE);
+                let used = plan.workers as u64 * (WORKER_OVERHEAD + plan.buffer_size as u64);
+                assert!(used <= limit || plan.workers == 1, "{:?} uses {} of {}", plan, used, limit);
+                assert!(plan.workers <= workers && plan.buffer_size <= DEFAULT_BUFFER_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("65536"), Ok(65536));
+        assert_eq!(parse_size("256M"), Ok(256 * MB));
+        assert_eq!(parse_size("1.5G"), Ok(1536 * MB));
+        assert_eq!(parse_size(" 2 GiB "), Ok(2048 * MB));
+        assert_eq!(parse_size("10kb"), Ok(10 * KB));
+        assert_eq!(parse_size("1T"), Ok(1 << 40));
+        for bad in ["", "M", "abc", "-1M", "5X", "1e999G"] {
+            assert!(parse_size(bad).is_err(), "{:?}", bad);
+        }
+    }
+}
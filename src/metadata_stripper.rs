@@ -1,196 +1,1577 @@
-use anyhow::{Context, Result};
-use clap::Parser;
+use anyhow::{bail, Context, Result};
+use console::{style, Alignment};
 use futures::future::join_all;
+use glob::{MatchOptions, Pattern};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use id3::frame::{Content, Frame};
 use id3::{Tag, TagLike};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use walkdir::WalkDir;
 
-#[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+use crate::archive_strip::bulk_strip_archives;
+use crate::art_safety::{export_cover, has_sidecar_art, keep_art, removes_art, ArtSafety, ArtSafetyHit};
+use crate::chunk_tags::{read_info, rewrite_chunks, ChunkEdit, InfoItem};
+use crate::defaults::DEFAULTS;
+use crate::disposal::{Disposal, Disposer};
+use crate::duplicates::hash_audio;
+use crate::error_summary::{error_kind, ErrorCollector};
+use crate::field_names::{check_field_lists, parse_field_list, FieldList, InvalidFieldName};
+use crate::filter::{PathFilter, TagFilter};
+use crate::format::{format_size, format_size_signed, print_table, truncate_middle};
+use crate::free_space::{is_out_of_space, preflight};
+use crate::genre::GenreNormalizer;
+use crate::input_guard::InputSet;
+use crate::io_profile::{settings_for_run, IoProfile, Workload};
+use crate::memory_budget::parse_size;
+use crate::ordering::SortOrder;
+use crate::output_paths::{CollisionPolicy, OutputCollision, OutputPlan};
+use crate::preserve::{make_writable, restore_attributes, Preserve, PreserveAttr};
+use crate::priority::run_in_background;
+use crate::reflink;
+use crate::result_log::ResultLog;
+use crate::result_stream::ResultStream;
+use crate::run_report::RunReport;
+use crate::sampling::{Sample, SampleMode};
+use crate::scan_progress::{ScanMonitor, STAGING_PREFIX};
+use crate::scan_roots::{RootCounts, RootOutcome, ScanRoots, SeenFiles};
+use crate::sniff::{sniff_file, AudioContainer, AudioFormat};
+use crate::tag_diff::{frame_label, same_frames, TagDiff};
+use crate::tag_upgrade::{upgrade_tag, version_name, TagUpgrade};
+use crate::write_check::{Restoration, WriteCheck};
+
+/// Options of a strip run: the `strip` subcommand's arguments, also built by
+/// [`MetadataStripper`](crate::MetadataStripper)
+#[derive(clap::Args, Debug, Clone, Serialize)]
+#[non_exhaustive]
 pub struct MetadataArgs {
-    /// Directory containing MP3 files to process
-    #[arg(short, long, default_value = ".")]
-    pub directory: PathBuf,
+    /// Directory containing MP3 files (or the other --formats) to process;
+    /// give it more than once to process several
+    #[arg(short = 'd', long = "directory", value_name = "DIRECTORY", default_value = ".")]
+    pub directories: Vec<PathBuf>,
 
     /// Output directory for processed files (if not specified, files are modified in place)
     #[arg(short, long)]
     pub output: Option<PathBuf>,
 
-    /// Number of concurrent workers
-    #[arg(short, long, default_value = "4")]
-    pub workers: usize,
+    /// What files with the same name, from different directories, do when
+    /// copied to --output: rename the later ones, skip them, or overwrite,
+    /// keeping only the last
+    #[arg(long, value_enum, default_value_t = CollisionPolicy::Rename, requires = "output")]
+    pub on_collision: CollisionPolicy,
 
-    /// Skip files that already have no metadata
-    #[arg(short, long)]
+    /// Number of concurrent workers [default: 4 unless the build sets BULK_UNZIP_DEFAULT_WORKERS, or the --io-profile's count]
+    #[arg(short, long, visible_alias = "threads")]
+    pub workers: Option<usize>,
+
+    /// Storage the MP3s are on, which picks the worker count: ssd, hdd,
+    /// network, or auto to sample random-read latency
+    #[arg(long, value_enum, default_value = DEFAULTS.io_profile)]
+    pub io_profile: Option<IoProfile>,
+
+    /// Be a good citizen on a busy machine: lower the process's CPU and I/O
+    /// priority, and halve the worker count unless --workers is given
+    #[arg(long)]
+    pub background: bool,
+
+    /// Skip files the other options would leave unchanged, e.g. already clean
+    /// or holding only the --keep-fields
+    #[arg(short, long, num_args = 0..=1, default_value_t = DEFAULTS.skip_clean, default_missing_value = "true", action = clap::ArgAction::Set)]
     pub skip_clean: bool,
 
     /// Keep only specific metadata fields (comma-separated: title,artist,album,year)
     #[arg(short, long)]
     pub keep_fields: Option<String>,
 
+    /// Remove specific fields (comma-separated: title,artist,...,chapters,lyrics,links,copyright,encoder or frame IDs)
+    #[arg(long)]
+    pub remove_fields: Option<String>,
+
+    /// Rewrite kept genres to canonical names: `(17)` becomes `Rock`, `hip hop` becomes `Hip-Hop`
+    #[arg(long)]
+    pub normalize_genre: bool,
+
+    /// TOML file of `"From" = "To"` genre corrections for --normalize-genre
+    #[arg(long, requires = "normalize_genre")]
+    pub genre_map: Option<PathBuf>,
+
+    /// Rewrite ID3v2.2 and v2.3 tags as v2.4, keeping every frame; TYER,
+    /// TDAT and TIME fold into TDRC. Files already at v2.4 are left alone.
+    #[arg(long, conflicts_with_all = ["remove_all", "keep_fields", "remove_fields", "strip_private"])]
+    pub upgrade_tags: bool,
+
     /// Remove all metadata completely
     #[arg(short, long)]
     pub remove_all: bool,
 
+    /// Fields --remove-all keeps, named as for --remove-fields (e.g. copyright,encoder);
+    /// ID3v1 and APE tags still go
+    #[arg(long, requires = "remove_all")]
+    pub protect: Option<String>,
+
+    /// File types to process, comma-separated: mp3, wav (ID3 chunk and LIST INFO)
+    /// and aiff (ID3 chunk)
+    #[arg(long, value_enum, value_delimiter = ',', default_value = "mp3", conflicts_with = "in_archive")]
+    pub formats: Vec<AudioFormat>,
+
+    /// Process just these files instead of scanning --directory; set by `retry`
+    #[arg(long, hide = true, conflicts_with = "in_archive")]
+    pub only: Vec<PathBuf>,
+
     /// Show what would be done without actually doing it
-    #[arg(short, long)]
+    #[arg(long)]
     pub dry_run: bool,
+
+    /// Descend into subdirectories
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub recursive: bool,
+
+    /// Maximum directory depth to scan (1 = top level only)
+    #[arg(long)]
+    pub max_depth: Option<usize>,
+
+    /// Skip hidden files and directories (dotfiles)
+    #[arg(long)]
+    pub skip_hidden: bool,
+
+    /// Also scan macOS AppleDouble `._*` files and empty files, skipped by default
+    #[arg(long)]
+    pub include_hidden: bool,
+
+    /// Never write tags to files smaller than this, e.g. 4K; nothing that small is real audio
+    #[arg(long, value_parser = parse_size, default_value_t = DEFAULT_MIN_SIZE)]
+    pub min_size: u64,
+
+    /// Padding after rewritten ID3 tags: none, minimal (256 bytes), default
+    /// (1 KiB) or a byte count; padding lets taggers grow a tag in place
+    #[arg(long, value_parser = parse_padding, default_value = "none")]
+    pub padding: usize,
+
+    /// Don't read each rewritten MP3 back to check its tag and the position
+    /// of its first audio frame; faster, but a bad write goes unnoticed
+    #[arg(long)]
+    pub no_verify_write: bool,
+
+    /// With --output, refuse to start unless this much stays free on its volume
+    /// once every file is copied, e.g. 1G; without it, a shortfall only warns
+    #[arg(long, value_parser = parse_size, requires = "output")]
+    pub min_free_space: Option<u64>,
+
+    /// With --output, remove each original once its copy is written and
+    /// checked; files that fail or are skipped keep theirs. The global
+    /// --disposal trash moves them to the trash instead.
+    #[arg(long, requires = "output", conflicts_with = "in_archive")]
+    pub delete_source: bool,
+
+    /// How originals are removed; set from the global --disposal
+    #[arg(skip)]
+    pub disposal: Disposal,
+
+    /// When embedded art is about to go and no cover image (cover.jpg,
+    /// folder.png, ...) sits beside the file: ignore it, warn, export the
+    /// front cover as cover.jpg first, or skip, keeping the art in those files
+    #[arg(long, value_enum, default_value_t = ArtSafety::Ignore, conflicts_with = "in_archive")]
+    pub art_safety: ArtSafety,
+
+    /// Only process files whose path (relative to the directory) matches this glob
+    #[arg(long)]
+    pub include: Vec<String>,
+
+    /// Skip files whose path (relative to the directory) matches this glob
+    #[arg(long)]
+    pub exclude: Vec<String>,
+
+    /// Order to process and report files in: byte-wise by path, or natural
+    /// (numbers by value, so track2 precedes track10)
+    #[arg(long, value_enum, default_value = "path")]
+    pub sort: SortOrder,
+
+    /// Process only N of the MP3s found, after sorting and filtering
+    #[arg(long, conflicts_with = "in_archive")]
+    pub limit: Option<usize>,
+
+    /// Which MP3s --limit keeps: the first N, or a random N (still processed in sort order)
+    #[arg(long, value_enum, default_value = "first", requires = "limit")]
+    pub sample: SampleMode,
+
+    /// Seed for --sample random, to pick the same MP3s again
+    #[arg(long, requires = "limit")]
+    pub seed: Option<u64>,
+
+    /// Only process files whose tags match an expression (e.g. `comment contains "ripped by"`)
+    #[arg(long = "where")]
+    pub where_expr: Option<String>,
+
+    /// Remove PRIV, UFID and GEOB frames plus vendor TXXX frames (Serato, Traktor, iTunes store)
+    #[arg(long)]
+    pub strip_private: bool,
+
+    /// Additional TXXX description glob to remove with --strip-private
+    #[arg(long, requires = "strip_private")]
+    pub strip_txxx: Vec<String>,
+
+    /// Modify files even when their content is another format (M4A, WAV, ...) renamed to .mp3
+    #[arg(long)]
+    pub force: bool,
+
+    /// Attributes of the original file to keep on the stripped one, comma-separated:
+    /// times, perms (including read-only), owner (needs root) or all
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub preserve: Vec<PreserveAttr>,
+
+    /// Strip MP3s inside zip archives and write `<name>-clean.zip` without extracting
+    #[arg(long)]
+    pub in_archive: bool,
+
+    /// With --in-archive, replace the original archive instead of writing `-clean.zip`
+    #[arg(long, requires = "in_archive")]
+    pub in_place_archive: bool,
+
+    /// Directory for MP3s staged out of archives and half-written archives;
+    /// defaults to `.bulk-unzip-tmp` inside the scanned directory
+    #[arg(long, requires = "in_archive")]
+    pub temp_dir: Option<PathBuf>,
+
+    /// List every discovered file with its size and metadata status
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    /// Write every error in full to this file; the summary groups repeated errors
+    #[arg(long)]
+    pub error_log: Option<PathBuf>,
+}
+
+impl MetadataArgs {
+    /// Check the field lists, so a misspelled field fails the run before it
+    /// writes anything, even its report
+    pub fn check_fields(&self) -> Result<(), InvalidFieldName> {
+        check_field_lists(self.keep_fields.as_deref(), self.remove_fields.as_deref(), self.protect.as_deref())
+    }
+}
+
+impl Default for MetadataArgs {
+    fn default() -> Self {
+        MetadataArgs {
+            directories: vec![PathBuf::from(".")],
+            output: None,
+            on_collision: CollisionPolicy::Rename,
+            workers: None,
+            io_profile: None,
+            background: false,
+            skip_clean: DEFAULTS.skip_clean,
+            keep_fields: None,
+            remove_fields: None,
+            normalize_genre: false,
+            genre_map: None,
+            upgrade_tags: false,
+            remove_all: false,
+            protect: None,
+            formats: vec![AudioFormat::Mp3],
+            only: Vec::new(),
+            dry_run: false,
+            recursive: true,
+            max_depth: None,
+            skip_hidden: false,
+            include_hidden: false,
+            min_size: DEFAULT_MIN_SIZE,
+            padding: 0,
+            no_verify_write: false,
+            min_free_space: None,
+            delete_source: false,
+            disposal: Disposal::Delete,
+            art_safety: ArtSafety::Ignore,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            sort: SortOrder::Path,
+            limit: None,
+            sample: SampleMode::First,
+            seed: None,
+            where_expr: None,
+            strip_private: false,
+            strip_txxx: Vec::new(),
+            force: false,
+            preserve: Vec::new(),
+            in_archive: false,
+            in_place_archive: false,
+            temp_dir: None,
+            verbose: false,
+            error_log: None,
+        }
+    }
+}
+
+/// Tag values captured during scanning, used to filter files before processing
+#[derive(Clone, Debug, Default)]
+pub struct TagInfo {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub year: Option<i32>,
+    pub track: Option<u32>,
+    pub genre: Option<String>,
+    pub comment: Option<String>,
+    pub has_art: bool,
+}
+
+impl TagInfo {
+    pub fn from_tag(tag: &Tag) -> Self {
+        TagInfo {
+            title: tag.title().map(str::to_string),
+            artist: tag.artist().map(str::to_string),
+            album: tag.album().map(str::to_string),
+            year: tag.year(),
+            track: tag.track(),
+            genre: tag.genre().map(str::to_string),
+            comment: tag.comments().next().map(|c| c.text.clone()),
+            has_art: tag.pictures().next().is_some(),
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<String> {
+        match name {
+            "title" => self.title.clone(),
+            "artist" => self.artist.clone(),
+            "album" => self.album.clone(),
+            "year" => self.year.map(|y| y.to_string()),
+            "track" => self.track.map(|t| t.to_string()),
+            "genre" => self.genre.clone(),
+            "comment" => self.comment.clone(),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct Mp3File {
-    path: PathBuf,
-    size: u64,
-    has_metadata: bool,
+    pub path: PathBuf,
+    pub size: u64,
+    pub has_metadata: bool,
+    pub tags: TagInfo,
+    /// Which of the `--formats` the extension says it is
+    pub kind: AudioFormat,
+    /// What the content really is, judged from its first audio bytes
+    pub format: AudioContainer,
+    /// Version of the leading ID3v2 tag, e.g. `ID3v2.3`
+    pub tag_version: Option<String>,
+    /// Size the ID3v2 header declares, frames and padding; 0 without a tag
+    pub tag_size_bytes: u64,
+}
+
+impl Mp3File {
+    /// Whether the content is positively not what the extension promises
+    pub fn is_wrong_format(&self) -> bool {
+        self.format.is_other_than(self.kind.container())
+    }
+}
+
+/// Smallest file tags are written to, the `--min-size` default
+pub const DEFAULT_MIN_SIZE: u64 = DEFAULTS.min_size;
+
+/// Padding of `--padding minimal`, enough for a tagger to add a few frames
+pub const MINIMAL_PADDING: usize = 256;
+
+/// Padding of `--padding default`, what most taggers leave
+pub const DEFAULT_PADDING: usize = 1024;
+
+/// Parse a `--padding` value: `none`, `minimal`, `default` or a size such as `2K`
+pub fn parse_padding(padding: &str) -> Result<usize, String> {
+    match padding.trim().to_lowercase().as_str() {
+        "none" => Ok(0),
+        "minimal" => Ok(MINIMAL_PADDING),
+        "default" => Ok(DEFAULT_PADDING),
+        _ => parse_size(padding)
+            .map_err(|_| format!("Expected none, minimal, default or a size such as 2K, got {:?}", padding))
+            .and_then(|bytes| usize::try_from(bytes).map_err(|_| format!("Padding {:?} is too large", padding))),
+    }
+}
+
+/// Controls how far `find_mp3_files` descends into the directory tree
+#[derive(Debug, Clone)]
+pub struct WalkOptions {
+    pub recursive: bool,
+    pub max_depth: Option<usize>,
+    pub skip_hidden: bool,
+    /// Keep AppleDouble `._*` files and empty files instead of skipping them
+    pub include_hidden: bool,
+    /// File types to pick up, by extension
+    pub formats: Vec<AudioFormat>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            recursive: true,
+            max_depth: None,
+            skip_hidden: false,
+            include_hidden: false,
+            formats: vec![AudioFormat::Mp3],
+        }
+    }
+}
+
+impl WalkOptions {
+    fn effective_max_depth(&self) -> Option<usize> {
+        if self.recursive {
+            self.max_depth
+        } else {
+            Some(1)
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        let depth = match self.effective_max_depth() {
+            None => "unlimited".to_string(),
+            Some(1) => "top level only".to_string(),
+            Some(depth) => format!("{} levels", depth),
+        };
+        if self.skip_hidden {
+            format!("{}, skipping hidden files", depth)
+        } else {
+            depth
+        }
+    }
+}
+
+fn is_hidden(entry: &walkdir::DirEntry) -> bool {
+    entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with('.')
 }
 
-pub async fn find_mp3_files(directory: &Path) -> Result<Vec<Mp3File>> {
+/// A macOS AppleDouble file, `._<name>`, holding another file's resource
+/// fork and extended attributes rather than audio
+pub fn is_apple_double(path: &Path) -> bool {
+    path.file_name().is_some_and(|name| name.to_string_lossy().starts_with("._"))
+}
+
+pub async fn find_mp3_files(directory: &Path, walk: &WalkOptions, monitor: &mut ScanMonitor<'_>) -> Result<Vec<Mp3File>> {
+    find_mp3_files_in(&ScanRoots::new(&[directory.to_path_buf()]), walk, monitor).await
+}
+
+/// [`find_mp3_files`] under every root; a file reachable from several is
+/// listed once, under the first
+pub async fn find_mp3_files_in(roots: &ScanRoots, walk: &WalkOptions, monitor: &mut ScanMonitor<'_>) -> Result<Vec<Mp3File>> {
     let mut mp3_files = Vec::new();
+    let mut seen = SeenFiles::default();
     
-    for entry in WalkDir::new(directory)
-        .follow_links(true)
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-        if path.is_file() && path.extension().map_or(false, |ext| ext == "mp3") {
+    for root in roots.iter() {
+        let mut walker = WalkDir::new(&root.path).follow_links(true);
+        if let Some(max_depth) = walk.effective_max_depth() {
+            walker = walker.max_depth(max_depth);
+        }
+        
+        let mut entries = walker
+            .into_iter()
+            .filter_entry(|e| !(walk.skip_hidden && is_hidden(e)));
+        while let Some(entry) = entries.next() {
+            let Ok(entry) = entry else { continue };
+            if monitor.excludes(&entry) {
+                entries.skip_current_dir();
+                continue;
+            }
+            monitor.visit(&entry)?;
+            let path = entry.path();
+            let kind = AudioFormat::from_path(path).filter(|kind| walk.formats.contains(kind));
+            if let (true, Some(kind)) = (path.is_file(), kind) {
+                if roots.is_multiple() && !seen.first_sighting(path) {
+                    continue;
+                }
+                let metadata = fs::metadata(path)
+                    .with_context(|| format!("Failed to read metadata for {:?}", path))?;
+                if !walk.include_hidden && (is_apple_double(path) || metadata.len() == 0) {
+                    monitor.skipped_sidecar();
+                    continue;
+                }
+                monitor.matched(metadata.len());
+                mp3_files.push(mp3_file_info(path, kind, metadata.len()));
+            }
+        }
+    }
+    
+    monitor.finish();
+    SortOrder::Path.sort_by_path(&mut mp3_files, |f| &f.path);
+    Ok(mp3_files)
+}
+
+fn mp3_file_info(path: &Path, kind: AudioFormat, size: u64) -> Mp3File {
+    let tag = Tag::read_from_path(path).ok();
+    let id3v2 = read_id3v2_header(path).ok().flatten();
+    let has_info = kind == AudioFormat::Wav && read_info(path).is_ok_and(|items| !items.is_empty());
+    
+    Mp3File {
+        path: path.to_path_buf(),
+        size,
+        has_metadata: tag.is_some() || has_info,
+        tags: tag.as_ref().map(TagInfo::from_tag).unwrap_or_default(),
+        kind,
+        format: sniff_file(path).unwrap_or_default(),
+        tag_version: id3v2.map(|header| header.version()),
+        tag_size_bytes: id3v2.map_or(0, |header| header.size),
+    }
+}
+
+/// Read just these files, as a `retry` of earlier failures does, instead of
+/// walking a directory
+pub fn mp3_files_at(paths: &[PathBuf]) -> Result<Vec<Mp3File>> {
+    paths
+        .iter()
+        .map(|path| {
+            let kind = AudioFormat::from_path(path)
+                .with_context(|| format!("{:?} is not an MP3, WAV or AIFF file", path))?;
             let metadata = fs::metadata(path)
                 .with_context(|| format!("Failed to read metadata for {:?}", path))?;
-            
-            let has_metadata = Tag::read_from_path(path).is_ok();
-            
-            mp3_files.push(Mp3File {
-                path: path.to_path_buf(),
-                size: metadata.len(),
-                has_metadata,
-            });
+            Ok(mp3_file_info(path, kind, metadata.len()))
+        })
+        .collect()
+}
+
+/// Version and declared size of a file's leading ID3v2 tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Id3v2Header {
+    /// 2, 3 or 4
+    pub major: u8,
+    /// Frames plus padding, not counting the 10-byte header or a footer
+    pub size: u64,
+}
+
+impl Id3v2Header {
+    /// e.g. `ID3v2.3`
+    pub fn version(&self) -> String {
+        format!("ID3v2.{}", self.major)
+    }
+}
+
+/// Read only the 10-byte ID3v2 header at the start of a file, so it stays
+/// cheap for huge libraries. `None` when there is no valid header.
+pub fn read_id3v2_header(path: &Path) -> io::Result<Option<Id3v2Header>> {
+    let mut header = [0u8; 10];
+    match fs::File::open(path)?.read_exact(&mut header) {
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        result => result?,
+    }
+    if &header[0..3] != b"ID3" || header[3] == 0xff || header[6..10].iter().any(|b| b & 0x80 != 0) {
+        return Ok(None);
+    }
+    let size = header[6..10]
+        .iter()
+        .fold(0u64, |acc, b| (acc << 7) | u64::from(*b));
+    Ok(Some(Id3v2Header { major: header[3], size }))
+}
+
+/// Locate the byte range holding the audio frames, skipping leading ID3v2
+/// tags and trailing APEv2/ID3v1 tags so content can be compared independent
+/// of metadata.
+pub fn audio_region(path: &Path) -> Result<Range<u64>> {
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let len = file.metadata()
+        .with_context(|| format!("Failed to read metadata for {:?}", path))?
+        .len();
+
+    let mut start = 0u64;
+    let mut header = [0u8; 10];
+    while start + 10 <= len {
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(&mut header)?;
+        if &header[0..3] != b"ID3" {
+            break;
+        }
+        let size = header[6..10]
+            .iter()
+            .fold(0u64, |acc, b| (acc << 7) | u64::from(b & 0x7f));
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        start += 10 + size + footer;
+    }
+
+    let mut end = len;
+    if end >= start + 128 {
+        let mut trailer = [0u8; 3];
+        file.seek(SeekFrom::Start(end - 128))?;
+        file.read_exact(&mut trailer)?;
+        if &trailer == b"TAG" {
+            end -= 128;
+        }
+    }
+    if end >= start + 32 {
+        let mut footer = [0u8; 32];
+        file.seek(SeekFrom::Start(end - 32))?;
+        file.read_exact(&mut footer)?;
+        if &footer[0..8] == b"APETAGEX" {
+            let size = u64::from(u32::from_le_bytes([footer[12], footer[13], footer[14], footer[15]]));
+            let flags = u32::from_le_bytes([footer[20], footer[21], footer[22], footer[23]]);
+            let header = if flags & 0x8000_0000 != 0 { 32 } else { 0 };
+            end = end.saturating_sub(size + header).max(start);
         }
     }
+
+    Ok(start.min(end)..end)
+}
+
+/// Frames that only ever carry tracking or vendor data, removed by `--strip-private`
+const PRIVATE_FRAME_IDS: &[&str] = &["PRIV", "UFID", "GEOB"];
+
+/// TXXX descriptions written by DJ software and music stores (matched case-insensitively)
+const VENDOR_TXXX_DESCRIPTIONS: &[&str] = &[
+    "serato*",
+    "traktor*",
+    "itunes_cddb_*",
+    "itunes*id",
+    "itunmovi",
+    "amazon*",
+];
+
+/// Removes PRIV/UFID/GEOB frames and vendor TXXX frames from a tag
+#[derive(Debug, Clone)]
+pub struct PrivateFrameFilter {
+    txxx: Vec<Pattern>,
+}
+
+impl PrivateFrameFilter {
+    pub fn new(extra_txxx: &[String]) -> Result<Self> {
+        let txxx = VENDOR_TXXX_DESCRIPTIONS
+            .iter()
+            .map(|d| d.to_string())
+            .chain(extra_txxx.iter().cloned())
+            .map(|d| Pattern::new(&d).with_context(|| format!("Invalid TXXX description glob {:?}", d)))
+            .collect::<Result<_>>()?;
+        Ok(PrivateFrameFilter { txxx })
+    }
+
+    fn matches_txxx(&self, description: &str) -> bool {
+        let options = MatchOptions { case_sensitive: false, ..MatchOptions::new() };
+        self.txxx.iter().any(|p| p.matches_with(description, options))
+    }
+
+    /// Strip matching frames in place, returning `ID (description)` for each removed frame
+    pub fn strip(&self, tag: &mut Tag) -> Vec<String> {
+        let mut removed = Vec::new();
+
+        for id in PRIVATE_FRAME_IDS {
+            removed.extend(tag.remove(id).iter().map(frame_label));
+        }
+
+        for frame in tag.remove("TXXX") {
+            match frame.content() {
+                Content::ExtendedText(text) if self.matches_txxx(&text.description) => {
+                    removed.push(frame_label(&frame));
+                }
+                _ => {
+                    tag.add_frame(frame);
+                }
+            }
+        }
+
+        removed
+    }
+}
+
+/// Frame IDs covered by a field name in `--remove-fields`. Unknown names are
+/// treated as raw frame IDs; `links` is handled separately as every `W***` frame.
+fn field_frame_ids(field: &str) -> Vec<String> {
+    let ids: &[&str] = match field {
+        "title" => &["TIT2"],
+        "artist" => &["TPE1"],
+        "album" => &["TALB"],
+        "year" => &["TYER", "TDRC"],
+        "track" => &["TRCK"],
+        "genre" => &["TCON"],
+        "comment" => &["COMM"],
+        "chapters" => &["CHAP", "CTOC"],
+        "lyrics" => &["USLT", "SYLT"],
+        "copyright" => &["TCOP"],
+        "encoder" => &["TSSE", "TENC"],
+        _ => return vec![field.to_uppercase()],
+    };
+    ids.iter().map(|id| id.to_string()).collect()
+}
+
+/// Remove the frames named by `fields`, returning the ID of each removed frame
+fn remove_fields(tag: &mut Tag, fields: &[String]) -> Vec<String> {
+    let mut ids: Vec<String> = Vec::new();
+    for field in fields {
+        if field == "links" {
+            ids.extend(tag.frames().map(|f| f.id().to_string()).filter(|id| id.starts_with('W')));
+        } else {
+            ids.extend(field_frame_ids(field));
+        }
+    }
+    ids.sort();
+    ids.dedup();
+
+    ids.iter()
+        .flat_map(|id| tag.remove(id))
+        .map(|frame| frame.id().to_string())
+        .collect()
+}
+
+/// The frames of `tag` named by `fields`, which `--protect` carries over
+/// a full strip; empty when none of them is present
+fn protected_tag(tag: &Tag, fields: &[String]) -> Tag {
+    let named = |id: &str| fields.iter().any(|field| if field == "links" {
+        id.starts_with('W')
+    } else {
+        field_frame_ids(field).iter().any(|named| named == id)
+    });
+    let mut protected = Tag::new();
+    for frame in tag.frames().filter(|frame| named(frame.id())) {
+        protected.add_frame(frame.clone());
+    }
+    protected
+}
+
+/// Rewrite the file so only its audio frames remain, dropping ID3v2, APE and
+/// ID3v1 tags alike, then confirm no tag is left and the audio is untouched.
+/// The audio is written beside the file and renamed over it, so a failed
+/// write leaves the file as it was.
+fn remove_all_tags(path: &Path) -> Result<()> {
+    let region = audio_region(path)?;
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let partial = path.with_file_name(format!("{}{}.partial", STAGING_PREFIX, file_name));
+    let written = write_region(path, region.clone(), &partial)
+        .with_context(|| format!("Failed to write stripped file {:?}", partial))
+        .and_then(|()| fs::rename(&partial, path).with_context(|| format!("Failed to move {:?} to {:?}", partial, path)));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+
+    let audio_bytes = region.end - region.start;
+    if audio_region(path)? != (0..audio_bytes) {
+        bail!("Metadata still present in {:?} after removing all tags", path);
+    }
+    Ok(())
+}
+
+/// Copy the bytes of `path` within `region` to a new file `to`, with the
+/// permissions of `path`
+fn write_region(path: &Path, region: Range<u64>, to: &Path) -> io::Result<()> {
+    let mut source = fs::File::open(path)?;
+    source.seek(SeekFrom::Start(region.start))?;
+    let mut target = fs::File::create(to)?;
+    io::copy(&mut source.take(region.end - region.start), &mut target)?;
+    target.sync_all()?;
+    fs::set_permissions(to, fs::metadata(path)?.permissions())
+}
+
+/// Which frames to strip from each file
+#[derive(Debug, Clone)]
+pub struct FrameRules {
+    /// Field names and frame IDs, as [`parse_field_list`] gives them
+    pub keep_fields: Option<Vec<String>>,
+    pub remove_fields: Vec<String>,
+    pub remove_all: bool,
+    /// Fields `remove_all` copies back into an otherwise empty tag
+    pub protect: Vec<String>,
+    pub private_filter: Option<PrivateFrameFilter>,
+    /// Rewrites the genre of files that keep one
+    pub genre: Option<GenreNormalizer>,
+    /// Rewrite older tags as ID3v2.4
+    pub upgrade_tags: bool,
+    /// Process files whose content sniffs as another format
+    pub force: bool,
+    /// Leave files smaller than this untouched
+    pub min_size: u64,
+    /// Bytes of padding written after rewritten ID3 tags
+    pub padding: usize,
+    /// Read rewritten MP3s back, restoring those that don't match
+    pub verify_write: bool,
+    /// Also process AppleDouble `._*` files, e.g. `__MACOSX/` entries of archives
+    pub include_hidden: bool,
+    /// Attributes of the original file the stripped one keeps
+    pub preserve: Preserve,
+    /// What happens to originals once their copy in the output is checked
+    pub source_removal: Option<SourceRemoval>,
+    /// Removes the originals, deleting them or moving them to the trash
+    pub disposer: Arc<dyn Disposer>,
+    /// What happens when removed art was the only artwork in its directory
+    pub art_safety: ArtSafety,
+}
+
+impl FrameRules {
+    pub fn from_args(args: &MetadataArgs) -> Result<Self> {
+        if args.protect.is_some() && !args.remove_all {
+            bail!("--protect only applies with --remove-all; use --keep-fields to keep fields otherwise");
+        }
+        let parse = |list, fields: Option<&str>| fields.map(|fields| parse_field_list(list, fields)).transpose();
+        Ok(FrameRules {
+            keep_fields: parse(FieldList::KeepFields, args.keep_fields.as_deref())?,
+            remove_fields: parse(FieldList::RemoveFields, args.remove_fields.as_deref())?.unwrap_or_default(),
+            remove_all: args.remove_all,
+            protect: parse(FieldList::Protect, args.protect.as_deref())?.unwrap_or_default(),
+            private_filter: if args.strip_private {
+                Some(PrivateFrameFilter::new(&args.strip_txxx)?)
+            } else {
+                None
+            },
+            genre: if args.normalize_genre {
+                Some(GenreNormalizer::from_map_file(args.genre_map.as_deref())?)
+            } else {
+                None
+            },
+            upgrade_tags: args.upgrade_tags,
+            force: args.force,
+            min_size: args.min_size,
+            padding: args.padding,
+            verify_write: !args.no_verify_write,
+            include_hidden: args.include_hidden,
+            preserve: Preserve::from_attrs(&args.preserve),
+            source_removal: match (args.delete_source, args.disposal) {
+                (false, _) => None,
+                (true, Disposal::Delete) => Some(SourceRemoval::Delete),
+                (true, Disposal::Trash) => Some(SourceRemoval::Trash),
+            },
+            disposer: args.disposal.disposer(),
+            art_safety: args.art_safety,
+        })
+    }
+}
+
+/// Outcome of processing a single file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub enum StripStatus {
+    Stripped,
+    Skipped,
+    Failed,
+    DryRun,
+    CopiedUnchanged,
+    /// Left alone because the content isn't MPEG audio
+    WrongFormat,
+    /// Left alone because `--skip-clean` found the rules would change nothing
+    SkippedNoop,
+    /// Left out by `--include`, `--exclude` or `--where`
+    SkippedFiltered,
+    /// Left out because another file was given its path under `--output`
+    SkippedCollision,
+    /// Not started because an earlier file ran out of disk space
+    SkippedOutOfSpace,
+    /// Rewritten, but the tag or audio read back wrong, so the file was put
+    /// back as it was, or its copy removed
+    WriteVerificationFailed,
+}
+
+impl StripStatus {
+    /// How the file counts towards its root's line of the summary
+    fn root_outcome(self) -> RootOutcome {
+        match self {
+            StripStatus::Stripped | StripStatus::CopiedUnchanged | StripStatus::DryRun => RootOutcome::Processed,
+            StripStatus::Failed | StripStatus::WriteVerificationFailed => RootOutcome::Failed,
+            _ => RootOutcome::Skipped,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct StripResult {
+    pub path: PathBuf,
+    pub status: StripStatus,
+    pub frames_removed: Vec<String>,
+    pub frames_kept: Vec<String>,
+    pub bytes_saved: i64,
+    /// Every original frame, marked removed or kept
+    pub diff: TagDiff,
+    /// The container found instead of MPEG audio, for `WrongFormat`
+    pub detected_format: Option<AudioContainer>,
+    /// The version change and frame counts, for files `--upgrade-tags` rewrote
+    pub tag_upgrade: Option<TagUpgrade>,
+    /// Another file of the batch wanted the same path under `--output`
+    pub collision: Option<OutputCollision>,
+    /// Copy in `--output` whose tags couldn't be rewritten, e.g. on a full disk
+    pub incomplete_copy: Option<IncompleteCopy>,
+    /// How `--delete-source` removed the original; `None` while it is still in place
+    pub source_removed: Option<SourceRemoval>,
+    /// Why `--delete-source` kept the original of a file that was copied
+    pub source_kept: Option<String>,
+    /// Size of the `--output` copy when it was made as a reflink sharing the
+    /// original's data, rather than copied byte by byte
+    pub reflinked: Option<u64>,
+    /// Set when the art this file lost, or kept by `--art-safety skip`, was
+    /// the only artwork in its directory
+    pub art_safety: Option<ArtSafetyHit>,
+    pub error: Option<String>,
+    /// Category used to group failures in the summary
+    #[serde(skip)]
+    pub error_kind: Option<String>,
+}
+
+impl StripResult {
+    fn new(path: &Path, status: StripStatus) -> Self {
+        StripResult {
+            path: path.to_path_buf(),
+            status,
+            frames_removed: Vec::new(),
+            frames_kept: Vec::new(),
+            bytes_saved: 0,
+            diff: TagDiff::default(),
+            detected_format: None,
+            tag_upgrade: None,
+            collision: None,
+            incomplete_copy: None,
+            source_removed: None,
+            source_kept: None,
+            reflinked: None,
+            art_safety: None,
+            error: None,
+            error_kind: None,
+        }
+    }
+
+    pub fn failed(path: &Path, error: &anyhow::Error) -> Self {
+        StripResult {
+            error: Some(format!("{:#}", error)),
+            error_kind: Some(error_kind(error)),
+            incomplete_copy: error.downcast_ref::<IncompleteCopy>().cloned(),
+            ..StripResult::new(path, StripStatus::Failed)
+        }
+    }
+}
+
+/// Apply the rules to a copy of `tag`, returning the new tag if anything changed
+fn plan_tag(tag: &Tag, rules: &FrameRules) -> Option<Tag> {
+    let mut tag = tag.clone();
     
-    Ok(mp3_files)
+    // Drop tracking frames first so kept fields can never bring them back
+    let private_removed = rules.private_filter
+        .as_ref()
+        .map(|filter| filter.strip(&mut tag))
+        .unwrap_or_default();
+    let mut changed = !private_removed.is_empty();
+    
+    if let Some(fields_to_keep) = rules.keep_fields.as_deref() {
+        // Keep only specified fields
+        let mut new_tag = Tag::new();
+        
+        for field in fields_to_keep {
+            match field.as_str() {
+                "title" => {
+                    if let Some(title) = tag.title() {
+                        new_tag.set_title(title);
+                    }
+                }
+                "artist" => {
+                    if let Some(artist) = tag.artist() {
+                        new_tag.set_artist(artist);
+                    }
+                }
+                "album" => {
+                    if let Some(album) = tag.album() {
+                        new_tag.set_album(album);
+                    }
+                }
+                "year" => {
+                    if let Some(year) = tag.year() {
+                        new_tag.set_year(year);
+                    }
+                }
+                "track" => {
+                    if let Some(track) = tag.track() {
+                        new_tag.set_track(track);
+                    }
+                }
+                "genre" => {
+                    if let Some(genre) = tag.genre() {
+                        new_tag.set_genre(genre);
+                    }
+                }
+                _ => {
+                    // Try to copy custom frames
+                    if let Some(frame) = tag.get(field) {
+                        new_tag.add_frame(frame.clone());
+                    }
+                }
+            }
+        }
+        
+        tag = new_tag;
+        changed = true;
+    }
+    
+    if !remove_fields(&mut tag, &rules.remove_fields).is_empty() {
+        changed = true;
+    }
+    
+    // Normalize whatever genre survived the rules above
+    if let (Some(normalizer), Some(genres)) = (&rules.genre, tag.genres()) {
+        let normalized = normalizer.normalize(genres.iter().copied());
+        if normalized != genres {
+            tag.remove_genre();
+            if !normalized.is_empty() {
+                tag.set_text_values("TCON", normalized);
+            }
+            changed = true;
+        }
+    }
+    
+    changed.then_some(tag)
+}
+
+/// Field names `--keep-fields` and `--remove-fields` use for WAV INFO items;
+/// other items go by their own ID, e.g. `ISFT`
+fn info_field(id: &str) -> Option<&'static str> {
+    Some(match id {
+        "INAM" => "title",
+        "IART" => "artist",
+        "IPRD" => "album",
+        "ICRD" => "year",
+        "ITRK" | "IPRT" => "track",
+        "IGNR" => "genre",
+        "ICMT" => "comment",
+        "ICOP" => "copyright",
+        "ISFT" => "encoder",
+        _ => return None,
+    })
+}
+
+/// IDs of the WAV INFO items the rules remove
+fn plan_info(items: &[InfoItem], rules: &FrameRules) -> Vec<String> {
+    let named = |field: &str, item: &InfoItem| {
+        let field = field.trim();
+        info_field(&item.id) == Some(field) || field.eq_ignore_ascii_case(&item.id)
+    };
+    items
+        .iter()
+        .filter(|item| {
+            let kept = rules.keep_fields.as_deref().is_none_or(|keep| keep.iter().any(|field| named(field, item)));
+            let protected = rules.protect.iter().any(|field| named(field, item));
+            (rules.remove_all && !protected) || !kept || rules.remove_fields.iter().any(|field| named(field, item))
+        })
+        .map(|item| item.id.clone())
+        .collect()
+}
+
+/// Whether stripping `mp3_file` with `rules` would leave it as it is, so
+/// `skip_clean` can pass it by. A file without a tag needs no reading; with
+/// `remove_all` an MP3 also counts trailing ID3v1 and APE tags, which only
+/// that mode removes. Keeping fields that are already all the tag holds is
+/// a no-op even though the tag would be rebuilt.
+fn is_noop(mp3_file: &Mp3File, rules: &FrameRules) -> Result<bool> {
+    if rules.remove_all {
+        let region = (mp3_file.kind == AudioFormat::Mp3).then(|| audio_region(&mp3_file.path)).transpose()?;
+        let trailing = region.as_ref().is_some_and(|region| region.end != mp3_file.size);
+        if trailing || !mp3_file.has_metadata || rules.protect.is_empty() {
+            let untagged = region.is_none_or(|region| region == (0..mp3_file.size));
+            return Ok(!mp3_file.has_metadata && untagged);
+        }
+        // Holding nothing but protected frames is as stripped as it gets
+        let info_changes = mp3_file.kind == AudioFormat::Wav && !plan_info(&read_info(&mp3_file.path)?, rules).is_empty();
+        let Ok(tag) = Tag::read_from_path(&mp3_file.path) else {
+            return Ok(!info_changes);
+        };
+        return Ok(!info_changes && same_frames(&protected_tag(&tag, &rules.protect), &tag));
+    }
+    if !mp3_file.has_metadata {
+        return Ok(true);
+    }
+    let info_changes = mp3_file.kind == AudioFormat::Wav && !plan_info(&read_info(&mp3_file.path)?, rules).is_empty();
+    let Ok(tag) = Tag::read_from_path(&mp3_file.path) else {
+        return Ok(!info_changes);
+    };
+    let tag_changes = plan_tag(&tag, rules).is_some_and(|planned| !same_frames(&planned, &tag));
+    let upgrade = rules.upgrade_tags && tag.version() != id3::Version::Id3v24;
+    Ok(!info_changes && !tag_changes && !upgrade)
+}
+
+/// Context of a failure to rewrite the tags of a copy in `--output`. The
+/// rewrite may have got part way, so the copy is removed rather than left
+/// looking finished.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct IncompleteCopy {
+    pub path: PathBuf,
+    /// False when the copy couldn't be removed either and may be damaged
+    pub removed: bool,
+}
+
+impl fmt::Display for IncompleteCopy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.removed {
+            write!(f, "Removed the copy {:?}, whose tags couldn't be rewritten", self.path)
+        } else {
+            write!(f, "The copy {:?} may be damaged: its tags couldn't be rewritten, nor it removed", self.path)
+        }
+    }
+}
+
+/// What `--delete-source` does with an original once its copy is checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum SourceRemoval {
+    Delete,
+    /// Moved to the system trash, by `--disposal trash`
+    Trash,
+}
+
+/// Check the copy at `output_path` before its original goes: it must be
+/// there and not empty, and an MP3 copy must hold the same audio frames.
+/// WAV and AIFF copies have their chunks rewritten, so only the size is checked.
+fn verify_copy(mp3_file: &Mp3File, output_path: &Path) -> Result<()> {
+    let size = fs::metadata(output_path)
+        .with_context(|| format!("Failed to read metadata for {:?}", output_path))?
+        .len();
+    if size == 0 {
+        bail!("The copy {:?} is empty", output_path);
+    }
+    if mp3_file.kind == AudioFormat::Mp3 && hash_audio(&mp3_file.path)?.0 != hash_audio(output_path)?.0 {
+        bail!("The audio of the copy {:?} differs from the original", output_path);
+    }
+    Ok(())
+}
+
+/// Remove the original of a finished copy, unless the copy fails [`verify_copy`]
+fn remove_source(mp3_file: &Mp3File, output_path: &Path, disposer: &dyn Disposer) -> Result<()> {
+    verify_copy(mp3_file, output_path)?;
+    disposer.dispose(&mp3_file.path)
+}
+
+/// The tags a file gets once its changes are planned
+struct TagWrite<'a> {
+    kind: AudioFormat,
+    rules: &'a FrameRules,
+    planned: Option<&'a Tag>,
+    /// Frames `--protect` keeps through `--remove-all`
+    protected: Option<&'a Tag>,
+    remove_info: &'a [String],
+}
+
+impl TagWrite<'_> {
+    /// WAV and AIFF keep their tag in a chunk, which a plain ID3 write
+    /// would put in front of the RIFF header instead
+    fn write(&self, path: &Path) -> Result<()> {
+        let rules = self.rules;
+        if self.kind != AudioFormat::Mp3 {
+            let id3 = if rules.remove_all { Some(self.protected) } else { self.planned.map(Some) };
+            if id3.is_some() || !self.remove_info.is_empty() {
+                rewrite_chunks(path, &ChunkEdit { id3, remove_info: self.remove_info.to_vec(), padding: rules.padding })?;
+            }
+        } else if rules.remove_all {
+            remove_all_tags(path)?;
+            if let Some(tag) = self.protected {
+                id3::Encoder::new()
+                    .version(id3::Version::Id3v24)
+                    .padding(rules.padding)
+                    .write_to_path(tag, path)
+                    .with_context(|| format!("Failed to write the protected frames to {:?}", path))?;
+            }
+        } else if let Some(tag) = self.planned {
+            id3::Encoder::new()
+                .version(id3::Version::Id3v24)
+                .padding(rules.padding)
+                .write_to_path(tag, path)
+                .with_context(|| format!("Failed to write filtered metadata to {:?}", path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Strip `mp3_file` in place, or into a copy at `output_path`
+pub async fn strip_metadata_file(
+    mp3_file: &Mp3File,
+    output_path: Option<&Path>,
+    rules: &FrameRules,
+    dry_run: bool,
+    verbose: bool,
+    progress_bar: ProgressBar,
+) -> Result<StripResult> {
+    strip_file_with(mp3_file, output_path, rules, dry_run, verbose, progress_bar, |tags: &TagWrite, path: &Path| tags.write(path)).await
+}
+
+/// [`strip_metadata_file`], putting the tags into the file with `write`
+async fn strip_file_with(
+    mp3_file: &Mp3File,
+    output_path: Option<&Path>,
+    rules: &FrameRules,
+    dry_run: bool,
+    verbose: bool,
+    progress_bar: ProgressBar,
+    write: impl Fn(&TagWrite, &Path) -> Result<()>,
+) -> Result<StripResult> {
+    let file_name = mp3_file.path.file_name().unwrap().to_string_lossy();
+    
+    let output_path = output_path.map_or_else(|| mp3_file.path.clone(), Path::to_path_buf);
+    
+    // A tag written onto an M4A or WAV corrupts it further
+    if mp3_file.is_wrong_format() && !rules.force {
+        progress_bar.finish_with_message(format!("Skipped: {}", file_name));
+        return Ok(StripResult {
+            detected_format: Some(mp3_file.format),
+            ..StripResult::new(&mp3_file.path, StripStatus::WrongFormat)
+        });
+    }
+    
+    // Too small to be audio; a tag written here lands in whatever this is
+    if mp3_file.size < rules.min_size {
+        progress_bar.finish_with_message(format!("Skipped (too small): {}", file_name));
+        return Ok(StripResult::new(&mp3_file.path, StripStatus::Skipped));
+    }
+    
+    // Work out the resulting tag before touching anything on disk
+    let original = Tag::read_from_path(&mp3_file.path).ok();
+    let mut planned = if rules.remove_all {
+        Some(original.as_ref().map(|tag| protected_tag(tag, &rules.protect)).unwrap_or_default())
+    } else {
+        original.as_ref().and_then(|tag| plan_tag(tag, rules))
+    };
+    
+    let mut result = StripResult::new(&mp3_file.path, StripStatus::Skipped);
+    // Pictures with no cover image beside them may be the album's only art
+    if let (Some(original), Some(tag)) = (&original, &mut planned) {
+        let directory = output_path.parent().unwrap_or(Path::new(""));
+        if rules.art_safety != ArtSafety::Ignore && removes_art(original, tag) && !has_sidecar_art(directory) {
+            let mut exported = None;
+            match rules.art_safety {
+                ArtSafety::Export if !dry_run => {
+                    exported = export_cover(original, directory)?;
+                    if let Some(cover) = &exported {
+                        progress_bar.suspend(|| println!("🖼️  {}: saved its cover art to {:?}", file_name, cover));
+                    }
+                }
+                ArtSafety::Skip => keep_art(original, tag),
+                _ => {}
+            }
+            result.art_safety = Some(ArtSafetyHit { directory: directory.to_path_buf(), action: rules.art_safety, exported });
+        }
+    }
+    // Keeping the art may leave nothing else to change
+    let art_kept = result.art_safety.as_ref().is_some_and(|hit| hit.action == ArtSafety::Skip);
+    if art_kept && !rules.remove_all && planned.as_ref().zip(original.as_ref()).is_some_and(|(tag, original)| same_frames(tag, original)) {
+        planned = None;
+    }
+    if let Some(original) = &original {
+        result.diff = TagDiff::new(original, planned.as_ref().unwrap_or(original));
+        result.frames_removed = result.diff.removed().map(|line| line.frame.clone()).collect();
+        result.frames_kept = result.diff.kept().map(|line| line.frame.clone()).collect();
+    }
+    // WAV recorders also write LIST INFO items, which the same rules cover
+    let remove_info = if mp3_file.kind == AudioFormat::Wav {
+        plan_info(&read_info(&mp3_file.path)?, rules)
+    } else {
+        Vec::new()
+    };
+    result.frames_removed.extend(remove_info.iter().map(|id| format!("INFO {}", id)));
+    
+    // The diff above shows only what the rules changed; the upgrade renames frames
+    if let Some(original) = original.as_ref().filter(|tag| rules.upgrade_tags && tag.version() != id3::Version::Id3v24) {
+        let (upgraded, merged) = upgrade_tag(planned.as_ref().unwrap_or(original))
+            .with_context(|| format!("Can't upgrade the tag of {:?} without losing frames", mp3_file.path))?;
+        result.tag_upgrade = Some(TagUpgrade {
+            from: version_name(original.version()).to_string(),
+            to: version_name(id3::Version::Id3v24).to_string(),
+            frames_before: original.frames().count(),
+            frames_after: upgraded.frames().count(),
+            merged,
+        });
+        planned = Some(upgraded);
+    }
+    if (verbose || dry_run) && !result.diff.lines.is_empty() {
+        let diff = result.diff.render().replace('\n', "\n    ");
+        progress_bar.suspend(|| println!("🔒 {}:\n    {}", file_name, diff));
+        for id in &remove_info {
+            progress_bar.suspend(|| println!("🔒 {}: removed INFO {}", file_name, id));
+        }
+    } else {
+        for removed in &result.frames_removed {
+            progress_bar.suspend(|| println!("🔒 {}: removed {}", file_name, removed));
+        }
+    }
+    
+    if dry_run {
+        if let Some(upgrade) = &result.tag_upgrade {
+            progress_bar.suspend(|| println!("⬆️  {}: {}", file_name, upgrade.describe()));
+        }
+        result.status = StripStatus::DryRun;
+        progress_bar.finish_with_message(format!("Checked: {}", file_name));
+        return Ok(result);
+    }
+    
+    // Create output directory if needed
+    if let Some(output_dir) = output_path.parent().filter(|_| output_path != mp3_file.path) {
+        fs::create_dir_all(output_dir)
+            .with_context(|| format!("Failed to create directory {:?}", output_dir))?;
+    }
+    
+    // Read before the rewrite changes the times
+    let source_metadata = fs::metadata(&mp3_file.path)
+        .with_context(|| format!("Failed to read metadata for {:?}", mp3_file.path))?;
+    
+    // Copy file to output location if different; a read-only original
+    // gives a read-only copy, which the tag write would fail on
+    let copied = output_path != mp3_file.path;
+    if copied {
+        // A copy cut short, e.g. by a full disk, would pass for a finished one
+        match reflink::copy_file(&mp3_file.path, &output_path) {
+            Ok(reflinked) => result.reflinked = reflinked.then_some(mp3_file.size),
+            Err(e) => {
+                let _ = fs::remove_file(&output_path);
+                return Err(e).with_context(|| format!("Failed to copy file from {:?} to {:?}", mp3_file.path, output_path));
+            }
+        }
+        make_writable(&output_path)?;
+    }
+    
+    // Process metadata
+    let protected = planned.as_ref().filter(|tag| rules.remove_all && tag.frames().next().is_some());
+    let writes_id3 = mp3_file.kind == AudioFormat::Mp3 && (rules.remove_all || planned.is_some());
+    let check = if rules.verify_write && writes_id3 {
+        let intended = if rules.remove_all { protected } else { planned.as_ref() };
+        Some(WriteCheck::before(&output_path, intended, !copied)?)
+    } else {
+        None
+    };
+    let tags = TagWrite { kind: mp3_file.kind, rules, planned: planned.as_ref(), protected, remove_info: &remove_info };
+    if let Err(e) = write(&tags, &output_path) {
+        if !copied {
+            return Err(e);
+        }
+        let removed = fs::remove_file(&output_path).is_ok();
+        return Err(e.context(IncompleteCopy { path: output_path.clone(), removed }));
+    }
+    
+    // Read the write back, putting the file back as it was should it not match
+    if let Some(check) = check {
+        if let Err(mismatch) = check.verify() {
+            let outcome = check.restore();
+            if outcome == Restoration::Restored {
+                restore_attributes(rules.preserve, &source_metadata, &output_path)?;
+            }
+            progress_bar.suspend(|| eprintln!("⚠️  {}: write verification failed, {}; {}", file_name, mismatch, outcome));
+            progress_bar.finish_with_message(format!("Restored: {}", file_name));
+            result.status = StripStatus::WriteVerificationFailed;
+            result.error = Some(format!("{}; {}", mismatch, outcome));
+            result.error_kind = Some("write verification failed".to_string());
+            return Ok(result);
+        }
+    }
+    
+    // Count what actually landed in the file, not what was planned
+    if let Some(upgrade) = &mut result.tag_upgrade {
+        upgrade.frames_after = Tag::read_from_path(&output_path)
+            .with_context(|| format!("Failed to read back the upgraded tag of {:?}", output_path))?
+            .frames()
+            .count();
+        progress_bar.suspend(|| println!("⬆️  {}: {}", file_name, upgrade.describe()));
+    }
+    
+    if !restore_attributes(rules.preserve, &source_metadata, &output_path)? {
+        progress_bar.suspend(|| eprintln!("⚠️  Could not change the owner of {:?} (not running as root?)", output_path));
+    }
+    
+    let new_size = fs::metadata(&output_path)
+        .with_context(|| format!("Failed to read metadata for {:?}", output_path))?
+        .len();
+    result.bytes_saved = mp3_file.size as i64 - new_size as i64;
+    if verbose || result.bytes_saved < 0 {
+        let change = if result.bytes_saved < 0 { "grew" } else { "saved" };
+        progress_bar.suspend(|| println!("📏 {}: {} → {} ({} {})",
+            file_name, format_size(mp3_file.size), format_size(new_size), change, format_size(result.bytes_saved.unsigned_abs())));
+    }
+    
+    let stripped = if rules.remove_all {
+        original.is_some() || result.bytes_saved != 0
+    } else {
+        planned.is_some() || !remove_info.is_empty()
+    };
+    result.status = if stripped {
+        StripStatus::Stripped
+    } else if copied {
+        StripStatus::CopiedUnchanged
+    } else {
+        StripStatus::Skipped
+    };
+    
+    if let Some(removal) = rules.source_removal.filter(|_| copied) {
+        match remove_source(mp3_file, &output_path, rules.disposer.as_ref()) {
+            Ok(()) => result.source_removed = Some(removal),
+            Err(e) => {
+                progress_bar.suspend(|| println!("⚠️  Kept {:?}: {:#}", mp3_file.path, e));
+                result.source_kept = Some(format!("{:#}", e));
+            }
+        }
+    }
+    
+    progress_bar.finish_with_message(format!("Processed: {}", file_name));
+    Ok(result)
+}
+
+pub async fn bulk_strip_metadata(args: MetadataArgs) -> Result<Vec<StripResult>> {
+    let mut report = RunReport::new("strip", &args);
+    bulk_strip_metadata_with_report(args, &mut report, |_, _, _| {}).await
 }
 
-pub async fn strip_metadata_file(
-    mp3_file: &Mp3File,
-    output_dir: Option<&Path>,
-    keep_fields: Option<&str>,
-    remove_all: bool,
-    dry_run: bool,
-    progress_bar: ProgressBar,
-) -> Result<()> {
-    let file_name = mp3_file.path.file_name().unwrap().to_string_lossy();
+/// [`bulk_strip_metadata`], recording the scan, results and errors in `report`
+/// as they become known. `on_finished` gets each result as soon as it's
+/// known, with its index in the returned vector and the total. When `report`
+/// streams its results, they go only to its results file, the returned
+/// vector is empty and the index counts results as they finish.
+pub async fn bulk_strip_metadata_with_report(
+    args: MetadataArgs,
+    report: &mut RunReport,
+    on_finished: impl Fn(usize, usize, &StripResult) + Send + Sync,
+) -> Result<Vec<StripResult>> {
+    // Checked first, so a misspelled field fails the run before any file is touched
+    let rules = FrameRules::from_args(&args)?;
+    let errors = ErrorCollector::new(args.error_log.as_deref())?;
+    let roots = ScanRoots::new(&args.directories);
+    let mut io = settings_for_run(args.io_profile, args.workers, Workload::Strip, roots.first());
+    if args.background {
+        report.set_priority(&run_in_background(&mut io, args.workers.is_some()));
+    }
+    report.set_io(&io);
+    if args.in_archive {
+        let results = bulk_strip_archives(&args, &roots, &rules, io.workers).await?;
+        let mut summary = StripSummary::default();
+        for (index, result) in results.iter().enumerate() {
+            summary.add(result, &errors);
+            on_finished(index, results.len(), result);
+        }
+        summary.print(&errors);
+        record_results(report, &results, &errors);
+        return Ok(results);
+    }
     
-    // Determine output path
-    let output_path = if let Some(output_dir) = output_dir {
-        output_dir.join(&*file_name)
+    let walk = WalkOptions {
+        recursive: args.recursive,
+        max_depth: args.max_depth,
+        skip_hidden: args.skip_hidden,
+        include_hidden: args.include_hidden,
+        formats: args.formats.clone(),
+    };
+    let kinds = AudioFormat::describe(&args.formats);
+    println!("🔍 Scanning for {} files in {} (depth: {})...", kinds, roots, walk.describe());
+    let mut monitor = ScanMonitor::new().excluding_output(args.output.as_deref());
+    let mut mp3_files = if args.only.is_empty() {
+        find_mp3_files_in(&roots, &walk, &mut monitor).await?
     } else {
-        mp3_file.path.clone()
+        mp3_files_at(&args.only)?
     };
+    if monitor.sidecars() > 0 {
+        println!("🙈 Skipped {} AppleDouble (._*) and empty files (--include-hidden to scan them)", monitor.sidecars());
+    }
+    args.sort.sort_by_path(&mut mp3_files, |f| &f.path);
     
-    if !dry_run {
-        // Create output directory if needed
-        if let Some(output_dir) = output_dir {
-            fs::create_dir_all(output_dir)
-                .with_context(|| format!("Failed to create directory {:?}", output_dir))?;
-        }
+    if mp3_files.is_empty() {
+        println!("❌ No {} files found in {}", kinds, roots);
+        return Ok(Vec::new());
+    }
+    
+    let scanned_count = mp3_files.len();
+    let path_filter = PathFilter::new(&args.include, &args.exclude)?;
+    let tag_filter = args.where_expr.as_deref().map(TagFilter::parse).transpose()?;
+    
+    let mut filtered = Vec::new();
+    if !path_filter.is_empty() || tag_filter.is_some() {
+        (mp3_files, filtered) = mp3_files.into_iter().partition(|f| {
+            let relative = f.path.strip_prefix(&roots.root_of(&f.path).path).unwrap_or(&f.path);
+            path_filter.matches(relative) && tag_filter.as_ref().is_none_or(|t| t.matches(&f.tags))
+        });
         
-        // Copy file to output location if different
-        if output_path != mp3_file.path {
-            fs::copy(&mp3_file.path, &output_path)
-                .with_context(|| format!("Failed to copy file from {:?} to {:?}", mp3_file.path, output_path))?;
-        }
+        println!("🔎 Files matching filters: {} of {}", mp3_files.len(), scanned_count);
         
-        // Process metadata
-        if let Ok(tag) = Tag::read_from_path(&output_path) {
-            if remove_all {
-                // Remove all metadata by writing an empty tag
-                let empty_tag = Tag::new();
-                empty_tag.write_to_path(&output_path, id3::Version::Id3v24)
-                    .with_context(|| format!("Failed to write stripped metadata to {:?}", output_path))?;
-            } else if let Some(fields_to_keep) = keep_fields {
-                // Keep only specified fields
-                let fields: Vec<&str> = fields_to_keep.split(',').collect();
-                let mut new_tag = Tag::new();
-                
-                for field in fields {
-                    match field.trim() {
-                        "title" => {
-                            if let Some(title) = tag.title() {
-                                new_tag.set_title(title);
-                            }
-                        }
-                        "artist" => {
-                            if let Some(artist) = tag.artist() {
-                                new_tag.set_artist(artist);
-                            }
-                        }
-                        "album" => {
-                            if let Some(album) = tag.album() {
-                                new_tag.set_album(album);
-                            }
-                        }
-                        "year" => {
-                            if let Some(year) = tag.year() {
-                                new_tag.set_year(year);
-                            }
-                        }
-                        "track" => {
-                            if let Some(track) = tag.track() {
-                                new_tag.set_track(track);
-                            }
-                        }
-                        "genre" => {
-                            if let Some(genre) = tag.genre() {
-                                new_tag.set_genre(genre);
-                            }
-                        }
-                        _ => {
-                            // Try to copy custom frames
-                            if let Some(frame) = tag.get(field) {
-                                new_tag.add_frame(frame.clone());
-                            }
-                        }
-                    }
-                }
-                
-                // Replace the tag
-                new_tag.write_to_path(&output_path, id3::Version::Id3v24)
-                    .with_context(|| format!("Failed to write filtered metadata to {:?}", output_path))?;
+        if mp3_files.is_empty() {
+            println!("❌ No {} files matched the filters", kinds);
+            let results: Vec<StripResult> = filtered.iter()
+                .map(|f| StripResult::new(&f.path, StripStatus::SkippedFiltered))
+                .collect();
+            for (index, result) in results.iter().enumerate() {
+                on_finished(index, results.len(), result);
             }
+            record_results(report, &results, &errors);
+            return Ok(if report.result_log().is_some() { Vec::new() } else { results });
         }
     }
     
-    progress_bar.finish_with_message(format!("Processed: {}", file_name));
-    Ok(())
-}
-
-pub async fn bulk_strip_metadata(args: MetadataArgs) -> Result<()> {
-    println!("🔍 Scanning for MP3 files in {:?}...", args.directory);
-    let mp3_files = find_mp3_files(&args.directory).await?;
-    
-    if mp3_files.is_empty() {
-        println!("❌ No MP3 files found in {:?}", args.directory);
-        return Ok(());
+    // Before the totals, so they describe only what runs
+    let (mp3_files, sampled) = Sample::new(args.limit, args.sample, args.seed).apply(mp3_files);
+    if let Some(sampled) = &sampled {
+        println!("🎯 Limit: {}", sampled.describe());
+        report.set_sample(sampled);
     }
     
-    let files_with_metadata: Vec<_> = mp3_files.iter()
+    let metadata_count = mp3_files.iter()
         .filter(|f| f.has_metadata)
-        .collect();
-    
-    println!("📦 Found {} MP3 files:", mp3_files.len());
-    println!("📊 Files with metadata: {}", files_with_metadata.len());
+        .count();
     let total_size: u64 = mp3_files.iter().map(|f| f.size).sum();
-    println!("📊 Total size: {:.2} MB", total_size as f64 / 1024.0 / 1024.0);
+    report.set_scan(&serde_json::json!({
+        "scanned": scanned_count,
+        "matching": mp3_files.len(),
+        "with_metadata": metadata_count,
+        "total_size": total_size,
+        "sidecars_skipped": monitor.sidecars(),
+    }));
+    
+    println!("📦 Found {} {} files:", mp3_files.len(), kinds);
+    println!("📊 Files with metadata: {}", metadata_count);
+    println!("📊 Total size: {}", format_size(total_size));
+    if args.verbose {
+        print_mp3_table(&mp3_files, &roots);
+    }
+    
+    // Files the rules wouldn't change are reported as skipped rather than
+    // rewritten; one that can't be read goes through so its failure is reported
+    let (noop_files, mut files_to_process): (Vec<_>, Vec<_>) = if args.skip_clean {
+        mp3_files.into_iter().partition(|f| is_noop(f, &rules).unwrap_or(false))
+    } else {
+        (Vec::new(), mp3_files)
+    };
+    let paths = filtered.iter().chain(&noop_files).chain(&files_to_process).map(|f| f.path.clone()).collect();
+    let sink = StripSink::new(report.result_log(), &errors, &roots, paths, args.sort, &on_finished);
+    for f in &filtered {
+        sink.add(StripResult::new(&f.path, StripStatus::SkippedFiltered));
+    }
+    for f in &noop_files {
+        sink.add(StripResult::new(&f.path, StripStatus::SkippedNoop));
+    }
     
-    if args.skip_clean && files_with_metadata.is_empty() {
-        println!("✅ All files are already clean (no metadata found)");
-        return Ok(());
+    // Every copy's path is settled up front, so a dry run shows the collisions too
+    let plan = args.output.as_deref().map(|output_dir| {
+        OutputPlan::reserve(files_to_process.iter().map(|f| f.path.as_path()), output_dir, args.on_collision)
+    });
+    if let Some(plan) = &plan {
+        print_collisions(plan, &roots);
+        files_to_process.retain(|f| plan.target(&f.path).is_some());
+        for (path, collision) in plan.collisions().iter().filter(|(_, c)| c.written_to.is_none()) {
+            sink.add(StripResult {
+                collision: Some(collision.clone()),
+                ..StripResult::new(path, StripStatus::SkippedCollision)
+            });
+        }
+        
+        // Nor may a copy land on another input, e.g. `live/a.mp3` onto `a.mp3`
+        // when the output is the input directory; its own path is in place
+        let inputs = InputSet::new(filtered.iter().chain(&noop_files).chain(&files_to_process).map(|f| f.path.as_path()));
+        files_to_process.retain(|f| {
+            let Some(target) = plan.target(&f.path) else { return true };
+            match inputs.check(target) {
+                Err(clobber) if fs::canonicalize(&f.path).ok().as_ref() != Some(&clobber.input) => {
+                    println!("❌ {:?}: {}", f.path, clobber);
+                    sink.add(StripResult::failed(&f.path, &anyhow::Error::new(clobber)));
+                    false
+                }
+                _ => true,
+            }
+        });
+    }
+    
+    if files_to_process.is_empty() {
+        let (results, summary) = sink.finish();
+        if summary.count(StripStatus::Failed) == 0 {
+            println!("✅ Nothing to change: every file is already as the options would leave it");
+        }
+        record_results(report, &results, &errors);
+        if !summary.roots.is_empty() {
+            report.set_roots(&summary.roots);
+        }
+        return Ok(results);
     }
     
-    // Create output directory if specified
+    // Create output directory if specified, after checking it can hold a copy of everything
     if let Some(ref output_dir) = args.output {
+        let needed = files_to_process.iter().map(|f| f.size).sum();
+        if let Some(space) = preflight(output_dir, needed, args.min_free_space)? {
+            report.set_space(&space);
+        }
         fs::create_dir_all(output_dir)
             .with_context(|| format!("Failed to create output directory {:?}", output_dir))?;
     }
@@ -203,50 +1584,66 @@ pub async fn bulk_strip_metadata(args: MetadataArgs) -> Result<()> {
         .progress_chars("#>-");
     
     // Process files with limited concurrency
-    let files_to_process: Vec<_> = if args.skip_clean {
-        files_with_metadata.into_iter().cloned().collect()
-    } else {
-        mp3_files
-    };
-    
     let chunks: Vec<_> = files_to_process
-        .chunks((files_to_process.len() + args.workers - 1) / args.workers)
+        .chunks(files_to_process.len().div_ceil(io.workers))
         .collect();
+    let out_of_space = AtomicBool::new(false);
     
     let futures: Vec<_> = chunks
         .into_iter()
         .map(|chunk| {
             let chunk = chunk.to_vec();
-            let output_dir = args.output.clone();
-            let keep_fields = args.keep_fields.clone();
-            let remove_all = args.remove_all;
+            let plan = &plan;
+            let out_of_space = &out_of_space;
+            let sink = &sink;
+            let rules = rules.clone();
             let dry_run = args.dry_run;
+            let verbose = args.verbose;
             let multi_progress = multi_progress.clone();
             let style = style.clone();
             
             async move {
                 for mp3_file in chunk {
+                    // Once one file hits a full disk, the rest would only fail the same way
+                    if out_of_space.load(Ordering::Relaxed) {
+                        sink.add(StripResult::new(&mp3_file.path, StripStatus::SkippedOutOfSpace));
+                        continue;
+                    }
                     let progress_bar = multi_progress.add(ProgressBar::new(1));
                     progress_bar.set_style(style.clone());
                     progress_bar.set_message(format!("Processing: {}", mp3_file.path.file_name().unwrap().to_string_lossy()));
                     
-                    if let Err(e) = strip_metadata_file(
+                    let output_path = plan.as_ref().and_then(|plan| plan.target(&mp3_file.path));
+                    let mut result = match strip_metadata_file(
                         &mp3_file,
-                        output_dir.as_deref(),
-                        keep_fields.as_deref(),
-                        remove_all,
+                        output_path,
+                        &rules,
                         dry_run,
+                        verbose,
                         progress_bar,
                     ).await {
-                        eprintln!("❌ Error processing {:?}: {}", mp3_file.path, e);
-                    }
+                        Ok(result) => result,
+                        Err(e) => {
+                            if is_out_of_space(&e) {
+                                out_of_space.store(true, Ordering::Relaxed);
+                            }
+                            StripResult::failed(&mp3_file.path, &e)
+                        }
+                    };
+                    result.collision = plan.as_ref().and_then(|plan| plan.collision(&mp3_file.path)).cloned();
+                    sink.add(result);
                 }
             }
         })
         .collect();
     
-    // Wait for all processing to complete
     join_all(futures).await;
+    let (results, summary) = sink.finish();
+    summary.print(&errors);
+    record_results(report, &results, &errors);
+    if !summary.roots.is_empty() {
+        report.set_roots(&summary.roots);
+    }
     
     if args.dry_run {
         println!("🔍 Dry run completed! No files were modified.");
@@ -259,5 +1656,881 @@ pub async fn bulk_strip_metadata(args: MetadataArgs) -> Result<()> {
         }
     }
     
-    Ok(())
-} 
\ No newline at end of file
+    Ok(results)
+}
+
+fn print_collisions(plan: &OutputPlan, roots: &ScanRoots) {
+    if plan.collisions().is_empty() {
+        return;
+    }
+    println!("⚠️  {} files share an output name with another file:", plan.collisions().len());
+    let relative = |path: &Path| roots.relative(path).display().to_string();
+    for (path, collision) in plan.collisions() {
+        match &collision.written_to {
+            Some(renamed) => println!("   {} → {}", relative(path), renamed.display()),
+            None => println!("   {} left out for {}", relative(path), relative(&collision.holder)),
+        }
+    }
+}
+
+fn record_results(report: &mut RunReport, results: &[StripResult], errors: &ErrorCollector) {
+    for result in results {
+        report.add_result(result);
+    }
+    report.set_errors(&errors.groups());
+}
+
+fn print_mp3_table(mp3_files: &[Mp3File], roots: &ScanRoots) {
+    let rows: Vec<Vec<String>> = mp3_files
+        .iter()
+        .map(|mp3_file| {
+            let relative = roots.relative(&mp3_file.path);
+            let status = if mp3_file.is_wrong_format() {
+                style(format!("{} content", mp3_file.format)).red().to_string()
+            } else if mp3_file.has_metadata {
+                style("tagged").yellow().to_string()
+            } else {
+                style("clean").green().to_string()
+            };
+            vec![
+                truncate_middle(&relative.to_string_lossy(), 60),
+                format_size(mp3_file.size),
+                status,
+            ]
+        })
+        .collect();
+    
+    print_table(
+        &[("PATH", Alignment::Left), ("SIZE", Alignment::Right), ("METADATA", Alignment::Left)],
+        &rows,
+    );
+}
+
+/// Most single-file warnings the summary lists, e.g. of wrong-format files
+const MAX_LISTED: usize = 50;
+
+type OnFinished<'a> = &'a (dyn Fn(usize, usize, &StripResult) + Sync);
+
+/// Where a strip run's results go as they are known: counted for the
+/// summary, handed to the caller's callback, then kept for the report and
+/// the caller, or with a streaming report written straight to its results
+/// file so memory stays flat
+struct StripSink<'a> {
+    log: Option<Arc<ResultLog>>,
+    errors: &'a ErrorCollector,
+    roots: &'a ScanRoots,
+    summary: Mutex<StripSummary>,
+    stream: ResultStream<StripResult, OnFinished<'a>>,
+}
+
+impl<'a> StripSink<'a> {
+    /// `paths` are those of every result to come, kept in `sort` order
+    fn new(
+        log: Option<Arc<ResultLog>>,
+        errors: &'a ErrorCollector,
+        roots: &'a ScanRoots,
+        mut paths: Vec<PathBuf>,
+        sort: SortOrder,
+        on_finished: OnFinished<'a>,
+    ) -> Self {
+        let summary = StripSummary { roots: if roots.is_multiple() { roots.counts() } else { Vec::new() }, ..StripSummary::default() };
+        let stream = if log.is_some() {
+            ResultStream::unordered(paths.len(), on_finished)
+        } else {
+            sort.sort_by_path(&mut paths, |path| path.as_path());
+            ResultStream::new(paths, on_finished)
+        };
+        StripSink { log, errors, roots, summary: Mutex::new(summary), stream }
+    }
+
+    fn add(&self, result: StripResult) {
+        let mut summary = self.summary.lock().unwrap_or_else(|e| e.into_inner());
+        summary.add(&result, self.errors);
+        if let Some(counts) = summary.roots.get_mut(self.roots.index_of(&result.path)) {
+            counts.add(result.status.root_outcome());
+        }
+        drop(summary);
+        if let Some(log) = &self.log {
+            log.record(&result);
+        }
+        let path = result.path.clone();
+        self.stream.finish(&path, result);
+    }
+
+    /// The kept results in sort order, none when streamed, and the summary
+    fn finish(self) -> (Vec<StripResult>, StripSummary) {
+        (self.stream.into_results(), self.summary.into_inner().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+/// What the end-of-run summary prints, counted one result at a time
+#[derive(Debug, Default)]
+struct StripSummary {
+    counts: HashMap<StripStatus, usize>,
+    bytes_saved: i64,
+    renamed: usize,
+    upgraded: usize,
+    grown: usize,
+    growth: u64,
+    deleted: usize,
+    trashed: usize,
+    kept: usize,
+    reflinked: usize,
+    /// Bytes reflinked copies didn't have to read and write
+    reflinked_bytes: u64,
+    /// Files by the root they were found under, when there are several
+    roots: Vec<RootCounts>,
+    /// Directories `--art-safety` found with no artwork but the embedded one
+    art_directories: HashSet<PathBuf>,
+    art_safety: Option<ArtSafety>,
+    /// Warnings about single files, up to [`MAX_LISTED`]
+    notes: Vec<String>,
+    unlisted: usize,
+}
+
+impl StripSummary {
+    /// Count `result`, recording a failure in `errors`
+    fn add(&mut self, result: &StripResult, errors: &ErrorCollector) {
+        *self.counts.entry(result.status).or_default() += 1;
+        self.bytes_saved += result.bytes_saved;
+        if result.collision.as_ref().is_some_and(|c| c.written_to.is_some()) {
+            self.renamed += 1;
+        }
+        if result.tag_upgrade.is_some() {
+            self.upgraded += 1;
+        }
+        if result.bytes_saved < 0 {
+            self.grown += 1;
+            self.growth += result.bytes_saved.unsigned_abs();
+        }
+        match result.source_removed {
+            Some(SourceRemoval::Delete) => self.deleted += 1,
+            Some(SourceRemoval::Trash) => self.trashed += 1,
+            None if result.source_kept.is_some() => self.kept += 1,
+            None => {}
+        }
+        if let Some(size) = result.reflinked {
+            self.reflinked += 1;
+            self.reflinked_bytes += size;
+        }
+        if let Some(hit) = &result.art_safety {
+            self.art_safety = Some(hit.action);
+            if self.art_directories.insert(hit.directory.clone()) && hit.action == ArtSafety::Warn {
+                let removes = if result.status == StripStatus::DryRun { "would remove" } else { "removed" };
+                self.note(format!("⚠️  {:?}: stripping {} its only artwork; no cover image sits beside the files", hit.directory, removes));
+            }
+        }
+        if let Some(copy) = &result.incomplete_copy {
+            self.note(format!("⚠️  {}", copy));
+        }
+        if result.status == StripStatus::WrongFormat {
+            let format = result.detected_format.unwrap_or_default();
+            self.note(format!("⚠️  {:?} is {}, not MPEG audio; left untouched (--force to modify anyway)", result.path, format));
+        }
+        if matches!(result.status, StripStatus::Failed | StripStatus::WriteVerificationFailed) {
+            errors.record(
+                result.path.parent().unwrap_or(Path::new("")),
+                &result.path,
+                result.error_kind.as_deref().unwrap_or("error"),
+                result.error.as_deref().unwrap_or("unknown error"),
+            );
+        }
+    }
+
+    fn note(&mut self, note: String) {
+        if self.notes.len() < MAX_LISTED {
+            self.notes.push(note);
+        } else {
+            self.unlisted += 1;
+        }
+    }
+
+    fn count(&self, status: StripStatus) -> usize {
+        self.counts.get(&status).copied().unwrap_or(0)
+    }
+
+    fn print(&self, errors: &ErrorCollector) {
+        let count = |status: StripStatus| self.count(status);
+        
+        println!("📊 Stripped: {}", style(count(StripStatus::Stripped)).green());
+        println!("📊 Copied unchanged: {}", count(StripStatus::CopiedUnchanged));
+        println!("📊 Skipped: {}", style(count(StripStatus::Skipped)).yellow());
+        if count(StripStatus::SkippedNoop) > 0 {
+            println!("📊 Skipped (nothing to change): {}", count(StripStatus::SkippedNoop));
+        }
+        if count(StripStatus::SkippedFiltered) > 0 {
+            println!("📊 Skipped (filtered): {}", count(StripStatus::SkippedFiltered));
+        }
+        if count(StripStatus::SkippedCollision) > 0 {
+            println!("📊 Skipped (output name taken): {}", style(count(StripStatus::SkippedCollision)).yellow());
+        }
+        if self.renamed > 0 {
+            println!("📊 Renamed (output name taken): {}", self.renamed);
+        }
+        if count(StripStatus::DryRun) > 0 {
+            println!("📊 Would process: {}", count(StripStatus::DryRun));
+        }
+        if self.upgraded > 0 {
+            println!("📊 Upgraded to ID3v2.4: {}", self.upgraded);
+        }
+        if count(StripStatus::WrongFormat) > 0 {
+            println!("📊 Wrong format: {}", style(count(StripStatus::WrongFormat)).yellow());
+        }
+        if count(StripStatus::Failed) > 0 {
+            println!("📊 Failed: {}", style(count(StripStatus::Failed)).red());
+        }
+        if count(StripStatus::WriteVerificationFailed) > 0 {
+            println!("📊 Write verification failed, restored: {}", style(count(StripStatus::WriteVerificationFailed)).red());
+        }
+        if count(StripStatus::SkippedOutOfSpace) > 0 {
+            println!("💥 Out of disk space: {} files not started", style(count(StripStatus::SkippedOutOfSpace)).red());
+        }
+        if self.deleted > 0 {
+            println!("🗑️  Originals deleted: {}", self.deleted);
+        }
+        if self.trashed > 0 {
+            println!("🗑️  Originals moved to the trash: {}", self.trashed);
+        }
+        if self.kept > 0 {
+            println!("⚠️  Originals kept because their copy couldn't be checked or they couldn't be removed: {}", style(self.kept).yellow());
+        }
+        if let Some(action) = self.art_safety {
+            let action = match action {
+                ArtSafety::Export => "front cover exported",
+                ArtSafety::Skip => "art kept",
+                ArtSafety::Warn | ArtSafety::Ignore => "warned",
+            };
+            println!("🖼️  Directories whose only artwork was embedded: {} ({})", style(self.art_directories.len()).yellow(), action);
+        }
+        println!("📊 Space saved: {}", format_size_signed(self.bytes_saved));
+        if self.reflinked > 0 {
+            println!("♻️  Reflinked copies: {} (about {} of I/O saved)", style(self.reflinked).green(), format_size(self.reflinked_bytes));
+        }
+        if self.grown > 0 {
+            println!("📊 Grown: {} (by {})", style(self.grown).yellow(), format_size(self.growth));
+        }
+        for counts in &self.roots {
+            println!("📂 {}", counts);
+        }
+        
+        for note in &self.notes {
+            println!("{}", note);
+        }
+        if self.unlisted > 0 {
+            println!("⚠️  ... and {} more warnings like these", self.unlisted);
+        }
+        errors.print_summary();
+    }
+}
+
+/// Why a hand-picked frame removal was rejected
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FrameEditError {
+    /// Selectors that matched no frame, with the labels of the frames the file has
+    UnknownFrames { unknown: Vec<String>, available: Vec<String> },
+    Failed { message: String },
+}
+
+impl From<anyhow::Error> for FrameEditError {
+    fn from(error: anyhow::Error) -> Self {
+        FrameEditError::Failed { message: format!("{:#}", error) }
+    }
+}
+
+/// Frame lists before and after removing hand-picked frames from one file
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FrameEdit {
+    pub path: PathBuf,
+    pub frames_before: Vec<String>,
+    pub frames_after: Vec<String>,
+    pub bytes_saved: i64,
+}
+
+/// Whether `selector` picks `frame`: either the frame's exact label
+/// (`ID (description)`, as reported in results) or any name accepted by
+/// `--remove-fields`
+fn selects_frame(selector: &str, frame: &Frame) -> bool {
+    if frame_label(frame) == selector {
+        return true;
+    }
+    if selector == "links" {
+        return frame.id().starts_with('W');
+    }
+    field_frame_ids(selector).iter().any(|id| id == frame.id())
+}
+
+/// Remove exactly the frames picked by `selectors` from one file. The file is
+/// rewritten through a temporary copy beside it and renamed into place, after
+/// saving the original to `<name>.bak` when `backup` is set.
+pub fn remove_frames(path: &Path, selectors: &[String], backup: bool, dry_run: bool) -> Result<FrameEdit, FrameEditError> {
+    let tag = match Tag::read_from_path(path) {
+        Ok(tag) => tag,
+        Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => Tag::new(),
+        Err(e) => return Err(anyhow::Error::new(e).context(format!("Failed to read tag from {:?}", path)).into()),
+    };
+    let frames_before: Vec<String> = tag.frames().map(frame_label).collect();
+
+    let unknown: Vec<String> = selectors
+        .iter()
+        .filter(|selector| !tag.frames().any(|frame| selects_frame(selector, frame)))
+        .cloned()
+        .collect();
+    if !unknown.is_empty() {
+        return Err(FrameEditError::UnknownFrames { unknown, available: frames_before });
+    }
+
+    let mut new_tag = Tag::new();
+    for frame in tag.frames().filter(|frame| !selectors.iter().any(|s| selects_frame(s, frame))) {
+        new_tag.add_frame(frame.clone());
+    }
+    let frames_after: Vec<String> = new_tag.frames().map(frame_label).collect();
+
+    let mut edit = FrameEdit {
+        path: path.to_path_buf(),
+        frames_before,
+        frames_after,
+        bytes_saved: 0,
+    };
+    if dry_run || selectors.is_empty() {
+        return Ok(edit);
+    }
+
+    let file_name = path.file_name().unwrap().to_string_lossy();
+    let partial = path.with_file_name(format!(".{}.partial", file_name));
+    let original_size = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata for {:?}", path))?
+        .len();
+
+    let written = fs::copy(path, &partial)
+        .with_context(|| format!("Failed to copy {:?} to {:?}", path, partial))
+        .and_then(|_| {
+            new_tag.write_to_path(&partial, id3::Version::Id3v24)
+                .with_context(|| format!("Failed to write metadata to {:?}", partial))
+        });
+    if let Err(e) = written {
+        let _ = fs::remove_file(&partial);
+        return Err(e.into());
+    }
+
+    if backup {
+        let backup_path = path.with_file_name(format!("{}.bak", file_name));
+        if let Err(e) = fs::copy(path, &backup_path) {
+            let _ = fs::remove_file(&partial);
+            return Err(anyhow::Error::new(e)
+                .context(format!("Failed to back up {:?} to {:?}", path, backup_path))
+                .into());
+        }
+    }
+    fs::rename(&partial, path)
+        .with_context(|| format!("Failed to replace {:?}", path))?;
+
+    let new_size = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata for {:?}", path))?
+        .len();
+    edit.bytes_saved = original_size as i64 - new_size as i64;
+    Ok(edit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disposal::RecordingDisposer;
+    use id3::frame::{Chapter, TableOfContents};
+
+    fn audio() -> Vec<u8> {
+        let mut frame = vec![0xFF, 0xFB, 0x90, 0x00];
+        frame.resize(417, 0x55);
+        frame.repeat(12)
+    }
+
+    /// An ID3v2.4 tag with a chapter and a table of contents, the audio and
+    /// an ID3v1 tag
+    fn podcast(path: &Path) -> Vec<u8> {
+        let mut tag = Tag::new();
+        tag.set_title("Episode");
+        tag.add_frame(Chapter {
+            element_id: "chp0".to_string(),
+            start_time: 0,
+            end_time: 1000,
+            start_offset: 0xffffffff,
+            end_offset: 0xffffffff,
+            frames: vec![Frame::text("TIT2", "Intro")],
+        });
+        tag.add_frame(TableOfContents {
+            element_id: "toc".to_string(),
+            top_level: true,
+            ordered: true,
+            elements: vec!["chp0".to_string()],
+            frames: Vec::new(),
+        });
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes, id3::Version::Id3v24).unwrap();
+        let audio = audio();
+        bytes.extend(&audio);
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        bytes.extend(id3v1);
+        fs::write(path, bytes).unwrap();
+        audio
+    }
+
+    #[test]
+    fn remove_all_tags_keeps_only_the_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episode.mp3");
+        let audio = podcast(&path);
+
+        remove_all_tags(&path).unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), audio);
+        let names: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|entry| entry.unwrap().file_name()).collect();
+        assert_eq!(names, vec!["episode.mp3"]);
+    }
+
+    #[test]
+    fn remove_all_tags_leaves_the_file_when_writing_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("episode.mp3");
+        podcast(&path);
+        let original = fs::read(&path).unwrap();
+        // A directory where the rewritten file would be written
+        fs::create_dir(dir.path().join(format!("{}episode.mp3.partial", STAGING_PREFIX))).unwrap();
+
+        assert!(remove_all_tags(&path).is_err());
+        assert_eq!(fs::read(&path).unwrap(), original);
+    }
+
+    /// An MP3 with an ID3v2.3 tag, which `--upgrade-tags` rewrites
+    fn tagged(path: &Path) -> Vec<u8> {
+        let mut tag = Tag::new();
+        tag.set_title("Song");
+        tag.set_artist("Band");
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes, id3::Version::Id3v23).unwrap();
+        bytes.extend(audio());
+        fs::write(path, &bytes).unwrap();
+        bytes
+    }
+
+    fn truncate(path: &Path) {
+        let file = fs::OpenOptions::new().write(true).open(path).unwrap();
+        file.set_len(file.metadata().unwrap().len() - 100).unwrap();
+    }
+
+    fn corrupt_tag(path: &Path) {
+        let mut bytes = fs::read(path).unwrap();
+        bytes[0] = b'X';
+        fs::write(path, bytes).unwrap();
+    }
+
+    /// Strip `path` with `--upgrade-tags`, `damage` applied to the written file
+    fn strip_damaged(path: &Path, output: Option<&Path>, damage: fn(&Path)) -> StripResult {
+        let rules = FrameRules::from_args(&MetadataArgs { upgrade_tags: true, ..MetadataArgs::default() }).unwrap();
+        let mp3_file = mp3_files_at(&[path.to_path_buf()]).unwrap().remove(0);
+        let write = |tags: &TagWrite, path: &Path| {
+            tags.write(path)?;
+            damage(path);
+            Ok(())
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        runtime.block_on(strip_file_with(&mp3_file, output, &rules, false, false, ProgressBar::hidden(), write)).unwrap()
+    }
+
+    #[test]
+    fn damaged_writes_are_restored_in_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        for damage in [truncate as fn(&Path), corrupt_tag] {
+            let original = tagged(&path);
+            let result = strip_damaged(&path, None, damage);
+            assert_eq!(result.status, StripStatus::WriteVerificationFailed);
+            assert!(result.error.unwrap().ends_with("the original was restored"));
+            assert_eq!(fs::read(&path).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn damaged_copies_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        let copy = dir.path().join("copy.mp3");
+        for damage in [truncate as fn(&Path), corrupt_tag] {
+            let original = tagged(&path);
+            let result = strip_damaged(&path, Some(&copy), damage);
+            assert_eq!(result.status, StripStatus::WriteVerificationFailed);
+            assert!(result.error.unwrap().ends_with("the copy was removed"));
+            assert!(!copy.exists());
+            assert_eq!(fs::read(&path).unwrap(), original);
+        }
+    }
+
+    #[test]
+    fn undamaged_writes_pass_the_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        tagged(&path);
+        let result = strip_damaged(&path, None, |_| {});
+        assert_eq!(result.status, StripStatus::Stripped);
+        assert_eq!(Tag::read_from_path(&path).unwrap().version(), id3::Version::Id3v24);
+    }
+
+    /// A WAV file with a `LIST INFO` chunk of `items` and a little audio
+    fn wav_with_info(path: &Path, items: &[(&str, &str)]) {
+        let mut info = b"INFO".to_vec();
+        for (id, value) in items {
+            info.extend_from_slice(id.as_bytes());
+            info.extend_from_slice(&(value.len() as u32 + 1).to_le_bytes());
+            info.extend_from_slice(value.as_bytes());
+            info.push(0);
+            if value.len() % 2 == 0 {
+                info.push(0);
+            }
+        }
+        let mut chunks = b"WAVEfmt \x10\0\0\0".to_vec();
+        chunks.extend_from_slice(&[1; 16]);
+        chunks.extend_from_slice(b"LIST");
+        chunks.extend_from_slice(&(info.len() as u32).to_le_bytes());
+        chunks.extend_from_slice(&info);
+        chunks.extend_from_slice(b"data\x04\0\0\0\x01\x02\x03\x04");
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend_from_slice(&(chunks.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&chunks);
+        fs::write(path, bytes).unwrap();
+    }
+
+    #[test]
+    fn info_items_follow_the_field_rules() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("field.wav");
+        wav_with_info(&path, &[("INAM", "Dawn chorus"), ("ICMT", "GPS 51.5,-0.1"), ("ISFT", "Recorder")]);
+        let items = read_info(&path).unwrap();
+        let removed = |args: MetadataArgs| plan_info(&items, &FrameRules::from_args(&args).unwrap());
+
+        assert_eq!(removed(MetadataArgs { keep_fields: Some("title".to_string()), ..MetadataArgs::default() }), ["ICMT", "ISFT"]);
+        assert_eq!(removed(MetadataArgs { remove_fields: Some("comment,ISFT".to_string()), ..MetadataArgs::default() }), ["ICMT", "ISFT"]);
+        assert_eq!(removed(MetadataArgs { remove_all: true, ..MetadataArgs::default() }), ["INAM", "ICMT", "ISFT"]);
+        assert!(removed(MetadataArgs::default()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn formats_pick_which_extensions_are_scanned() {
+        let dir = tempfile::tempdir().unwrap();
+        wav_with_info(&dir.path().join("field.wav"), &[("ICMT", "GPS")]);
+        fs::write(dir.path().join("take.AIF"), b"FORM\x04\0\0\0AIFF").unwrap();
+        fs::write(dir.path().join("song.mp3"), audio()).unwrap();
+        fs::write(dir.path().join("notes.txt"), b"notes").unwrap();
+        let root = dir.path();
+        let found = |formats: Vec<AudioFormat>| {
+            let walk = WalkOptions { formats, ..WalkOptions::default() };
+            async move {
+                let mut files = find_mp3_files(root, &walk, &mut ScanMonitor::new()).await.unwrap();
+                files.sort_by(|a, b| a.path.cmp(&b.path));
+                files.into_iter().map(|file| (file.path.file_name().unwrap().to_string_lossy().into_owned(), file.kind, file.has_metadata)).collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(found(vec![AudioFormat::Mp3]).await, [("song.mp3".to_string(), AudioFormat::Mp3, false)]);
+        assert_eq!(
+            found(vec![AudioFormat::Wav, AudioFormat::Aiff]).await,
+            [("field.wav".to_string(), AudioFormat::Wav, true), ("take.AIF".to_string(), AudioFormat::Aiff, false)]
+        );
+    }
+
+    #[tokio::test]
+    async fn skip_clean_passes_by_files_the_rules_leave_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        tagged(&dir.path().join("tagged.mp3"));
+        fs::write(dir.path().join("untagged.mp3"), audio()).unwrap();
+        let mut trailing = audio();
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        trailing.extend(id3v1);
+        fs::write(dir.path().join("trailing.mp3"), trailing).unwrap();
+        let files = find_mp3_files(dir.path(), &WalkOptions::default(), &mut ScanMonitor::new()).await.unwrap();
+        let noop = |args: MetadataArgs| {
+            let rules = FrameRules::from_args(&args).unwrap();
+            let mut names: Vec<String> = files
+                .iter()
+                .filter(|file| is_noop(file, &rules).unwrap())
+                .map(|file| file.path.file_name().unwrap().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            names
+        };
+
+        assert_eq!(noop(MetadataArgs { keep_fields: Some("title,artist".to_string()), ..MetadataArgs::default() }), ["tagged.mp3", "trailing.mp3", "untagged.mp3"]);
+        assert_eq!(noop(MetadataArgs { keep_fields: Some("title".to_string()), ..MetadataArgs::default() }), ["trailing.mp3", "untagged.mp3"]);
+        assert_eq!(noop(MetadataArgs { upgrade_tags: true, ..MetadataArgs::default() }), ["trailing.mp3", "untagged.mp3"]);
+        // Only --remove-all takes ID3v1 tags off
+        assert_eq!(noop(MetadataArgs { remove_all: true, ..MetadataArgs::default() }), ["untagged.mp3"]);
+    }
+
+    /// Strip `path` in place with the rules `args` give
+    async fn strip_with(path: &Path, args: MetadataArgs) -> StripResult {
+        let rules = FrameRules::from_args(&args).unwrap();
+        let mp3_file = mp3_files_at(&[path.to_path_buf()]).unwrap().remove(0);
+        strip_metadata_file(&mp3_file, None, &rules, false, false, ProgressBar::hidden()).await.unwrap()
+    }
+
+    #[test]
+    fn padding_takes_names_and_sizes() {
+        assert_eq!(parse_padding("none"), Ok(0));
+        assert_eq!(parse_padding(" Minimal "), Ok(MINIMAL_PADDING));
+        assert_eq!(parse_padding("default"), Ok(DEFAULT_PADDING));
+        assert_eq!(parse_padding("2K"), Ok(2048));
+        assert_eq!(parse_padding("100"), Ok(100));
+        assert!(parse_padding("lots").unwrap_err().starts_with("Expected none, minimal, default or a size"));
+    }
+
+    #[tokio::test]
+    async fn keeping_fields_of_a_minimal_file_does_not_grow_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        let mut tag = Tag::new();
+        tag.set_title("Song");
+        tag.set_artist("Band");
+        let mut bytes = Vec::new();
+        id3::Encoder::new().version(id3::Version::Id3v24).padding(0).encode(&tag, &mut bytes).unwrap();
+        bytes.extend(audio());
+        fs::write(&path, &bytes).unwrap();
+        let keep = || MetadataArgs { keep_fields: Some("title,artist".to_string()), ..MetadataArgs::default() };
+
+        let result = strip_with(&path, keep()).await;
+        assert_eq!(result.bytes_saved, 0);
+        assert_eq!(fs::metadata(&path).unwrap().len(), bytes.len() as u64);
+
+        let result = strip_with(&path, MetadataArgs { padding: DEFAULT_PADDING, ..keep() }).await;
+        assert_eq!(result.bytes_saved, -(DEFAULT_PADDING as i64));
+        assert_eq!(fs::metadata(&path).unwrap().len(), (bytes.len() + DEFAULT_PADDING) as u64);
+    }
+
+    #[tokio::test]
+    async fn protected_frames_survive_a_full_strip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        let mut tag = Tag::new();
+        tag.set_title("Song");
+        tag.set_artist("Band");
+        tag.add_frame(Frame::text("TCOP", "2024 Label"));
+        tag.add_frame(Frame::text("TSSE", "LAME 3.100"));
+        let mut bytes = Vec::new();
+        tag.write_to(&mut bytes, id3::Version::Id3v24).unwrap();
+        bytes.extend(audio());
+        let mut id3v1 = b"TAG".to_vec();
+        id3v1.resize(128, b' ');
+        bytes.extend(id3v1);
+        fs::write(&path, &bytes).unwrap();
+
+        let args = MetadataArgs { remove_all: true, protect: Some("copyright,encoder".to_string()), ..MetadataArgs::default() };
+        strip_with(&path, args).await;
+
+        let stripped = Tag::read_from_path(&path).unwrap();
+        let mut ids: Vec<&str> = stripped.frames().map(|frame| frame.id()).collect();
+        ids.sort();
+        assert_eq!(ids, ["TCOP", "TSSE"]);
+        assert_eq!(stripped.get("TCOP").and_then(|frame| frame.content().text()), Some("2024 Label"));
+        // The ID3v1 tag goes all the same
+        assert!(fs::read(&path).unwrap().ends_with(&audio()));
+    }
+
+    #[test]
+    fn protect_requires_remove_all() {
+        let args = MetadataArgs { protect: Some("copyright".to_string()), ..MetadataArgs::default() };
+        let error = FrameRules::from_args(&args).err().unwrap();
+        assert!(error.to_string().starts_with("--protect only applies with --remove-all"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn delete_source_goes_through_the_disposer() {
+        let dir = tempfile::tempdir().unwrap();
+        let (path, copy) = (dir.path().join("song.mp3"), dir.path().join("out/song.mp3"));
+        fs::create_dir(dir.path().join("out")).unwrap();
+        let args = MetadataArgs { remove_all: true, output: Some(dir.path().join("out")), delete_source: true, ..MetadataArgs::default() };
+        let strip = |disposer: Arc<RecordingDisposer>| {
+            let mut rules = FrameRules::from_args(&args).unwrap();
+            rules.disposer = disposer;
+            let mp3_file = mp3_files_at(std::slice::from_ref(&path)).unwrap().remove(0);
+            let copy = copy.clone();
+            async move { strip_metadata_file(&mp3_file, Some(&copy), &rules, false, false, ProgressBar::hidden()).await.unwrap() }
+        };
+
+        tagged(&path);
+        let disposer = Arc::new(RecordingDisposer::failing());
+        let result = strip(disposer.clone()).await;
+        assert_eq!(disposer.disposed(), [path.as_path()]);
+        assert!(result.source_kept.unwrap().contains("to the trash"));
+        assert!(path.exists());
+
+        let disposer = Arc::new(RecordingDisposer::default());
+        let result = strip(disposer.clone()).await;
+        assert_eq!(disposer.disposed(), [path.as_path()]);
+        assert_eq!(result.source_removed, Some(SourceRemoval::Delete));
+        assert!(!path.exists() && copy.exists());
+    }
+
+    #[tokio::test]
+    async fn output_copies_record_whether_they_were_reflinked() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        fs::write(&path, tagged(&path)).unwrap();
+        let original = fs::read(&path).unwrap();
+        // Whether this filesystem clones is the same for the copy below
+        let probe = crate::reflink::copy_file(&path, &dir.path().join("probe")).unwrap();
+
+        let rules = FrameRules::from_args(&MetadataArgs { remove_all: true, ..MetadataArgs::default() }).unwrap();
+        let mp3_file = mp3_files_at(std::slice::from_ref(&path)).unwrap().remove(0);
+        let output = dir.path().join("out/song.mp3");
+        let result = strip_metadata_file(&mp3_file, Some(&output), &rules, false, false, ProgressBar::hidden()).await.unwrap();
+        assert_eq!(result.reflinked, probe.then_some(mp3_file.size));
+        assert_eq!(fs::read(&path).unwrap(), original);
+        assert!(fs::read(&output).unwrap().len() < original.len());
+
+        let mut summary = StripSummary::default();
+        let errors = ErrorCollector::new(None).unwrap();
+        summary.add(&StripResult { reflinked: Some(1000), ..result.clone() }, &errors);
+        summary.add(&StripResult { reflinked: None, ..result }, &errors);
+        assert_eq!((summary.reflinked, summary.reflinked_bytes), (1, 1000));
+    }
+
+    #[tokio::test]
+    async fn a_misspelled_field_fails_the_run_before_any_file_is_touched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        fs::write(&path, tagged(&path)).unwrap();
+        let original = fs::read(&path).unwrap();
+
+        let args = MetadataArgs {
+            directories: vec![dir.path().to_path_buf()],
+            keep_fields: Some("titel,artist".to_string()),
+            ..MetadataArgs::default()
+        };
+        assert_eq!(args.check_fields().unwrap_err().suggestion.as_deref(), Some("title"));
+        let error = bulk_strip_metadata(args).await.unwrap_err();
+        assert!(error.downcast_ref::<InvalidFieldName>().is_some(), "{:#}", error);
+        assert_eq!(fs::read(&path).unwrap(), original);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn preserve_keeps_the_source_times_modes_and_owner() {
+        use std::fs::FileTimes;
+        use std::os::unix::fs::{MetadataExt, PermissionsExt};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let dir = tempfile::tempdir().unwrap();
+        let (source, output) = (dir.path().join("song.mp3"), dir.path().join("out.mp3"));
+        tagged(&source);
+        let modified = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        let file = fs::File::options().write(true).open(&source).unwrap();
+        file.set_times(FileTimes::new().set_modified(modified)).unwrap();
+        drop(file);
+        let root = unsafe { libc::geteuid() } == 0;
+        if root {
+            std::os::unix::fs::chown(&source, Some(4321), Some(4321)).unwrap();
+        }
+        fs::set_permissions(&source, fs::Permissions::from_mode(0o444)).unwrap();
+
+        let rules = FrameRules::from_args(&MetadataArgs { remove_all: true, preserve: vec![PreserveAttr::All], ..MetadataArgs::default() }).unwrap();
+        let mp3_file = mp3_files_at(std::slice::from_ref(&source)).unwrap().remove(0);
+        let result = strip_metadata_file(&mp3_file, Some(&output), &rules, false, false, ProgressBar::hidden()).await.unwrap();
+        assert_eq!(result.status, StripStatus::Stripped);
+
+        let metadata = fs::metadata(&output).unwrap();
+        assert_eq!(metadata.permissions().mode() & 0o7777, 0o444);
+        assert_eq!(metadata.modified().unwrap(), modified);
+        if root {
+            assert_eq!((metadata.uid(), metadata.gid()), (4321, 4321));
+        }
+        assert_ne!(&fs::read(&output).unwrap()[..3], b"ID3");
+    }
+
+    #[test]
+    fn id3v2_headers_give_version_and_synchsafe_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let header = |name: &str, bytes: &[u8]| {
+            let path = dir.path().join(name);
+            fs::write(&path, [bytes, &audio()].concat()).unwrap();
+            read_id3v2_header(&path).unwrap()
+        };
+
+        // Each size byte holds 7 bits: 2 * 128 + 1
+        let v23 = header("v23.mp3", b"ID3\x03\x00\x00\x00\x00\x02\x01").unwrap();
+        assert_eq!((v23.version(), v23.size), ("ID3v2.3".to_string(), 257));
+        let v24 = header("v24.mp3", b"ID3\x04\x00\x10\x01\x7f\x7f\x7f").unwrap();
+        assert_eq!((v24.version(), v24.size), ("ID3v2.4".to_string(), (1 << 21) | (0x7f << 14) | (0x7f << 7) | 0x7f));
+        let v22 = header("v22.mp3", b"ID3\x02\x00\x00\x00\x00\x00\x40").unwrap();
+        assert_eq!((v22.version(), v22.size), ("ID3v2.2".to_string(), 64));
+
+        // A size byte with its top bit set isn't synchsafe, so it isn't a header
+        assert!(header("unsafe.mp3", b"ID3\x04\x00\x00\x00\x00\x80\x00").is_none());
+        assert!(header("bare.mp3", b"").is_none());
+        let short = dir.path().join("short.mp3");
+        fs::write(&short, b"ID3\x04").unwrap();
+        assert!(read_id3v2_header(&short).unwrap().is_none());
+    }
+
+    #[test]
+    fn scans_record_tag_version_and_declared_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut paths = Vec::new();
+        for (name, version) in [("v23.mp3", id3::Version::Id3v23), ("v24.mp3", id3::Version::Id3v24)] {
+            let mut tag = Tag::new();
+            tag.set_title("Song");
+            let mut bytes = Vec::new();
+            id3::Encoder::new().version(version).padding(1000).encode(&tag, &mut bytes).unwrap();
+            bytes.extend(audio());
+            fs::write(dir.path().join(name), bytes).unwrap();
+            paths.push(dir.path().join(name));
+        }
+        fs::write(dir.path().join("none.mp3"), audio()).unwrap();
+        paths.push(dir.path().join("none.mp3"));
+
+        let files = mp3_files_at(&paths).unwrap();
+        let recorded: Vec<(Option<&str>, u64)> = files.iter().map(|f| (f.tag_version.as_deref(), f.tag_size_bytes)).collect();
+        // A title frame of 10 header bytes, an encoding byte and "Song" as
+        // UTF-16 with a byte order mark, then the padding
+        let v23_size = 10 + 1 + 2 + 8 + 1000;
+        assert_eq!(recorded[0], (Some("ID3v2.3"), v23_size));
+        assert_eq!(recorded[1].0, Some("ID3v2.4"));
+        assert!(recorded[1].1 >= 1000, "{:?}", recorded[1]);
+        assert_eq!(recorded[2], (None, 0));
+    }
+
+    #[tokio::test]
+    async fn filtered_clean_and_wrong_format_files_each_have_their_own_status() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("skip")).unwrap();
+        tagged(&dir.path().join("song.mp3"));
+        tagged(&dir.path().join("skip/song.mp3"));
+        fs::write(dir.path().join("clean.mp3"), audio()).unwrap();
+        // Tagged, so not clean, but an MP4 behind the tag
+        let mut video = tagged(&dir.path().join("video.mp3"));
+        let tag_end = video.len() - audio().len();
+        video.truncate(tag_end);
+        video.extend(b"\0\0\0\x20ftypM4A \0\0\0\0");
+        video.resize(tag_end + 5000, 0);
+        fs::write(dir.path().join("video.mp3"), video).unwrap();
+
+        let results = bulk_strip_metadata(MetadataArgs {
+            directories: vec![dir.path().to_path_buf()],
+            remove_all: true,
+            skip_clean: true,
+            exclude: vec!["skip/*".to_string()],
+            ..MetadataArgs::default()
+        })
+        .await
+        .unwrap();
+        let mut statuses: Vec<(PathBuf, StripStatus)> = results
+            .into_iter()
+            .map(|result| (result.path.strip_prefix(dir.path()).unwrap().to_path_buf(), result.status))
+            .collect();
+        statuses.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(statuses, [
+            (PathBuf::from("clean.mp3"), StripStatus::SkippedNoop),
+            (PathBuf::from("skip/song.mp3"), StripStatus::SkippedFiltered),
+            (PathBuf::from("song.mp3"), StripStatus::Stripped),
+            (PathBuf::from("video.mp3"), StripStatus::WrongFormat),
+        ]);
+    }
+}
@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Live counters of the running extraction, shared by the workers and
+/// `--serve-status`. Workers only touch relaxed atomics; `begin` resets
+/// everything for the next operation.
+#[derive(Default, Clone)]
+pub struct OperationMetrics(Arc<Counters>);
+
+#[derive(Default)]
+struct Counters {
+    operation_id: AtomicU64,
+    started: Mutex<Option<Instant>>,
+    archives_total: AtomicU64,
+    archives_done: AtomicU64,
+    bytes_total: AtomicU64,
+    bytes_written: AtomicU64,
+    files_written: AtomicU64,
+}
+
+/// Point-in-time view of `OperationMetrics` with the derived rates
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MetricsSnapshot {
+    pub operation_id: u64,
+    pub archives_done: u64,
+    pub archives_total: u64,
+    pub bytes_written: u64,
+    pub bytes_total: u64,
+    pub files_written: u64,
+    pub elapsed_ms: u64,
+    /// Average since the operation started
+    pub bytes_per_sec: f64,
+    pub files_per_sec: f64,
+    /// Time left at the current throughput, once anything has been written
+    pub eta_ms: Option<u64>,
+}
+
+impl OperationMetrics {
+    /// Reset the counters for a new operation and return its id
+    pub fn begin(&self, archives_total: u64, bytes_total: u64) -> u64 {
+        let counters = &self.0;
+        *counters.started.lock().unwrap() = Some(Instant::now());
+        counters.archives_total.store(archives_total, Ordering::Relaxed);
+        counters.archives_done.store(0, Ordering::Relaxed);
+        counters.bytes_total.store(bytes_total, Ordering::Relaxed);
+        counters.bytes_written.store(0, Ordering::Relaxed);
+        counters.files_written.store(0, Ordering::Relaxed);
+        counters.operation_id.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    pub fn add_bytes(&self, bytes: u64) {
+        self.0.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn file_done(&self) {
+        self.0.files_written.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count the files of an archive at once, where they aren't seen one by one
+    pub fn add_files(&self, files: u64) {
+        self.0.files_written.fetch_add(files, Ordering::Relaxed);
+    }
+
+    pub fn archive_done(&self) {
+        self.0.archives_done.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn operation_id(&self) -> u64 {
+        self.0.operation_id.load(Ordering::Relaxed)
+    }
+
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        let counters = &self.0;
+        let elapsed = counters.started.lock().unwrap().map_or(Duration::ZERO, |started| started.elapsed());
+        let snapshot = MetricsSnapshot {
+            operation_id: counters.operation_id.load(Ordering::Relaxed),
+            archives_done: counters.archives_done.load(Ordering::Relaxed),
+            archives_total: counters.archives_total.load(Ordering::Relaxed),
+            bytes_written: counters.bytes_written.load(Ordering::Relaxed),
+            bytes_total: counters.bytes_total.load(Ordering::Relaxed),
+            files_written: counters.files_written.load(Ordering::Relaxed),
+            elapsed_ms: elapsed.as_millis() as u64,
+            ..MetricsSnapshot::default()
+        };
+        snapshot.with_rates(elapsed)
+    }
+}
+
+impl MetricsSnapshot {
+    fn with_rates(mut self, elapsed: Duration) -> Self {
+        let seconds = elapsed.as_secs_f64();
+        if seconds > 0.0 {
+            self.bytes_per_sec = self.bytes_written as f64 / seconds;
+            self.files_per_sec = self.files_written as f64 / seconds;
+        }
+        if self.bytes_per_sec > 0.0 {
+            let remaining = self.bytes_total.saturating_sub(self.bytes_written);
+            self.eta_ms = Some((remaining as f64 / self.bytes_per_sec * 1000.0) as u64);
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn workers_updating_at_once_lose_nothing() {
+        let metrics = OperationMetrics::default();
+        let id = metrics.begin(4, 4000);
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                let metrics = metrics.clone();
+                scope.spawn(move || {
+                    for _ in 0..10 {
+                        metrics.add_bytes(100);
+                        metrics.file_done();
+                    }
+                    metrics.archive_done();
+                });
+            }
+        });
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.operation_id, id);
+        assert_eq!((snapshot.archives_done, snapshot.archives_total), (4, 4));
+        assert_eq!((snapshot.bytes_written, snapshot.bytes_total), (4000, 4000));
+        assert_eq!(snapshot.files_written, 40);
+        assert!(snapshot.bytes_per_sec > 0.0 && snapshot.files_per_sec > 0.0);
+        assert_eq!(snapshot.eta_ms, Some(0));
+    }
+
+    #[test]
+    fn rates_and_eta_follow_the_average_throughput() {
+        let snapshot = MetricsSnapshot { bytes_written: 50_000_000, bytes_total: 150_000_000, files_written: 100, ..MetricsSnapshot::default() }
+            .with_rates(Duration::from_secs(10));
+
+        assert_eq!(snapshot.bytes_per_sec, 5_000_000.0);
+        assert_eq!(snapshot.files_per_sec, 10.0);
+        assert_eq!(snapshot.eta_ms, Some(20_000));
+    }
+
+    #[test]
+    fn no_eta_before_anything_is_written() {
+        let snapshot = MetricsSnapshot { bytes_total: 1000, ..MetricsSnapshot::default() }.with_rates(Duration::from_secs(3));
+        assert_eq!((snapshot.bytes_per_sec, snapshot.eta_ms), (0.0, None));
+        let snapshot = MetricsSnapshot { bytes_written: 10, bytes_total: 1000, ..MetricsSnapshot::default() }.with_rates(Duration::ZERO);
+        assert_eq!((snapshot.bytes_per_sec, snapshot.eta_ms), (0.0, None));
+    }
+
+    #[test]
+    fn begin_starts_the_next_operation_from_zero() {
+        let metrics = OperationMetrics::default();
+        assert_eq!(metrics.snapshot().elapsed_ms, 0);
+        let first = metrics.begin(2, 100);
+        metrics.add_bytes(100);
+        metrics.add_files(3);
+        metrics.archive_done();
+
+        let second = metrics.begin(5, 500);
+
+        assert_eq!(second, first + 1);
+        assert_eq!(metrics.operation_id(), second);
+        let snapshot = metrics.snapshot();
+        assert_eq!((snapshot.archives_done, snapshot.archives_total), (0, 5));
+        assert_eq!((snapshot.bytes_written, snapshot.bytes_total, snapshot.files_written), (0, 500, 0));
+    }
+}
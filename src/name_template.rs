@@ -0,0 +1,169 @@
+use anyhow::{bail, Context, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Literal(String),
+    Stem,
+    Name,
+    Parent,
+    Date(String),
+    Size,
+}
+
+/// Output directory name pattern such as `{parent}-{stem}` or `{stem} [{date:%Y-%m-%d}]`
+#[derive(Debug, Clone, Serialize)]
+#[serde(into = "String")]
+pub struct NameTemplate {
+    segments: Vec<Segment>,
+    /// The template as written, for reports
+    source: String,
+}
+
+impl From<NameTemplate> for String {
+    fn from(template: NameTemplate) -> String {
+        template.source
+    }
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut rest = template;
+
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                segments.push(Segment::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .with_context(|| format!("Unclosed placeholder in name template {:?}", template))?;
+            let placeholder = &rest[open + 1..open + close];
+            segments.push(match placeholder.split_once(':') {
+                None if placeholder == "stem" => Segment::Stem,
+                None if placeholder == "name" => Segment::Name,
+                None if placeholder == "parent" => Segment::Parent,
+                None if placeholder == "size" => Segment::Size,
+                None if placeholder == "date" => Segment::Date("%Y-%m-%d".to_string()),
+                Some(("date", format)) => {
+                    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+                        bail!("Invalid date format {:?} in name template placeholder {{{}}}", format, placeholder);
+                    }
+                    Segment::Date(format.to_string())
+                }
+                _ => bail!(
+                    "Unknown placeholder {{{}}} in name template; expected {{stem}}, {{name}}, {{parent}}, {{date:FORMAT}} or {{size}}",
+                    placeholder
+                ),
+            });
+            rest = &rest[open + close + 1..];
+        }
+        if rest.contains('}') {
+            bail!("Unmatched '}}' in name template {:?}", template);
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+
+        Ok(NameTemplate { segments, source: template.to_string() })
+    }
+
+    /// Render the directory name for one archive, sanitized for use as a path component
+    pub fn render(&self, zip_path: &Path) -> Result<String> {
+        let metadata = fs::metadata(zip_path)
+            .with_context(|| format!("Failed to read metadata for {:?}", zip_path))?;
+        let lossy = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+
+        let mut name = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => name.push_str(text),
+                Segment::Stem => name.push_str(&lossy(zip_path.file_stem())),
+                Segment::Name => name.push_str(&lossy(zip_path.file_name())),
+                Segment::Parent => name.push_str(&lossy(zip_path.parent().and_then(Path::file_name))),
+                Segment::Size => name.push_str(&metadata.len().to_string()),
+                Segment::Date(format) => {
+                    let modified: DateTime<Local> = metadata.modified()?.into();
+                    name.push_str(&modified.format(format).to_string());
+                }
+            }
+        }
+
+        Ok(sanitize(&name))
+    }
+}
+
+/// Replace characters that are invalid in file names on common filesystems
+fn sanitize(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let trimmed = cleaned.trim().trim_end_matches('.');
+    match trimmed {
+        "" | "." | ".." => "_".to_string(),
+        _ => trimmed.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, SystemTime};
+
+    /// `photos/trip.zip` holding 5 bytes, modified at noon UTC on 2024-03-15
+    fn archive(dir: &Path) -> std::path::PathBuf {
+        let path = dir.join("photos/trip.zip");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"12345").unwrap();
+        let noon = SystemTime::UNIX_EPOCH + Duration::from_secs(1_710_504_000);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(noon).unwrap();
+        path
+    }
+
+    fn render(template: &str, path: &Path) -> String {
+        NameTemplate::parse(template).unwrap().render(path).unwrap()
+    }
+
+    #[test]
+    fn placeholders() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive(dir.path());
+
+        assert_eq!(render("{stem}", &path), "trip");
+        assert_eq!(render("{name}", &path), "trip.zip");
+        assert_eq!(render("{parent}-{stem}", &path), "photos-trip");
+        assert_eq!(render("{stem} ({size} bytes)", &path), "trip (5 bytes)");
+        assert_eq!(render("{stem} [{date}]", &path), "trip [2024-03-15]");
+        assert_eq!(render("{date:%Y}/{date:%m}", &path), "2024_03");
+    }
+
+    #[test]
+    fn rendered_names_are_safe_path_components() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = archive(dir.path());
+
+        assert_eq!(render("a:b*c?{stem}", &path), "a_b_c_trip");
+        assert_eq!(render("  {stem}...", &path), "trip");
+        assert_eq!(render("..", &path), "_");
+        assert_eq!(sanitize("tab\there"), "tab_here");
+    }
+
+    #[test]
+    fn invalid_templates_name_the_bad_placeholder() {
+        let error = |template: &str| NameTemplate::parse(template).unwrap_err().to_string();
+
+        assert!(error("{stem}-{author}").contains("Unknown placeholder {author}"));
+        assert!(error("{stem").contains("Unclosed placeholder"));
+        assert!(error("stem}").contains("Unmatched '}'"));
+        assert!(error("{date:%Q}").contains("Invalid date format \"%Q\""));
+    }
+}
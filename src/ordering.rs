@@ -0,0 +1,107 @@
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::iter::Peekable;
+use std::path::Path;
+use std::str::Chars;
+
+/// How discovered archives and MP3s are ordered, and so the order results are
+/// reported in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Byte-wise by path, the same on every platform
+    #[default]
+    Path,
+    /// Numbers compared by value and letters case-insensitively, so `track2`
+    /// precedes `track10`
+    Natural,
+}
+
+impl SortOrder {
+    pub fn compare(self, a: &Path, b: &Path) -> Ordering {
+        match self {
+            SortOrder::Path => a.as_os_str().as_encoded_bytes().cmp(b.as_os_str().as_encoded_bytes()),
+            SortOrder::Natural => natural_cmp(&a.to_string_lossy(), &b.to_string_lossy()),
+        }
+    }
+
+    /// Sort `items` by the path `key` returns. The sort is stable, so items
+    /// with the same path keep their relative order.
+    pub fn sort_by_path<T>(self, items: &mut [T], key: impl Fn(&T) -> &Path) {
+        items.sort_by(|a, b| self.compare(key(a), key(b)));
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Human-friendly ordering: runs of digits compare by numeric value (leading
+/// zeros ignored), everything else case-insensitively. Strings that only
+/// differ in case or leading zeros fall back to byte order, so the ordering
+/// is total.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a_chars, mut b_chars) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        let ordering = match (a_chars.peek().copied(), b_chars.peek().copied()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_digits(&mut a_chars), take_digits(&mut b_chars));
+                let (x, y) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                x.len().cmp(&y.len()).then_with(|| x.cmp(y))
+            }
+            (Some(x), Some(y)) => {
+                a_chars.next();
+                b_chars.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn numbers_compare_by_value() {
+        assert_eq!(natural_cmp("track2", "track10"), Ordering::Less);
+        assert_eq!(natural_cmp("track10", "track9"), Ordering::Greater);
+        assert_eq!(natural_cmp("disc 1/track 3", "disc 1/track 12"), Ordering::Less);
+        assert_eq!(natural_cmp("v1.10", "v1.9"), Ordering::Greater);
+        assert_eq!(natural_cmp("track", "track1"), Ordering::Less);
+    }
+
+    #[test]
+    fn case_and_leading_zeros_only_break_ties() {
+        assert_eq!(natural_cmp("Beta", "alpha"), Ordering::Greater);
+        assert_eq!(natural_cmp("track007", "track8"), Ordering::Less);
+        // Equal up to case or zeros, still a total order
+        assert_eq!(natural_cmp("Track1", "track1"), Ordering::Less);
+        assert_eq!(natural_cmp("track01", "track1"), Ordering::Less);
+        assert_eq!(natural_cmp("track1", "track1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn sort_by_path_orders_items() {
+        let names = ["track10.mp3", "Track2.mp3", "track1.mp3", "album/track3.mp3"];
+        let sorted = |order: SortOrder| {
+            let mut items: Vec<(PathBuf, usize)> = names.iter().enumerate().map(|(i, name)| (PathBuf::from(name), i)).collect();
+            order.sort_by_path(&mut items, |(path, _)| path);
+            items.into_iter().map(|(_, i)| names[i]).collect::<Vec<_>>()
+        };
+
+        assert_eq!(sorted(SortOrder::Path), ["Track2.mp3", "album/track3.mp3", "track1.mp3", "track10.mp3"]);
+        assert_eq!(sorted(SortOrder::Natural), ["album/track3.mp3", "track1.mp3", "Track2.mp3", "track10.mp3"]);
+    }
+}
@@ -0,0 +1,140 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+/// Extraction directories being written right now, lowercased so paths that
+/// differ only in case count as the same directory
+static IN_FLIGHT: LazyLock<Mutex<HashSet<PathBuf>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// How often a waiting archive checks whether its directory is free
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+/// What an archive does when another archive is extracting into the same directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum BusyPolicy {
+    /// Wait for the other archive to finish, then extract into the same directory
+    #[default]
+    Wait,
+    /// Extract into `<name> (2)`, `<name> (3)`, ... instead
+    Rename,
+    /// Fail with [`OutputBusy`]
+    Fail,
+}
+
+/// Another archive is extracting into the directory
+#[derive(Debug)]
+pub struct OutputBusy {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for OutputBusy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Output directory {:?} is being written by another archive", self.path)
+    }
+}
+
+impl std::error::Error for OutputBusy {}
+
+fn key(path: &Path) -> PathBuf {
+    PathBuf::from(path.to_string_lossy().to_lowercase())
+}
+
+/// Exclusive use of an extraction directory, released when dropped
+#[derive(Debug)]
+pub struct OutputLock {
+    path: PathBuf,
+}
+
+impl OutputLock {
+    fn try_acquire(path: &Path) -> Option<OutputLock> {
+        let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+        in_flight.insert(key(path)).then(|| OutputLock { path: path.to_path_buf() })
+    }
+
+    /// Claim `extract_dir`, or deal with it being taken according to `policy`.
+    /// The lock's [`path`](OutputLock::path) is where to extract, which differs
+    /// from `extract_dir` when renamed.
+    pub async fn acquire(extract_dir: &Path, policy: BusyPolicy) -> Result<OutputLock> {
+        if let Some(lock) = Self::try_acquire(extract_dir) {
+            return Ok(lock);
+        }
+        match policy {
+            BusyPolicy::Wait => loop {
+                tokio::time::sleep(WAIT_INTERVAL).await;
+                if let Some(lock) = Self::try_acquire(extract_dir) {
+                    return Ok(lock);
+                }
+            },
+            BusyPolicy::Rename => {
+                let name = extract_dir.file_name().unwrap_or_default().to_string_lossy();
+                let mut n = 2;
+                loop {
+                    let candidate = extract_dir.with_file_name(format!("{} ({})", name, n));
+                    if let Some(lock) = Self::try_acquire(&candidate) {
+                        return Ok(lock);
+                    }
+                    n += 1;
+                }
+            }
+            BusyPolicy::Fail => Err(OutputBusy { path: extract_dir.to_path_buf() }.into()),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for OutputLock {
+    fn drop(&mut self) {
+        IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner()).remove(&key(&self.path));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn a_second_archive_waits_until_the_first_is_done() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("album");
+        let first = OutputLock::acquire(&target, BusyPolicy::Wait).await.unwrap();
+
+        let waiting = target.clone();
+        let second = tokio::spawn(async move { OutputLock::acquire(&waiting, BusyPolicy::Wait).await.unwrap() });
+        tokio::time::sleep(WAIT_INTERVAL * 4).await;
+        assert!(!second.is_finished());
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(5), second).await.unwrap().unwrap();
+        assert_eq!(second.path(), target);
+    }
+
+    #[tokio::test]
+    async fn busy_directories_are_renamed_or_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let target = dir.path().join("album");
+        let _first = OutputLock::acquire(&target, BusyPolicy::Wait).await.unwrap();
+
+        // Differing only in case is the same directory on some filesystems
+        let upper = dir.path().join("ALBUM");
+        let second = OutputLock::acquire(&upper, BusyPolicy::Rename).await.unwrap();
+        assert_eq!(second.path(), dir.path().join("ALBUM (2)"));
+        let third = OutputLock::acquire(&target, BusyPolicy::Rename).await.unwrap();
+        assert_eq!(third.path(), dir.path().join("album (3)"));
+
+        let refused = OutputLock::acquire(&target, BusyPolicy::Fail).await.unwrap_err();
+        assert_eq!(refused.downcast_ref::<OutputBusy>().unwrap().path, target);
+
+        // Released when dropped
+        drop(second);
+        let again = OutputLock::acquire(&dir.path().join("album (2)"), BusyPolicy::Fail).await.unwrap();
+        assert_eq!(again.path(), dir.path().join("album (2)"));
+    }
+}
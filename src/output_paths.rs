@@ -0,0 +1,185 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// What files of a strip batch do when they'd be copied to the same path
+/// under the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CollisionPolicy {
+    /// Copy the later files to `<name> (2).mp3`, `<name> (3).mp3`, ...
+    #[default]
+    Rename,
+    /// Copy only the first file, in processing order
+    Skip,
+    /// Copy only the last file, as copying them all in turn would leave
+    Overwrite,
+}
+
+/// A file whose output path another file of the batch also wants
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct OutputCollision {
+    /// Where the file would have been copied
+    pub wanted: PathBuf,
+    /// The file copied there instead, when this one was renamed or left out
+    pub holder: PathBuf,
+    /// Where the file is copied instead; `None` when it's left out
+    pub written_to: Option<PathBuf>,
+}
+
+/// The output path of every file in a batch, reserved before anything is
+/// copied so no file silently replaces another
+#[derive(Debug, Default)]
+pub struct OutputPlan {
+    targets: HashMap<PathBuf, PathBuf>,
+    collisions: Vec<(PathBuf, OutputCollision)>,
+}
+
+impl OutputPlan {
+    /// Give each of `sources`, in processing order, a path under
+    /// `output_dir` named after it. Names are compared case-insensitively,
+    /// as on Windows and macOS filesystems.
+    pub fn reserve<'a>(sources: impl IntoIterator<Item = &'a Path>, output_dir: &Path, policy: CollisionPolicy) -> Self {
+        let mut groups: Vec<(PathBuf, Vec<&Path>)> = Vec::new();
+        let mut group_of: HashMap<String, usize> = HashMap::new();
+        for source in sources {
+            let wanted = output_dir.join(source.file_name().unwrap_or_default());
+            let index = *group_of.entry(key(&wanted)).or_insert_with(|| {
+                groups.push((wanted, Vec::new()));
+                groups.len() - 1
+            });
+            groups[index].1.push(source);
+        }
+
+        let mut plan = OutputPlan::default();
+        let mut taken: HashSet<String> = group_of.into_keys().collect();
+        for (wanted, claimants) in groups {
+            let holder = match policy {
+                CollisionPolicy::Overwrite => claimants[claimants.len() - 1],
+                CollisionPolicy::Rename | CollisionPolicy::Skip => claimants[0],
+            };
+            plan.targets.insert(holder.to_path_buf(), wanted.clone());
+            for &source in claimants.iter().filter(|&&source| source != holder) {
+                let written_to = (policy == CollisionPolicy::Rename).then(|| {
+                    let renamed = free_name(&wanted, &taken);
+                    taken.insert(key(&renamed));
+                    plan.targets.insert(source.to_path_buf(), renamed.clone());
+                    renamed
+                });
+                let collision = OutputCollision { wanted: wanted.clone(), holder: holder.to_path_buf(), written_to };
+                plan.collisions.push((source.to_path_buf(), collision));
+            }
+        }
+        plan
+    }
+
+    /// Where `source` is copied; `None` when a collision leaves it out
+    pub fn target(&self, source: &Path) -> Option<&Path> {
+        self.targets.get(source).map(PathBuf::as_path)
+    }
+
+    pub fn collision(&self, source: &Path) -> Option<&OutputCollision> {
+        self.collisions.iter().find(|(path, _)| path == source).map(|(_, collision)| collision)
+    }
+
+    /// Every file renamed or left out, in processing order
+    pub fn collisions(&self) -> &[(PathBuf, OutputCollision)] {
+        &self.collisions
+    }
+}
+
+fn key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// `<stem> (n).<ext>` beside `wanted`, for the lowest n from 2 not taken
+fn free_name(wanted: &Path, taken: &HashSet<String>) -> PathBuf {
+    let stem = wanted.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = wanted.extension().map(|ext| format!(".{}", ext.to_string_lossy())).unwrap_or_default();
+    (2..)
+        .map(|n| wanted.with_file_name(format!("{} ({}){}", stem, n, extension)))
+        .find(|candidate| !taken.contains(&key(candidate)))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(names: &[&str]) -> Vec<PathBuf> {
+        names.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn later_files_are_renamed_skipped_or_win() {
+        let sources = paths(&["a/Song.mp3", "b/song.mp3", "c/other.mp3", "d/SONG.mp3"]);
+        let out = Path::new("out");
+        let plan = |policy| OutputPlan::reserve(sources.iter().map(PathBuf::as_path), out, policy);
+
+        let renamed = plan(CollisionPolicy::Rename);
+        let targets: Vec<_> = sources.iter().map(|source| renamed.target(source).unwrap().to_path_buf()).collect();
+        assert_eq!(targets, paths(&["out/Song.mp3", "out/Song (2).mp3", "out/other.mp3", "out/Song (3).mp3"]));
+        assert_eq!(
+            renamed.collision(Path::new("b/song.mp3")),
+            Some(&OutputCollision { wanted: "out/Song.mp3".into(), holder: "a/Song.mp3".into(), written_to: Some("out/Song (2).mp3".into()) })
+        );
+        assert_eq!(renamed.collisions().len(), 2);
+
+        let skipped = plan(CollisionPolicy::Skip);
+        assert_eq!(skipped.target(Path::new("a/Song.mp3")), Some(Path::new("out/Song.mp3")));
+        assert_eq!(skipped.target(Path::new("b/song.mp3")), None);
+        assert_eq!(skipped.collision(Path::new("d/SONG.mp3")).unwrap().written_to, None);
+
+        let overwritten = plan(CollisionPolicy::Overwrite);
+        assert_eq!(overwritten.target(Path::new("d/SONG.mp3")), Some(Path::new("out/Song.mp3")));
+        assert_eq!(overwritten.target(Path::new("a/Song.mp3")), None);
+        assert_eq!(overwritten.collision(Path::new("a/Song.mp3")).unwrap().holder, Path::new("d/SONG.mp3"));
+        assert!(overwritten.collision(Path::new("c/other.mp3")).is_none());
+    }
+
+    #[test]
+    fn renames_step_around_names_already_in_the_batch() {
+        let sources = paths(&["a/x.mp3", "b/x.mp3", "c/x (2).mp3"]);
+        let plan = OutputPlan::reserve(sources.iter().map(PathBuf::as_path), Path::new("out"), CollisionPolicy::Rename);
+        assert_eq!(plan.target(Path::new("b/x.mp3")), Some(Path::new("out/x (3).mp3")));
+        assert_eq!(plan.target(Path::new("c/x (2).mp3")), Some(Path::new("out/x (2).mp3")));
+    }
+
+    /// Generated batches full of same-name files, checked against what
+    /// every policy promises
+    #[test]
+    fn generated_collisions_never_share_a_target() {
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut next = |bound: usize| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % bound as u64) as usize
+        };
+        let names = ["a.mp3", "A.MP3", "a (2).mp3", "b.mp3", "a", "a (3).mp3", "b (2).MP3"];
+        for _ in 0..500 {
+            let sources: Vec<PathBuf> = (0..1 + next(12)).map(|i| PathBuf::from(format!("{}/{}", i, names[next(names.len())]))).collect();
+            for policy in [CollisionPolicy::Rename, CollisionPolicy::Skip, CollisionPolicy::Overwrite] {
+                let plan = OutputPlan::reserve(sources.iter().map(PathBuf::as_path), Path::new("out"), policy);
+
+                let mut targets = HashSet::new();
+                for source in &sources {
+                    let wanted = Path::new("out").join(source.file_name().unwrap());
+                    match (plan.target(source), plan.collision(source)) {
+                        (Some(target), None) => assert_eq!(key(target), key(&wanted)),
+                        (Some(target), Some(collision)) => assert_eq!(collision.written_to.as_deref(), Some(target)),
+                        (None, Some(collision)) => assert!(policy != CollisionPolicy::Rename && collision.written_to.is_none()),
+                        (None, None) => panic!("{:?} was neither placed nor reported", source),
+                    }
+                    if let Some(target) = plan.target(source) {
+                        assert!(targets.insert(key(target)), "{:?} is taken twice in {:?}", target, sources);
+                    }
+                }
+                let wanted: HashSet<String> = sources.iter().map(|source| key(&Path::new("out").join(source.file_name().unwrap()))).collect();
+                // One file per wanted name, plus the renamed ones
+                assert_eq!(targets.len(), if policy == CollisionPolicy::Rename { sources.len() } else { wanted.len() });
+            }
+        }
+    }
+}
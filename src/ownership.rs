@@ -0,0 +1,202 @@
+use anyhow::Result;
+use serde::Serialize;
+use std::path::Path;
+
+/// Info-ZIP "new Unix" extra field: variable-size UID and GID
+const EXTRA_UNIX_NEW: u16 = 0x7875;
+/// Info-ZIP "Unix" extra field: 16-bit UID and GID
+const EXTRA_UNIX_OLD: u16 = 0x7855;
+
+/// Owner and permissions applied to every extracted file and directory
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Ownership {
+    /// `--chown uid:gid`
+    pub owner: Option<(u32, u32)>,
+    /// `--chmod` mode; directories also get search permission wherever read is granted
+    pub mode: Option<u32>,
+    /// Use the UID/GID recorded in the entry's Unix extra field when there is one
+    pub preserve_owner: bool,
+}
+
+/// Entries whose owner couldn't be changed, reported once per archive
+#[derive(Debug, Default)]
+pub struct ChownFailures {
+    pub count: usize,
+    pub first: Option<String>,
+}
+
+/// Parse a `--chown uid:gid` argument
+pub fn parse_owner(owner: &str) -> Result<(u32, u32), String> {
+    let (uid, gid) = owner
+        .split_once(':')
+        .ok_or_else(|| format!("Expected numeric \"uid:gid\", got {:?}", owner))?;
+    let parse = |id: &str| id.parse::<u32>().map_err(|_| format!("Expected numeric \"uid:gid\", got {:?}", owner));
+    Ok((parse(uid)?, parse(gid)?))
+}
+
+/// Parse a `--chmod` octal mode such as `644` or `0750`
+pub fn parse_mode(mode: &str) -> Result<u32, String> {
+    match u32::from_str_radix(mode, 8) {
+        Ok(mode) if mode <= 0o7777 => Ok(mode),
+        _ => Err(format!("Expected an octal mode such as 644, got {:?}", mode)),
+    }
+}
+
+/// UID and GID from an entry's Unix extra fields, if it has one
+pub fn unix_owner(extra: &[u8]) -> Option<(u32, u32)> {
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let id = u16::from_le_bytes([rest[0], rest[1]]);
+        let size = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+        let data = rest.get(4..4 + size)?;
+        match id {
+            EXTRA_UNIX_NEW => {
+                if let Some(owner) = parse_unix_new(data) {
+                    return Some(owner);
+                }
+            }
+            EXTRA_UNIX_OLD if data.len() >= 4 => {
+                let uid = u16::from_le_bytes([data[0], data[1]]);
+                let gid = u16::from_le_bytes([data[2], data[3]]);
+                return Some((uid as u32, gid as u32));
+            }
+            _ => {}
+        }
+        rest = &rest[4 + size..];
+    }
+    None
+}
+
+/// Version 1 layout: version, UID size, UID, GID size, GID, all little-endian
+fn parse_unix_new(data: &[u8]) -> Option<(u32, u32)> {
+    if *data.first()? != 1 {
+        return None;
+    }
+    let (uid, rest) = read_sized_id(&data[1..])?;
+    let (gid, _) = read_sized_id(rest)?;
+    Some((uid, gid))
+}
+
+fn read_sized_id(data: &[u8]) -> Option<(u32, &[u8])> {
+    let size = *data.first()? as usize;
+    let bytes = data.get(1..1 + size)?;
+    if size > 4 && bytes[4..].iter().any(|&b| b != 0) {
+        return None;
+    }
+    let id = bytes.iter().take(4).rev().fold(0u32, |id, &b| (id << 8) | b as u32);
+    Some((id, &data[1 + size..]))
+}
+
+impl Ownership {
+    pub fn is_empty(&self) -> bool {
+        self.owner.is_none() && self.mode.is_none() && !self.preserve_owner
+    }
+
+    /// Refuse ownership options where they can't be honored
+    pub fn check_supported(&self) -> Result<()> {
+        if cfg!(not(unix)) && !self.is_empty() {
+            anyhow::bail!("--chown, --chmod and --preserve-owner are only supported on Unix");
+        }
+        Ok(())
+    }
+
+    /// Apply the configured mode and owner to one extracted path. A failed
+    /// chown (typically not running as root) is counted in `failures`
+    /// instead of failing the entry.
+    #[cfg(unix)]
+    pub fn apply(&self, path: &Path, is_dir: bool, extra: &[u8], failures: &mut ChownFailures) -> Result<()> {
+        use anyhow::Context;
+        use std::fs;
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Some(mode) = self.mode {
+            let mode = if is_dir { mode | ((mode & 0o444) >> 2) } else { mode };
+            fs::set_permissions(path, fs::Permissions::from_mode(mode))
+                .with_context(|| format!("Failed to set permissions on {:?}", path))?;
+        }
+
+        let owner = self.preserve_owner.then(|| unix_owner(extra)).flatten().or(self.owner);
+        if let Some((uid, gid)) = owner {
+            if std::os::unix::fs::chown(path, Some(uid), Some(gid)).is_err() {
+                failures.count += 1;
+                failures.first.get_or_insert_with(|| path.to_string_lossy().into_owned());
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    pub fn apply(&self, _path: &Path, _is_dir: bool, _extra: &[u8], _failures: &mut ChownFailures) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An extra field with `id` holding `data`
+    fn field(id: u16, data: &[u8]) -> Vec<u8> {
+        let mut field = id.to_le_bytes().to_vec();
+        field.extend((data.len() as u16).to_le_bytes());
+        field.extend(data);
+        field
+    }
+
+    #[test]
+    fn owner_and_mode_arguments() {
+        assert_eq!(parse_owner("1000:100"), Ok((1000, 100)));
+        assert!(parse_owner("1000").is_err());
+        assert!(parse_owner("www-data:www-data").is_err());
+        assert_eq!(parse_mode("644"), Ok(0o644));
+        assert_eq!(parse_mode("0750"), Ok(0o750));
+        assert!(parse_mode("8").is_err());
+        assert!(parse_mode("17777").is_err());
+    }
+
+    #[test]
+    fn owner_from_the_new_unix_extra_field() {
+        // Version 1, 4-byte UID 1000, 4-byte GID 100
+        let extra = field(EXTRA_UNIX_NEW, &[1, 4, 0xe8, 0x03, 0, 0, 4, 100, 0, 0, 0]);
+        assert_eq!(unix_owner(&extra), Some((1000, 100)));
+        // Shorter and longer IDs, as long as the extra bytes are zero
+        let extra = field(EXTRA_UNIX_NEW, &[1, 2, 0xe8, 0x03, 8, 100, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(unix_owner(&extra), Some((1000, 100)));
+        let too_big = field(EXTRA_UNIX_NEW, &[1, 8, 0, 0, 0, 0, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(unix_owner(&too_big), None);
+        assert_eq!(unix_owner(&field(EXTRA_UNIX_NEW, &[2, 1, 5, 1, 5])), None);
+    }
+
+    #[test]
+    fn owner_from_the_old_unix_extra_field_after_other_fields() {
+        // Extended timestamp, then the old field's UID 33 and GID 33
+        let mut extra = field(0x5455, &[1, 0, 0, 0, 0]);
+        extra.extend(field(EXTRA_UNIX_OLD, &[33, 0, 33, 0]));
+        assert_eq!(unix_owner(&extra), Some((33, 33)));
+        assert_eq!(unix_owner(&field(0x5455, &[1, 0, 0, 0, 0])), None);
+        // A field claiming more bytes than there are
+        assert_eq!(unix_owner(&[0x55, 0x78, 40, 0, 33, 0]), None);
+        assert_eq!(unix_owner(&[]), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn modes_are_applied_with_search_permission_on_directories() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let (file, sub) = (dir.path().join("file"), dir.path().join("sub"));
+        std::fs::write(&file, b"x").unwrap();
+        std::fs::create_dir(&sub).unwrap();
+        let ownership = Ownership { mode: Some(0o640), ..Ownership::default() };
+        let mut failures = ChownFailures::default();
+
+        ownership.apply(&file, false, &[], &mut failures).unwrap();
+        ownership.apply(&sub, true, &[], &mut failures).unwrap();
+
+        let mode = |path: &Path| std::fs::metadata(path).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(mode(&file), 0o640);
+        assert_eq!(mode(&sub), 0o750);
+        assert_eq!(failures.count, 0);
+    }
+}
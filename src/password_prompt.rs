@@ -0,0 +1,95 @@
+use indicatif::MultiProgress;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::terminal;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::copy_hooks::CopyControl;
+use crate::passwords::{PasswordPrompt, PROMPT_ATTEMPTS};
+
+/// Asks on the terminal, the progress bars held back meanwhile. Typing
+/// isn't echoed; Enter on nothing or Esc skips the archive, and Ctrl-C,
+/// which raw mode keeps from reaching the signal handler, cancels the run.
+pub struct TerminalPrompt {
+    bars: MultiProgress,
+    control: Arc<CopyControl>,
+}
+
+/// Leaves raw mode however reading ends
+struct RawMode;
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// What was typed at the prompt
+enum Typed {
+    Password(String),
+    Skip,
+    Cancel,
+}
+
+impl TerminalPrompt {
+    /// `None` when stderr isn't a terminal, so there is no one to ask
+    pub fn new(bars: MultiProgress, control: Arc<CopyControl>) -> Option<Self> {
+        io::stderr().is_terminal().then_some(TerminalPrompt { bars, control })
+    }
+}
+
+impl PasswordPrompt for TerminalPrompt {
+    fn ask(&self, archive: &Path, attempt: usize) -> Option<String> {
+        if self.control.is_cancelled() {
+            return None;
+        }
+        let name = archive.file_name().unwrap_or(archive.as_os_str()).to_string_lossy();
+        let typed = self.bars.suspend(|| {
+            if attempt > 1 {
+                eprintln!("❌ Wrong password for {}", name);
+            }
+            eprint!("🔑 Password for {} ({}/{}, Enter to skip): ", name, attempt, PROMPT_ATTEMPTS);
+            let _ = io::stderr().flush();
+            let typed = read_hidden();
+            eprintln!();
+            typed
+        });
+        match typed {
+            Ok(Typed::Password(password)) => Some(password),
+            Ok(Typed::Skip) => None,
+            Ok(Typed::Cancel) => {
+                self.control.cancel();
+                eprintln!("⏹️  Cancelling...");
+                None
+            }
+            Err(e) => {
+                eprintln!("⚠️  Can't read a password from the terminal: {}; {} skipped", e, name);
+                None
+            }
+        }
+    }
+}
+
+/// A line from the terminal, without echoing it
+fn read_hidden() -> io::Result<Typed> {
+    terminal::enable_raw_mode()?;
+    let _raw = RawMode;
+    let mut password = String::new();
+    loop {
+        let Event::Key(KeyEvent { code, modifiers, kind: KeyEventKind::Press, .. }) = event::read()? else {
+            continue;
+        };
+        match code {
+            KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => return Ok(Typed::Cancel),
+            KeyCode::Enter if password.is_empty() => return Ok(Typed::Skip),
+            KeyCode::Enter => return Ok(Typed::Password(password)),
+            KeyCode::Esc => return Ok(Typed::Skip),
+            KeyCode::Backspace => {
+                password.pop();
+            }
+            KeyCode::Char(c) => password.push(c),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,446 @@
+use anyhow::{bail, Context, Result};
+use glob::{MatchOptions, Pattern};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use toml::{Table, Value};
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// Where the password that opened an encrypted archive came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum PasswordSource {
+    /// The `--password-map` entry with this pattern
+    Map { pattern: String },
+    /// The `--password` given at this position, counted from 1
+    List { index: usize },
+    /// Typed in when asked, for this archive or an earlier one
+    Prompt,
+    /// Nothing tried opened it
+    None,
+}
+
+struct MapEntry {
+    pattern: Pattern,
+    password: String,
+}
+
+/// `--password-map`: a TOML file of glob patterns and the password of the
+/// archives they match, e.g. `"vendor-a/*.zip" = "secret1"`. A pattern
+/// matches the end of an archive's path, so `*.zip` matches every archive
+/// and `vendor-a/*.zip` those directly in a `vendor-a` directory.
+pub struct PasswordMap {
+    path: PathBuf,
+    /// Most specific first: by the characters a pattern matches literally,
+    /// so `vendor-a/x.zip` goes before the longer `*/*/*.zip`
+    entries: Vec<MapEntry>,
+}
+
+impl PasswordMap {
+    /// Read the map at `path`, warning when other users can read it. Parse
+    /// errors give a line, never the text, which may hold a password.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read password map {:?}", path))?;
+        warn_if_world_readable(path);
+        let table: Table = text.parse().map_err(|e: toml::de::Error| {
+            let line = e.span().map_or(0, |span| text[..span.start].lines().count().max(1));
+            anyhow::anyhow!("Failed to parse password map {:?} at line {}: {}", path, line, e.message())
+        })?;
+        let mut entries = Vec::new();
+        for (pattern, password) in table {
+            let Value::String(password) = password else {
+                bail!("Password map {:?}: the password for {:?} isn't a string", path, pattern);
+            };
+            let pattern = Pattern::new(&pattern).with_context(|| format!("Password map {:?}: invalid pattern {:?}", path, pattern))?;
+            entries.push(MapEntry { pattern, password });
+        }
+        entries.sort_by_key(|entry| std::cmp::Reverse(literal_chars(entry.pattern.as_str())));
+        Ok(PasswordMap { path: path.to_path_buf(), entries })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Entries whose pattern matches the end of `archive`, most specific first
+    fn matching<'a>(&'a self, archive: &Path) -> impl Iterator<Item = &'a MapEntry> {
+        let components: Vec<String> = archive.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+        let suffixes: Vec<String> = (0..components.len()).map(|start| components[start..].join("/")).collect();
+        let options = MatchOptions { require_literal_separator: true, ..MatchOptions::new() };
+        self.entries
+            .iter()
+            .filter(move |entry| suffixes.iter().any(|suffix| entry.pattern.matches_with(suffix, options)))
+    }
+}
+
+/// Characters of a glob pattern outside wildcards and `[...]` classes
+fn literal_chars(pattern: &str) -> usize {
+    let mut count = 0;
+    let mut in_class = false;
+    for c in pattern.chars() {
+        match c {
+            '[' if !in_class => in_class = true,
+            ']' if in_class => in_class = false,
+            '*' | '?' if !in_class => {}
+            _ if !in_class => count += 1,
+            _ => {}
+        }
+    }
+    count
+}
+
+#[cfg(unix)]
+fn warn_if_world_readable(path: &Path) {
+    use std::os::unix::fs::PermissionsExt;
+
+    if fs::metadata(path).is_ok_and(|m| m.permissions().mode() & 0o004 != 0) {
+        eprintln!("⚠️  Password map {:?} is readable by every user; chmod 600 it", path);
+    }
+}
+
+#[cfg(not(unix))]
+fn warn_if_world_readable(_path: &Path) {}
+
+/// How many times [`PasswordPrompt::ask`] is asked about one archive
+pub const PROMPT_ATTEMPTS: usize = 3;
+
+/// Asks for the password of an encrypted archive nothing given opens
+pub trait PasswordPrompt: Send + Sync {
+    /// `attempt` counts from 1 to [`PROMPT_ATTEMPTS`]; `None` gives up on
+    /// the archive, which is skipped
+    fn ask(&self, archive: &Path, attempt: usize) -> Option<String>;
+}
+
+/// The passwords tried on encrypted archives: matching `--password-map`
+/// entries first, then each `--password` in the order given, then those
+/// typed in so far; last, the prompt, if any, is asked
+#[derive(Default)]
+pub struct Passwords {
+    map: Option<PasswordMap>,
+    list: Vec<String>,
+    prompt: Option<Arc<dyn PasswordPrompt>>,
+    /// Passwords the prompt was given that opened an archive, remembered
+    /// for the rest of the run. Held while asking, so one archive is asked
+    /// about at a time.
+    typed: Mutex<Vec<String>>,
+}
+
+/// Never shows a password
+impl fmt::Debug for Passwords {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Passwords")
+            .field("map", &self.map.as_ref().map(|map| &map.path))
+            .field("list", &self.list.len())
+            .field("prompt", &self.prompt.is_some())
+            .finish()
+    }
+}
+
+impl Passwords {
+    pub fn new(map: Option<PasswordMap>, list: Vec<String>) -> Self {
+        Passwords { map, list, ..Passwords::default() }
+    }
+
+    /// Ask `prompt` about archives nothing else opens
+    pub fn with_prompt(mut self, prompt: Arc<dyn PasswordPrompt>) -> Self {
+        self.prompt = Some(prompt);
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_none() && self.list.is_empty() && self.prompt.is_none()
+    }
+
+    fn candidates<'a>(&'a self, archive: &'a Path) -> impl Iterator<Item = (PasswordSource, &'a str)> {
+        let mapped = self.map.iter().flat_map(move |map| map.matching(archive)).map(|entry| {
+            (PasswordSource::Map { pattern: entry.pattern.as_str().to_string() }, entry.password.as_str())
+        });
+        let listed = self.list.iter().enumerate().map(|(i, password)| (PasswordSource::List { index: i + 1 }, password.as_str()));
+        mapped.chain(listed)
+    }
+
+    /// The first candidate that opens the archive at `path`, asking the
+    /// prompt up to [`PROMPT_ATTEMPTS`] times once none does
+    pub fn find(&self, path: &Path) -> Result<Option<(PasswordSource, String)>> {
+        let Some(mut check) = PasswordCheck::open(path)? else {
+            return Ok(None);
+        };
+        for (source, password) in self.candidates(path) {
+            if check.opens(password) {
+                return Ok(Some((source, password.to_string())));
+            }
+        }
+        // Taken before trying what was typed, which another archive's prompt may add to
+        let mut typed = self.typed.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(password) = typed.iter().find(|password| check.opens(password)) {
+            return Ok(Some((PasswordSource::Prompt, password.clone())));
+        }
+        let Some(prompt) = &self.prompt else {
+            return Ok(None);
+        };
+        for attempt in 1..=PROMPT_ATTEMPTS {
+            let Some(password) = prompt.ask(path, attempt) else {
+                break;
+            };
+            if check.opens(&password) {
+                typed.push(password.clone());
+                return Ok(Some((PasswordSource::Prompt, password)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// The smallest encrypted entry of an archive, which candidate passwords
+/// are tried on
+pub struct PasswordCheck {
+    archive: ZipArchive<fs::File>,
+    index: usize,
+}
+
+impl PasswordCheck {
+    /// `None` when no entry of the archive at `path` is encrypted
+    pub fn open(path: &Path) -> Result<Option<Self>> {
+        let file = fs::File::open(path).with_context(|| format!("Failed to open zip file {:?}", path))?;
+        let mut archive = ZipArchive::new(file).with_context(|| format!("Failed to read zip archive {:?}", path))?;
+        let mut smallest: Option<(usize, u64)> = None;
+        for i in 0..archive.len() {
+            let encrypted = matches!(
+                archive.by_index(i),
+                Err(ZipError::UnsupportedArchive(message)) if message == ZipError::PASSWORD_REQUIRED
+            );
+            if encrypted {
+                let size = archive.by_index_raw(i)?.size();
+                if smallest.is_none_or(|(_, smallest)| size < smallest) {
+                    smallest = Some((i, size));
+                }
+            }
+        }
+        Ok(smallest.map(|(index, _)| PasswordCheck { archive, index }))
+    }
+
+    /// Whether `password` decrypts the entry, CRC-32 included, since
+    /// ZipCrypto's own check lets about one wrong password in 256 through
+    pub fn opens(&mut self, password: &str) -> bool {
+        match self.archive.by_index_decrypt(self.index, password.as_bytes()) {
+            Ok(Ok(mut entry)) => io::copy(&mut entry, &mut io::sink()).is_ok(),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// CRC-32 of one more byte, as ZipCrypto's keys are updated
+    fn crc32_byte(crc: u32, byte: u8) -> u32 {
+        let mut crc = crc ^ u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+        crc
+    }
+
+    struct ZipCrypto([u32; 3]);
+
+    impl ZipCrypto {
+        fn new(password: &str) -> Self {
+            let mut keys = ZipCrypto([0x1234_5678, 0x2345_6789, 0x3456_7890]);
+            password.bytes().for_each(|byte| keys.update(byte));
+            keys
+        }
+
+        fn update(&mut self, byte: u8) {
+            let [k0, k1, k2] = &mut self.0;
+            *k0 = crc32_byte(*k0, byte);
+            *k1 = k1.wrapping_add(*k0 & 0xff).wrapping_mul(134_775_813).wrapping_add(1);
+            *k2 = crc32_byte(*k2, (*k1 >> 24) as u8);
+        }
+
+        fn encrypt(&mut self, plain: u8) -> u8 {
+            let temp = (self.0[2] | 2) & 0xffff;
+            let cipher = plain ^ ((temp.wrapping_mul(temp ^ 1) >> 8) & 0xff) as u8;
+            self.update(plain);
+            cipher
+        }
+    }
+
+    /// A zip of one stored entry encrypted with `password`
+    fn encrypted_zip(path: &Path, password: &str) {
+        let (name, content) = (b"secret.txt", b"top secret");
+        let crc = crc32fast::hash(content);
+        let mut crypto = ZipCrypto::new(password);
+        let mut header = [0u8; 12];
+        header[11] = (crc >> 24) as u8;
+        let data: Vec<u8> = header.iter().chain(content).map(|&byte| crypto.encrypt(byte)).collect();
+
+        // Version needed, flags (encrypted), method (stored), time, date, CRC, sizes
+        let mut fields = Vec::new();
+        for value in [20u16, 1, 0, 0, 0x21] {
+            fields.extend(value.to_le_bytes());
+        }
+        for value in [crc, data.len() as u32, content.len() as u32] {
+            fields.extend(value.to_le_bytes());
+        }
+        fields.extend((name.len() as u16).to_le_bytes());
+        fields.extend(0u16.to_le_bytes());
+
+        let mut zip = 0x0403_4b50u32.to_le_bytes().to_vec();
+        zip.extend(&fields);
+        zip.extend(name);
+        zip.extend(&data);
+        let central = zip.len() as u32;
+        zip.extend(0x0201_4b50u32.to_le_bytes());
+        zip.extend(20u16.to_le_bytes());
+        zip.extend(&fields);
+        // Comment length, disk, internal and external attributes, local header offset
+        zip.extend([0u8; 12]);
+        zip.extend(0u32.to_le_bytes());
+        zip.extend(name);
+        let central_size = zip.len() as u32 - central;
+        zip.extend(0x0605_4b50u32.to_le_bytes());
+        zip.extend([0, 0, 0, 0, 1, 0, 1, 0]);
+        zip.extend(central_size.to_le_bytes());
+        zip.extend(central.to_le_bytes());
+        zip.extend([0, 0]);
+        fs::write(path, zip).unwrap();
+    }
+
+    fn password_map(dir: &Path, toml: &str) -> PasswordMap {
+        let path = dir.join("passwords.toml");
+        fs::write(&path, toml).unwrap();
+        PasswordMap::load(&path).unwrap()
+    }
+
+    fn matching(map: &PasswordMap, archive: &str) -> Vec<String> {
+        map.matching(Path::new(archive)).map(|entry| entry.pattern.as_str().to_string()).collect()
+    }
+
+    /// Answers with `answers` in turn, counting the questions
+    struct MockPrompt {
+        answers: Vec<&'static str>,
+        asked: AtomicUsize,
+    }
+
+    impl MockPrompt {
+        fn new(answers: &[&'static str]) -> Arc<Self> {
+            Arc::new(MockPrompt { answers: answers.to_vec(), asked: AtomicUsize::new(0) })
+        }
+
+        fn asked(&self) -> usize {
+            self.asked.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PasswordPrompt for MockPrompt {
+        fn ask(&self, _archive: &Path, attempt: usize) -> Option<String> {
+            assert_eq!(attempt, self.asked.fetch_add(1, Ordering::SeqCst) + 1);
+            self.answers.get(attempt - 1).map(|answer| answer.to_string())
+        }
+    }
+
+    #[test]
+    fn patterns_match_the_end_of_the_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let map = password_map(dir.path(), r#""vendor-a/*.zip" = "a"
+"*.zip" = "any""#);
+
+        assert_eq!(matching(&map, "/data/in/vendor-a/x.zip"), ["vendor-a/*.zip", "*.zip"]);
+        assert_eq!(matching(&map, "vendor-a/x.zip"), ["vendor-a/*.zip", "*.zip"]);
+        assert_eq!(matching(&map, "/data/vendor-b/x.zip"), ["*.zip"]);
+        assert_eq!(matching(&map, "/data/vendor-a/x.tar"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn wildcards_dont_match_separators() {
+        let dir = tempfile::tempdir().unwrap();
+        let map = password_map(dir.path(), r#""vendor-a/*.zip" = "a""#);
+
+        // `*` stops at `/`, so only archives directly in vendor-a match
+        assert!(matching(&map, "/data/vendor-a/sub/x.zip").is_empty());
+        assert!(matching(&map, "/data/vendor-a-old/x.zip").is_empty());
+    }
+
+    #[test]
+    fn most_specific_pattern_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let map = password_map(dir.path(), r#""*/*/*.zip" = "deep"
+"vendor-a/x.zip" = "exact"
+"*.zip" = "any"
+"v[a-z]ndor-a/*.zip" = "class""#);
+
+        assert_eq!(matching(&map, "/data/vendor-a/x.zip"), ["vendor-a/x.zip", "v[a-z]ndor-a/*.zip", "*/*/*.zip", "*.zip"]);
+        assert_eq!(literal_chars("*/*/*.zip"), 6);
+        assert_eq!(literal_chars("v[a-z]ndor-a/*.zip"), 12);
+    }
+
+    #[test]
+    fn map_before_list_before_prompt() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("vendor-a/x.zip");
+        fs::create_dir(dir.path().join("vendor-a")).unwrap();
+        encrypted_zip(&archive, "right");
+
+        let map = || Some(password_map(dir.path(), r#""vendor-a/*.zip" = "right"
+"*.zip" = "wrong""#));
+        let prompt = MockPrompt::new(&["right"]);
+        let passwords = Passwords::new(map(), vec!["right".to_string()]).with_prompt(prompt.clone());
+        let found = passwords.find(&archive).unwrap().unwrap();
+        assert_eq!(found, (PasswordSource::Map { pattern: "vendor-a/*.zip".to_string() }, "right".to_string()));
+
+        let passwords = Passwords::new(None, vec!["wrong".to_string(), "right".to_string()]).with_prompt(prompt.clone());
+        assert_eq!(passwords.find(&archive).unwrap().unwrap(), (PasswordSource::List { index: 2 }, "right".to_string()));
+        assert_eq!(prompt.asked(), 0);
+
+        let passwords = Passwords::new(None, vec!["wrong".to_string()]).with_prompt(prompt.clone());
+        assert_eq!(passwords.find(&archive).unwrap().unwrap(), (PasswordSource::Prompt, "right".to_string()));
+        assert_eq!(prompt.asked(), 1);
+        // What was typed is tried on the next archive before asking again
+        assert_eq!(passwords.find(&archive).unwrap().unwrap(), (PasswordSource::Prompt, "right".to_string()));
+        assert_eq!(prompt.asked(), 1);
+    }
+
+    #[test]
+    fn prompt_is_asked_at_most_prompt_attempts_times() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("x.zip");
+        encrypted_zip(&archive, "right");
+
+        let prompt = MockPrompt::new(&["no", "nope", "still no", "right"]);
+        let passwords = Passwords::new(None, Vec::new()).with_prompt(prompt.clone());
+        assert_eq!(passwords.find(&archive).unwrap(), None);
+        assert_eq!(prompt.asked(), PROMPT_ATTEMPTS);
+
+        let prompt = MockPrompt::new(&["no", "right"]);
+        let passwords = Passwords::new(None, Vec::new()).with_prompt(prompt.clone());
+        assert_eq!(passwords.find(&archive).unwrap().unwrap(), (PasswordSource::Prompt, "right".to_string()));
+        assert_eq!(prompt.asked(), 2);
+
+        // Giving up stops the asking
+        let prompt = MockPrompt::new(&[]);
+        let passwords = Passwords::new(None, Vec::new()).with_prompt(prompt.clone());
+        assert_eq!(passwords.find(&archive).unwrap(), None);
+        assert_eq!(prompt.asked(), 1);
+    }
+
+    #[test]
+    fn unencrypted_archives_need_no_password() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("plain.zip");
+        let mut zip = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+        zip.start_file("a.txt", zip::write::FileOptions::default()).unwrap();
+        fs::write(&archive, zip.finish().unwrap().into_inner()).unwrap();
+
+        let prompt = MockPrompt::new(&["right"]);
+        let passwords = Passwords::new(None, Vec::new()).with_prompt(prompt.clone());
+        assert_eq!(passwords.find(&archive).unwrap(), None);
+        assert_eq!(prompt.asked(), 0);
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, FileTimes, Metadata};
+use std::path::Path;
+
+/// One `--preserve` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PreserveAttr {
+    /// Modification and access times
+    Times,
+    /// Unix permission bits, or the read-only attribute on Windows
+    Perms,
+    /// UID and GID; only takes effect when running as root
+    Owner,
+    /// All of the above
+    All,
+}
+
+/// Which attributes of the source the output keeps: an archive entry's
+/// recorded ones when extracting, the original file's when stripping
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preserve {
+    pub times: bool,
+    pub perms: bool,
+    pub owner: bool,
+}
+
+impl Preserve {
+    /// Entry modification times only, what extraction keeps by default
+    pub const TIMES: Preserve = Preserve { times: true, perms: false, owner: false };
+
+    pub fn from_attrs(attrs: &[PreserveAttr]) -> Self {
+        let has = |attr| attrs.contains(&attr) || attrs.contains(&PreserveAttr::All);
+        Preserve { times: has(PreserveAttr::Times), perms: has(PreserveAttr::Perms), owner: has(PreserveAttr::Owner) }
+    }
+}
+
+/// Set an archive entry's recorded Unix mode on an extracted path; on
+/// Windows only whether it is writable carries over
+pub fn set_mode(path: &Path, mode: u32) -> Result<()> {
+    #[cfg(unix)]
+    let permissions = {
+        use std::os::unix::fs::PermissionsExt;
+        fs::Permissions::from_mode(mode & 0o7777)
+    };
+    #[cfg(not(unix))]
+    let permissions = {
+        let mut permissions = fs::metadata(path)
+            .with_context(|| format!("Failed to read metadata for {:?}", path))?
+            .permissions();
+        permissions.set_readonly(mode & 0o222 == 0);
+        permissions
+    };
+    fs::set_permissions(path, permissions)
+        .with_context(|| format!("Failed to set permissions on {:?}", path))
+}
+
+/// Give the owner write permission, so a copy of a read-only file can have
+/// its tag rewritten
+pub fn make_writable(path: &Path) -> Result<()> {
+    let mut permissions = fs::metadata(path)
+        .with_context(|| format!("Failed to read metadata for {:?}", path))?
+        .permissions();
+    if !permissions.readonly() {
+        return Ok(());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        permissions.set_mode(permissions.mode() | 0o200);
+    }
+    #[cfg(not(unix))]
+    #[allow(clippy::permissions_set_readonly_false)]
+    permissions.set_readonly(false);
+    fs::set_permissions(path, permissions)
+        .with_context(|| format!("Failed to make {:?} writable", path))
+}
+
+/// Carry the preserved attributes of the original file, read before it was
+/// copied or rewritten, over to `target`. Times go first because setting
+/// them needs the file open for writing, which restored read-only permissions
+/// would refuse. Returns `false` when the owner couldn't be changed, which
+/// needs root and only warrants a warning.
+pub fn restore_attributes(preserve: Preserve, source: &Metadata, target: &Path) -> Result<bool> {
+    if preserve.times {
+        let mut times = FileTimes::new();
+        if let Ok(modified) = source.modified() {
+            times = times.set_modified(modified);
+        }
+        if let Ok(accessed) = source.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        fs::File::options()
+            .write(true)
+            .open(target)
+            .and_then(|file| file.set_times(times))
+            .with_context(|| format!("Failed to set times of {:?}", target))?;
+    }
+
+    #[cfg(unix)]
+    let owner_changed = {
+        use std::os::unix::fs::MetadataExt;
+        !preserve.owner || std::os::unix::fs::chown(target, Some(source.uid()), Some(source.gid())).is_ok()
+    };
+    #[cfg(not(unix))]
+    let owner_changed = true;
+
+    if preserve.perms {
+        fs::set_permissions(target, source.permissions())
+            .with_context(|| format!("Failed to set permissions on {:?}", target))?;
+    }
+    Ok(owner_changed)
+}
@@ -0,0 +1,443 @@
+use anyhow::{bail, Context, Result};
+use clap::parser::ValueSource;
+use clap::{Arg, ArgAction, ArgMatches};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs;
+use std::path::PathBuf;
+use toml::{Table, Value};
+
+/// Overrides where the config file is, e.g. for a shared or portable setup
+pub const CONFIG_ENV: &str = "BULK_UNZIP_CONFIG";
+
+/// Options a preset never holds: the input directory, so one preset serves
+/// any folder; credentials, which the config file would keep in the clear;
+/// and options set by `retry` or for presets themselves
+const NOT_SAVED: &[&str] = &["directory", "directories", "bearer_token", "headers", "passwords", "only", "preset", "save_preset", "help"];
+
+/// Options saved under a name, for `unzip` or `strip`. The CLI stores values
+/// as given on its command line, the app as its form holds them; each reads
+/// the other's.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// `unzip` or `strip`
+    pub command: String,
+    /// Values by option, e.g. `workers = "8"`, `skip_existing = true` or
+    /// `exclude = ["*.tmp", "*.bak"]`
+    #[serde(default)]
+    pub options: Table,
+}
+
+/// `--preset` and `--save-preset`, shared by unzip and strip
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct PresetArgs {
+    /// Start from the options saved under this name; options given here win
+    /// over the preset's, and the preset's over the defaults
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+
+    /// Save the options given here, with those of any --preset, under this
+    /// name in the config file, then run
+    #[arg(long, value_name = "NAME")]
+    pub save_preset: Option<String>,
+}
+
+/// Manage the presets `--save-preset` stores
+#[derive(clap::Subcommand, Debug)]
+pub enum PresetAction {
+    /// List the saved presets
+    List,
+    /// Print a preset's options
+    Show { name: String },
+    /// Remove a preset
+    Delete { name: String },
+}
+
+/// `$BULK_UNZIP_CONFIG`, or `bulk-unzip/config.toml` in the user's
+/// configuration directory: `%APPDATA%` on Windows, else `$XDG_CONFIG_HOME`
+/// or `~/.config`
+pub fn config_path() -> Result<PathBuf> {
+    if let Some(path) = env::var_os(CONFIG_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join("bulk-unzip").join("config.toml"))
+        .with_context(|| format!("Can't find the configuration directory; set {} to the config file to use", CONFIG_ENV))
+}
+
+/// The `[presets.<name>]` tables of the config file. Anything else in the
+/// file is kept as it is when a preset is saved or deleted.
+#[derive(Debug, Clone)]
+pub struct PresetStore {
+    path: PathBuf,
+}
+
+impl PresetStore {
+    pub fn open() -> Result<Self> {
+        Ok(PresetStore { path: config_path()? })
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    fn read_config(&self) -> Result<Table> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => text.parse().with_context(|| format!("Failed to parse {:?}", self.path)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Table::new()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read {:?}", self.path)),
+        }
+    }
+
+    fn write_config(&self, config: &Table) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        let text = toml::to_string_pretty(config).context("Failed to serialize the presets")?;
+        fs::write(&self.path, text).with_context(|| format!("Failed to write {:?}", self.path))
+    }
+
+    pub fn list(&self) -> Result<BTreeMap<String, Preset>> {
+        match self.read_config()?.remove("presets") {
+            Some(presets) => presets.try_into().with_context(|| format!("Invalid [presets] in {:?}", self.path)),
+            None => Ok(BTreeMap::new()),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Preset> {
+        let mut presets = self.list()?;
+        presets.remove(name).with_context(|| {
+            let known: Vec<&str> = presets.keys().map(String::as_str).collect();
+            if known.is_empty() {
+                format!("No preset named {:?}; none are saved in {:?}", name, self.path)
+            } else {
+                format!("No preset named {:?}; saved presets: {}", name, known.join(", "))
+            }
+        })
+    }
+
+    /// Save `preset` as `name`, replacing any preset of that name
+    pub fn save(&self, name: &str, preset: &Preset) -> Result<()> {
+        if name.trim().is_empty() {
+            bail!("A preset needs a name");
+        }
+        let mut config = self.read_config()?;
+        let presets = config.entry("presets").or_insert_with(|| Value::Table(Table::new()));
+        let Value::Table(presets) = presets else {
+            bail!("`presets` in {:?} isn't a table", self.path);
+        };
+        presets.insert(name.to_string(), Value::try_from(preset).context("Failed to serialize the preset")?);
+        self.write_config(&config)
+    }
+
+    /// Remove preset `name`; false when there was none
+    pub fn delete(&self, name: &str) -> Result<bool> {
+        let mut config = self.read_config()?;
+        let removed = match config.get_mut("presets") {
+            Some(Value::Table(presets)) => presets.remove(name).is_some(),
+            _ => false,
+        };
+        if removed {
+            self.write_config(&config)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// A form's options with a preset applied, and the preset options it has no
+/// field for or whose value doesn't fit, e.g. CLI-only options
+#[derive(Debug, Serialize)]
+pub struct AppliedPreset {
+    pub options: Json,
+    pub ignored: Vec<String>,
+}
+
+/// The options of a form that differ from its `defaults`, as a preset stores them
+pub fn from_form(options: &Json, defaults: &Json) -> Table {
+    let mut table = Table::new();
+    let Json::Object(options) = options else {
+        return table;
+    };
+    for (name, value) in options {
+        if NOT_SAVED.contains(&name.as_str()) || value.is_null() || defaults.get(name) == Some(value) {
+            continue;
+        }
+        if let Ok(value) = Value::try_from(value) {
+            table.insert(name.clone(), value);
+        }
+    }
+    table
+}
+
+/// `options` with the values of `preset` put into the fields of the same
+/// name. The CLI stores every value as text, so `"8"` may go into a number
+/// field and `"a,b"` into a list; `fits` says whether the form accepts a value.
+pub fn apply_to_form(mut options: Json, preset: &Preset, fits: impl Fn(&Json) -> bool) -> AppliedPreset {
+    let mut ignored = Vec::new();
+    for (name, value) in &preset.options {
+        if NOT_SAVED.contains(&name.as_str()) || options.get(name).is_none() {
+            ignored.push(name.clone());
+            continue;
+        }
+        let previous = options[name].clone();
+        let fitted = candidates(value).into_iter().any(|candidate| {
+            options[name] = candidate;
+            fits(&options)
+        });
+        if !fitted {
+            options[name] = previous;
+            ignored.push(name.clone());
+        }
+    }
+    AppliedPreset { options, ignored }
+}
+
+/// What a stored value may stand for in a form, most literal first
+fn candidates(value: &Value) -> Vec<Json> {
+    let Ok(json) = serde_json::to_value(value) else {
+        return Vec::new();
+    };
+    let mut candidates = vec![json.clone()];
+    if let Value::String(text) = value {
+        if let Ok(number) = text.parse::<u64>() {
+            candidates.push(number.into());
+        } else if let Ok(number) = text.parse::<f64>() {
+            candidates.push(number.into());
+        }
+        if let Ok(flag) = text.parse::<bool>() {
+            candidates.push(flag.into());
+        }
+        candidates.push(Json::Array(text.split(',').map(|part| Json::String(part.trim().to_string())).collect()));
+    } else if !json.is_array() {
+        candidates.push(Json::Array(vec![json]));
+    }
+    candidates
+}
+
+/// `argv` with the options of its `--preset` added after the ones given,
+/// leaving out those given explicitly. Parsing errors are left for the
+/// caller's own parse to report, so `argv` comes back unchanged.
+pub fn expand(command: &clap::Command, argv: Vec<OsString>) -> Result<Vec<OsString>> {
+    let mut command = command.clone();
+    command.build();
+    let Ok(matches) = command.clone().try_get_matches_from(&argv) else {
+        return Ok(argv);
+    };
+    let Some((name, sub_matches)) = matches.subcommand() else {
+        return Ok(argv);
+    };
+    let Some(preset_name) = sub_matches.try_get_one::<String>("preset").ok().flatten() else {
+        return Ok(argv);
+    };
+    let preset = PresetStore::open()?.get(preset_name)?;
+    if preset.command != name {
+        bail!("Preset {:?} is for {}, not {}", preset_name, preset.command, name);
+    }
+    let subcommand = command.find_subcommand(name).expect("matched subcommand exists");
+
+    let mut argv = argv;
+    for (option, value) in &preset.options {
+        let Some(arg) = subcommand.get_arguments().find(|arg| arg.get_id() == option.as_str()) else {
+            eprintln!("⚠️  Preset {:?}: {} has no option {:?}; ignored", preset_name, name, option);
+            continue;
+        };
+        let given = |arg: &Arg| sub_matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine);
+        // Either side may declare a conflict
+        let conflicts = |a: &Arg, b: &Arg| subcommand.get_arg_conflicts_with(a).into_iter().any(|c| c.get_id() == b.get_id());
+        // An option given here also wins over preset options it can't be used with
+        let overruled = subcommand.get_arguments().filter(|other| given(other)).any(|other| conflicts(arg, other) || conflicts(other, arg));
+        if NOT_SAVED.contains(&option.as_str()) || given(arg) || overruled {
+            continue;
+        }
+        match tokens(arg, value) {
+            Some(tokens) => argv.extend(tokens),
+            None => eprintln!("⚠️  Preset {:?}: {:?} isn't a valid value for {}; ignored", preset_name, value, option),
+        }
+    }
+    Ok(argv)
+}
+
+/// Save the options `argv` gives explicitly as its `--save-preset`, if any,
+/// returning the name it was saved as
+pub fn save_requested(command: &clap::Command, argv: &[OsString]) -> Result<Option<String>> {
+    let mut command = command.clone();
+    command.build();
+    let Ok(matches) = command.clone().try_get_matches_from(argv) else {
+        return Ok(None);
+    };
+    let Some((name, sub_matches)) = matches.subcommand() else {
+        return Ok(None);
+    };
+    let Some(preset_name) = sub_matches.try_get_one::<String>("save_preset").ok().flatten() else {
+        return Ok(None);
+    };
+    let subcommand = command.find_subcommand(name).expect("matched subcommand exists");
+    let preset = Preset { command: name.to_string(), options: record(subcommand, sub_matches) };
+    PresetStore::open()?.save(preset_name, &preset)?;
+    Ok(Some(preset_name.clone()))
+}
+
+/// Every option of `subcommand` given on the command line, as stored in a preset
+fn record(subcommand: &clap::Command, matches: &ArgMatches) -> Table {
+    let mut options = Table::new();
+    for arg in subcommand.get_arguments() {
+        let id = arg.get_id().as_str();
+        if NOT_SAVED.contains(&id) || arg.is_positional() || matches.value_source(id) != Some(ValueSource::CommandLine) {
+            continue;
+        }
+        let values: Vec<Value> = matches
+            .get_raw(id)
+            .into_iter()
+            .flatten()
+            .map(|value| Value::String(value.to_string_lossy().into_owned()))
+            .collect();
+        let value = if !arg.get_action().takes_values() || values.is_empty() {
+            Value::Boolean(true)
+        } else if matches!(arg.get_action(), ArgAction::Append) || values.len() > 1 {
+            Value::Array(values)
+        } else {
+            values.into_iter().next().expect("one value")
+        };
+        options.insert(id.to_string(), value);
+    }
+    options
+}
+
+/// The command-line form of `value` for `arg`; `None` when it can't take it
+fn tokens(arg: &Arg, value: &Value) -> Option<Vec<OsString>> {
+    let flag = match (arg.get_long(), arg.get_short()) {
+        (Some(long), _) => format!("--{}", long),
+        (None, Some(short)) => format!("-{}", short),
+        (None, None) => return None,
+    };
+    let with_value = |value: String| -> Vec<OsString> {
+        if flag.starts_with("--") {
+            vec![format!("{}={}", flag, value).into()]
+        } else {
+            vec![flag.clone().into(), value.into()]
+        }
+    };
+    if !arg.get_action().takes_values() {
+        return match value {
+            Value::Boolean(true) => Some(vec![flag.into()]),
+            Value::Boolean(false) => Some(Vec::new()),
+            _ => None,
+        };
+    }
+    match value {
+        // An option whose value is optional, given bare
+        Value::Boolean(true) if arg.get_num_args().is_some_and(|range| range.min_values() == 0) => Some(vec![flag.into()]),
+        Value::Array(values) => values.iter().map(scalar).collect::<Option<Vec<_>>>().map(|values| values.into_iter().flat_map(with_value).collect()),
+        value => scalar(value).map(with_value),
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(value) => Some(value.clone()),
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) => Some(value.to_string()),
+        Value::Boolean(value) => Some(value.to_string()),
+        Value::Datetime(value) => Some(value.to_string()),
+        Value::Array(_) | Value::Table(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn store(dir: &tempfile::TempDir) -> PresetStore {
+        PresetStore { path: dir.path().join("bulk-unzip/config.toml") }
+    }
+
+    fn preset(command: &str, options: &str) -> Preset {
+        Preset { command: command.to_string(), options: options.parse().unwrap() }
+    }
+
+    #[test]
+    fn presets_are_saved_beside_the_rest_of_the_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = store(&dir);
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store.get("intake").unwrap_err().to_string(), format!("No preset named \"intake\"; none are saved in {:?}", store.path()));
+
+        fs::create_dir_all(store.path().parent().unwrap()).unwrap();
+        fs::write(store.path(), "theme = \"dark\"\n").unwrap();
+        let intake = preset("unzip", "workers = \"8\"\nskip_existing = true\nexclude = [\"*.tmp\", \"*.bak\"]");
+        store.save("intake", &intake).unwrap();
+        store.save("podcast", &preset("strip", "remove_all = true")).unwrap();
+        assert_eq!(store.get("intake").unwrap(), intake);
+        assert_eq!(store.list().unwrap().keys().collect::<Vec<_>>(), ["intake", "podcast"]);
+        assert!(store.get("intak").unwrap_err().to_string().ends_with("saved presets: intake, podcast"));
+
+        assert!(store.delete("intake").unwrap());
+        assert!(!store.delete("intake").unwrap());
+        let config: Table = fs::read_to_string(store.path()).unwrap().parse().unwrap();
+        assert_eq!(config["theme"].as_str(), Some("dark"));
+        assert_eq!(store.list().unwrap().keys().collect::<Vec<_>>(), ["podcast"]);
+        assert!(store.save(" ", &intake).is_err());
+    }
+
+    #[test]
+    fn forms_save_what_differs_from_their_defaults() {
+        let defaults = json!({ "workers": 4, "skip_existing": false, "exclude": [], "passwords": [] });
+        let options = json!({ "workers": 8, "skip_existing": false, "exclude": ["*.tmp"], "passwords": ["secret"], "output": null });
+        let saved = from_form(&options, &defaults);
+        assert_eq!(saved, "workers = 8\nexclude = [\"*.tmp\"]".parse::<Table>().unwrap());
+    }
+
+    #[test]
+    fn cli_presets_fit_into_form_fields() {
+        let form = json!({ "workers": 4, "skip_existing": false, "exclude": [], "ratio": 1.0, "mode": "rename" });
+        // The form's types, as the app checks them
+        let fits = |options: &Json| {
+            options["workers"].is_u64()
+                && options["skip_existing"].is_boolean()
+                && options["exclude"].as_array().is_some_and(|list| list.iter().all(Json::is_string))
+                && options["ratio"].is_f64()
+                && options["mode"].as_str().is_some_and(|mode| ["rename", "skip"].contains(&mode))
+        };
+        let cli = preset(
+            "unzip",
+            "workers = \"8\"\nskip_existing = \"true\"\nexclude = \"*.tmp, *.bak\"\nratio = \"0.5\"\nmode = \"clobber\"\nto_stdout = true\npasswords = [\"secret\"]",
+        );
+        let applied = apply_to_form(form, &cli, fits);
+        assert_eq!(applied.options, json!({ "workers": 8, "skip_existing": true, "exclude": ["*.tmp", "*.bak"], "ratio": 0.5, "mode": "rename" }));
+        assert_eq!(applied.ignored, ["mode", "passwords", "to_stdout"]);
+
+        // One value where the form holds a list
+        let applied = apply_to_form(json!({ "exclude": [] }), &preset("unzip", "exclude = \"*.tmp\""), |options| options["exclude"].is_array());
+        assert_eq!(applied.options, json!({ "exclude": ["*.tmp"] }));
+    }
+
+    #[test]
+    fn values_become_command_line_tokens() {
+        let flag = Arg::new("background").long("background").action(ArgAction::SetTrue);
+        assert_eq!(tokens(&flag, &Value::Boolean(true)), Some(vec!["--background".into()]));
+        assert_eq!(tokens(&flag, &Value::Boolean(false)), Some(Vec::new()));
+        assert_eq!(tokens(&flag, &Value::String("yes".into())), None);
+
+        let list = Arg::new("exclude").long("exclude").action(ArgAction::Append);
+        let values = Value::Array(vec![Value::String("*.tmp".into()), Value::Integer(3)]);
+        assert_eq!(tokens(&list, &values), Some(vec!["--exclude=*.tmp".into(), "--exclude=3".into()]));
+
+        let short = Arg::new("workers").short('w').action(ArgAction::Set);
+        assert_eq!(tokens(&short, &Value::Integer(8)), Some(vec!["-w".into(), "8".into()]));
+
+        let optional = Arg::new("state_file").long("state-file").num_args(0..=1).action(ArgAction::Set);
+        assert_eq!(tokens(&optional, &Value::Boolean(true)), Some(vec!["--state-file".into()]));
+        assert_eq!(tokens(&optional, &Value::Table(Table::new())), None);
+    }
+}
@@ -0,0 +1,191 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io;
+
+use crate::io_profile::IoSettings;
+
+/// What a background run did to the process's scheduling, for the run
+/// header and report
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct AppliedPriority {
+    /// e.g. `nice 10`, or why it was left alone
+    pub cpu: String,
+    /// e.g. `best-effort 7`, or why it was left alone
+    pub io: String,
+}
+
+impl AppliedPriority {
+    /// e.g. `CPU nice 10, I/O best-effort 7`
+    pub fn describe(&self) -> String {
+        format!("CPU {}, I/O {}", self.cpu, self.io)
+    }
+}
+
+/// Lowers the CPU and I/O priority of the running process. Each call only
+/// ever lowers it, and returns what it set.
+pub trait PriorityControl {
+    fn lower_cpu(&self) -> io::Result<String>;
+    fn lower_io(&self) -> io::Result<String>;
+}
+
+/// The platform's own scheduler controls; on platforms without any, both
+/// calls fail as unsupported and the priority is left alone
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemPriority;
+
+impl PriorityControl for SystemPriority {
+    fn lower_cpu(&self) -> io::Result<String> {
+        platform::lower_cpu()
+    }
+
+    fn lower_io(&self) -> io::Result<String> {
+        platform::lower_io()
+    }
+}
+
+/// Lower the process's priority through `control` for a background run. A
+/// step that fails is reported, not fatal: the run goes ahead at normal priority.
+pub fn enter_background(control: &dyn PriorityControl) -> AppliedPriority {
+    AppliedPriority {
+        cpu: control.lower_cpu().unwrap_or_else(|e| format!("unchanged ({})", e)),
+        io: control.lower_io().unwrap_or_else(|e| format!("unchanged ({})", e)),
+    }
+}
+
+/// Set a `--background` run up: lower the process's priority and, unless
+/// `--workers` was given, halve the workers `io` chose. What was applied is
+/// printed as part of the run header.
+pub fn run_in_background(io: &mut IoSettings, workers_given: bool) -> AppliedPriority {
+    if !workers_given {
+        io.workers = (io.workers / 2).max(1);
+    }
+    let priority = enter_background(&SystemPriority);
+    println!("🐢 Background priority: {}; {} workers", priority.describe(), io.workers);
+    priority
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::fs;
+    use std::io;
+
+    const BACKGROUND_NICE: libc::c_int = 10;
+    const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+    const IOPRIO_CLASS_SHIFT: libc::c_int = 13;
+    const IOPRIO_CLASS_BE: libc::c_int = 2;
+    /// The lowest level of the best-effort class; the idle class could starve a run outright
+    const IOPRIO_BE_LOWEST: libc::c_int = 7;
+
+    /// Every thread of the process. Priorities on Linux belong to threads,
+    /// and threads started later inherit them from the one starting them.
+    fn threads() -> Vec<libc::id_t> {
+        let tids: Vec<libc::id_t> = fs::read_dir("/proc/self/task")
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .filter_map(|entry| entry.file_name().to_str()?.parse().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if tids.is_empty() { vec![0] } else { tids }
+    }
+
+    /// A thread that exited since it was listed is no failure
+    fn check_exited(error: io::Error) -> io::Result<()> {
+        if error.raw_os_error() == Some(libc::ESRCH) { Ok(()) } else { Err(error) }
+    }
+
+    pub fn lower_cpu() -> io::Result<String> {
+        let mut nice = BACKGROUND_NICE;
+        for tid in threads() {
+            // -1 is a valid nice value as well as the error return, so a failed read counts as -1
+            let current = unsafe { libc::getpriority(libc::PRIO_PROCESS as _, tid) };
+            let target = current.max(BACKGROUND_NICE);
+            if unsafe { libc::setpriority(libc::PRIO_PROCESS as _, tid, target) } != 0 {
+                check_exited(io::Error::last_os_error())?;
+            }
+            nice = nice.max(target);
+        }
+        Ok(format!("nice {}", nice))
+    }
+
+    pub fn lower_io() -> io::Result<String> {
+        let priority = (IOPRIO_CLASS_BE << IOPRIO_CLASS_SHIFT) | IOPRIO_BE_LOWEST;
+        for tid in threads() {
+            if unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, tid, priority) } != 0 {
+                check_exited(io::Error::last_os_error())?;
+            }
+        }
+        Ok(format!("best-effort {}", IOPRIO_BE_LOWEST))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use std::io;
+
+    const BACKGROUND_NICE: libc::c_int = 10;
+    const IOPOL_TYPE_DISK: libc::c_int = 0;
+    const IOPOL_SCOPE_PROCESS: libc::c_int = 0;
+    const IOPOL_THROTTLE: libc::c_int = 3;
+
+    extern "C" {
+        fn setiopolicy_np(iotype: libc::c_int, scope: libc::c_int, policy: libc::c_int) -> libc::c_int;
+    }
+
+    pub fn lower_cpu() -> io::Result<String> {
+        let current = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        let target = current.max(BACKGROUND_NICE);
+        if unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, target) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(format!("nice {}", target))
+    }
+
+    pub fn lower_io() -> io::Result<String> {
+        if unsafe { setiopolicy_np(IOPOL_TYPE_DISK, IOPOL_SCOPE_PROCESS, IOPOL_THROTTLE) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok("throttled".to_string())
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use std::io;
+    use windows_sys::Win32::System::Threading::{
+        GetCurrentProcess, SetPriorityClass, BELOW_NORMAL_PRIORITY_CLASS, PROCESS_MODE_BACKGROUND_BEGIN,
+    };
+
+    pub fn lower_cpu() -> io::Result<String> {
+        if unsafe { SetPriorityClass(GetCurrentProcess(), BELOW_NORMAL_PRIORITY_CLASS) } == 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok("below normal".to_string())
+    }
+
+    /// Background mode also lowers memory priority; it fails when already on
+    pub fn lower_io() -> io::Result<String> {
+        if unsafe { SetPriorityClass(GetCurrentProcess(), PROCESS_MODE_BACKGROUND_BEGIN) } == 0 {
+            let error = io::Error::last_os_error();
+            // ERROR_PROCESS_MODE_ALREADY_BACKGROUND
+            if error.raw_os_error() != Some(402) {
+                return Err(error);
+            }
+        }
+        Ok("very low (background mode)".to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+mod platform {
+    use std::io;
+
+    pub fn lower_cpu() -> io::Result<String> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform"))
+    }
+
+    pub fn lower_io() -> io::Result<String> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform"))
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+
+use crate::metrics::MetricsSnapshot;
+
+/// What a progress event reports. Only `Progress` events are throttled;
+/// archive state changes are always sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProgressKind {
+    Progress,
+    ArchiveStarted,
+    ArchiveFinished,
+    ArchiveFailed,
+}
+
+/// One progress event of an extraction; `progress` and `total` are bytes
+/// across the whole batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnzipProgress {
+    pub kind: ProgressKind,
+    pub current_file: String,
+    pub progress: u64,
+    pub total: u64,
+    pub message: String,
+    pub metrics: MetricsSnapshot,
+}
+
+/// Where progress events go: the desktop app's `unzip-progress` event, or
+/// the command line's `--serve-status` endpoint
+pub trait ProgressSink: Send + Sync {
+    fn send(&self, event: &UnzipProgress);
+}
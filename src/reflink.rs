@@ -0,0 +1,119 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Copy `from` to `to` like [`fs::copy`], but let the copy share the
+/// original's data blocks where the filesystem can (btrfs, XFS, APFS), so
+/// only the blocks later rewritten take new space or I/O. Anywhere else,
+/// e.g. across filesystems or on ext4, it falls back to a plain copy.
+/// Returns whether the copy is a reflink.
+pub fn copy_file(from: &Path, to: &Path) -> io::Result<bool> {
+    copy_with(from, to, platform::reflink)
+}
+
+fn copy_with(from: &Path, to: &Path, reflink: impl FnOnce(&Path, &Path) -> io::Result<()>) -> io::Result<bool> {
+    if reflink(from, to).is_ok() {
+        return Ok(true);
+    }
+    fs::copy(from, to)?;
+    Ok(false)
+}
+
+#[cfg(all(feature = "reflink", target_os = "linux"))]
+mod platform {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    /// `FICLONE` fails on filesystems without shared extents and across
+    /// mounts, leaving `to` empty for the plain copy to fill
+    pub fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+        let source = File::open(from)?;
+        let permissions = source.metadata()?.permissions();
+        let target = File::create(to)?;
+        if unsafe { libc::ioctl(target.as_raw_fd(), libc::FICLONE as _, source.as_raw_fd()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        target.set_permissions(permissions)
+    }
+}
+
+#[cfg(all(feature = "reflink", target_os = "macos"))]
+mod platform {
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    /// `clonefile` refuses to replace a file, so an existing `to` falls back
+    /// to the plain copy as well
+    pub fn reflink(from: &Path, to: &Path) -> io::Result<()> {
+        let from = CString::new(from.as_os_str().as_bytes())?;
+        let to = CString::new(to.as_os_str().as_bytes())?;
+        if unsafe { libc::clonefile(from.as_ptr(), to.as_ptr(), 0) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(all(feature = "reflink", any(target_os = "linux", target_os = "macos"))))]
+mod platform {
+    use std::io;
+    use std::path::Path;
+
+    pub fn reflink(_from: &Path, _to: &Path) -> io::Result<()> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "not supported on this platform"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_match_their_source_whichever_way_they_are_made() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from.mp3"), dir.path().join("to.mp3"));
+        fs::write(&from, vec![7u8; 64 * 1024]).unwrap();
+        let mut permissions = fs::metadata(&from).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&from, permissions).unwrap();
+
+        copy_file(&from, &to).unwrap();
+        assert_eq!(fs::read(&to).unwrap(), fs::read(&from).unwrap());
+        assert!(fs::metadata(&to).unwrap().permissions().readonly());
+    }
+
+    #[test]
+    fn a_failed_reflink_falls_back_to_a_plain_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let (from, to) = (dir.path().join("from.mp3"), dir.path().join("to.mp3"));
+        fs::write(&from, b"audio").unwrap();
+
+        // As FICLONE does, leave behind the file it created
+        let unsupported = |_: &Path, to: &Path| {
+            fs::write(to, b"")?;
+            Err(io::Error::from(io::ErrorKind::Unsupported))
+        };
+        assert!(!copy_with(&from, &to, unsupported).unwrap());
+        assert_eq!(fs::read(&to).unwrap(), b"audio");
+
+        assert!(copy_with(&from, &dir.path().join("cloned.mp3"), |_, _| Ok(())).unwrap());
+        assert!(copy_with(&dir.path().join("missing.mp3"), &to, unsupported).is_err());
+    }
+
+    /// Set `REFLINK_TEST_DIR` to a directory on btrfs, XFS or APFS
+    #[test]
+    #[ignore = "needs REFLINK_TEST_DIR on a filesystem with shared extents"]
+    fn copies_on_a_cloning_filesystem_are_reflinks() {
+        let root = std::env::var_os("REFLINK_TEST_DIR").expect("REFLINK_TEST_DIR is not set");
+        let dir = tempfile::tempdir_in(root).unwrap();
+        let (from, to) = (dir.path().join("from.mp3"), dir.path().join("to.mp3"));
+        fs::write(&from, vec![7u8; 1024 * 1024]).unwrap();
+
+        assert!(copy_file(&from, &to).unwrap());
+        assert_eq!(fs::read(&to).unwrap(), fs::read(&from).unwrap());
+    }
+}
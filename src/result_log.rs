@@ -0,0 +1,181 @@
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::run_report::result_status;
+
+/// Failures a streamed run keeps in memory, the most recent ones
+pub const RECENT_ERRORS: usize = 100;
+
+/// A failed result kept by [`ResultLog`]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RecentError {
+    pub path: PathBuf,
+    pub error: String,
+}
+
+/// Per-file results appended to an NDJSON file as they finish, for runs too
+/// large to hold every result in memory. Only the count of each status and
+/// the last [`RECENT_ERRORS`] failures stay behind.
+#[derive(Debug)]
+pub struct ResultLog {
+    path: PathBuf,
+    state: Mutex<LogState>,
+}
+
+#[derive(Debug)]
+struct LogState {
+    writer: BufWriter<File>,
+    counts: BTreeMap<String, usize>,
+    recent_errors: VecDeque<RecentError>,
+    /// The first write that failed; later results are only counted
+    write_error: Option<String>,
+}
+
+/// Where the results of the report at `report` are streamed: beside it,
+/// with an `.ndjson` extension
+pub fn results_file_for(report: &Path) -> PathBuf {
+    report.with_extension("ndjson")
+}
+
+impl ResultLog {
+    pub fn create(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        let file = File::create(path)
+            .with_context(|| format!("Failed to create results file {:?}", path))?;
+        Ok(ResultLog {
+            path: path.to_path_buf(),
+            state: Mutex::new(LogState {
+                writer: BufWriter::new(file),
+                counts: BTreeMap::new(),
+                recent_errors: VecDeque::new(),
+                write_error: None,
+            }),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append `result` as one line and count its status
+    pub fn record(&self, result: &impl Serialize) {
+        let value = serde_json::to_value(result)
+            .unwrap_or_else(|e| serde_json::Value::String(format!("unserializable: {}", e)));
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((path, status)) = result_status(&value) {
+            *state.counts.entry(status).or_default() += 1;
+            if let Some(error) = value.get("error").and_then(|error| error.as_str()) {
+                if state.recent_errors.len() == RECENT_ERRORS {
+                    state.recent_errors.pop_front();
+                }
+                state.recent_errors.push_back(RecentError { path, error: error.to_string() });
+            }
+        }
+        if state.write_error.is_none() {
+            let line = serde_json::to_string(&value).unwrap_or_default();
+            if let Err(e) = writeln!(state.writer, "{}", line) {
+                state.write_error = Some(format!("Failed to write results file {:?}: {}", self.path, e));
+            }
+        }
+    }
+
+    /// How many results had each status so far
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).counts.clone()
+    }
+
+    /// The last [`RECENT_ERRORS`] failures, oldest first
+    pub fn recent_errors(&self) -> Vec<RecentError> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner()).recent_errors.iter().cloned().collect()
+    }
+
+    /// Write out what is buffered, failing when any result couldn't be written
+    pub fn flush(&self) -> Result<()> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(error) = &state.write_error {
+            bail!("{}", error);
+        }
+        state.writer.flush()
+            .with_context(|| format!("Failed to write results file {:?}", self.path))
+    }
+}
+
+/// Every result of a results file, in the order they were written
+pub fn read_results(path: &Path) -> Result<Vec<serde_json::Value>> {
+    read_page(path, 0, usize::MAX)
+}
+
+/// Up to `limit` results of a results file, from the `offset`th on, for the
+/// app to page through a streamed run
+pub fn read_page(path: &Path, offset: usize, limit: usize) -> Result<Vec<serde_json::Value>> {
+    let file = File::open(path)
+        .with_context(|| format!("Failed to open results file {:?}", path))?;
+    BufReader::new(file)
+        .lines()
+        .filter(|line| line.as_ref().map_or(true, |line| !line.trim().is_empty()))
+        .skip(offset)
+        .take(limit)
+        .map(|line| {
+            let line = line.with_context(|| format!("Failed to read results file {:?}", path))?;
+            serde_json::from_str(&line).with_context(|| format!("{:?} holds a line that isn't a result", path))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_stripper::StripResult;
+
+    /// A batch far past what a run would keep in memory leaves only the
+    /// counts and the last few failures behind
+    #[test]
+    fn large_batches_keep_only_counters_in_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = ResultLog::create(&dir.path().join("reports/run.ndjson")).unwrap();
+        let total = 200_000;
+        for i in 0..total {
+            let path = PathBuf::from(format!("music/{}.mp3", i));
+            if i % 10 == 0 {
+                log.record(&StripResult::failed(&path, &anyhow::anyhow!("damaged frame {}", i)));
+            } else {
+                log.record(&serde_json::json!({ "path": path, "status": "Stripped" }));
+            }
+        }
+        log.flush().unwrap();
+
+        assert_eq!(log.counts(), BTreeMap::from([("Failed".to_string(), total / 10), ("Stripped".to_string(), total - total / 10)]));
+        let recent = log.recent_errors();
+        assert_eq!(recent.len(), RECENT_ERRORS);
+        assert_eq!(recent.last().unwrap().path, Path::new("music/199990.mp3"));
+        assert_eq!(recent[0].path, PathBuf::from(format!("music/{}.mp3", total - 10 * RECENT_ERRORS)));
+        assert_eq!(recent[0].error, format!("damaged frame {}", total - 10 * RECENT_ERRORS));
+
+        let page = read_page(log.path(), 199_998, 10).unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[1]["path"], "music/199999.mp3");
+        assert_eq!(read_results(log.path()).unwrap().len(), total);
+    }
+
+    #[test]
+    fn pages_skip_blank_lines_and_refuse_garbage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.ndjson");
+        fs::write(&path, "{\"path\":\"a.mp3\"}\n\n{\"path\":\"b.mp3\"}\n").unwrap();
+        assert_eq!(read_page(&path, 1, 5).unwrap(), [serde_json::json!({ "path": "b.mp3" })]);
+        assert!(read_page(&path, 5, 5).unwrap().is_empty());
+
+        fs::write(&path, "{\"path\":\"a.mp3\"}\nnot json\n").unwrap();
+        assert!(read_results(&path).unwrap_err().to_string().ends_with("holds a line that isn't a result"));
+        assert_eq!(results_file_for(Path::new("out/report.json")), Path::new("out/report.ndjson"));
+    }
+}
@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// Collects a batch's results in their final order and hands each one to a
+/// callback as soon as it's known, so the frontend can fill its table while
+/// the run goes on. The index the callback gets is the result's position in
+/// the vector [`into_results`](Self::into_results) returns.
+pub struct ResultStream<T, F> {
+    index: HashMap<PathBuf, usize>,
+    slots: Mutex<Vec<Option<T>>>,
+    on_finished: F,
+    /// Set by [`unordered`](Self::unordered): the total and how many finished
+    unkept: Option<(usize, AtomicUsize)>,
+}
+
+impl<T, F: Fn(usize, usize, &T)> ResultStream<T, F> {
+    /// `order` lists every path the batch reports, in final order; each must
+    /// finish once. `on_finished` gets the index, the total and the result.
+    pub fn new(order: Vec<PathBuf>, on_finished: F) -> Self {
+        let slots = Mutex::new(order.iter().map(|_| None).collect());
+        let index = order.into_iter().enumerate().map(|(i, path)| (path, i)).collect();
+        ResultStream { index, slots, on_finished, unkept: None }
+    }
+
+    /// Hand each of `total` results to `on_finished` in the order they
+    /// finish, without keeping any, for runs whose results are streamed to disk
+    pub fn unordered(total: usize, on_finished: F) -> Self {
+        ResultStream { index: HashMap::new(), slots: Mutex::new(Vec::new()), on_finished, unkept: Some((total, AtomicUsize::new(0))) }
+    }
+
+    /// Report `result` for `path` and keep it at its index. A path missing
+    /// from the order goes last rather than being lost.
+    pub fn finish(&self, path: &Path, result: T) {
+        if let Some((total, finished)) = &self.unkept {
+            let index = finished.fetch_add(1, Ordering::Relaxed);
+            (self.on_finished)(index, (*total).max(index + 1), &result);
+            return;
+        }
+        let mut slots = self.slots.lock().unwrap_or_else(|e| e.into_inner());
+        let index = match self.index.get(path) {
+            Some(&index) => index,
+            None => {
+                slots.push(None);
+                slots.len() - 1
+            }
+        };
+        (self.on_finished)(index, slots.len(), &result);
+        slots[index] = Some(result);
+    }
+
+    /// Every finished result, in order; none for an [`unordered`](Self::unordered) stream
+    pub fn into_results(self) -> Vec<T> {
+        self.slots.into_inner().unwrap_or_else(|e| e.into_inner()).into_iter().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_keep_their_order_whatever_finishes_first() {
+        let order: Vec<PathBuf> = ["a.zip", "b.zip", "c.zip"].iter().map(PathBuf::from).collect();
+        let seen = Mutex::new(Vec::new());
+        let stream = ResultStream::new(order, |index, total, result: &&str| seen.lock().unwrap().push((index, total, *result)));
+
+        stream.finish(Path::new("c.zip"), "c");
+        stream.finish(Path::new("a.zip"), "a");
+        stream.finish(Path::new("stray.zip"), "stray");
+        stream.finish(Path::new("b.zip"), "b");
+
+        assert_eq!(stream.into_results(), ["a", "b", "c", "stray"]);
+        assert_eq!(seen.into_inner().unwrap(), [(2, 3, "c"), (0, 3, "a"), (3, 4, "stray"), (1, 4, "b")]);
+    }
+
+    #[test]
+    fn unordered_streams_count_but_keep_nothing() {
+        let seen = Mutex::new(Vec::new());
+        let stream = ResultStream::unordered(2, |index, total, result: &u32| seen.lock().unwrap().push((index, total, *result)));
+
+        stream.finish(Path::new("b.zip"), 20);
+        stream.finish(Path::new("a.zip"), 10);
+        stream.finish(Path::new("extra.zip"), 30);
+
+        assert!(stream.into_results().is_empty());
+        assert_eq!(seen.into_inner().unwrap(), [(0, 2, 20), (1, 2, 10), (2, 3, 30)]);
+    }
+}
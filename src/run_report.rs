@@ -0,0 +1,352 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+use crate::result_log::{read_results, RecentError, ResultLog};
+use crate::schemas::SCHEMA_VERSION;
+
+/// Where the end-of-run report goes: the `--report` and `--no-report` flags
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ReportArgs {
+    /// Write the run report here instead of `bulk-unzip-report-<timestamp>.json`
+    /// in the output directory
+    #[arg(long, conflicts_with = "no_report")]
+    pub report: Option<PathBuf>,
+
+    /// Don't write a run report
+    #[arg(long)]
+    pub no_report: bool,
+
+    /// Append each result to `<report>.ndjson` as it finishes instead of
+    /// keeping it for the report, which then holds only counts; for runs
+    /// over millions of files
+    #[arg(long, conflicts_with = "no_report")]
+    pub stream_results: bool,
+}
+
+impl ReportArgs {
+    /// Where to write the report, or `None` when disabled. Dry runs only write
+    /// one when a path was given, so they still touch nothing by default.
+    pub fn path(&self, output_dir: &Path, dry_run: bool) -> Option<PathBuf> {
+        if self.no_report {
+            return None;
+        }
+        match &self.report {
+            Some(path) => Some(path.clone()),
+            None if dry_run => None,
+            None => Some(output_dir.join(default_report_name())),
+        }
+    }
+}
+
+/// Options whose values the command line in a report leaves out
+const SECRET_FLAGS: &[&str] = &["--bearer-token", "--header"];
+
+/// This process's arguments without the program name, with the values of
+/// [`SECRET_FLAGS`] replaced, for [`RunReport::set_command_line`]
+pub fn command_line() -> Vec<String> {
+    let mut args = Vec::new();
+    let mut redact_next = false;
+    for arg in std::env::args().skip(1) {
+        if redact_next {
+            args.push("<redacted>".to_string());
+            redact_next = false;
+        } else if let Some(flag) = SECRET_FLAGS.iter().find(|flag| arg.starts_with(&format!("{}=", flag))) {
+            args.push(format!("{}=<redacted>", flag));
+        } else {
+            redact_next = SECRET_FLAGS.contains(&arg.as_str());
+            args.push(arg);
+        }
+    }
+    args
+}
+
+/// `bulk-unzip-report-<timestamp>.json`, in local time
+pub fn default_report_name() -> String {
+    format!("bulk-unzip-report-{}.json", Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Machine-readable record of one run: the options, what the scan found,
+/// every result and error, and how long it took. Filled in as the run goes,
+/// so a run that fails part way still records what it got to.
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
+pub struct RunReport {
+    /// [`SCHEMA_VERSION`] when written
+    #[serde(default)]
+    pub schema_version: u32,
+    pub command: String,
+    /// The arguments the run was started with, secrets redacted, so `retry`
+    /// can run it again; empty for runs not started from the command line
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub argv: Vec<String>,
+    /// RFC 3339, local time
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub elapsed_ms: u64,
+    pub options: serde_json::Value,
+    pub scan: serde_json::Value,
+    /// The I/O profile and the worker count and buffer size it led to
+    pub io: serde_json::Value,
+    /// Groups of byte-identical archives, when duplicates were skipped
+    pub duplicates: serde_json::Value,
+    /// `--latest-per-group` groups and the archive extracted of each
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub groups: serde_json::Value,
+    /// Files found and what became of them, by input directory, when several were scanned
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub roots: serde_json::Value,
+    /// How many of the discovered files `limit` kept, and the seed of a random sample
+    pub sample: serde_json::Value,
+    /// Bytes the run was about to write against what its output volume had free
+    #[serde(default)]
+    pub space: serde_json::Value,
+    /// The CPU and I/O priority a background run lowered the process to
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub priority: serde_json::Value,
+    /// Every warning category by its stable name, with what it covers,
+    /// whether `--strict` failed archives on it and how many raised it
+    #[serde(default, skip_serializing_if = "serde_json::Value::is_null")]
+    pub warnings: serde_json::Value,
+    pub results: Vec<serde_json::Value>,
+    /// NDJSON file the results were streamed to instead of `results`, one
+    /// per line in the order they finished; it sits beside the report
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub results_file: Option<PathBuf>,
+    /// How many streamed results had each status
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub result_counts: BTreeMap<String, usize>,
+    /// The last failures among the streamed results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recent_errors: Vec<RecentError>,
+    pub errors: serde_json::Value,
+    /// Why the run stopped early: the error, or the panic message
+    pub failure: Option<String>,
+    /// Each `retry` merged into this report: when, which statuses and how many results
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retries: Vec<serde_json::Value>,
+    #[serde(skip, default = "Instant::now")]
+    started: Instant,
+    #[serde(skip)]
+    log: Option<Arc<ResultLog>>,
+}
+
+impl RunReport {
+    pub fn new(command: &str, options: &impl Serialize) -> Self {
+        RunReport {
+            schema_version: SCHEMA_VERSION,
+            command: command.to_string(),
+            argv: Vec::new(),
+            started_at: Local::now().to_rfc3339(),
+            finished_at: None,
+            elapsed_ms: 0,
+            options: to_value(options),
+            scan: serde_json::Value::Null,
+            io: serde_json::Value::Null,
+            duplicates: serde_json::Value::Null,
+            groups: serde_json::Value::Null,
+            roots: serde_json::Value::Null,
+            sample: serde_json::Value::Null,
+            space: serde_json::Value::Null,
+            priority: serde_json::Value::Null,
+            warnings: serde_json::Value::Null,
+            results: Vec::new(),
+            results_file: None,
+            result_counts: BTreeMap::new(),
+            recent_errors: Vec::new(),
+            errors: serde_json::Value::Null,
+            failure: None,
+            retries: Vec::new(),
+            started: Instant::now(),
+            log: None,
+        }
+    }
+
+    /// Read a report written by an earlier run. Reports from a newer version
+    /// are refused rather than half understood. Streamed results are read
+    /// back into `results`, so the report is written whole again.
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read report {:?}", path))?;
+        let mut report: RunReport = serde_json::from_str(&json)
+            .with_context(|| format!("{:?} is not a bulk-unzip run report", path))?;
+        if let Some(file_name) = report.results_file.take().as_deref().and_then(Path::file_name) {
+            report.results = read_results(&path.with_file_name(file_name))?;
+            report.result_counts.clear();
+            report.recent_errors.clear();
+        }
+        if report.schema_version > SCHEMA_VERSION {
+            anyhow::bail!(
+                "Report {:?} has schema version {}, newer than the {} this version reads",
+                path,
+                report.schema_version,
+                SCHEMA_VERSION
+            );
+        }
+        Ok(report)
+    }
+
+    pub fn set_command_line(&mut self, argv: Vec<String>) {
+        self.argv = argv;
+    }
+
+    /// Paths of the results whose status is one of `statuses`, in report order
+    pub fn paths_with_status(&self, statuses: &[String]) -> Vec<PathBuf> {
+        self.results
+            .iter()
+            .filter_map(result_status)
+            .filter(|(_, status)| statuses.contains(status))
+            .map(|(path, _)| path)
+            .collect()
+    }
+
+    /// Replace the results `retry` ran again with its results, in place, and
+    /// take its errors and failure; the rest of this report stays as it was
+    pub fn merge_retry(&mut self, retry: RunReport, statuses: &[String]) {
+        let mut retried: Vec<(Option<PathBuf>, serde_json::Value)> = retry
+            .results
+            .into_iter()
+            .map(|result| (result_status(&result).map(|(path, _)| path), result))
+            .collect();
+        for result in &mut self.results {
+            let Some((path, _)) = result_status(result) else { continue };
+            if let Some(i) = retried.iter().position(|(retried_path, _)| retried_path.as_ref() == Some(&path)) {
+                *result = retried.remove(i).1;
+            }
+        }
+        self.results.extend(retried.into_iter().map(|(_, result)| result));
+        self.retries.push(serde_json::json!({
+            "started_at": retry.started_at,
+            "argv": retry.argv,
+            "statuses": statuses,
+        }));
+        self.errors = retry.errors;
+        self.failure = retry.failure;
+        self.schema_version = SCHEMA_VERSION;
+    }
+
+    pub fn set_scan(&mut self, scan: &impl Serialize) {
+        self.scan = to_value(scan);
+    }
+
+    pub fn set_io(&mut self, io: &impl Serialize) {
+        self.io = to_value(io);
+    }
+
+    pub fn set_duplicates(&mut self, duplicates: &impl Serialize) {
+        self.duplicates = to_value(duplicates);
+    }
+
+    pub fn set_groups(&mut self, groups: &impl Serialize) {
+        self.groups = to_value(groups);
+    }
+
+    pub fn set_roots(&mut self, roots: &impl Serialize) {
+        self.roots = to_value(roots);
+    }
+
+    pub fn set_sample(&mut self, sample: &impl Serialize) {
+        self.sample = to_value(sample);
+    }
+
+    pub fn set_space(&mut self, space: &impl Serialize) {
+        self.space = to_value(space);
+    }
+
+    pub fn set_priority(&mut self, priority: &impl Serialize) {
+        self.priority = to_value(priority);
+    }
+
+    pub fn set_warnings(&mut self, warnings: &impl Serialize) {
+        self.warnings = to_value(warnings);
+    }
+
+    /// Stream results to the NDJSON file at `path` from now on
+    pub fn stream_results(&mut self, path: &Path) -> Result<()> {
+        self.log = Some(Arc::new(ResultLog::create(path)?));
+        self.results_file = Some(path.to_path_buf());
+        Ok(())
+    }
+
+    /// The file results are streamed to, for workers that record their
+    /// results themselves while the report is borrowed elsewhere
+    pub fn result_log(&self) -> Option<Arc<ResultLog>> {
+        self.log.clone()
+    }
+
+    pub fn add_result(&mut self, result: &impl Serialize) {
+        match &self.log {
+            Some(log) => log.record(result),
+            None => self.results.push(to_value(result)),
+        }
+    }
+
+    pub fn set_errors(&mut self, errors: &impl Serialize) {
+        self.errors = to_value(errors);
+    }
+
+    pub fn fail(&mut self, failure: impl Into<String>) {
+        self.failure = Some(failure.into());
+    }
+
+    /// Stamp the finish time and write the report via a temporary file and
+    /// rename, so readers never see a partial file
+    pub fn write(&mut self, path: &Path) -> Result<()> {
+        self.finished_at = Some(Local::now().to_rfc3339());
+        self.elapsed_ms = self.started.elapsed().as_millis() as u64;
+        if let Some(log) = &self.log {
+            if let Err(e) = log.flush() {
+                self.failure.get_or_insert_with(|| format!("{:#}", e));
+            }
+            self.result_counts = log.counts();
+            self.recent_errors = log.recent_errors();
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let json = serde_json::to_string_pretty(self)?;
+        fs::write(&tmp, json)
+            .with_context(|| format!("Failed to write {:?}", tmp))?;
+        fs::rename(&tmp, path)
+            .with_context(|| format!("Failed to replace {:?}", path))?;
+        Ok(())
+    }
+}
+
+/// Path and status of one recorded result: the path is `archive` or `path`,
+/// the status sits at the top level or in `outcome`, and a status carrying
+/// data, such as `{"SkippedDuplicateOf": ...}`, is named by its variant
+pub fn result_status(result: &serde_json::Value) -> Option<(PathBuf, String)> {
+    let path = result.get("archive").or_else(|| result.get("path"))?.as_str()?;
+    let status = result.get("status").or_else(|| result.get("outcome")?.get("status"))?;
+    let status = match status {
+        serde_json::Value::String(status) => status.clone(),
+        serde_json::Value::Object(variant) => variant.keys().next()?.clone(),
+        _ => return None,
+    };
+    Some((PathBuf::from(path), status))
+}
+
+/// Values that fail to serialize are recorded as their error instead
+fn to_value(value: &impl Serialize) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_else(|e| serde_json::Value::String(format!("unserializable: {}", e)))
+}
+
+/// The message of a caught panic, for [`RunReport::fail`]
+pub fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
@@ -0,0 +1,102 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Which of the discovered files `--limit` keeps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SampleMode {
+    /// The first N in sort order
+    #[default]
+    First,
+    /// N picked at random, still processed in sort order
+    Random,
+}
+
+/// `--limit`, `--sample` and `--seed`: how many of the discovered files a run
+/// processes, and which
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Sample {
+    pub limit: Option<usize>,
+    pub mode: SampleMode,
+    /// Seed for a random sample; taken from the clock when absent
+    pub seed: Option<u64>,
+}
+
+/// What [`Sample::apply`] kept
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SampleSummary {
+    pub kept: usize,
+    pub discovered: usize,
+    /// The seed a random sample used, so it can be repeated
+    pub seed: Option<u64>,
+}
+
+impl SampleSummary {
+    /// e.g. `processing 10 of 3,481 discovered (random, --seed 42 to repeat)`
+    pub fn describe(&self) -> String {
+        let random = self.seed.map(|seed| format!(" (random, --seed {} to repeat)", seed)).unwrap_or_default();
+        format!("processing {} of {} discovered{}", thousands(self.kept), thousands(self.discovered), random)
+    }
+}
+
+fn thousands(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::new();
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// splitmix64, which turns any seed, 0 included, into well-spread values
+struct SplitMix(u64);
+
+impl SplitMix {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+impl Sample {
+    pub fn new(limit: Option<usize>, mode: SampleMode, seed: Option<u64>) -> Self {
+        Sample { limit, mode, seed }
+    }
+
+    /// Keep at most `limit` of `items`, which are already sorted and filtered,
+    /// leaving them in their order. The summary is `None` without a limit.
+    pub fn apply<T>(&self, items: Vec<T>) -> (Vec<T>, Option<SampleSummary>) {
+        let Some(limit) = self.limit else { return (items, None) };
+        let discovered = items.len();
+        let kept = limit.min(discovered);
+        if self.mode == SampleMode::First {
+            let mut items = items;
+            items.truncate(kept);
+            return (items, Some(SampleSummary { kept, discovered, seed: None }));
+        }
+
+        // A clock seed is kept to 32 bits, short enough to retype and exact in JavaScript
+        let seed = self.seed.unwrap_or_else(|| {
+            SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_nanos() as u64 & 0xffff_ffff)
+        });
+        // Partial Fisher-Yates: the first `kept` slots end up a uniform sample
+        let mut rng = SplitMix(seed);
+        let mut indices: Vec<usize> = (0..discovered).collect();
+        for i in 0..kept {
+            let j = i + (rng.next() % (discovered - i) as u64) as usize;
+            indices.swap(i, j);
+        }
+        let mut chosen = vec![false; discovered];
+        for &i in &indices[..kept] {
+            chosen[i] = true;
+        }
+        let items = items.into_iter().zip(chosen).filter_map(|(item, keep)| keep.then_some(item)).collect();
+        (items, Some(SampleSummary { kept, discovered, seed: Some(seed) }))
+    }
+}
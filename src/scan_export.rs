@@ -0,0 +1,226 @@
+use anyhow::{bail, Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use crate::schemas::SCHEMA_VERSION;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Format named by the report's extension, `.csv` or `.json`
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()).map(str::to_lowercase).as_deref() {
+            Some("csv") => Ok(ExportFormat::Csv),
+            Some("json") => Ok(ExportFormat::Json),
+            _ => bail!("Can't tell the report format from {:?}; use a .csv or .json extension", path),
+        }
+    }
+}
+
+/// One scanned file, flattened so zips and MP3s share a spreadsheet.
+/// Columns that don't apply to the file's kind are left empty.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct ScanRow {
+    /// `zip`, `comic`, `book` or `mp3`
+    pub kind: &'static str,
+    pub path: String,
+    pub size: u64,
+    pub entries: Option<usize>,
+    pub uncompressed_size: Option<u64>,
+    /// Why the scan flagged the file, absent when it looked fine
+    pub suspect: Option<String>,
+    pub has_metadata: Option<bool>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub has_art: Option<bool>,
+    /// Archive modification time, seconds since the Unix epoch
+    pub mtime: Option<i64>,
+    /// Archive SHA-256, when the scan hashed
+    pub sha256: Option<String>,
+    /// ID3v2 version of an MP3's tag, e.g. `ID3v2.3`
+    pub tag_version: Option<String>,
+    /// Size an MP3's ID3v2 header declares, padding included
+    pub tag_size_bytes: Option<u64>,
+    /// Whether an archive has zip64 records
+    pub zip64: Option<bool>,
+}
+
+/// A JSON scan report: the rows under `files`, beside the schema version
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ScanExport<'a> {
+    pub schema_version: u32,
+    pub files: &'a [ScanRow],
+}
+
+const COLUMNS: [&str; 16] = [
+    "kind",
+    "path",
+    "size",
+    "entries",
+    "uncompressed_size",
+    "suspect",
+    "has_metadata",
+    "title",
+    "artist",
+    "album",
+    "has_art",
+    "mtime",
+    "sha256",
+    "tag_version",
+    "tag_size_bytes",
+    "zip64",
+];
+
+impl ScanRow {
+    fn csv_fields(&self) -> [String; 16] {
+        fn opt<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
+        }
+        [
+            self.kind.to_string(),
+            self.path.clone(),
+            self.size.to_string(),
+            opt(&self.entries),
+            opt(&self.uncompressed_size),
+            opt(&self.suspect),
+            opt(&self.has_metadata),
+            opt(&self.title),
+            opt(&self.artist),
+            opt(&self.album),
+            opt(&self.has_art),
+            opt(&self.mtime),
+            opt(&self.sha256),
+            opt(&self.tag_version),
+            opt(&self.tag_size_bytes),
+            opt(&self.zip64),
+        ]
+    }
+}
+
+/// Write `rows` to `path`, row by row through a buffered writer
+pub fn write_scan(rows: &[ScanRow], path: &Path, format: ExportFormat) -> Result<()> {
+    let file = fs::File::create(path)
+        .with_context(|| format!("Failed to create report {:?}", path))?;
+    let mut writer = BufWriter::new(file);
+    match format {
+        ExportFormat::Csv => write_csv(rows, &mut writer),
+        ExportFormat::Json => write_json(rows, &mut writer),
+    }
+    .and_then(|_| writer.flush())
+    .with_context(|| format!("Failed to write report {:?}", path))
+}
+
+fn write_csv(rows: &[ScanRow], writer: &mut impl Write) -> io::Result<()> {
+    write!(writer, "{}\r\n", COLUMNS.join(","))?;
+    for row in rows {
+        let fields = row.csv_fields();
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            writer.write_all(csv_field(field).as_bytes())?;
+        }
+        writer.write_all(b"\r\n")?;
+    }
+    Ok(())
+}
+
+/// Quote a field containing separators, quotes, line breaks or edge
+/// whitespace, doubling embedded quotes (RFC 4180)
+fn csv_field(value: &str) -> Cow<'_, str> {
+    let needs_quotes = value.contains([',', '"', '\n', '\r']) || value.trim() != value;
+    if needs_quotes {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// A [`ScanExport`], one row per line
+fn write_json(rows: &[ScanRow], writer: &mut impl Write) -> io::Result<()> {
+    write!(writer, "{{\"schema_version\":{},\"files\":[", SCHEMA_VERSION)?;
+    for (i, row) in rows.iter().enumerate() {
+        writer.write_all(if i == 0 { b"\n  " } else { b",\n  " })?;
+        serde_json::to_writer(&mut *writer, row)?;
+    }
+    writer.write_all(b"\n]}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows() -> Vec<ScanRow> {
+        vec![
+            ScanRow { kind: "zip", path: "/in/a.zip".to_string(), size: 10, entries: Some(2), uncompressed_size: Some(30), zip64: Some(false), ..ScanRow::default() },
+            ScanRow {
+                kind: "mp3",
+                path: "/in/Me, \"Myself\".mp3".to_string(),
+                size: 20,
+                has_metadata: Some(true),
+                title: Some(" padded ".to_string()),
+                album: Some("two\nlines".to_string()),
+                ..ScanRow::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_they_need_it() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("a\r\nb"), "\"a\r\nb\"");
+        assert_eq!(csv_field(" edge"), "\" edge\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_has_a_header_and_a_line_per_row() {
+        let mut out = Vec::new();
+        write_csv(&rows(), &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+
+        let expected = [
+            COLUMNS.join(","),
+            "zip,/in/a.zip,10,2,30,,,,,,,,,,,false".to_string(),
+            "mp3,\"/in/Me, \"\"Myself\"\".mp3\",20,,,,true,\" padded \",,\"two\nlines\",,,,,,".to_string(),
+        ];
+        assert_eq!(csv, expected.join("\r\n") + "\r\n");
+    }
+
+    #[test]
+    fn json_holds_the_schema_version_and_every_row() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.json");
+        write_scan(&rows(), &path, ExportFormat::Json).unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["schema_version"], SCHEMA_VERSION);
+        assert_eq!(json["files"].as_array().unwrap().len(), 2);
+        assert_eq!(json["files"][0]["entries"], 2);
+        assert_eq!(json["files"][1]["path"], "/in/Me, \"Myself\".mp3");
+
+        write_scan(&[], &path, ExportFormat::Json).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["files"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn the_format_comes_from_the_extension() {
+        assert_eq!(ExportFormat::from_path(Path::new("report.CSV")).unwrap(), ExportFormat::Csv);
+        assert_eq!(ExportFormat::from_path(Path::new("report.json")).unwrap(), ExportFormat::Json);
+        assert!(ExportFormat::from_path(Path::new("report.txt")).is_err());
+        assert!(ExportFormat::from_path(Path::new("report")).is_err());
+    }
+}
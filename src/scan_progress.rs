@@ -0,0 +1,153 @@
+use anyhow::{bail, Result};
+use indicatif::{ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use crate::format::format_size;
+
+/// Refresh the spinner message, or call back, after this many walked entries
+const PROGRESS_INTERVAL: u64 = 100;
+
+/// Prefix of the temporary directories this tool creates while working
+pub const STAGING_PREFIX: &str = ".bulk-unzip-";
+
+type ProgressCallback<'a> = Box<dyn Fn(&ScanProgress) + Send + Sync + 'a>;
+
+/// Counts of a directory walk so far; the payload of the app's `scan-progress` events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanProgress {
+    pub directories: u64,
+    pub files_matched: u64,
+    pub bytes: u64,
+    /// Bytes read so far when the scan also hashes what it found
+    pub bytes_hashed: u64,
+    /// AppleDouble `._*` and empty files passed over
+    pub sidecars_skipped: u64,
+}
+
+/// Live counts for a directory walk, shown on a spinner or handed to a
+/// callback, which can also stop the walk
+pub struct ScanMonitor<'a> {
+    counts: ScanProgress,
+    visited: u64,
+    spinner: ProgressBar,
+    on_progress: Option<ProgressCallback<'a>>,
+    cancel: Option<&'a AtomicBool>,
+    /// Canonical output directory of the current run, never walked into
+    output: Option<PathBuf>,
+}
+
+impl Default for ScanMonitor<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> ScanMonitor<'a> {
+    pub fn new() -> Self {
+        let spinner = ProgressBar::new_spinner();
+        spinner.set_style(ProgressStyle::default_spinner().template("{spinner} {msg}").unwrap());
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        ScanMonitor {
+            counts: ScanProgress::default(),
+            visited: 0,
+            spinner,
+            on_progress: None,
+            cancel: None,
+            output: None,
+        }
+    }
+
+    /// A monitor without a spinner that hands the counts to `on_progress`
+    /// and fails the walk once `cancel` is set
+    pub fn reporting(on_progress: impl Fn(&ScanProgress) + Send + Sync + 'a, cancel: &'a AtomicBool) -> Self {
+        ScanMonitor {
+            counts: ScanProgress::default(),
+            visited: 0,
+            spinner: ProgressBar::hidden(),
+            on_progress: Some(Box::new(on_progress)),
+            cancel: Some(cancel),
+            output: None,
+        }
+    }
+
+    /// Also skip `output` when walking, so a second run with the output inside
+    /// the scanned directory doesn't pick up what the first one wrote
+    pub fn excluding_output(mut self, output: Option<&Path>) -> Self {
+        self.output = output.and_then(|o| fs::canonicalize(o).ok());
+        self
+    }
+
+    /// Whether the walk should skip `entry`: the run's output directory or a
+    /// staging directory left behind by this tool
+    pub fn excludes(&self, entry: &walkdir::DirEntry) -> bool {
+        if entry.depth() == 0 || !entry.file_type().is_dir() {
+            return false;
+        }
+
+        let staging = entry.file_name().to_string_lossy().starts_with(STAGING_PREFIX);
+        let output = self.output.as_ref().is_some_and(|output| {
+            output.file_name() == Some(entry.file_name())
+                && fs::canonicalize(entry.path()).is_ok_and(|path| &path == output)
+        });
+        if staging || output {
+            self.spinner.suspend(|| eprintln!("⏭️  Not scanning {:?} (created by bulk_unzip)", entry.path()));
+        }
+        staging || output
+    }
+
+    /// Count one walked entry, failing once the scan has been cancelled
+    pub fn visit(&mut self, entry: &walkdir::DirEntry) -> Result<()> {
+        if self.cancel.is_some_and(|cancel| cancel.load(Ordering::Relaxed)) {
+            bail!("Scan cancelled");
+        }
+
+        if entry.file_type().is_dir() {
+            self.counts.directories += 1;
+        }
+        self.visited += 1;
+        if self.visited.is_multiple_of(PROGRESS_INTERVAL) {
+            self.report();
+        }
+        Ok(())
+    }
+
+    pub fn matched(&mut self, size: u64) {
+        self.counts.files_matched += 1;
+        self.counts.bytes += size;
+    }
+
+    pub fn skipped_sidecar(&mut self) {
+        self.counts.sidecars_skipped += 1;
+    }
+
+    pub fn sidecars(&self) -> u64 {
+        self.counts.sidecars_skipped
+    }
+
+    /// Counts of the walk so far, for phases that report after it
+    pub fn progress(&self) -> ScanProgress {
+        self.counts.clone()
+    }
+
+    /// Clear the spinner, or send the final counts, once the walk is done
+    pub fn finish(&self) {
+        self.report();
+        self.spinner.finish_and_clear();
+    }
+
+    fn report(&self) {
+        match &self.on_progress {
+            Some(on_progress) => on_progress(&self.counts),
+            None => self.spinner.set_message(format!(
+                "{} directories, {} files matched ({})",
+                self.counts.directories,
+                self.counts.files_matched,
+                format_size(self.counts.bytes),
+            )),
+        }
+    }
+}
@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One directory a run scans
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanRoot {
+    pub path: PathBuf,
+    /// Names the root in output paths and summaries: its last component,
+    /// numbered when several roots share it (`incoming`, `incoming-2`)
+    pub label: String,
+}
+
+/// The directories given with `--directory`, in order, without repeats
+#[derive(Debug, Clone)]
+pub struct ScanRoots(Vec<ScanRoot>);
+
+impl ScanRoots {
+    /// Directories naming the same place, compared canonically, are kept once
+    pub fn new(directories: &[PathBuf]) -> Self {
+        let mut seen = SeenFiles::default();
+        let mut roots: Vec<ScanRoot> = Vec::new();
+        for directory in directories {
+            if !seen.first_sighting(directory) {
+                continue;
+            }
+            let base = root_label(directory);
+            let mut label = base.clone();
+            let mut n = 1;
+            while roots.iter().any(|root| root.label.eq_ignore_ascii_case(&label)) {
+                n += 1;
+                label = format!("{}-{}", base, n);
+            }
+            roots.push(ScanRoot { path: directory.clone(), label });
+        }
+        ScanRoots(roots)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &ScanRoot> {
+        self.0.iter()
+    }
+
+    /// Whether more than one directory is scanned, so items need their root to tell them apart
+    pub fn is_multiple(&self) -> bool {
+        self.0.len() > 1
+    }
+
+    /// The first directory given, which stands for the run where one is needed
+    pub fn first(&self) -> &Path {
+        &self.0[0].path
+    }
+
+    /// Index of the root `path` was found under. The scan keeps a file
+    /// under the first root that reaches it, which is the first whose
+    /// path it starts with.
+    pub fn index_of(&self, path: &Path) -> usize {
+        self.0.iter().position(|root| path.starts_with(&root.path)).unwrap_or(0)
+    }
+
+    pub fn root_of(&self, path: &Path) -> &ScanRoot {
+        &self.0[self.index_of(path)]
+    }
+
+    /// `path` relative to its root, under the root's label when there are several
+    pub fn relative(&self, path: &Path) -> PathBuf {
+        let root = self.root_of(path);
+        let relative = path.strip_prefix(&root.path).unwrap_or(path);
+        if self.is_multiple() { Path::new(&root.label).join(relative) } else { relative.to_path_buf() }
+    }
+
+    /// Zeroed counts, one per root, for [`ScanRoots::index_of`] to pick from
+    pub fn counts(&self) -> Vec<RootCounts> {
+        self.0
+            .iter()
+            .map(|root| RootCounts { root: root.path.clone(), label: root.label.clone(), ..RootCounts::default() })
+            .collect()
+    }
+}
+
+impl fmt::Display for ScanRoots {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, root) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{:?}", root.path)?;
+        }
+        Ok(())
+    }
+}
+
+/// The last component of the canonical path, so `.` is labelled by the
+/// directory's name; a drive or filesystem root by what it's called
+fn root_label(directory: &Path) -> String {
+    let canonical = fs::canonicalize(directory).unwrap_or_else(|_| directory.to_path_buf());
+    match canonical.file_name() {
+        Some(name) => name.to_string_lossy().into_owned(),
+        None => {
+            let name: String = canonical.to_string_lossy().chars().filter(|c| c.is_alphanumeric()).collect();
+            if name.is_empty() { "root".to_string() } else { name }
+        }
+    }
+}
+
+/// Canonical paths of the files met so far, so a file reached from two
+/// roots, nested or linked, is taken once
+#[derive(Debug, Default)]
+pub struct SeenFiles(HashSet<PathBuf>);
+
+impl SeenFiles {
+    /// True the first time `path`, or another path to the same file, is given
+    pub fn first_sighting(&mut self, path: &Path) -> bool {
+        self.0.insert(fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()))
+    }
+}
+
+/// What became of the files found under one root
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootCounts {
+    pub root: PathBuf,
+    pub label: String,
+    pub found: usize,
+    /// Extracted or stripped
+    pub processed: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+/// What became of one file, as [`RootCounts`] counts it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RootOutcome {
+    Processed,
+    Skipped,
+    Failed,
+}
+
+impl RootCounts {
+    pub fn add(&mut self, outcome: RootOutcome) {
+        self.found += 1;
+        match outcome {
+            RootOutcome::Processed => self.processed += 1,
+            RootOutcome::Skipped => self.skipped += 1,
+            RootOutcome::Failed => self.failed += 1,
+        }
+    }
+}
+
+impl fmt::Display for RootCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({:?}): {} found, {} processed, {} skipped, {} failed",
+            self.label, self.root, self.found, self.processed, self.skipped, self.failed
+        )
+    }
+}
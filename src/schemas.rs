@@ -0,0 +1,93 @@
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+use serde::Serialize;
+
+use crate::extract::ExtractOutcome;
+use crate::metadata_stripper::StripResult;
+use crate::run_report::RunReport;
+use crate::scan_export::ScanExport;
+
+/// Version of the layout of every document written: run reports, scan
+/// exports, `stats --json` and duplicate reports, and the results inside
+/// them. Bumped whenever a field changes meaning or goes away; documents
+/// written before versioning read as 0.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The serialized types `bulk_unzip schema` describes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SchemaKind {
+    /// One archive's outcome, as in the `results` of an unzip report
+    Extraction,
+    /// One file's outcome, as in the `results` of a strip report
+    Strip,
+    /// A `scan --out` JSON file
+    Scan,
+    /// A run report, the envelope around the results
+    Report,
+}
+
+/// JSON Schema of `kind`, tagged with [`SCHEMA_VERSION`] as `x-schema-version`
+pub fn schema(kind: SchemaKind) -> RootSchema {
+    let mut schema = match kind {
+        SchemaKind::Extraction => schema_for!(ExtractOutcome),
+        SchemaKind::Strip => schema_for!(StripResult),
+        SchemaKind::Scan => schema_for!(ScanExport<'static>),
+        SchemaKind::Report => schema_for!(RunReport),
+    };
+    schema.schema.extensions.insert("x-schema-version".to_string(), SCHEMA_VERSION.into());
+    schema
+}
+
+/// A document with `schema_version` ahead of its own fields
+#[derive(Debug, Serialize)]
+pub struct Versioned<'a, T> {
+    pub schema_version: u32,
+    #[serde(flatten)]
+    pub document: &'a T,
+}
+
+pub fn versioned<T: Serialize>(document: &T) -> Versioned<'_, T> {
+    Versioned { schema_version: SCHEMA_VERSION, document }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::ValueEnum;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// The committed schema of `kind`, under `schemas/`
+    fn snapshot_path(kind: SchemaKind) -> PathBuf {
+        let name = kind.to_possible_value().unwrap().get_name().to_string();
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("schemas").join(format!("{}.schema.json", name))
+    }
+
+    /// Fails when a serialized type changes shape. After a deliberate change,
+    /// bump SCHEMA_VERSION if it breaks readers, then rewrite the snapshots
+    /// with `UPDATE_SCHEMAS=1 cargo test schemas`.
+    #[test]
+    fn schemas_match_their_snapshots() {
+        for &kind in SchemaKind::value_variants() {
+            let generated = format!("{}\n", serde_json::to_string_pretty(&schema(kind)).unwrap());
+            let path = snapshot_path(kind);
+            if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, &generated).unwrap();
+                continue;
+            }
+            let snapshot = fs::read_to_string(&path).unwrap_or_default();
+            assert!(snapshot == generated, "the schema of {:?} no longer matches {:?}; see this test's comment", kind, path);
+        }
+    }
+
+    #[test]
+    fn versioned_documents_lead_with_the_version() {
+        #[derive(Serialize)]
+        struct Document {
+            name: &'static str,
+        }
+        let json = serde_json::to_string(&versioned(&Document { name: "run" })).unwrap();
+        assert_eq!(json, format!("{{\"schema_version\":{},\"name\":\"run\"}}", SCHEMA_VERSION));
+    }
+}
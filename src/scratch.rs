@@ -0,0 +1,121 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use walkdir::WalkDir;
+
+use crate::scan_progress::STAGING_PREFIX;
+
+/// Name of the default scratch directory, created inside the output root
+const DEFAULT_DIR_NAME: &str = ".bulk-unzip-tmp";
+
+/// Scratch space for intermediate files: downloads, staged MP3s and
+/// half-written archives. Every user works in its own subdirectory named
+/// with `STAGING_PREFIX`, so leftovers of an interrupted run are recognizable.
+pub struct ScratchDir {
+    root: PathBuf,
+    /// Created by us inside the output root rather than given with --temp-dir
+    owned: bool,
+    peak: AtomicU64,
+}
+
+impl ScratchDir {
+    /// Use `configured`, or `.bulk-unzip-tmp` inside `output_root` so renames
+    /// into the output stay on one filesystem. Stale staging directories from
+    /// earlier runs are removed, and the directory must be writable.
+    pub fn prepare(configured: Option<&Path>, output_root: &Path) -> Result<Self> {
+        let (root, owned) = match configured {
+            Some(dir) => (dir.to_path_buf(), false),
+            None => (output_root.join(DEFAULT_DIR_NAME), true),
+        };
+        fs::create_dir_all(&root)
+            .with_context(|| format!("Failed to create temporary directory {:?}", root))?;
+
+        let scratch = ScratchDir { root, owned, peak: AtomicU64::new(0) };
+        scratch.remove_leftovers()?;
+
+        let probe = tempfile::Builder::new()
+            .prefix(STAGING_PREFIX)
+            .tempfile_in(&scratch.root)
+            .with_context(|| format!("Temporary directory {:?} is not writable", scratch.root))?;
+        drop(probe);
+        Ok(scratch)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// A fresh staging directory, removed when the returned value is dropped
+    pub fn staging(&self, label: &str) -> Result<tempfile::TempDir> {
+        tempfile::Builder::new()
+            .prefix(&format!("{}{}-", STAGING_PREFIX, label))
+            .tempdir_in(&self.root)
+            .with_context(|| format!("Failed to create a staging directory in {:?}", self.root))
+    }
+
+    /// Measure what the scratch directory holds now and keep the maximum.
+    /// Called by users at the point their usage peaks.
+    pub fn note_usage(&self) {
+        let used: u64 = WalkDir::new(&self.root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter_map(|e| e.metadata().ok())
+            .filter(|m| m.is_file())
+            .map(|m| m.len())
+            .sum();
+        self.peak.fetch_max(used, Ordering::Relaxed);
+    }
+
+    pub fn peak_usage(&self) -> u64 {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    /// Remove what's left after a successful run, and the directory itself
+    /// when it is the default one
+    pub fn cleanup(self) -> Result<()> {
+        self.remove_leftovers()?;
+        if self.owned {
+            fs::remove_dir(&self.root)
+                .with_context(|| format!("Failed to remove temporary directory {:?}", self.root))?;
+        }
+        Ok(())
+    }
+
+    fn remove_leftovers(&self) -> Result<()> {
+        let entries = fs::read_dir(&self.root)
+            .with_context(|| format!("Failed to read temporary directory {:?}", self.root))?;
+        for entry in entries.filter_map(|e| e.ok()) {
+            if !entry.file_name().to_string_lossy().starts_with(STAGING_PREFIX) {
+                continue;
+            }
+            let path = entry.path();
+            let removed = if path.is_dir() { fs::remove_dir_all(&path) } else { fs::remove_file(&path) };
+            removed.with_context(|| format!("Failed to remove stale temporary file {:?}", path))?;
+        }
+        Ok(())
+    }
+}
+
+/// Move a finished file out of scratch space. When `from` and `to` are on
+/// different filesystems the file is copied beside `to` first and renamed
+/// over it, so `to` is still replaced atomically.
+pub fn move_file(from: &Path, to: &Path) -> Result<()> {
+    match fs::rename(from, to) {
+        Ok(()) => return Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+        Err(e) => return Err(e).with_context(|| format!("Failed to move {:?} to {:?}", from, to)),
+    }
+
+    let file_name = to.file_name().unwrap_or_default().to_string_lossy();
+    let partial = to.with_file_name(format!("{}{}.partial", STAGING_PREFIX, file_name));
+    fs::copy(from, &partial)
+        .with_context(|| format!("Failed to copy {:?} to {:?}", from, partial))?;
+    if let Err(e) = fs::rename(&partial, to) {
+        let _ = fs::remove_file(&partial);
+        return Err(e).with_context(|| format!("Failed to move {:?} to {:?}", partial, to));
+    }
+    fs::remove_file(from)
+        .with_context(|| format!("Failed to remove {:?}", from))
+}
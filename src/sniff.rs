@@ -0,0 +1,194 @@
+use anyhow::{Context, Result};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::metadata_stripper::audio_region;
+
+/// Container an audio file's content actually is, whatever its extension says
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum AudioContainer {
+    /// MPEG audio frames (MP3, MP2)
+    Mpeg,
+    /// ISO base media (`ftyp` box): M4A, AAC in MP4, ALAC
+    Mp4,
+    /// RIFF WAVE
+    Wav,
+    /// AIFF or AIFF-C (`FORM` chunk)
+    Aiff,
+    Flac,
+    Ogg,
+    /// Nothing recognisable, e.g. junk or padding before the first MPEG frame
+    #[default]
+    Unknown,
+}
+
+impl AudioContainer {
+    /// Whether the content is positively some other format, so an `.mp3`
+    /// holding it must not be given an ID3 tag
+    pub fn is_other_format(self) -> bool {
+        self.is_other_than(AudioContainer::Mpeg)
+    }
+
+    /// Whether the content is positively something other than `expected`
+    pub fn is_other_than(self, expected: AudioContainer) -> bool {
+        self != expected && self != AudioContainer::Unknown
+    }
+}
+
+/// A file type `--formats` selects, by extension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AudioFormat {
+    /// `.mp3`, with ID3v2 tags at the start of the file
+    #[default]
+    Mp3,
+    /// `.wav`, with an `id3 ` chunk and `LIST INFO` metadata
+    Wav,
+    /// `.aiff`, `.aif` and `.aifc`, with an `ID3 ` chunk
+    Aiff,
+}
+
+impl AudioFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "mp3" => Some(AudioFormat::Mp3),
+            "wav" => Some(AudioFormat::Wav),
+            "aiff" | "aif" | "aifc" => Some(AudioFormat::Aiff),
+            _ => None,
+        }
+    }
+
+    /// e.g. `MP3`
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioFormat::Mp3 => "MP3",
+            AudioFormat::Wav => "WAV",
+            AudioFormat::Aiff => "AIFF",
+        }
+    }
+
+    /// e.g. `MP3/WAV`, for messages about a run's files
+    pub fn describe(formats: &[AudioFormat]) -> String {
+        formats.iter().map(|format| format.label()).collect::<Vec<_>>().join("/")
+    }
+
+    /// The container a file of this type should hold
+    pub fn container(self) -> AudioContainer {
+        match self {
+            AudioFormat::Mp3 => AudioContainer::Mpeg,
+            AudioFormat::Wav => AudioContainer::Wav,
+            AudioFormat::Aiff => AudioContainer::Aiff,
+        }
+    }
+}
+
+impl fmt::Display for AudioContainer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AudioContainer::Mpeg => "MPEG audio",
+            AudioContainer::Mp4 => "MP4/M4A",
+            AudioContainer::Wav => "WAV",
+            AudioContainer::Aiff => "AIFF",
+            AudioContainer::Flac => "FLAC",
+            AudioContainer::Ogg => "Ogg",
+            AudioContainer::Unknown => "unknown",
+        })
+    }
+}
+
+/// Identify the container from the first bytes of the audio data
+pub fn sniff_bytes(bytes: &[u8]) -> AudioContainer {
+    match bytes {
+        [_, _, _, _, b'f', b't', b'y', b'p', ..] => AudioContainer::Mp4,
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'A', b'V', b'E', ..] => AudioContainer::Wav,
+        [b'F', b'O', b'R', b'M', _, _, _, _, b'A', b'I', b'F', b'F' | b'C', ..] => AudioContainer::Aiff,
+        [b'f', b'L', b'a', b'C', ..] => AudioContainer::Flac,
+        [b'O', b'g', b'g', b'S', ..] => AudioContainer::Ogg,
+        // Frame sync (11 set bits) with a layer other than the reserved 00
+        [0xff, second, ..] if second & 0xe0 == 0xe0 && second & 0x06 != 0 => AudioContainer::Mpeg,
+        _ => AudioContainer::Unknown,
+    }
+}
+
+/// Identify the container of a file from the bytes after any ID3v2 tags
+pub fn sniff_file(path: &Path) -> Result<AudioContainer> {
+    let region = audio_region(path)?;
+    let mut file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    file.seek(SeekFrom::Start(region.start))
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    let mut head = Vec::with_capacity(12);
+    file.take(12).read_to_end(&mut head)
+        .with_context(|| format!("Failed to read {:?}", path))?;
+    Ok(sniff_bytes(&head))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_container_is_known_by_its_magic_number() {
+        let cases: [(&[u8], AudioContainer); 7] = [
+            (b"\0\0\0\x20ftypM4A \0\0\0\0", AudioContainer::Mp4),
+            (b"RIFF\x24\0\0\0WAVEfmt ", AudioContainer::Wav),
+            (b"FORM\0\0\0\x2eAIFFCOMM", AudioContainer::Aiff),
+            (b"FORM\0\0\0\x2eAIFCFVER", AudioContainer::Aiff),
+            (b"fLaC\0\0\0\x22\x10\0\x10\0", AudioContainer::Flac),
+            (b"OggS\0\x02\0\0\0\0\0\0", AudioContainer::Ogg),
+            (b"\xff\xfb\x90\x64\0\0\0\0\0\0\0\0", AudioContainer::Mpeg),
+        ];
+        for (bytes, container) in cases {
+            assert_eq!(sniff_bytes(bytes), container, "{:?}", String::from_utf8_lossy(bytes));
+        }
+        // RIFF and FORM that aren't audio
+        assert_eq!(sniff_bytes(b"RIFF\x24\0\0\0AVI LIST"), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(b"FORM\0\0\0\x2e8SVXVHDR"), AudioContainer::Unknown);
+    }
+
+    #[test]
+    fn frame_sync_needs_a_layer_other_than_the_reserved_one() {
+        // MPEG-1 layer III, II and I
+        for second in [0xfb, 0xfd, 0xff] {
+            assert_eq!(sniff_bytes(&[0xff, second, 0x90, 0x64]), AudioContainer::Mpeg);
+        }
+        // Layer bits 00 are reserved; 11 set bits alone aren't enough
+        assert_eq!(sniff_bytes(&[0xff, 0xf9, 0x90, 0x64]), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(&[0xff, 0xe1, 0x90, 0x64]), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(&[0xff, 0xdb, 0x90, 0x64]), AudioContainer::Unknown);
+    }
+
+    #[test]
+    fn short_inputs_match_only_what_fits() {
+        assert_eq!(sniff_bytes(b""), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(&[0xff]), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(&[0xff, 0xfb]), AudioContainer::Mpeg);
+        assert_eq!(sniff_bytes(b"fLaC"), AudioContainer::Flac);
+        assert_eq!(sniff_bytes(b"OggS"), AudioContainer::Ogg);
+        // Cut off before the form type or the box type
+        assert_eq!(sniff_bytes(b"RIFF\x24\0\0\0WAV"), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(b"FORM\0\0\0\x2e"), AudioContainer::Unknown);
+        assert_eq!(sniff_bytes(b"\0\0\0\x20fty"), AudioContainer::Unknown);
+    }
+
+    #[test]
+    fn files_are_sniffed_after_their_id3_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("song.mp3");
+        // An ID3v2.4 tag of 0x80 bytes, its size synchsafe as 0x01 0x00
+        let mut bytes = b"ID3\x04\0\0\0\0\x01\0".to_vec();
+        bytes.extend_from_slice(&[0; 0x80]);
+        bytes.extend_from_slice(b"\0\0\0\x20ftypM4A \0\0\0\0isomiso2");
+        bytes.extend_from_slice(&[0; 64]);
+        fs::write(&path, &bytes).unwrap();
+        assert_eq!(sniff_file(&path).unwrap(), AudioContainer::Mp4);
+
+        fs::write(&path, &bytes[..10 + 0x80 + 6]).unwrap();
+        assert_eq!(sniff_file(&path).unwrap(), AudioContainer::Unknown);
+    }
+}
@@ -0,0 +1,127 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::defaults::DEFAULTS;
+
+/// How long a file's size and modification time must hold still for it to
+/// count as finished; also how recently it must have changed to be watched
+pub const SETTLE_WINDOW: Duration = Duration::from_secs(2);
+
+/// How long to wait for archives still being written, the `--stabilize-timeout` default
+pub const DEFAULT_STABILIZE_TIMEOUT: Duration = Duration::from_secs(DEFAULTS.stabilize_timeout_secs);
+
+/// Size and modification time, which a download in progress keeps changing
+type Snapshot = (u64, Option<SystemTime>);
+
+fn snapshot(path: &Path) -> Option<Snapshot> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// Whether another process has `path` open for writing. Browsers and the
+/// Explorer copy dialog deny shared writes while a file is incomplete.
+#[cfg(windows)]
+fn is_locked(path: &Path) -> bool {
+    use std::os::windows::fs::OpenOptionsExt;
+    const FILE_SHARE_READ: u32 = 1;
+    fs::OpenOptions::new().read(true).share_mode(FILE_SHARE_READ).open(path).is_err()
+}
+
+#[cfg(not(windows))]
+fn is_locked(_path: &Path) -> bool {
+    false
+}
+
+/// Whether `path` changed within the last [`SETTLE_WINDOW`] or is locked for
+/// writing, so may still be being downloaded or copied
+pub fn may_be_writing(path: &Path) -> bool {
+    let recent = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| SystemTime::now().duration_since(modified).map_or(true, |age| age < SETTLE_WINDOW));
+    recent || is_locked(path)
+}
+
+/// Archives [`wait_until_settled`] had to watch
+#[derive(Debug, Default)]
+pub struct Settled {
+    /// Held still for a whole window; worth scanning again
+    pub settled: Vec<PathBuf>,
+    /// Still changing or locked when the timeout ran out
+    pub unstable: Vec<PathBuf>,
+}
+
+/// Watch those of `paths` that [`may_be_writing`] until each keeps its size
+/// and modification time for a [`SETTLE_WINDOW`], giving up after `timeout`.
+/// At least one window is always waited, so a zero timeout still tells a
+/// finished file from one being written. Other paths aren't returned.
+pub async fn wait_until_settled<'a>(paths: impl IntoIterator<Item = &'a Path>, timeout: Duration) -> Settled {
+    let mut watching: Vec<(PathBuf, Option<Snapshot>)> = paths
+        .into_iter()
+        .filter(|path| may_be_writing(path))
+        .map(|path| (path.to_path_buf(), snapshot(path)))
+        .collect();
+    let mut result = Settled::default();
+    let deadline = tokio::time::Instant::now() + timeout.max(SETTLE_WINDOW);
+    while !watching.is_empty() {
+        tokio::time::sleep(SETTLE_WINDOW).await;
+        watching.retain_mut(|(path, before)| {
+            let now = snapshot(path);
+            if now == *before && !is_locked(path) {
+                result.settled.push(path.clone());
+                return false;
+            }
+            *before = now;
+            true
+        });
+        if tokio::time::Instant::now() >= deadline {
+            break;
+        }
+    }
+    result.unstable = watching.into_iter().map(|(path, _)| path).collect();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+
+    /// Append to `path` from another thread every 200 ms, as a download
+    /// does, until the returned flag is set
+    fn keep_appending(path: &Path) -> (Arc<AtomicBool>, JoinHandle<()>) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        let stopping = stop.clone();
+        let appender = std::thread::spawn(move || {
+            while !stopping.load(Ordering::Relaxed) {
+                file.write_all(&[0; 64]).unwrap();
+                std::thread::sleep(Duration::from_millis(200));
+            }
+        });
+        (stop, appender)
+    }
+
+    #[tokio::test]
+    async fn files_still_growing_are_unstable_and_quiet_ones_settle() {
+        let dir = tempfile::tempdir().unwrap();
+        let [growing, quiet, old] = ["growing.zip", "quiet.zip", "old.zip"].map(|name| dir.path().join(name));
+        for path in [&growing, &quiet, &old] {
+            fs::write(path, b"PK").unwrap();
+        }
+        let an_hour_ago = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&old).unwrap().set_modified(an_hour_ago).unwrap();
+        assert!(!may_be_writing(&old));
+
+        let (stop, appender) = keep_appending(&growing);
+        let settled = wait_until_settled([growing.as_path(), quiet.as_path(), old.as_path()], Duration::ZERO).await;
+        stop.store(true, Ordering::Relaxed);
+        appender.join().unwrap();
+
+        assert_eq!(settled.settled, [quiet]);
+        assert_eq!(settled.unstable, [growing]);
+    }
+}
@@ -0,0 +1,266 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use zip::ZipArchive;
+
+use crate::extract::ZipFile;
+
+/// Where `--state-file` keeps the journal when given no path, under the output
+pub const STATE_FILE_NAME: &str = ".bulk-unzip-state";
+
+/// First line of every journal, followed by the format version
+const HEADER_PREFIX: &str = "bulk-unzip-state v";
+const VERSION: u32 = 1;
+
+/// Records beyond twice the archives tracked, plus this many, trigger a rewrite
+const COMPACT_SLACK: usize = 64;
+
+/// One archive a run extracted, as it was when extracted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveRecord {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub mtime: i64,
+    /// Hex SHA-256 over the central directory's names, CRCs and sizes
+    pub central_directory: String,
+    /// Directory under the output it was extracted into; absent in records
+    /// written before this was kept, which match any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_name: Option<String>,
+    /// Files it extracted, relative to that directory: all `--delete-removed`
+    /// may remove once the archive no longer has them. Absent in records
+    /// written before this was kept, which let nothing be removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extracted: Option<Vec<String>>,
+}
+
+impl ArchiveRecord {
+    fn of(zip_file: &ZipFile, extracted: &[String]) -> Result<Self> {
+        Ok(ArchiveRecord {
+            path: journal_key(&zip_file.path),
+            size: zip_file.size,
+            mtime: zip_file.mtime,
+            central_directory: central_directory_hash(&zip_file.path)?,
+            output_name: Some(zip_file.output_name.clone()),
+            extracted: Some(extracted.to_vec()),
+        })
+    }
+}
+
+#[derive(Debug)]
+struct JournalState {
+    records: HashMap<PathBuf, ArchiveRecord>,
+    file: fs::File,
+    /// Records in the file, superseded ones included
+    lines: usize,
+}
+
+/// Append-only journal of the archives extracted so far, so a restarted or
+/// repeated run skips them without looking at their output. Every record is
+/// synced before the next archive starts; a record cut short by a crash is
+/// dropped, along with anything after it, the next time the journal opens.
+#[derive(Debug)]
+pub struct StateJournal {
+    path: PathBuf,
+    state: Mutex<JournalState>,
+    discarded_bytes: u64,
+}
+
+impl StateJournal {
+    /// Load the journal at `path`, creating it if missing; `reset` forgets
+    /// every record first. Fails on files that aren't journals, or are
+    /// journals from a newer version, rather than overwriting them.
+    pub fn open(path: impl Into<PathBuf>, reset: bool) -> Result<Self> {
+        let path = path.into();
+        let data = match fs::read(&path) {
+            Ok(data) if !reset => data,
+            Ok(_) => Vec::new(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read state file {:?}", path)),
+        };
+        let (records, lines, valid) = parse_journal(&data)
+            .with_context(|| format!("Failed to load state file {:?}", path))?;
+        let discarded_bytes = (data.len() - valid) as u64;
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {:?}", parent))?;
+        }
+        let mut state = JournalState { records, file: append_to(&path)?, lines };
+        if valid == 0 || discarded_bytes > 0 || needs_compaction(&state) {
+            compact(&path, &mut state)?;
+        }
+        Ok(StateJournal { path, state: Mutex::new(state), discarded_bytes })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Archives the journal has a record for
+    pub fn archives(&self) -> usize {
+        self.lock().records.len()
+    }
+
+    /// Bytes of damaged or incomplete records dropped when the journal opened
+    pub fn discarded_bytes(&self) -> u64 {
+        self.discarded_bytes
+    }
+
+    /// Whether `zip_file` was extracted before, into the directory it would be
+    /// now, and hasn't changed since. The central directory is only read when
+    /// everything else matches.
+    pub fn contains(&self, zip_file: &ZipFile) -> bool {
+        let recorded = {
+            let state = self.lock();
+            match state.records.get(&journal_key(&zip_file.path)) {
+                Some(record)
+                    if record.size == zip_file.size
+                        && record.mtime == zip_file.mtime
+                        && record.output_name.as_ref().is_none_or(|name| *name == zip_file.output_name) =>
+                {
+                    record.central_directory.clone()
+                }
+                _ => return false,
+            }
+        };
+        central_directory_hash(&zip_file.path).is_ok_and(|hash| hash == recorded)
+    }
+
+    /// The files the last recorded extraction of `zip_file` wrote into the
+    /// directory it extracts to now, whatever changed in the archive since;
+    /// `None` without such a record
+    pub fn receipt(&self, zip_file: &ZipFile) -> Option<Vec<String>> {
+        let state = self.lock();
+        let record = state.records.get(&journal_key(&zip_file.path))?;
+        if record.output_name.as_ref().is_some_and(|name| *name != zip_file.output_name) {
+            return None;
+        }
+        record.extracted.clone()
+    }
+
+    /// Split archives into those still to extract and those already extracted
+    pub fn split_processed(&self, zip_files: Vec<ZipFile>) -> (Vec<ZipFile>, Vec<ZipFile>) {
+        zip_files.into_iter().partition(|zip_file| !self.contains(zip_file))
+    }
+
+    /// Append a record for a freshly extracted archive, with the `extracted`
+    /// files of its [`ExtractOutcome`](crate::extract::ExtractOutcome), and
+    /// sync it to disk, rewriting the journal once superseded records pile up
+    pub fn record(&self, zip_file: &ZipFile, extracted: &[String]) -> Result<()> {
+        let record = ArchiveRecord::of(zip_file, extracted)?;
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        let mut state = self.lock();
+        state.file.write_all(line.as_bytes())
+            .and_then(|_| state.file.sync_data())
+            .with_context(|| format!("Failed to write state file {:?}", self.path))?;
+        state.lines += 1;
+        state.records.insert(record.path.clone(), record);
+        if needs_compaction(&state) {
+            compact(&self.path, &mut state)?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JournalState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The records in `data`, how many lines held them, and how many leading
+/// bytes were valid; parsing stops at the first damaged or unterminated line
+fn parse_journal(data: &[u8]) -> Result<(HashMap<PathBuf, ArchiveRecord>, usize, usize)> {
+    let mut records = HashMap::new();
+    let Some(header_end) = data.iter().position(|&b| b == b'\n') else {
+        return Ok((records, 0, 0));
+    };
+    let header = String::from_utf8_lossy(&data[..header_end]);
+    match header.strip_prefix(HEADER_PREFIX).and_then(|version| version.parse::<u32>().ok()) {
+        Some(version) if version > VERSION => {
+            bail!("written by a newer bulk_unzip (format v{}, this one reads v{}); use --reset-state to start over", version, VERSION)
+        }
+        Some(_) => {}
+        None => bail!("not a bulk_unzip state file; use --reset-state to replace it"),
+    }
+    let mut valid = header_end + 1;
+    let mut lines = 0;
+    while let Some(length) = data[valid..].iter().position(|&b| b == b'\n') {
+        let Ok(record) = serde_json::from_slice::<ArchiveRecord>(&data[valid..valid + length]) else {
+            break;
+        };
+        records.insert(record.path.clone(), record);
+        lines += 1;
+        valid += length + 1;
+    }
+    Ok((records, lines, valid))
+}
+
+fn needs_compaction(state: &JournalState) -> bool {
+    state.lines > state.records.len() * 2 + COMPACT_SLACK
+}
+
+/// Rewrite the journal with one record per archive still on disk, replacing
+/// the old file only once the new one is synced
+fn compact(path: &Path, state: &mut JournalState) -> Result<()> {
+    state.records.retain(|archive, _| archive.exists());
+    let mut records: Vec<_> = state.records.values().collect();
+    records.sort_by(|a, b| a.path.cmp(&b.path));
+    let mut contents = format!("{}{}\n", HEADER_PREFIX, VERSION);
+    for record in &records {
+        contents.push_str(&serde_json::to_string(record)?);
+        contents.push('\n');
+    }
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    let mut file = fs::File::create(&temp)
+        .with_context(|| format!("Failed to create {:?}", temp))?;
+    file.write_all(contents.as_bytes())
+        .and_then(|_| file.sync_all())
+        .with_context(|| format!("Failed to write {:?}", temp))?;
+    fs::rename(&temp, path)
+        .with_context(|| format!("Failed to replace state file {:?}", path))?;
+    state.file = append_to(path)?;
+    state.lines = records.len();
+    Ok(())
+}
+
+fn append_to(path: &Path) -> Result<fs::File> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open state file {:?}", path))
+}
+
+/// Archives are recorded by absolute path, so runs from other working
+/// directories still find them
+fn journal_key(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Hex SHA-256 over every central directory entry's name, CRC and sizes, and
+/// the archive comment: cheap to read, and changes whenever the contents do
+fn central_directory_hash(path: &Path) -> Result<String> {
+    let file = fs::File::open(path)
+        .with_context(|| format!("Failed to open {:?}", path))?;
+    let mut archive = ZipArchive::new(BufReader::new(file))
+        .with_context(|| format!("Failed to read zip archive {:?}", path))?;
+    let mut hasher = Sha256::new();
+    hasher.update(archive.comment());
+    for i in 0..archive.len() {
+        let entry = archive.by_index_raw(i)?;
+        hasher.update(entry.name_raw());
+        hasher.update([0]);
+        hasher.update(entry.crc32().to_le_bytes());
+        hasher.update(entry.compressed_size().to_le_bytes());
+        hasher.update(entry.size().to_le_bytes());
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+/// How many of the largest items a summary lists
+const LARGEST_COUNT: usize = 10;
+
+/// One entry in the largest-items list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargestItem {
+    /// `zip` or `mp3`
+    pub kind: String,
+    pub path: String,
+    pub size: u64,
+}
+
+/// Summary of a directory's archives and MP3s, built from the scanners' results
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DirectoryStats {
+    /// Archive count by extension
+    pub archives: BTreeMap<String, usize>,
+    pub compressed_size: u64,
+    /// Sum of the sizes recorded in the readable archives' central directories
+    pub estimated_uncompressed_size: u64,
+    pub encrypted_archives: usize,
+    /// Archives that are empty, truncated or whose central directory can't be read
+    pub corrupt_archives: usize,
+    pub mp3_files: usize,
+    pub mp3_with_metadata: usize,
+    pub mp3_size: u64,
+    pub largest: Vec<LargestItem>,
+}
+
+impl DirectoryStats {
+    /// Count one archive; `uncompressed_size` is `None` when its central directory is unreadable
+    pub fn add_archive(&mut self, path: &Path, size: u64, uncompressed_size: Option<u64>, corrupt: bool, encrypted: bool) {
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        *self.archives.entry(extension).or_insert(0) += 1;
+        self.compressed_size += size;
+        self.estimated_uncompressed_size += uncompressed_size.unwrap_or(0);
+        if corrupt || uncompressed_size.is_none() {
+            self.corrupt_archives += 1;
+        }
+        if encrypted {
+            self.encrypted_archives += 1;
+        }
+        self.add_largest("zip", path, size);
+    }
+
+    pub fn add_mp3(&mut self, path: &Path, size: u64, has_metadata: bool) {
+        self.mp3_files += 1;
+        self.mp3_size += size;
+        if has_metadata {
+            self.mp3_with_metadata += 1;
+        }
+        self.add_largest("mp3", path, size);
+    }
+
+    /// Keep only the largest items, biggest first
+    fn add_largest(&mut self, kind: &str, path: &Path, size: u64) {
+        if self.largest.len() == LARGEST_COUNT && self.largest.last().is_some_and(|item| item.size >= size) {
+            return;
+        }
+        let at = self.largest.partition_point(|item| item.size >= size);
+        self.largest.insert(at, LargestItem { kind: kind.to_string(), path: path.to_string_lossy().into_owned(), size });
+        self.largest.truncate(LARGEST_COUNT);
+    }
+}
+
+/// Whether any entry of the archive needs a password. Unreadable archives
+/// count as not encrypted; they are reported as corrupt instead.
+pub fn is_encrypted(path: &Path) -> bool {
+    let Ok(file) = fs::File::open(path) else {
+        return false;
+    };
+    let Ok(mut archive) = ZipArchive::new(file) else {
+        return false;
+    };
+    (0..archive.len()).any(|i| {
+        matches!(
+            archive.by_index(i),
+            Err(ZipError::UnsupportedArchive(message)) if message == ZipError::PASSWORD_REQUIRED
+        )
+    })
+}
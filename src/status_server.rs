@@ -0,0 +1,468 @@
+use anyhow::{bail, Context, Result};
+use futures::FutureExt;
+use serde::Serialize;
+use std::collections::{BTreeMap, VecDeque};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{broadcast, oneshot};
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::extract::ExtractStatus;
+use crate::metrics::{MetricsSnapshot, OperationMetrics};
+use crate::progress_sink::{ProgressKind, ProgressSink, UnzipProgress};
+
+/// Gap between `Progress` events, which carry cumulative counters
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+/// How long shutdown waits for `/events` clients to get the last event
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(1);
+/// How long a client may take to send its request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// Events a slow `/events` client may fall behind by; one further behind
+/// gets a fresh `status` event instead of those it missed
+const EVENT_BACKLOG: usize = 256;
+/// Largest request head read; `GET /status` needs a fraction of it
+const MAX_REQUEST: usize = 8 * 1024;
+/// Failures [`StatusBoard::recent_failures`] remembers
+const RECENT_FAILURES: usize = 32;
+
+/// Parse `--serve-status`: `host:port`, or `:port` or a bare port on localhost
+pub fn parse_serve_addr(value: &str) -> Result<SocketAddr, String> {
+    let invalid = || format!("Expected an address such as 8080, :8080 or 0.0.0.0:8080, got {:?}", value);
+    let value = value.trim();
+    if let Ok(port) = value.trim_start_matches(':').parse::<u16>() {
+        return Ok(SocketAddr::from((Ipv4Addr::LOCALHOST, port)));
+    }
+    if let Some(port) = value.strip_prefix("localhost:") {
+        return port.parse().map(|port| SocketAddr::from((Ipv4Addr::LOCALHOST, port))).map_err(|_| invalid());
+    }
+    value.parse().map_err(|_| invalid())
+}
+
+/// Where one archive is in the run
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemStatus {
+    /// `Pending` or `Running`, then the archive's status as in the run report,
+    /// e.g. `Extracted` or `{"SkippedDuplicateOf": "a.zip"}`
+    pub status: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ItemStatus {
+    fn state(state: &str) -> Self {
+        ItemStatus { status: serde_json::Value::String(state.to_string()), error: None }
+    }
+}
+
+/// What `GET /status` returns
+#[derive(Debug, Clone, Serialize)]
+pub struct StatusSnapshot {
+    /// Whether the batch is over; nothing changes after
+    pub finished: bool,
+    pub metrics: MetricsSnapshot,
+    /// Every archive of the run by path
+    pub items: BTreeMap<String, ItemStatus>,
+}
+
+#[derive(Debug, Clone)]
+enum StreamEvent {
+    Progress(UnzipProgress),
+    Finished,
+}
+
+/// What `--serve-status` and `--tui` show: the run's metrics, each
+/// archive's status, and its progress events, fanned out to every `/events`
+/// client
+pub struct StatusBoard {
+    pub metrics: OperationMetrics,
+    items: Mutex<BTreeMap<String, ItemStatus>>,
+    /// Archives being extracted, in the order they started
+    running: Mutex<Vec<String>>,
+    /// How many archives failed, and the latest of them with their errors
+    failures: Mutex<(usize, VecDeque<(String, String)>)>,
+    finished: AtomicBool,
+    events: broadcast::Sender<StreamEvent>,
+}
+
+impl Default for StatusBoard {
+    fn default() -> Self {
+        StatusBoard {
+            metrics: OperationMetrics::default(),
+            items: Mutex::new(BTreeMap::new()),
+            running: Mutex::new(Vec::new()),
+            failures: Mutex::new((0, VecDeque::new())),
+            finished: AtomicBool::new(false),
+            events: broadcast::channel(EVENT_BACKLOG).0,
+        }
+    }
+}
+
+impl StatusBoard {
+    pub fn snapshot(&self) -> StatusSnapshot {
+        StatusSnapshot {
+            finished: self.finished.load(Ordering::Relaxed),
+            metrics: self.metrics.snapshot(),
+            items: self.items.lock().unwrap_or_else(|e| e.into_inner()).clone(),
+        }
+    }
+
+    /// The archives being extracted, one per busy worker
+    pub fn running(&self) -> Vec<String> {
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// How many archives failed, and the latest, newest last, as archive and error
+    pub fn recent_failures(&self) -> (usize, Vec<(String, String)>) {
+        let failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+        (failures.0, failures.1.iter().cloned().collect())
+    }
+
+    fn set_item(&self, archive: &Path, item: ItemStatus) {
+        self.items.lock().unwrap_or_else(|e| e.into_inner()).insert(archive.display().to_string(), item);
+    }
+
+    /// Start the counters for the archives about to be extracted, all pending
+    pub fn begin<'a>(&self, archives: impl IntoIterator<Item = &'a Path>, bytes_total: u64) {
+        let mut items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+        let before = items.len();
+        for archive in archives {
+            items.insert(archive.display().to_string(), ItemStatus::state("Pending"));
+        }
+        self.metrics.begin((items.len() - before) as u64, bytes_total);
+    }
+
+    pub fn archive_started(&self, archive: &Path) {
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).push(archive.display().to_string());
+        self.set_item(archive, ItemStatus::state("Running"));
+        self.archive_event(ProgressKind::ArchiveStarted, archive);
+    }
+
+    /// Record how an archive ended, and the files it extracted
+    pub fn archive_finished(&self, archive: &Path, result: Result<(&ExtractStatus, u64), &str>) {
+        let (item, kind) = match result {
+            Ok((status, files)) => {
+                self.metrics.add_files(files);
+                (ItemStatus { status: serde_json::to_value(status).unwrap_or_default(), error: None }, ProgressKind::ArchiveFinished)
+            }
+            Err(error) => {
+                let mut failures = self.failures.lock().unwrap_or_else(|e| e.into_inner());
+                failures.0 += 1;
+                if failures.1.len() == RECENT_FAILURES {
+                    failures.1.pop_front();
+                }
+                failures.1.push_back((archive.display().to_string(), error.to_string()));
+                (ItemStatus { status: "Failed".into(), error: Some(error.to_string()) }, ProgressKind::ArchiveFailed)
+            }
+        };
+        self.running.lock().unwrap_or_else(|e| e.into_inner()).retain(|running| Path::new(running) != archive);
+        self.metrics.archive_done();
+        self.set_item(archive, item);
+        self.archive_event(kind, archive);
+    }
+
+    /// Record an archive's final status, e.g. of one left out before
+    /// extraction as a duplicate
+    pub fn record(&self, archive: &Path, status: &ExtractStatus) {
+        self.set_item(archive, ItemStatus { status: serde_json::to_value(status).unwrap_or_default(), error: None });
+    }
+
+    /// Record an archive as failed once the run is over, e.g. one `--strict`
+    /// failed for being skipped as a duplicate
+    pub fn record_failed(&self, archive: &Path, error: &str) {
+        self.set_item(archive, ItemStatus { status: "Failed".into(), error: Some(error.to_string()) });
+    }
+
+    /// Bring the byte count up to `bytes_done` across the batch and send a
+    /// `Progress` event, when anything changed
+    pub fn advance_to(&self, bytes_done: u64) {
+        let written = self.metrics.snapshot().bytes_written;
+        if bytes_done > written {
+            self.metrics.add_bytes(bytes_done - written);
+            self.send(&self.event(ProgressKind::Progress, String::new(), "Extracting".to_string()));
+        }
+    }
+
+    /// Keep the byte count up to date from `position`, e.g. the batch progress
+    /// bar's, until the returned task is aborted
+    pub fn watch_bytes(self: &Arc<Self>, position: impl Fn() -> u64 + Send + 'static) -> JoinHandle<()> {
+        let board = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PROGRESS_INTERVAL);
+            loop {
+                interval.tick().await;
+                board.advance_to(position());
+            }
+        })
+    }
+
+    fn archive_event(&self, kind: ProgressKind, archive: &Path) {
+        let name = archive.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let message = match kind {
+            ProgressKind::ArchiveStarted => format!("Started: {}", name),
+            ProgressKind::ArchiveFailed => format!("Failed: {}", name),
+            _ => format!("Finished: {}", name),
+        };
+        self.send(&self.event(kind, name, message));
+    }
+
+    fn event(&self, kind: ProgressKind, current_file: String, message: String) -> UnzipProgress {
+        let metrics = self.metrics.snapshot();
+        UnzipProgress { kind, current_file, progress: metrics.bytes_written, total: metrics.bytes_total, message, metrics }
+    }
+
+    fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+        let _ = self.events.send(StreamEvent::Finished);
+    }
+}
+
+impl ProgressSink for StatusBoard {
+    fn send(&self, event: &UnzipProgress) {
+        // No client listening is no error
+        let _ = self.events.send(StreamEvent::Progress(event.clone()));
+    }
+}
+
+/// The `--serve-status` HTTP server: read-only `GET /status`, the
+/// [`StatusSnapshot`] as JSON, and `GET /events`, a server-sent event stream
+/// of the progress events that ends with a `finished` event
+pub struct StatusServer {
+    addr: SocketAddr,
+    board: Arc<StatusBoard>,
+    stop: oneshot::Sender<()>,
+    accept: JoinHandle<()>,
+}
+
+impl StatusServer {
+    /// Listen on `addr`, which must be on localhost unless `allow_remote`:
+    /// the status shows the paths being extracted to anyone who can connect
+    pub async fn start(addr: SocketAddr, allow_remote: bool) -> Result<Self> {
+        if !addr.ip().is_loopback() && !allow_remote {
+            bail!("--serve-status {} would serve other machines; add --allow-remote to allow that", addr);
+        }
+        let listener = TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to listen on {} for --serve-status", addr))?;
+        let addr = listener.local_addr()?;
+        let board = Arc::new(StatusBoard::default());
+        let (stop, stopped) = oneshot::channel();
+        let hosts = AllowedHosts { port: addr.port(), remote: allow_remote };
+        let accept = tokio::spawn(accept_clients(listener, board.clone(), hosts, stopped));
+        Ok(StatusServer { addr, board, stop, accept })
+    }
+
+    /// The address listened on, with the port the system chose for port 0
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn board(&self) -> &Arc<StatusBoard> {
+        &self.board
+    }
+
+    /// Mark the batch finished and stop listening. `/events` clients get the
+    /// final status, for at most a second; the rest are cut off.
+    pub async fn shutdown(self) {
+        self.board.finish();
+        let _ = self.stop.send(());
+        let _ = self.accept.await;
+    }
+}
+
+/// The `Host` headers served: `localhost` or an IP address, with the port
+/// listened on. A page on another site can point its own name at 127.0.0.1
+/// (DNS rebinding), but its requests still carry that name, so they're
+/// refused rather than shown the paths being extracted.
+#[derive(Debug, Clone, Copy)]
+struct AllowedHosts {
+    port: u16,
+    /// Any IP address rather than only loopback ones, with `--allow-remote`
+    remote: bool,
+}
+
+impl AllowedHosts {
+    fn allows(&self, host: &str) -> bool {
+        let Some((name, port)) = host.trim().rsplit_once(':') else {
+            return false;
+        };
+        if port.parse() != Ok(self.port) {
+            return false;
+        }
+        if name.eq_ignore_ascii_case("localhost") {
+            return true;
+        }
+        let ip = name.strip_prefix('[').and_then(|name| name.strip_suffix(']')).unwrap_or(name);
+        ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_loopback() || self.remote)
+    }
+}
+
+async fn accept_clients(listener: TcpListener, board: Arc<StatusBoard>, hosts: AllowedHosts, mut stop: oneshot::Receiver<()>) {
+    let mut clients = JoinSet::new();
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            accepted = listener.accept() => {
+                if let Ok((stream, _)) = accepted {
+                    // A client that disconnects mid-response is its own loss
+                    clients.spawn(serve_client(stream, board.clone(), hosts).map(drop));
+                }
+            }
+            Some(_) = clients.join_next(), if !clients.is_empty() => {}
+        }
+    }
+    drop(listener);
+    let _ = tokio::time::timeout(SHUTDOWN_GRACE, async { while clients.join_next().await.is_some() {} }).await;
+}
+
+/// The request line and `Host` header of a request
+struct Request {
+    method: String,
+    path: String,
+    host: Option<String>,
+}
+
+/// The request on `stream`; `None` when the client sent no complete request in time
+async fn read_request(stream: &mut TcpStream) -> io::Result<Option<Request>> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 1024];
+    let read = async {
+        while !head.windows(4).any(|window| window == b"\r\n\r\n") && head.len() < MAX_REQUEST {
+            let n = stream.read(&mut buffer).await?;
+            if n == 0 {
+                break;
+            }
+            head.extend_from_slice(&buffer[..n]);
+        }
+        io::Result::Ok(())
+    };
+    if tokio::time::timeout(REQUEST_TIMEOUT, read).await.is_err() {
+        return Ok(None);
+    }
+    let head = String::from_utf8_lossy(&head);
+    let mut lines = head.lines();
+    let mut parts = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(None);
+    };
+    let host = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("host"))
+        .map(|(_, value)| value.trim().to_string());
+    Ok(Some(Request { method: method.to_string(), path: path.to_string(), host }))
+}
+
+async fn respond(stream: &mut TcpStream, status: &str, content_type: &str, body: &str) -> io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn serve_client(mut stream: TcpStream, board: Arc<StatusBoard>, hosts: AllowedHosts) -> io::Result<()> {
+    let Some(Request { method, path, host }) = read_request(&mut stream).await? else {
+        return Ok(());
+    };
+    if !host.as_deref().is_some_and(|host| hosts.allows(host)) {
+        let body = format!("Host must be localhost:{} or 127.0.0.1:{}\n", hosts.port, hosts.port);
+        return respond(&mut stream, "403 Forbidden", "text/plain", &body).await;
+    }
+    if method != "GET" {
+        return respond(&mut stream, "405 Method Not Allowed", "text/plain", "Read-only: only GET is served\n").await;
+    }
+    match path.split('?').next().unwrap_or_default() {
+        "/status" => {
+            let body = serde_json::to_string(&board.snapshot()).map_err(io::Error::other)?;
+            respond(&mut stream, "200 OK", "application/json", &body).await
+        }
+        "/events" => stream_events(stream, board).await,
+        _ => respond(&mut stream, "404 Not Found", "text/plain", "Try /status or /events\n").await,
+    }
+}
+
+async fn write_event(stream: &mut TcpStream, event: &str, data: &impl Serialize) -> io::Result<()> {
+    let data = serde_json::to_string(data).map_err(io::Error::other)?;
+    stream.write_all(format!("event: {}\ndata: {}\n\n", event, data).as_bytes()).await
+}
+
+/// A `status` event with the current snapshot, then a `progress` event per
+/// progress event, and a `finished` event with the final snapshot to end
+async fn stream_events(mut stream: TcpStream, board: Arc<StatusBoard>) -> io::Result<()> {
+    // Subscribed before the snapshot is taken, so no event falls in between
+    let mut events = board.events.subscribe();
+    stream
+        .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n")
+        .await?;
+    let snapshot = board.snapshot();
+    if snapshot.finished {
+        return write_event(&mut stream, "finished", &snapshot).await;
+    }
+    write_event(&mut stream, "status", &snapshot).await?;
+    loop {
+        match events.recv().await {
+            Ok(StreamEvent::Progress(event)) => write_event(&mut stream, "progress", &event).await?,
+            Ok(StreamEvent::Finished) | Err(broadcast::error::RecvError::Closed) => break,
+            Err(broadcast::error::RecvError::Lagged(_)) => write_event(&mut stream, "status", &board.snapshot()).await?,
+        }
+    }
+    write_event(&mut stream, "finished", &board.snapshot()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get(addr: SocketAddr, host: Option<&str>) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let host = host.map(|host| format!("Host: {}\r\n", host)).unwrap_or_default();
+        stream.write_all(format!("GET /status HTTP/1.1\r\n{}Accept: */*\r\n\r\n", host).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[test]
+    fn allowed_hosts() {
+        let local = AllowedHosts { port: 8080, remote: false };
+        for host in ["localhost:8080", "LOCALHOST:8080", "127.0.0.1:8080", "[::1]:8080", " 127.0.0.1:8080 "] {
+            assert!(local.allows(host), "{:?}", host);
+        }
+        for host in ["localhost", "localhost:8081", "127.0.0.1", "evil.example:8080", "localhost.evil.example:8080", "192.168.1.2:8080", ""] {
+            assert!(!local.allows(host), "{:?}", host);
+        }
+        let remote = AllowedHosts { port: 8080, remote: true };
+        assert!(remote.allows("192.168.1.2:8080"));
+        assert!(!remote.allows("evil.example:8080"));
+    }
+
+    #[tokio::test]
+    async fn rebound_hosts_are_refused() {
+        let server = StatusServer::start(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), false).await.unwrap();
+        let addr = server.addr();
+
+        let served = get(addr, Some(&format!("localhost:{}", addr.port()))).await;
+        assert!(served.starts_with("HTTP/1.1 200 OK"), "{}", served);
+        assert!(served.contains("\"finished\":false"));
+        let served = get(addr, Some(&addr.to_string())).await;
+        assert!(served.starts_with("HTTP/1.1 200 OK"), "{}", served);
+
+        for host in [Some(format!("evil.example:{}", addr.port())), Some("localhost:1".to_string()), None] {
+            let refused = get(addr, host.as_deref()).await;
+            assert!(refused.starts_with("HTTP/1.1 403 Forbidden"), "{:?}: {}", host, refused);
+            assert!(!refused.contains("metrics"));
+        }
+        server.shutdown().await;
+    }
+}
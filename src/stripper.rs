@@ -0,0 +1,270 @@
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+
+use crate::art_safety::ArtSafety;
+use crate::disposal::Disposal;
+use crate::io_profile::IoProfile;
+use crate::metadata_stripper::{bulk_strip_metadata, MetadataArgs, StripResult};
+use crate::ordering::SortOrder;
+use crate::output_paths::CollisionPolicy;
+use crate::preserve::PreserveAttr;
+use crate::sampling::SampleMode;
+use crate::sniff::AudioFormat;
+
+/// Strips ID3 metadata from every MP3 in a directory, with the same
+/// behaviour and console output as `bulk_unzip strip`.
+///
+/// ```no_run
+/// # async fn run() -> anyhow::Result<()> {
+/// use bulk_unzip::MetadataStripper;
+///
+/// let results = MetadataStripper::new()
+///     .keep_fields("title,artist")
+///     .strip_private(true)
+///     .output("/srv/clean")
+///     .strip_dir("/srv/extracted")
+///     .await?;
+/// println!("{} files processed", results.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MetadataStripper {
+    args: MetadataArgs,
+}
+
+impl MetadataStripper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start from a full set of options, e.g. the `strip` subcommand's arguments
+    pub fn from_args(args: MetadataArgs) -> Self {
+        MetadataStripper { args }
+    }
+
+    /// Write processed files here instead of modifying them in place
+    pub fn output(mut self, output: impl Into<PathBuf>) -> Self {
+        self.args.output = Some(output.into());
+        self
+    }
+
+    /// What files with the same name do when copied to the output directory;
+    /// later ones are renamed by default
+    pub fn on_collision(mut self, policy: CollisionPolicy) -> Self {
+        self.args.on_collision = policy;
+        self
+    }
+
+    /// Refuse to start unless this many bytes stay free on the output
+    /// directory's volume once every file is copied
+    pub fn min_free_space(mut self, bytes: u64) -> Self {
+        self.args.min_free_space = Some(bytes);
+        self
+    }
+
+    /// Remove each original once its copy in the output directory is
+    /// written and checked, as [`disposal`](Self::disposal) says
+    pub fn delete_source(mut self, delete_source: bool) -> Self {
+        self.args.delete_source = delete_source;
+        self
+    }
+
+    /// Whether removed originals are deleted, the default, or moved to the trash
+    pub fn disposal(mut self, disposal: Disposal) -> Self {
+        self.args.disposal = disposal;
+        self
+    }
+
+    /// What happens when embedded art about to be removed has no cover
+    /// image beside it; removed regardless by default
+    pub fn art_safety(mut self, art_safety: ArtSafety) -> Self {
+        self.args.art_safety = art_safety;
+        self
+    }
+
+    pub fn workers(mut self, workers: usize) -> Self {
+        self.args.workers = Some(workers.max(1));
+        self
+    }
+
+    /// Pick the worker count for the storage the files are on; an explicit
+    /// [`workers`](Self::workers) still wins
+    pub fn io_profile(mut self, profile: IoProfile) -> Self {
+        self.args.io_profile = Some(profile);
+        self
+    }
+
+    /// Lower the process's CPU and I/O priority for the run, and halve the
+    /// worker count unless [`workers`](Self::workers) was set
+    pub fn background(mut self, background: bool) -> Self {
+        self.args.background = background;
+        self
+    }
+
+    /// Skip files the other options would leave unchanged
+    pub fn skip_clean(mut self, skip_clean: bool) -> Self {
+        self.args.skip_clean = skip_clean;
+        self
+    }
+
+    /// Keep only these fields, comma-separated as for `--keep-fields`
+    pub fn keep_fields(mut self, fields: &str) -> Self {
+        self.args.keep_fields = Some(fields.to_string());
+        self
+    }
+
+    /// Remove these fields, comma-separated as for `--remove-fields`
+    pub fn remove_fields(mut self, fields: &str) -> Self {
+        self.args.remove_fields = Some(fields.to_string());
+        self
+    }
+
+    /// Rewrite kept genres to canonical names, with corrections from an
+    /// optional `--genre-map` TOML file
+    pub fn normalize_genre(mut self, genre_map: Option<PathBuf>) -> Self {
+        self.args.normalize_genre = true;
+        self.args.genre_map = genre_map;
+        self
+    }
+
+    /// Process only `limit` of the MP3s found, after sorting and filtering:
+    /// the first ones, or a random subset that `seed` makes repeatable
+    pub fn limit(mut self, limit: usize, mode: SampleMode, seed: Option<u64>) -> Self {
+        self.args.limit = Some(limit);
+        self.args.sample = mode;
+        self.args.seed = seed;
+        self
+    }
+
+    /// Rewrite ID3v2.2 and v2.3 tags as v2.4 without dropping frames
+    pub fn upgrade_tags(mut self, upgrade_tags: bool) -> Self {
+        self.args.upgrade_tags = upgrade_tags;
+        self
+    }
+
+    pub fn remove_all(mut self, remove_all: bool) -> Self {
+        self.args.remove_all = remove_all;
+        self
+    }
+
+    /// Fields `remove_all` keeps, comma-separated like `remove_fields`
+    pub fn protect(mut self, fields: &str) -> Self {
+        self.args.protect = Some(fields.to_string());
+        self
+    }
+
+    /// File types to process; MP3 alone unless set
+    pub fn formats(mut self, formats: &[AudioFormat]) -> Self {
+        self.args.formats = formats.to_vec();
+        self
+    }
+
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.args.dry_run = dry_run;
+        self
+    }
+
+    pub fn recursive(mut self, recursive: bool) -> Self {
+        self.args.recursive = recursive;
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.args.max_depth = max_depth;
+        self
+    }
+
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.args.skip_hidden = skip_hidden;
+        self
+    }
+
+    /// Only process files whose relative path matches this glob; repeatable
+    pub fn include(mut self, glob: &str) -> Self {
+        self.args.include.push(glob.to_string());
+        self
+    }
+
+    /// Also process macOS AppleDouble `._*` files and empty files
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.args.include_hidden = include_hidden;
+        self
+    }
+
+    /// Leave files smaller than this many bytes untouched
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.args.min_size = min_size;
+        self
+    }
+
+    /// Bytes of padding to leave after rewritten ID3 tags; none by default
+    pub fn padding(mut self, padding: usize) -> Self {
+        self.args.padding = padding;
+        self
+    }
+
+    /// Skip files whose relative path matches this glob; repeatable
+    pub fn exclude(mut self, glob: &str) -> Self {
+        self.args.exclude.push(glob.to_string());
+        self
+    }
+
+    /// Order to process and report files in; byte-wise by path by default
+    pub fn sort(mut self, sort: SortOrder) -> Self {
+        self.args.sort = sort;
+        self
+    }
+
+    /// Only process files whose tags match a `--where` expression
+    pub fn where_expr(mut self, expr: &str) -> Self {
+        self.args.where_expr = Some(expr.to_string());
+        self
+    }
+
+    pub fn strip_private(mut self, strip_private: bool) -> Self {
+        self.args.strip_private = strip_private;
+        self
+    }
+
+    /// Also modify files whose content is another format renamed to `.mp3`
+    pub fn force(mut self, force: bool) -> Self {
+        self.args.force = force;
+        self
+    }
+
+    /// Keep these attributes of the original files when writing to an output directory
+    pub fn preserve(mut self, attrs: &[PreserveAttr]) -> Self {
+        self.args.preserve = attrs.to_vec();
+        self
+    }
+
+    /// Strip MP3s inside zip archives, replacing them when `in_place`
+    pub fn in_archive(mut self, in_archive: bool, in_place: bool) -> Self {
+        self.args.in_archive = in_archive;
+        self.args.in_place_archive = in_archive && in_place;
+        self
+    }
+
+    pub fn temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.args.temp_dir = Some(temp_dir.into());
+        self
+    }
+
+    pub fn error_log(mut self, error_log: impl Into<PathBuf>) -> Self {
+        self.args.error_log = Some(error_log.into());
+        self
+    }
+
+    /// Process every MP3 under `directory`, returning one result per file
+    pub async fn strip_dir(&self, directory: impl AsRef<Path>) -> Result<Vec<StripResult>> {
+        let mut args = self.args.clone();
+        args.directories = vec![directory.as_ref().to_path_buf()];
+        bulk_strip_metadata(args).await
+    }
+
+    /// Process the directory given in the options
+    pub async fn run(self) -> Result<Vec<StripResult>> {
+        bulk_strip_metadata(self.args).await
+    }
+}
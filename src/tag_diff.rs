@@ -0,0 +1,273 @@
+use id3::frame::{Content, Frame};
+use id3::Tag;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Longest frame value shown in a diff line, in characters
+const MAX_VALUE_CHARS: usize = 60;
+
+/// Whether a frame survives the strip
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub enum DiffOp {
+    Removed,
+    Kept,
+    /// Kept with a new value, e.g. a normalized genre
+    Changed,
+}
+
+/// One frame of the original tag
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagDiffLine {
+    pub op: DiffOp,
+    /// `ID` or `ID (description)`
+    pub frame: String,
+    /// Truncated text, or type and size for binary frames
+    pub value: String,
+    /// The value after stripping, for `Changed`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub new_value: Option<String>,
+}
+
+/// Frame-by-frame comparison of a file's tag with the tag it gets after stripping
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct TagDiff {
+    pub lines: Vec<TagDiffLine>,
+}
+
+/// Label a frame as `ID` or `ID (description)` for reporting
+pub fn frame_label(frame: &Frame) -> String {
+    let description = match frame.content() {
+        Content::Private(private) => &private.owner_identifier,
+        Content::UniqueFileIdentifier(ufid) => &ufid.owner_identifier,
+        Content::EncapsulatedObject(object) => &object.description,
+        Content::ExtendedText(text) => &text.description,
+        Content::ExtendedLink(link) => &link.description,
+        Content::Comment(comment) => &comment.description,
+        _ => return frame.id().to_string(),
+    };
+    format!("{} ({})", frame.id(), description)
+}
+
+/// Whether two tags hold the same frames, whatever their order. Frames are
+/// compared by ID and content, so a text encoding or ID3 version difference
+/// alone doesn't make the tags differ.
+pub fn same_frames(a: &Tag, b: &Tag) -> bool {
+    let mut unmatched: Vec<&Frame> = b.frames().collect();
+    if a.frames().count() != unmatched.len() {
+        return false;
+    }
+    a.frames().all(|frame| {
+        match unmatched.iter().position(|other| other.id() == frame.id() && other.content() == frame.content()) {
+            Some(i) => {
+                unmatched.swap_remove(i);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// A frame's value for display; binary content is described, not shown
+fn frame_value(content: &Content) -> String {
+    let text = match content {
+        Content::Picture(picture) => {
+            return format!("{:?} picture, {}, {} bytes", picture.picture_type, picture.mime_type, picture.data.len())
+        }
+        Content::EncapsulatedObject(object) => return format!("object {}, {} bytes", object.mime_type, object.data.len()),
+        Content::Private(private) => return format!("{} bytes", private.private_data.len()),
+        Content::UniqueFileIdentifier(ufid) => return format!("{} bytes", ufid.identifier.len()),
+        Content::Unknown(unknown) => return format!("{} bytes", unknown.data.len()),
+        Content::ExtendedText(text) => text.value.clone(),
+        Content::ExtendedLink(link) => link.link.clone(),
+        Content::Comment(comment) => comment.text.clone(),
+        Content::Lyrics(lyrics) => lyrics.text.clone(),
+        other => other.to_string(),
+    };
+    let text = text.replace(['\n', '\r'], " ");
+    if text.chars().count() <= MAX_VALUE_CHARS {
+        return text;
+    }
+    let truncated: String = text.chars().take(MAX_VALUE_CHARS - 1).collect();
+    format!("{}…", truncated)
+}
+
+impl TagDiff {
+    /// Compare every frame of `original` with `planned`; a frame is kept when
+    /// `planned` holds one with the same ID and content, and changed when it
+    /// holds one with the same label but other content
+    pub fn new(original: &Tag, planned: &Tag) -> Self {
+        let lines = original
+            .frames()
+            .map(|frame| {
+                let label = frame_label(frame);
+                let kept = planned.frames().any(|f| f.id() == frame.id() && f.content() == frame.content());
+                let changed = (!kept)
+                    .then(|| planned.frames().find(|f| frame_label(f) == label))
+                    .flatten();
+                TagDiffLine {
+                    op: match (kept, changed) {
+                        (true, _) => DiffOp::Kept,
+                        (false, Some(_)) => DiffOp::Changed,
+                        (false, None) => DiffOp::Removed,
+                    },
+                    frame: label,
+                    value: frame_value(frame.content()),
+                    new_value: changed.map(|f| frame_value(f.content())),
+                }
+            })
+            .collect();
+        TagDiff { lines }
+    }
+
+    pub fn removed(&self) -> impl Iterator<Item = &TagDiffLine> {
+        self.lines.iter().filter(|line| line.op == DiffOp::Removed)
+    }
+
+    /// Frames that survive, changed or not
+    pub fn kept(&self) -> impl Iterator<Item = &TagDiffLine> {
+        self.lines.iter().filter(|line| line.op != DiffOp::Removed)
+    }
+
+    /// Lines prefixed `-` for removed, `=` for kept and `~` for changed
+    /// frames, as in a unified diff
+    pub fn render(&self) -> String {
+        self.lines
+            .iter()
+            .map(|line| match (line.op, &line.new_value) {
+                (DiffOp::Changed, Some(new_value)) => format!("~ {} {} → {}", line.frame, line.value, new_value),
+                (DiffOp::Removed, _) => format!("- {} {}", line.frame, line.value),
+                _ => format!("= {} {}", line.frame, line.value),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::frame::{
+        Comment, EncapsulatedObject, ExtendedLink, ExtendedText, Lyrics, Picture, PictureType, Private, UniqueFileIdentifier, Unknown,
+    };
+    use id3::{TagLike, Version};
+
+    /// A tag with a frame of every kind the diff describes
+    fn every_kind() -> Tag {
+        let mut tag = Tag::new();
+        tag.add_frame(Frame::text("TIT2", "Blue in Green"));
+        tag.add_frame(Frame::text("TCON", "jazz"));
+        tag.add_frame(ExtendedText { description: "SERATO_MARKERS".to_string(), value: "a\nb".to_string() });
+        tag.add_frame(Frame::link("WOAR", "https://example.com/miles"));
+        tag.add_frame(ExtendedLink { description: "shop".to_string(), link: "https://example.com/buy".to_string() });
+        tag.add_frame(Comment { lang: "eng".to_string(), description: "note".to_string(), text: "x".repeat(100) });
+        tag.add_frame(Lyrics { lang: "eng".to_string(), description: String::new(), text: "So blue".to_string() });
+        tag.add_frame(Picture {
+            mime_type: "image/jpeg".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: vec![0; 2048],
+        });
+        tag.add_frame(EncapsulatedObject {
+            mime_type: "application/octet-stream".to_string(),
+            filename: "cues.bin".to_string(),
+            description: "cues".to_string(),
+            data: vec![0; 10],
+        });
+        tag.add_frame(Private { owner_identifier: "com.apple.streaming".to_string(), private_data: vec![0; 5] });
+        tag.add_frame(UniqueFileIdentifier { owner_identifier: "http://musicbrainz.org".to_string(), identifier: vec![0; 36] });
+        tag.add_frame(Frame::with_content("XYZW", Content::Unknown(Unknown { data: vec![0; 7], version: Version::Id3v24 })));
+        tag
+    }
+
+    fn line<'a>(diff: &'a TagDiff, frame: &str) -> &'a TagDiffLine {
+        diff.lines.iter().find(|line| line.frame == frame).unwrap_or_else(|| panic!("no line for {}", frame))
+    }
+
+    #[test]
+    fn every_frame_kind_is_labelled_and_described() {
+        let diff = TagDiff::new(&every_kind(), &Tag::new());
+        let value = |frame: &str| line(&diff, frame).value.as_str();
+
+        assert_eq!(diff.lines.len(), 12);
+        assert_eq!(value("TIT2"), "Blue in Green");
+        assert_eq!(value("TXXX (SERATO_MARKERS)"), "a b");
+        assert_eq!(value("WOAR"), "https://example.com/miles");
+        assert_eq!(value("WXXX (shop)"), "https://example.com/buy");
+        assert_eq!(value("COMM (note)"), format!("{}…", "x".repeat(MAX_VALUE_CHARS - 1)));
+        assert_eq!(value("USLT"), "So blue");
+        assert_eq!(value("APIC"), "CoverFront picture, image/jpeg, 2048 bytes");
+        assert_eq!(value("GEOB (cues)"), "object application/octet-stream, 10 bytes");
+        assert_eq!(value("PRIV (com.apple.streaming)"), "5 bytes");
+        assert_eq!(value("UFID (http://musicbrainz.org)"), "36 bytes");
+        assert_eq!(value("XYZW"), "7 bytes");
+        assert_eq!(diff.removed().count(), 12);
+    }
+
+    #[test]
+    fn kept_removed_and_changed_frames() {
+        let original = every_kind();
+        let mut planned = Tag::new();
+        planned.add_frame(Frame::text("TIT2", "Blue in Green"));
+        planned.add_frame(Frame::text("TCON", "Jazz"));
+
+        let diff = TagDiff::new(&original, &planned);
+
+        assert_eq!(line(&diff, "TIT2").op, DiffOp::Kept);
+        let genre = line(&diff, "TCON");
+        assert_eq!((genre.op, genre.value.as_str(), genre.new_value.as_deref()), (DiffOp::Changed, "jazz", Some("Jazz")));
+        assert_eq!(line(&diff, "APIC").op, DiffOp::Removed);
+        assert_eq!(diff.kept().count(), 2);
+        assert_eq!(diff.removed().count(), 10);
+        let rendered = diff.render();
+        assert!(rendered.starts_with("= TIT2 Blue in Green\n~ TCON jazz → Jazz\n- TXXX (SERATO_MARKERS) a b\n"), "{}", rendered);
+    }
+
+    #[test]
+    fn frame_order_and_version_do_not_make_tags_differ() {
+        let mut a = Tag::new();
+        a.add_frame(Frame::text("TIT2", "Title"));
+        a.add_frame(Frame::text("TPE1", "Artist"));
+        let mut b = Tag::with_version(Version::Id3v23);
+        b.add_frame(Frame::text("TPE1", "Artist"));
+        b.add_frame(Frame::text("TIT2", "Title"));
+        assert!(same_frames(&a, &b));
+
+        b.add_frame(Frame::text("TALB", "Album"));
+        assert!(!same_frames(&a, &b));
+        a.add_frame(Frame::text("TALB", "Other album"));
+        assert!(!same_frames(&a, &b));
+    }
+
+    #[test]
+    fn same_frames_ignores_order_and_version() {
+        let mut a = Tag::with_version(Version::Id3v23);
+        a.set_title("Song");
+        a.set_artist("Band");
+        a.add_frame(Comment { lang: "eng".to_string(), description: String::new(), text: "one".to_string() });
+        let mut b = Tag::with_version(Version::Id3v24);
+        b.add_frame(Comment { lang: "eng".to_string(), description: String::new(), text: "one".to_string() });
+        b.set_artist("Band");
+        b.set_title("Song");
+        assert!(same_frames(&a, &b));
+        assert!(same_frames(&b, &a));
+
+        b.set_title("Other");
+        assert!(!same_frames(&a, &b));
+    }
+
+    #[test]
+    fn same_frames_counts_every_frame() {
+        let comment = |text: &str| Comment { lang: "eng".to_string(), description: text.to_string(), text: text.to_string() };
+        let mut a = Tag::new();
+        a.add_frame(comment("x"));
+        a.add_frame(comment("y"));
+        let mut b = Tag::new();
+        b.add_frame(comment("y"));
+        assert!(!same_frames(&a, &b));
+        assert!(!same_frames(&b, &a));
+
+        b.add_frame(comment("x"));
+        assert!(same_frames(&a, &b));
+        assert!(same_frames(&Tag::new(), &Tag::new()));
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use id3::{Frame, Tag, TagLike, Version};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A tag rewritten as ID3v2.4 by `--upgrade-tags`, with the frame counts
+/// that show nothing was lost
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TagUpgrade {
+    /// e.g. `ID3v2.2`
+    pub from: String,
+    pub to: String,
+    pub frames_before: usize,
+    /// Frames in the written tag, read back from the file; the planned tag's on a dry run
+    pub frames_after: usize,
+    /// ID3v2.3 date frames folded into TDRC, each one fewer frame after
+    pub merged: Vec<String>,
+}
+
+impl TagUpgrade {
+    /// e.g. `ID3v2.2 → ID3v2.4, 9 → 9 frames`
+    pub fn describe(&self) -> String {
+        let merged = if self.merged.is_empty() {
+            String::new()
+        } else {
+            format!(" ({} merged into TDRC)", self.merged.join(", "))
+        };
+        format!("{} → {}, {} → {} frames{}", self.from, self.to, self.frames_before, self.frames_after, merged)
+    }
+}
+
+pub fn version_name(version: Version) -> &'static str {
+    match version {
+        Version::Id3v22 => "ID3v2.2",
+        Version::Id3v23 => "ID3v2.3",
+        Version::Id3v24 => "ID3v2.4",
+    }
+}
+
+/// The text of a frame, if it is a text frame
+fn text<'a>(tag: &'a Tag, id: &str) -> Option<&'a str> {
+    tag.get(id).and_then(|frame| frame.content().text())
+}
+
+/// `tag` with every frame under its ID3v2.4 ID. The id3 crate already maps
+/// ID3v2.2 IDs on read; what's left is TORY, which becomes TDOR, and TYER,
+/// TDAT and TIME, which become a single TDRC. Fails naming the frames with
+/// no ID3v2.4 ID, since writing would silently drop them.
+pub fn upgrade_tag(tag: &Tag) -> Result<(Tag, Vec<String>)> {
+    let unmappable: Vec<&str> = tag
+        .frames()
+        .filter(|frame| frame.id_for_version(Version::Id3v24).is_none())
+        .map(|frame| frame.id())
+        .collect();
+    if !unmappable.is_empty() {
+        bail!("frames {} have no ID3v2.4 equivalent", unmappable.join(", "));
+    }
+
+    let mut upgraded = tag.clone();
+    if let Some(original_year) = text(tag, "TORY") {
+        if tag.get("TDOR").is_none() {
+            upgraded.add_frame(Frame::text("TDOR", original_year));
+        }
+        upgraded.remove("TORY");
+    }
+
+    let mut merged = Vec::new();
+    if let Some(year) = text(tag, "TYER") {
+        let mut date = year.trim().to_string();
+        // TDAT is DDMM and TIME is HHMM
+        if let Some(day_month) = text(tag, "TDAT").filter(|d| d.len() == 4) {
+            date = format!("{}-{}-{}", date, &day_month[2..4], &day_month[0..2]);
+            merged.push("TDAT".to_string());
+            if let Some(time) = text(tag, "TIME").filter(|t| t.len() == 4) {
+                date = format!("{}T{}:{}", date, &time[0..2], &time[2..4]);
+                merged.push("TIME".to_string());
+            }
+        }
+        if tag.get("TDRC").is_none() {
+            upgraded.add_frame(Frame::text("TDRC", date));
+        } else {
+            merged.push("TYER".to_string());
+        }
+        for id in ["TYER", "TDAT", "TIME"] {
+            if id == "TYER" || merged.iter().any(|m| m == id) {
+                upgraded.remove(id);
+            }
+        }
+    }
+    Ok((upgraded, merged))
+}
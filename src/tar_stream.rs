@@ -0,0 +1,151 @@
+use anyhow::{bail, Context, Result};
+use indicatif::ProgressBar;
+use serde::Serialize;
+use std::fs;
+use std::io::{self, Read, Seek, Write};
+use std::time::UNIX_EPOCH;
+use tar::{Builder, EntryType, Header};
+use zip::ZipArchive;
+
+use crate::entry_paths::{plan_entry_paths, read_entry_names};
+use crate::extract::{ExtractOptions, ZipFile};
+use crate::file_types::is_signature_file;
+use crate::timestamps::TimeClamps;
+
+/// Mode of entries whose archive records none
+const DEFAULT_FILE_MODE: u32 = 0o644;
+const DEFAULT_DIR_MODE: u32 = 0o755;
+
+/// What went into a tar stream
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TarSummary {
+    pub archives: usize,
+    pub files: usize,
+    pub bytes: u64,
+    /// Archives left out for having more entries than `max_entries`
+    pub skipped: usize,
+    /// Entries left out because their path escapes the archive's directory
+    pub unsafe_paths: usize,
+}
+
+/// Counts what passes through, so a short read shows up before the tar
+/// header's size is trusted
+struct CountingReader<'a, R> {
+    inner: R,
+    read: u64,
+    progress: &'a ProgressBar,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        self.progress.inc(n as u64);
+        Ok(n)
+    }
+}
+
+/// Write every archive's entries, one archive after another, into a single
+/// tar stream on `writer`. Each archive becomes a top-level directory named
+/// after its `output_name`; entries keep their sizes, modification times and
+/// Unix modes. An error part way leaves the stream truncated, so callers must
+/// fail rather than report success.
+pub fn write_tar<W: Write>(zip_files: &[ZipFile], writer: W, options: &ExtractOptions, progress: &ProgressBar) -> Result<TarSummary> {
+    let mut builder = Builder::new(writer);
+    let mut summary = TarSummary::default();
+    for zip_file in zip_files {
+        if let (Some(limit), Some(entries)) = (options.max_entries, zip_file.entries) {
+            if entries > limit {
+                progress.suspend(|| eprintln!("⚠️  Skipped {:?}: {} entries exceed --max-entries {}", zip_file.path, entries, limit));
+                summary.skipped += 1;
+                continue;
+            }
+        }
+        let file = fs::File::open(&zip_file.path)
+            .with_context(|| format!("Failed to open zip file {:?}", zip_file.path))?;
+        let mut archive = ZipArchive::new(file)
+            .with_context(|| format!("Failed to read zip archive {:?}", zip_file.path))?;
+        append_archive(&mut builder, &mut archive, zip_file, options, progress, &mut summary)
+            .with_context(|| format!("Failed to stream {:?}; the tar output is truncated", zip_file.path))?;
+        summary.archives += 1;
+    }
+    builder.into_inner()
+        .and_then(|mut writer| writer.flush())
+        .context("Failed to finish the tar stream")?;
+    Ok(summary)
+}
+
+fn append_archive<W: Write, R: Read + Seek>(
+    builder: &mut Builder<W>,
+    archive: &mut ZipArchive<R>,
+    zip_file: &ZipFile,
+    options: &ExtractOptions,
+    progress: &ProgressBar,
+    summary: &mut TarSummary,
+) -> Result<()> {
+    let plan = plan_entry_paths(
+        &read_entry_names(archive, &zip_file.path)?,
+        options.flatten_depth,
+        options.normalize_extensions.as_ref(),
+        options.depth_limit,
+    );
+    if let Some(example) = &plan.unsafe_paths.example {
+        summary.unsafe_paths += plan.unsafe_paths.count;
+        progress.suspend(|| {
+            eprintln!(
+                "⚠️  Skipped {} entries in {:?} whose path leaves the archive's directory, e.g. {:?}",
+                plan.unsafe_paths.count, zip_file.path, example
+            )
+        });
+    }
+    let root = zip_file.output_name.trim_end_matches('/');
+    let mut time_clamps = TimeClamps::default();
+
+    let mut header = Header::new_gnu();
+    header.set_entry_type(EntryType::Directory);
+    header.set_size(0);
+    header.set_mode(DEFAULT_DIR_MODE);
+    header.set_mtime(zip_file.mtime.max(0) as u64);
+    builder.append_data(&mut header, format!("{}/", root), io::empty())?;
+
+    for (i, name) in plan.paths.iter().enumerate() {
+        let Some(name) = name else { continue };
+        let entry = archive.by_index(i)
+            .with_context(|| format!("Failed to read file at index {} in {:?}", i, zip_file.path))?;
+        if options.skip_signatures && is_signature_file(entry.name()) {
+            continue;
+        }
+        let modified = time_clamps.entry_time(name, entry.last_modified(), options.strict_times)?;
+        let mut header = Header::new_gnu();
+        header.set_mtime(modified.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()));
+        let path = format!("{}/{}", root, name);
+
+        if name.ends_with('/') {
+            header.set_entry_type(EntryType::Directory);
+            header.set_size(0);
+            header.set_mode(entry.unix_mode().map_or(DEFAULT_DIR_MODE, |mode| mode & 0o7777));
+            builder.append_data(&mut header, &path, io::empty())?;
+            continue;
+        }
+
+        let size = entry.size();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(size);
+        header.set_mode(entry.unix_mode().map_or(DEFAULT_FILE_MODE, |mode| mode & 0o7777));
+        let mut reader = CountingReader { inner: entry, read: 0, progress };
+        builder.append_data(&mut header, &path, &mut reader)
+            .with_context(|| format!("Failed to write {:?} to the tar stream", path))?;
+        if reader.read != size {
+            bail!("Entry {:?} held {} bytes but declared {}", name, reader.read, size);
+        }
+        summary.files += 1;
+        summary.bytes += size;
+    }
+
+    if let Some(example) = &time_clamps.example {
+        progress.suspend(|| {
+            eprintln!("⚠️  Clamped {} invalid entry timestamps in {:?}, e.g. {:?}", time_clamps.count, zip_file.path, example)
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use anyhow::{bail, Result};
+use chrono::{Local, NaiveDate, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How far past the current time an entry's timestamp may lie before it's clamped
+const FUTURE_SLACK: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Entries of one archive whose timestamps had to be clamped
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TimeClamps {
+    pub count: usize,
+    /// Name of the first clamped entry
+    pub example: Option<String>,
+}
+
+/// Convert an entry's DOS timestamp, which is in local time, to a
+/// `SystemTime`. `None` when a field is out of range, e.g. month 0 or
+/// second 62, as written by tools that store a zero date.
+pub fn dos_to_system_time(time: zip::DateTime) -> Option<SystemTime> {
+    let naive = NaiveDate::from_ymd_opt(time.year() as i32, time.month() as u32, time.day() as u32)?
+        .and_hms_opt(time.hour() as u32, time.minute() as u32, time.second() as u32)?;
+    let local = Local.from_local_datetime(&naive).earliest()?;
+    Some(local.into())
+}
+
+/// The modification time to give an extracted entry: its own timestamp when
+/// that is valid and no later than `now` plus a day, otherwise the epoch
+/// (invalid or pre-epoch) or `now` plus a day (future). The flag tells whether
+/// the timestamp was clamped.
+pub fn clamp_entry_time(time: zip::DateTime, now: SystemTime) -> (SystemTime, bool) {
+    let ceiling = now + FUTURE_SLACK;
+    match dos_to_system_time(time) {
+        Some(t) if t < UNIX_EPOCH => (UNIX_EPOCH, true),
+        Some(t) if t > ceiling => (ceiling, true),
+        Some(t) => (t, false),
+        None => (UNIX_EPOCH, true),
+    }
+}
+
+fn describe(time: zip::DateTime) -> String {
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        time.year(),
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+impl TimeClamps {
+    /// Modification time for entry `name`, counting it when clamped. With
+    /// `strict`, an out-of-range timestamp is an error instead.
+    pub fn entry_time(&mut self, name: &str, time: zip::DateTime, strict: bool) -> Result<SystemTime> {
+        let (modified, clamped) = clamp_entry_time(time, SystemTime::now());
+        if clamped {
+            if strict {
+                bail!("Entry {:?} has an invalid timestamp ({})", name, describe(time));
+            }
+            self.count += 1;
+            self.example.get_or_insert_with(|| name.to_string());
+        }
+        Ok(modified)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dos(year: u16, month: u8, day: u8, hour: u8, minute: u8, second: u8) -> zip::DateTime {
+        zip::DateTime::from_date_and_time(year, month, day, hour, minute, second).unwrap()
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32, second: u32) -> SystemTime {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, second).earliest().unwrap().into()
+    }
+
+    #[test]
+    fn dos_times_are_local_times() {
+        assert_eq!(dos_to_system_time(dos(2020, 6, 15, 12, 30, 10)), Some(local(2020, 6, 15, 12, 30, 10)));
+        assert_eq!(dos_to_system_time(dos(1980, 1, 1, 0, 0, 0)), Some(local(1980, 1, 1, 0, 0, 0)));
+    }
+
+    #[test]
+    fn out_of_range_fields_have_no_time() {
+        // The all-zero date some tools write: month 0, day 0
+        assert_eq!(dos_to_system_time(zip::DateTime::from_msdos(0, 0)), None);
+        // Seconds are stored halved, so the largest value reads as 62
+        let june_15_2020 = (40 << 9) | (6 << 5) | 15;
+        assert_eq!(zip::DateTime::from_msdos(june_15_2020, 0b11111).second(), 62);
+        assert_eq!(dos_to_system_time(zip::DateTime::from_msdos(june_15_2020, 0b11111)), None);
+        assert_eq!(dos_to_system_time(dos(2021, 2, 30, 0, 0, 0)), None);
+    }
+
+    #[test]
+    fn clamping() {
+        let now = local(2024, 3, 15, 12, 0, 0);
+        assert_eq!(clamp_entry_time(dos(2020, 6, 15, 12, 30, 10), now), (local(2020, 6, 15, 12, 30, 10), false));
+        assert_eq!(clamp_entry_time(zip::DateTime::from_msdos(0, 0), now), (UNIX_EPOCH, true));
+        assert_eq!(clamp_entry_time(dos(2107, 12, 31, 0, 0, 0), now), (now + FUTURE_SLACK, true));
+        // Up to a day ahead passes, for clocks that disagree
+        assert_eq!(clamp_entry_time(dos(2024, 3, 16, 11, 0, 0), now), (local(2024, 3, 16, 11, 0, 0), false));
+    }
+
+    #[test]
+    fn clamped_entries_are_counted_or_refused() {
+        let mut clamps = TimeClamps::default();
+        clamps.entry_time("good.txt", dos(2020, 6, 15, 12, 30, 10), false).unwrap();
+        assert_eq!(clamps.entry_time("zero.txt", zip::DateTime::from_msdos(0, 0), false).unwrap(), UNIX_EPOCH);
+        clamps.entry_time("future.txt", dos(2107, 1, 1, 0, 0, 0), false).unwrap();
+        assert_eq!(clamps.count, 2);
+        assert_eq!(clamps.example.as_deref(), Some("zero.txt"));
+
+        let error = TimeClamps::default().entry_time("zero.txt", zip::DateTime::from_msdos(0, 0), true).unwrap_err();
+        assert_eq!(error.to_string(), "Entry \"zero.txt\" has an invalid timestamp (1980-00-00 00:00:00)");
+    }
+}
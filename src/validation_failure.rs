@@ -0,0 +1,68 @@
+use chrono::Local;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What becomes of an archive's directory when its data fails a check late
+/// in the extraction: an entry's CRC-32, or the entry count cross-check
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OnValidationFailure {
+    /// Remove the directory and everything extracted into it
+    Remove,
+    /// Leave it where it is, for --skip-existing to find on the next run
+    Keep,
+    /// Move it aside to `<name>.failed-<timestamp>`, so the next run extracts afresh
+    #[default]
+    Rename,
+}
+
+/// Marks an error as a failed check of the archive's data, rather than e.g. a full disk
+#[derive(Debug)]
+pub struct ValidationFailed;
+
+impl fmt::Display for ValidationFailed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("validation failed")
+    }
+}
+
+/// Whether `error` is a failed check: marked [`ValidationFailed`], or the zip
+/// crate's CRC-32 check at the end of an entry
+pub fn is_validation_failure(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<ValidationFailed>().is_some()
+        || error.chain().any(|cause| {
+            cause.downcast_ref::<io::Error>().is_some_and(|e| e.to_string() == "Invalid checksum")
+        })
+}
+
+/// Deal with the directory of an archive that failed a check as `policy`
+/// says, and add what became of it to `error`. A directory that existed
+/// before the archive was extracted, e.g. one being updated, is always kept.
+pub fn handle_failed_output(extract_dir: &Path, created: bool, policy: OnValidationFailure, error: anyhow::Error) -> anyhow::Error {
+    let outcome = match policy {
+        _ if !created => format!("output in {:?} kept, as it existed before this run", extract_dir),
+        OnValidationFailure::Keep => format!("partial output kept in {:?}", extract_dir),
+        OnValidationFailure::Remove => match fs::remove_dir_all(extract_dir) {
+            Ok(()) => format!("partial output {:?} removed", extract_dir),
+            Err(e) => format!("partial output kept in {:?}, as removing it failed: {}", extract_dir, e),
+        },
+        OnValidationFailure::Rename => {
+            let target = failed_path(extract_dir);
+            match fs::rename(extract_dir, &target) {
+                Ok(()) => format!("partial output moved to {:?}", target),
+                Err(e) => format!("partial output kept in {:?}, as moving it aside failed: {}", extract_dir, e),
+            }
+        }
+    };
+    error.context(outcome)
+}
+
+/// `<name>.failed-<timestamp>` beside `extract_dir`, in local time
+fn failed_path(extract_dir: &Path) -> PathBuf {
+    let name = extract_dir.file_name().unwrap_or_default().to_string_lossy();
+    extract_dir.with_file_name(format!("{}.failed-{}", name, Local::now().format("%Y%m%d-%H%M%S")))
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use walkdir::WalkDir;
+use zip::ZipArchive;
+
+use crate::entry_depth::DepthLimit;
+use crate::entry_paths::{plan_entry_paths, read_entry_names};
+use crate::extensions::ExtensionMap;
+
+/// What `verify-output` checks beyond each entry's presence and size
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct VerifyOptions {
+    /// Read every output file and compare its CRC-32 with the archive's
+    pub crc: bool,
+    /// Report output files no entry accounts for
+    pub check_extra: bool,
+    /// The `--flatten-depth` the output was extracted with
+    pub flatten_depth: usize,
+    /// The `--normalize-extensions` mapping the output was extracted with
+    pub normalize_extensions: Option<ExtensionMap>,
+    /// The `--max-entry-depth` and `--flatten-overdeep` the output was extracted with
+    pub depth_limit: Option<DepthLimit>,
+}
+
+/// How an archive's extraction directory compares with its central directory
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveVerification {
+    /// Entries compared
+    pub checked: usize,
+    /// Entries with nothing at their output path
+    pub missing: Vec<String>,
+    /// Entries whose output differs in kind, size or CRC, with the difference
+    pub mismatched: Vec<String>,
+    /// Output files not in the archive, with `check_extra`
+    pub extra: Vec<String>,
+}
+
+impl ArchiveVerification {
+    pub fn passed(&self) -> bool {
+        self.missing.is_empty() && self.mismatched.is_empty() && self.extra.is_empty()
+    }
+}
+
+/// CRC-32 of a file's contents, as stored in zip headers
+pub fn file_crc32(path: &Path) -> io::Result<u32> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Compare `extract_dir` with the entries of `zip_path`, reading only the
+/// central directory and, with `crc`, the extracted files
+pub fn verify_archive(zip_path: &Path, extract_dir: &Path, options: &VerifyOptions) -> Result<ArchiveVerification> {
+    let file = fs::File::open(zip_path)
+        .with_context(|| format!("Failed to open zip file {:?}", zip_path))?;
+    let mut archive = ZipArchive::new(file)
+        .with_context(|| format!("Failed to read zip archive {:?}", zip_path))?;
+    let plan = plan_entry_paths(
+        &read_entry_names(&mut archive, zip_path)?,
+        options.flatten_depth,
+        options.normalize_extensions.as_ref(),
+        options.depth_limit,
+    );
+
+    let mut verification = ArchiveVerification::default();
+    let mut expected = HashSet::new();
+    for (i, name) in plan.paths.into_iter().enumerate() {
+        let Some(name) = name else { continue };
+        let entry = archive.by_index_raw(i)
+            .with_context(|| format!("Failed to read file at index {} in {:?}", i, zip_path))?;
+        let outpath = extract_dir.join(&name);
+        verification.checked += 1;
+        expected.insert(name.trim_end_matches('/').to_string());
+
+        let metadata = match fs::metadata(&outpath) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                verification.missing.push(name);
+                continue;
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read metadata for {:?}", outpath)),
+        };
+
+        if name.ends_with('/') {
+            if !metadata.is_dir() {
+                verification.mismatched.push(format!("{}: not a directory", name));
+            }
+        } else if !metadata.is_file() {
+            verification.mismatched.push(format!("{}: not a file", name));
+        } else if metadata.len() != entry.size() {
+            verification.mismatched.push(format!("{}: {} bytes, archive has {}", name, metadata.len(), entry.size()));
+        } else if options.crc {
+            let crc = file_crc32(&outpath).with_context(|| format!("Failed to read {:?}", outpath))?;
+            if crc != entry.crc32() {
+                verification.mismatched.push(format!("{}: CRC {:08x}, archive has {:08x}", name, crc, entry.crc32()));
+            }
+        }
+    }
+
+    if options.check_extra {
+        for entry in WalkDir::new(extract_dir).min_depth(1).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let relative = entry.path().strip_prefix(extract_dir).unwrap_or(entry.path());
+            let relative: Vec<_> = relative.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+            let relative = relative.join("/");
+            if !expected.contains(&relative) {
+                verification.extra.push(relative);
+            }
+        }
+        verification.extra.sort();
+    }
+
+    Ok(verification)
+}
@@ -0,0 +1,224 @@
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use indicatif::ProgressBar;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A kind of warning, by the name results, `--strict-except` and the run
+/// report use. Names are never changed or reused; new kinds are added.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum WarningCategory {
+    /// Entry timestamps out of range, clamped to the epoch or tomorrow
+    ClampedTimestamps,
+    /// Entry names rewritten: Windows separators, trailing spaces and dots
+    SanitizedNames,
+    /// Entry paths too long for the filesystem, shortened
+    ShortenedNames,
+    /// Extensions changed by --normalize-extensions
+    NormalizedExtensions,
+    /// Entries extracting to one path once their extensions are normalized
+    ExtensionCollisions,
+    /// Branches --flatten-depth flattened less than asked, to avoid collisions
+    FlattenStopped,
+    /// Entries past --max-entry-depth, skipped or collapsed
+    OverdeepEntries,
+    /// Entries whose path would leave the extraction directory, skipped
+    UnsafePaths,
+    /// Entries whose owner couldn't be changed
+    ChownFailed,
+    /// Files the --post-entry-hook failed
+    HookFailures,
+    /// Encrypted archives no password opened, skipped
+    EncryptedArchives,
+    /// Archives skipped as empty, truncated, without central directory or rar
+    SuspectArchives,
+    /// Archives skipped as still being written
+    UnstableArchives,
+    /// Archives skipped as byte-identical to another, with --skip-duplicates
+    DuplicateArchives,
+    /// An output filesystem that can't store some entries or permissions
+    OutputFilesystem,
+}
+
+impl WarningCategory {
+    pub fn name(self) -> &'static str {
+        match self {
+            WarningCategory::ClampedTimestamps => "clamped-timestamps",
+            WarningCategory::SanitizedNames => "sanitized-names",
+            WarningCategory::ShortenedNames => "shortened-names",
+            WarningCategory::NormalizedExtensions => "normalized-extensions",
+            WarningCategory::ExtensionCollisions => "extension-collisions",
+            WarningCategory::FlattenStopped => "flatten-stopped",
+            WarningCategory::OverdeepEntries => "overdeep-entries",
+            WarningCategory::UnsafePaths => "unsafe-paths",
+            WarningCategory::ChownFailed => "chown-failed",
+            WarningCategory::HookFailures => "hook-failures",
+            WarningCategory::EncryptedArchives => "encrypted-archives",
+            WarningCategory::SuspectArchives => "suspect-archives",
+            WarningCategory::UnstableArchives => "unstable-archives",
+            WarningCategory::DuplicateArchives => "duplicate-archives",
+            WarningCategory::OutputFilesystem => "output-filesystem",
+        }
+    }
+
+    /// What the category covers: its line in `--help`
+    pub fn description(self) -> String {
+        self.to_possible_value().and_then(|value| value.get_help().map(|help| help.to_string())).unwrap_or_default()
+    }
+
+    /// Leads the warning's line on the terminal
+    fn icon(self) -> &'static str {
+        match self {
+            WarningCategory::SanitizedNames => "↪️  ",
+            WarningCategory::ShortenedNames => "✂️  ",
+            WarningCategory::NormalizedExtensions => "🔤 ",
+            WarningCategory::OverdeepEntries => "📏 ",
+            WarningCategory::HookFailures => "🛡️  ",
+            _ => "⚠️  ",
+        }
+    }
+}
+
+impl fmt::Display for WarningCategory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// One warning an archive raised
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Warning {
+    pub category: WarningCategory,
+    /// Entries, files or archives it is about
+    pub count: usize,
+    pub message: String,
+}
+
+/// Where one archive's warnings go: each is printed as it's raised, the
+/// progress bars held back meanwhile, and kept for the archive's outcome
+pub struct ArchiveWarnings<'a> {
+    progress_bar: &'a ProgressBar,
+    raised: Vec<Warning>,
+}
+
+impl<'a> ArchiveWarnings<'a> {
+    pub fn new(progress_bar: &'a ProgressBar) -> Self {
+        ArchiveWarnings { progress_bar, raised: Vec::new() }
+    }
+
+    pub fn raise(&mut self, category: WarningCategory, count: usize, message: String) {
+        self.progress_bar.suspend(|| eprintln!("{}{}", category.icon(), message));
+        self.raised.push(Warning { category, count, message });
+    }
+
+    pub fn into_raised(self) -> Vec<Warning> {
+        self.raised
+    }
+}
+
+/// `--strict`: warnings fail the archive raising them, except those of the
+/// categories `--strict-except` names
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct StrictMode {
+    pub except: Vec<WarningCategory>,
+}
+
+impl StrictMode {
+    pub fn new(except: Vec<WarningCategory>) -> Self {
+        StrictMode { except }
+    }
+
+    pub fn fails_on(&self, category: WarningCategory) -> bool {
+        !self.except.contains(&category)
+    }
+
+    /// An error naming each of `warnings` this fails on, if any
+    pub fn check(&self, warnings: &[Warning]) -> Result<()> {
+        let failing: Vec<String> = warnings
+            .iter()
+            .filter(|warning| self.fails_on(warning.category))
+            .map(|warning| format!("{}: {}", warning.category, warning.message))
+            .collect();
+        if !failing.is_empty() {
+            bail!("--strict: {}", failing.join("; "));
+        }
+        Ok(())
+    }
+}
+
+/// A category of the run report's taxonomy, with how the run fared on it
+#[derive(Debug, Clone, Serialize)]
+pub struct CategorySummary {
+    pub name: WarningCategory,
+    pub description: String,
+    /// Whether `--strict` failed archives on it
+    pub strict: bool,
+    /// Archives that raised it
+    pub archives: usize,
+}
+
+/// Every category, in a stable order, with the archives that raised each
+/// among `raised`
+pub fn taxonomy(raised: &BTreeMap<WarningCategory, usize>, strict: Option<&StrictMode>) -> Vec<CategorySummary> {
+    WarningCategory::value_variants()
+        .iter()
+        .map(|&category| CategorySummary {
+            name: category,
+            description: category.description(),
+            strict: strict.is_some_and(|strict| strict.fails_on(category)),
+            archives: raised.get(&category).copied().unwrap_or(0),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warning(category: WarningCategory) -> Warning {
+        Warning { category, count: 2, message: format!("{} happened", category) }
+    }
+
+    #[test]
+    fn names_are_the_same_everywhere() {
+        for &category in WarningCategory::value_variants() {
+            assert_eq!(serde_json::to_value(category).unwrap(), category.name());
+            assert_eq!(category.to_possible_value().unwrap().get_name(), category.name());
+            assert_eq!(WarningCategory::from_str(category.name(), false), Ok(category));
+            assert!(!category.description().is_empty(), "{} has no description", category);
+        }
+    }
+
+    #[test]
+    fn strict_mode_fails_on_all_but_the_excepted() {
+        let warnings = [warning(WarningCategory::ClampedTimestamps), warning(WarningCategory::NormalizedExtensions)];
+        let error = StrictMode::default().check(&warnings).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "--strict: clamped-timestamps: clamped-timestamps happened; normalized-extensions: normalized-extensions happened"
+        );
+        let strict = StrictMode::new(vec![WarningCategory::ClampedTimestamps]);
+        assert_eq!(strict.check(&warnings).unwrap_err().to_string(), "--strict: normalized-extensions: normalized-extensions happened");
+        let strict = StrictMode::new(vec![WarningCategory::NormalizedExtensions, WarningCategory::ClampedTimestamps]);
+        assert!(strict.check(&warnings).is_ok());
+        assert!(StrictMode::default().check(&[]).is_ok());
+    }
+
+    #[test]
+    fn the_taxonomy_lists_every_category_in_order() {
+        let raised = BTreeMap::from([(WarningCategory::SuspectArchives, 3)]);
+        let strict = StrictMode::new(vec![WarningCategory::SuspectArchives]);
+        let summary = taxonomy(&raised, Some(&strict));
+        let names: Vec<&str> = summary.iter().map(|c| c.name.name()).collect();
+        assert_eq!(names[..3], ["clamped-timestamps", "sanitized-names", "shortened-names"]);
+        assert_eq!(names.len(), WarningCategory::value_variants().len());
+        let suspect = summary.iter().find(|c| c.name == WarningCategory::SuspectArchives).unwrap();
+        assert_eq!((suspect.strict, suspect.archives), (false, 3));
+        assert!(summary.iter().filter(|c| c.name != WarningCategory::SuspectArchives).all(|c| c.strict && c.archives == 0));
+        // Without --strict, nothing fails
+        assert!(taxonomy(&raised, None).iter().all(|c| !c.strict));
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use id3::Tag;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::metadata_stripper::audio_region;
+use crate::tag_diff::same_frames;
+
+/// Bytes compared at the start of the audio: an MPEG frame header
+const FRAME_HEADER: usize = 4;
+
+/// What a rewritten MP3 should read back as, noted before its tag is
+/// written: the intended frames, and where its audio sits. Players reject
+/// files whose tag size doesn't lead them to the first audio frame.
+pub struct WriteCheck {
+    path: PathBuf,
+    /// The frames the new tag should hold; `None` for no tag at all
+    tag: Option<Tag>,
+    audio: Range<u64>,
+    /// The first bytes of the audio, the first frame's header
+    head: Vec<u8>,
+    /// The file's bytes before the write, to put back; `None` for a copy,
+    /// which is removed instead
+    backup: Option<Vec<u8>>,
+}
+
+fn read_head(path: &Path, audio: &Range<u64>) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    file.seek(SeekFrom::Start(audio.start))?;
+    let mut head = Vec::with_capacity(FRAME_HEADER);
+    file.take(FRAME_HEADER.min((audio.end - audio.start) as usize) as u64).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+impl WriteCheck {
+    /// Note the file at `path` before its tag is rewritten as `tag`. A file
+    /// stripped in place is held in memory so it can be put back.
+    pub fn before(path: &Path, tag: Option<&Tag>, in_place: bool) -> Result<Self> {
+        let audio = audio_region(path)?;
+        let head = read_head(path, &audio).with_context(|| format!("Failed to read the audio of {:?}", path))?;
+        let backup = if in_place { Some(fs::read(path).with_context(|| format!("Failed to read {:?}", path))?) } else { None };
+        Ok(WriteCheck { path: path.to_path_buf(), tag: tag.cloned(), audio, head, backup })
+    }
+
+    /// Read the file back: its tag must hold exactly the intended frames,
+    /// and its audio be the same length and start with the same frame
+    /// header, right after the tag. Says what differs otherwise.
+    pub fn verify(&self) -> Result<(), String> {
+        let written = match Tag::read_from_path(&self.path) {
+            Ok(tag) => Some(tag),
+            Err(e) if matches!(e.kind, id3::ErrorKind::NoTag) => None,
+            Err(e) => return Err(format!("the written tag can't be read back: {}", e)),
+        };
+        let frames = |tag: Option<&Tag>| tag.map_or(0, |tag| tag.frames().count());
+        let matches = match (&self.tag, &written) {
+            (Some(intended), Some(written)) => same_frames(intended, written),
+            (intended, written) => frames(intended.as_ref()) == 0 && frames(written.as_ref()) == 0,
+        };
+        if !matches {
+            return Err(format!(
+                "the tag reads back with {} frames, not the {} intended",
+                frames(written.as_ref()),
+                frames(self.tag.as_ref())
+            ));
+        }
+
+        let audio = audio_region(&self.path).map_err(|e| format!("{:#}", e))?;
+        if audio.end - audio.start != self.audio.end - self.audio.start {
+            return Err(format!("the audio is {} bytes, was {}", audio.end - audio.start, self.audio.end - self.audio.start));
+        }
+        let head = read_head(&self.path, &audio).map_err(|e| format!("can't read the audio back: {}", e))?;
+        if head != self.head {
+            return Err(format!("the first audio frame isn't at byte {}: found {}, expected {}", audio.start, hex(&head), hex(&self.head)));
+        }
+        Ok(())
+    }
+
+    /// Put the file back as it was: its original bytes, or no copy at all
+    pub fn restore(self) -> Restoration {
+        match &self.backup {
+            Some(backup) if fs::write(&self.path, backup).is_ok() => Restoration::Restored,
+            Some(_) => Restoration::RestoreFailed,
+            None if fs::remove_file(&self.path).is_ok() => Restoration::Removed,
+            None => Restoration::RemoveFailed,
+        }
+    }
+}
+
+/// What [`WriteCheck::restore`] did with a file that failed its check
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restoration {
+    /// The original's bytes were written back
+    Restored,
+    /// The copy was removed
+    Removed,
+    RestoreFailed,
+    RemoveFailed,
+}
+
+impl fmt::Display for Restoration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Restoration::Restored => "the original was restored",
+            Restoration::Removed => "the copy was removed",
+            Restoration::RestoreFailed => "restoring the original failed too; the file may be damaged",
+            Restoration::RemoveFailed => "removing the copy failed too; it may be damaged",
+        })
+    }
+}
@@ -0,0 +1,169 @@
+use anyhow::{bail, Result};
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Size of an end-of-central-directory record without a comment
+const EOCD_SIZE: usize = 22;
+const EOCD_SIGNATURE: &[u8] = b"PK\x05\x06";
+const ZIP64_LOCATOR_SIZE: usize = 20;
+const ZIP64_LOCATOR_SIGNATURE: &[u8] = b"PK\x06\x07";
+const ZIP64_EOCD_SIGNATURE: &[u8] = b"PK\x06\x06";
+/// Up to and including the total entry count of a zip64 end-of-central-directory record
+const ZIP64_EOCD_PREFIX: usize = 40;
+
+/// Largest entry count the classic record can hold; zip64 archives store
+/// this there and the real count in their own record
+const CLASSIC_MAX_ENTRIES: u64 = u16::MAX as u64;
+
+/// What an archive's end-of-central-directory records declare, read
+/// independently of the zip crate so its entry count can be checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CentralDirectory {
+    pub entries: u64,
+    /// Whether a zip64 record is present, for more than 65,535 entries or
+    /// anything past 4 GiB
+    pub zip64: bool,
+}
+
+fn u16_at(bytes: &[u8], at: usize) -> u64 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]]) as u64
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    let mut le = [0u8; 8];
+    le.copy_from_slice(&bytes[at..at + 8]);
+    u64::from_le_bytes(le)
+}
+
+/// Read the end-of-central-directory record and, when a zip64 locator
+/// precedes it, the zip64 record it points to. `None` when there is no
+/// record, or the classic count is saturated and the zip64 one unreadable.
+pub fn read_central_directory(path: &Path) -> io::Result<Option<CentralDirectory>> {
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+    // The record is followed only by a comment of at most 64 KiB
+    let tail_len = size.min((EOCD_SIZE + ZIP64_LOCATOR_SIZE + u16::MAX as usize) as u64);
+    let mut tail = Vec::with_capacity(tail_len as usize);
+    file.seek(SeekFrom::End(-(tail_len as i64)))?;
+    file.read_to_end(&mut tail)?;
+
+    let Some(eocd) = tail.windows(EOCD_SIGNATURE.len()).rposition(|w| w == EOCD_SIGNATURE) else {
+        return Ok(None);
+    };
+    if eocd + EOCD_SIZE > tail.len() {
+        return Ok(None);
+    }
+    let classic_entries = u16_at(&tail, eocd + 10);
+
+    let locator = eocd.checked_sub(ZIP64_LOCATOR_SIZE).filter(|&at| &tail[at..at + 4] == ZIP64_LOCATOR_SIGNATURE);
+    let Some(locator) = locator else {
+        return Ok((classic_entries < CLASSIC_MAX_ENTRIES).then_some(CentralDirectory { entries: classic_entries, zip64: false }));
+    };
+
+    // Offsets are from the start of the zip data, which is the start of the
+    // file unless something (a self-extractor stub) was prepended
+    let mut record = [0u8; ZIP64_EOCD_PREFIX];
+    file.seek(SeekFrom::Start(u64_at(&tail, locator + 8)))?;
+    let entries = match file.read_exact(&mut record) {
+        Ok(()) if &record[..4] == ZIP64_EOCD_SIGNATURE => u64_at(&record, 32),
+        _ if classic_entries < CLASSIC_MAX_ENTRIES => classic_entries,
+        _ => return Ok(None),
+    };
+    Ok(Some(CentralDirectory { entries, zip64: true }))
+}
+
+/// Whether an archive carries zip64 records; unreadable archives count as not
+pub fn is_zip64(path: &Path) -> bool {
+    read_central_directory(path).ok().flatten().is_some_and(|cd| cd.zip64)
+}
+
+/// Fail when the entries the zip crate listed (`listed`) or the extraction
+/// loop went through (`visited`) fall short of what the archive declares,
+/// such as a zip64 archive cut off at the classic record's 65,535
+pub fn check_entry_count(path: &Path, listed: usize, visited: usize) -> Result<()> {
+    if visited != listed {
+        bail!("entry count mismatch: processed {} of the {} entries in the central directory", visited, listed);
+    }
+    // An unreadable record was already got past by the zip crate; nothing to compare
+    let Ok(Some(declared)) = read_central_directory(path) else { return Ok(()) };
+    if declared.entries != listed as u64 {
+        bail!(
+            "entry count mismatch: read {} entries but the {}end-of-central-directory record declares {}",
+            listed,
+            if declared.zip64 { "zip64 " } else { "" },
+            declared.entries
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Write};
+    use zip::write::FileOptions;
+    use zip::ZipWriter;
+
+    /// Write a zip of `count` one-byte stored entries
+    fn write_entries(path: &Path, count: usize) {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        let options = FileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        for i in 0..count {
+            zip.start_file(format!("{}/{}.txt", i / 1000, i), options).unwrap();
+            zip.write_all(b"x").unwrap();
+        }
+        std::fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    }
+
+    #[test]
+    fn classic_archives_declare_their_count() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.zip");
+        write_entries(&path, 3);
+
+        assert_eq!(read_central_directory(&path).unwrap(), Some(CentralDirectory { entries: 3, zip64: false }));
+        assert!(!is_zip64(&path));
+        check_entry_count(&path, 3, 3).unwrap();
+    }
+
+    #[test]
+    fn zip64_records_hold_counts_past_65535() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("many.zip");
+        write_entries(&path, 65_536);
+
+        assert_eq!(read_central_directory(&path).unwrap(), Some(CentralDirectory { entries: 65_536, zip64: true }));
+        assert!(is_zip64(&path));
+        check_entry_count(&path, 65_536, 65_536).unwrap();
+
+        // What a reader stopping at the classic record's count would list
+        let error = check_entry_count(&path, 65_535, 65_535).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "entry count mismatch: read 65535 entries but the zip64 end-of-central-directory record declares 65536"
+        );
+    }
+
+    #[test]
+    fn entries_skipped_by_the_loop_are_a_mismatch() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.zip");
+        write_entries(&path, 3);
+
+        let error = check_entry_count(&path, 3, 2).unwrap_err();
+        assert_eq!(error.to_string(), "entry count mismatch: processed 2 of the 3 entries in the central directory");
+    }
+
+    #[test]
+    fn files_without_a_record_have_nothing_to_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("not.zip");
+        std::fs::write(&path, b"just some text").unwrap();
+
+        assert_eq!(read_central_directory(&path).unwrap(), None);
+        assert!(!is_zip64(&path));
+        assert!(!is_zip64(&dir.path().join("missing.zip")));
+        check_entry_count(&path, 5, 5).unwrap();
+    }
+}
@@ -0,0 +1,87 @@
+//! Runs `bulk_unzip unzip --profile java|android` over a directory of
+//! packages and plain zips and checks which were extracted, and how
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::process::Command;
+use std::time::{Duration, SystemTime};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Binary XML starts with this chunk type, which isn't valid UTF-8 text
+const BINARY_XML: &[u8] = &[0x03, 0x00, 0x08, 0x00, 0xff, 0xfe, 0x00, 0x80];
+
+fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, data) in entries {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(data).unwrap();
+    }
+    fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    // Old enough that the run doesn't wait for it to settle
+    let written = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options().write(true).open(path).unwrap().set_modified(written).unwrap();
+}
+
+fn unzip(root: &Path, output: &str, args: &[&str]) {
+    let run = Command::new(env!("CARGO_BIN_EXE_bulk_unzip"))
+        .args(["--no-color", "unzip", "--no-report", "--directory"])
+        .arg(root.join("zips"))
+        .arg("--output")
+        .arg(root.join(output))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(run.status.success(), "{}", String::from_utf8_lossy(&run.stderr));
+}
+
+/// Names of the directories the run extracted into, sorted
+fn extracted(root: &Path, output: &str) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(root.join(output))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn profiles_extract_only_their_packages_without_signatures() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("zips")).unwrap();
+    let signed: &[(&str, &[u8])] = &[
+        ("META-INF/MANIFEST.MF", b"Manifest-Version: 1.0\n"),
+        ("META-INF/CERT.SF", b"signature"),
+        ("META-INF/CERT.RSA", b"block"),
+        ("com/example/Main.class", b"\xca\xfe\xba\xbe"),
+    ];
+    for name in ["lib.jar", "site.war", "suite.ear"] {
+        write_zip(&root.join("zips").join(name), signed);
+    }
+    write_zip(&root.join("zips/app.apk"), &[("AndroidManifest.xml", BINARY_XML), ("classes.dex", b"dex\n035"), ("META-INF/CERT.SF", b"signature")]);
+    write_zip(&root.join("zips/plain.zip"), &[("notes.txt", b"notes")]);
+
+    unzip(root, "java", &["--profile", "java"]);
+    assert_eq!(extracted(root, "java"), ["lib", "site", "suite"]);
+    assert!(root.join("java/lib/com/example/Main.class").is_file());
+    assert!(root.join("java/lib/META-INF/MANIFEST.MF").is_file());
+    assert!(!root.join("java/lib/META-INF/CERT.SF").exists());
+    assert!(!root.join("java/lib/META-INF/CERT.RSA").exists());
+
+    unzip(root, "android", &["--profile", "android"]);
+    assert_eq!(extracted(root, "android"), ["app"]);
+    // Extracted byte for byte, not decoded as text
+    assert_eq!(fs::read(root.join("android/app/AndroidManifest.xml")).unwrap(), BINARY_XML);
+    assert!(!root.join("android/app/META-INF").exists());
+
+    unzip(root, "signed", &["--profile", "java", "--keep-signatures"]);
+    assert!(root.join("signed/lib/META-INF/CERT.SF").is_file());
+
+    // Without a profile every kind is picked up, signatures and all
+    unzip(root, "all", &[]);
+    assert_eq!(extracted(root, "all"), ["app", "lib", "plain", "site", "suite"]);
+    assert!(root.join("all/app/META-INF/CERT.SF").is_file());
+}
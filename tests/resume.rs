@@ -0,0 +1,126 @@
+//! Kills `bulk_unzip unzip` part way through a multi-archive run, finishes
+//! it with `--resume` and checks the output against an uninterrupted run
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::{CompressionMethod, DateTime, ZipWriter};
+
+/// Archives big enough that a kill lands inside one most of the time
+fn write_archives(directory: &Path) {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for archive in 0..4 {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for entry in 0..20 {
+            let method = if entry % 4 == 0 { CompressionMethod::Deflated } else { CompressionMethod::Stored };
+            let time = DateTime::from_date_and_time(2021, 3, 1 + entry, 12, 0, 0).unwrap();
+            let options = FileOptions::default().compression_method(method).last_modified_time(time).unix_permissions(0o640);
+            zip.start_file(format!("dir{}/file{}.bin", entry % 3, entry), options).unwrap();
+            let data: Vec<u8> = (0..256 * 1024)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            zip.write_all(&data).unwrap();
+        }
+        let path = directory.join(format!("archive{}.zip", archive));
+        fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+        // Old enough that the run doesn't wait for them to settle
+        let written = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(written).unwrap();
+    }
+}
+
+fn unzip(input: &Path, output: &Path, resume: bool) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_bulk_unzip"));
+    command.args(["--no-color", "unzip", "--directory"]).arg(input).arg("--output").arg(output);
+    if resume {
+        command.arg("--resume");
+    }
+    command.stdout(Stdio::null());
+    command
+}
+
+/// Run to the end, failing with what the run printed when it doesn't succeed
+fn run(mut command: Command, context: &str) {
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{} failed: {}", context, String::from_utf8_lossy(&output.stderr));
+}
+
+/// Contents, modification time and mode of every extracted file under
+/// `root`, leaving out the state file, staging directories and run reports
+fn snapshot(root: &Path) -> BTreeMap<PathBuf, (Vec<u8>, SystemTime, u32)> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            let name = entry.file_name().to_string_lossy();
+            !name.starts_with(".bulk-unzip-") && !name.starts_with("bulk-unzip-report-")
+        })
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| {
+            let metadata = entry.metadata().unwrap();
+            #[cfg(unix)]
+            let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions());
+            #[cfg(not(unix))]
+            let mode = metadata.permissions().readonly() as u32;
+            let relative = entry.path().strip_prefix(root).unwrap().to_path_buf();
+            (relative, (fs::read(entry.path()).unwrap(), metadata.modified().unwrap(), mode))
+        })
+        .collect()
+}
+
+#[test]
+fn killed_runs_resume_to_the_same_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let input = dir.path().join("zips");
+    fs::create_dir(&input).unwrap();
+    write_archives(&input);
+
+    let reference = dir.path().join("reference");
+    let started = Instant::now();
+    run(unzip(&input, &reference, false), "the uninterrupted run");
+    let full_run = started.elapsed();
+    let expected = snapshot(&reference);
+    assert_eq!(expected.len(), 80);
+
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    for attempt in 0..6 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        let kill_after = full_run.mul_f64((state % 1000) as f64 / 1000.0);
+        let output = dir.path().join(format!("attempt{}", attempt));
+
+        let mut child = unzip(&input, &output, true).stderr(Stdio::null()).spawn().unwrap();
+        thread::sleep(kill_after);
+        // SIGKILL on Unix: no chance to clean up
+        let _ = child.kill();
+        child.wait().unwrap();
+
+        run(unzip(&input, &output, true), &format!("resume after {:?}", kill_after));
+        let resumed = snapshot(&output);
+        assert_eq!(resumed.keys().collect::<Vec<_>>(), expected.keys().collect::<Vec<_>>(), "killed after {:?}", kill_after);
+        for (path, (data, modified, mode)) in &expected {
+            let (resumed_data, resumed_modified, resumed_mode) = &resumed[path];
+            assert!(resumed_data == data, "{:?} differs after a kill at {:?}", path, kill_after);
+            assert_eq!((resumed_modified, resumed_mode), (modified, mode), "{:?} after a kill at {:?}", path, kill_after);
+        }
+        // Nothing left over from the killed run
+        let staging: Vec<_> = WalkDir::new(&output)
+            .into_iter()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.depth() > 0 && entry.file_name().to_string_lossy().starts_with(".bulk-unzip-") && entry.file_type().is_dir())
+            .collect();
+        assert!(staging.is_empty(), "{:?}", staging);
+    }
+}
@@ -0,0 +1,80 @@
+//! Runs `bulk_unzip unzip` with one archive failing, then `bulk_unzip retry`
+//! with a changed option, and checks only the failed archive runs again
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, SystemTime};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+fn write_zip(path: &Path, names: &[&str]) {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for name in names {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(name.as_bytes()).unwrap();
+    }
+    fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    // Old enough that the run doesn't wait for it to settle
+    let written = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options().write(true).open(path).unwrap().set_modified(written).unwrap();
+}
+
+/// Run in `root`, so the report records paths relative to it
+fn bulk_unzip(root: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bulk_unzip"))
+        .arg("--no-color")
+        .args(args)
+        .current_dir(root)
+        .output()
+        .unwrap()
+}
+
+/// Each result's archive file name and status, in report order
+fn statuses(report: &Path) -> Vec<(String, String)> {
+    let report: serde_json::Value = serde_json::from_slice(&fs::read(report).unwrap()).unwrap();
+    report["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            let archive = Path::new(result["archive"].as_str().unwrap()).file_name().unwrap().to_string_lossy().into_owned();
+            // Under `outcome` once extracted
+            let status = result["status"].as_str().or(result["outcome"]["status"].as_str()).unwrap();
+            (archive, status.to_string())
+        })
+        .collect()
+}
+
+#[test]
+fn retry_reruns_only_the_failed_archives() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("zips")).unwrap();
+    write_zip(&root.join("zips/big.zip"), &["a.txt", "b.txt", "c.txt"]);
+    write_zip(&root.join("zips/small.zip"), &["a.txt"]);
+
+    bulk_unzip(root, &["unzip", "--directory", "zips", "--output", "out", "--report", "report.json", "--max-entries", "2"]);
+    assert_eq!(statuses(&root.join("report.json")), [("big.zip".to_string(), "Failed".to_string()), ("small.zip".to_string(), "Extracted".to_string())]);
+    assert!(!root.join("out/big").exists());
+    // Gone, so a second extraction of small.zip would show
+    fs::remove_dir_all(root.join("out/small")).unwrap();
+
+    let retried = bulk_unzip(root, &["retry", "--report", "report.json", "--", "--max-entries", "10"]);
+    assert!(retried.status.success(), "{}", String::from_utf8_lossy(&retried.stderr));
+    assert!(String::from_utf8_lossy(&retried.stdout).contains("Retrying 1 of 2 results"));
+    assert!(root.join("out/big/c.txt").is_file());
+    assert!(!root.join("out/small").exists());
+
+    // Merged in place, and the retry's own report removed
+    assert_eq!(statuses(&root.join("report.json")), [("big.zip".to_string(), "Extracted".to_string()), ("small.zip".to_string(), "Extracted".to_string())]);
+    let report: serde_json::Value = serde_json::from_slice(&fs::read(root.join("report.json")).unwrap()).unwrap();
+    assert_eq!(report["retries"][0]["statuses"], serde_json::json!(["Failed", "SkippedEncrypted"]));
+    assert!(!root.join("report.json.retry").exists());
+
+    // Nothing left to retry
+    let again = bulk_unzip(root, &["retry", "--report", "report.json"]);
+    assert!(again.status.success());
+    assert!(String::from_utf8_lossy(&again.stdout).contains("Nothing to retry"));
+}
@@ -0,0 +1,138 @@
+//! Kills `bulk_unzip unzip --state-file` once it has journaled an archive,
+//! starts it again and checks the journaled archives aren't extracted twice
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use walkdir::WalkDir;
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+/// Archives big enough that the kill lands before the last is journaled
+fn write_archives(directory: &Path) {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    for archive in 0..6 {
+        let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+        for entry in 0..10 {
+            zip.start_file(format!("file{}.bin", entry), FileOptions::default()).unwrap();
+            let data: Vec<u8> = (0..256 * 1024)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect();
+            zip.write_all(&data).unwrap();
+        }
+        let path = directory.join(format!("archive{}.zip", archive));
+        fs::write(&path, zip.finish().unwrap().into_inner()).unwrap();
+        // Old enough that the run doesn't wait for them to settle
+        let written = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options().write(true).open(&path).unwrap().set_modified(written).unwrap();
+    }
+}
+
+/// Run in `root`, where relative paths among `args` land
+fn unzip(root: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_bulk_unzip"));
+    command
+        .args(["--no-color", "unzip", "--workers", "1", "--directory"])
+        .arg(root.join("zips"))
+        .arg("--output")
+        .arg(root.join("out"))
+        .arg("--state-file")
+        .arg(root.join("state"))
+        .args(args)
+        .current_dir(root)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+/// File names of the archives the state file has a record for
+fn journaled(root: &Path) -> BTreeSet<String> {
+    let journal = fs::read_to_string(root.join("state")).unwrap_or_default();
+    journal
+        .lines()
+        .skip(1)
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .map(|record| Path::new(record["path"].as_str().unwrap()).file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Each archive's file name with its status in the run report
+fn statuses(report: &Path) -> Vec<(String, String)> {
+    let report: serde_json::Value = serde_json::from_slice(&fs::read(report).unwrap()).unwrap();
+    let mut statuses: Vec<(String, String)> = report["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|result| {
+            let archive = Path::new(result["archive"].as_str().unwrap()).file_name().unwrap().to_string_lossy().into_owned();
+            // Under `outcome` once extracted
+            let status = result["status"].as_str().or(result["outcome"]["status"].as_str()).unwrap();
+            (archive, status.to_string())
+        })
+        .collect();
+    statuses.sort();
+    statuses
+}
+
+#[test]
+fn a_restarted_run_skips_the_archives_it_journaled() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("zips")).unwrap();
+    write_archives(&root.join("zips"));
+
+    let mut child = unzip(root, &[]).spawn().unwrap();
+    let started = Instant::now();
+    while journaled(root).is_empty() {
+        assert!(started.elapsed() < Duration::from_secs(60), "nothing was journaled");
+        thread::sleep(Duration::from_millis(5));
+    }
+    // SIGKILL on Unix: no chance to clean up
+    let _ = child.kill();
+    child.wait().unwrap();
+    let done = journaled(root);
+
+    // Were a journaled archive extracted again, this would come back
+    let first = done.iter().next().unwrap().trim_end_matches(".zip").to_string();
+    fs::remove_file(root.join("out").join(&first).join("file0.bin")).unwrap();
+
+    let restarted = unzip(root, &["--report", "restarted.json"]).status().unwrap();
+    assert!(restarted.success());
+    let expected: Vec<(String, String)> = (0..6)
+        .map(|archive| {
+            let name = format!("archive{}.zip", archive);
+            let status = if done.contains(&name) { "SkippedProcessed" } else { "Extracted" };
+            (name, status.to_string())
+        })
+        .collect();
+    assert_eq!(statuses(&root.join("restarted.json")), expected);
+    assert!(!root.join("out").join(&first).join("file0.bin").exists());
+    assert_eq!(journaled(root).len(), 6);
+
+    // One directory per archive, none renamed aside as a second copy
+    let directories: Vec<String> = WalkDir::new(root.join("out"))
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.file_type().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    assert_eq!(directories.len(), 6, "{:?}", directories);
+
+    // A third run has nothing to do, until the state is reset
+    unzip(root, &["--report", "again.json"]).status().unwrap();
+    assert!(statuses(&root.join("again.json")).iter().all(|(_, status)| status == "SkippedProcessed"));
+    unzip(root, &["--report", "reset.json", "--reset-state", "--overwrite"]).status().unwrap();
+    assert!(statuses(&root.join("reset.json")).iter().all(|(_, status)| status == "Extracted"));
+    assert!(root.join("out").join(&first).join("file0.bin").is_file());
+}
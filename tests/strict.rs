@@ -0,0 +1,78 @@
+//! Runs `bulk_unzip unzip --strict` over an archive that raises a warning
+//! and checks the exit status, what was extracted and the run report
+
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::process::{Command, Output};
+use std::time::{Duration, SystemTime};
+use zip::write::FileOptions;
+use zip::ZipWriter;
+
+fn write_zip(path: &Path, names: &[&str]) {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    for name in names {
+        zip.start_file(*name, FileOptions::default()).unwrap();
+        zip.write_all(name.as_bytes()).unwrap();
+    }
+    fs::write(path, zip.finish().unwrap().into_inner()).unwrap();
+    // Old enough that the run doesn't wait for it to settle
+    let written = SystemTime::now() - Duration::from_secs(3600);
+    fs::File::options().write(true).open(path).unwrap().set_modified(written).unwrap();
+}
+
+fn unzip(root: &Path, output: &str, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_bulk_unzip"))
+        .args(["--no-color", "unzip", "--normalize-extensions", "--directory"])
+        .arg(root.join("zips"))
+        .arg("--output")
+        .arg(root.join(output))
+        .arg("--report")
+        .arg(root.join(format!("{}.json", output)))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn report(root: &Path, output: &str) -> serde_json::Value {
+    serde_json::from_slice(&fs::read(root.join(format!("{}.json", output))).unwrap()).unwrap()
+}
+
+#[test]
+fn strict_runs_fail_on_warnings_unless_excepted() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path();
+    fs::create_dir(root.join("zips")).unwrap();
+    write_zip(&root.join("zips/album.zip"), &["Track 01.MP3"]);
+    write_zip(&root.join("zips/plain.zip"), &["notes.txt"]);
+
+    // A warning alone doesn't change the exit status
+    let lenient = unzip(root, "lenient", &[]);
+    assert!(lenient.status.success(), "{}", String::from_utf8_lossy(&lenient.stderr));
+    assert!(root.join("lenient/album/Track 01.mp3").is_file());
+
+    let strict = unzip(root, "strict", &["--strict"]);
+    let stderr = String::from_utf8_lossy(&strict.stderr);
+    assert_eq!(strict.status.code(), Some(1), "{}", stderr);
+    assert!(stderr.contains("1 archives failed under --strict"), "{}", stderr);
+    assert!(root.join("strict/plain/notes.txt").is_file());
+    let warnings = &report(root, "strict")["warnings"];
+    let normalized = warnings.as_array().unwrap().iter().find(|c| c["name"] == "normalized-extensions").unwrap();
+    assert_eq!((&normalized["strict"], &normalized["archives"]), (&true.into(), &1.into()));
+
+    let excepted = unzip(root, "excepted", &["--strict", "--strict-except", "clamped-timestamps,normalized-extensions"]);
+    assert!(excepted.status.success(), "{}", String::from_utf8_lossy(&excepted.stderr));
+    assert!(root.join("excepted/album/Track 01.mp3").is_file());
+    let warnings = &report(root, "excepted")["warnings"];
+    let names: Vec<&str> = warnings.as_array().unwrap().iter().map(|c| c["name"].as_str().unwrap()).collect();
+    assert_eq!(names.len(), 15);
+    let strict_about = |name: &str| warnings.as_array().unwrap().iter().find(|c| c["name"] == name).unwrap()["strict"].clone();
+    assert_eq!(strict_about("normalized-extensions"), false);
+    assert_eq!(strict_about("sanitized-names"), true);
+
+    // Refused by the argument parser, which names the categories
+    let unknown = unzip(root, "unknown", &["--strict", "--strict-except", "normalised-extensions"]);
+    let stderr = String::from_utf8_lossy(&unknown.stderr);
+    assert_eq!(unknown.status.code(), Some(2), "{}", stderr);
+    assert!(stderr.contains("possible values: clamped-timestamps"), "{}", stderr);
+}